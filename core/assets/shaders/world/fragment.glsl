@@ -5,6 +5,9 @@ layout(location = 0) in vec4 frag_biome_color;
 layout(location = 1) in vec2 frag_main_tex_coord;
 layout(location = 2) in vec2 frag_biome_color_tex_coord;
 layout(location = 3) in vec4 frag_flags;
+layout(location = 4) in vec4 frag_fog;
+layout(location = 5) in float frag_light;
+layout(location = 6) in float frag_ambient;
 
 // BlockType-based unform - bound based on which block type is being drawn
 layout(set = 1, binding = 0) uniform sampler2D texSampler;
@@ -43,4 +46,10 @@ void main()
 	// Discard any fragments that are wholly transparent.
 	// TODO: Partial transparency will still write to depth buffer.
 	if (outColor.a <= 0) discard;
+
+	// Sky light is not propagated yet, so the whole world is as bright as the time of day
+	// and block light brightens the voxels around light-emitting blocks.
+	outColor.rgb = min(outColor.rgb * (frag_ambient + 0.5 * frag_light), vec3(1.0));
+
+	outColor.rgb = mix(outColor.rgb, frag_fog.rgb, frag_fog.a);
 }
\ No newline at end of file
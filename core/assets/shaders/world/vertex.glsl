@@ -6,27 +6,48 @@
 vec3 CHUNK_SIZE = vec3(16.0, 16.0, 16.0);
 
 // Camera-based unform - changes each frame based on the camera's POV and chunk data
+// MIRRORS: `camera::UniformData`
 layout(set = 0, binding = 0) uniform CameraUniform {
 	mat4 view;
 	mat4 proj;
 	mat4 inv_rotation;
 	vec3 posOfCurrentChunk;
+	vec4 fogColor;
+	vec2 fogRange; // x: distance fog starts, y: distance fog fully obscures
+	float fogEnabled; // 1.0 if fog is applied, 0.0 if it is disabled (e.g. for screenshots)
+	float time; // seconds since rendering started
+	float ambient; // brightness of voxels without block light, based on the time of day
 } camera;
 
 // Model attributes - changes based on the block type being drawn
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec4 tex_coord;
-layout(location = 2) in vec4 model_flags;
+layout(location = 2) in vec4 model_flags; // x: flags bitfield, y: animation frame count, z: seconds per frame, w: distance between frames
 
 // Instance attributes - changes based on a specific block being drawn
 layout(location = 3) in vec3 chunk_coordinate;
 layout(location = 4) in mat4 model_matrix; // slots [4,8)
-layout(location = 8) in vec4 instance_flags;
+layout(location = 8) in vec4 instance_flags; // x: enabled faces bitfield, y: block state (`block::State`), z: block light level
 
 layout(location = 0) out vec4 frag_biome_color;
 layout(location = 1) out vec2 frag_main_tex_coord;
 layout(location = 2) out vec2 frag_biome_color_tex_coord;
 layout(location = 3) out vec4 frag_flags;
+layout(location = 4) out vec4 frag_fog; // rgb: fog color, a: amount of fog
+layout(location = 5) out float frag_light; // block light falling on the voxel, in the range [0, 1]
+layout(location = 6) out float frag_ambient;
+
+// Rotates a face bit-mask (MIRRORS: `Face::model_bit`) by a quarter turn around the up axis,
+// moving the front face to the right.
+int turnFaceMask(int mask)
+{
+	int turned = mask & 0xC; // up & down
+	if ((mask & 0x10) != 0) turned |= 0x02; // front -> right
+	if ((mask & 0x02) != 0) turned |= 0x20; // right -> back
+	if ((mask & 0x20) != 0) turned |= 0x01; // back -> left
+	if ((mask & 0x01) != 0) turned |= 0x10; // left -> front
+	return turned;
+}
 
 highp int bitSubset(int field, int size, int start, int end)
 {
@@ -44,21 +65,44 @@ void main()
 	vec3 chunk_offset = chunk_coordinate - camera.posOfCurrentChunk;
 	// Convert the chunk distance into a number of blocks
 	vec3 blockPosRelativeToCameraChunk = chunk_offset * CHUNK_SIZE;
-	// Now add the position of the block inside the chunk to the number of blocks from the camera's chunk
-	vec3 vertPos = blockPosRelativeToCameraChunk + position;
-	// Integrate the vertex model matrix with its block-offset position
-	// and the camera's view (which includes the camera's offset in its chunk) and projection.
+	// Directional blocks are turned around the vertical axis through the center of the block.
+	// MIRRORS: `block::ORIENTATION_MASK`
+	int quarterTurns = floatBitsToInt(instance_flags.y) & 0x3;
+	vec3 modelPos = position;
+	for (int turn = 0; turn < quarterTurns; ++turn)
+	{
+		modelPos = vec3(1.0 - modelPos.z, modelPos.y, modelPos.x);
+	}
+	// The model matrix moves the vertex to the position of the block inside the chunk
+	// (and stretches greedy meshed quads across the blocks they cover, MIRRORS: `Instance::from_quad`),
+	// then the number of blocks from the camera's chunk is added.
+	vec4 vertPos = vec4(blockPosRelativeToCameraChunk, 0.0) + model_matrix * vec4(modelPos, 1.0);
+	// Integrate the block position with the camera's view (which includes the camera's offset in its chunk) and projection.
 	// This results in the virtual position of the block, on the screen,
 	// relative to the camera's view (position & orientation).
-	gl_Position = camera.proj * camera.view * model_matrix * vec4(vertPos, 1.0);
+	vec4 viewPos = camera.view * vertPos;
+	gl_Position = camera.proj * viewPos;
+
+	// Fade the vertex into the fog based on its distance from the camera,
+	// so that chunks at the edge of the view distance fade in instead of popping in.
+	float fogAmount = camera.fogEnabled * smoothstep(camera.fogRange.x, camera.fogRange.y, length(viewPos.xyz));
+	frag_fog = vec4(camera.fogColor.rgb, fogAmount);
 
 	int model_flags1 = floatBitsToInt(model_flags.x);
 	int instance_flags1 = floatBitsToInt(instance_flags.x);
+
+	// MIRRORS: `light::MAX_LEVEL`
+	frag_light = float(floatBitsToInt(instance_flags.z) & 0xF) / 15.0;
+	frag_ambient = camera.ambient;
 	
 	// Determine if the face should be drawn
 	// -------------------------------------
-	// Bit-Mask which indicates which of the 6 faces this vertex is on
+	// Bit-Mask which indicates which of the 6 faces this vertex is on (after the block is turned)
 	int faceMask = model_flags1 & 0x3F; // 0b111111
+	for (int turn = 0; turn < quarterTurns; ++turn)
+	{
+		faceMask = turnFaceMask(faceMask);
+	}
 	// Get the bit-mask for which faces are enabled/visible for this instance
 	int faceEnabledBits = instance_flags1 & 0x3F;
 	// Tell the fragment shader if this fragment is actually visible;
@@ -82,5 +126,13 @@ void main()
 
 	// Copy over the texture coordinate for sampling from atlas
 	frag_main_tex_coord = tex_coord.rg;
+	// Animated textures have a cell for each frame in the same row of the atlas,
+	// so the texture coordinate is moved to the cell of the current frame.
+	float frame_count = model_flags.y;
+	if (frame_count > 1.0)
+	{
+		float frame = mod(floor(camera.time / model_flags.z), frame_count);
+		frag_main_tex_coord.x += frame * model_flags.w;
+	}
 	frag_biome_color_tex_coord = tex_coord.ba;
 }
\ No newline at end of file
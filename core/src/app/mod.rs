@@ -13,6 +13,20 @@ pub fn store_during<T, F>(
 ) where
 	T: 'static + Send + Sync,
 	F: (Fn() -> anyhow::Result<Option<T>>) + 'static + Send + Sync,
+{
+	store_during_with(app_state, state, move |_data| fn_create());
+}
+
+/// Like [`store_during`], but `fn_create` is provided the data of the transition which entered the state
+/// (e.g. so it can tell if [`InGame`](state::State::InGame) was entered from the main menu or by reconnecting).
+/// The data is None if the app was already in the state when the callbacks were added.
+pub fn store_during_with<T, F>(
+	app_state: &Arc<RwLock<state::Machine>>,
+	state: state::State,
+	fn_create: F,
+) where
+	T: 'static + Send + Sync,
+	F: (Fn(&state::TransitionData) -> anyhow::Result<Option<T>>) + 'static + Send + Sync,
 {
 	use state::{
 		storage::{Event::*, Storage},
@@ -25,3 +39,117 @@ pub fn store_during<T, F>(
 		.with_event(Destroy, OperationKey(Some(state), Some(Exit), None))
 		.create_callbacks(&app_state, fn_create);
 }
+
+/// Like [`store_during`], but `fn_create` is only called the first time the app enters the state,
+/// and the value it creates is destroyed the first time the app leaves the state.
+/// Entering the state again does not create another value.
+pub fn store_during_once<T, F>(
+	app_state: &Arc<RwLock<state::Machine>>,
+	state: state::State,
+	fn_create: F,
+) where
+	T: 'static + Send + Sync,
+	F: (FnOnce() -> anyhow::Result<Option<T>>) + 'static + Send + Sync,
+{
+	use state::{
+		storage::{Event::*, Storage},
+		Transition::*,
+		*,
+	};
+
+	Storage::<T>::default()
+		.with_event(Create, OperationKey(None, Some(Enter), Some(state)))
+		.with_event(Destroy, OperationKey(Some(state), Some(Exit), None))
+		.create_callbacks_once(&app_state, move |_data| fn_create());
+}
+
+#[cfg(test)]
+mod store_during {
+	use super::*;
+	use engine::EngineSystem;
+	use state::State::*;
+	use std::{sync::Mutex, time::Duration};
+
+	/// Why the game was entered, as provided by whatever requested the transition.
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	enum Reason {
+		Hosted,
+		Reconnected,
+	}
+
+	fn transition(
+		app_state: &Arc<RwLock<state::Machine>>,
+		next: state::State,
+		reason: Option<Reason>,
+	) {
+		let mut machine = app_state.write().unwrap();
+		machine.transition_to(
+			next,
+			reason.map(|reason| Box::new(reason) as Box<dyn std::any::Any + Send + Sync>),
+		);
+		machine.update(Duration::ZERO, true);
+	}
+
+	#[test]
+	fn create_receives_transition_data() {
+		let app_state = state::Machine::new(MainMenu).arclocked();
+		let reasons = Arc::new(Mutex::new(Vec::new()));
+		let created = reasons.clone();
+		store_during_with(&app_state, InGame, move |data| {
+			let reason = data
+				.as_ref()
+				.and_then(|data| data.downcast_ref::<Reason>())
+				.cloned();
+			created.lock().unwrap().push(reason);
+			Ok(Some(()))
+		});
+
+		transition(&app_state, InGame, Some(Reason::Hosted));
+		transition(&app_state, MainMenu, None);
+		transition(&app_state, InGame, Some(Reason::Reconnected));
+		transition(&app_state, MainMenu, None);
+		transition(&app_state, InGame, None);
+		assert_eq!(
+			*reasons.lock().unwrap(),
+			vec![Some(Reason::Hosted), Some(Reason::Reconnected), None]
+		);
+	}
+
+	/// Counts how many times it has been dropped.
+	struct Tracked(Arc<Mutex<usize>>);
+	impl Drop for Tracked {
+		fn drop(&mut self) {
+			*self.0.lock().unwrap() += 1;
+		}
+	}
+
+	#[test]
+	fn once_creates_and_destroys_once() {
+		let app_state = state::Machine::new(MainMenu).arclocked();
+		let created = Arc::new(Mutex::new(0));
+		let destroyed = Arc::new(Mutex::new(0));
+		let (callback_created, callback_destroyed) = (created.clone(), destroyed.clone());
+		store_during_once(&app_state, InGame, move || {
+			*callback_created.lock().unwrap() += 1;
+			Ok(Some(Tracked(callback_destroyed)))
+		});
+
+		transition(&app_state, InGame, None);
+		assert_eq!(
+			(*created.lock().unwrap(), *destroyed.lock().unwrap()),
+			(1, 0)
+		);
+		// The value is still destroyed when the state is left the first time.
+		transition(&app_state, MainMenu, None);
+		assert_eq!(
+			(*created.lock().unwrap(), *destroyed.lock().unwrap()),
+			(1, 1)
+		);
+		transition(&app_state, InGame, None);
+		transition(&app_state, MainMenu, None);
+		assert_eq!(
+			(*created.lock().unwrap(), *destroyed.lock().unwrap()),
+			(1, 1)
+		);
+	}
+}
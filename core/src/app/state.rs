@@ -98,6 +98,15 @@ pub struct Operation<'transition>(
 );
 pub type FnOperation = Box<dyn Fn(&Operation) + Send + Sync>;
 
+/// Whether a callback should be kept after it is called,
+/// so callbacks which should only run once can remove themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retain {
+	Keep,
+	Remove,
+}
+type FnRetainOperation = Box<dyn Fn(&Operation) -> Retain + Send + Sync>;
+
 impl<'transition> Operation<'transition> {
 	pub fn prev(&self) -> &Option<State> {
 		&self.0
@@ -135,7 +144,7 @@ impl<'transition> Operation<'transition> {
 pub type ArcLockMachine = Arc<RwLock<Machine>>;
 pub struct Machine {
 	state: State,
-	callbacks: HashMap<OperationKey, Vec<FnOperation>>,
+	callbacks: HashMap<OperationKey, Vec<FnRetainOperation>>,
 	next_transition: Option<(State, TransitionData)>,
 }
 
@@ -194,9 +203,23 @@ impl Machine {
 	pub fn add_callback<F>(&mut self, key: OperationKey, callback: F)
 	where
 		F: Fn(&Operation) + Send + Sync + 'static,
+	{
+		self.add_removable_callback(key, move |operation| {
+			callback(operation);
+			Retain::Keep
+		});
+	}
+
+	/// Adds a callback which is removed (and never called again) once it returns [`Retain::Remove`].
+	pub fn add_removable_callback<F>(&mut self, key: OperationKey, callback: F)
+	where
+		F: Fn(&Operation) -> Retain + Send + Sync + 'static,
 	{
 		if key.2 == Some(self.state) && key.1 == Some(Transition::Enter) {
-			callback(&Operation(None, Transition::Enter, self.state, &None));
+			let retain = callback(&Operation(None, Transition::Enter, self.state, &None));
+			if retain == Retain::Remove {
+				return;
+			}
 		}
 
 		if !self.callbacks.contains_key(&key) {
@@ -219,13 +242,10 @@ impl Machine {
 	}
 
 	fn dispatch_callback(&mut self, operation: Operation) {
-		let relevant_callbacks = operation
-			.all_keys()
-			.into_iter()
-			.filter_map(|key| self.callbacks.get(&key))
-			.flatten();
-		for callback in relevant_callbacks {
-			callback(&operation);
+		for key in operation.all_keys().into_iter() {
+			if let Some(callbacks) = self.callbacks.get_mut(&key) {
+				callbacks.retain(|callback| callback(&operation) == Retain::Keep);
+			}
 		}
 	}
 
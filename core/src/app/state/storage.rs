@@ -1,4 +1,4 @@
-use super::{ArcLockMachine, OperationKey};
+use super::{ArcLockMachine, OperationKey, Retain, TransitionData};
 use anyhow::Result;
 use std::sync::{Arc, Mutex};
 
@@ -29,9 +29,11 @@ where
 		self
 	}
 
+	/// Adds the callbacks for each event to the app state.
+	/// The create callback is provided the data of the transition which caused the [`Create`](Event::Create) event.
 	pub fn create_callbacks<F>(self, app_state: &ArcLockMachine, create_callback: F)
 	where
-		F: (Fn() -> Result<Option<T>>) + 'static + Send + Sync,
+		F: (Fn(&TransitionData) -> Result<Option<T>>) + 'static + Send + Sync,
 	{
 		let storage: Arc<Mutex<Option<T>>> = Default::default();
 		let creator = Arc::new(create_callback);
@@ -42,9 +44,50 @@ where
 			match event {
 				Event::Create => {
 					let callback_creator = creator.clone();
-					app_state.add_callback(
-						operation_key,
-						move |_operation| match callback_creator() {
+					app_state.add_callback(operation_key, move |operation| match callback_creator(
+						operation.data(),
+					) {
+						Ok(item) => {
+							let mut storage = callback_storage.lock().unwrap();
+							*storage = item;
+						}
+						Err(err) => {
+							log::error!(target: "storage", "{:?}", err);
+						}
+					});
+				}
+				Event::Destroy => {
+					app_state.add_callback(operation_key, move |_operation| {
+						let mut storage = callback_storage.lock().unwrap();
+						*storage = None;
+					});
+				}
+			}
+		}
+	}
+
+	/// Like [`create_callbacks`](Self::create_callbacks), but each event only happens once.
+	/// The callbacks remove themselves from the app state after they are called,
+	/// so the create callback is never called again (and a value is only destroyed the first time).
+	pub fn create_callbacks_once<F>(self, app_state: &ArcLockMachine, create_callback: F)
+	where
+		F: (FnOnce(&TransitionData) -> Result<Option<T>>) + 'static + Send + Sync,
+	{
+		let storage: Arc<Mutex<Option<T>>> = Default::default();
+		let creator = Arc::new(Mutex::new(Some(create_callback)));
+
+		let mut app_state = app_state.write().unwrap();
+		for (operation_key, event) in self.events.into_iter() {
+			let callback_storage = storage.clone();
+			match event {
+				Event::Create => {
+					let callback_creator = creator.clone();
+					app_state.add_removable_callback(operation_key, move |operation| {
+						let create = match callback_creator.lock().unwrap().take() {
+							Some(create) => create,
+							None => return Retain::Remove,
+						};
+						match create(operation.data()) {
 							Ok(item) => {
 								let mut storage = callback_storage.lock().unwrap();
 								*storage = item;
@@ -52,13 +95,15 @@ where
 							Err(err) => {
 								log::error!(target: "storage", "{:?}", err);
 							}
-						},
-					);
+						}
+						Retain::Remove
+					});
 				}
 				Event::Destroy => {
-					app_state.add_callback(operation_key, move |_operation| {
+					app_state.add_removable_callback(operation_key, move |_operation| {
 						let mut storage = callback_storage.lock().unwrap();
 						*storage = None;
+						Retain::Remove
 					});
 				}
 			}
@@ -4,5 +4,13 @@ mod lookup;
 pub use lookup::*;
 mod point;
 pub use point::*;
+mod property;
+pub use property::*;
+mod shape;
+pub use shape::*;
 mod side;
 pub use side::*;
+mod state;
+pub use state::*;
+mod texture_rotation;
+pub use texture_rotation::*;
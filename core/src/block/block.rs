@@ -1,14 +1,29 @@
-use super::Side;
+use super::{Properties, Property, PropertyRegistry, PropertyValue, Shape, Side, TextureRotation};
 use crate::graphics::voxel::Face;
 use engine::asset::{self, AnyBox};
 use enumset::EnumSet;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+/// Declares that a texture is a vertical strip of frames (each as tall as the texture is wide),
+/// which are drawn one after another on a loop.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TextureAnimation {
+	/// How long each frame is drawn before the next.
+	pub frame_duration: Duration,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextureEntry {
 	pub texture_id: asset::Id,
 	pub all_texture_ids: Vec<asset::Id>,
 	pub biome_color: (bool, Option<asset::Id>),
+	/// The rotation of the texture (and its biome color mask) on each face it is applied to.
+	#[serde(default)]
+	pub rotation: TextureRotation,
+	/// If present, the texture is animated (the biome color mask is not).
+	#[serde(default)]
+	pub animation: Option<TextureAnimation>,
 }
 impl TextureEntry {
 	pub fn texture_ids(&self) -> &Vec<asset::Id> {
@@ -22,6 +37,18 @@ pub struct Block {
 	textures: Vec<(TextureEntry, EnumSet<Face>)>,
 	/// True if the block's model is fully opaque/has no chance of seeing other blocks through it.
 	is_opaque: bool,
+	/// The level of block light the block emits, in the range [0, 15].
+	#[serde(default)]
+	light_emission: u8,
+	/// The shape that voxels of the block collide with.
+	#[serde(default)]
+	collider: Shape,
+	/// The values of each property declared by the block asset, by the name of the property.
+	#[serde(default)]
+	property_values: HashMap<String, PropertyValue>,
+	/// The typed properties, parsed from the `property_values` when the block is loaded.
+	#[serde(skip)]
+	properties: Properties,
 }
 
 impl Default for Block {
@@ -30,6 +57,10 @@ impl Default for Block {
 			asset_type: String::new(),
 			textures: Vec::new(),
 			is_opaque: true,
+			light_emission: 0,
+			collider: Shape::default(),
+			property_values: HashMap::new(),
+			properties: Properties::default(),
 		}
 	}
 }
@@ -59,10 +90,97 @@ impl Block {
 		};
 	}
 
+	pub fn light_emission(&self) -> u8 {
+		self.light_emission
+	}
+
+	fn set_light_emission(&mut self, node: &kdl::KdlNode) {
+		use crate::common::world::light::MAX_LEVEL;
+		self.light_emission = match node.get(0).map(|entry| entry.value()) {
+			Some(kdl::KdlValue::Base10(level)) => (*level).clamp(0, MAX_LEVEL as i64) as u8,
+			_ => 0,
+		};
+	}
+
+	pub fn collider(&self) -> &Shape {
+		&self.collider
+	}
+
+	pub fn with_collider(mut self, collider: Shape) -> Self {
+		self.collider = collider;
+		self
+	}
+
+	fn set_collider(&mut self, node: &kdl::KdlNode) {
+		self.collider = match node.get(0).map(|entry| entry.value()) {
+			Some(kdl::KdlValue::String(name)) => match Shape::from_name(&name) {
+				Some(shape) => shape,
+				None => {
+					log::warn!(
+						"Unknown collider shape \"{}\", expected one of cube, none, slab, top_slab, or stairs",
+						name
+					);
+					Shape::default()
+				}
+			},
+			_ => Shape::default(),
+		};
+	}
+
+	/// Returns the property of a type, or None if the block does not declare it
+	/// (or its value was not valid when the block was loaded).
+	pub fn get_property<T: Property>(&self) -> Option<&T> {
+		self.properties.get::<T>()
+	}
+
+	/// Parses the values of the properties declared by the block asset into their types.
+	pub(super) fn load_properties(&mut self, registry: &PropertyRegistry) {
+		self.properties = registry.parse(&self.property_values);
+	}
+
+	fn set_properties(&mut self, node: &kdl::KdlNode) {
+		self.property_values.clear();
+		let doc = match node.children() {
+			Some(doc) => doc,
+			None => return,
+		};
+		for node in doc.nodes().iter() {
+			let name = match node.get(0).map(|entry| entry.value()) {
+				Some(kdl::KdlValue::String(name)) => name.clone(),
+				_ => continue,
+			};
+			match node
+				.get(1)
+				.map(|entry| PropertyValue::from_kdl(entry.value()))
+			{
+				Some(Some(value)) => {
+					self.property_values.insert(name, value);
+				}
+				_ => log::warn!(
+					"Block property \"{}\" must have a boolean, integer, or string value",
+					name
+				),
+			}
+		}
+	}
+
 	pub fn textures(&self) -> &Vec<(TextureEntry, EnumSet<Face>)> {
 		&self.textures
 	}
 
+	pub fn with_texture(mut self, entry: TextureEntry, faces: EnumSet<Face>) -> Self {
+		self.textures.push((entry, faces));
+		self
+	}
+
+	/// Returns the texture applied to a face of the block, or None if the block has no texture on that face.
+	pub fn texture_on(&self, face: Face) -> Option<&TextureEntry> {
+		self.textures
+			.iter()
+			.find(|(_, faces)| faces.contains(face))
+			.map(|(entry, _)| entry)
+	}
+
 	fn set_textures(&mut self, node: &kdl::KdlNode) {
 		use engine::utility::kdl::{value_as_asset_id, value_map_asset_id};
 		use std::convert::TryFrom;
@@ -79,8 +197,22 @@ impl Block {
 				all_texture_ids: vec![texture_id.clone()],
 				texture_id,
 				biome_color: (false, None),
+				rotation: TextureRotation::default(),
+				animation: None,
 			};
 
+			match node.get("rotation").map(|e| e.value()) {
+				Some(kdl::KdlValue::Base10(degrees)) => match TextureRotation::try_from(*degrees) {
+					Ok(rotation) => entry.rotation = rotation,
+					Err(_) => log::warn!(
+							"Texture {} has rotation {}, but only multiples of 90 degrees are supported",
+							entry.texture_id,
+							degrees
+						),
+				},
+				_ => {}
+			}
+
 			if let Some(doc) = node.children() {
 				for node in doc.nodes().iter() {
 					match node.name().value() {
@@ -97,6 +229,22 @@ impl Block {
 								entry.all_texture_ids.push(id.clone());
 							}
 						}
+						"animation" => {
+							entry.animation = match node.get("frame_ms").map(|e| e.value()) {
+								Some(kdl::KdlValue::Base10(ms)) if *ms > 0 => {
+									Some(TextureAnimation {
+										frame_duration: Duration::from_millis(*ms as u64),
+									})
+								}
+								_ => {
+									log::warn!(
+										"Texture {} has an animation without a positive frame_ms",
+										entry.texture_id
+									);
+									None
+								}
+							};
+						}
 						_ => {}
 					}
 				}
@@ -155,11 +303,27 @@ impl engine::asset::kdl::Asset<Block> for Block {
 				..Default::default()
 			}
 		}
+		fn animation() -> Node<Block> {
+			Node {
+				name: Name::Defined("animation"),
+				properties: vec![Property {
+					name: "frame_ms",
+					value: Value::Integer,
+					optional: false,
+				}],
+				..Default::default()
+			}
+		}
 		fn texture_node(name: &'static str) -> Node<Block> {
 			Node {
 				name: Name::Defined(name),
 				values: Items::Select(vec![Value::String(None)]),
-				children: Items::Select(vec![biome_color()]),
+				properties: vec![Property {
+					name: "rotation",
+					value: Value::Integer,
+					optional: true,
+				}],
+				children: Items::Select(vec![biome_color(), animation()]),
 				..Default::default()
 			}
 		}
@@ -187,7 +351,34 @@ impl engine::asset::kdl::Asset<Block> for Block {
 					..Default::default()
 				},
 				Node {
-					children: Items::Select(vec![biome_color(), texture_sides()]),
+					name: Name::Defined("light_emission"),
+					values: Items::Ordered(vec![Value::Integer]),
+					on_validation_successful: Some(Block::set_light_emission),
+					..Default::default()
+				},
+				Node {
+					name: Name::Defined("collider"),
+					values: Items::Ordered(vec![Value::String(None)]),
+					on_validation_successful: Some(Block::set_collider),
+					..Default::default()
+				},
+				Node {
+					name: Name::Defined("properties"),
+					children: Items::Select(vec![Node {
+						name: Name::Defined("property"),
+						// The name of the property, followed by its value.
+						values: Items::Select(vec![
+							Value::String(None),
+							Value::Boolean,
+							Value::Integer,
+						]),
+						..Default::default()
+					}]),
+					on_validation_successful: Some(Block::set_properties),
+					..Default::default()
+				},
+				Node {
+					children: Items::Select(vec![biome_color(), animation(), texture_sides()]),
 					on_validation_successful: Some(Block::set_textures),
 					..texture_node("textures")
 				},
@@ -196,3 +387,82 @@ impl engine::asset::kdl::Asset<Block> for Block {
 		}
 	}
 }
+
+#[cfg(test)]
+mod properties {
+	use super::*;
+
+	/// A plugin's own level of light emitted by a block.
+	#[derive(Debug, Clone, PartialEq)]
+	struct Glow(u8);
+
+	impl Property for Glow {
+		fn name() -> &'static str {
+			"glow"
+		}
+
+		fn parse(value: &PropertyValue) -> Option<Self> {
+			match value {
+				PropertyValue::Integer(level) if (0..=15).contains(level) => {
+					Some(Self(*level as u8))
+				}
+				_ => None,
+			}
+		}
+	}
+
+	#[derive(Debug, Clone, PartialEq)]
+	struct Hardness(i64);
+
+	impl Property for Hardness {
+		fn name() -> &'static str {
+			"hardness"
+		}
+
+		fn parse(value: &PropertyValue) -> Option<Self> {
+			match value {
+				PropertyValue::Integer(hardness) => Some(Self(*hardness)),
+				_ => None,
+			}
+		}
+	}
+
+	fn block_with(properties: &str) -> Block {
+		let doc = properties.parse::<kdl::KdlDocument>().unwrap();
+		let mut block = Block::default();
+		block.set_properties(&doc.nodes()[0]);
+		block
+	}
+
+	#[test]
+	fn registered_property_is_readable() {
+		let mut registry = PropertyRegistry::default();
+		registry.register::<Glow>();
+		let mut block = block_with(
+			"properties {
+				property \"glow\" 12
+				property \"unregistered\" true
+			}",
+		);
+		block.load_properties(&registry);
+		assert_eq!(block.get_property::<Glow>(), Some(&Glow(12)));
+		// Properties which the block doesn't declare, or which are not registered, are missing.
+		assert_eq!(block.get_property::<Hardness>(), None);
+
+		// Properties are parsed again after the block is deserialized (as it is when loaded from its asset).
+		let bytes = bincode::serialize(&block).unwrap();
+		let mut loaded = bincode::deserialize::<Block>(&bytes).unwrap();
+		assert_eq!(loaded.get_property::<Glow>(), None);
+		loaded.load_properties(&registry);
+		assert_eq!(loaded.get_property::<Glow>(), Some(&Glow(12)));
+	}
+
+	#[test]
+	fn invalid_values_are_missing() {
+		let mut registry = PropertyRegistry::default();
+		registry.register::<Glow>();
+		let mut block = block_with("properties { property \"glow\" 99; }");
+		block.load_properties(&registry);
+		assert_eq!(block.get_property::<Glow>(), None);
+	}
+}
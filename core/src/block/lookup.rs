@@ -1,28 +1,42 @@
-use super::Block;
+use super::{Block, PropertyRegistry, Shape};
+use crate::common::asset_batch;
 use engine::asset;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::{Arc, RwLock},
+};
 
 pub type LookupId = usize;
 
+static LOG: &'static str = "block-lookup";
+
 /// A mapping of [`block id`](asset::Id) to unsized-integer (and back)
 /// for serializing block asset ids to save space in memory and in network packets.
+///
+/// Also holds the [`definition`](Block) of every block, so that commands and plugins
+/// can enumerate the registered blocks without loading their assets.
 #[derive(Default)]
 pub struct Lookup {
 	ordered_ids: Vec<asset::Id>,
 	id_values: HashMap<asset::Id, LookupId>,
+	blocks: Vec<Block>,
 }
 
 impl Lookup {
-	fn instance() -> &'static mut Option<Arc<Self>> {
-		static mut INSTANCE: Option<Arc<Lookup>> = None;
-		unsafe { &mut INSTANCE }
+	fn instance() -> &'static RwLock<Option<Arc<Self>>> {
+		use engine::utility::singleton::*;
+		static mut INSTANCE: Singleton<Option<Arc<Lookup>>> = Singleton::uninit();
+		unsafe { INSTANCE.get_or_default() }
 	}
 
-	pub fn get() -> Option<&'static Arc<Self>> {
-		Self::instance().as_ref()
+	/// Returns the lookup which was last initialized (or reloaded).
+	/// The lookup can be replaced at any time, so callers should not hold onto it longer than they need to.
+	pub fn get() -> Option<Arc<Self>> {
+		Self::instance().read().ok()?.clone()
 	}
 
-	pub(crate) fn initialize() {
+	/// Loads every block asset (reading each pak archive once) and assigns their lookup ids.
+	pub async fn initialize() {
 		// Gather asset ids for all block assets
 		let block_ids = {
 			let mut block_ids = match asset::Library::read().get_ids_of_type::<Block>() {
@@ -32,23 +46,67 @@ impl Lookup {
 			block_ids.sort();
 			block_ids
 		};
+		let properties = Self::registered_properties();
+		let blocks = match asset_batch::load_each::<Block>(block_ids).await {
+			Ok(blocks) => blocks,
+			Err(err) => {
+				log::error!(target: LOG, "Failed to load block assets: {}", err);
+				return;
+			}
+		};
+		let mut lookup = Self::default();
+		for (id, block) in blocks.into_iter() {
+			let mut block = match block {
+				Ok(block) => *block,
+				Err(err) => {
+					log::warn!(target: LOG, "Failed to load block {}: {}", id, err);
+					Block::default()
+				}
+			};
+			block.load_properties(&properties);
+			lookup.push(id, block);
+		}
+		Self::set(lookup);
+	}
+
+	/// Parses the properties of every block again, using the property types registered by the plugins which are active now.
+	/// Block ids are unchanged, so lookup ids which have already been saved or replicated are still valid.
+	pub fn reload_properties() {
+		let current = match Self::get() {
+			Some(lookup) => lookup,
+			None => return,
+		};
+		let properties = Self::registered_properties();
 		let mut lookup = Self::default();
-		for id in block_ids.into_iter() {
-			lookup.push(id.clone());
+		for (id, block) in current.ordered_ids.iter().zip(current.blocks.iter()) {
+			let mut block = block.clone();
+			block.load_properties(&properties);
+			lookup.push(id.clone(), block);
 		}
 		Self::set(lookup);
 	}
 
+	fn registered_properties() -> PropertyRegistry {
+		let mut properties = PropertyRegistry::default();
+		if let Ok(plugins) = crate::plugin::Manager::read() {
+			plugins.register_block_properties(&mut properties);
+		}
+		properties
+	}
+
 	fn set(lookup: Lookup) {
-		*Self::instance() = Some(Arc::new(lookup));
+		if let Ok(mut instance) = Self::instance().write() {
+			*instance = Some(Arc::new(lookup));
+		}
 	}
 }
 
 impl Lookup {
-	pub(crate) fn push(&mut self, id: asset::Id) -> LookupId {
+	pub(crate) fn push(&mut self, id: asset::Id, block: Block) -> LookupId {
 		let value = self.ordered_ids.len();
 		self.id_values.insert(id.clone(), value);
 		self.ordered_ids.push(id);
+		self.blocks.push(block);
 		value
 	}
 
@@ -67,4 +125,129 @@ impl Lookup {
 			.map(|lookup| lookup.ordered_ids.get(value).cloned())
 			.flatten()
 	}
+
+	/// Returns the definition of the block with the provided lookup id.
+	pub fn block(&self, value: LookupId) -> Option<&Block> {
+		self.blocks.get(value)
+	}
+
+	/// Returns the shape voxels of a block collide with,
+	/// which is a full cube if there is no block with the provided lookup id.
+	pub fn collider(&self, value: LookupId) -> Shape {
+		self.block(value)
+			.map(|block| block.collider().clone())
+			.unwrap_or_default()
+	}
+
+	/// Returns the [`collider`](Self::collider) of a block in the lookup which was last initialized,
+	/// or a full cube if the lookup has not been initialized.
+	pub fn collider_of(value: LookupId) -> Shape {
+		Self::get()
+			.map(|lookup| lookup.collider(value))
+			.unwrap_or_default()
+	}
+
+	/// Returns the level of light emitted by a block in the lookup which was last initialized,
+	/// or 0 if there is no such block (or the lookup has not been initialized).
+	pub fn light_emission_of(value: LookupId) -> u8 {
+		Self::get()
+			.and_then(|lookup| lookup.block(value).map(Block::light_emission))
+			.unwrap_or(0)
+	}
+
+	/// Returns true if a block in the lookup which was last initialized stops light from passing through it.
+	/// Blocks are opaque unless they are known not to be, like the full cube [`collider`](Self::collider_of).
+	pub fn is_opaque_of(value: LookupId) -> bool {
+		Self::get()
+			.and_then(|lookup| lookup.block(value).map(Block::is_opaque))
+			.unwrap_or(true)
+	}
+
+	/// Returns every registered block in order of its lookup id.
+	/// The order is stable for a given set of block assets, because ids are sorted when the lookup is initialized.
+	pub fn iter(&self) -> impl Iterator<Item = (LookupId, &Block)> + '_ {
+		self.blocks.iter().enumerate()
+	}
+
+	/// Returns every block whose asset id matches a human-typed name, in order of its lookup id.
+	///
+	/// The (case insensitive) prefix can match the whole id (`vanilla:blocks/stone`),
+	/// or the path of the id starting at any of its segments (`blocks/stone`, `grass` for `blocks/grass/default`).
+	pub fn find_by_name(&self, prefix: &str) -> Vec<(LookupId, &asset::Id)> {
+		let prefix = prefix.to_lowercase();
+		self.ordered_ids
+			.iter()
+			.enumerate()
+			.filter(|(_, id)| Self::matches_name(&id.to_string().to_lowercase(), &prefix))
+			.collect()
+	}
+
+	fn matches_name(id: &str, prefix: &str) -> bool {
+		let path = id.split_once(':').map(|(_, path)| path).unwrap_or(id);
+		let mut segment_starts =
+			std::iter::once(0).chain(path.match_indices('/').map(|(idx, _)| idx + 1));
+		id.starts_with(prefix) || segment_starts.any(|start| path[start..].starts_with(prefix))
+	}
+}
+
+#[cfg(test)]
+mod lookup {
+	use super::*;
+
+	fn create_lookup() -> Lookup {
+		let mut lookup = Lookup::default();
+		for name in [
+			"blocks/dirt",
+			"blocks/glass/clear",
+			"blocks/grass/default",
+			"blocks/stone",
+			"blocks/stone_bricks",
+		] {
+			lookup.push(asset::Id::new("vanilla", name), Block::default());
+		}
+		lookup.push(
+			asset::Id::new("crystal-sphinx", "blocks/debug"),
+			Block::default(),
+		);
+		lookup
+	}
+
+	fn matched_ids(lookup: &Lookup, prefix: &str) -> Vec<LookupId> {
+		lookup
+			.find_by_name(prefix)
+			.into_iter()
+			.map(|(id, _)| id)
+			.collect()
+	}
+
+	#[test]
+	fn find_by_name_prefix() {
+		let lookup = create_lookup();
+		assert_eq!(matched_ids(&lookup, "stone"), vec![3, 4]);
+		assert_eq!(matched_ids(&lookup, "Gr"), vec![2]);
+		assert_eq!(matched_ids(&lookup, "blocks/g"), vec![1, 2]);
+		assert_eq!(matched_ids(&lookup, "vanilla:blocks/stone_"), vec![4]);
+		assert_eq!(matched_ids(&lookup, "crystal-sphinx:"), vec![5]);
+		assert_eq!(matched_ids(&lookup, "sand"), Vec::<LookupId>::new());
+	}
+
+	#[test]
+	fn colliders_of_blocks() {
+		let mut lookup = create_lookup();
+		let slab = lookup.push(
+			asset::Id::new("vanilla", "blocks/stone_slab"),
+			Block::default().with_collider(Shape::slab()),
+		);
+		assert_eq!(lookup.collider(slab), Shape::slab());
+		assert_eq!(lookup.collider(0), Shape::cube());
+		// Unknown blocks are solid.
+		assert_eq!(lookup.collider(lookup.count()), Shape::cube());
+	}
+
+	#[test]
+	fn iteration_is_ordered_by_id() {
+		let lookup = create_lookup();
+		let ids = lookup.iter().map(|(id, _)| id).collect::<Vec<_>>();
+		assert_eq!(ids, (0..lookup.count()).collect::<Vec<_>>());
+	}
 }
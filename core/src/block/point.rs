@@ -1,7 +1,8 @@
 use crate::common::world::chunk;
 use engine::math::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Point {
 	chunk: Point3<i64>,
 	offset: Point3<i8>,
@@ -21,6 +22,18 @@ impl Point {
 	pub fn offset(&self) -> &Point3<i8> {
 		&self.offset
 	}
+
+	/// Returns every point in a chunk, in the order of the chunk's
+	/// [`dense block layout`](chunk::offset_index) (x fastest, then z, then y).
+	pub fn chunk_offsets(chunk: Point3<i64>) -> impl Iterator<Item = Self> {
+		(0..chunk::VOLUME).map(move |index| {
+			let offset = chunk::index_offset(index);
+			Self {
+				chunk,
+				offset: Point3::new(offset.x as i8, offset.y as i8, offset.z as i8),
+			}
+		})
+	}
 }
 
 impl std::fmt::Debug for Point {
@@ -190,4 +203,42 @@ mod block_point {
 		let expected = Point::new(Point3::new(-1, 5, 2), Point3::new(1, 0, 15));
 		assert_eq!(point - change, expected);
 	}
+
+	fn usize_offset(point: &Point) -> Point3<usize> {
+		let offset = point.offset();
+		Point3::new(offset.x as usize, offset.y as usize, offset.z as usize)
+	}
+
+	#[test]
+	fn chunk_offsets_order() {
+		let chunk = Point3::new(2, -1, 0);
+		let points = Point::chunk_offsets(chunk).collect::<Vec<_>>();
+		assert_eq!(points.len(), chunk::VOLUME);
+		assert_eq!(points[0], Point::new(chunk, Point3::new(0, 0, 0)));
+		assert_eq!(points[1], Point::new(chunk, Point3::new(1, 0, 0)));
+		assert_eq!(points[16], Point::new(chunk, Point3::new(0, 0, 1)));
+		assert_eq!(points[256], Point::new(chunk, Point3::new(0, 1, 0)));
+		for (index, point) in points.iter().enumerate() {
+			assert_eq!(*point.chunk(), chunk);
+			assert_eq!(chunk::offset_index(&usize_offset(point)), index);
+		}
+	}
+
+	#[test]
+	fn chunk_offsets_match_block_array() {
+		let coordinate = Point3::new(0, 0, 0);
+		let mut chunk = chunk::Chunk::new(coordinate);
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(1));
+		chunk.set_block_id(Point3::new(15, 0, 0), Some(2));
+		chunk.set_block_id(Point3::new(0, 0, 15), Some(3));
+		chunk.set_block_id(Point3::new(3, 9, 12), Some(4));
+		chunk.set_block_id(Point3::new(15, 15, 15), Some(5));
+
+		let ids = chunk.block_id_array();
+		assert_eq!(ids.len(), chunk::VOLUME);
+		for (point, id) in Point::chunk_offsets(coordinate).zip(ids.iter()) {
+			let expected = chunk.block_ids().get(&usize_offset(&point)).cloned();
+			assert_eq!(*id, expected, "mismatch at {}", point);
+		}
+	}
 }
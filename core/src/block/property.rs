@@ -0,0 +1,109 @@
+use anymap::any::CloneAny;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+static LOG: &'static str = "block-property";
+
+/// Data which plugins attach to block-types (e.g. hardness, tool requirements, light emission),
+/// declared in block assets by a `property` node in the `properties` of the block:
+///
+/// ```kdl
+/// properties {
+/// 	property "hardness" 3
+/// }
+/// ```
+///
+/// Property types are [`registered`](crate::plugin::Plugin::register_block_properties) by plugins,
+/// and are parsed from the values in block assets when the [`block lookup`](super::Lookup) is initialized.
+pub trait Property: Clone + Send + Sync + 'static {
+	/// The name of the property in block assets.
+	fn name() -> &'static str;
+
+	/// Returns the property for a value in a block asset, or None if the value is not valid for the property.
+	fn parse(value: &PropertyValue) -> Option<Self>;
+}
+
+/// The value of a property as it is written in a block asset, before it is parsed into its [`Property`] type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+	Boolean(bool),
+	Integer(i64),
+	String(String),
+}
+
+impl PropertyValue {
+	pub fn from_kdl(value: &kdl::KdlValue) -> Option<Self> {
+		match value {
+			kdl::KdlValue::Bool(value) => Some(Self::Boolean(*value)),
+			kdl::KdlValue::Base10(value) => Some(Self::Integer(*value)),
+			kdl::KdlValue::String(value) => Some(Self::String(value.clone())),
+			_ => None,
+		}
+	}
+}
+
+/// The typed properties of a block-type, with at most one value of each [`Property`] type.
+#[derive(Default, Clone)]
+pub struct Properties(anymap::Map<dyn CloneAny + Send + Sync>);
+
+impl std::fmt::Debug for Properties {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Properties({})", self.0.len())
+	}
+}
+
+impl Properties {
+	pub fn insert<T: Property>(&mut self, property: T) {
+		self.0.insert(property);
+	}
+
+	pub fn get<T: Property>(&self) -> Option<&T> {
+		self.0.get::<T>()
+	}
+}
+
+/// The [`Property`] types which block assets can declare, by their name.
+#[derive(Default)]
+pub struct PropertyRegistry {
+	parsers: HashMap<&'static str, fn(&PropertyValue, &mut Properties) -> bool>,
+}
+
+impl PropertyRegistry {
+	pub fn register<T: Property>(&mut self) {
+		self.parsers.insert(T::name(), parse_into::<T>);
+	}
+
+	/// Parses the values declared by a block asset into their registered property types.
+	/// Properties which are not registered (e.g. their plugin is not loaded) or have invalid values are skipped.
+	pub fn parse(&self, values: &HashMap<String, PropertyValue>) -> Properties {
+		let mut properties = Properties::default();
+		for (name, value) in values.iter() {
+			match self.parsers.get(name.as_str()) {
+				Some(parse) => {
+					if !parse(value, &mut properties) {
+						log::warn!(
+							target: LOG,
+							"Block property \"{}\" has an invalid value {:?}",
+							name,
+							value
+						);
+					}
+				}
+				None => {
+					log::debug!(target: LOG, "Block property \"{}\" is not registered", name);
+				}
+			}
+		}
+		properties
+	}
+}
+
+fn parse_into<T: Property>(value: &PropertyValue, properties: &mut Properties) -> bool {
+	match T::parse(value) {
+		Some(property) => {
+			properties.insert(property);
+			true
+		}
+		None => false,
+	}
+}
@@ -0,0 +1,176 @@
+use crate::{block::Point, common::world::chunk, entity::component::physics::groups::Groups};
+use engine::math::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// An axis-aligned box within the unit cube of a block, where <0, 0, 0> is the minimum corner of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cuboid {
+	pub min: Point3<f32>,
+	pub max: Point3<f32>,
+}
+
+impl Cuboid {
+	pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+		Self { min, max }
+	}
+
+	pub fn half_extents(&self) -> Vector3<f32> {
+		(self.max - self.min) * 0.5
+	}
+
+	pub fn center(&self) -> Point3<f32> {
+		self.min + self.half_extents()
+	}
+}
+
+/// A single cuboid collider of a voxel in world space.
+///
+/// Maps directly to a rapier `SharedShape::cuboid(half_extents)` translated to `center`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+	pub center: Point3<f64>,
+	pub half_extents: Vector3<f32>,
+	pub groups: Groups,
+}
+
+impl Collider {
+	/// A collider in the [`terrain`](Groups::terrain) layer, as all voxels are.
+	pub fn terrain(center: Point3<f64>, half_extents: Vector3<f32>) -> Self {
+		Self {
+			center,
+			half_extents,
+			groups: Groups::terrain(),
+		}
+	}
+}
+
+/// The collision shape of a block-type, as a compound of cuboids within the block.
+///
+/// Declared per block-type by the `collider` node of a block asset (defaulting to a full cube).
+/// Each voxel builds its own colliders from its block-type's shape,
+/// so neighboring partial blocks (e.g. two slabs) are never merged into a single collider.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape(Vec<Cuboid>);
+
+impl Default for Shape {
+	fn default() -> Self {
+		Self::cube()
+	}
+}
+
+impl Shape {
+	pub fn cube() -> Self {
+		Self(vec![Cuboid::new(
+			Point3::new(0.0, 0.0, 0.0),
+			Point3::new(1.0, 1.0, 1.0),
+		)])
+	}
+
+	/// A block which cannot be collided with.
+	pub fn empty() -> Self {
+		Self(Vec::new())
+	}
+
+	/// The bottom half of a block.
+	pub fn slab() -> Self {
+		Self(vec![Cuboid::new(
+			Point3::new(0.0, 0.0, 0.0),
+			Point3::new(1.0, 0.5, 1.0),
+		)])
+	}
+
+	/// The top half of a block.
+	pub fn top_slab() -> Self {
+		Self(vec![Cuboid::new(
+			Point3::new(0.0, 0.5, 0.0),
+			Point3::new(1.0, 1.0, 1.0),
+		)])
+	}
+
+	/// A slab with a step on its back half, where the stairs ascend towards -z.
+	pub fn stairs() -> Self {
+		Self(vec![
+			Cuboid::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.5, 1.0)),
+			Cuboid::new(Point3::new(0.0, 0.5, 0.0), Point3::new(1.0, 1.0, 0.5)),
+		])
+	}
+
+	/// Returns the shape with the provided name, as used by the `collider` node of block assets.
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"cube" => Some(Self::cube()),
+			"none" => Some(Self::empty()),
+			"slab" => Some(Self::slab()),
+			"top_slab" => Some(Self::top_slab()),
+			"stairs" => Some(Self::stairs()),
+			_ => None,
+		}
+	}
+
+	pub fn cuboids(&self) -> &Vec<Cuboid> {
+		&self.0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns the world-space colliders of a voxel of this shape at a point in the world.
+	pub fn colliders_at(&self, point: &Point) -> Vec<Collider> {
+		let mut origin = Point3::<f64>::origin();
+		for i in 0..3 {
+			origin[i] =
+				(point.chunk()[i] * chunk::SIZE_I[i] as i64) as f64 + point.offset()[i] as f64;
+		}
+		self.0
+			.iter()
+			.map(|cuboid| {
+				Collider::terrain(
+					origin + cuboid.center().coords.cast::<f64>(),
+					cuboid.half_extents(),
+				)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod colliders {
+	use super::*;
+
+	#[test]
+	fn slab_is_half_height() {
+		let point = Point::new(Point3::new(1, -1, 0), Point3::new(2, 3, 4));
+		let colliders = Shape::slab().colliders_at(&point);
+		assert_eq!(
+			colliders,
+			vec![Collider {
+				center: Point3::new(18.5, -12.75, 4.5),
+				half_extents: Vector3::new(0.5, 0.25, 0.5),
+				groups: Groups::terrain(),
+			}]
+		);
+	}
+
+	#[test]
+	fn neighboring_slabs_are_not_merged() {
+		let slab = Shape::from_name("slab").unwrap();
+		let left = slab.colliders_at(&Point::new(Point3::new(0, 0, 0), Point3::new(0, 0, 0)));
+		let right = slab.colliders_at(&Point::new(Point3::new(0, 0, 0), Point3::new(1, 0, 0)));
+		assert_eq!(left.len(), 1);
+		assert_eq!(right.len(), 1);
+		assert_eq!(
+			right[0].center - left[0].center,
+			Vector3::new(1.0, 0.0, 0.0)
+		);
+		assert_eq!(left[0].half_extents, right[0].half_extents);
+	}
+
+	#[test]
+	fn stairs_are_compound() {
+		let stairs = Shape::stairs();
+		assert_eq!(stairs.cuboids().len(), 2);
+		assert!(Shape::from_name("none").unwrap().is_empty());
+		assert!(Shape::from_name("wedge").is_none());
+	}
+}
@@ -0,0 +1,16 @@
+/// A small value stored alongside the [`LookupId`](super::LookupId) of each placed block,
+/// whose meaning is specific to the block-type (e.g. orientation, on/off, or growth stage).
+///
+/// The bits in [`ORIENTATION_MASK`] are the number of quarter turns the block's model is rotated around the up axis,
+/// which the renderer applies to every block (blocks which do not face a direction leave them unset).
+///
+/// Empty points (air) have neither a block-type nor a state.
+pub type State = u8;
+
+/// The state of a block which has not been given one (and of all blocks whose type has no states).
+pub const DEFAULT_STATE: State = 0;
+
+/// The bits of a [`State`] which are the number of quarter turns of the block's model around the up axis,
+/// where each turn moves the model's front face to the right.
+/// MIRRORS: `world/vertex.glsl`
+pub const ORIENTATION_MASK: State = 0b11;
@@ -0,0 +1,52 @@
+use engine::math::nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+/// The clockwise rotation of a texture on the faces of a block.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Serialize, Deserialize)]
+pub enum TextureRotation {
+	None,
+	Clockwise90,
+	Clockwise180,
+	Clockwise270,
+}
+
+impl Default for TextureRotation {
+	fn default() -> Self {
+		Self::None
+	}
+}
+
+impl TextureRotation {
+	pub fn degrees(&self) -> u16 {
+		match self {
+			Self::None => 0,
+			Self::Clockwise90 => 90,
+			Self::Clockwise180 => 180,
+			Self::Clockwise270 => 270,
+		}
+	}
+
+	/// Returns the coordinate in the unrotated texture which is sampled at `uv`,
+	/// where both are normalized to the `0..1` range of the texture.
+	pub fn apply(&self, uv: Vector2<f32>) -> Vector2<f32> {
+		match self {
+			Self::None => uv,
+			Self::Clockwise90 => Vector2::new(uv.y, 1.0 - uv.x),
+			Self::Clockwise180 => Vector2::new(1.0 - uv.x, 1.0 - uv.y),
+			Self::Clockwise270 => Vector2::new(1.0 - uv.y, uv.x),
+		}
+	}
+}
+
+impl std::convert::TryFrom<i64> for TextureRotation {
+	type Error = ();
+	fn try_from(degrees: i64) -> Result<Self, Self::Error> {
+		match degrees.rem_euclid(360) {
+			0 => Ok(Self::None),
+			90 => Ok(Self::Clockwise90),
+			180 => Ok(Self::Clockwise180),
+			270 => Ok(Self::Clockwise270),
+			_ => Err(()),
+		}
+	}
+}
@@ -5,3 +5,5 @@ pub mod world;
 
 mod update_camera_view;
 pub use update_camera_view::*;
+mod update_held_item;
+pub use update_held_item::*;
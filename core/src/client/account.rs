@@ -3,7 +3,10 @@ use crate::common::{
 	utility::DataFile,
 };
 use anyhow::Result;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
 
 static LOG: &'static str = "account-manager";
 
@@ -12,6 +15,8 @@ static LOG: &'static str = "account-manager";
 pub struct Manager {
 	root: PathBuf,
 	accounts: HashMap<account::Id, Account>,
+	/// Finds the account to log in as by name.
+	resolver: Box<dyn account::Resolver>,
 	active_id: Option<account::Id>,
 }
 
@@ -22,6 +27,7 @@ impl Default for Manager {
 		Self {
 			root,
 			accounts: HashMap::new(),
+			resolver: Box::new(account::Offline::default()),
 			active_id: None,
 		}
 	}
@@ -46,6 +52,22 @@ impl Manager {
 }
 
 impl Manager {
+	/// Changes the directory accounts are scanned from and created in,
+	/// so tests do not create accounts in the working directory.
+	#[cfg(test)]
+	pub(crate) fn set_root(&mut self, root: PathBuf) {
+		self.root = root;
+	}
+
+	/// Replaces how account names are resolved to ids.
+	/// Accounts which have already been scanned or created are registered with the new resolver.
+	pub fn set_resolver(&mut self, mut resolver: Box<dyn account::Resolver>) {
+		for (id, account) in self.accounts.iter() {
+			resolver.register(id.clone(), account.display_name().clone());
+		}
+		self.resolver = resolver;
+	}
+
 	pub fn scan_accounts(&mut self) -> Result<()> {
 		if !self.root.exists() {
 			std::fs::create_dir_all(&self.root)?;
@@ -53,26 +75,29 @@ impl Manager {
 		for entry in std::fs::read_dir(&self.root)? {
 			let account = Account::load(&entry?.path())?;
 			log::info!(target: LOG, "Scanned account {}", account);
-			self.accounts.insert(account.id().clone(), account);
+			self.insert(account);
 		}
 		Ok(())
 	}
 
+	fn insert(&mut self, account: Account) {
+		self.resolver
+			.register(account.id(), account.display_name().clone());
+		self.accounts.insert(account.id(), account);
+	}
+
 	pub fn find_id(&self, name: &String) -> Option<account::Id> {
-		for (id, account) in self.accounts.iter() {
-			if account.display_name() == name {
-				return Some(id.clone());
-			}
-		}
-		None
+		self.resolver
+			.id_of(name)
+			.filter(|id| self.accounts.contains_key(id))
 	}
 
 	pub fn create_account(&mut self, name: String) -> Result<account::Id> {
 		let account = Account::new_private(&self.root, name)?;
 		log::info!(target: LOG, "Created account {}", account);
 		account.save(&account.path())?;
-		let id = account.id().clone();
-		self.accounts.insert(account.id().clone(), account);
+		let id = account.id();
+		self.insert(account);
 		Ok(id)
 	}
 
@@ -120,6 +145,21 @@ impl Manager {
 			None => Err(Error::NoAccountLoggedIn)?,
 		}
 	}
+
+	/// Writes the public key of the active account to `path` as a PEM file,
+	/// so it can be shared with the administrators of a server.
+	pub fn export_public_key(&self, path: &Path) -> Result<()> {
+		let account = self.active_account()?;
+		let pem = account.key().public_key()?.to_pem()?;
+		std::fs::write(&path, pem)?;
+		log::info!(
+			target: LOG,
+			"Exported public key of {} to {}",
+			account,
+			path.display()
+		);
+		Ok(())
+	}
 }
 
 #[derive(thiserror::Error, Debug)]
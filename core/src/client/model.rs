@@ -8,6 +8,8 @@ use std::sync::{Arc, Mutex, RwLock, Weak};
 pub mod blender;
 mod gather_entities_to_render;
 pub use gather_entities_to_render::*;
+mod held_item;
+pub use held_item::*;
 pub mod instance;
 mod player_model;
 pub use player_model::*;
@@ -19,6 +21,7 @@ pub struct SystemDependencies {
 
 	pub render_chain: Weak<RwLock<graphics::Chain>>,
 	pub render_phase: Weak<graphics::procedure::Phase>,
+	pub viewmodel_phase: Weak<graphics::procedure::Phase>,
 
 	pub camera: Weak<RwLock<Camera>>,
 	pub world: Weak<RwLock<entity::World>>,
@@ -31,6 +34,8 @@ struct RenderSystemObjects {
 	render: Arc<RwLock<RenderModel>>,
 	#[allow(dead_code)]
 	system: Arc<RwLock<GatherEntitiesToRender>>,
+	#[allow(dead_code)]
+	held_item: Arc<RwLock<RenderHeldItem>>,
 }
 impl SystemDependencies {
 	pub fn add_state_listener(self, app_state: &Arc<RwLock<state::Machine>>) {
@@ -62,6 +67,7 @@ impl SystemDependencies {
 					storage: _,
 					render_chain,
 					render_phase,
+					viewmodel_phase,
 					camera,
 					world,
 					blender_model_cache,
@@ -69,25 +75,42 @@ impl SystemDependencies {
 				} = callback_deps.clone();
 				let chain = render_chain.upgrade().unwrap();
 				let phase = render_phase.upgrade().unwrap();
+				let viewmodel_phase = viewmodel_phase.upgrade().unwrap();
 				let camera = camera.upgrade().unwrap();
 
-				let instance_buffer = Arc::new(RwLock::new(instance::Buffer::new(
-					&chain.read().unwrap().allocator()?,
-					std::mem::size_of::<instance::Instance>() * 30, // magic number, entity count will be way higher than 30
-				)?));
+				let instance_buffer = {
+					let chain = chain.read().unwrap();
+					Arc::new(RwLock::new(instance::Buffer::new(
+						&chain.allocator()?,
+						std::mem::size_of::<instance::Instance>() * 30, // magic number, entity count will be way higher than 30
+						chain.view_count(),
+					)?))
+				};
 
 				let render = RenderModel::create(
 					&chain,
 					&phase,
+					camera.clone(),
+					blender_model_cache.clone(),
+					instance_buffer.clone(),
+					texture_cache.clone(),
+				)?;
+				let held_item = RenderHeldItem::create(
+					&chain,
+					&viewmodel_phase,
 					camera,
+					world.clone(),
 					blender_model_cache,
-					instance_buffer.clone(),
 					texture_cache.clone(),
 				)?;
 				let system =
 					GatherEntitiesToRender::create(world.clone(), &instance_buffer, &texture_cache);
 
-				return Ok(Some(RenderSystemObjects { render, system }));
+				return Ok(Some(RenderSystemObjects {
+					render,
+					system,
+					held_item,
+				}));
 			});
 	}
 }
@@ -58,8 +58,10 @@ impl std::fmt::Display for Component {
 }
 
 impl debug::EguiInformation for Component {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!("Model Id: {}", self.descriptor_id.model_id));
-		ui.label(format!("Texture Id: {}", self.descriptor_id.texture_id));
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!("Model Id: {}", self.descriptor_id.model_id),
+			format!("Texture Id: {}", self.descriptor_id.texture_id),
+		]
 	}
 }
@@ -4,7 +4,10 @@ use crate::{
 		instance::{self, Instance},
 		texture, DescriptorId,
 	},
-	graphics::{model::Model as ModelTrait, voxel::camera},
+	graphics::{
+		model::Model as ModelTrait,
+		voxel::{camera, instance::ChangeStats},
+	},
 	CrystalSphinx,
 };
 use anyhow::Result;
@@ -14,15 +17,21 @@ use engine::{
 		chain::{operation::RequiresRecording, Operation},
 		command, flags,
 		procedure::Phase,
-		resource::ColorBuffer,
 		Chain, Drawable, Uniform,
 	},
 	Application,
 };
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc, Mutex, RwLock,
+};
 
 static ID: &'static str = "render-entity";
 
+/// The size of the entity instances written during the most recent frame.
+static CHANGED_RANGES: AtomicUsize = AtomicUsize::new(0);
+static CHANGED_INDICES: AtomicUsize = AtomicUsize::new(0);
+
 /// Management of non-block models and executing draw-calls for entities during frame render.
 /// Exists only as long as the user is in a world
 /// (it is saved to session storage, created when entering a game and destroyed upon leaving).
@@ -96,6 +105,15 @@ impl RenderModel {
 	fn arclocked(self) -> Arc<RwLock<Self>> {
 		Arc::new(RwLock::new(self))
 	}
+
+	/// Returns the size of the entity instances written to the gpu by the most recent frame
+	/// whose instance buffer could be locked.
+	pub fn last_changes() -> ChangeStats {
+		ChangeStats {
+			ranges: CHANGED_RANGES.load(Ordering::Relaxed),
+			indices: CHANGED_INDICES.load(Ordering::Relaxed),
+		}
+	}
 }
 
 impl Operation for RenderModel {
@@ -109,11 +127,7 @@ impl Operation for RenderModel {
 	fn construct(&mut self, chain: &Chain, subpass_index: usize) -> anyhow::Result<()> {
 		use graphics::pipeline::{state::*, Pipeline};
 
-		let sample_count = {
-			let arc = chain.resources().get::<ColorBuffer>()?;
-			let color_buffer = arc.read().unwrap();
-			color_buffer.sample_count()
-		};
+		let sample_count = crate::graphics::color_sample_count(chain);
 
 		let tex_desc_layout = self
 			.texture_cache
@@ -183,7 +197,14 @@ impl Operation for RenderModel {
 		// so that updating one buffer doesn't wait for the previous from to be complete.
 		// If the instances change, we need to re-record the render.
 		let was_changed = match self.instance_buffer.write() {
-			Ok(mut buffer) => buffer.submit(chain, chain.signal_sender())?,
+			Ok(mut buffer) => {
+				let was_changed = buffer.submit(chain, chain.signal_sender())?;
+				let changes = buffer.last_changes();
+				CHANGED_RANGES.store(changes.ranges, Ordering::Relaxed);
+				CHANGED_INDICES.store(changes.indices, Ordering::Relaxed);
+				was_changed
+			}
+			// The last frame's stats are kept.
 			Err(_) => false,
 		};
 		Ok(match was_changed {
@@ -14,6 +14,7 @@ static LOG: &'static str = "subsystem:GatherEntitiesToRender";
 
 type QueryBundle<'c> = hecs::PreparedQuery<(
 	&'c component::physics::linear::Position,
+	Option<&'c component::physics::linear::InterpolatedPosition>,
 	&'c component::Orientation,
 	Option<&'c blender::Component>,
 	Option<&'c PlayerModel>,
@@ -64,7 +65,7 @@ impl EngineSystem for GatherEntitiesToRender {
 
 		let world = arc_world.read().unwrap();
 		let mut query_bundle = QueryBundle::new();
-		for (entity, (position, orientation, basic_model, player_model)) in
+		for (entity, (position, interpolated, orientation, basic_model, player_model)) in
 			query_bundle.query(&world).iter()
 		{
 			let body_rotation = {
@@ -74,6 +75,13 @@ impl EngineSystem for GatherEntitiesToRender {
 				math::face_towards_rh(&forward, &*world::global_up())
 			};
 
+			// Replicated entities are rendered where they have been interpolated to,
+			// rather than snapping to each position the server sends.
+			let position = match interpolated {
+				Some(interpolated) => interpolated.rendered(),
+				None => position,
+			};
+
 			let instance = Instance::builder()
 				.with_chunk(*position.chunk())
 				.with_offset(*position.offset())
@@ -107,7 +115,7 @@ impl EngineSystem for GatherEntitiesToRender {
 	}
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DescriptorId {
 	pub model_id: asset::Id,
 	pub texture_id: asset::Id,
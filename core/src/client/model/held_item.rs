@@ -0,0 +1,170 @@
+use crate::{
+	client::model::{instance::Instance, DescriptorId},
+	entity::{
+		self,
+		component::{self, debug, Registration},
+	},
+};
+use engine::{
+	math::nalgebra::{Point3, Unit, UnitQuaternion, Vector3},
+	world,
+};
+use serde::{Deserialize, Serialize};
+
+mod render;
+pub use render::*;
+
+/// The item an entity is holding in its hand.
+/// For the entity controlled by the local player, the item is drawn in front of the camera.
+///
+/// Added (holding nothing) to the local player by the [`client archetype`](crate::entity::archetype::player::Client),
+/// and kept in sync with the block the player would place by [`UpdateHeldItem`](crate::client::UpdateHeldItem).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeldItem {
+	item: Option<DescriptorId>,
+}
+
+impl HeldItem {
+	pub fn new(item: Option<DescriptorId>) -> Self {
+		Self { item }
+	}
+
+	pub fn item(&self) -> Option<&DescriptorId> {
+		self.item.as_ref()
+	}
+
+	pub fn set_item(&mut self, item: Option<DescriptorId>) {
+		self.item = item;
+	}
+}
+
+impl component::Component for HeldItem {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::model::HeldItem"
+	}
+
+	fn display_name() -> &'static str {
+		"Held Item"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for HeldItem {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match &self.item {
+			Some(item) => write!(
+				f,
+				"HeldItem(model={}, texture={})",
+				item.model_id, item.texture_id
+			),
+			None => write!(f, "HeldItem(None)"),
+		}
+	}
+}
+
+impl debug::EguiInformation for HeldItem {
+	fn describe(&self) -> Vec<String> {
+		match &self.item {
+			Some(item) => vec![
+				format!("Model Id: {}", item.model_id),
+				format!("Texture Id: {}", item.texture_id),
+			],
+			None => vec!["Nothing".to_owned()],
+		}
+	}
+}
+
+type QueryBundle<'c> = hecs::PreparedQuery<(
+	&'c HeldItem,
+	&'c component::Camera,
+	Option<&'c component::physics::linear::Velocity>,
+)>;
+
+/// How far (in blocks) the held item moves up and down while its holder is moving.
+static BOB_AMPLITUDE: f32 = 0.04;
+/// How many times per second the held item bobs while its holder is moving at full speed.
+static BOB_FREQUENCY: f32 = 1.5;
+/// The horizontal speed (in blocks per second) at which the held item bobs the most.
+static BOB_FULL_SPEED: f32 = 4.0;
+
+/// Returns the item held by the entity the local player is viewing the world through (the entity with a camera),
+/// and where it should be drawn relative to the camera, or None if nothing is held.
+///
+/// `time` is the number of seconds since rendering began, and is used to bob the item while the holder moves.
+pub fn held_item_view(
+	world: &entity::World,
+	time: f32,
+) -> Option<(hecs::Entity, DescriptorId, Instance)> {
+	let mut query_bundle = QueryBundle::new();
+	let mut query = query_bundle.query(&world);
+	let (entity, (held_item, _camera, velocity)) = query.iter().next()?;
+	let item = held_item.item()?.clone();
+
+	let speed = match velocity {
+		Some(velocity) => Vector3::new(velocity.x, 0.0, velocity.z).magnitude(),
+		None => 0.0,
+	};
+	let bob_scale = (speed / BOB_FULL_SPEED).min(1.0);
+	let bob = (time * BOB_FREQUENCY * std::f32::consts::TAU).sin() * BOB_AMPLITUDE * bob_scale;
+
+	// The item is held to the bottom-right of the camera, and turned slightly towards the center of the screen.
+	let offset = *world::global_right() * 0.6
+		+ *world::global_up() * (bob - 0.5)
+		+ *world::global_forward() * 1.2;
+	let orientation = UnitQuaternion::from_axis_angle(
+		&Unit::new_normalize(*world::global_up()),
+		20.0f32.to_radians(),
+	);
+	let instance = Instance::builder()
+		.with_offset(Point3::from(offset))
+		.with_orientation(orientation)
+		.build();
+
+	Some((entity, item, instance))
+}
+
+#[cfg(test)]
+mod held_item {
+	use super::*;
+	use crate::CrystalSphinx;
+	use engine::Application;
+
+	fn item() -> DescriptorId {
+		DescriptorId {
+			model_id: CrystalSphinx::get_asset_id("entity/humanoid/default"),
+			texture_id: CrystalSphinx::get_asset_id("entity/humanoid/textures/default"),
+		}
+	}
+
+	#[test]
+	fn nothing_without_component() {
+		let mut world = entity::World::new();
+		world.spawn((component::Camera::default(),));
+		assert!(held_item_view(&world, 0.0).is_none());
+	}
+
+	#[test]
+	fn nothing_when_empty_handed() {
+		let mut world = entity::World::new();
+		world.spawn((component::Camera::default(), HeldItem::new(None)));
+		assert!(held_item_view(&world, 0.0).is_none());
+	}
+
+	#[test]
+	fn held_by_camera_entity() {
+		let mut world = entity::World::new();
+		// Items held by other entities are not drawn in front of the camera.
+		world.spawn((HeldItem::new(Some(item())),));
+		let local = world.spawn((component::Camera::default(), HeldItem::new(Some(item()))));
+		let (entity, descriptor, _instance) = held_item_view(&world, 0.0).unwrap();
+		assert_eq!(entity, local);
+		assert_eq!(descriptor.model_id, item().model_id);
+	}
+}
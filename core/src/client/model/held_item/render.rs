@@ -0,0 +1,271 @@
+use crate::{
+	client::model::{
+		blender::model,
+		instance::{self, Instance},
+		texture, DescriptorId,
+	},
+	entity,
+	graphics::{model::Model as ModelTrait, voxel::camera},
+	CrystalSphinx,
+};
+use anyhow::Result;
+use engine::{
+	graphics::{
+		self,
+		chain::{operation::RequiresRecording, Operation},
+		command, flags,
+		procedure::Phase,
+		Chain, Drawable, Uniform,
+	},
+	Application,
+};
+use std::{
+	sync::{Arc, Mutex, RwLock, Weak},
+	time::Instant,
+};
+
+static ID: &'static str = "render-held-item";
+
+/// Draws the [`item held`](super::HeldItem) by the local player in front of the camera, during the viewmodel phase.
+///
+/// The item is drawn from a camera which is fixed at the origin (with the same projection as the world camera),
+/// so it stays in the bottom-right of the screen regardless of where the player is looking.
+/// Exists only as long as the user is in a world.
+pub struct RenderHeldItem {
+	drawable: Drawable,
+	camera_uniform: Uniform,
+	camera: Arc<RwLock<camera::Camera>>,
+	world: Weak<RwLock<entity::World>>,
+	model_cache: Arc<model::Cache>,
+	instance_buffer: instance::Buffer,
+	texture_cache: Arc<Mutex<texture::Cache>>,
+	start_time: Instant,
+	/// The item drawn by the last recording, if any.
+	held: Option<DescriptorId>,
+}
+
+impl RenderHeldItem {
+	pub fn create(
+		chain: &Arc<RwLock<Chain>>,
+		phase: &Arc<Phase>,
+		camera: Arc<RwLock<camera::Camera>>,
+		world: Weak<RwLock<entity::World>>,
+		model_cache: Arc<model::Cache>,
+		texture_cache: Arc<Mutex<texture::Cache>>,
+	) -> Result<Arc<RwLock<Self>>> {
+		log::info!(target: ID, "Initializing");
+		let instance = Arc::new(RwLock::new(Self::new(
+			&chain.read().unwrap(),
+			camera,
+			world,
+			model_cache,
+			texture_cache,
+		)?));
+
+		log::trace!(target: ID, "Adding to render chain");
+		let mut chain = chain.write().unwrap();
+		chain.add_operation(phase, Arc::downgrade(&instance), None)?;
+
+		Ok(instance)
+	}
+
+	fn new(
+		chain: &Chain,
+		camera: Arc<RwLock<camera::Camera>>,
+		world: Weak<RwLock<entity::World>>,
+		model_cache: Arc<model::Cache>,
+		texture_cache: Arc<Mutex<texture::Cache>>,
+	) -> Result<Self> {
+		log::trace!(target: ID, "Creating renderer");
+
+		let mut drawable = Drawable::default().with_name(ID);
+		drawable.add_shader(&CrystalSphinx::get_asset_id("shaders/entity/vertex"))?;
+		drawable.add_shader(&CrystalSphinx::get_asset_id("shaders/entity/fragment"))?;
+
+		let camera_uniform = Uniform::new::<camera::UniformData, &str>(
+			"RenderHeldItem.Camera",
+			&chain.logical()?,
+			&chain.allocator()?,
+			chain.persistent_descriptor_pool(),
+			chain.view_count(),
+		)?;
+
+		// Only one item is ever held at a time.
+		let instance_buffer = instance::Buffer::new(
+			&chain.allocator()?,
+			std::mem::size_of::<Instance>(),
+			chain.view_count(),
+		)?;
+
+		Ok(Self {
+			drawable,
+			camera_uniform,
+			camera,
+			world,
+			model_cache,
+			instance_buffer,
+			texture_cache,
+			start_time: Instant::now(),
+			held: None,
+		})
+	}
+}
+
+impl Operation for RenderHeldItem {
+	fn initialize(&mut self, chain: &Chain) -> anyhow::Result<()> {
+		self.drawable.create_shaders(&chain.logical()?)?;
+		self.camera_uniform
+			.write_descriptor_sets(&*chain.logical()?);
+		Ok(())
+	}
+
+	fn construct(&mut self, chain: &Chain, subpass_index: usize) -> anyhow::Result<()> {
+		use graphics::pipeline::{state::*, Pipeline};
+
+		let sample_count = crate::graphics::color_sample_count(chain);
+
+		let tex_desc_layout = self
+			.texture_cache
+			.lock()
+			.unwrap()
+			.descriptor_layout()
+			.clone();
+
+		// The viewmodel phase has its own depth buffer, so the held item is always drawn on top of the world
+		// while the faces of its model still occlude each other.
+		self.drawable.create_pipeline(
+			&chain.logical()?,
+			vec![self.camera_uniform.layout(), &tex_desc_layout],
+			Pipeline::builder()
+				.with_vertex_layout(
+					vertex::Layout::default()
+						.with_object::<model::Vertex>(0, flags::VertexInputRate::VERTEX)
+						.with_object::<Instance>(1, flags::VertexInputRate::INSTANCE),
+				)
+				.set_viewport_state(Viewport::from(*chain.extent()))
+				.set_color_blending(
+					color_blend::ColorBlend::default()
+						.add_attachment(color_blend::Attachment::default()),
+				)
+				.with_multisampling(
+					Multisampling::default()
+						.with_sample_count(sample_count)
+						.with_sample_shading(Some(0.25)),
+				)
+				.with_depth_stencil(
+					DepthStencil::default()
+						.with_depth_test()
+						.with_depth_write()
+						.with_depth_bounds(0.0, 1.0)
+						.with_depth_compare_op(flags::CompareOp::LESS),
+				),
+			chain.render_pass(),
+			subpass_index,
+		)?;
+		Ok(())
+	}
+
+	fn deconstruct(&mut self, _chain: &Chain) -> anyhow::Result<()> {
+		self.drawable.destroy_pipeline()?;
+		Ok(())
+	}
+
+	fn prepare_for_frame(&mut self, _chain: &Chain) -> anyhow::Result<()> {
+		Ok(())
+	}
+
+	fn prepare_for_submit(
+		&mut self,
+		chain: &Chain,
+		frame_image: usize,
+	) -> anyhow::Result<RequiresRecording> {
+		let view = match self.world.upgrade() {
+			Some(arc_world) => {
+				let world = arc_world.read().unwrap();
+				super::held_item_view(&world, self.start_time.elapsed().as_secs_f32())
+			}
+			None => None,
+		};
+
+		let was_holding = self.held.is_some();
+		let (entity, descriptor, instance) = match view {
+			Some(view) => view,
+			None => {
+				self.held = None;
+				// The previous recording drew the item, so the frame must be re-recorded without it.
+				return Ok(match was_holding {
+					true => RequiresRecording::CurrentFrame,
+					false => RequiresRecording::NotRequired,
+				});
+			}
+		};
+
+		let data = {
+			let world_camera = self.camera.read().unwrap();
+			let view_camera = camera::Camera {
+				projection: world_camera.projection,
+				..Default::default()
+			};
+			view_camera.as_uniform_data(&chain.resolution())
+		};
+		self.camera_uniform.write_data(frame_image, &data)?;
+
+		if let Ok(mut cache) = self.texture_cache.lock() {
+			cache.mark_required(&descriptor.texture_id);
+			cache.load_pending(chain)?;
+		}
+
+		self.held = Some(descriptor.clone());
+		self.instance_buffer
+			.set_pending(vec![(entity, descriptor, instance)]);
+		self.instance_buffer.submit(chain, chain.signal_sender())?;
+
+		Ok(RequiresRecording::CurrentFrame)
+	}
+
+	fn record(&mut self, buffer: &mut command::Buffer, buffer_index: usize) -> anyhow::Result<()> {
+		use graphics::debug;
+		profiling::scope!("record:RenderHeldItem");
+
+		let DescriptorId {
+			model_id,
+			texture_id,
+		} = match &self.held {
+			Some(descriptor) => descriptor,
+			// When nothing is held, nothing is drawn.
+			None => return Ok(()),
+		};
+		let (model, index_start, vertex_offset) = match self.model_cache.get(&model_id) {
+			Some(entry) => entry,
+			None => return Ok(()),
+		};
+		let texture_descriptor_set = {
+			let texture_cache = self.texture_cache.lock().unwrap();
+			texture_cache.get_or_default(&texture_id).cloned()
+		};
+		let tex_desc_set = match texture_descriptor_set.map(|set| set.upgrade()).flatten() {
+			Some(set) => set,
+			None => return Ok(()),
+		};
+
+		buffer.begin_label(
+			format!("Draw:HeldItem({model_id}, {texture_id})"),
+			debug::LABEL_COLOR_DRAW,
+		);
+		self.drawable.bind_pipeline(buffer);
+		buffer.bind_vertex_buffers(0, vec![&self.model_cache.vertex_buffer], vec![0]);
+		buffer.bind_vertex_buffers(1, vec![self.instance_buffer.buffer()], vec![0]);
+		buffer.bind_index_buffer(&self.model_cache.index_buffer, 0);
+		self.drawable.bind_descriptors(
+			buffer,
+			vec![
+				&self.camera_uniform.get_set(buffer_index).unwrap(),
+				&tex_desc_set,
+			],
+		);
+		buffer.draw(model.indices().len(), *index_start, 1, 0, *vertex_offset);
+		buffer.end_label();
+
+		Ok(())
+	}
+}
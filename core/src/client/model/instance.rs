@@ -1,4 +1,7 @@
-use crate::client::model::DescriptorId;
+use crate::{
+	client::model::DescriptorId,
+	graphics::{voxel::instance::ChangeStats, Growable},
+};
 use engine::{
 	channels::mpsc::Sender,
 	graphics::{
@@ -98,27 +101,40 @@ Any mutations to the category slices or data itself must be marked and mutated d
 pub struct Buffer {
 	pending: Option<Vec<(hecs::Entity, DescriptorId, Instance)>>,
 	submitted: Vec<(hecs::Entity, DescriptorId, usize)>,
-	buffer: Arc<buffer::Buffer>,
+	allocator: Arc<alloc::Allocator>,
+	buffer: Growable<Arc<buffer::Buffer>>,
+	last_changes: ChangeStats,
 }
 
 impl Buffer {
 	pub fn new(
 		allocator: &Arc<alloc::Allocator>,
 		instance_buffer_size: usize,
+		frames_in_flight: usize,
 	) -> anyhow::Result<Self> {
-		let buffer = buffer::Buffer::create_gpu(
+		let capacity = instance_buffer_size / std::mem::size_of::<Instance>();
+		let buffer = Self::create_buffer(allocator, capacity)?;
+		Ok(Self {
+			pending: None,
+			submitted: Vec::new(),
+			allocator: allocator.clone(),
+			buffer: Growable::new("RenderModel", buffer, capacity, frames_in_flight),
+			last_changes: ChangeStats::default(),
+		})
+	}
+
+	fn create_buffer(
+		allocator: &Arc<alloc::Allocator>,
+		capacity: usize,
+	) -> anyhow::Result<Arc<buffer::Buffer>> {
+		Ok(buffer::Buffer::create_gpu(
 			format!("RenderModel.InstanceBuffer"),
 			allocator,
 			flags::BufferUsage::VERTEX_BUFFER,
-			instance_buffer_size,
+			capacity * std::mem::size_of::<Instance>(),
 			None,
 			false,
-		)?;
-		Ok(Self {
-			pending: None,
-			submitted: Vec::new(),
-			buffer,
-		})
+		)?)
 	}
 
 	pub fn set_pending(&mut self, entities: Vec<(hecs::Entity, DescriptorId, Instance)>) {
@@ -126,38 +142,63 @@ impl Buffer {
 	}
 
 	pub fn buffer(&self) -> &Arc<buffer::Buffer> {
-		&self.buffer
+		self.buffer.current()
+	}
+
+	/// Returns true if the most recent submission had more instances than the buffer could hold,
+	/// and a larger buffer could not be allocated. The instances which did not fit are not rendered.
+	pub fn capacity_exceeded(&self) -> bool {
+		self.buffer.capacity_exceeded()
 	}
 
 	pub fn submitted(&self) -> &Vec<(hecs::Entity, DescriptorId, usize)> {
 		&self.submitted
 	}
 
+	/// The size of the instances written by the most recent [`submit`](Self::submit).
+	/// Every instance is rewritten when the instances change, so this is a single range (or none).
+	pub fn last_changes(&self) -> ChangeStats {
+		self.last_changes
+	}
+
 	pub fn submit(
 		&mut self,
 		context: &impl GpuOpContext,
 		signal_sender: &Sender<Arc<command::Semaphore>>,
 	) -> anyhow::Result<bool> {
-		let (descriptors, instances): (Vec<(hecs::Entity, DescriptorId, usize)>, Vec<Instance>) =
-			match self.pending.take() {
-				Some(entities) => entities
-					.into_iter()
-					.enumerate()
-					.map(|(idx, (entity, descriptor, instance))| {
-						((entity, descriptor, idx), instance)
-					})
-					.unzip(),
-				None => return Ok(false),
-			};
+		let (mut descriptors, mut instances): (
+			Vec<(hecs::Entity, DescriptorId, usize)>,
+			Vec<Instance>,
+		) = match self.pending.take() {
+			Some(entities) => entities
+				.into_iter()
+				.enumerate()
+				.map(|(idx, (entity, descriptor, instance))| ((entity, descriptor, idx), instance))
+				.unzip(),
+			None => {
+				self.last_changes = ChangeStats::default();
+				return Ok(false);
+			}
+		};
+
+		let writable_count = {
+			let allocator = &self.allocator;
+			self.buffer.reserve(instances.len(), |capacity| {
+				Self::create_buffer(allocator, capacity)
+			})
+		};
+		descriptors.truncate(writable_count);
+		instances.truncate(writable_count);
+		self.last_changes = ChangeStats::of(&[0..writable_count]);
+		let buffer = self.buffer.current().clone();
 
 		let mut ranges = Vec::with_capacity(1);
 
 		let mut task = {
 			profiling::scope!("prepare-task");
 			let mut task =
-				GpuOperationBuilder::new(format!("Write({})", self.buffer.name()), context)?
-					.begin()?;
-			task.stage_start(self.buffer.size())?;
+				GpuOperationBuilder::new(format!("Write({})", buffer.name()), context)?.begin()?;
+			task.stage_start(buffer.size())?;
 			task
 		};
 
@@ -167,13 +208,13 @@ impl Buffer {
 			ranges.push(command::CopyBufferRange {
 				start_in_src: 0,
 				start_in_dst: 0,
-				size: self.buffer.size(),
+				size: buffer.size(),
 			});
 		}
 
 		{
 			profiling::scope!("run-task");
-			task.copy_stage_to_buffer_ranges(&self.buffer, ranges)
+			task.copy_stage_to_buffer_ranges(&buffer, ranges)
 				.send_signal_to(signal_sender)?
 				.end()?;
 		}
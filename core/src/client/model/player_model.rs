@@ -69,16 +69,12 @@ impl std::fmt::Display for PlayerModel {
 }
 
 impl debug::EguiInformation for PlayerModel {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label("Third Person");
-		ui.indent("third", |ui| {
-			ui.label(format!("Model Id: {}", self.third_person.model_id));
-			ui.label(format!("Texture Id: {}", self.third_person.texture_id));
-		});
-		ui.label("First Person");
-		ui.indent("first", |ui| {
-			ui.label(format!("Model Id: {}", self.first_person.model_id));
-			ui.label(format!("Texture Id: {}", self.first_person.texture_id));
-		});
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!("Third Person Model Id: {}", self.third_person.model_id),
+			format!("Third Person Texture Id: {}", self.third_person.texture_id),
+			format!("First Person Model Id: {}", self.first_person.model_id),
+			format!("First Person Texture Id: {}", self.first_person.texture_id),
+		]
 	}
 }
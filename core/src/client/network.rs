@@ -1,12 +1,26 @@
 use crate::{client::account, client::world::chunk, common, common::account::key};
 use anyhow::Result;
 use socknet::connection::Connection;
-use std::sync::{Arc, RwLock, Weak};
+use std::{
+	collections::VecDeque,
+	sync::{Arc, RwLock, Weak},
+};
+
+/// The most players who left the server which a client remembers, the oldest are forgotten first.
+pub static MAX_DEPARTURES: usize = 16;
 
 /// Container class for all client data which is present when a user is connected to a game server.
 pub struct Storage {
 	chunk_sender: chunk::OperationSender,
 	chunk_receiver: chunk::OperationReceiver,
+	/// The radius of the largest area of the relevance last received from the server.
+	relevance_radius: Option<u64>,
+	/// The quality of the connection to the server, last reported in a [`heartbeat`](common::network::heartbeat).
+	connection_quality: Option<common::network::heartbeat::Quality>,
+	/// The estimate of the server's time of day, once it has been [`replicated`](common::network::clock).
+	clock: Option<common::world::clock::Interpolated>,
+	/// The accounts of the players who most recently [`left`](common::network::client_left) the server, oldest first.
+	departures: VecDeque<common::account::Id>,
 }
 
 impl Default for Storage {
@@ -15,6 +29,10 @@ impl Default for Storage {
 		Self {
 			chunk_sender,
 			chunk_receiver,
+			relevance_radius: None,
+			connection_quality: None,
+			clock: None,
+			departures: VecDeque::new(),
 		}
 	}
 }
@@ -28,6 +46,46 @@ impl Storage {
 		&self.chunk_receiver
 	}
 
+	pub fn relevance_radius(&self) -> Option<u64> {
+		self.relevance_radius
+	}
+
+	pub fn set_relevance_radius(&mut self, radius: Option<u64>) {
+		self.relevance_radius = radius;
+	}
+
+	pub fn connection_quality(&self) -> Option<common::network::heartbeat::Quality> {
+		self.connection_quality
+	}
+
+	pub fn set_connection_quality(&mut self, quality: Option<common::network::heartbeat::Quality>) {
+		self.connection_quality = quality;
+	}
+
+	pub fn clock(&self) -> Option<&common::world::clock::Interpolated> {
+		self.clock.as_ref()
+	}
+
+	pub fn clock_mut(&mut self) -> Option<&mut common::world::clock::Interpolated> {
+		self.clock.as_mut()
+	}
+
+	pub fn set_clock(&mut self, clock: Option<common::world::clock::Interpolated>) {
+		self.clock = clock;
+	}
+
+	pub fn departures(&self) -> &VecDeque<common::account::Id> {
+		&self.departures
+	}
+
+	/// Records that a player left the server, forgetting the oldest departure if [`MAX_DEPARTURES`] are already remembered.
+	pub fn push_departure(&mut self, account_id: common::account::Id) {
+		while self.departures.len() >= MAX_DEPARTURES {
+			self.departures.pop_front();
+		}
+		self.departures.push_back(account_id);
+	}
+
 	pub fn get_keys(&self) -> Result<(rustls::Certificate, rustls::PrivateKey)> {
 		let certificate: rustls::Certificate;
 		let private_key: rustls::PrivateKey;
@@ -0,0 +1,159 @@
+use super::model::{DescriptorId, HeldItem};
+use crate::{
+	block,
+	entity::{
+		self,
+		component::{self, Inventory},
+	},
+	graphics::voxel::Face,
+};
+use engine::{asset, Engine, EngineSystem};
+use std::sync::{Arc, RwLock, Weak};
+
+type QueryBundle<'c> =
+	hecs::PreparedQuery<(&'c component::Camera, &'c Inventory, &'c mut HeldItem)>;
+
+/// The model held blocks are drawn with, a cube which is textured by the front face of the block.
+fn block_model_id() -> asset::Id {
+	use engine::Application;
+	crate::CrystalSphinx::get_asset_id("item/block")
+}
+
+/// Returns the item an entity holds given its inventory,
+/// which is the block the entity would place (its [`first block`](Inventory::first_block)).
+///
+/// Returns None if the inventory has no blocks, or if the block has no texture on its front face.
+pub fn held_item_of(inventory: &Inventory, lookup: &block::Lookup) -> Option<DescriptorId> {
+	let (_slot, block_id) = inventory.first_block()?;
+	let texture = lookup.block(block_id)?.texture_on(Face::Front)?;
+	Some(DescriptorId {
+		model_id: block_model_id(),
+		texture_id: texture.texture_id.clone(),
+	})
+}
+
+/// Sets the [`HeldItem`] of the entity the local player is viewing the world through
+/// to the block in its inventory, as the inventory is replicated from the server.
+pub struct UpdateHeldItem {
+	world: Weak<RwLock<entity::World>>,
+}
+
+impl UpdateHeldItem {
+	pub fn create(world: Weak<RwLock<entity::World>>) -> anyhow::Result<Option<Arc<RwLock<Self>>>> {
+		let arc_self = Arc::new(RwLock::new(Self { world }));
+		// Run updates on the system as long as the object exists (i.e. while the app's state is `InGame`).
+		if let Ok(mut engine) = Engine::get().write() {
+			engine.add_weak_system(Arc::downgrade(&arc_self));
+		}
+		Ok(Some(arc_self))
+	}
+
+	fn update_world(world: &entity::World, lookup: &block::Lookup) {
+		let mut query_bundle = QueryBundle::new();
+		for (_entity, (_camera, inventory, held_item)) in query_bundle.query(&world).iter() {
+			let item = held_item_of(inventory, lookup);
+			if held_item.item() != item.as_ref() {
+				held_item.set_item(item);
+			}
+		}
+	}
+}
+
+impl EngineSystem for UpdateHeldItem {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!("subsystem:update_held_item");
+
+		let lookup = match block::Lookup::get() {
+			Some(lookup) => lookup,
+			None => return,
+		};
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		let world = arc_world.read().unwrap();
+		Self::update_world(&world, &lookup);
+	}
+}
+
+#[cfg(test)]
+mod held_block {
+	use super::*;
+	use crate::{
+		block::{Block, TextureEntry, TextureRotation},
+		entity::component::{Item, Stack},
+		CrystalSphinx,
+	};
+	use engine::Application;
+	use enumset::EnumSet;
+
+	fn texture(name: &str) -> TextureEntry {
+		let texture_id = CrystalSphinx::get_asset_id(name);
+		TextureEntry {
+			all_texture_ids: vec![texture_id.clone()],
+			texture_id,
+			biome_color: (false, None),
+			rotation: TextureRotation::default(),
+			animation: None,
+		}
+	}
+
+	fn create_lookup() -> block::Lookup {
+		let mut lookup = block::Lookup::default();
+		lookup.push(
+			CrystalSphinx::get_asset_id("blocks/glass"),
+			Block::default(),
+		);
+		lookup.push(
+			CrystalSphinx::get_asset_id("blocks/grass"),
+			Block::default()
+				.with_texture(texture("blocks/grass/top"), EnumSet::only(Face::Up))
+				.with_texture(
+					texture("blocks/grass/side"),
+					EnumSet::all() - EnumSet::only(Face::Up),
+				),
+		);
+		lookup
+	}
+
+	#[test]
+	fn holds_the_block_which_is_placed() {
+		let lookup = create_lookup();
+		let mut inventory = Inventory::new(3);
+		assert_eq!(held_item_of(&inventory, &lookup), None);
+
+		inventory.set(
+			1,
+			Some(Stack {
+				item: Item::Block(1),
+				count: 5,
+			}),
+		);
+		let item = held_item_of(&inventory, &lookup).unwrap();
+		assert_eq!(item.model_id, block_model_id());
+		assert_eq!(
+			item.texture_id,
+			CrystalSphinx::get_asset_id("blocks/grass/side")
+		);
+
+		// Blocks without a texture can't be drawn.
+		inventory.set(1, None);
+		inventory.insert(Item::Block(0), 1);
+		assert_eq!(held_item_of(&inventory, &lookup), None);
+	}
+
+	#[test]
+	fn follows_the_inventory() {
+		let lookup = create_lookup();
+		let mut world = entity::World::new();
+		let mut inventory = Inventory::new(3);
+		inventory.insert(Item::Block(1), 1);
+		let local = world.spawn((component::Camera::default(), inventory, HeldItem::new(None)));
+		UpdateHeldItem::update_world(&world, &lookup);
+		assert!(world.get::<HeldItem>(local).unwrap().item().is_some());
+
+		world.get_mut::<Inventory>(local).unwrap().remove(0, 1);
+		UpdateHeldItem::update_world(&world, &lookup);
+		assert!(world.get::<HeldItem>(local).unwrap().item().is_none());
+	}
+}
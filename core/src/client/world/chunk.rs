@@ -3,9 +3,27 @@ use engine::math::nalgebra::Point3;
 
 use crate::block;
 
+mod prediction;
+pub use prediction::*;
+
 pub type OperationSender = Sender<Operation>;
 pub type OperationReceiver = Receiver<Operation>;
 pub enum Operation {
 	Remove(Point3<i64>),
-	Insert(Point3<i64>, Vec<(Point3<usize>, block::LookupId)>),
+	/// The authoritative chunk was received from the server, at a [`version`](crate::common::world::chunk::Chunk::version).
+	Insert(
+		Point3<i64>,
+		u64,
+		Vec<(Point3<usize>, block::LookupId, block::State)>,
+	),
+	/// The seed of the world the client has joined, which chunks are [`predicted`](Operation::Predict) with.
+	Seed(u64),
+	/// The chunk has become relevant, but has not been received from the server yet.
+	/// A provisional chunk is generated locally until the authoritative chunk is [`inserted`](Operation::Insert).
+	Predict(Point3<i64>),
+	/// Blocks in chunks the client has been sent were changed on the server, in the order they changed.
+	/// Changes to chunks which have not been received yet (including those which are only predicted)
+	/// are [`deferred`](PredictionCache::defer_change) until the authoritative chunk is inserted.
+	/// Each change carries the version of its chunk after the change, and changes the client's copy already has are ignored.
+	SetBlocks(Vec<BlockChange>),
 }
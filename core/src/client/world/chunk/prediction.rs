@@ -0,0 +1,181 @@
+use crate::{block, common::world::generator};
+use engine::math::nalgebra::Point3;
+use std::collections::{HashMap, HashSet};
+
+/// Tracks which chunks on the client are provisional predictions and which have been received from the server.
+///
+/// Predicted chunks are generated locally with the same [`pipeline`](generator::Pipeline::for_world) as the server
+/// (using the world seed the server sends, and the decorators of the plugins the client has),
+/// so the chunk can be displayed before it has been replicated.
+/// When the authoritative chunk arrives, it is reconciled with the prediction
+/// instead of replacing it, so only the blocks which differ are updated.
+///
+/// The [`version`](crate::common::world::chunk::Chunk::version) of each received chunk is tracked,
+/// so updates which arrive out of order (and are older than the client's copy) can be discarded.
+///
+/// Block changes can arrive before the chunk they are in (the chunk may have been sent just before the change was made),
+/// so changes to chunks which have not been received yet are deferred until the chunk arrives.
+#[derive(Default)]
+pub struct PredictionCache {
+	/// The seed of the world the client is in, which nothing is predicted without.
+	seed: Option<u64>,
+	/// The generator for the seed, and the [`revision`](crate::plugin::Manager::revision) of the plugin config its decorators were registered at.
+	generator: Option<(generator::Pipeline, usize)>,
+	/// Chunks which have been predicted, but not yet received from the server.
+	predicted: HashSet<Point3<i64>>,
+	/// Chunks which have been received from the server, and the latest version of each the client has.
+	authoritative: HashMap<Point3<i64>, u64>,
+	/// Changes to chunks which have not been received yet, in the order they arrived.
+	deferred: HashMap<Point3<i64>, Vec<BlockChange>>,
+}
+
+/// A block which changed on the server, with the version of its chunk after the change.
+pub type BlockChange = (block::Point, Option<(block::LookupId, block::State)>, u64);
+
+impl PredictionCache {
+	/// Sets the seed of the world the client is in, which chunks are predicted with from then on.
+	pub fn set_seed(&mut self, seed: u64) {
+		if self.seed != Some(seed) {
+			self.seed = Some(seed);
+			self.generator = None;
+		}
+	}
+
+	/// Generates the predicted blocks of a chunk, if it has not already been predicted or received
+	/// and the seed of the world is known.
+	pub fn predict(
+		&mut self,
+		coordinate: Point3<i64>,
+	) -> Option<Vec<(Point3<usize>, block::LookupId, block::State)>> {
+		let seed = self.seed?;
+		if self.authoritative.contains_key(&coordinate) || !self.predicted.insert(coordinate) {
+			return None;
+		}
+		// The generator is created lazily because it requires the block lookup to have been loaded,
+		// and again if the plugins (and so the decorators) have changed since.
+		let plugins = crate::plugin::Manager::read().unwrap();
+		let is_stale = match &self.generator {
+			Some((_, revision)) => *revision != plugins.revision(),
+			None => true,
+		};
+		if is_stale {
+			let pipeline = generator::Pipeline::for_world(seed, &plugins);
+			self.generator = Some((pipeline, plugins.revision()));
+		}
+		let (generator, _) = self.generator.as_ref().unwrap();
+		Some(generator.generate_chunk(coordinate).blocks())
+	}
+
+	/// Returns true if the client already has a newer version of the chunk than `version`.
+	pub fn is_stale(&self, coordinate: &Point3<i64>, version: u64) -> bool {
+		matches!(self.authoritative.get(coordinate), Some(current) if *current > version)
+	}
+
+	/// Marks a chunk as received from the server at a version.
+	/// Returns true if the chunk was predicted, and therefore needs to be reconciled with its prediction.
+	pub fn receive(&mut self, coordinate: Point3<i64>, version: u64) -> bool {
+		self.authoritative.insert(coordinate, version);
+		self.predicted.remove(&coordinate)
+	}
+
+	/// Returns true if the chunk has been received from the server (and has not been removed since).
+	pub fn is_received(&self, coordinate: &Point3<i64>) -> bool {
+		self.authoritative.contains_key(coordinate)
+	}
+
+	/// Returns true if a change which leaves a chunk at `version` should be applied,
+	/// recording that the client's copy of the chunk is now at that version.
+	/// Changes to chunks which have not been received, or which the received chunk already includes, are rejected.
+	pub fn accept_change(&mut self, coordinate: &Point3<i64>, version: u64) -> bool {
+		match self.authoritative.get_mut(coordinate) {
+			Some(current) if *current < version => {
+				*current = version;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Holds onto a change to a chunk which has not been received yet,
+	/// until it is [`taken`](Self::take_deferred_changes) when the chunk is received.
+	pub fn defer_change(&mut self, change: BlockChange) {
+		let coordinate = *change.0.chunk();
+		self.deferred.entry(coordinate).or_default().push(change);
+	}
+
+	/// Returns the deferred changes to a chunk which has been received, in the order they arrived,
+	/// without those the received chunk already includes.
+	pub fn take_deferred_changes(&mut self, coordinate: &Point3<i64>) -> Vec<BlockChange> {
+		let changes = self.deferred.remove(coordinate).unwrap_or_default();
+		changes
+			.into_iter()
+			.filter(|(_, _, version)| self.accept_change(coordinate, *version))
+			.collect()
+	}
+
+	/// Forgets a chunk which is no longer relevant, so it can be predicted again if it becomes relevant.
+	pub fn remove(&mut self, coordinate: &Point3<i64>) {
+		self.predicted.remove(coordinate);
+		self.authoritative.remove(coordinate);
+		self.deferred.remove(coordinate);
+	}
+}
+
+#[cfg(test)]
+mod versions {
+	use super::*;
+
+	#[test]
+	fn nothing_is_predicted_without_a_seed() {
+		let mut cache = PredictionCache::default();
+		assert!(cache.predict(Point3::new(0, 0, 0)).is_none());
+		// The chunk can still be predicted once the seed is known.
+		assert!(cache.predicted.is_empty());
+	}
+
+	#[test]
+	fn older_changes_are_ignored() {
+		let coordinate = Point3::new(0, 1, 0);
+		let mut cache = PredictionCache::default();
+		// Changes to chunks which have not been received are ignored.
+		assert!(!cache.accept_change(&coordinate, 1));
+
+		assert!(!cache.receive(coordinate, 5));
+		assert!(!cache.accept_change(&coordinate, 4));
+		assert!(!cache.accept_change(&coordinate, 5));
+		assert!(cache.accept_change(&coordinate, 7));
+		// A change which was delayed behind a newer one is discarded.
+		assert!(!cache.accept_change(&coordinate, 6));
+		assert!(cache.is_stale(&coordinate, 6));
+		assert!(!cache.is_stale(&coordinate, 7));
+
+		cache.remove(&coordinate);
+		assert!(!cache.is_stale(&coordinate, 0));
+	}
+
+	#[test]
+	fn changes_before_the_chunk_are_deferred() {
+		let coordinate = Point3::new(0, 1, 0);
+		let change = |x: i8, version: u64| {
+			let point = block::Point::new(coordinate, Point3::new(x, 0, 0));
+			(point, Some((1, block::DEFAULT_STATE)), version)
+		};
+		let mut cache = PredictionCache::default();
+		cache.defer_change(change(0, 3));
+		cache.defer_change(change(1, 4));
+		cache.defer_change(change(2, 6));
+
+		// The chunk was sent after the first two changes were made.
+		cache.receive(coordinate, 4);
+		assert_eq!(cache.take_deferred_changes(&coordinate), vec![change(2, 6)]);
+		assert!(cache.take_deferred_changes(&coordinate).is_empty());
+		assert!(!cache.accept_change(&coordinate, 5));
+
+		// Changes to a chunk which is no longer relevant are forgotten.
+		cache.remove(&coordinate);
+		cache.defer_change(change(0, 7));
+		cache.remove(&coordinate);
+		cache.receive(coordinate, 0);
+		assert!(cache.take_deferred_changes(&coordinate).is_empty());
+	}
+}
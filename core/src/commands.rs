@@ -5,6 +5,33 @@ pub use network_load::*;
 mod network_stop;
 pub use network_stop::*;
 
+mod antialiasing;
+pub use antialiasing::*;
+
+mod chunk_limits;
+pub use chunk_limits::*;
+mod log_filter;
+pub use log_filter::*;
+mod copy;
+pub use copy::*;
+mod fill;
+pub use fill::*;
+mod paste;
+pub use paste::*;
+mod plugins;
+pub use plugins::*;
+mod save_all;
+pub use save_all::*;
+mod set_block;
+pub use set_block::*;
+mod stop;
+pub use stop::*;
+
+mod teleport;
+pub use teleport::*;
+mod time;
+pub use time::*;
+
 mod world_load;
 pub use world_load::*;
 mod world_unload;
@@ -12,12 +39,46 @@ pub use world_unload::*;
 
 mod command;
 pub use command::*;
+mod console;
+mod intake;
+pub use intake::Intake;
 
+use crate::{common::network::Storage, entity};
 use std::sync::{Arc, Mutex, RwLock};
-pub fn create_list(app_state: &Arc<RwLock<crate::app::state::Machine>>) -> CommandList {
+pub fn create_list(
+	app_state: &Arc<RwLock<crate::app::state::Machine>>,
+	storage: &Arc<RwLock<Storage>>,
+	world: &entity::ArcLockEntityWorld,
+) -> CommandList {
 	let mut cmds: Vec<ArctexCommand> = vec![];
 	cmds.push(LoadNetwork::new(app_state.clone()).as_arctex());
 	cmds.push(UnloadNetwork::new(app_state.clone()).as_arctex());
 	cmds.push(Connect::new(app_state.clone()).as_arctex());
+	cmds.push(
+		Teleport::new(
+			app_state.clone(),
+			Arc::downgrade(&storage),
+			Arc::downgrade(&world),
+		)
+		.as_arctex(),
+	);
+	cmds.push(ChunkLimits::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(SaveAll::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(CopyRegion::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(Paste::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(SetBlock::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(Fill::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(Time::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(Stop::new(app_state.clone(), Arc::downgrade(&storage)).as_arctex());
+	cmds.push(
+		LoadWorld::new(
+			app_state.clone(),
+			Arc::downgrade(&storage),
+			Arc::downgrade(&world),
+		)
+		.as_arctex(),
+	);
+	cmds.push(LogFilter::new().as_arctex());
+	cmds.push(Plugins::new().as_arctex());
 	Arc::new(Mutex::new(cmds))
 }
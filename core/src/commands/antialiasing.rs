@@ -0,0 +1,120 @@
+use super::Command;
+use crate::graphics;
+use engine::graphics::Chain;
+use std::sync::{RwLock, Weak};
+
+static LOG: &'static str = "command:msaa";
+
+/// Parses the sample count of `msaa <samples>`.
+/// Any positive number is accepted, because it is clamped to a supported count when the chain is reconstructed.
+pub fn parse_samples(line: &str) -> Result<u8, AntialiasingError> {
+	let args = line.split_whitespace().collect::<Vec<_>>();
+	match args[..] {
+		[_, samples] => samples
+			.parse::<u8>()
+			.ok()
+			.filter(|samples| *samples > 0)
+			.ok_or_else(|| AntialiasingError::InvalidSampleCount(samples.to_owned())),
+		_ => Err(AntialiasingError::InvalidArguments(line.to_owned())),
+	}
+}
+
+/// Graphics setting which changes the number of samples per pixel used to antialias the world.
+/// The chain is reconstructed (as it is when the window is resized),
+/// which recreates its color buffer and every pipeline which draws to it with the new sample count.
+/// Turning antialiasing on or off (to or from a single sample) needs different [`phases`](graphics::fits_phases),
+/// so it is refused until the next launch.
+pub struct Antialiasing {
+	chain: Weak<RwLock<Chain>>,
+	samples: u8,
+	message: Option<String>,
+}
+
+impl Antialiasing {
+	pub fn new(chain: Weak<RwLock<Chain>>) -> Self {
+		// Without a choice, the chain uses the most samples the device supports.
+		let max_samples = *graphics::SUPPORTED_SAMPLE_COUNTS.last().unwrap();
+		let samples = graphics::requested_samples().unwrap_or(max_samples);
+		Self {
+			chain,
+			samples,
+			message: None,
+		}
+	}
+
+	fn apply(&self, samples: u8) -> Result<String, AntialiasingError> {
+		let arc_chain = self
+			.chain
+			.upgrade()
+			.ok_or(AntialiasingError::InvalidChain)?;
+		if !graphics::fits_phases(samples) {
+			return Err(AntialiasingError::RequiresRestart(samples));
+		}
+		graphics::set_requested_samples(samples);
+		arc_chain.write().unwrap().mark_dirty();
+		let message = format!("Antialiasing set to {}x", samples);
+		log::info!(target: LOG, "{}", message);
+		Ok(message)
+	}
+}
+
+impl Command for Antialiasing {
+	fn is_allowed(&self) -> bool {
+		true
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Antialiasing");
+			for samples in graphics::SUPPORTED_SAMPLE_COUNTS.iter() {
+				ui.selectable_value(&mut self.samples, *samples, format!("{}x", samples));
+			}
+			if ui.button("Apply").clicked() {
+				self.message = Some(match self.apply(self.samples) {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["msaa"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let samples = parse_samples(line)?;
+		self.samples = samples;
+		Ok(self.apply(samples)?)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AntialiasingError {
+	#[error("\"{0}\" is not a valid command, expected msaa <samples>")]
+	InvalidArguments(String),
+	#[error("\"{0}\" is not a valid sample count")]
+	InvalidSampleCount(String),
+	#[error("graphics chain is invalid")]
+	InvalidChain,
+	#[error("switching antialiasing on or off requires a restart, launch with -msaa={0}")]
+	RequiresRestart(u8),
+}
+
+#[cfg(test)]
+mod antialiasing {
+	use super::*;
+
+	#[test]
+	fn parse_sample_count() {
+		assert_eq!(parse_samples("msaa 4").unwrap(), 4);
+		assert_eq!(parse_samples("msaa 3").unwrap(), 3);
+		assert!(parse_samples("msaa").is_err());
+		assert!(parse_samples("msaa 0").is_err());
+		assert!(parse_samples("msaa four").is_err());
+		assert!(parse_samples("msaa 4 8").is_err());
+	}
+}
@@ -0,0 +1,195 @@
+use super::Command;
+use crate::{
+	app,
+	common::network::{mode, Storage},
+	server::{network::DEFAULT_WORLD, world::chunk::Limits},
+};
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+static LOG: &'static str = "command:chunk-limits";
+
+/// A change to the [`chunk limits`](Limits) of a running server,
+/// parsed from the form `set-chunk-expiry <secs>` or `set-max-view-distance <chunks>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitChange {
+	ChunkExpiry(Duration),
+	MaxViewDistance(u64),
+}
+
+impl std::str::FromStr for LimitChange {
+	type Err = ChunkLimitsError;
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		let args = value.split_whitespace().collect::<Vec<_>>();
+		match args[..] {
+			["set-chunk-expiry", secs] => {
+				let secs = secs
+					.parse::<u64>()
+					.map_err(|_| ChunkLimitsError::InvalidNumber(secs.to_owned()))?;
+				Ok(Self::ChunkExpiry(Duration::from_secs(secs)))
+			}
+			["set-max-view-distance", distance] => {
+				let distance = distance
+					.parse::<u64>()
+					.map_err(|_| ChunkLimitsError::InvalidNumber(distance.to_owned()))?;
+				Ok(Self::MaxViewDistance(distance))
+			}
+			_ => Err(ChunkLimitsError::UnknownCommand(value.to_owned())),
+		}
+	}
+}
+
+impl std::fmt::Display for LimitChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::ChunkExpiry(delay) => write!(f, "Chunk expiry set to {}s", delay.as_secs()),
+			Self::MaxViewDistance(distance) => {
+				write!(f, "Max view distance set to {} chunks", distance)
+			}
+		}
+	}
+}
+
+impl LimitChange {
+	/// Changes the limits, which the chunk thread and replicator observe on their next update.
+	pub fn apply(&self, limits: &Limits) {
+		match self {
+			Self::ChunkExpiry(delay) => limits.set_expiration_delay(*delay),
+			Self::MaxViewDistance(distance) => limits.set_max_view_distance(*distance),
+		}
+	}
+}
+
+/// Debug command which changes how long chunks stay loaded and how far clients can see,
+/// without needing to restart the server.
+pub struct ChunkLimits {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	command: String,
+	message: Option<String>,
+}
+
+impl ChunkLimits {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			command: "set-max-view-distance 16".to_owned(),
+			message: None,
+		}
+	}
+
+	fn limits(&self) -> Result<Arc<Limits>, ChunkLimitsError> {
+		let arc_storage = self
+			.storage
+			.upgrade()
+			.ok_or(ChunkLimitsError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		let arc_server = storage
+			.server()
+			.as_ref()
+			.ok_or(ChunkLimitsError::InvalidStorage)?;
+		// Only the default world's limits can be changed, which is the world every player starts in.
+		let server = arc_server.read().unwrap();
+		let entry = server
+			.registry()
+			.get(DEFAULT_WORLD)
+			.ok_or(ChunkLimitsError::InvalidStorage)?;
+		Ok(entry.chunk_limits().clone())
+	}
+
+	fn apply(&self, command: &str) -> Result<LimitChange, ChunkLimitsError> {
+		let change = command.parse::<LimitChange>()?;
+		change.apply(&*self.limits()?);
+		Ok(change)
+	}
+}
+
+impl Command for ChunkLimits {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut self.command);
+			if ui.button("Apply").clicked() {
+				self.message = Some(match self.apply(&self.command) {
+					Ok(change) => {
+						log::info!(target: LOG, "{}", change);
+						format!("{}", change)
+					}
+					Err(err) => {
+						log::warn!(target: LOG, "Failed to change chunk limits: {}", err);
+						format!("{}", err)
+					}
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["set-chunk-expiry", "set-max-view-distance"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let change = self.apply(line)?;
+		log::info!(target: LOG, "{}", change);
+		Ok(format!("{}", change))
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChunkLimitsError {
+	#[error("\"{0}\" is not a valid command, expected set-chunk-expiry <secs> or set-max-view-distance <chunks>")]
+	UnknownCommand(String),
+	#[error("\"{0}\" is not a valid number")]
+	InvalidNumber(String),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+}
+
+#[cfg(test)]
+mod chunk_limits {
+	use super::*;
+
+	#[test]
+	fn parse_commands() {
+		assert_eq!(
+			"set-chunk-expiry 5".parse::<LimitChange>().unwrap(),
+			LimitChange::ChunkExpiry(Duration::from_secs(5))
+		);
+		assert_eq!(
+			"set-max-view-distance 4".parse::<LimitChange>().unwrap(),
+			LimitChange::MaxViewDistance(4)
+		);
+		assert!("set-chunk-expiry".parse::<LimitChange>().is_err());
+		assert!("set-chunk-expiry -1".parse::<LimitChange>().is_err());
+		assert!("set-view-distance 4".parse::<LimitChange>().is_err());
+	}
+
+	#[test]
+	fn apply_changes_shared_limits() {
+		let limits = Arc::new(Limits::default());
+		let observed = limits.clone();
+		"set-chunk-expiry 5"
+			.parse::<LimitChange>()
+			.unwrap()
+			.apply(&limits);
+		"set-max-view-distance 2"
+			.parse::<LimitChange>()
+			.unwrap()
+			.apply(&limits);
+		assert_eq!(observed.expiration_delay(), Duration::from_secs(5));
+		assert_eq!(observed.max_view_distance(), 2);
+	}
+}
@@ -2,9 +2,22 @@ use std::sync::{Arc, Mutex};
 
 pub type CommandList = Arc<Mutex<Vec<ArctexCommand>>>;
 pub type ArctexCommand = Arc<Mutex<dyn Command + 'static>>;
-pub trait Command {
+pub trait Command: Send + Sync {
 	fn is_allowed(&self) -> bool;
 	fn render(&mut self, ui: &mut egui::Ui);
+
+	/// The names the command can be run by from a line of text (e.g. `tp`) through an [`Intake`](super::Intake).
+	/// Commands without any names can only be run from the debug panel.
+	fn names(&self) -> &[&'static str] {
+		&[]
+	}
+
+	/// Runs the command from a line of text which starts with one of its [`names`](Command::names),
+	/// returning a message describing what happened.
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		Err(super::intake::Error::NotExecutable(line.to_owned()))?
+	}
+
 	fn as_arctex(self) -> ArctexCommand
 	where
 		Self: Sized + 'static,
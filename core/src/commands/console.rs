@@ -0,0 +1,200 @@
+//! The interactive console of dedicated servers, which reads commands from stdin
+//! with a history of previous lines and tab-completion of command names.
+//!
+//! Lines are read on their own thread (reading blocks until a line is entered),
+//! and are sent to the [`Intake`](super::Intake) to be run on the next update of the engine.
+
+use super::CommandList;
+use rustyline::{
+	completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+	validate::Validator, Context, Editor,
+};
+use std::sync::mpsc::Sender;
+
+static LOG: &'static str = "console";
+
+/// The prompt shown while waiting for a command to be entered.
+pub static PROMPT: &'static str = "> ";
+
+/// Starts reading lines from stdin on a separate thread, sending each line to be run as a command.
+pub fn spawn(commands: CommandList, sender: Sender<String>) -> std::io::Result<()> {
+	std::thread::Builder::new()
+		.name(LOG.to_owned())
+		.spawn(move || {
+			let mut editor = match Editor::<Helper>::new() {
+				Ok(editor) => editor,
+				Err(err) => {
+					log::error!(target: LOG, "Failed to open console: {:?}", err);
+					return;
+				}
+			};
+			editor.set_helper(Some(Helper { commands }));
+			read_lines(
+				|| {
+					let line = editor.readline(PROMPT);
+					if let Ok(line) = &line {
+						editor.add_history_entry(line.as_str());
+					}
+					line
+				},
+				&sender,
+			);
+		})?;
+	Ok(())
+}
+
+/// Sends each non-empty line to be run as a command, until there are no more lines or the intake is dropped.
+///
+/// Stdin reaching its end (e.g. input piped into the server has been consumed) only stops the console,
+/// the server keeps running until it is stopped some other way.
+/// Interrupting the console (ctrl+c) runs the [`stop`](super::Stop) command.
+pub fn read_lines<F>(mut read_line: F, sender: &Sender<String>)
+where
+	F: FnMut() -> Result<String, ReadlineError>,
+{
+	loop {
+		let line = match read_line() {
+			Ok(line) => line,
+			Err(ReadlineError::Eof) => {
+				log::info!(target: LOG, "Stdin was closed, commands can no longer be entered");
+				break;
+			}
+			Err(ReadlineError::Interrupted) => {
+				// The console captures ctrl+c while waiting for a line, so it stops the server
+				// through the stop command, which saves the world before exiting.
+				log::info!(target: LOG, "Interrupted, shutting down");
+				super::stop::NAME.to_owned()
+			}
+			Err(err) => {
+				log::error!(target: LOG, "Failed to read stdin: {:?}", err);
+				break;
+			}
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+		if sender.send(line).is_err() {
+			break;
+		}
+	}
+}
+
+/// Returns the names of commands which start with the first word of the line,
+/// if the cursor (at `pos`) is still within that word.
+///
+/// Returns the position in the line the completion starts at, and the names which complete it.
+pub fn complete(names: &[&'static str], line: &str, pos: usize) -> (usize, Vec<String>) {
+	let line = &line[..pos];
+	let start = line.len() - line.trim_start().len();
+	let word = &line[start..];
+	// Only command names are completed, not their arguments.
+	if word.contains(char::is_whitespace) {
+		return (pos, Vec::new());
+	}
+	let candidates = names
+		.iter()
+		.filter(|name| name.starts_with(word))
+		.map(|name| name.to_string())
+		.collect();
+	(start, candidates)
+}
+
+/// Completes command names for the line editor.
+struct Helper {
+	commands: CommandList,
+}
+
+impl Completer for Helper {
+	type Candidate = String;
+
+	fn complete(
+		&self,
+		line: &str,
+		pos: usize,
+		_ctx: &Context<'_>,
+	) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+		Ok(complete(&super::intake::names(&self.commands), line, pos))
+	}
+}
+
+impl Hinter for Helper {
+	type Hint = String;
+}
+
+impl Highlighter for Helper {}
+
+impl Validator for Helper {}
+
+impl rustyline::Helper for Helper {}
+
+#[cfg(test)]
+mod console {
+	use super::*;
+	use crate::commands::{Command, Intake};
+	use engine::EngineSystem;
+	use std::sync::{Arc, Mutex};
+
+	struct Say(Arc<Mutex<Vec<String>>>);
+
+	impl Command for Say {
+		fn is_allowed(&self) -> bool {
+			true
+		}
+
+		fn render(&mut self, _ui: &mut egui::Ui) {}
+
+		fn names(&self) -> &[&'static str] {
+			&["say"]
+		}
+
+		fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+			self.0.lock().unwrap().push(line.to_owned());
+			Ok(String::new())
+		}
+	}
+
+	#[test]
+	fn lines_run_until_eof() {
+		let said = Arc::new(Mutex::new(Vec::new()));
+		let commands: CommandList = Arc::new(Mutex::new(vec![Say(said.clone()).as_arctex()]));
+		let mut intake = Intake::new(commands);
+
+		let mut lines = vec![Ok("say hello".to_owned()), Ok("   ".to_owned())].into_iter();
+		read_lines(
+			|| lines.next().unwrap_or(Err(ReadlineError::Eof)),
+			&intake.sender(),
+		);
+		intake.update(std::time::Duration::ZERO, false);
+		assert_eq!(*said.lock().unwrap(), vec!["say hello".to_owned()]);
+
+		// The intake still runs commands from other sources after stdin closes.
+		intake.sender().send("say again".to_owned()).unwrap();
+		intake.update(std::time::Duration::ZERO, false);
+		assert_eq!(said.lock().unwrap().len(), 2);
+	}
+
+	#[test]
+	fn interrupt_stops_through_command() {
+		let (sender, receiver) = std::sync::mpsc::channel();
+		let mut lines = vec![Err(ReadlineError::Interrupted)].into_iter();
+		read_lines(|| lines.next().unwrap_or(Err(ReadlineError::Eof)), &sender);
+		// The process is not exited by the console, so the stop command can save the world first.
+		assert_eq!(
+			receiver.try_iter().collect::<Vec<_>>(),
+			vec![super::super::stop::NAME.to_owned()]
+		);
+	}
+
+	#[test]
+	fn completes_command_names() {
+		let names = ["save", "set", "setblock", "tp"];
+		assert_eq!(
+			complete(&names, "se", 2),
+			(0, vec!["set".to_owned(), "setblock".to_owned()])
+		);
+		assert_eq!(complete(&names, "  t", 3), (2, vec!["tp".to_owned()]));
+		assert_eq!(complete(&names, "x", 1), (0, Vec::new()));
+		// Arguments are not completed.
+		assert_eq!(complete(&names, "tp se", 5), (5, Vec::new()));
+	}
+}
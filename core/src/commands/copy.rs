@@ -0,0 +1,180 @@
+use super::{parse_block_point, Command, DEFAULT_SCHEMATIC};
+use crate::{
+	app,
+	common::network::{mode, Storage},
+	common::world::schematic,
+	server::{network::DEFAULT_WORLD, world::edit},
+};
+use engine::math::nalgebra::Point3;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:copy";
+
+/// Parses `copy <x> <y> <z> <x> <y> <z> [schematic]` into the corners of the region and the name of the schematic.
+pub fn parse_copy(line: &str) -> Result<(Point3<i64>, Point3<i64>, &str), CopyError> {
+	let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+	let name = match args.len() {
+		6 => DEFAULT_SCHEMATIC,
+		7 => args[6],
+		_ => return Err(CopyError::InvalidArguments(line.to_owned())),
+	};
+	let min = parse_block_point(&args[0..3])?;
+	let max = parse_block_point(&args[3..6])?;
+	Ok((min, max, name))
+}
+
+/// World editing command which saves the blocks in a region of the default world
+/// as a schematic in the savegame's `schematics` directory, so it can be [`pasted`](super::Paste) elsewhere.
+pub struct CopyRegion {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	schematic: String,
+	min: String,
+	max: String,
+	status: edit::Status,
+}
+
+impl CopyRegion {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			schematic: DEFAULT_SCHEMATIC.to_owned(),
+			min: "0 0 0".to_owned(),
+			max: "0 0 0".to_owned(),
+			status: edit::Status::default(),
+		}
+	}
+
+	/// Copies the region on its own thread, returning the message to show until it has been copied.
+	fn start(&self, min: Point3<i64>, max: Point3<i64>, name: &str) -> anyhow::Result<String> {
+		let storage = self.storage.clone();
+		let thread_name = name.to_owned();
+		edit::spawn(LOG, &self.status, move || {
+			Self::report(Self::copy(&storage, min, max, &thread_name))
+		})?;
+		Ok(format!(
+			"Copying <{}, {}, {}> to <{}, {}, {}> into \"{}\"",
+			min.x, min.y, min.z, max.x, max.y, max.z, name
+		))
+	}
+
+	fn copy(
+		storage: &Weak<RwLock<Storage>>,
+		min: Point3<i64>,
+		max: Point3<i64>,
+		name: &str,
+	) -> anyhow::Result<String> {
+		let (path, arc_database) = {
+			let arc_storage = storage.upgrade().ok_or(CopyError::InvalidStorage)?;
+			let storage = arc_storage.read().unwrap();
+			let arc_server = storage.server().as_ref().ok_or(CopyError::InvalidStorage)?;
+			let server = arc_server.read().unwrap();
+			let arc_database = server.world(DEFAULT_WORLD).ok_or(CopyError::InvalidWorld)?;
+			(server.get_schematic_path(name)?, arc_database.clone())
+		};
+		// Regions too large to be loaded again are refused before any chunks are loaded.
+		schematic::volume(&(max - min).map(|v| v.unsigned_abs() as usize + 1))?;
+		// The tickets which load the region are dropped once it has been copied.
+		let chunks = edit::Chunks::load_region(&arc_database, min, max, edit::LOAD_TIMEOUT)?;
+		let schematic = chunks.copy(min, max);
+		drop(chunks);
+		schematic.save(&path)?;
+
+		let size = schematic.size();
+		Ok(format!(
+			"Copied {}x{}x{} blocks to \"{}\"",
+			size.x, size.y, size.z, name
+		))
+	}
+
+	fn report(result: anyhow::Result<String>) -> anyhow::Result<String> {
+		match &result {
+			Ok(message) => log::info!(target: LOG, "{}", message),
+			Err(err) => log::warn!(target: LOG, "Failed to copy: {}", err),
+		}
+		result
+	}
+}
+
+impl Command for CopyRegion {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Schematic");
+			ui.text_edit_singleline(&mut self.schematic);
+		});
+		ui.horizontal(|ui| {
+			ui.label("From");
+			ui.text_edit_singleline(&mut self.min);
+			ui.label("To");
+			ui.text_edit_singleline(&mut self.max);
+			if ui.button("Copy").clicked() {
+				let min = self.min.split_whitespace().collect::<Vec<_>>();
+				let max = self.max.split_whitespace().collect::<Vec<_>>();
+				let result = parse_block_point(&min)
+					.and_then(|min| Ok((min, parse_block_point(&max)?)))
+					.map_err(anyhow::Error::from)
+					.and_then(|(min, max)| self.start(min, max, &self.schematic));
+				if let Err(err) = result {
+					log::warn!(target: LOG, "Failed to copy: {}", err);
+					*self.status.lock().unwrap() = Some(format!("{}", err));
+				}
+			}
+		});
+		if let Some(message) = &*self.status.lock().unwrap() {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["copy"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let (min, max, name) = parse_copy(line)?;
+		self.start(min, max, name)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CopyError {
+	#[error("\"{0}\" is not a valid command, expected copy <x> <y> <z> <x> <y> <z> [schematic]")]
+	InvalidArguments(String),
+	#[error(transparent)]
+	InvalidCoordinate(#[from] super::PasteError),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("the default world is not loaded")]
+	InvalidWorld,
+}
+
+#[cfg(test)]
+mod copy {
+	use super::*;
+
+	#[test]
+	fn parse_corners_and_name() {
+		assert_eq!(
+			parse_copy("copy 0 1 2 -3 4 5").unwrap(),
+			(
+				Point3::new(0, 1, 2),
+				Point3::new(-3, 4, 5),
+				DEFAULT_SCHEMATIC
+			)
+		);
+		assert_eq!(
+			parse_copy("copy 0 1 2 3 4 5 tower").unwrap(),
+			(Point3::new(0, 1, 2), Point3::new(3, 4, 5), "tower")
+		);
+		assert!(parse_copy("copy 0 1 2 3 4").is_err());
+		assert!(parse_copy("copy 0 1 2 3 four 5").is_err());
+	}
+}
@@ -0,0 +1,229 @@
+use super::{parse_block_point, parse_registered_block, Command};
+use crate::{
+	app, block,
+	common::{
+		network::{mode, Storage},
+		world::schematic,
+	},
+	server::{network::DEFAULT_WORLD, world::edit},
+};
+use engine::math::nalgebra::Point3;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:fill";
+
+/// Parses `fill <x> <y> <z> <x> <y> <z> <block>` into the corners of the region and the name of the block.
+pub fn parse_fill(line: &str) -> Result<(Point3<i64>, Point3<i64>, &str), FillError> {
+	let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+	if args.len() != 7 {
+		return Err(FillError::InvalidArguments(line.to_owned()));
+	}
+	let min = parse_block_point(&args[0..3])?;
+	let max = parse_block_point(&args[3..6])?;
+	Ok((min, max, args[6]))
+}
+
+/// The most blocks a single fill can change, the same as the most blocks a [`schematic`](schematic::MAX_VOLUME) can hold.
+pub static MAX_VOLUME: usize = schematic::MAX_VOLUME;
+
+/// Returns the number of blocks in the region between two (inclusive) corners,
+/// or an error if the region is larger than can be filled at once.
+pub fn fill_volume(min: Point3<i64>, max: Point3<i64>) -> Result<usize, FillError> {
+	let size = (max - min).map(|v| v.unsigned_abs() as usize + 1);
+	size.x
+		.checked_mul(size.y)
+		.and_then(|area| area.checked_mul(size.z))
+		.filter(|volume| *volume <= MAX_VOLUME)
+		.ok_or(FillError::TooLarge(size.x, size.y, size.z))
+}
+
+/// World editing command which sets every block in a region of the default world to the same block.
+///
+/// The region is filled on its own thread, [`a few chunks at a time`](edit::FILL_BATCH_CHUNKS),
+/// so only those chunks are kept loaded and locked while they are changed.
+pub struct Fill {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	min: String,
+	max: String,
+	block: String,
+	status: edit::Status,
+}
+
+impl Fill {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			min: "0 0 0".to_owned(),
+			max: "0 0 0".to_owned(),
+			block: String::new(),
+			status: edit::Status::default(),
+		}
+	}
+
+	/// Fills the region on its own thread, returning the message to show until it has been filled.
+	/// Regions larger than [`MAX_VOLUME`] are refused before any chunks are loaded.
+	fn start(
+		&self,
+		min: Point3<i64>,
+		max: Point3<i64>,
+		id: Option<block::LookupId>,
+	) -> anyhow::Result<String> {
+		fill_volume(min, max)?;
+		let storage = self.storage.clone();
+		edit::spawn(LOG, &self.status, move || {
+			Self::report(Self::fill(&storage, min, max, id))
+		})?;
+		Ok(format!(
+			"Filling <{}, {}, {}> to <{}, {}, {}>",
+			min.x, min.y, min.z, max.x, max.y, max.z
+		))
+	}
+
+	fn fill(
+		storage: &Weak<RwLock<Storage>>,
+		min: Point3<i64>,
+		max: Point3<i64>,
+		id: Option<block::LookupId>,
+	) -> anyhow::Result<String> {
+		let arc_database = {
+			let arc_storage = storage.upgrade().ok_or(FillError::InvalidStorage)?;
+			let storage = arc_storage.read().unwrap();
+			let arc_server = storage.server().as_ref().ok_or(FillError::InvalidStorage)?;
+			let server = arc_server.read().unwrap();
+			let arc_database = server.world(DEFAULT_WORLD).ok_or(FillError::InvalidWorld)?;
+			arc_database.clone()
+		};
+
+		let mut report = edit::Report::default();
+		for regions in edit::chunk_regions(min, max).chunks(edit::FILL_BATCH_CHUNKS) {
+			let edits = regions
+				.iter()
+				.flat_map(|(min, max)| edit::fill(*min, *max, id))
+				.collect::<Vec<_>>();
+			// The tickets for the batch are dropped before the next batch is loaded.
+			let chunks = edit::Chunks::load_for(&arc_database, &edits, edit::LOAD_TIMEOUT)?;
+			let plugins = crate::plugin::Manager::read().unwrap();
+			report += chunks.apply(&plugins, None, &edits);
+		}
+
+		let message = format!(
+			"Filled <{}, {}, {}> to <{}, {}, {}>, changing {} blocks",
+			min.x, min.y, min.z, max.x, max.y, max.z, report.changed
+		);
+		Ok(match report.denied {
+			0 => message,
+			denied => format!("{} ({} were denied)", message, denied),
+		})
+	}
+
+	fn report(result: anyhow::Result<String>) -> anyhow::Result<String> {
+		match &result {
+			Ok(message) => log::info!(target: LOG, "{}", message),
+			Err(err) => log::warn!(target: LOG, "Failed to fill: {}", err),
+		}
+		result
+	}
+}
+
+impl Command for Fill {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Block");
+			ui.text_edit_singleline(&mut self.block);
+		});
+		ui.horizontal(|ui| {
+			ui.label("From");
+			ui.text_edit_singleline(&mut self.min);
+			ui.label("To");
+			ui.text_edit_singleline(&mut self.max);
+			if ui.button("Fill").clicked() {
+				let min = self.min.split_whitespace().collect::<Vec<_>>();
+				let max = self.max.split_whitespace().collect::<Vec<_>>();
+				let result = parse_block_point(&min)
+					.and_then(|min| Ok((min, parse_block_point(&max)?)))
+					.map_err(anyhow::Error::from)
+					.and_then(|(min, max)| Ok((min, max, parse_registered_block(&self.block)?)))
+					.and_then(|(min, max, id)| self.start(min, max, id));
+				if let Err(err) = result {
+					log::warn!(target: LOG, "Failed to fill: {}", err);
+					*self.status.lock().unwrap() = Some(format!("{}", err));
+				}
+			}
+		});
+		if let Some(message) = &*self.status.lock().unwrap() {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["fill"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let (min, max, name) = parse_fill(line)?;
+		let id = parse_registered_block(name)?;
+		self.start(min, max, id)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FillError {
+	#[error("\"{0}\" is not a valid command, expected fill <x> <y> <z> <x> <y> <z> <block>")]
+	InvalidArguments(String),
+	#[error(transparent)]
+	InvalidCoordinate(#[from] super::PasteError),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("the default world is not loaded")]
+	InvalidWorld,
+	#[error(
+		"the region of {0}x{1}x{2} blocks is too large, at most {} blocks can be filled at once",
+		MAX_VOLUME
+	)]
+	TooLarge(usize, usize, usize),
+}
+
+#[cfg(test)]
+mod fill {
+	use super::*;
+
+	#[test]
+	fn parse_corners_and_block() {
+		assert_eq!(
+			parse_fill("fill 0 1 2 -3 4 5 stone").unwrap(),
+			(Point3::new(0, 1, 2), Point3::new(-3, 4, 5), "stone")
+		);
+		assert!(parse_fill("fill 0 1 2 3 4 5").is_err());
+		assert!(parse_fill("fill 0 1 2 3 four 5 stone").is_err());
+	}
+
+	#[test]
+	fn large_regions_are_refused() {
+		let min = Point3::new(-1, 0, 5);
+		assert_eq!(fill_volume(min, Point3::new(1, 0, 3)).unwrap(), 9);
+		let side = 256;
+		assert_eq!(
+			fill_volume(Point3::origin(), Point3::new(side - 1, side - 1, side - 1)).unwrap(),
+			MAX_VOLUME
+		);
+		assert!(matches!(
+			fill_volume(Point3::origin(), Point3::new(side, side - 1, side - 1)),
+			Err(FillError::TooLarge(257, 256, 256))
+		));
+		assert!(fill_volume(
+			Point3::new(i64::MIN / 2, 0, 0),
+			Point3::new(i64::MAX / 2, 0, 0)
+		)
+		.is_err());
+	}
+}
@@ -0,0 +1,172 @@
+use super::CommandList;
+use engine::EngineSystem;
+use std::sync::{
+	mpsc::{self, Receiver, Sender},
+	Arc, Mutex, RwLock,
+};
+
+static LOG: &'static str = "command-intake";
+
+/// Runs commands from lines of text, independent of the debug panel.
+///
+/// Lines are submitted through [`senders`](Intake::sender) by any source
+/// (stdin on a dedicated server, a console on a client)
+/// and run on the next update, so commands always run on the same thread as the rest of the engine systems.
+pub struct Intake {
+	commands: CommandList,
+	// Both ends are behind a mutex so the intake is Sync, as is required of engine systems.
+	sender: Mutex<Sender<String>>,
+	receiver: Mutex<Receiver<String>>,
+	/// If the results of commands are printed to stdout (for a console) instead of being logged.
+	prints_results: bool,
+}
+
+impl Intake {
+	pub fn new(commands: CommandList) -> Self {
+		let (sender, receiver) = mpsc::channel();
+		Self {
+			commands,
+			sender: Mutex::new(sender),
+			receiver: Mutex::new(receiver),
+			prints_results: false,
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+
+	/// Returns a sender which submits lines of text to be run as commands.
+	pub fn sender(&self) -> Sender<String> {
+		self.sender.lock().unwrap().clone()
+	}
+
+	/// Reads lines from stdin through a [`console`](super::console) with history and tab-completion,
+	/// submitting each as a command and printing its result, until stdin is closed or the intake is dropped.
+	pub fn with_stdin(mut self) -> Self {
+		if let Err(err) = super::console::spawn(self.commands.clone(), self.sender()) {
+			log::error!(target: LOG, "Failed to start reading commands from stdin: {:?}", err);
+		}
+		self.prints_results = true;
+		self
+	}
+
+	/// Runs a line of text as a command, returning the command's message if it was run.
+	pub fn submit(&self, line: &str) -> anyhow::Result<String> {
+		let line = line.trim();
+		let name = line.split_whitespace().next().ok_or(Error::Empty)?;
+		let command_list = self.commands.lock().unwrap();
+		let arc_cmd = command_list
+			.iter()
+			.find(|arc_cmd| arc_cmd.lock().unwrap().names().contains(&name))
+			.ok_or_else(|| Error::UnknownCommand(name.to_owned()))?;
+		let mut command = arc_cmd.lock().unwrap();
+		if !command.is_allowed() {
+			return Err(Error::NotAllowed(name.to_owned()))?;
+		}
+		command.execute(line)
+	}
+
+	/// Returns the names of every command which can be run from text.
+	pub fn names(&self) -> Vec<&'static str> {
+		names(&self.commands)
+	}
+}
+
+/// Returns the names of every command in the list which can be run from text, in alphabetical order.
+pub(super) fn names(commands: &CommandList) -> Vec<&'static str> {
+	let command_list = commands.lock().unwrap();
+	let mut names = command_list
+		.iter()
+		.flat_map(|arc_cmd| arc_cmd.lock().unwrap().names().to_vec())
+		.collect::<Vec<_>>();
+	names.sort();
+	names
+}
+
+impl EngineSystem for Intake {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!("subsystem:command-intake");
+		let lines = self.receiver.lock().unwrap().try_iter().collect::<Vec<_>>();
+		for line in lines.into_iter() {
+			match (self.submit(&line), self.prints_results) {
+				(Ok(message), true) => println!("{}", message),
+				(Ok(message), false) => log::info!(target: LOG, "{}", message),
+				(Err(err), true) => println!("\"{}\" failed: {}", line, err),
+				(Err(err), false) => log::warn!(target: LOG, "\"{}\" failed: {}", line, err),
+			}
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("no command was entered")]
+	Empty,
+	#[error("there is no command named \"{0}\"")]
+	UnknownCommand(String),
+	#[error("\"{0}\" cannot be run right now")]
+	NotAllowed(String),
+	#[error("\"{0}\" can only be run from the debug panel")]
+	NotExecutable(String),
+}
+
+#[cfg(test)]
+mod intake {
+	use super::*;
+	use crate::commands::Command;
+
+	struct Echo {
+		allowed: bool,
+		executed: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl Command for Echo {
+		fn is_allowed(&self) -> bool {
+			self.allowed
+		}
+
+		fn render(&mut self, _ui: &mut egui::Ui) {}
+
+		fn names(&self) -> &[&'static str] {
+			&["echo"]
+		}
+
+		fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+			self.executed.lock().unwrap().push(line.to_owned());
+			Ok(line.trim_start_matches("echo").trim().to_owned())
+		}
+	}
+
+	fn intake(allowed: bool) -> (Intake, Arc<Mutex<Vec<String>>>) {
+		let executed = Arc::new(Mutex::new(Vec::new()));
+		let echo = Echo {
+			allowed,
+			executed: executed.clone(),
+		};
+		let commands: CommandList = Arc::new(Mutex::new(vec![echo.as_arctex()]));
+		(Intake::new(commands), executed)
+	}
+
+	#[test]
+	fn submitted_line_runs_handler() {
+		let (mut intake, executed) = intake(true);
+		assert_eq!(intake.submit("  echo hello world ").unwrap(), "hello world");
+		intake.sender().send("echo again".to_owned()).unwrap();
+		intake.update(std::time::Duration::ZERO, false);
+		assert_eq!(
+			*executed.lock().unwrap(),
+			vec!["echo hello world".to_owned(), "echo again".to_owned()]
+		);
+		assert_eq!(intake.names(), vec!["echo"]);
+	}
+
+	#[test]
+	fn rejects_unknown_and_disallowed() {
+		let (intake, executed) = intake(false);
+		assert!(intake.submit("").is_err());
+		assert!(intake.submit("tp 0 0 0").is_err());
+		assert!(intake.submit("echo hello").is_err());
+		assert!(executed.lock().unwrap().is_empty());
+	}
+}
@@ -0,0 +1,116 @@
+use super::Command;
+use crate::common::log_filter;
+
+/// What `log <target> <level>` or `log <target> reset` asks for.
+#[derive(Debug, PartialEq)]
+pub enum LogRequest<'a> {
+	Set(&'a str, &'a str),
+	Reset(&'a str),
+}
+
+pub fn parse_request(line: &str) -> Result<LogRequest, LogFilterError> {
+	let args = line.split_whitespace().collect::<Vec<_>>();
+	match args[..] {
+		[_, target, "reset"] => Ok(LogRequest::Reset(target)),
+		[_, target, level] => Ok(LogRequest::Set(target, level)),
+		_ => Err(LogFilterError::InvalidArguments(line.to_owned())),
+	}
+}
+
+/// Changes the level a log target (e.g. `subsystem:replicator`) is logged at while the application is running.
+pub struct LogFilter {
+	target: String,
+	level: String,
+	message: Option<String>,
+}
+
+impl LogFilter {
+	pub fn new() -> Self {
+		Self {
+			target: String::new(),
+			level: "debug".to_owned(),
+			message: None,
+		}
+	}
+
+	fn apply(&self, request: LogRequest) -> Result<String, LogFilterError> {
+		Ok(match request {
+			LogRequest::Set(target, level) => {
+				let level = log_filter::set(target, level)?;
+				format!("Logging {} at {}", target, level)
+			}
+			LogRequest::Reset(target) => match log_filter::reset(target) {
+				true => format!("Logging {} without a filter", target),
+				false => format!("{} was not filtered", target),
+			},
+		})
+	}
+}
+
+impl Command for LogFilter {
+	fn is_allowed(&self) -> bool {
+		true
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Target");
+			ui.text_edit_singleline(&mut self.target);
+			ui.label("Level");
+			ui.text_edit_singleline(&mut self.level);
+			if ui.button("Set").clicked() {
+				let result = self.apply(LogRequest::Set(&self.target, &self.level));
+				self.message = Some(match result {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+			if ui.button("Reset").clicked() {
+				let result = self.apply(LogRequest::Reset(&self.target));
+				self.message = Some(match result {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["log"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let request = parse_request(line)?;
+		Ok(self.apply(request)?)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LogFilterError {
+	#[error("\"{0}\" is not a valid command, expected log <target> <level|reset>")]
+	InvalidArguments(String),
+	#[error(transparent)]
+	InvalidFilter(#[from] log_filter::Error),
+}
+
+#[cfg(test)]
+mod log_filter_command {
+	use super::*;
+
+	#[test]
+	fn parse_target_and_level() {
+		assert_eq!(
+			parse_request("log subsystem:replicator warn").unwrap(),
+			LogRequest::Set("subsystem:replicator", "warn")
+		);
+		assert_eq!(
+			parse_request("log chunk-loading reset").unwrap(),
+			LogRequest::Reset("chunk-loading")
+		);
+		assert!(parse_request("log").is_err());
+		assert!(parse_request("log chunk-loading").is_err());
+	}
+}
@@ -1,6 +1,5 @@
 use super::Command;
-use crate::common::network::mode;
-use crate::common::utility::get_named_arg;
+use crate::common::network::{mode, NetworkConfig, DEFAULT_PORT};
 use crate::{app, common::network::task::Instruction};
 use std::sync::{Arc, RwLock};
 
@@ -13,7 +12,7 @@ impl Connect {
 	pub fn new(app_state: Arc<RwLock<app::state::Machine>>) -> Self {
 		Self {
 			app_state,
-			url: "127.0.0.1:25565".to_string(),
+			url: format!("127.0.0.1:{}", DEFAULT_PORT),
 		}
 	}
 }
@@ -28,16 +27,25 @@ impl Command for Connect {
 		ui.horizontal(|ui| {
 			ui.text_edit_singleline(&mut self.url);
 			if ui.button("Connect").clicked() {
-				self.app_state.write().unwrap().transition_to(
-					app::state::State::Connecting,
-					Some(Box::new(Instruction {
-						mode: mode::Kind::Client.into(),
-						port: get_named_arg("client_port"),
-						world_name: None,
-						server_url: Some(self.url.clone()),
-					})),
-				);
+				self.connect(mode::Kind::Client.into());
+			}
+			if ui.button("Spectate").clicked() {
+				self.connect(mode::Kind::Client + mode::Kind::Spectator);
 			}
 		});
 	}
 }
+
+impl Connect {
+	fn connect(&self, mode: mode::Set) {
+		self.app_state.write().unwrap().transition_to(
+			app::state::State::Connecting,
+			Some(Box::new(Instruction {
+				mode,
+				network: NetworkConfig::from_args("client_port"),
+				world_name: None,
+				server_url: Some(self.url.clone()),
+			})),
+		);
+	}
+}
@@ -1,6 +1,6 @@
 use super::Command;
-use crate::common::network::mode;
-use crate::{app, common::utility::get_named_arg};
+use crate::app;
+use crate::common::network::{mode, NetworkConfig};
 use std::sync::{Arc, RwLock};
 
 #[derive(PartialEq, Clone)]
@@ -12,19 +12,19 @@ pub enum WorldOption {
 impl WorldOption {
 	fn to_transition_data(&self) -> app::state::TransitionData {
 		use crate::common::network::task::Instruction;
-		let mode = mode::Set::all();
-		let port = get_named_arg("host_port");
+		let mode = mode::Kind::ListenServer.as_set();
+		let network = NetworkConfig::from_args("host_port");
 		Some(Box::new(match self {
 			Self::New => Instruction {
 				mode,
-				port,
+				network,
 				// TODO: Create a unique identifier based on a user-provided world name
 				world_name: Some("tmp".to_owned()),
 				server_url: None,
 			},
 			Self::Path(path) => Instruction {
 				mode,
-				port,
+				network,
 				world_name: Some(path.clone()),
 				server_url: None,
 			},
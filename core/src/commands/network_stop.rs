@@ -13,7 +13,7 @@ impl UnloadNetwork {
 	}
 
 	fn is_dedicated_client(&self) -> bool {
-		mode::get() == mode::Kind::Client
+		mode::is_dedicated_client(mode::get())
 	}
 }
 
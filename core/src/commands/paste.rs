@@ -0,0 +1,198 @@
+use super::Command;
+use crate::{
+	app,
+	common::{
+		network::{mode, Storage},
+		world::schematic::Schematic,
+	},
+	server::{network::DEFAULT_WORLD, world::edit},
+};
+use engine::math::nalgebra::Point3;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:paste";
+
+/// The schematic which is pasted (and copied to) if a command does not name one.
+pub static DEFAULT_SCHEMATIC: &'static str = "clipboard";
+
+/// Parses a world-space block coordinate from the form `x y z`.
+pub fn parse_block_point(args: &[&str]) -> Result<Point3<i64>, PasteError> {
+	let axes = args
+		.iter()
+		.map(|axis| {
+			axis.parse::<i64>()
+				.map_err(|_| PasteError::InvalidCoordinate((*axis).to_owned()))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	match axes[..] {
+		[x, y, z] => Ok(Point3::new(x, y, z)),
+		_ => Err(PasteError::InvalidCoordinateCount(axes.len())),
+	}
+}
+
+/// Parses `paste <x> <y> <z> [schematic]` into the minimum corner to paste at and the name of the schematic.
+pub fn parse_paste(line: &str) -> Result<(Point3<i64>, &str), PasteError> {
+	let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+	match args.len() {
+		3 => Ok((parse_block_point(&args)?, DEFAULT_SCHEMATIC)),
+		4 => Ok((parse_block_point(&args[..3])?, args[3])),
+		_ => Err(PasteError::InvalidArguments(line.to_owned())),
+	}
+}
+
+/// World editing command which places every block of a schematic (saved in the savegame's `schematics` directory)
+/// into the default world, with the minimum corner of the schematic at the provided block coordinate.
+pub struct Paste {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	schematic: String,
+	origin: String,
+	status: edit::Status,
+}
+
+impl Paste {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			schematic: DEFAULT_SCHEMATIC.to_owned(),
+			origin: "0 0 0".to_owned(),
+			status: edit::Status::default(),
+		}
+	}
+
+	/// Pastes the schematic on its own thread, returning the message to show until it has been pasted.
+	fn start(&self, origin: Point3<i64>, name: &str) -> anyhow::Result<String> {
+		let storage = self.storage.clone();
+		let thread_name = name.to_owned();
+		edit::spawn(LOG, &self.status, move || {
+			Self::report(Self::paste(&storage, origin, &thread_name))
+		})?;
+		Ok(format!(
+			"Pasting \"{}\" at <{}, {}, {}>",
+			name, origin.x, origin.y, origin.z
+		))
+	}
+
+	fn paste(
+		storage: &Weak<RwLock<Storage>>,
+		origin: Point3<i64>,
+		name: &str,
+	) -> anyhow::Result<String> {
+		let (path, arc_database) = {
+			let arc_storage = storage.upgrade().ok_or(PasteError::InvalidStorage)?;
+			let storage = arc_storage.read().unwrap();
+			let arc_server = storage
+				.server()
+				.as_ref()
+				.ok_or(PasteError::InvalidStorage)?;
+			let server = arc_server.read().unwrap();
+			let arc_database = server
+				.world(DEFAULT_WORLD)
+				.ok_or(PasteError::InvalidWorld)?;
+			(server.get_schematic_path(name)?, arc_database.clone())
+		};
+		let schematic = Schematic::load(&path)
+			.map_err(|err| PasteError::InvalidSchematic(name.to_owned(), err))?;
+		let edits = edit::paste(&schematic, origin);
+
+		// Every chunk is loaded before any block is placed, so the schematic is never partially pasted.
+		let chunks = edit::Chunks::load_for(&arc_database, &edits, edit::LOAD_TIMEOUT)?;
+		let plugins = crate::plugin::Manager::read().unwrap();
+		let report = chunks.apply(&plugins, None, &edits);
+
+		let message = format!(
+			"Pasted \"{}\" at <{}, {}, {}>, changing {} blocks",
+			name, origin.x, origin.y, origin.z, report.changed
+		);
+		Ok(match report.denied {
+			0 => message,
+			denied => format!("{} ({} were denied)", message, denied),
+		})
+	}
+
+	fn report(result: anyhow::Result<String>) -> anyhow::Result<String> {
+		match &result {
+			Ok(message) => log::info!(target: LOG, "{}", message),
+			Err(err) => log::warn!(target: LOG, "Failed to paste: {}", err),
+		}
+		result
+	}
+}
+
+impl Command for Paste {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Schematic");
+			ui.text_edit_singleline(&mut self.schematic);
+		});
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut self.origin);
+			if ui.button("Paste").clicked() {
+				let args = self.origin.split_whitespace().collect::<Vec<_>>();
+				let result = parse_block_point(&args)
+					.map_err(anyhow::Error::from)
+					.and_then(|origin| self.start(origin, &self.schematic));
+				if let Err(err) = result {
+					log::warn!(target: LOG, "Failed to paste: {}", err);
+					*self.status.lock().unwrap() = Some(format!("{}", err));
+				}
+			}
+		});
+		if let Some(message) = &*self.status.lock().unwrap() {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["paste"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let (origin, name) = parse_paste(line)?;
+		self.start(origin, name)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasteError {
+	#[error("\"{0}\" is not a valid command, expected paste <x> <y> <z> [schematic]")]
+	InvalidArguments(String),
+	#[error("\"{0}\" is not a valid block coordinate")]
+	InvalidCoordinate(String),
+	#[error("expected 3 coordinates (x y z) but found {0}")]
+	InvalidCoordinateCount(usize),
+	#[error("failed to load schematic \"{0}\": {1}")]
+	InvalidSchematic(String, anyhow::Error),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("the default world is not loaded")]
+	InvalidWorld,
+}
+
+#[cfg(test)]
+mod paste {
+	use super::*;
+
+	#[test]
+	fn parse_origin_and_name() {
+		assert_eq!(
+			parse_paste("paste 1 -2 3").unwrap(),
+			(Point3::new(1, -2, 3), DEFAULT_SCHEMATIC)
+		);
+		assert_eq!(
+			parse_paste("paste 16 64 -16 tower").unwrap(),
+			(Point3::new(16, 64, -16), "tower")
+		);
+		assert!(parse_paste("paste 1 2").is_err());
+		assert!(parse_paste("paste 1.5 2 3").is_err());
+	}
+}
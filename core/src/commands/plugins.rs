@@ -0,0 +1,188 @@
+use super::Command;
+use crate::plugin;
+
+/// What `plugin <name> enable|disable` or `plugin <name> set <key> <value>` asks for.
+#[derive(Debug, PartialEq)]
+pub enum PluginRequest<'a> {
+	SetEnabled(&'a str, bool),
+	SetParameter(&'a str, &'a str, &'a str),
+}
+
+pub fn parse_plugin_request(line: &str) -> Result<PluginRequest, PluginsError> {
+	let args = line.split_whitespace().collect::<Vec<_>>();
+	match args[..] {
+		[_, name, "enable"] => Ok(PluginRequest::SetEnabled(name, true)),
+		[_, name, "disable"] => Ok(PluginRequest::SetEnabled(name, false)),
+		[_, name, "set", key, value] => Ok(PluginRequest::SetParameter(name, key, value)),
+		_ => Err(PluginsError::InvalidArguments(line.to_owned())),
+	}
+}
+
+/// Changes the config of the compiled plugins while the game is running,
+/// [`reloading`](plugin::reload_config) the plugins with the changed config.
+pub struct Plugins {
+	name: String,
+	key: String,
+	value: String,
+	message: Option<String>,
+}
+
+impl Plugins {
+	pub fn new() -> Self {
+		Self {
+			name: String::new(),
+			key: String::new(),
+			value: String::new(),
+			message: None,
+		}
+	}
+
+	fn apply(&self, request: PluginRequest) -> Result<String, PluginsError> {
+		let config = plugin::Manager::read().unwrap().config().clone();
+		let (config, message) = apply_request(config, request)?;
+		plugin::reload_config(&config);
+		Ok(message)
+	}
+}
+
+fn apply_request(
+	config: plugin::Config,
+	request: PluginRequest,
+) -> Result<(plugin::Config, String), PluginsError> {
+	let name = match &request {
+		PluginRequest::SetEnabled(name, _) => *name,
+		PluginRequest::SetParameter(name, _, _) => *name,
+	};
+	if !config.has_plugin(name) {
+		return Err(PluginsError::UnknownPlugin(
+			name.to_owned(),
+			config.plugin_names().join(", "),
+		));
+	}
+	Ok(match request {
+		PluginRequest::SetEnabled(name, enabled) => {
+			let message = match enabled {
+				true => format!("Enabled plugin {}", name),
+				false => format!("Disabled plugin {}", name),
+			};
+			(config.with_enabled(name, enabled), message)
+		}
+		PluginRequest::SetParameter(name, key, value) => {
+			let message = format!("Configured plugin {} with {}={}", name, key, value);
+			(config.with_parameter(name, key, value), message)
+		}
+	})
+}
+
+impl Command for Plugins {
+	fn is_allowed(&self) -> bool {
+		true
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Plugin");
+			ui.text_edit_singleline(&mut self.name);
+			if ui.button("Enable").clicked() {
+				let result = self.apply(PluginRequest::SetEnabled(&self.name, true));
+				self.message = Some(match result {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+			if ui.button("Disable").clicked() {
+				let result = self.apply(PluginRequest::SetEnabled(&self.name, false));
+				self.message = Some(match result {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		ui.horizontal(|ui| {
+			ui.label("Parameter");
+			ui.text_edit_singleline(&mut self.key);
+			ui.label("Value");
+			ui.text_edit_singleline(&mut self.value);
+			if ui.button("Set").clicked() {
+				let request = PluginRequest::SetParameter(&self.name, &self.key, &self.value);
+				self.message = Some(match self.apply(request) {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["plugin"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let request = parse_plugin_request(line)?;
+		Ok(self.apply(request)?)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginsError {
+	#[error("\"{0}\" is not a valid command, expected plugin <name> <enable|disable|set>")]
+	InvalidArguments(String),
+	#[error("there is no plugin named {0}, the plugins are: {1}")]
+	UnknownPlugin(String, String),
+}
+
+#[cfg(test)]
+mod plugin_command {
+	use super::*;
+	use crate::app::state::State;
+
+	struct Named(&'static str);
+
+	impl plugin::Plugin for Named {
+		fn name(&self) -> &'static str {
+			self.0
+		}
+
+		fn version(&self) -> semver::Version {
+			semver::Version::new(0, 1, 0)
+		}
+
+		fn register_state_background(&self, _state: State, _list: &mut Vec<engine::asset::Id>) {}
+	}
+
+	#[test]
+	fn parse_name_and_change() {
+		assert_eq!(
+			parse_plugin_request("plugin vanilla disable").unwrap(),
+			PluginRequest::SetEnabled("vanilla", false)
+		);
+		assert_eq!(
+			parse_plugin_request("plugin vanilla set volume 0.5").unwrap(),
+			PluginRequest::SetParameter("vanilla", "volume", "0.5")
+		);
+		assert!(parse_plugin_request("plugin vanilla").is_err());
+		assert!(parse_plugin_request("plugin vanilla set volume").is_err());
+	}
+
+	#[test]
+	fn changes_are_applied_to_the_config() -> anyhow::Result<()> {
+		let config = plugin::Config::default().with(Named("vanilla"));
+		let request = PluginRequest::SetEnabled("vanilla", false);
+		let (config, _) = apply_request(config, request)?;
+		assert!(!config.is_enabled("vanilla"));
+
+		let request = PluginRequest::SetParameter("vanilla", "volume", "0.5");
+		let (config, _) = apply_request(config, request)?;
+		assert_eq!(
+			config.parameters("vanilla").get("volume"),
+			Some(&"0.5".to_owned())
+		);
+
+		let request = PluginRequest::SetEnabled("unknown", true);
+		assert!(apply_request(config, request).is_err());
+		Ok(())
+	}
+}
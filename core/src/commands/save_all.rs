@@ -0,0 +1,118 @@
+use super::Command;
+use crate::{
+	app,
+	common::network::{mode, Storage},
+};
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+static LOG: &'static str = "command:save-all";
+
+/// How long to wait for the chunk loading threads to save their chunks before giving up.
+static FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Debug command which saves the entire savegame and pauses further saving,
+/// so the savegame can be safely copied (e.g. backed up) while the server is running.
+pub struct SaveAll {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	message: Option<String>,
+}
+
+impl SaveAll {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			message: None,
+		}
+	}
+
+	fn save_all_and_pause(&self) -> anyhow::Result<String> {
+		let arc_storage = self.storage.upgrade().ok_or(SaveAllError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		let arc_server = storage
+			.server()
+			.as_ref()
+			.ok_or(SaveAllError::InvalidStorage)?;
+		let report = arc_server
+			.read()
+			.unwrap()
+			.save_all_and_pause(FLUSH_TIMEOUT)?;
+		Ok(match report.failed {
+			0 => format!("Saved {} chunks, safe to copy", report.saved),
+			failed => format!(
+				"Saved {} chunks, but {} failed to save (see log)",
+				report.saved, failed
+			),
+		})
+	}
+
+	fn resume(&self) -> anyhow::Result<String> {
+		let arc_storage = self.storage.upgrade().ok_or(SaveAllError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		let arc_server = storage
+			.server()
+			.as_ref()
+			.ok_or(SaveAllError::InvalidStorage)?;
+		arc_server.read().unwrap().resume_saving();
+		Ok("Resumed saving".to_owned())
+	}
+}
+
+impl Command for SaveAll {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			let result = if ui.button("Save All & Pause").clicked() {
+				Some(self.save_all_and_pause())
+			} else if ui.button("Resume").clicked() {
+				Some(self.resume())
+			} else {
+				None
+			};
+			if let Some(result) = result {
+				self.message = Some(match result {
+					Ok(message) => {
+						log::info!(target: LOG, "{}", message);
+						message
+					}
+					Err(err) => {
+						log::warn!(target: LOG, "Failed to save all: {:?}", err);
+						format!("{}", err)
+					}
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["save-all", "save-resume"]
+	}
+
+	/// Runs `save-all` to save and pause saving, or `save-resume` to resume saving.
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		match line.split_whitespace().next() {
+			Some("save-resume") => self.resume(),
+			_ => self.save_all_and_pause(),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SaveAllError {
+	#[error("network storage is invalid")]
+	InvalidStorage,
+}
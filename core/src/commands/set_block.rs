@@ -0,0 +1,239 @@
+use super::{parse_block_point, Command};
+use crate::{
+	app, block,
+	common::network::{mode, Storage},
+	server::{network::DEFAULT_WORLD, world::edit},
+};
+use engine::math::nalgebra::Point3;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:setblock";
+
+/// The name which world editing commands use to remove blocks.
+pub static AIR: &'static str = "air";
+
+/// Resolves a human-typed block name (e.g. `stone` or `vanilla:blocks/stone`) to its lookup id,
+/// or None if the name is [`air`](AIR).
+///
+/// A name which is the prefix of many blocks is only valid if it is also the whole name of one of them.
+pub fn parse_block(
+	lookup: &block::Lookup,
+	name: &str,
+) -> Result<Option<block::LookupId>, SetBlockError> {
+	if name.eq_ignore_ascii_case(AIR) {
+		return Ok(None);
+	}
+	let matches = lookup.find_by_name(name);
+	if let [(id, _)] = matches[..] {
+		return Ok(Some(id));
+	}
+	let name_lower = name.to_lowercase();
+	let exact = matches.iter().find(|(_, asset_id)| {
+		let asset_id = asset_id.to_string().to_lowercase();
+		asset_id == name_lower
+			|| asset_id.ends_with(&format!(":{}", name_lower))
+			|| asset_id.ends_with(&format!("/{}", name_lower))
+	});
+	match (exact, matches.len()) {
+		(Some((id, _)), _) => Ok(Some(*id)),
+		(None, 0) => Err(SetBlockError::UnknownBlock(name.to_owned())),
+		(None, _) => Err(SetBlockError::AmbiguousBlock(
+			name.to_owned(),
+			matches
+				.iter()
+				.map(|(_, asset_id)| asset_id.to_string())
+				.collect::<Vec<_>>()
+				.join(", "),
+		)),
+	}
+}
+
+/// Resolves a block name using the blocks which were registered when the game was initialized.
+pub fn parse_registered_block(name: &str) -> Result<Option<block::LookupId>, SetBlockError> {
+	let lookup = block::Lookup::get().ok_or(SetBlockError::NoBlocks)?;
+	parse_block(&lookup, name)
+}
+
+/// World editing command which places (or, with `air`, removes) a single block in the default world.
+pub struct SetBlock {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	point: String,
+	block: String,
+	status: edit::Status,
+}
+
+impl SetBlock {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			point: "0 0 0".to_owned(),
+			block: String::new(),
+			status: edit::Status::default(),
+		}
+	}
+
+	/// Sets the block on its own thread (its chunk may need to be loaded first),
+	/// returning the message to show until it has been set.
+	fn start(&self, point: Point3<i64>, id: Option<block::LookupId>) -> anyhow::Result<String> {
+		let storage = self.storage.clone();
+		edit::spawn(LOG, &self.status, move || {
+			Self::report(Self::set_block(&storage, point, id))
+		})?;
+		Ok(format!("Setting <{}, {}, {}>", point.x, point.y, point.z))
+	}
+
+	fn set_block(
+		storage: &Weak<RwLock<Storage>>,
+		point: Point3<i64>,
+		id: Option<block::LookupId>,
+	) -> anyhow::Result<String> {
+		let arc_database = {
+			let arc_storage = storage.upgrade().ok_or(SetBlockError::InvalidStorage)?;
+			let storage = arc_storage.read().unwrap();
+			let arc_server = storage
+				.server()
+				.as_ref()
+				.ok_or(SetBlockError::InvalidStorage)?;
+			let server = arc_server.read().unwrap();
+			let arc_database = server
+				.world(DEFAULT_WORLD)
+				.ok_or(SetBlockError::InvalidWorld)?;
+			arc_database.clone()
+		};
+		let edits = vec![(point, id)];
+		let chunks = edit::Chunks::load_for(&arc_database, &edits, edit::LOAD_TIMEOUT)?;
+		let plugins = crate::plugin::Manager::read().unwrap();
+		let report = chunks.apply(&plugins, None, &edits);
+
+		let block_name = match id {
+			Some(id) => block::Lookup::lookup_id(id)
+				.map(|asset_id| asset_id.to_string())
+				.unwrap_or_default(),
+			None => AIR.to_owned(),
+		};
+		Ok(match report {
+			edit::Report { changed: 1, .. } => format!(
+				"Set <{}, {}, {}> to {}",
+				point.x, point.y, point.z, block_name
+			),
+			edit::Report { denied: 1, .. } => format!(
+				"Setting <{}, {}, {}> to {} was denied",
+				point.x, point.y, point.z, block_name
+			),
+			_ => format!(
+				"<{}, {}, {}> is already {}",
+				point.x, point.y, point.z, block_name
+			),
+		})
+	}
+
+	fn report(result: anyhow::Result<String>) -> anyhow::Result<String> {
+		match &result {
+			Ok(message) => log::info!(target: LOG, "{}", message),
+			Err(err) => log::warn!(target: LOG, "Failed to set block: {}", err),
+		}
+		result
+	}
+}
+
+impl Command for SetBlock {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Block");
+			ui.text_edit_singleline(&mut self.block);
+		});
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut self.point);
+			if ui.button("Set Block").clicked() {
+				let args = self.point.split_whitespace().collect::<Vec<_>>();
+				let result = parse_block_point(&args)
+					.map_err(anyhow::Error::from)
+					.and_then(|point| Ok((point, parse_registered_block(&self.block)?)))
+					.and_then(|(point, id)| self.start(point, id));
+				if let Err(err) = result {
+					log::warn!(target: LOG, "Failed to set block: {}", err);
+					*self.status.lock().unwrap() = Some(format!("{}", err));
+				}
+			}
+		});
+		if let Some(message) = &*self.status.lock().unwrap() {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["setblock"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+		if args.len() != 4 {
+			return Err(SetBlockError::InvalidArguments(line.to_owned()))?;
+		}
+		let point = parse_block_point(&args[0..3])?;
+		let id = parse_registered_block(args[3])?;
+		self.start(point, id)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SetBlockError {
+	#[error("\"{0}\" is not a valid command, expected setblock <x> <y> <z> <block>")]
+	InvalidArguments(String),
+	#[error("no blocks have been registered")]
+	NoBlocks,
+	#[error("there is no block named \"{0}\"")]
+	UnknownBlock(String),
+	#[error("\"{0}\" could be any of [{1}]")]
+	AmbiguousBlock(String, String),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("the default world is not loaded")]
+	InvalidWorld,
+}
+
+#[cfg(test)]
+mod set_block {
+	use super::*;
+	use engine::asset;
+
+	fn create_lookup() -> block::Lookup {
+		let mut lookup = block::Lookup::default();
+		for name in ["blocks/glass/clear", "blocks/stone", "blocks/stone_bricks"] {
+			lookup.push(asset::Id::new("vanilla", name), block::Block::default());
+		}
+		lookup
+	}
+
+	#[test]
+	fn parse_block_names() {
+		let lookup = create_lookup();
+		assert_eq!(parse_block(&lookup, "air").unwrap(), None);
+		assert_eq!(parse_block(&lookup, "glass").unwrap(), Some(0));
+		// A prefix of many blocks resolves to the block with that exact name.
+		assert_eq!(parse_block(&lookup, "stone").unwrap(), Some(1));
+		assert_eq!(parse_block(&lookup, "stone_b").unwrap(), Some(2));
+		assert_eq!(
+			parse_block(&lookup, "vanilla:blocks/stone").unwrap(),
+			Some(1)
+		);
+		assert!(matches!(
+			parse_block(&lookup, "st"),
+			Err(SetBlockError::AmbiguousBlock(_, _))
+		));
+		assert!(matches!(
+			parse_block(&lookup, "sand"),
+			Err(SetBlockError::UnknownBlock(_))
+		));
+	}
+}
@@ -0,0 +1,101 @@
+use super::Command;
+use crate::{
+	app,
+	common::network::{mode, Storage},
+};
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+static LOG: &'static str = "command:stop";
+
+/// The name the command is run by, which the [`console`](super::console) also sends when it is interrupted (ctrl+c).
+pub(super) const NAME: &'static str = "stop";
+
+/// How long to wait for the chunk loading threads to save their chunks before giving up.
+static FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Server command which saves the entire savegame and then exits the process.
+/// If the savegame could not be saved, the server keeps running so the save can be tried again.
+pub struct Stop {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	message: Option<String>,
+}
+
+impl Stop {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			message: None,
+		}
+	}
+
+	/// Saves every user, entity, and loaded chunk of the hosted server (if a world is loaded).
+	/// Chunk saving is left paused, so nothing is written to the savegame while the process exits.
+	fn save(&self) -> anyhow::Result<()> {
+		if self.app_state.read().unwrap().get() != app::state::State::InGame {
+			return Ok(());
+		}
+		let arc_storage = self.storage.upgrade().ok_or(StopError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		let arc_server = storage.server().as_ref().ok_or(StopError::InvalidStorage)?;
+		let report = arc_server
+			.read()
+			.unwrap()
+			.save_all_and_pause(FLUSH_TIMEOUT)?;
+		if report.failed > 0 {
+			return Err(StopError::ChunksNotSaved(report.failed))?;
+		}
+		Ok(())
+	}
+
+	fn stop(&self) -> anyhow::Result<String> {
+		log::info!(target: LOG, "Saving before shutting down");
+		if let Err(err) = self.save() {
+			log::error!(target: LOG, "Failed to save, not shutting down: {:?}", err);
+			return Err(err);
+		}
+		log::info!(target: LOG, "Saved, shutting down");
+		std::process::exit(0);
+	}
+}
+
+impl Command for Stop {
+	fn is_allowed(&self) -> bool {
+		mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		if ui.button("Save & Stop").clicked() {
+			if let Err(err) = self.stop() {
+				self.message = Some(format!("{}", err));
+			}
+		}
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&[NAME]
+	}
+
+	/// Runs `stop`, which saves and then exits.
+	fn execute(&mut self, _line: &str) -> anyhow::Result<String> {
+		self.stop()
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StopError {
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("{0} chunks failed to save (see log)")]
+	ChunksNotSaved(usize),
+}
@@ -0,0 +1,291 @@
+use super::Command;
+use crate::{
+	app,
+	common::{account, network::mode, network::Storage},
+	entity::{self, component},
+};
+use engine::math::nalgebra::Point3;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:tp";
+
+/// The largest absolute value (in blocks) any axis of a teleport destination can have.
+pub static MAX_COORDINATE: f64 = 30_000_000.0;
+
+/// One axis of a teleport destination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coordinate {
+	/// The position on the axis, in blocks from the world origin (`x`).
+	Absolute(f64),
+	/// The amount to move the target along the axis from its current position (`~dx`).
+	Relative(f64),
+}
+
+impl std::str::FromStr for Coordinate {
+	type Err = TeleportError;
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		let parse = |value: &str| {
+			value
+				.parse::<f64>()
+				.ok()
+				.filter(|value| value.is_finite())
+				.ok_or_else(|| TeleportError::InvalidCoordinate(value.to_owned()))
+		};
+		match value.strip_prefix('~') {
+			Some("") => Ok(Self::Relative(0.0)),
+			Some(delta) => Ok(Self::Relative(parse(delta)?)),
+			None => Ok(Self::Absolute(parse(value)?)),
+		}
+	}
+}
+
+impl Coordinate {
+	fn resolve(&self, current: f64) -> f64 {
+		match self {
+			Self::Absolute(value) => *value,
+			Self::Relative(delta) => current + *delta,
+		}
+	}
+}
+
+/// Where a teleport command will move its target to, parsed from the form `x y z` or `~dx ~dy ~dz`.
+/// Absolute and relative coordinates can be mixed per-axis (e.g. `~ 64 ~`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Destination([Coordinate; 3]);
+
+impl std::str::FromStr for Destination {
+	type Err = TeleportError;
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		let coordinates = value
+			.split_whitespace()
+			.map(|axis| axis.parse::<Coordinate>())
+			.collect::<Result<Vec<_>, _>>()?;
+		match coordinates[..] {
+			[x, y, z] => Ok(Self([x, y, z])),
+			_ => Err(TeleportError::InvalidCoordinateCount(coordinates.len())),
+		}
+	}
+}
+
+impl Destination {
+	/// Returns the position (in blocks from the world origin) the target should be moved to
+	/// given its current position, or an error if that position is outside the world's bounds.
+	pub fn resolve(&self, current: &Point3<f64>) -> Result<Point3<f64>, TeleportError> {
+		let mut target = Point3::origin();
+		for i in 0..3 {
+			target[i] = self.0[i].resolve(current[i]);
+			if target[i].abs() > MAX_COORDINATE {
+				return Err(TeleportError::OutOfRange(target[i]));
+			}
+		}
+		Ok(target)
+	}
+}
+
+/// Debug command which moves a player's entity to a new position.
+/// The position is changed on the server and replicates to clients like any other movement,
+/// and the change in chunk updates the chunk tickets & relevancy of the player.
+pub struct Teleport {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	world: Weak<RwLock<entity::World>>,
+	/// The display name of the player to teleport.
+	/// If empty, the local player is teleported (which only exists if this process is also a client).
+	target: String,
+	destination: String,
+	message: Option<String>,
+}
+
+impl Teleport {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+		world: Weak<RwLock<entity::World>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			world,
+			target: String::new(),
+			destination: "~ ~ ~".to_owned(),
+			message: None,
+		}
+	}
+
+	fn find_target_id(&self, target: &str) -> anyhow::Result<account::Id> {
+		if target.is_empty() {
+			// A dedicated server has no local player (or client accounts), so the player must be named.
+			if !mode::get().contains(mode::Kind::Client) {
+				return Err(TeleportError::NoLocalPlayer)?;
+			}
+			let manager = crate::client::account::Manager::read()?;
+			return Ok(manager.active_account()?.id().clone());
+		}
+		let arc_storage = self
+			.storage
+			.upgrade()
+			.ok_or(TeleportError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		let arc_server = storage
+			.server()
+			.as_ref()
+			.ok_or(TeleportError::InvalidStorage)?;
+		let server = arc_server.read().unwrap();
+		let user = server
+			.find_user_by_name(target)
+			.ok_or_else(|| TeleportError::UnknownPlayer(target.to_owned()))?;
+		let id = user.read().unwrap().account().id().clone();
+		Ok(id)
+	}
+
+	/// Teleports the player with the display name `target` (or the local player if empty) to `destination`.
+	fn teleport(&self, target: &str, destination: &str) -> anyhow::Result<Point3<f64>> {
+		let destination = destination.parse::<Destination>()?;
+		let account_id = self.find_target_id(target)?;
+
+		let arc_world = self.world.upgrade().ok_or(TeleportError::InvalidWorld)?;
+		let world = arc_world.read().unwrap();
+		let mut query = world.query::<(
+			&component::OwnedByAccount,
+			&mut component::physics::linear::Position,
+		)>();
+		let position = query
+			.iter()
+			.find(|(_, (owner, _))| *owner.id() == account_id)
+			.map(|(_, (_, position))| position)
+			.ok_or_else(|| TeleportError::NoPlayerEntity(account_id.clone()))?;
+		let target = destination.resolve(&position.world_position())?;
+		position.set_world_position(target);
+		Ok(target)
+	}
+
+	fn report(result: anyhow::Result<Point3<f64>>) -> anyhow::Result<String> {
+		match result {
+			Ok(target) => {
+				let message = format!("Teleported to <{}, {}, {}>", target.x, target.y, target.z);
+				log::info!(target: LOG, "{}", message);
+				Ok(message)
+			}
+			Err(err) => {
+				log::warn!(target: LOG, "Failed to teleport: {}", err);
+				Err(err)
+			}
+		}
+	}
+}
+
+impl Command for Teleport {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		// Positions are authoritative on the server, so only (integrated) servers can teleport players.
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Player");
+			ui.text_edit_singleline(&mut self.target);
+		});
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut self.destination);
+			if ui.button("Teleport").clicked() {
+				let result = self.teleport(&self.target, &self.destination);
+				self.message = Some(match Self::report(result) {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["tp"]
+	}
+
+	/// Runs `tp [player] <x> <y> <z>`, where the local player is teleported if no player is named.
+	/// Dedicated servers (whose commands are read from stdin) have no local player, so they must name one.
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+		let (target, coordinates) = match args.len() {
+			4 => (args[0], &args[1..]),
+			_ => ("", &args[..]),
+		};
+		Self::report(self.teleport(target, &coordinates.join(" ")))
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TeleportError {
+	#[error("\"{0}\" is not a valid coordinate")]
+	InvalidCoordinate(String),
+	#[error("expected 3 coordinates (x y z) but found {0}")]
+	InvalidCoordinateCount(usize),
+	#[error("coordinate {0} is outside the world (max {})", MAX_COORDINATE)]
+	OutOfRange(f64),
+	#[error("no player named \"{0}\" has joined the server")]
+	UnknownPlayer(String),
+	#[error("a player must be named, because there is no local player to teleport")]
+	NoLocalPlayer,
+	#[error("account({0}) does not have an entity in the world")]
+	NoPlayerEntity(account::Id),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("entity world is invalid")]
+	InvalidWorld,
+}
+
+#[cfg(test)]
+mod teleport {
+	use super::*;
+
+	#[test]
+	fn parse_absolute() {
+		let destination = "10 -64.5 3".parse::<Destination>().unwrap();
+		let current = Point3::new(1.0, 2.0, 3.0);
+		assert_eq!(
+			destination.resolve(&current).unwrap(),
+			Point3::new(10.0, -64.5, 3.0)
+		);
+	}
+
+	#[test]
+	fn parse_relative() {
+		let destination = "~5 ~-2.5 ~".parse::<Destination>().unwrap();
+		let current = Point3::new(1.0, 2.0, 3.0);
+		assert_eq!(
+			destination.resolve(&current).unwrap(),
+			Point3::new(6.0, -0.5, 3.0)
+		);
+	}
+
+	#[test]
+	fn parse_mixed() {
+		let destination = "~ 64 ~-1".parse::<Destination>().unwrap();
+		let current = Point3::new(1.0, 2.0, 3.0);
+		assert_eq!(
+			destination.resolve(&current).unwrap(),
+			Point3::new(1.0, 64.0, 2.0)
+		);
+	}
+
+	#[test]
+	fn reject_invalid() {
+		assert!("1 2".parse::<Destination>().is_err());
+		assert!("1 2 3 4".parse::<Destination>().is_err());
+		assert!("1 two 3".parse::<Destination>().is_err());
+		assert!("~x 2 3".parse::<Destination>().is_err());
+		assert!("NaN 2 3".parse::<Destination>().is_err());
+	}
+
+	#[test]
+	fn reject_out_of_range() {
+		let destination = "~ 40000000 ~".parse::<Destination>().unwrap();
+		assert!(destination.resolve(&Point3::origin()).is_err());
+		let destination = "~-20000000 0 0".parse::<Destination>().unwrap();
+		let current = Point3::new(-20_000_000.0, 0.0, 0.0);
+		assert!(destination.resolve(&current).is_err());
+	}
+}
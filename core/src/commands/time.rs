@@ -0,0 +1,188 @@
+use super::Command;
+use crate::{
+	app,
+	common::{
+		network::{mode, Storage},
+		world::clock::{self, Clock},
+	},
+};
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "command:time";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeRequest {
+	/// Reports the current time.
+	Query,
+	/// Sets the time of day (in ticks since sunrise), keeping the current day.
+	Set(u64),
+	/// Stops (or resumes) time from advancing.
+	Freeze(bool),
+}
+
+/// Parses `time`, `time set <ticks|day|noon|sunset|night>`, `time freeze`, or `time resume`.
+pub fn parse_time(line: &str) -> Result<TimeRequest, TimeError> {
+	let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+	match args[..] {
+		[] => Ok(TimeRequest::Query),
+		["set", time] => match clock::named_time_of_day(time) {
+			Some(ticks) => Ok(TimeRequest::Set(ticks)),
+			None => time
+				.parse::<u64>()
+				.map(TimeRequest::Set)
+				.map_err(|_| TimeError::InvalidTime(time.to_owned())),
+		},
+		["freeze"] => Ok(TimeRequest::Freeze(true)),
+		["resume"] => Ok(TimeRequest::Freeze(false)),
+		_ => Err(TimeError::InvalidArguments(line.to_owned())),
+	}
+}
+
+/// Applies a request to the clock, returning a description of the clock afterwards.
+pub fn apply_time(request: TimeRequest, clock: &mut Clock) -> String {
+	match request {
+		TimeRequest::Query => {}
+		TimeRequest::Set(time_of_day) => clock.set_time_of_day(time_of_day),
+		TimeRequest::Freeze(frozen) => clock.set_frozen(frozen),
+	}
+	format!("It is {}", clock)
+}
+
+/// Admin command which reports, sets, or freezes the server's time of day.
+pub struct Time {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	time: String,
+	message: Option<String>,
+}
+
+impl Time {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			time: "noon".to_owned(),
+			message: None,
+		}
+	}
+
+	fn apply(&self, request: TimeRequest) -> anyhow::Result<String> {
+		let arc_clock = {
+			let arc_storage = self.storage.upgrade().ok_or(TimeError::InvalidStorage)?;
+			let storage = arc_storage.read().unwrap();
+			let arc_server = storage.server().as_ref().ok_or(TimeError::InvalidStorage)?;
+			let server = arc_server.read().unwrap();
+			server.clock().clone()
+		};
+		let mut clock = arc_clock.write().unwrap();
+		Ok(apply_time(request, &mut clock))
+	}
+
+	fn report(result: anyhow::Result<String>) -> anyhow::Result<String> {
+		match &result {
+			Ok(message) => log::info!(target: LOG, "{}", message),
+			Err(err) => log::warn!(target: LOG, "Failed to change the time: {}", err),
+		}
+		result
+	}
+
+	fn render_result(&mut self, result: anyhow::Result<String>) {
+		self.message = Some(match Self::report(result) {
+			Ok(message) => message,
+			Err(err) => format!("{}", err),
+		});
+	}
+}
+
+impl Command for Time {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut self.time);
+			if ui.button("Set Time").clicked() {
+				let result = parse_time(&format!("time set {}", self.time))
+					.map_err(anyhow::Error::from)
+					.and_then(|request| self.apply(request));
+				self.render_result(result);
+			}
+		});
+		ui.horizontal(|ui| {
+			if ui.button("Freeze").clicked() {
+				let result = self.apply(TimeRequest::Freeze(true));
+				self.render_result(result);
+			}
+			if ui.button("Resume").clicked() {
+				let result = self.apply(TimeRequest::Freeze(false));
+				self.render_result(result);
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["time"]
+	}
+
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let request = parse_time(line)?;
+		Self::report(self.apply(request))
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TimeError {
+	#[error("\"{0}\" is not a valid command, expected time [set <ticks|day|noon|sunset|night>|freeze|resume]")]
+	InvalidArguments(String),
+	#[error("\"{0}\" is not a number of ticks or one of day, noon, sunset, or night")]
+	InvalidTime(String),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+}
+
+#[cfg(test)]
+mod time {
+	use super::*;
+
+	#[test]
+	fn parse_requests() {
+		assert_eq!(parse_time("time").unwrap(), TimeRequest::Query);
+		assert_eq!(parse_time("time set 1234").unwrap(), TimeRequest::Set(1234));
+		assert_eq!(parse_time("time set noon").unwrap(), TimeRequest::Set(6000));
+		assert_eq!(
+			parse_time("time freeze").unwrap(),
+			TimeRequest::Freeze(true)
+		);
+		assert_eq!(
+			parse_time("time resume").unwrap(),
+			TimeRequest::Freeze(false)
+		);
+		assert!(parse_time("time set later").is_err());
+		assert!(parse_time("time stop now").is_err());
+	}
+
+	#[test]
+	fn set_overrides_advancing_clock() {
+		let mut clock = Clock::default();
+		clock.advance(clock::TICKS_PER_DAY + 500);
+		let message = apply_time(parse_time("time set night").unwrap(), &mut clock);
+		assert_eq!(clock.time_of_day(), 18000);
+		assert_eq!(clock.day(), 1);
+		assert_eq!(message, "It is day 1, time 18000");
+
+		apply_time(parse_time("time freeze").unwrap(), &mut clock);
+		clock.advance(100);
+		assert_eq!(clock.time_of_day(), 18000);
+		apply_time(parse_time("time resume").unwrap(), &mut clock);
+		clock.advance(100);
+		assert_eq!(clock.time_of_day(), 18100);
+	}
+}
@@ -1 +1,131 @@
+use super::Command;
+use crate::{
+	app,
+	common::{account, network::mode, network::Storage},
+	entity,
+};
+use std::sync::{Arc, RwLock, Weak};
 
+static LOG: &'static str = "command:world";
+
+/// Server command which moves a player into another world of the savegame, loading the world if it is not loaded yet.
+/// The player's entity keeps its position, and starts loading (and being sent) the chunks of the new world.
+pub struct LoadWorld {
+	app_state: Arc<RwLock<app::state::Machine>>,
+	storage: Weak<RwLock<Storage>>,
+	world: Weak<RwLock<entity::World>>,
+	/// The display name of the player to move.
+	target: String,
+	world_name: String,
+	message: Option<String>,
+}
+
+impl LoadWorld {
+	pub fn new(
+		app_state: Arc<RwLock<app::state::Machine>>,
+		storage: Weak<RwLock<Storage>>,
+		world: Weak<RwLock<entity::World>>,
+	) -> Self {
+		Self {
+			app_state,
+			storage,
+			world,
+			target: String::new(),
+			world_name: String::new(),
+			message: None,
+		}
+	}
+
+	/// Moves the player with the display name `target` into the world named `world_name`.
+	fn move_player(&self, target: &str, world_name: &str) -> anyhow::Result<account::Id> {
+		let arc_world = self.world.upgrade().ok_or(LoadWorldError::InvalidWorld)?;
+		let arc_storage = self
+			.storage
+			.upgrade()
+			.ok_or(LoadWorldError::InvalidStorage)?;
+		let storage = arc_storage.read().unwrap();
+		// Loading the world writes to the server, so it must be done before the server is locked to move the player.
+		storage.load_server_world(world_name)?;
+
+		let arc_server = storage
+			.server()
+			.as_ref()
+			.ok_or(LoadWorldError::InvalidStorage)?;
+		let mut server = arc_server.write().unwrap();
+		let account_id = {
+			let user = server
+				.find_user_by_name(target)
+				.ok_or_else(|| LoadWorldError::UnknownPlayer(target.to_owned()))?;
+			let user = user.read().unwrap();
+			user.account().id().clone()
+		};
+		server.move_player(&account_id, world_name, &arc_world)?;
+		Ok(account_id)
+	}
+
+	fn report(&self, target: &str, world_name: &str) -> anyhow::Result<String> {
+		match self.move_player(target, world_name) {
+			Ok(account_id) => {
+				let message = format!("Moved {} to world \"{}\"", account_id, world_name);
+				log::info!(target: LOG, "{}", message);
+				Ok(message)
+			}
+			Err(err) => {
+				log::warn!(target: LOG, "Failed to move {} to another world: {}", target, err);
+				Err(err)
+			}
+		}
+	}
+}
+
+impl Command for LoadWorld {
+	fn is_allowed(&self) -> bool {
+		let current_state = self.app_state.read().unwrap().get();
+		current_state == app::state::State::InGame && mode::get().contains(mode::Kind::Server)
+	}
+
+	fn render(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label("Player");
+			ui.text_edit_singleline(&mut self.target);
+		});
+		ui.horizontal(|ui| {
+			ui.label("World");
+			ui.text_edit_singleline(&mut self.world_name);
+			if ui.button("Move").clicked() {
+				self.message = Some(match self.report(&self.target, &self.world_name) {
+					Ok(message) => message,
+					Err(err) => format!("{}", err),
+				});
+			}
+		});
+		if let Some(message) = &self.message {
+			ui.label(message);
+		}
+	}
+
+	fn names(&self) -> &[&'static str] {
+		&["world"]
+	}
+
+	/// Runs `world <player> <name>`.
+	fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+		let args = line.split_whitespace().skip(1).collect::<Vec<_>>();
+		match args[..] {
+			[target, world_name] => self.report(target, world_name),
+			_ => Err(LoadWorldError::InvalidArguments(args.len()))?,
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadWorldError {
+	#[error("expected a player and a world name but found {0} arguments")]
+	InvalidArguments(usize),
+	#[error("no player named \"{0}\" has joined the server")]
+	UnknownPlayer(String),
+	#[error("network storage is invalid")]
+	InvalidStorage,
+	#[error("entity world is invalid")]
+	InvalidWorld,
+}
@@ -1,4 +1,7 @@
 pub mod account;
+pub mod asset_batch;
+pub mod log_filter;
 pub mod network;
+pub mod physics;
 pub mod utility;
 pub mod world;
@@ -4,3 +4,6 @@ mod account;
 pub use account::*;
 
 pub mod key;
+
+mod resolver;
+pub use resolver::*;
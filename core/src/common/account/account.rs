@@ -1,6 +1,6 @@
 use crate::common::{
 	account::key::{self, Certificate, Key, PrivateKey, PublicKey},
-	utility::DataFile,
+	utility::{versioned, DataFile},
 };
 use anyhow::Result;
 use std::path::{Path, PathBuf};
@@ -76,6 +76,11 @@ impl Account {
 	}
 }
 
+/// The version of the `meta.kdl` format, written as the first node of the file.
+/// Files written before the version was recorded are version 0,
+/// which have the same nodes as version 1 and are upgraded when next saved.
+const META_VERSION: versioned::Version = 1;
+
 impl DataFile for Account {
 	fn file_name() -> &'static str {
 		"meta.kdl"
@@ -96,6 +101,7 @@ impl DataFile for Account {
 		};
 
 		let mut text = String::new();
+		text += &format!("version {}\n", META_VERSION);
 		text += &format!("display-name \"{}\"\n", self.display_name);
 		text += &format!("key \"{}\"\n", key_id);
 		std::fs::write(&file_path, text)?;
@@ -107,10 +113,21 @@ impl DataFile for Account {
 		let root = file_path.parent().unwrap().to_owned();
 		let meta_text = std::fs::read_to_string(&file_path)?;
 		let nodes = meta_text.parse::<kdl::KdlDocument>()?;
+		let mut version = 0;
 		let mut display_name = String::new();
 		let mut key_id = String::new();
 		for node in nodes.into_iter() {
 			match node.name().value() {
+				"version" => {
+					let entry = node
+						.entries()
+						.first()
+						.ok_or(LoadError::MissingValue("version", 0))?;
+					match entry.value() {
+						kdl::KdlValue::Base10(v) => version = *v,
+						_ => return Err(LoadError::InvalidType("version", 0, "Integer"))?,
+					}
+				}
 				"display-name" => {
 					let entry = node
 						.entries()
@@ -134,6 +151,9 @@ impl DataFile for Account {
 				_ => {}
 			}
 		}
+		if version > META_VERSION as i64 {
+			return Err(LoadError::UnsupportedVersion(version, META_VERSION))?;
+		}
 		let key = match key_id.as_str() {
 			"Private" => {
 				let certificate = Certificate::load(&root)?;
@@ -165,4 +185,6 @@ enum LoadError {
 	MissingValue(&'static str, usize),
 	#[error("kdl value in node {0} index {1} is not a {2}")]
 	InvalidType(&'static str, usize, &'static str),
+	#[error("account was saved with version {0}, but only versions up to {1} are supported")]
+	UnsupportedVersion(i64, versioned::Version),
 }
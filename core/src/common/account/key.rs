@@ -27,6 +27,87 @@ fn parse_pem(pem: String) -> Option<Vec<u8>> {
 	None
 }
 
+/// The labels of the PEM blocks used when importing and exporting keys.
+static PEM_CERTIFICATE: &'static str = "CERTIFICATE";
+static PEM_PRIVATE_KEY: &'static str = "PRIVATE KEY";
+static PEM_PUBLIC_KEY: &'static str = "PUBLIC KEY";
+
+/// The DER encoding of a SubjectPublicKeyInfo for an ECDSA P-256 key,
+/// up to (but excluding) the 65 byte uncompressed public point.
+const P256_SPKI_PREFIX: [u8; 26] = [
+	0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+	0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+const P256_POINT_LEN: usize = 65;
+
+/// Encodes DER bytes as a PEM block with the provided label (e.g. `CERTIFICATE`).
+fn encode_pem(label: &str, der: &[u8]) -> String {
+	use base64ct::{Base64, Encoding};
+	let encoded = Base64::encode_string(der);
+	let mut pem = format!("-----BEGIN {}-----\n", label);
+	let mut remaining = encoded.as_str();
+	while !remaining.is_empty() {
+		let (line, next) = remaining.split_at(remaining.len().min(64));
+		pem += line;
+		pem += "\n";
+		remaining = next;
+	}
+	pem += &format!("-----END {}-----\n", label);
+	pem
+}
+
+/// Decodes every PEM block in the text, returning the label and DER bytes of each block in order.
+fn decode_pem_blocks(pem: &str) -> std::result::Result<Vec<(String, Vec<u8>)>, Error> {
+	use base64ct::{Base64, Encoding};
+	let mut blocks = Vec::new();
+	let mut current: Option<(String, String)> = None;
+	for line in pem.lines().map(str::trim) {
+		let begin = line
+			.strip_prefix("-----BEGIN ")
+			.and_then(|line| line.strip_suffix("-----"));
+		let end = line
+			.strip_prefix("-----END ")
+			.and_then(|line| line.strip_suffix("-----"));
+		if let Some(label) = begin {
+			if let Some((open_label, _)) = &current {
+				return Err(Error::MalformedPEM(format!(
+					"{} block begins before the {} block ends",
+					label, open_label
+				)));
+			}
+			current = Some((label.to_owned(), String::new()));
+		} else if let Some(label) = end {
+			match current.take() {
+				Some((open_label, body)) if open_label == label => {
+					let der = Base64::decode_vec(&body).map_err(|_| {
+						Error::MalformedPEM(format!("{} block is not valid base64", label))
+					})?;
+					blocks.push((open_label, der));
+				}
+				_ => {
+					return Err(Error::MalformedPEM(format!(
+						"{} block ends without beginning",
+						label
+					)));
+				}
+			}
+		} else if let Some((_, body)) = &mut current {
+			body.push_str(line);
+		}
+	}
+	if let Some((label, _)) = current {
+		return Err(Error::MalformedPEM(format!("{} block never ends", label)));
+	}
+	Ok(blocks)
+}
+
+fn find_pem_block<'a>(blocks: &'a [(String, Vec<u8>)], label: &str) -> Option<&'a Vec<u8>> {
+	blocks
+		.iter()
+		.find(|(block_label, _)| block_label == label)
+		.map(|(_, der)| der)
+}
+
 #[derive(Clone)]
 pub enum Key {
 	Private(Certificate, PrivateKey),
@@ -42,6 +123,63 @@ impl std::fmt::Debug for Key {
 	}
 }
 
+impl Key {
+	/// Exports the key as standard PEM blocks.
+	/// Private keys are exported as their x509 certificate followed by the PKCS#8 private key,
+	/// public keys are exported as a SubjectPublicKeyInfo `PUBLIC KEY` block.
+	pub fn to_pem(&self) -> Result<String> {
+		Ok(match self {
+			Self::Private(certificate, private_key) => {
+				format!("{}{}", certificate.to_pem(), private_key.to_pem())
+			}
+			Self::Public(public_key) => public_key.to_pem()?,
+		})
+	}
+
+	/// Imports a key exported by [`to_pem`](Self::to_pem).
+	/// If the PEM contains a private key, it must also contain the certificate that the private key belongs to.
+	pub fn from_pem(pem: &str) -> Result<Self> {
+		let blocks = decode_pem_blocks(pem)?;
+		match find_pem_block(&blocks, PEM_PRIVATE_KEY) {
+			Some(private_key) => {
+				let certificate = find_pem_block(&blocks, PEM_CERTIFICATE)
+					.ok_or(Error::MissingPEMBlock(PEM_CERTIFICATE))?;
+				let certificate = Certificate(certificate.clone());
+				let private_key = PrivateKey(private_key.clone());
+				if !certificate.contains(&private_key.public_key()?)? {
+					return Err(Error::MismatchedKeyPair)?;
+				}
+				Ok(Self::Private(certificate, private_key))
+			}
+			None => Ok(Self::Public(PublicKey::from_pem_blocks(&blocks)?)),
+		}
+	}
+
+	/// Returns an error if the key could not be used,
+	/// because a private key does not belong to its certificate or a public key is not a supported key.
+	pub fn validate(&self) -> Result<()> {
+		match self {
+			Self::Private(certificate, private_key) => {
+				if !certificate.contains(&private_key.public_key()?)? {
+					return Err(Error::MismatchedKeyPair)?;
+				}
+			}
+			Self::Public(public_key) => {
+				public_key.to_spki()?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns the public key of this key, deriving it from the private key if necessary.
+	pub fn public_key(&self) -> Result<PublicKey> {
+		match self {
+			Self::Private(_, private_key) => private_key.public_key(),
+			Self::Public(public_key) => Ok(public_key.clone()),
+		}
+	}
+}
+
 #[derive(Clone)]
 pub struct Certificate(Vec<u8>);
 
@@ -66,10 +204,20 @@ impl Certificate {
 		Ok(Self(bytes))
 	}
 
+	pub fn to_pem(&self) -> String {
+		encode_pem(PEM_CERTIFICATE, &self.0)
+	}
+
 	pub fn fingerprint(&self) -> String {
 		use socknet::utility::fingerprint;
 		fingerprint(&self.clone().into())
 	}
+
+	/// Returns true if the certificate was issued for the provided public key.
+	fn contains(&self, public_key: &PublicKey) -> Result<bool> {
+		let spki = public_key.to_spki()?;
+		Ok(self.0.windows(spki.len()).any(|window| window == &spki[..]))
+	}
 }
 
 impl Into<rustls::Certificate> for Certificate {
@@ -97,6 +245,23 @@ impl DataFile for PrivateKey {
 	}
 }
 
+impl PrivateKey {
+	pub fn to_pem(&self) -> String {
+		encode_pem(PEM_PRIVATE_KEY, &self.0)
+	}
+
+	/// Derives the public key from the PKCS#8 private key.
+	pub fn public_key(&self) -> Result<PublicKey> {
+		use ring::signature::{self, EcdsaKeyPair, KeyPair};
+		let key_pair =
+			EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, &self.0)
+				.map_err(|err| Error::InvalidPrivateKey(err.description_()))?;
+		Ok(PublicKey::from_bytes(
+			key_pair.public_key().as_ref().to_vec(),
+		))
+	}
+}
+
 impl Into<rustls::PrivateKey> for PrivateKey {
 	fn into(self) -> rustls::PrivateKey {
 		rustls::PrivateKey(self.0)
@@ -131,6 +296,41 @@ impl PublicKey {
 		use socknet::utility::decode_bytes;
 		decode_bytes(&self.0)
 	}
+
+	/// Exports the key as a SubjectPublicKeyInfo `PUBLIC KEY` PEM block.
+	pub fn to_pem(&self) -> Result<String> {
+		Ok(encode_pem(PEM_PUBLIC_KEY, &self.to_spki()?))
+	}
+
+	/// Imports a key exported by [`to_pem`](Self::to_pem).
+	pub fn from_pem(pem: &str) -> Result<Self> {
+		Self::from_pem_blocks(&decode_pem_blocks(pem)?)
+	}
+
+	fn from_pem_blocks(blocks: &[(String, Vec<u8>)]) -> Result<Self> {
+		if find_pem_block(&blocks, PEM_PRIVATE_KEY).is_some() {
+			return Err(Error::InvalidPrivacyPrivate)?;
+		}
+		let spki = find_pem_block(&blocks, PEM_PUBLIC_KEY)
+			.ok_or(Error::MissingPEMBlock(PEM_PUBLIC_KEY))?;
+		let is_p256 = spki.len() == P256_SPKI_PREFIX.len() + P256_POINT_LEN
+			&& spki.starts_with(&P256_SPKI_PREFIX);
+		if !is_p256 {
+			return Err(Error::UnsupportedPublicKey)?;
+		}
+		Ok(Self::from_bytes(spki[P256_SPKI_PREFIX.len()..].to_vec()))
+	}
+
+	/// Returns the DER encoded SubjectPublicKeyInfo of the key.
+	fn to_spki(&self) -> Result<Vec<u8>> {
+		let point = self.as_bytes()?;
+		if point.len() != P256_POINT_LEN {
+			return Err(Error::UnsupportedPublicKey)?;
+		}
+		let mut spki = P256_SPKI_PREFIX.to_vec();
+		spki.extend(point);
+		Ok(spki)
+	}
 }
 
 impl std::fmt::Display for PublicKey {
@@ -147,4 +347,92 @@ pub enum Error {
 	InvalidPrivacyPublic,
 	#[error("Expected public key, but found private key")]
 	InvalidPrivacyPrivate,
+	#[error("PEM is malformed: {0}")]
+	MalformedPEM(String),
+	#[error("PEM does not contain a {0} block")]
+	MissingPEMBlock(&'static str),
+	#[error("Public key is not an uncompressed ECDSA P-256 key")]
+	UnsupportedPublicKey,
+	#[error("Private key was rejected: {0}")]
+	InvalidPrivateKey(&'static str),
+	#[error("Private key does not belong to the certificate it was provided with")]
+	MismatchedKeyPair,
+}
+
+#[cfg(test)]
+mod pem {
+	use super::*;
+
+	fn create_key() -> Key {
+		let (_fingerprint, certificate, private_key) = create_pem().unwrap();
+		Key::from_pem(&format!("{}{}", certificate, private_key)).unwrap()
+	}
+
+	fn expect_error(result: Result<Key>) -> Error {
+		result
+			.expect_err("importing the pem should fail")
+			.downcast::<Error>()
+			.expect("expected a key error")
+	}
+
+	#[test]
+	fn private_key_round_trip() {
+		let key = create_key();
+		let pem = key.to_pem().unwrap();
+		let imported = Key::from_pem(&pem).unwrap();
+		assert!(matches!(imported, Key::Private(_, _)));
+		assert_eq!(imported.to_pem().unwrap(), pem);
+		assert!(imported.public_key().unwrap() == key.public_key().unwrap());
+	}
+
+	#[test]
+	fn public_key_round_trip() {
+		let public_key = create_key().public_key().unwrap();
+		let pem = public_key.to_pem().unwrap();
+		assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+		assert!(PublicKey::from_pem(&pem).unwrap() == public_key);
+		match Key::from_pem(&pem).unwrap() {
+			Key::Public(imported) => assert!(imported == public_key),
+			key => panic!("expected a public key, found {:?}", key),
+		}
+	}
+
+	#[test]
+	fn mismatched_key_pair() {
+		let (_, certificate, _) = create_pem().unwrap();
+		let (_, _, other_private_key) = create_pem().unwrap();
+		let pem = format!("{}{}", certificate, other_private_key);
+		assert!(matches!(
+			expect_error(Key::from_pem(&pem)),
+			Error::MismatchedKeyPair
+		));
+	}
+
+	#[test]
+	fn malformed_pem() {
+		let not_base64 = "-----BEGIN PUBLIC KEY-----\n!!!!\n-----END PUBLIC KEY-----\n";
+		assert!(matches!(
+			expect_error(Key::from_pem(not_base64)),
+			Error::MalformedPEM(_)
+		));
+
+		let truncated = create_key().to_pem().unwrap();
+		let truncated = &truncated[..truncated.len() - 10];
+		assert!(matches!(
+			expect_error(Key::from_pem(truncated)),
+			Error::MalformedPEM(_)
+		));
+
+		let (_, _, private_key) = create_pem().unwrap();
+		assert!(matches!(
+			expect_error(Key::from_pem(&private_key)),
+			Error::MissingPEMBlock(_)
+		));
+
+		let unsupported = encode_pem(PEM_PUBLIC_KEY, &[0x30, 0x03, 0x02, 0x01, 0x00]);
+		assert!(matches!(
+			expect_error(Key::from_pem(&unsupported)),
+			Error::UnsupportedPublicKey
+		));
+	}
 }
@@ -0,0 +1,86 @@
+use super::Id;
+use std::collections::HashMap;
+
+/// Maps the display names of accounts to their ids (and back), so the same name always refers to the same account.
+///
+/// Clients use a resolver to find which of their accounts to log in as,
+/// and servers use one to find users by the name other players know them by.
+/// Alternate identity providers (e.g. an external account service) can be used by implementing this trait.
+pub trait Resolver: Send + Sync {
+	/// Returns the id of the account known by `name`, if there is one.
+	fn id_of(&self, name: &str) -> Option<Id>;
+
+	/// Returns the name the account with `id` is known by, if the account is known.
+	fn name_of(&self, id: &Id) -> Option<String>;
+
+	/// Records that the account with `id` is known by `name`,
+	/// replacing any name it was previously known by.
+	fn register(&mut self, id: Id, name: String);
+}
+
+/// The default [`Resolver`], which only knows of the accounts it has been told about.
+///
+/// Account ids are the fingerprints of their certificates (not derived from their names),
+/// so a name resolves to whichever account most recently registered it.
+#[derive(Default, Debug, Clone)]
+pub struct Offline {
+	ids: HashMap<String, Id>,
+	names: HashMap<Id, String>,
+}
+
+impl Resolver for Offline {
+	fn id_of(&self, name: &str) -> Option<Id> {
+		self.ids.get(name).cloned()
+	}
+
+	fn name_of(&self, id: &Id) -> Option<String> {
+		self.names.get(id).cloned()
+	}
+
+	fn register(&mut self, id: Id, name: String) {
+		if let Some(previous_name) = self.names.insert(id.clone(), name.clone()) {
+			if self.ids.get(&previous_name) == Some(&id) {
+				self.ids.remove(&previous_name);
+			}
+		}
+		if let Some(previous_id) = self.ids.insert(name, id.clone()) {
+			if previous_id != id {
+				self.names.remove(&previous_id);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod offline {
+	use super::*;
+
+	#[test]
+	fn same_name_resolves_to_same_id() {
+		let mut resolver = Offline::default();
+		resolver.register("a1b2".to_owned(), "alice".to_owned());
+		resolver.register("c3d4".to_owned(), "bob".to_owned());
+		for _ in 0..3 {
+			assert_eq!(resolver.id_of("alice"), Some("a1b2".to_owned()));
+			assert_eq!(
+				resolver.name_of(&"a1b2".to_owned()),
+				Some("alice".to_owned())
+			);
+		}
+		assert_eq!(resolver.id_of("carol"), None);
+	}
+
+	#[test]
+	fn renaming_releases_old_name() {
+		let mut resolver = Offline::default();
+		resolver.register("a1b2".to_owned(), "alice".to_owned());
+		resolver.register("a1b2".to_owned(), "alicia".to_owned());
+		assert_eq!(resolver.id_of("alice"), None);
+		assert_eq!(resolver.id_of("alicia"), Some("a1b2".to_owned()));
+
+		// Another account taking the name means the original account no longer has a name.
+		resolver.register("c3d4".to_owned(), "alicia".to_owned());
+		assert_eq!(resolver.id_of("alicia"), Some("c3d4".to_owned()));
+		assert_eq!(resolver.name_of(&"a1b2".to_owned()), None);
+	}
+}
@@ -0,0 +1,433 @@
+//! Loads many assets at once, reading every requested asset from a pak archive before moving on to the next.
+//!
+//! Each pak archive is opened once per batch. The assets it holds are listed from the opened archive,
+//! and every requested asset among them is read from it, so which archive an asset is in is never guessed from its id.
+//! Requested assets which are not in any archive fail to load.
+use engine::asset::{self, AnyBox};
+use std::{
+	collections::HashMap,
+	io::Read,
+	path::{Path, PathBuf},
+};
+
+static LOG: &'static str = "asset-batch";
+
+/// Where the assets of a batch are read from.
+pub trait Archives: Clone + Send + Sync + 'static {
+	type Archive;
+
+	/// Returns the names of every archive which can be opened.
+	fn names(&self) -> anyhow::Result<Vec<String>>;
+
+	fn open(&self, name: &str) -> anyhow::Result<Self::Archive>;
+
+	/// Returns the ids of the assets held by an opened archive.
+	fn ids(&self, archive: &Self::Archive) -> Vec<asset::Id>;
+
+	fn read<T>(&self, archive: &mut Self::Archive, id: &asset::Id) -> anyhow::Result<Box<T>>
+	where
+		T: asset::Asset + 'static;
+}
+
+/// The pak archives in a directory.
+/// A pak is a zip archive named after the module whose assets it holds (`<module>.pak`),
+/// with an entry for each compiled asset at the path of the asset (plus an optional extension).
+#[derive(Clone)]
+pub struct Paks {
+	directory: PathBuf,
+}
+
+/// An opened pak archive, and the entry of each asset it holds.
+pub struct Pak {
+	zip: zip::ZipArchive<std::fs::File>,
+	entries: HashMap<asset::Id, String>,
+}
+
+impl Paks {
+	pub fn new(directory: PathBuf) -> Self {
+		Self { directory }
+	}
+
+	/// The paks the game was run with, which are in its working directory.
+	pub fn current() -> anyhow::Result<Self> {
+		Ok(Self::new(std::env::current_dir()?))
+	}
+
+	fn path_of(&self, name: &str) -> PathBuf {
+		self.directory.join(format!("{}.pak", name))
+	}
+
+	/// Returns the id of the asset at an entry of the pak for a module, or None if the entry is a directory.
+	fn entry_id(module: &str, entry: &str) -> Option<asset::Id> {
+		if entry.ends_with('/') {
+			return None;
+		}
+		let path = Path::new(entry).with_extension("");
+		let path = path.to_str()?.replace('\\', "/");
+		Some(asset::Id::new(module, &path))
+	}
+}
+
+impl Archives for Paks {
+	type Archive = Pak;
+
+	fn names(&self) -> anyhow::Result<Vec<String>> {
+		let mut names = Vec::new();
+		for entry in std::fs::read_dir(&self.directory)? {
+			let path = entry?.path();
+			if path.extension().map(|ext| ext == "pak") != Some(true) {
+				continue;
+			}
+			if let Some(name) = path.file_stem().map(|stem| stem.to_str()).flatten() {
+				names.push(name.to_owned());
+			}
+		}
+		names.sort();
+		Ok(names)
+	}
+
+	fn open(&self, name: &str) -> anyhow::Result<Self::Archive> {
+		let file = std::fs::File::open(self.path_of(name))?;
+		let zip = zip::ZipArchive::new(file)?;
+		let entries = zip
+			.file_names()
+			.filter_map(|entry| Some((Self::entry_id(name, entry)?, entry.to_owned())))
+			.collect();
+		Ok(Pak { zip, entries })
+	}
+
+	fn ids(&self, archive: &Self::Archive) -> Vec<asset::Id> {
+		archive.entries.keys().cloned().collect()
+	}
+
+	fn read<T>(&self, archive: &mut Self::Archive, id: &asset::Id) -> anyhow::Result<Box<T>>
+	where
+		T: asset::Asset + 'static,
+	{
+		let entry = archive
+			.entries
+			.get(id)
+			.ok_or_else(|| Error::NotFound(id.clone()))?;
+		let mut bytes = Vec::new();
+		archive.zip.by_name(entry)?.read_to_end(&mut bytes)?;
+		let any_box: AnyBox = T::decompile(&bytes)?;
+		match any_box.downcast::<T>() {
+			Ok(asset) => Ok(asset),
+			Err(_) => Err(Error::UnexpectedType(id.clone()))?,
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("asset {0} is not of the expected type")]
+	UnexpectedType(asset::Id),
+	#[error("asset {0} is not in any archive")]
+	NotFound(asset::Id),
+}
+
+/// The result of loading each asset in a batch, in the order the assets were requested.
+pub type Loaded<T> = Vec<(asset::Id, anyhow::Result<Box<T>>)>;
+
+/// Synchronously loads a batch of assets of the same type from the pak archives in the working directory,
+/// in the order they were requested.
+/// Fails if any of the assets cannot be loaded or are not of the expected type.
+pub fn load_many_sync<T>(asset_ids: &[asset::Id]) -> anyhow::Result<Vec<(asset::Id, Box<T>)>>
+where
+	T: asset::Asset + 'static,
+{
+	all_loaded(load_each_sync_from(&Paks::current()?, asset_ids))
+}
+
+/// Loads a batch of assets of the same type from the pak archives in the working directory, in the order they were requested.
+/// Each archive is read on its own blocking thread, so batches which span many archives load concurrently.
+/// Fails if any of the assets cannot be loaded or are not of the expected type.
+pub async fn load_many<T>(asset_ids: Vec<asset::Id>) -> anyhow::Result<Vec<(asset::Id, Box<T>)>>
+where
+	T: asset::Asset + Send + 'static,
+{
+	all_loaded(load_each_from(Paks::current()?, asset_ids).await?)
+}
+
+/// Loads a batch of assets like [`load_many`], but reports whether each asset loaded
+/// instead of failing the batch when any of them do not.
+pub async fn load_each<T>(asset_ids: Vec<asset::Id>) -> anyhow::Result<Loaded<T>>
+where
+	T: asset::Asset + Send + 'static,
+{
+	load_each_from(Paks::current()?, asset_ids).await
+}
+
+/// Opens an archive and reads every requested asset which it holds,
+/// returning each asset with the position it was requested at.
+fn load_group<A, T>(
+	archives: &A,
+	name: &str,
+	requested: &HashMap<asset::Id, Vec<usize>>,
+) -> Vec<(usize, anyhow::Result<Box<T>>)>
+where
+	A: Archives,
+	T: asset::Asset + 'static,
+{
+	let mut archive = match archives.open(name) {
+		Ok(archive) => archive,
+		Err(err) => {
+			// The assets in the archive are reported as not found.
+			log::error!(target: LOG, "Failed to open archive {}: {:?}", name, err);
+			return Vec::new();
+		}
+	};
+	let mut assets = Vec::new();
+	for id in archives.ids(&archive).into_iter() {
+		let orders = match requested.get(&id) {
+			Some(orders) => orders,
+			None => continue,
+		};
+		// An asset requested more than once is read (from the same opened archive) for each request.
+		for order in orders.iter() {
+			assets.push((*order, archives.read::<T>(&mut archive, &id)));
+		}
+	}
+	assets
+}
+
+fn requested_orders(asset_ids: &[asset::Id]) -> HashMap<asset::Id, Vec<usize>> {
+	let mut requested: HashMap<asset::Id, Vec<usize>> = HashMap::new();
+	for (order, id) in asset_ids.iter().enumerate() {
+		requested.entry(id.clone()).or_default().push(order);
+	}
+	requested
+}
+
+/// Pairs each requested id with the asset loaded for it, failing the ids which were not in any archive.
+/// If an asset is in more than one archive, the first archive (in name order) is used.
+fn into_requested_order<T>(
+	asset_ids: &[asset::Id],
+	found: Vec<(usize, anyhow::Result<T>)>,
+) -> Vec<(asset::Id, anyhow::Result<T>)> {
+	let mut by_order = HashMap::with_capacity(found.len());
+	for (order, asset) in found.into_iter() {
+		by_order.entry(order).or_insert(asset);
+	}
+	asset_ids
+		.iter()
+		.enumerate()
+		.map(|(order, id)| {
+			let asset = match by_order.remove(&order) {
+				Some(asset) => asset,
+				None => Err(Error::NotFound(id.clone()).into()),
+			};
+			(id.clone(), asset)
+		})
+		.collect()
+}
+
+fn all_loaded<T>(assets: Loaded<T>) -> anyhow::Result<Vec<(asset::Id, Box<T>)>> {
+	assets
+		.into_iter()
+		.map(|(id, asset)| Ok((id, asset?)))
+		.collect()
+}
+
+fn load_each_sync_from<A, T>(archives: &A, asset_ids: &[asset::Id]) -> Loaded<T>
+where
+	A: Archives,
+	T: asset::Asset + 'static,
+{
+	let requested = requested_orders(asset_ids);
+	let mut assets = Vec::with_capacity(asset_ids.len());
+	match archives.names() {
+		Ok(names) => {
+			for name in names.into_iter() {
+				assets.extend(load_group::<A, T>(archives, &name, &requested));
+			}
+		}
+		Err(err) => log::error!(target: LOG, "Failed to list archives: {:?}", err),
+	}
+	into_requested_order(asset_ids, assets)
+}
+
+async fn load_each_from<A, T>(archives: A, asset_ids: Vec<asset::Id>) -> anyhow::Result<Loaded<T>>
+where
+	A: Archives,
+	T: asset::Asset + Send + 'static,
+{
+	let requested = std::sync::Arc::new(requested_orders(&asset_ids));
+	let tasks = archives.names()?.into_iter().map(|name| {
+		let archives = archives.clone();
+		let requested = requested.clone();
+		tokio::task::spawn_blocking(move || load_group::<A, T>(&archives, &name, &requested))
+	});
+	let mut assets = Vec::with_capacity(asset_ids.len());
+	for group in futures::future::try_join_all(tasks).await?.into_iter() {
+		assets.extend(group);
+	}
+	Ok(into_requested_order(&asset_ids, assets))
+}
+
+#[cfg(test)]
+mod batch {
+	use super::*;
+	use std::{
+		io::Write,
+		sync::{Arc, Mutex},
+	};
+
+	#[derive(Debug, Clone, PartialEq)]
+	struct Text(String);
+
+	impl asset::Asset for Text {
+		fn asset_type() -> asset::TypeId {
+			"text"
+		}
+
+		fn decompile(bin: &Vec<u8>) -> anyhow::Result<AnyBox> {
+			Ok(Box::new(Self(String::from_utf8(bin.clone())?)))
+		}
+	}
+
+	/// Real pak archives, which count how many times each archive was opened.
+	#[derive(Clone)]
+	struct CountedPaks {
+		paks: Paks,
+		opened: Arc<Mutex<HashMap<String, usize>>>,
+	}
+
+	impl Archives for CountedPaks {
+		type Archive = Pak;
+
+		fn names(&self) -> anyhow::Result<Vec<String>> {
+			self.paks.names()
+		}
+
+		fn open(&self, name: &str) -> anyhow::Result<Self::Archive> {
+			*self
+				.opened
+				.lock()
+				.unwrap()
+				.entry(name.to_owned())
+				.or_default() += 1;
+			self.paks.open(name)
+		}
+
+		fn ids(&self, archive: &Self::Archive) -> Vec<asset::Id> {
+			self.paks.ids(archive)
+		}
+
+		fn read<T>(&self, archive: &mut Self::Archive, id: &asset::Id) -> anyhow::Result<Box<T>>
+		where
+			T: asset::Asset + 'static,
+		{
+			self.paks.read::<T>(archive, id)
+		}
+	}
+
+	impl CountedPaks {
+		/// Writes a pak for each module (holding an entry per path, whose content is the id of the asset).
+		fn write(modules: &[(&str, &[&str])]) -> anyhow::Result<Self> {
+			let mut directory = std::env::temp_dir();
+			directory.push(format!("asset_batch_{}", uuid::Uuid::new_v4()));
+			std::fs::create_dir_all(&directory)?;
+			for (module, paths) in modules.iter() {
+				let file = std::fs::File::create(directory.join(format!("{}.pak", module)))?;
+				let mut zip = zip::ZipWriter::new(file);
+				for path in paths.iter() {
+					zip.start_file(
+						format!("{}.asset", path),
+						zip::write::FileOptions::default(),
+					)?;
+					zip.write_all(format!("{}:{}", module, path).as_bytes())?;
+				}
+				zip.finish()?;
+			}
+			Ok(Self {
+				paks: Paks::new(directory),
+				opened: Arc::new(Mutex::new(HashMap::new())),
+			})
+		}
+
+		fn opened(&self) -> HashMap<String, usize> {
+			self.opened.lock().unwrap().clone()
+		}
+	}
+
+	impl Drop for CountedPaks {
+		fn drop(&mut self) {
+			if Arc::strong_count(&self.opened) == 1 {
+				let _ = std::fs::remove_dir_all(&self.paks.directory);
+			}
+		}
+	}
+
+	fn create_paks() -> anyhow::Result<CountedPaks> {
+		CountedPaks::write(&[
+			("vanilla", &["blocks/stone", "blocks/dirt", "blocks/grass"]),
+			("crystal-sphinx", &["blocks/debug"]),
+			("unrequested", &["blocks/glass"]),
+		])
+	}
+
+	fn requested_ids() -> Vec<asset::Id> {
+		vec![
+			asset::Id::new("vanilla", "blocks/stone"),
+			asset::Id::new("crystal-sphinx", "blocks/debug"),
+			asset::Id::new("vanilla", "blocks/dirt"),
+			asset::Id::new("vanilla", "blocks/grass"),
+		]
+	}
+
+	fn expected_assets() -> Vec<Text> {
+		requested_ids()
+			.into_iter()
+			.map(|id| Text(id.to_string()))
+			.collect()
+	}
+
+	fn expected_opens() -> HashMap<String, usize> {
+		vec![
+			("vanilla".to_owned(), 1),
+			("crystal-sphinx".to_owned(), 1),
+			("unrequested".to_owned(), 1),
+		]
+		.into_iter()
+		.collect()
+	}
+
+	#[test]
+	fn each_archive_is_opened_once() -> anyhow::Result<()> {
+		let paks = create_paks()?;
+		let assets = all_loaded(load_each_sync_from::<_, Text>(&paks, &requested_ids()))?;
+		let assets = assets
+			.into_iter()
+			.map(|(_, asset)| *asset)
+			.collect::<Vec<_>>();
+		assert_eq!(assets, expected_assets());
+		assert_eq!(paks.opened(), expected_opens());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn async_batch_opens_each_archive_once() -> anyhow::Result<()> {
+		let paks = create_paks()?;
+		let assets = all_loaded(load_each_from::<_, Text>(paks.clone(), requested_ids()).await?)?;
+		let assets = assets
+			.into_iter()
+			.map(|(_, asset)| *asset)
+			.collect::<Vec<_>>();
+		assert_eq!(assets, expected_assets());
+		assert_eq!(paks.opened(), expected_opens());
+		Ok(())
+	}
+
+	#[test]
+	fn missing_asset_fails_the_batch() -> anyhow::Result<()> {
+		let paks = create_paks()?;
+		let mut asset_ids = requested_ids();
+		asset_ids.push(asset::Id::new("vanilla", "blocks/missing"));
+		let assets = load_each_sync_from::<_, Text>(&paks, &asset_ids);
+		assert_eq!(assets.iter().filter(|(_, asset)| asset.is_err()).count(), 1);
+		assert!(assets.last().unwrap().1.is_err());
+		assert!(all_loaded(assets).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,357 @@
+//! Per-target log levels which can be changed while the application is running,
+//! so a single subsystem (e.g. `subsystem:replicator`) can be debugged without recompiling or drowning in noise.
+//!
+//! The logger which writes records must be [`installed`](install) through this module,
+//! which wraps it in a [`Filtered`] logger so the levels set here are checked before each record is written.
+//! The game's [`Runtime`](crate::Runtime) installs a [`Writer`] this way when its log file is set up.
+//! Levels are set with the `log <target> <level>` command,
+//! or at launch with `-log_filter=<target>=<level>[,<target>=<level>...]`.
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+	collections::{HashMap, HashSet},
+	io::Write,
+	mem::MaybeUninit,
+	path::Path,
+	str::FromStr,
+	sync::{Arc, Mutex, Once, RwLock},
+};
+
+static LOG: &'static str = "log-filter";
+
+/// The most targets which are remembered as having been logged to.
+/// Some targets are created per connection (e.g. `relevancy[<address>]`), so they are not remembered forever.
+/// Once this many are remembered, new targets are not, and setting their level warns that they are unknown.
+static MAX_SEEN_TARGETS: usize = 1024;
+
+/// The levels set for specific log targets.
+/// Targets without a level are not filtered (beyond whatever filtering the engine's logger does).
+#[derive(Default)]
+pub struct Filters {
+	levels: HashMap<String, LevelFilter>,
+	/// Every target a record has been logged to, so setting the level of a target which is never used can be reported.
+	seen_targets: HashSet<String>,
+}
+
+pub type ArcLockFilters = Arc<RwLock<Filters>>;
+
+impl Filters {
+	/// Parses the filters from the value of the `-log_filter` launch argument (`target=level,target=level`).
+	pub fn parse(value: &str) -> Result<Self, Error> {
+		let mut filters = Self::default();
+		for entry in value.split(',').filter(|entry| !entry.is_empty()) {
+			let (target, level) = entry
+				.split_once('=')
+				.ok_or_else(|| Error::InvalidEntry(entry.to_owned()))?;
+			filters
+				.levels
+				.insert(target.to_owned(), parse_level(level)?);
+		}
+		Ok(filters)
+	}
+
+	/// Sets the most verbose level which is logged for a target (and any targets it prefixes, e.g. `relevancy[...]`).
+	/// Returns false if nothing has logged to the target yet.
+	/// The level is still set, because the target may be used later (or the name may be misspelled).
+	pub fn set(&mut self, target: &str, level: LevelFilter) -> bool {
+		self.levels.insert(target.to_owned(), level);
+		self.is_known(target)
+	}
+
+	/// Removes the level of a target, so it is no longer filtered.
+	/// Returns false if the target did not have a level.
+	pub fn reset(&mut self, target: &str) -> bool {
+		self.levels.remove(target).is_some()
+	}
+
+	pub fn levels(&self) -> &HashMap<String, LevelFilter> {
+		&self.levels
+	}
+
+	fn is_known(&self, target: &str) -> bool {
+		self.seen_targets
+			.iter()
+			.any(|seen| seen.starts_with(target))
+	}
+
+	/// Returns the level set for the target, or for the longest target which prefixes it.
+	fn level_of(&self, target: &str) -> Option<LevelFilter> {
+		self.levels
+			.iter()
+			.filter(|(filter_target, _)| target.starts_with(filter_target.as_str()))
+			.max_by_key(|(filter_target, _)| filter_target.len())
+			.map(|(_, level)| *level)
+	}
+
+	pub fn is_enabled(&self, metadata: &Metadata) -> bool {
+		match self.level_of(metadata.target()) {
+			Some(level) => metadata.level() <= level,
+			None => true,
+		}
+	}
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, Error> {
+	LevelFilter::from_str(level).map_err(|_| Error::InvalidLevel(level.to_owned()))
+}
+
+/// Parses the filters from the `-log_filter` launch argument, if it was provided.
+fn launch_filters() -> Result<Filters, Error> {
+	let arg = std::env::args().find_map(|arg| {
+		arg.strip_prefix("-log_filter=")
+			.map(|value| value.to_owned())
+	});
+	match arg {
+		Some(value) => Filters::parse(&value),
+		None => Ok(Filters::default()),
+	}
+}
+
+/// Returns the filters used by the application's logger.
+/// The first time this is called, the filters are read from the `-log_filter` launch argument
+/// (an invalid argument is reported when the logger is [`installed`](install)).
+pub fn filters() -> &'static ArcLockFilters {
+	static mut INSTANCE: (MaybeUninit<ArcLockFilters>, Once) = (MaybeUninit::uninit(), Once::new());
+	unsafe {
+		INSTANCE.1.call_once(|| {
+			let filters = launch_filters().unwrap_or_default();
+			INSTANCE
+				.0
+				.as_mut_ptr()
+				.write(Arc::new(RwLock::new(filters)));
+		});
+		&*INSTANCE.0.as_ptr()
+	}
+}
+
+/// Sets the global logger to `inner`, wrapped in a [`Filtered`] logger which uses the application's [`filters`].
+/// Must be called once at startup, instead of setting `inner` as the global logger directly.
+pub fn install<L>(inner: L, max_level: LevelFilter) -> Result<(), log::SetLoggerError>
+where
+	L: Log + 'static,
+{
+	log::set_boxed_logger(Box::new(Filtered::new(inner)))?;
+	log::set_max_level(max_level);
+	// Reported once there is a logger to report it to.
+	if let Err(err) = launch_filters() {
+		log::warn!(target: LOG, "Ignoring -log_filter: {}", err);
+	}
+	Ok(())
+}
+
+/// Sets the level of a target in the application's [`filters`], warning if nothing has logged to the target yet.
+pub fn set(target: &str, level: &str) -> Result<LevelFilter, Error> {
+	let level = parse_level(level)?;
+	let is_known = filters().write().unwrap().set(target, level);
+	// Logged after the filters are unlocked, because the logger reads them.
+	if !is_known {
+		log::warn!(
+			target: LOG,
+			"Nothing has been logged to \"{}\" yet, its level will apply if it is used",
+			target
+		);
+	}
+	Ok(level)
+}
+
+/// Removes the level of a target in the application's [`filters`], returning false if it did not have one.
+pub fn reset(target: &str) -> bool {
+	filters().write().unwrap().reset(target)
+}
+
+/// Wraps a logger, only passing it the records which are [`enabled`](Filters::is_enabled) by the filters.
+pub struct Filtered<L> {
+	inner: L,
+	filters: ArcLockFilters,
+}
+
+impl<L> Filtered<L> {
+	/// Filters the logger using the application's [`filters`].
+	pub fn new(inner: L) -> Self {
+		Self::with_filters(inner, filters().clone())
+	}
+
+	pub fn with_filters(inner: L, filters: ArcLockFilters) -> Self {
+		Self { inner, filters }
+	}
+}
+
+impl<L> Log for Filtered<L>
+where
+	L: Log,
+{
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		self.filters.read().unwrap().is_enabled(metadata) && self.inner.enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		let is_enabled = {
+			let target = record.target();
+			let filters = self.filters.read().unwrap();
+			let is_new_target = !filters.seen_targets.contains(target);
+			let is_enabled = filters.is_enabled(record.metadata());
+			drop(filters);
+			if is_new_target {
+				let mut filters = self.filters.write().unwrap();
+				if filters.seen_targets.len() < MAX_SEEN_TARGETS {
+					filters.seen_targets.insert(target.to_owned());
+				}
+			}
+			is_enabled
+		};
+		if is_enabled {
+			self.inner.log(record);
+		}
+	}
+
+	fn flush(&self) {
+		self.inner.flush();
+	}
+}
+
+/// Writes every record it is given to a log file, and records of [`Level::Info`] or more severe to the terminal.
+pub struct Writer {
+	file: Mutex<std::fs::File>,
+}
+
+impl Writer {
+	pub fn create(path: &Path) -> std::io::Result<Self> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		let file = Mutex::new(std::fs::File::create(path)?);
+		Ok(Self { file })
+	}
+}
+
+impl Log for Writer {
+	fn enabled(&self, _metadata: &Metadata) -> bool {
+		true
+	}
+
+	fn log(&self, record: &Record) {
+		let line = format!(
+			"{} {:<5} [{}] {}",
+			chrono::Local::now().format("%H:%M:%S%.3f"),
+			record.level(),
+			record.target(),
+			record.args()
+		);
+		if record.level() <= Level::Info {
+			println!("{}", line);
+		}
+		if let Ok(mut file) = self.file.lock() {
+			let _ = writeln!(file, "{}", line);
+		}
+	}
+
+	fn flush(&self) {
+		if let Ok(mut file) = self.file.lock() {
+			let _ = file.flush();
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("\"{0}\" is not a log level, expected one of off, error, warn, info, debug, or trace")]
+	InvalidLevel(String),
+	#[error("\"{0}\" is not a valid filter, expected <target>=<level>")]
+	InvalidEntry(String),
+}
+
+#[cfg(test)]
+mod log_filter {
+	use super::*;
+	use log::Level;
+	use std::sync::Mutex;
+
+	/// Records the target and level of every record it is given.
+	#[derive(Default)]
+	struct Capture(Mutex<Vec<(String, Level)>>);
+
+	impl Log for Capture {
+		fn enabled(&self, _metadata: &Metadata) -> bool {
+			true
+		}
+
+		fn log(&self, record: &Record) {
+			let entry = (record.target().to_owned(), record.level());
+			self.0.lock().unwrap().push(entry);
+		}
+
+		fn flush(&self) {}
+	}
+
+	fn log_to(logger: &dyn Log, target: &str, level: Level) {
+		logger.log(
+			&Record::builder()
+				.target(target)
+				.level(level)
+				.args(format_args!("message"))
+				.build(),
+		);
+	}
+
+	#[test]
+	fn level_only_filters_its_target() {
+		let filters = Arc::new(RwLock::new(Filters::default()));
+		let logger = Filtered::with_filters(Capture::default(), filters.clone());
+		log_to(&logger, "subsystem:replicator", Level::Debug);
+		let is_known = filters
+			.write()
+			.unwrap()
+			.set("subsystem:replicator", LevelFilter::Warn);
+		assert!(is_known);
+		logger.inner.0.lock().unwrap().clear();
+
+		log_to(&logger, "subsystem:replicator", Level::Info);
+		log_to(&logger, "subsystem:replicator", Level::Error);
+		log_to(&logger, "chunk-loading", Level::Info);
+		log_to(&logger, "chunk-loading", Level::Debug);
+
+		let logged = logger.inner.0.lock().unwrap().clone();
+		assert_eq!(
+			logged,
+			vec![
+				("subsystem:replicator".to_owned(), Level::Error),
+				("chunk-loading".to_owned(), Level::Info),
+				("chunk-loading".to_owned(), Level::Debug),
+			]
+		);
+	}
+
+	#[test]
+	fn longest_prefix_wins() {
+		let filters = Filters::parse("relevancy=error,relevancy[127.0.0.1:25565]=trace").unwrap();
+		assert_eq!(
+			filters.level_of("relevancy[127.0.0.1:25565]"),
+			Some(LevelFilter::Trace)
+		);
+		assert_eq!(
+			filters.level_of("relevancy[127.0.0.1:25566]"),
+			Some(LevelFilter::Error)
+		);
+		assert_eq!(filters.level_of("render-voxel"), None);
+	}
+
+	#[test]
+	fn seen_targets_are_capped() {
+		let filters = Arc::new(RwLock::new(Filters::default()));
+		let logger = Filtered::with_filters(Capture::default(), filters.clone());
+		for connection in 0..MAX_SEEN_TARGETS + 10 {
+			log_to(&logger, &format!("relevancy[{}]", connection), Level::Info);
+		}
+		assert_eq!(filters.read().unwrap().seen_targets.len(), MAX_SEEN_TARGETS);
+		// Records to targets which are not remembered are still logged.
+		assert_eq!(logger.inner.0.lock().unwrap().len(), MAX_SEEN_TARGETS + 10);
+	}
+
+	#[test]
+	fn unknown_target_is_allowed() {
+		let mut filters = Filters::default();
+		assert!(!filters.set("not-a-target", LevelFilter::Off));
+		assert_eq!(filters.level_of("not-a-target"), Some(LevelFilter::Off));
+		assert!(Filters::parse("render-voxel=loud").is_err());
+		assert!(Filters::parse("render-voxel").is_err());
+	}
+}
@@ -6,14 +6,25 @@ pub use broadcast::*;
 mod close_code;
 pub use close_code::*;
 
+mod config;
+pub use config::*;
+
 pub mod connection;
 
 pub mod handshake;
 
+pub mod heartbeat;
+
 pub mod client_joined;
 
+pub mod clock;
+
+pub mod client_left;
+
 pub mod move_player;
 
+pub mod world_ready;
+
 mod storage;
 pub use storage::*;
 
@@ -0,0 +1,151 @@
+use crate::common::{account, network::Storage};
+use anyhow::Result;
+use socknet::{
+	connection::{self, Connection},
+	stream,
+};
+use std::sync::{Arc, RwLock, Weak};
+
+pub struct Identifier(Arc<AppContext>);
+impl Identifier {
+	pub fn new(context: Arc<AppContext>) -> Self {
+		Self(context)
+	}
+}
+impl stream::Identifier for Identifier {
+	type SendBuilder = AppContext;
+	type RecvBuilder = AppContext;
+	fn unique_id() -> &'static str {
+		"client_left"
+	}
+	fn send_builder(&self) -> &Arc<Self::SendBuilder> {
+		&self.0
+	}
+	fn recv_builder(&self) -> &Arc<Self::RecvBuilder> {
+		&self.0
+	}
+}
+
+#[derive(Default)]
+pub struct AppContext {
+	/// The network storage, where the client records the players who left (see [`departures`](crate::client::network::Storage::departures)).
+	pub storage: Weak<RwLock<Storage>>,
+}
+impl stream::send::AppContext for AppContext {
+	type Opener = stream::uni::Opener;
+}
+impl stream::recv::AppContext for AppContext {
+	type Extractor = stream::uni::Extractor;
+	type Receiver = Receiver;
+}
+impl AppContext {
+	fn record_departure(&self, account_id: account::Id) -> Result<()> {
+		use crate::common::network::Error::{
+			FailedToReadStorage, FailedToWriteClient, InvalidClient, InvalidStorage,
+		};
+		let arc_storage = self.storage.upgrade().ok_or(InvalidStorage)?;
+		let storage = arc_storage.read().map_err(|_| FailedToReadStorage)?;
+		let arc = storage.client().as_ref().ok_or(InvalidClient)?;
+		let mut client = arc.write().map_err(|_| FailedToWriteClient)?;
+		client.push_departure(account_id);
+		Ok(())
+	}
+}
+
+pub struct Sender {
+	#[allow(dead_code)]
+	context: Arc<AppContext>,
+	#[allow(dead_code)]
+	connection: Arc<Connection>,
+	send: stream::kind::send::Ongoing,
+}
+impl From<stream::send::Context<AppContext>> for Sender {
+	fn from(context: stream::send::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			send: context.stream,
+		}
+	}
+}
+impl stream::handler::Initiator for Sender {
+	type Identifier = Identifier;
+}
+impl Sender {
+	/// Notifies the client that the player with the account has disconnected from the server.
+	pub async fn send(mut self, account_id: account::Id) -> Result<()> {
+		use stream::kind::{Send, Write};
+		self.send.write(&account_id).await?;
+		self.send.finish().await?;
+		Ok(())
+	}
+}
+
+pub struct Receiver {
+	context: Arc<AppContext>,
+	connection: Arc<Connection>,
+	recv: stream::kind::recv::Ongoing,
+}
+impl From<stream::recv::Context<AppContext>> for Receiver {
+	fn from(context: stream::recv::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			recv: context.stream,
+		}
+	}
+}
+impl stream::handler::Receiver for Receiver {
+	type Identifier = Identifier;
+	fn receive(mut self) {
+		use connection::Active;
+		let log = format!(
+			"{}[{}]",
+			<Identifier as stream::Identifier>::unique_id(),
+			self.connection.remote_address()
+		);
+		self.connection.clone().spawn(log.clone(), async move {
+			use stream::kind::Read;
+			let account_id = self.recv.read::<account::Id>().await?;
+			log::info!(target: &log, "ClientLeft({})", account_id);
+			self.context.record_departure(account_id)?;
+			Ok(())
+		});
+	}
+}
+
+#[cfg(test)]
+mod departures {
+	use super::*;
+	use crate::client::network::MAX_DEPARTURES;
+
+	#[test]
+	fn client_records_departed_players() {
+		let storage = Arc::new(RwLock::new(Storage::default()));
+		storage.write().unwrap().set_client(Default::default());
+		let context = AppContext {
+			storage: Arc::downgrade(&storage),
+		};
+		for i in 0..=MAX_DEPARTURES {
+			context.record_departure(format!("player-{}", i)).unwrap();
+		}
+		let storage = storage.read().unwrap();
+		let client = storage.client().as_ref().unwrap().read().unwrap();
+		// The oldest departure is forgotten once there are too many.
+		assert_eq!(client.departures().len(), MAX_DEPARTURES);
+		assert_eq!(client.departures().front().unwrap(), "player-1");
+		assert_eq!(
+			client.departures().back().unwrap(),
+			&format!("player-{}", MAX_DEPARTURES)
+		);
+	}
+
+	#[test]
+	fn servers_without_a_client_do_not_record() {
+		let storage = Arc::new(RwLock::new(Storage::default()));
+		let context = AppContext {
+			storage: Arc::downgrade(&storage),
+		};
+		assert!(context.record_departure("player".to_owned()).is_err());
+	}
+}
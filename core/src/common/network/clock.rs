@@ -0,0 +1,220 @@
+//! Periodic datagrams from the server to each client with the server's [`time of day`](crate::common::world::clock),
+//! which the client interpolates between so the sky changes smoothly.
+//!
+//! Clients which connect are sent the time as soon as the server sees their connection,
+//! so they don't start at the wrong time of day while waiting for the next periodic sample.
+
+use crate::common::{
+	network::{connection, Storage},
+	world::clock::{Clock, Interpolated, Sample},
+};
+use anyhow::Result;
+use engine::EngineSystem;
+use socknet::{
+	connection::{Active, Connection},
+	stream::{self, kind},
+};
+use std::{
+	collections::HashSet,
+	net::SocketAddr,
+	sync::{Arc, RwLock, Weak},
+	time::{Duration, Instant},
+};
+
+static LOG: &'static str = "clock";
+
+/// How often the server sends the time to every connection.
+pub static INTERVAL: Duration = Duration::from_secs(2);
+
+/// Identifies the clock stream, which is only opened by the server.
+pub struct Identifier(Arc<AppContext>);
+
+impl Identifier {
+	pub fn new(storage: Weak<RwLock<Storage>>) -> Self {
+		Self(Arc::new(AppContext { storage }))
+	}
+}
+
+impl stream::Identifier for Identifier {
+	type SendBuilder = AppContext;
+	type RecvBuilder = AppContext;
+	fn unique_id() -> &'static str {
+		"clock"
+	}
+	fn send_builder(&self) -> &Arc<Self::SendBuilder> {
+		&self.0
+	}
+	fn recv_builder(&self) -> &Arc<Self::RecvBuilder> {
+		&self.0
+	}
+}
+
+pub struct AppContext {
+	/// The network storage, where the client records its estimate of the server's clock.
+	storage: Weak<RwLock<Storage>>,
+}
+
+impl stream::send::AppContext for AppContext {
+	type Opener = stream::datagram::Opener;
+}
+
+impl stream::recv::AppContext for AppContext {
+	type Extractor = stream::datagram::Extractor;
+	type Receiver = Receiver;
+}
+
+impl AppContext {
+	fn receive_sample(&self, sample: Sample) -> Result<()> {
+		use crate::common::network::Error::{
+			FailedToReadStorage, FailedToWriteClient, InvalidClient, InvalidStorage,
+		};
+		let arc_storage = self.storage.upgrade().ok_or(InvalidStorage)?;
+		let storage = arc_storage.read().map_err(|_| FailedToReadStorage)?;
+		let arc = storage.client().as_ref().ok_or(InvalidClient)?;
+		let mut client = arc.write().map_err(|_| FailedToWriteClient)?;
+		let now = Instant::now();
+		match client.clock_mut() {
+			Some(clock) => clock.receive(sample, now),
+			None => client.set_clock(Some(Interpolated::new(sample, now))),
+		}
+		Ok(())
+	}
+}
+
+pub struct Sender {
+	#[allow(dead_code)]
+	context: Arc<AppContext>,
+	#[allow(dead_code)]
+	connection: Arc<Connection>,
+	send: kind::send::Datagram,
+}
+
+impl From<stream::send::Context<AppContext>> for Sender {
+	fn from(context: stream::send::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			send: context.stream,
+		}
+	}
+}
+
+impl stream::handler::Initiator for Sender {
+	type Identifier = Identifier;
+}
+
+impl Sender {
+	async fn send(mut self, sample: Sample) -> Result<()> {
+		use stream::kind::{Send, Write};
+		self.send.write(&sample).await?;
+		self.send.finish().await?;
+		Ok(())
+	}
+
+	fn spawn(connection: Weak<Connection>, sample: Sample) -> Result<()> {
+		let arc = Connection::upgrade(&connection)?;
+		let log = <Identifier as stream::Identifier>::log_category("send", &arc);
+		arc.spawn(log, async move {
+			use stream::handler::Initiator;
+			Sender::open(&connection)?.await?.send(sample).await
+		});
+		Ok(())
+	}
+}
+
+pub struct Receiver {
+	context: Arc<AppContext>,
+	connection: Arc<Connection>,
+	recv: kind::recv::Datagram,
+}
+
+impl From<stream::recv::Context<AppContext>> for Receiver {
+	fn from(context: stream::recv::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			recv: context.stream,
+		}
+	}
+}
+
+impl stream::handler::Receiver for Receiver {
+	type Identifier = Identifier;
+	fn receive(mut self) {
+		let log = <Identifier as stream::Identifier>::log_category("recv", &self.connection);
+		self.connection.clone().spawn(log, async move {
+			use stream::kind::Read;
+			let sample = self.recv.read::<Sample>().await?;
+			self.context.receive_sample(sample)
+		});
+	}
+}
+
+/// Server system which sends the time of day to every connection each [`INTERVAL`] (or as soon as it is set),
+/// and to new connections as soon as they are seen.
+pub struct ReplicateClock {
+	clock: Weak<RwLock<Clock>>,
+	connection_list: Weak<RwLock<connection::List>>,
+	ticks_per_second: u32,
+	since_replicated: Duration,
+	/// The [`revision`](Clock::revision) of the clock when it was last sent.
+	revision: u32,
+	/// The connections which have been sent the time at least once.
+	synced: HashSet<SocketAddr>,
+}
+
+impl ReplicateClock {
+	pub fn new(
+		clock: &Arc<RwLock<Clock>>,
+		connection_list: &Arc<RwLock<connection::List>>,
+		ticks_per_second: u32,
+	) -> Self {
+		Self {
+			clock: Arc::downgrade(&clock),
+			connection_list: Arc::downgrade(&connection_list),
+			ticks_per_second,
+			since_replicated: Duration::ZERO,
+			revision: 0,
+			synced: HashSet::new(),
+		}
+	}
+}
+
+impl EngineSystem for ReplicateClock {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!("subsystem:replicate-clock");
+		let (arc_clock, arc_list) = match (self.clock.upgrade(), self.connection_list.upgrade()) {
+			(Some(arc_clock), Some(arc_list)) => (arc_clock, arc_list),
+			_ => return,
+		};
+		let (sample, revision) = {
+			let clock = arc_clock.read().unwrap();
+			(clock.sample(self.ticks_per_second), clock.revision())
+		};
+
+		self.since_replicated += delta_time;
+		let is_periodic = self.since_replicated >= INTERVAL || revision != self.revision;
+		if is_periodic {
+			self.since_replicated = Duration::ZERO;
+			self.revision = revision;
+		}
+
+		let connections = arc_list.read().unwrap().all().clone();
+		// Connections which have dropped are synced again if they reconnect.
+		self.synced
+			.retain(|address| connections.contains_key(address));
+		for (address, weak_connection) in connections.into_iter() {
+			if !is_periodic && self.synced.contains(&address) {
+				continue;
+			}
+			match Sender::spawn(weak_connection, sample) {
+				Ok(_) => {
+					self.synced.insert(address);
+				}
+				Err(err) => {
+					log::warn!(target: LOG, "Failed to send time to {}: {:?}", address, err);
+				}
+			}
+		}
+	}
+}
@@ -1,8 +1,13 @@
 #[repr(u32)] // specifically a u32 so it fits in `socknet::Connection::close()`.
 pub enum CloseCode {
 	/// Error code for clients which failed authentication.
-	/// Reason:
-	/// Ø => token failed verification
-	/// \[0u8\] => there was an error while processing the stream
+	/// Reason: the utf8 [`user message`](crate::common::network::handshake::HandshakeError::user_message)
+	/// of why the handshake failed (which never reveals if the account has joined before)
 	FailedAuthentication = 1,
+	/// Error code for clients which were rejected because the server has no free player slots.
+	/// Reason: the utf8 message of the [`capacity error`](crate::server::capacity::Error) (e.g. "server full")
+	ServerFull = 2,
+	/// Error code for clients which gave up waiting for the server to send the world around them.
+	/// Reason: the utf8 message [`WORLD_NOT_READY`](crate::common::network::world_ready::WORLD_NOT_READY)
+	WorldNotReady = 3,
 }
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// The port servers listen on (and clients bind to) if one is not provided.
+pub static DEFAULT_PORT: u16 = 25565;
+
+/// Where a network endpoint is bound.
+///
+/// Servers listen for connections at this address,
+/// and clients send from it when connecting to a server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+	pub bind_addr: IpAddr,
+	pub port: u16,
+}
+
+impl Default for NetworkConfig {
+	fn default() -> Self {
+		Self {
+			bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+			port: DEFAULT_PORT,
+		}
+	}
+}
+
+impl NetworkConfig {
+	/// Reads the config from the launch arguments,
+	/// where the bind address is provided by `-bind_addr=<ip>` and the port by `-<port_arg>=<port>`.
+	/// Values which are not provided use the defaults.
+	pub fn from_args(port_arg: &str) -> Self {
+		Self::from_arg_list(std::env::args(), port_arg)
+	}
+
+	fn from_arg_list(args: impl Iterator<Item = String>, port_arg: &str) -> Self {
+		let port_prefix = format!("-{}=", port_arg);
+		let mut bind_addr = None;
+		let mut port = None;
+		for arg in args {
+			if let Some(value) = arg.strip_prefix("-bind_addr=") {
+				bind_addr = bind_addr.or(value.parse::<IpAddr>().ok());
+			}
+			if let Some(value) = arg.strip_prefix(&port_prefix) {
+				port = port.or(value.parse::<u16>().ok());
+			}
+		}
+		let mut config = Self::default();
+		if let Some(bind_addr) = bind_addr {
+			config = config.with_bind_addr(bind_addr);
+		}
+		if let Some(port) = port {
+			config = config.with_port(port);
+		}
+		config
+	}
+
+	pub fn with_bind_addr(mut self, bind_addr: IpAddr) -> Self {
+		self.bind_addr = bind_addr;
+		self
+	}
+
+	pub fn with_port(mut self, port: u16) -> Self {
+		self.port = port;
+		self
+	}
+
+	pub fn address(&self) -> SocketAddr {
+		SocketAddr::new(self.bind_addr, self.port)
+	}
+
+	/// Describes why the endpoint could not be built at the address,
+	/// so a port which is already in use is reported clearly instead of as a generic io error.
+	pub fn bind_error(&self, err: impl Into<anyhow::Error>) -> anyhow::Error {
+		use std::io::ErrorKind;
+		let address = self.address();
+		match err.into().downcast::<std::io::Error>() {
+			Ok(err) if err.kind() == ErrorKind::AddrInUse => ConfigError::PortInUse(address).into(),
+			Ok(err) => ConfigError::FailedToBind(address, err).into(),
+			Err(err) => err,
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+	#[error("cannot bind to {0}, the port is already in use")]
+	PortInUse(SocketAddr),
+	#[error("failed to bind to {0}: {1}")]
+	FailedToBind(SocketAddr, std::io::Error),
+}
+
+#[cfg(test)]
+mod launch_args {
+	use super::*;
+	use std::net::UdpSocket;
+
+	fn args(list: &[&str]) -> impl Iterator<Item = String> {
+		list.iter()
+			.map(|arg| arg.to_string())
+			.collect::<Vec<_>>()
+			.into_iter()
+	}
+
+	#[test]
+	fn args_build_config() {
+		let config = NetworkConfig::from_arg_list(
+			args(&["-server", "-bind_addr=0.0.0.0", "-host_port=30000"]),
+			"host_port",
+		);
+		assert_eq!(
+			config,
+			NetworkConfig::default()
+				.with_bind_addr(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+				.with_port(30000)
+		);
+		assert_eq!(config.address(), "0.0.0.0:30000".parse().unwrap());
+	}
+
+	#[test]
+	fn missing_or_invalid_args_use_defaults() {
+		let config = NetworkConfig::from_arg_list(
+			args(&[
+				"-bind_addr=nowhere",
+				"-client_port=30000",
+				"-host_port=99999",
+			]),
+			"host_port",
+		);
+		assert_eq!(config, NetworkConfig::default());
+		assert_eq!(config.address().port(), DEFAULT_PORT);
+	}
+
+	#[test]
+	fn port_in_use_is_reported() {
+		let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let port = socket.local_addr().unwrap().port();
+		let config = NetworkConfig::default().with_port(port);
+		let err = UdpSocket::bind(config.address()).unwrap_err();
+		let err = config.bind_error(err);
+		assert!(matches!(
+			err.downcast_ref::<ConfigError>(),
+			Some(ConfigError::PortInUse(_))
+		));
+
+		// Errors which are not from binding the socket are passed through unchanged.
+		let err = config.bind_error(anyhow::anyhow!("invalid certificate"));
+		assert!(err.downcast_ref::<ConfigError>().is_none());
+	}
+}
@@ -1,3 +1,4 @@
+use crate::server::metrics::Metrics;
 use engine::channels::broadcast::{Bus, BusReader};
 use socknet::{
 	connection::{self, event, Active, Connection},
@@ -68,10 +69,12 @@ impl List {
 
 	pub fn insert(&mut self, address: SocketAddr, connection: Weak<Connection>) {
 		self.connections.insert(address, connection);
+		Metrics::get().set_active_connections(self.connections.len());
 	}
 
 	pub fn remove(&mut self, address: &SocketAddr) {
 		self.connections.remove(&address);
+		Metrics::get().set_active_connections(self.connections.len());
 	}
 
 	pub fn all(&self) -> &HashMap<SocketAddr, Weak<Connection>> {
@@ -1,6 +1,15 @@
 //! Stream initiated by clients to join the server.
 //!
 //! See [Identifier] for stream graph.
+//!
+//! The handshake establishes the identity of a connection: the server sends a random [`token`](TokenConfig)
+//! which the client signs with its account's private key, and the signature is verified against the public key the client sent.
+//! It does not establish any session keys.
+//! Every stream (including the handshake and all gameplay streams like replication) is sent over QUIC,
+//! whose TLS 1.3 session (configured in [`Storage::create_config`](crate::common::network::Storage::create_config))
+//! encrypts all packets with keys unique to the connection, so gameplay packets do not need a separate encryption layer.
+//! TLS does not identify either peer though: servers accept any client certificate, and clients skip verifying the server's,
+//! so the signed token exchange is the only proof of which account a connection belongs to.
 
 #[doc(hidden)]
 mod identifier;
@@ -10,3 +19,9 @@ pub use identifier::*;
 pub mod client;
 /// Context & Handler for the server/receiver.
 pub mod server;
+
+mod error;
+pub use error::*;
+
+mod token;
+pub use token::*;
@@ -1,3 +1,4 @@
+use super::HandshakeError as Error;
 use crate::app;
 use anyhow::Result;
 use socknet::{self, connection::Connection, stream};
@@ -52,7 +53,13 @@ impl Handshake {
 		use stream::Identifier;
 		let log = super::Identifier::log_category("client", &self.connection);
 		self.connection.clone().spawn(log.clone(), async move {
-			self.process(&log).await?;
+			if let Err(error) = self.process(&log).await {
+				// The server closes the connection with the reason the player could not join.
+				match super::failed_authentication_reason(&error) {
+					Some(reason) => log::error!(target: &log, "Kicked by the server: {}", reason),
+					None => return Err(error),
+				}
+			}
 			Ok(())
 		});
 	}
@@ -97,6 +104,16 @@ impl Handshake {
 			.await
 			.context("writing display name")?;
 
+		// Tell the server if we are joining as a player or only to observe.
+		let is_spectator = {
+			use crate::common::network::mode;
+			mode::get().contains(mode::Kind::Spectator)
+		};
+		self.send
+			.write(&is_spectator)
+			.await
+			.context("writing spectator flag")?;
+
 		// Step 3: Sign the random token & send it to the server.
 		let token = self.recv.read_bytes().await.context("reading token")?;
 		let signature = {
@@ -120,31 +137,22 @@ impl Handshake {
 		// Streams are going to be stopped regardless.
 		// If we have failed auth, the connection will also be closed.
 
-		// TODO: Server should send a "all data is ready" signal to tell the client
-		// that it is safe to enter the game, once relevant chunks and entities have been loaded.
-		// Must require:
-		// - player's entity and components have been replicated
-		// - some of the chunks in the immediate vicinity (so the entity doesn't fall through the world)
-
-		self.app_state()?.write().unwrap().transition_to(
-			match authenticated {
-				true => crate::app::state::State::InGame,
-				false => crate::app::state::State::MainMenu,
-			},
-			None,
-		);
+		let app_state = self.app_state()?;
+		match authenticated {
+			// The server will send a world ready signal once the chunks and entities
+			// around the player have been replicated, at which point it is safe to enter the game.
+			true => {
+				use crate::common::network::world_ready;
+				world_ready::wait_for_world(&app_state, &self.connection);
+			}
+			false => {
+				app_state
+					.write()
+					.unwrap()
+					.transition_to(crate::app::state::State::MainMenu, None);
+			}
+		}
 
 		Ok(())
 	}
 }
-
-#[derive(thiserror::Error, Debug)]
-enum Error {
-	#[error("Key rejected during parsing: {0}")]
-	KeyRejected(&'static str),
-	#[error("Failed to sign handshake token")]
-	FailedToSignToken,
-
-	#[error("Application state machine is invalid")]
-	InvalidAppState,
-}
@@ -0,0 +1,173 @@
+use crate::common::network::CloseCode;
+
+/// The message shown to a player for any failure which would reveal whether their account has joined before.
+pub static GENERIC_MESSAGE: &'static str = "Failed to authenticate with the server";
+
+/// The reasons either side of the handshake can fail.
+#[derive(thiserror::Error, Debug)]
+pub enum HandshakeError {
+	#[error("Key rejected during parsing: {0}")]
+	KeyRejected(&'static str),
+	#[error("Failed to sign handshake token")]
+	FailedToSignToken,
+	#[error("Application state machine is invalid")]
+	InvalidAppState,
+
+	#[error("failed to read user for id({0})")]
+	FailedToReadUser(String),
+	#[error("provided public key did not match previous login")]
+	InvalidPublicKey,
+	#[error("Entity World is invalid")]
+	InvalidEntityWorld,
+	#[error("signed token failed verification")]
+	FailedVerification,
+	/// Any other failure while processing the handshake (e.g. reading the stream or the server's storage).
+	#[error(transparent)]
+	Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for HandshakeError {
+	fn from(error: anyhow::Error) -> Self {
+		// Keep the specific reason if it was only given context on the way up.
+		match error.downcast::<Self>() {
+			Ok(error) => error,
+			Err(error) => Self::Internal(error),
+		}
+	}
+}
+
+impl HandshakeError {
+	/// Returns true if telling the player exactly why they failed would reveal
+	/// that an account with their id has (or has not) joined the server before.
+	pub fn reveals_account(&self) -> bool {
+		match self {
+			Self::FailedToReadUser(_) | Self::InvalidPublicKey => true,
+			Self::KeyRejected(_)
+			| Self::FailedToSignToken
+			| Self::InvalidAppState
+			| Self::InvalidEntityWorld
+			| Self::FailedVerification
+			| Self::Internal(_) => false,
+		}
+	}
+
+	/// The message which can be shown to the player (e.g. when they are kicked),
+	/// which is [`GENERIC_MESSAGE`] if the specific reason [`reveals the account`](Self::reveals_account).
+	pub fn user_message(&self) -> String {
+		match self {
+			Self::KeyRejected(reason) => format!("Your account key could not be read ({})", reason),
+			Self::FailedToSignToken => {
+				"Failed to sign the server's authentication token".to_owned()
+			}
+			Self::InvalidAppState => "The game closed while joining the server".to_owned(),
+			Self::InvalidEntityWorld => "The server failed to create your player".to_owned(),
+			Self::FailedVerification => {
+				"Your signature of the server's authentication token was invalid".to_owned()
+			}
+			Self::Internal(_) => "The server failed while you were joining".to_owned(),
+			Self::FailedToReadUser(_) | Self::InvalidPublicKey => GENERIC_MESSAGE.to_owned(),
+		}
+	}
+}
+
+/// Returns the reason the server gave for closing the connection, if `error` was caused by
+/// the server closing it with [`FailedAuthentication`](CloseCode::FailedAuthentication).
+pub fn failed_authentication_reason(error: &anyhow::Error) -> Option<String> {
+	use quinn::{ConnectionError, ReadError, ReadExactError, WriteError};
+	let code = quinn::VarInt::from_u32(CloseCode::FailedAuthentication as u32);
+	error.chain().find_map(|cause| {
+		let lost = match cause.downcast_ref::<ConnectionError>() {
+			Some(lost) => Some(lost),
+			None => match cause.downcast_ref::<ReadExactError>() {
+				Some(ReadExactError::ReadError(ReadError::ConnectionLost(lost))) => Some(lost),
+				_ => match cause.downcast_ref::<ReadError>() {
+					Some(ReadError::ConnectionLost(lost)) => Some(lost),
+					_ => match cause.downcast_ref::<WriteError>() {
+						Some(WriteError::ConnectionLost(lost)) => Some(lost),
+						_ => None,
+					},
+				},
+			},
+		};
+		match lost {
+			Some(ConnectionError::ApplicationClosed(close)) if close.error_code == code => {
+				Some(String::from_utf8_lossy(&close.reason).into_owned())
+			}
+			_ => None,
+		}
+	})
+}
+
+#[cfg(test)]
+mod handshake_error {
+	use super::*;
+
+	#[test]
+	fn account_failures_use_the_generic_message() {
+		let generic = vec![
+			HandshakeError::FailedToReadUser("abc".to_owned()),
+			HandshakeError::InvalidPublicKey,
+		];
+		for error in generic.into_iter() {
+			assert!(error.reveals_account());
+			assert_eq!(error.user_message(), GENERIC_MESSAGE);
+		}
+
+		let specific = vec![
+			HandshakeError::KeyRejected("InvalidEncoding"),
+			HandshakeError::FailedToSignToken,
+			HandshakeError::InvalidAppState,
+			HandshakeError::InvalidEntityWorld,
+			HandshakeError::FailedVerification,
+			HandshakeError::Internal(anyhow::anyhow!("reading token")),
+		];
+		for error in specific.into_iter() {
+			assert!(!error.reveals_account());
+			assert_ne!(error.user_message(), GENERIC_MESSAGE);
+		}
+		assert_eq!(
+			HandshakeError::KeyRejected("InvalidEncoding").user_message(),
+			"Your account key could not be read (InvalidEncoding)"
+		);
+	}
+
+	#[test]
+	fn context_keeps_the_reason() {
+		use anyhow::Context;
+		let result: Result<(), HandshakeError> = Err(HandshakeError::InvalidPublicKey);
+		let error = HandshakeError::from(result.context("public key validation").unwrap_err());
+		assert!(matches!(error, HandshakeError::InvalidPublicKey));
+		assert_eq!(error.user_message(), GENERIC_MESSAGE);
+
+		let error = HandshakeError::from(anyhow::anyhow!("reading display name"));
+		assert!(matches!(error, HandshakeError::Internal(_)));
+		assert!(!error.reveals_account());
+	}
+
+	#[test]
+	fn reads_the_reason_of_failed_authentication() {
+		use quinn::{ApplicationClose, ConnectionError, ReadError, ReadExactError, VarInt};
+		let closed = |code: CloseCode| {
+			ConnectionError::ApplicationClosed(ApplicationClose {
+				error_code: VarInt::from_u32(code as u32),
+				reason: GENERIC_MESSAGE.as_bytes().to_vec().into(),
+			})
+		};
+
+		let read = ReadExactError::ReadError(ReadError::ConnectionLost(closed(
+			CloseCode::FailedAuthentication,
+		)));
+		let error = anyhow::Error::new(read).context("reading token");
+		assert_eq!(
+			failed_authentication_reason(&error),
+			Some(GENERIC_MESSAGE.to_owned())
+		);
+
+		let error = anyhow::Error::new(closed(CloseCode::ServerFull));
+		assert_eq!(failed_authentication_reason(&error), None);
+		assert_eq!(
+			failed_authentication_reason(&anyhow::anyhow!("reading token")),
+			None
+		);
+	}
+}
@@ -35,12 +35,15 @@ use std::sync::Arc;
 /// 		Note over S: Trigger Client Authenticate Event
 /// 		Note over S: Create entity for client
 /// 		par Server to Incoming
-/// 			Note over C: Transition To InGame
+/// 			Note over C: Wait on loading screen
 /// 			S->>C: Client Joined
 /// 		and Server to Others
 /// 			S->>CAll: Client Joined
 /// 			Note over CAll: Stub
 /// 		end
+/// 		Note over S: Replicate nearby chunks & entities
+/// 		S->>C: World Ready
+/// 		Note over C: Transition To InGame
 /// 	else if failure
 /// 		S->>C: Connection Closed
 /// 		Note over C: Transition To MainMenu
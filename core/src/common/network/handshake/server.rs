@@ -1,10 +1,11 @@
+use super::HandshakeError as Error;
 use crate::{
 	common::{
 		account,
 		network::{client_joined, connection, mode, Broadcast, CloseCode, Storage},
 	},
 	entity,
-	server::network::Storage as ServerStorage,
+	server::{capacity, network::Storage as ServerStorage, world::spawn},
 };
 use anyhow::Result;
 use socknet::{self, connection::Connection, stream};
@@ -13,6 +14,8 @@ use std::sync::{Arc, RwLock, Weak};
 pub struct AppContext {
 	pub storage: Weak<RwLock<Storage>>,
 	pub entity_world: Weak<RwLock<entity::World>>,
+	/// How the token clients sign to authenticate is generated.
+	pub token: super::TokenConfig,
 }
 
 impl stream::recv::AppContext for AppContext {
@@ -59,12 +62,36 @@ impl Handshake {
 		Ok(storage.connection_list().clone())
 	}
 
-	fn entity_world(&self) -> Result<Arc<RwLock<entity::World>>> {
-		Ok(self
-			.context
+	/// Returns the reason the account cannot join if the server does not have a free slot for it,
+	/// counting every other connection (whether it has finished its handshake or not).
+	fn check_capacity(
+		&self,
+		account_id: &account::Id,
+	) -> Result<std::result::Result<(), capacity::Error>> {
+		use crate::common::network::Error::FailedToReadServer;
+		use socknet::connection::Active;
+		let connected = {
+			let arc_list = self.connection_list()?;
+			let list = arc_list
+				.read()
+				.map_err(|_| connection::Error::FailedToReadList)?;
+			list.all()
+				.iter()
+				.filter(|(address, _)| **address != self.connection.remote_address())
+				.filter_map(|(_, connection)| connection.upgrade())
+				.filter_map(|connection| connection.fingerprint().ok())
+				.collect::<Vec<_>>()
+		};
+		let server = self.server()?;
+		let server = server.read().map_err(|_| FailedToReadServer)?;
+		Ok(server.capacity().check(account_id, connected.iter()))
+	}
+
+	fn entity_world(&self) -> std::result::Result<Arc<RwLock<entity::World>>, Error> {
+		self.context
 			.entity_world
 			.upgrade()
-			.ok_or(Error::InvalidEntityWorld)?)
+			.ok_or(Error::InvalidEntityWorld)
 	}
 }
 
@@ -74,19 +101,22 @@ impl stream::handler::Receiver for Handshake {
 		use stream::Identifier;
 		let log = super::Identifier::log_category("server", &self.connection);
 		self.connection.clone().spawn(log.clone(), async move {
-			use anyhow::Context;
 			use stream::kind::{Recv, Send};
-			if let Err(error) = self
-				.process_server(&log)
-				.await
-				.context("Failed authentication")
-			{
+			let metrics = crate::server::metrics::Metrics::get();
+			metrics.begin_handshake();
+			let result = self.process_server(&log).await;
+			metrics.end_handshake();
+			if let Err(error) = result {
 				use socknet::connection::Active;
-				log::error!(target: &log, "{:?}", error);
-				self.recv.stop().await?;
-				self.send.finish().await?;
-				self.connection
-					.close(CloseCode::FailedAuthentication as u32, &vec![0u8]);
+				log::error!(target: &log, "Failed authentication: {:?}", error);
+				// The streams may already be closed (e.g. if the token failed verification),
+				// which must not stop the client from being told why they were kicked.
+				let _ = self.recv.stop().await;
+				let _ = self.send.finish().await;
+				self.connection.close(
+					CloseCode::FailedAuthentication as u32,
+					error.user_message().as_bytes(),
+				);
 			}
 			Ok(())
 		});
@@ -94,20 +124,41 @@ impl stream::handler::Receiver for Handshake {
 }
 
 impl Handshake {
-	async fn process_server(&mut self, log: &String) -> Result<()> {
+	async fn process_server(&mut self, log: &String) -> std::result::Result<(), Error> {
 		use crate::common::network::Error::{FailedToReadServer, FailedToWriteServer};
 		use account::key::{Key, PublicKey};
 		use anyhow::Context;
 		use socknet::connection::Active;
 		use stream::kind::{Read, Recv, Send, Write};
 
-		let account_id = self.connection.fingerprint()?;
+		let account_id = self
+			.connection
+			.fingerprint()
+			.context("reading account id")?;
 		log::info!(
 			target: &log,
 			"Received handshake from account({})",
 			account_id
 		);
 
+		// Step 0: Reject the connection before it takes up any resources if the server is full.
+		let capacity = self
+			.check_capacity(&account_id)
+			.context("checking capacity")?;
+		if let Err(error) = capacity {
+			log::info!(
+				target: &log,
+				"Rejected account({}): {}",
+				account_id,
+				error
+			);
+			self.recv.stop().await.context("stopping stream")?;
+			self.send.finish().await.context("finishing stream")?;
+			self.connection
+				.close(CloseCode::ServerFull as u32, error.to_string().as_bytes());
+			return Ok(());
+		}
+
 		// Step 1: Receive the client's public key
 		// (which is derived from there private_key and is different from the certificate)
 		let public_key = self.recv.read_bytes().await.context("reading public key")?;
@@ -144,11 +195,10 @@ impl Handshake {
 		if !is_new {
 			let user = arc_user
 				.read()
-				.map_err(|_| Error::FailedToReadUser(account_id.clone()))
-				.context("public key validation")?;
+				.map_err(|_| Error::FailedToReadUser(account_id.clone()))?;
 			if let Key::Public(account_key) = user.account().key() {
 				if public_key != *account_key {
-					return Err(Error::InvalidPublicKey)?;
+					return Err(Error::InvalidPublicKey);
 				}
 			} else {
 				unimplemented!();
@@ -162,19 +212,18 @@ impl Handshake {
 			.context("reading display name")?;
 		{
 			let mut user = arc_user.write().unwrap();
-			user.account_mut().set_display_name(display_name);
+			user.account_mut().set_display_name(display_name.clone());
 		}
 
+		let is_spectator = self
+			.recv
+			.read::<bool>()
+			.await
+			.context("reading spectator flag")?;
+
 		// Step 3: Generate a random token and send it to be signed by the client
-		let token = {
-			use rand::Rng;
-			let raw_token: String = rand::thread_rng()
-				.sample_iter(&rand::distributions::Alphanumeric)
-				.take(64)
-				.map(char::from)
-				.collect();
-			bincode::serialize(&raw_token)?
-		};
+		let token =
+			bincode::serialize(&self.context.token.generate()).context("serializing token")?;
 		self.send
 			.write_bytes(&token)
 			.await
@@ -185,46 +234,128 @@ impl Handshake {
 
 		let verified = {
 			use ring::signature::{self, UnparsedPublicKey};
-			let bytes = public_key.as_bytes()?;
+			let bytes = public_key.as_bytes().context("decoding public key")?;
 			let key = UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, &bytes);
 			key.verify(&token, &signed_token).is_ok()
 		};
 
-		self.send.write(&verified).await?;
+		self.send
+			.write(&verified)
+			.await
+			.context("sending verification")?;
 
-		self.recv.stop().await?;
-		self.send.finish().await?;
+		self.recv.stop().await.context("stopping stream")?;
+		self.send.finish().await.context("finishing stream")?;
 
 		if !verified {
-			log::info!(target: &log, "Failed authentication");
-			self.connection
-				.close(CloseCode::FailedAuthentication as u32, &vec![]);
-			return Ok(());
+			return Err(Error::FailedVerification);
 		}
 
 		log::info!(target: &log, "Passed authentication");
 
-		if is_new {
+		{
 			let server = self.server().context("fetching server data")?;
 			let mut server = server
 				.write()
 				.map_err(|_| FailedToWriteServer)
 				.context("adding user")?;
-			server.add_user(account_id.clone(), arc_user);
+			if is_new {
+				server.add_user(account_id.clone(), arc_user);
+			}
+			server.register_name(account_id.clone(), display_name);
 		}
 
 		// Broadcast authenticated event locally to initiate other objects (like replication streams)
 		let connection_list = self.connection_list()?;
 		connection_list
 			.write()
-			.map_err(|_| connection::Error::FailedToWriteList)?
+			.map_err(|_| connection::Error::FailedToWriteList)
+			.context("broadcasting authentication")?
 			.broadcast(connection::Event::Authenticated(
 				self.connection.remote_address(),
 				Arc::downgrade(&self.connection),
 			));
 
-		{
+		if is_spectator {
 			use entity::archetype;
+			let arc_world = self.entity_world()?;
+			let mut world = arc_world.write().unwrap();
+			log::debug!(
+				target: &log,
+				"Initializing spectator camera for account({})",
+				account_id
+			);
+			// Spectators are not given a player entity (or any entity owned by the connection),
+			// only a camera which is the source of the connection's chunk relevance.
+			world
+				.spawn(archetype::spectator::Server::new(self.connection.remote_address()).build());
+		} else {
+			use entity::{
+				archetype,
+				component::{
+					chunk::TicketOwner,
+					physics::linear::{AcknowledgedInput, Position},
+					OwnedByConnection, PersistentId, Registry,
+				},
+				PersistentIds,
+			};
+			let persistent_id = PersistentId::for_account(&account_id);
+			let (arc_ids, saved, arc_database, world_name, ticket_world) = {
+				let server = self.server()?;
+				let server = server
+					.read()
+					.map_err(|_| FailedToReadServer)
+					.context("loading the player")?;
+				let saved = match PersistentIds::read_saved(
+					&server.get_entities_dir_path(),
+					&persistent_id,
+				) {
+					Ok(saved) => saved,
+					Err(err) => {
+						log::warn!(
+							target: &log,
+							"Failed to load the saved entity of player({}), they will be respawned: {:?}",
+							account_id,
+							err
+						);
+						None
+					}
+				};
+				let world_name = server.world_of(&account_id).to_owned();
+				let arc_database = server
+					.world(&world_name)
+					.context("finding the player's world")?
+					.clone();
+				let ticket_world = server
+					.ticket_world(&world_name)
+					.context("finding the player's world")?;
+				(
+					server.persistent_ids().clone(),
+					saved,
+					arc_database,
+					world_name,
+					ticket_world,
+				)
+			};
+			// Players who have joined before continue from where they were saved.
+			// New players are placed on the surface of the column they would otherwise spawn in,
+			// so they never spawn inside (or far above) the terrain.
+			// Finding the surface waits for the chunks of the column to load, which must not block the async runtime.
+			let spawn_point = if saved.is_none() {
+				let around = Position::default()
+					.world_position()
+					.map(|v| v.floor() as i64);
+				let point = tokio::task::spawn_blocking(move || {
+					spawn::find_safe_spawn(&arc_database, around)
+				})
+				.await
+				.context("finding a spawn point")?
+				.context("finding a spawn point")?;
+				Some(point)
+			} else {
+				None
+			};
+
 			let arc_world = self.entity_world()?;
 			let mut world = arc_world.write().unwrap();
 			log::debug!(
@@ -235,14 +366,17 @@ impl Handshake {
 
 			// Build an entity for the player which is marked with
 			// the account id of the user and the ip address of the connection.
-			let mut builder = archetype::player::Server::new()
+			let mut player = archetype::player::Server::new()
 				.with_user_id(account_id.clone())
-				.with_address(self.connection.remote_address())
-				.build();
+				.with_address(self.connection.remote_address());
+			if let Some(spawn_point) = spawn_point {
+				player = player.with_spawn_point(spawn_point);
+			}
+			let mut builder = player.build();
 
 			// Integrated Client-Server needs to spawn client-only components
 			// if its the local player's entity.
-			if mode::get().contains(mode::Kind::Client) {
+			if mode::get().contains(mode::Kind::ListenServer) {
 				let client_reg = crate::client::account::Manager::read().unwrap();
 				let local_account = client_reg.active_account().unwrap();
 				// If the account ids match, then this entity is the local player's avatar
@@ -251,7 +385,30 @@ impl Handshake {
 				}
 			}
 
-			world.spawn(builder.build());
+			let mut persistent_ids = arc_ids.write().unwrap();
+			let (entity, _) = match saved {
+				Some(saved) => persistent_ids.load(&mut world, &Registry::read(), saved, builder),
+				None => persistent_ids.spawn(&mut world, builder),
+			}
+			.context("spawning the player's entity")?;
+			// A saved entity still has the connection (and the acknowledged input of the client)
+			// from when the player was last online.
+			let session = (
+				OwnedByConnection::new(self.connection.remote_address()),
+				AcknowledgedInput::default(),
+			);
+			world
+				.insert(entity, session)
+				.context("marking the player's connection")?;
+			// The chunks around the player are loaded in (and replicated from) the world they are in.
+			if let Ok(mut ticket_owner) = world.get::<&mut TicketOwner>(entity) {
+				ticket_owner.set_world(&world_name, ticket_world);
+			}
+		}
+
+		// Other clients are only told about players joining, spectators are not announced.
+		if is_spectator {
+			return Ok(());
 		}
 
 		Broadcast::<client_joined::Sender>::new(connection_list)
@@ -267,14 +424,3 @@ impl Handshake {
 		Ok(())
 	}
 }
-
-#[derive(thiserror::Error, Debug)]
-enum Error {
-	#[error("failed to read user for id({0})")]
-	FailedToReadUser(String),
-	#[error("provided public key did not match previous login")]
-	InvalidPublicKey,
-
-	#[error("Entity World is invalid")]
-	InvalidEntityWorld,
-}
@@ -0,0 +1,92 @@
+use rand::{rngs::OsRng, Rng};
+
+/// The number of characters in an authentication token, if a length is not provided.
+pub static DEFAULT_TOKEN_LENGTH: usize = 64;
+/// The fewest characters an authentication token can have and still be hard to guess.
+pub static MIN_TOKEN_LENGTH: usize = 32;
+
+/// How the server generates the random token which clients sign to prove they own their account's private key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenConfig {
+	length: usize,
+}
+
+impl Default for TokenConfig {
+	fn default() -> Self {
+		Self {
+			length: DEFAULT_TOKEN_LENGTH,
+		}
+	}
+}
+
+impl TokenConfig {
+	/// Reads the token length from the `-auth_token_length=<length>` launch argument,
+	/// using the default length if it is not provided.
+	pub fn from_args() -> Result<Self, Error> {
+		match crate::common::utility::get_named_arg("auth_token_length") {
+			Some(length) => Self::default().with_length(length as usize),
+			None => Ok(Self::default()),
+		}
+	}
+
+	/// Changes the length of generated tokens.
+	/// Lengths shorter than [`MIN_TOKEN_LENGTH`] are rejected.
+	pub fn with_length(mut self, length: usize) -> Result<Self, Error> {
+		if length < MIN_TOKEN_LENGTH {
+			return Err(Error::TooShort(length));
+		}
+		self.length = length;
+		Ok(self)
+	}
+
+	pub fn length(&self) -> usize {
+		self.length
+	}
+
+	/// Generates a new random alphanumeric token.
+	/// Tokens are drawn from the operating system's cryptographically secure random number generator,
+	/// rather than a user-space generator, because they are what authenticates a client.
+	pub fn generate(&self) -> String {
+		OsRng
+			.sample_iter(&rand::distributions::Alphanumeric)
+			.take(self.length)
+			.map(char::from)
+			.collect()
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error(
+		"authentication tokens must be at least {} characters, but {0} was provided",
+		MIN_TOKEN_LENGTH
+	)]
+	TooShort(usize),
+}
+
+#[cfg(test)]
+mod token_length {
+	use super::*;
+
+	#[test]
+	fn tokens_match_configured_length() {
+		let config = TokenConfig::default().with_length(40).unwrap();
+		let token = config.generate();
+		assert_eq!(token.len(), 40);
+		assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+		assert_ne!(token, config.generate());
+		assert_eq!(
+			TokenConfig::default().generate().len(),
+			DEFAULT_TOKEN_LENGTH
+		);
+	}
+
+	#[test]
+	fn short_lengths_are_rejected() {
+		assert!(matches!(
+			TokenConfig::default().with_length(MIN_TOKEN_LENGTH - 1),
+			Err(Error::TooShort(_))
+		));
+		assert!(TokenConfig::default().with_length(MIN_TOKEN_LENGTH).is_ok());
+	}
+}
@@ -0,0 +1,422 @@
+//! Periodic datagrams from the server to each client, which the client acknowledges,
+//! so the server can estimate the round-trip time & packet loss of each connection.
+//! Each heartbeat carries the server's latest estimate, which is how the client learns the quality of its connection.
+//!
+//! Heartbeats are datagrams (not streams) so that packets lost by the network are not retransmitted,
+//! and show up as unacknowledged heartbeats.
+
+use crate::common::network::{connection, Storage};
+use anyhow::Result;
+use engine::EngineSystem;
+use serde::{Deserialize, Serialize};
+use socknet::{
+	connection::{Active, Connection},
+	stream::{self, kind},
+};
+use std::{
+	collections::{HashMap, VecDeque},
+	net::SocketAddr,
+	sync::{Arc, Mutex, RwLock, Weak},
+	time::{Duration, Instant},
+};
+
+static LOG: &'static str = "heartbeat";
+
+/// How often the server sends a heartbeat to each connection.
+pub static INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the server waits for a heartbeat to be acknowledged before it is considered lost.
+pub static ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The number of most recent heartbeats that packet loss is estimated from.
+pub static LOSS_WINDOW: usize = 20;
+
+/// How much each round-trip sample moves the smoothed round-trip time (as in TCP's SRTT).
+static RTT_SMOOTHING: f64 = 0.125;
+
+/// The health of a connection, as estimated by the server.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Quality {
+	/// The client is integrated with the server (client-on-top-of-server), so packets are never lost or delayed.
+	Local,
+	Remote {
+		/// The smoothed round-trip time, or None if no heartbeat has been acknowledged yet.
+		round_trip: Option<Duration>,
+		/// The fraction (0..=1) of recent heartbeats which were never acknowledged.
+		loss: f32,
+	},
+}
+
+impl std::fmt::Display for Quality {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Local => write!(f, "local/0ms"),
+			Self::Remote {
+				round_trip: Some(round_trip),
+				loss,
+			} => write!(f, "{}ms/{:.0}% loss", round_trip.as_millis(), loss * 100.0),
+			Self::Remote {
+				round_trip: None,
+				loss,
+			} => write!(f, "?ms/{:.0}% loss", loss * 100.0),
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Sample {
+	sequence: u32,
+	sent_at: Instant,
+	is_acked: bool,
+}
+
+/// Estimates the quality of a connection from when heartbeats were sent & acknowledged.
+#[derive(Default, Debug)]
+pub struct Tracker {
+	next_sequence: u32,
+	samples: VecDeque<Sample>,
+	round_trip: Option<Duration>,
+}
+
+impl Tracker {
+	/// Records that a heartbeat is being sent, returning its sequence number.
+	pub fn send(&mut self, now: Instant) -> u32 {
+		let sequence = self.next_sequence;
+		self.next_sequence = self.next_sequence.wrapping_add(1);
+		self.samples.push_back(Sample {
+			sequence,
+			sent_at: now,
+			is_acked: false,
+		});
+		// Samples which are still waiting on an ack are kept, even if they are outside of the window.
+		while self.samples.len() > LOSS_WINDOW && self.samples[0].sent_at + ACK_TIMEOUT <= now {
+			self.samples.pop_front();
+		}
+		sequence
+	}
+
+	/// Records that a heartbeat was acknowledged.
+	/// Acks for heartbeats which have already been acknowledged (or are too old to be tracked) are ignored.
+	pub fn ack(&mut self, sequence: u32, now: Instant) {
+		let sample = self
+			.samples
+			.iter_mut()
+			.find(|sample| sample.sequence == sequence && !sample.is_acked);
+		let sample = match sample {
+			Some(sample) => sample,
+			None => return,
+		};
+		sample.is_acked = true;
+		let round_trip = now.saturating_duration_since(sample.sent_at);
+		self.round_trip = Some(match self.round_trip {
+			Some(smoothed) => {
+				smoothed.mul_f64(1.0 - RTT_SMOOTHING) + round_trip.mul_f64(RTT_SMOOTHING)
+			}
+			None => round_trip,
+		});
+	}
+
+	/// Returns the quality of the connection based on the most recent heartbeats.
+	/// Heartbeats which were sent less than [`ACK_TIMEOUT`] ago, and have not been acknowledged,
+	/// are not counted as lost (or received) yet.
+	pub fn quality(&self, now: Instant) -> Quality {
+		let settled = self
+			.samples
+			.iter()
+			.filter(|sample| sample.is_acked || sample.sent_at + ACK_TIMEOUT <= now)
+			.collect::<Vec<_>>();
+		let settled = &settled[settled.len().saturating_sub(LOSS_WINDOW)..];
+		let lost = settled.iter().filter(|sample| !sample.is_acked).count();
+		let loss = match settled.len() {
+			0 => 0.0,
+			count => lost as f32 / count as f32,
+		};
+		Quality::Remote {
+			round_trip: self.round_trip,
+			loss,
+		}
+	}
+}
+
+/// What is sent in each heartbeat datagram.
+#[derive(Serialize, Deserialize, Debug)]
+enum Beat {
+	/// Sent by the server, and answered with an [`Ack`](Beat::Ack) by the client.
+	Heartbeat {
+		sequence: u32,
+		quality: Quality,
+	},
+	Ack {
+		sequence: u32,
+	},
+}
+
+/// Identifies the heartbeat stream, which is opened by the server for heartbeats and by the client for acks.
+pub struct Identifier(Arc<AppContext>);
+
+impl Identifier {
+	pub fn new(context: Arc<AppContext>) -> Self {
+		Self(context)
+	}
+}
+
+impl stream::Identifier for Identifier {
+	type SendBuilder = AppContext;
+	type RecvBuilder = AppContext;
+	fn unique_id() -> &'static str {
+		"heartbeat"
+	}
+	fn send_builder(&self) -> &Arc<Self::SendBuilder> {
+		&self.0
+	}
+	fn recv_builder(&self) -> &Arc<Self::RecvBuilder> {
+		&self.0
+	}
+}
+
+#[derive(Default)]
+pub struct AppContext {
+	/// The network storage, where the client records the quality of its connection.
+	pub storage: Weak<RwLock<Storage>>,
+	/// The heartbeats sent to each connection, on the server.
+	pub trackers: Mutex<HashMap<SocketAddr, Tracker>>,
+}
+
+impl stream::send::AppContext for AppContext {
+	type Opener = stream::datagram::Opener;
+}
+
+impl stream::recv::AppContext for AppContext {
+	type Extractor = stream::datagram::Extractor;
+	type Receiver = Receiver;
+}
+
+impl AppContext {
+	fn set_client_quality(&self, quality: Quality) -> Result<()> {
+		use crate::common::network::Error::{
+			FailedToReadStorage, FailedToWriteClient, InvalidClient, InvalidStorage,
+		};
+		let arc_storage = self.storage.upgrade().ok_or(InvalidStorage)?;
+		let storage = arc_storage.read().map_err(|_| FailedToReadStorage)?;
+		let arc = storage.client().as_ref().ok_or(InvalidClient)?;
+		let mut client = arc.write().map_err(|_| FailedToWriteClient)?;
+		client.set_connection_quality(Some(quality));
+		Ok(())
+	}
+}
+
+pub struct Sender {
+	#[allow(dead_code)]
+	context: Arc<AppContext>,
+	#[allow(dead_code)]
+	connection: Arc<Connection>,
+	send: kind::send::Datagram,
+}
+
+impl From<stream::send::Context<AppContext>> for Sender {
+	fn from(context: stream::send::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			send: context.stream,
+		}
+	}
+}
+
+impl stream::handler::Initiator for Sender {
+	type Identifier = Identifier;
+}
+
+impl Sender {
+	async fn send(mut self, beat: Beat) -> Result<()> {
+		use stream::kind::{Send, Write};
+		self.send.write(&beat).await?;
+		self.send.finish().await?;
+		Ok(())
+	}
+
+	fn spawn(connection: Weak<Connection>, beat: Beat) -> Result<()> {
+		let arc = Connection::upgrade(&connection)?;
+		let log = <Identifier as stream::Identifier>::log_category("send", &arc);
+		arc.spawn(log, async move {
+			use stream::handler::Initiator;
+			Sender::open(&connection)?.await?.send(beat).await
+		});
+		Ok(())
+	}
+}
+
+pub struct Receiver {
+	context: Arc<AppContext>,
+	connection: Arc<Connection>,
+	recv: kind::recv::Datagram,
+}
+
+impl From<stream::recv::Context<AppContext>> for Receiver {
+	fn from(context: stream::recv::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			recv: context.stream,
+		}
+	}
+}
+
+impl stream::handler::Receiver for Receiver {
+	type Identifier = Identifier;
+	fn receive(mut self) {
+		let log = <Identifier as stream::Identifier>::log_category("recv", &self.connection);
+		self.connection.clone().spawn(log, async move {
+			use stream::kind::Read;
+			match self.recv.read::<Beat>().await? {
+				Beat::Heartbeat { sequence, quality } => {
+					self.context.set_client_quality(quality)?;
+					Sender::spawn(Arc::downgrade(&self.connection), Beat::Ack { sequence })?;
+				}
+				Beat::Ack { sequence } => {
+					let address = self.connection.remote_address();
+					let mut trackers = self.context.trackers.lock().unwrap();
+					if let Some(tracker) = trackers.get_mut(&address) {
+						tracker.ack(sequence, Instant::now());
+					}
+				}
+			}
+			Ok(())
+		});
+	}
+}
+
+/// Server system which sends a heartbeat to every connection each [`INTERVAL`].
+pub struct SendHeartbeats {
+	context: Arc<AppContext>,
+	connection_list: Weak<RwLock<connection::List>>,
+	since_heartbeat: Duration,
+}
+
+impl SendHeartbeats {
+	pub fn new(context: &Arc<AppContext>, connection_list: &Arc<RwLock<connection::List>>) -> Self {
+		Self {
+			context: context.clone(),
+			connection_list: Arc::downgrade(&connection_list),
+			since_heartbeat: Duration::ZERO,
+		}
+	}
+}
+
+impl EngineSystem for SendHeartbeats {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!("subsystem:heartbeat");
+		self.since_heartbeat += delta_time;
+		if self.since_heartbeat < INTERVAL {
+			return;
+		}
+		self.since_heartbeat = Duration::ZERO;
+
+		let arc_list = match self.connection_list.upgrade() {
+			Some(arc_list) => arc_list,
+			None => return,
+		};
+		let connections = arc_list.read().unwrap().all().clone();
+		let now = Instant::now();
+		let mut trackers = self.context.trackers.lock().unwrap();
+		// Connections which have dropped no longer need to be tracked.
+		trackers.retain(|address, _| connections.contains_key(address));
+		for (address, weak_connection) in connections.into_iter() {
+			let is_local = match weak_connection.upgrade() {
+				Some(connection) => connection.is_local(),
+				None => continue,
+			};
+			let beat = match is_local {
+				true => Beat::Heartbeat {
+					sequence: 0,
+					quality: Quality::Local,
+				},
+				false => {
+					let tracker = trackers.entry(address).or_default();
+					let quality = tracker.quality(now);
+					Beat::Heartbeat {
+						sequence: tracker.send(now),
+						quality,
+					}
+				}
+			};
+			if let Err(err) = Sender::spawn(weak_connection, beat) {
+				log::warn!(target: LOG, "Failed to send heartbeat to {}: {:?}", address, err);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tracker {
+	use super::*;
+
+	fn millis(value: u64) -> Duration {
+		Duration::from_millis(value)
+	}
+
+	#[test]
+	fn loss_and_round_trip_from_acks() {
+		let start = Instant::now();
+		let mut tracker = Tracker::default();
+		// 10 heartbeats, every other one of the last 4 is lost, and every ack takes 40ms.
+		for i in 0..10 {
+			let sent_at = start + INTERVAL * i;
+			let sequence = tracker.send(sent_at);
+			if i < 6 || i % 2 == 0 {
+				tracker.ack(sequence, sent_at + millis(40));
+			}
+		}
+		let now = start + INTERVAL * 9 + ACK_TIMEOUT;
+		assert_eq!(
+			tracker.quality(now),
+			Quality::Remote {
+				round_trip: Some(millis(40)),
+				loss: 0.2,
+			}
+		);
+	}
+
+	#[test]
+	fn round_trip_is_smoothed() {
+		let start = Instant::now();
+		let mut tracker = Tracker::default();
+		let first = tracker.send(start);
+		tracker.ack(first, start + millis(100));
+		let second = tracker.send(start + INTERVAL);
+		tracker.ack(second, start + INTERVAL + millis(20));
+		// Duplicate acks do not count as another sample.
+		tracker.ack(second, start + INTERVAL + millis(500));
+		match tracker.quality(start + INTERVAL * 2) {
+			Quality::Remote {
+				round_trip: Some(round_trip),
+				loss,
+			} => {
+				// 100 * 7/8 + 20 * 1/8
+				assert_eq!(round_trip, millis(90));
+				assert_eq!(loss, 0.0);
+			}
+			quality => panic!("unexpected quality {:?}", quality),
+		}
+	}
+
+	#[test]
+	fn pending_heartbeats_are_not_lost() {
+		let start = Instant::now();
+		let mut tracker = Tracker::default();
+		tracker.send(start);
+		let quality = tracker.quality(start + ACK_TIMEOUT / 2);
+		assert_eq!(
+			quality,
+			Quality::Remote {
+				round_trip: None,
+				loss: 0.0
+			}
+		);
+		assert_eq!(
+			tracker.quality(start + ACK_TIMEOUT).to_string(),
+			"?ms/100% loss"
+		);
+		assert_eq!(Quality::Local.to_string(), "local/0ms");
+	}
+}
@@ -8,6 +8,13 @@ use std::{
 pub enum Kind {
 	Client,
 	Server,
+	/// Added to a dedicated [`Client`](Kind::Client) which joins the server as an observer.
+	/// The server gives spectators a free-flying camera instead of a player entity.
+	Spectator,
+	/// An integrated client and server running in the same process,
+	/// where the local client is connected to the server it is hosting.
+	/// Always accompanied by both [`Client`](Kind::Client) and [`Server`](Kind::Server) in a [`Set`].
+	ListenServer,
 }
 
 pub type Set = EnumSet<Kind>;
@@ -17,10 +24,52 @@ impl std::fmt::Display for Kind {
 		match self {
 			Self::Client => write!(f, "Client"),
 			Self::Server => write!(f, "Server"),
+			Self::Spectator => write!(f, "Spectator"),
+			Self::ListenServer => write!(f, "ListenServer"),
 		}
 	}
 }
 
+impl Kind {
+	/// Returns the kind the application was launched as, based on the `-client`, `-server`, and `-listen_server` arguments.
+	/// Providing both `-client` and `-server` is the same as `-listen_server`.
+	/// Returns None if none of the arguments were provided.
+	pub fn from_args<I, S>(args: I) -> Option<Self>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<str>,
+	{
+		let (mut is_client, mut is_server) = (false, false);
+		for arg in args.into_iter() {
+			match arg.as_ref() {
+				"-client" => is_client = true,
+				"-server" => is_server = true,
+				"-listen_server" => return Some(Self::ListenServer),
+				_ => {}
+			}
+		}
+		match (is_client, is_server) {
+			(true, true) => Some(Self::ListenServer),
+			(true, false) => Some(Self::Client),
+			(false, true) => Some(Self::Server),
+			(false, false) => None,
+		}
+	}
+
+	/// Returns every kind that this kind implies (including itself).
+	pub fn as_set(self) -> Set {
+		match self {
+			Self::ListenServer => Self::ListenServer + (Self::Client + Self::Server),
+			_ => self.into(),
+		}
+	}
+
+	/// Returns true if this kind implies the other (e.g. a listen server is also a client and a server).
+	pub fn contains(self, other: Kind) -> bool {
+		self.as_set().contains(other)
+	}
+}
+
 impl std::ops::Add<Kind> for Kind {
 	type Output = Set;
 	fn add(self, other: Kind) -> Self::Output {
@@ -41,9 +90,13 @@ impl std::ops::Add<Set> for Kind {
 	}
 }
 
-// Modes: Client, Server, Client + Server
+// Modes: Client, Server, ListenServer (Client + Server)
 pub fn all() -> Vec<Set> {
-	vec![Kind::Client.into(), Kind::Server.into(), Set::all()]
+	vec![
+		Kind::Client.as_set(),
+		Kind::Server.as_set(),
+		Kind::ListenServer.as_set(),
+	]
 }
 
 fn instance() -> &'static RwLock<Set> {
@@ -56,6 +109,11 @@ fn instance() -> &'static RwLock<Set> {
 	}
 }
 
+/// Returns true if the mode is a client connected to a remote server (whether or not it is spectating).
+pub fn is_dedicated_client(mode: Set) -> bool {
+	mode.contains(Kind::Client) && !mode.contains(Kind::Server)
+}
+
 pub fn set(mode: Set) {
 	*instance().write().unwrap() = mode;
 }
@@ -63,3 +121,37 @@ pub fn set(mode: Set) {
 pub fn get() -> Set {
 	instance().read().unwrap().clone()
 }
+
+#[cfg(test)]
+mod args {
+	use super::*;
+
+	#[test]
+	fn arg_combinations() {
+		let cases: &[(&[&str], Option<Kind>)] = &[
+			(&[], None),
+			(&["-user=a"], None),
+			(&["-client"], Some(Kind::Client)),
+			(&["-server"], Some(Kind::Server)),
+			(&["-client", "-server"], Some(Kind::ListenServer)),
+			(&["-server", "-user=a", "-client"], Some(Kind::ListenServer)),
+			(&["-listen_server"], Some(Kind::ListenServer)),
+			(&["-client", "-listen_server"], Some(Kind::ListenServer)),
+			(&["-server", "-listen_server"], Some(Kind::ListenServer)),
+		];
+		for (args, expected) in cases.iter() {
+			assert_eq!(Kind::from_args(args.iter()), *expected, "{:?}", args);
+		}
+	}
+
+	#[test]
+	fn listen_server_contains_client_and_server() {
+		assert!(Kind::ListenServer.contains(Kind::Client));
+		assert!(Kind::ListenServer.contains(Kind::Server));
+		assert!(!Kind::ListenServer.contains(Kind::Spectator));
+		assert!(!Kind::Client.contains(Kind::Server));
+		assert!(!Kind::Server.contains(Kind::ListenServer));
+		assert!(!is_dedicated_client(Kind::ListenServer.as_set()));
+		assert!(is_dedicated_client(Kind::Client + Kind::Spectator));
+	}
+}
@@ -1,3 +1,4 @@
+use crate::{block, common::world::raycast::RaycastHit};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use engine::math::nalgebra::{UnitQuaternion, Vector3};
@@ -13,9 +14,23 @@ pub mod server;
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Datum {
 	pub timestamp: DateTime<Utc>,
-	pub server_entity: hecs::Entity,
+	/// The server's id for the player entity being moved.
+	/// None if the client is spectating, in which case the server moves the connection's spectator camera.
+	pub server_entity: Option<hecs::Entity>,
 	pub velocity: Vector3<f32>,
 	pub orientation: UnitQuaternion<f32>,
+	/// The block the player is holding the break action on, if any.
+	/// The server tracks the progress of breaking it in a [`BlockInteraction`](crate::entity::component::BlockInteraction).
+	#[serde(default)]
+	pub breaking: Option<block::Point>,
+	/// The block the player pressed the place action while looking at, if they did since the last input.
+	/// The server places a block against the face which was hit (see [`BlockPlacement`](crate::entity::component::BlockPlacement)).
+	#[serde(default)]
+	pub placing: Option<RaycastHit>,
+	/// The sequence number of this input, which the server replicates back in
+	/// [`AcknowledgedInput`](crate::entity::component::physics::linear::AcknowledgedInput) once it has applied it.
+	#[serde(default)]
+	pub sequence: u32,
 }
 
 impl Datum {
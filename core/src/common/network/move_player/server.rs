@@ -45,7 +45,14 @@ impl stream::handler::Receiver for Handler {
 		use stream::Identifier;
 		let log = super::Identifier::log_category("server", &self.connection);
 		self.connection.clone().spawn(log.clone(), async move {
-			use crate::entity::component::{physics::linear, Orientation};
+			use crate::{
+				common::world::raycast,
+				entity::component::{
+					physics::{linear, Dynamic},
+					BlockInteraction, BlockPlacement, Orientation,
+				},
+			};
+			use engine::math::nalgebra::Vector3;
 			use stream::kind::Read;
 			let data = self.recv.read::<Datum>().await?;
 
@@ -65,14 +72,93 @@ impl stream::handler::Receiver for Handler {
 				None => return Ok(()),
 			};
 
-			let world = arc_world.write().unwrap();
-			if let Ok(entity_ref) = world.entity(data.server_entity) {
+			let mut world = arc_world.write().unwrap();
+			let server_entity = match data.server_entity {
+				Some(entity) => Some(entity),
+				None => {
+					use crate::entity::component::Spectator;
+					let address = self.connection.remote_address();
+					world
+						.query_mut::<&Spectator>()
+						.into_iter()
+						.find(|(_, spectator)| *spectator.address() == address)
+						.map(|(entity, _)| entity)
+				}
+			};
+			let entity_ref = server_entity
+				.map(|entity| world.entity(entity).ok())
+				.flatten();
+			let mut is_breaking_other_block = false;
+			let mut breaking = None;
+			let mut placing = None;
+			if let Some(entity_ref) = entity_ref {
+				let is_dynamic = entity_ref.has::<Dynamic>();
 				if let Some(mut velocity) = entity_ref.get::<&mut linear::Velocity>() {
-					**velocity = data.velocity;
+					// Dynamic bodies fall under the server's gravity, so clients only control their horizontal movement.
+					let (reported, fall_speed) = match is_dynamic {
+						true => (
+							Vector3::new(data.velocity.x, 0.0, data.velocity.z),
+							velocity.y,
+						),
+						false => (data.velocity, 0.0),
+					};
+					// Clients cannot move their entity faster than the max speed.
+					let validator = crate::server::movement::Validator::default();
+					**velocity = validator
+						.validate_from(&self.connection.remote_address(), &reported)
+						+ Vector3::new(0.0, fall_speed, 0.0);
 				}
 				if let Some(mut orientation) = entity_ref.get::<&mut Orientation>() {
 					**orientation = data.orientation;
 				}
+				if let Some(mut acknowledged) = entity_ref.get::<&mut linear::AcknowledgedInput>() {
+					acknowledged.set_sequence(data.sequence);
+				}
+				// Clients cannot break blocks which are out of their reach.
+				breaking = match (data.breaking, entity_ref.get::<&linear::Position>()) {
+					(Some(target), Some(position)) => {
+						let eye = raycast::eye_position(&position);
+						if raycast::is_within_reach(&eye, &target) {
+							Some(target)
+						} else {
+							log::debug!(target: &log, "Ignoring break of out-of-reach block {}", target);
+							None
+						}
+					}
+					_ => None,
+				};
+				// Nor can they place blocks against blocks which are out of their reach.
+				placing = match (data.placing, entity_ref.get::<&linear::Position>()) {
+					(Some(hit), Some(position)) => {
+						let eye = raycast::eye_position(&position);
+						if raycast::is_within_reach(&eye, &hit.point) {
+							Some(hit)
+						} else {
+							log::debug!(target: &log, "Ignoring placement against out-of-reach block {}", hit.point);
+							None
+						}
+					}
+					_ => None,
+				};
+				let current_target = entity_ref
+					.get::<&BlockInteraction>()
+					.map(|interaction| *interaction.target());
+				is_breaking_other_block = current_target != breaking;
+			}
+
+			// Only players break blocks, spectators cannot interact with the world.
+			if let (Some(entity), Some(_)) = (data.server_entity, server_entity) {
+				if is_breaking_other_block {
+					// Looking at a different block restarts the progress, and releasing the break action cancels it.
+					let _ = world.remove_one::<BlockInteraction>(entity);
+					if let Some(target) = breaking {
+						let _ = world.insert_one(entity, BlockInteraction::new(target));
+					}
+				}
+				// The block is placed by the `PlaceBlocks` system, which takes it from the player's inventory.
+				if let Some(hit) = placing {
+					let _ = world.insert_one(entity, BlockPlacement::new(hit));
+				}
 			}
 
 			Ok(())
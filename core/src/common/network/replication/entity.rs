@@ -12,6 +12,9 @@ pub use identifier::*;
 mod update;
 pub use update::*;
 
+mod delta;
+pub use delta::*;
+
 /// Context & Handler for the client/receiver.
 pub mod client;
 /// Context & Handler for the server/sender.
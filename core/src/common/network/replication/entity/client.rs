@@ -1,11 +1,16 @@
 use crate::{
-	common::network::replication::entity::update::Update,
+	common::network::replication::entity::{update::Update, PositionDelta},
 	entity::{
 		self, archetype,
-		component::{self, binary::SerializedEntity},
+		component::{
+			self,
+			binary::SerializedEntity,
+			physics::linear::{InterpolatedPosition, Position, Prediction},
+		},
 	},
 };
 use anyhow::Result;
+use engine::math::nalgebra::Point3;
 use socknet::{
 	connection::Connection,
 	stream::{self, kind::recv::Ongoing},
@@ -26,31 +31,19 @@ impl stream::recv::AppContext for AppContext {
 	type Receiver = Handler;
 }
 
-impl AppContext {
-	fn entity_world(&self) -> Result<Arc<RwLock<hecs::World>>> {
-		Ok(self
-			.entity_world
-			.upgrade()
-			.ok_or(Error::InvalidEntityWorld)?)
-	}
-}
-
 /// The stream handler for the client/receiver of the entity replication stream.
 pub struct Handler {
-	context: Arc<AppContext>,
 	connection: Arc<Connection>,
 	recv: Ongoing,
-	/// The Server->Client map of entity ids
-	entity_map_s2c: HashMap</*server*/ hecs::Entity, /*client*/ hecs::Entity>,
+	replica: Replica,
 }
 
 impl From<stream::recv::Context<AppContext>> for Handler {
 	fn from(context: stream::recv::Context<AppContext>) -> Self {
 		Self {
-			context: context.builder,
+			replica: Replica::new(context.builder.entity_world.clone()),
 			connection: context.connection,
 			recv: context.stream,
-			entity_map_s2c: HashMap::new(),
 		}
 	}
 }
@@ -63,7 +56,7 @@ impl stream::handler::Receiver for Handler {
 		engine::task::spawn(log.clone(), async move {
 			use stream::kind::Read;
 			while let Ok(update) = self.recv.read::<Update>().await {
-				if let Err(err) = self.process_update(&log, update) {
+				if let Err(err) = self.replica.process_update(&log, update) {
 					log::error!(target: &log, "{:?}", err);
 				}
 			}
@@ -72,17 +65,41 @@ impl stream::handler::Receiver for Handler {
 	}
 }
 
-impl Handler {
+/// The entities a client has been sent by the server, spawned into the client's entity world.
+/// Applies the [`updates`](Update) received by the entity replication stream [`Handler`],
+/// and those replayed by headless clients (see [`recording::Player`](crate::entity::system::replicator::recording::Player)).
+pub struct Replica {
+	entity_world: Weak<RwLock<entity::World>>,
+	/// The Server->Client map of entity ids
+	entity_map_s2c: HashMap</*server*/ hecs::Entity, /*client*/ hecs::Entity>,
+	/// The last position replicated for each server entity, which position deltas are relative to.
+	replicated_positions: HashMap</*server*/ hecs::Entity, Point3<f64>>,
+}
+
+impl Replica {
+	pub fn new(entity_world: Weak<RwLock<entity::World>>) -> Self {
+		Self {
+			entity_world,
+			entity_map_s2c: HashMap::new(),
+			replicated_positions: HashMap::new(),
+		}
+	}
+
 	fn entity_world(&self) -> Result<Arc<RwLock<entity::World>>> {
-		self.context.entity_world()
+		Ok(self
+			.entity_world
+			.upgrade()
+			.ok_or(Error::InvalidEntityWorld)?)
 	}
 
-	fn process_update(&mut self, log: &str, update: Update) -> Result<()> {
+	pub fn process_update(&mut self, log: &str, update: Update) -> Result<()> {
+		// Changed updates only include the components which changed, so the components they omit are kept.
+		let is_complete = !matches!(update, Update::Changed(_));
 		match update {
 			Update::Relevant(serialized) => {
 				self.spawn_entity(serialized)?;
 			}
-			Update::Update(serialized) => {
+			Update::Update(serialized) | Update::Changed(serialized) => {
 				let client_entity = match self.get_client_entity(&serialized.entity) {
 					Some(entity) => entity,
 					None => {
@@ -90,7 +107,17 @@ impl Handler {
 						return Ok(());
 					}
 				};
-				self.update_entity(client_entity, serialized)?;
+				self.update_entity(client_entity, serialized, is_complete)?;
+			}
+			Update::Moved(server_entity, delta) => {
+				let client_entity = match self.get_client_entity(&server_entity) {
+					Some(entity) => entity,
+					None => {
+						log::warn!(target: &log, "Received entity replication Moved({0}), but the client has not yet received the Relevant({0}) notification.", server_entity.id());
+						return Ok(());
+					}
+				};
+				self.move_entity(server_entity, client_entity, delta)?;
 			}
 			Update::Irrelevant(server_entity) | Update::Destroyed(server_entity) => {
 				self.despawn_entity(server_entity)?;
@@ -99,10 +126,24 @@ impl Handler {
 		Ok(())
 	}
 
-	fn get_client_entity(&self, server_entity: &hecs::Entity) -> Option<hecs::Entity> {
+	/// Returns the client entity which replicates an entity of the server, if it is relevant to the client.
+	pub fn get_client_entity(&self, server_entity: &hecs::Entity) -> Option<hecs::Entity> {
 		self.entity_map_s2c.get(&server_entity).cloned()
 	}
 
+	/// Records the position of a replicated snapshot, so future position deltas can be applied to it.
+	fn record_position(&mut self, server_entity: hecs::Entity, builder: &hecs::EntityBuilder) {
+		match builder.get::<&Position>() {
+			Some(position) => {
+				self.replicated_positions
+					.insert(server_entity, position.world_position());
+			}
+			None => {
+				self.replicated_positions.remove(&server_entity);
+			}
+		}
+	}
+
 	fn is_builder_locally_owned(&self, builder: &hecs::EntityBuilder) -> bool {
 		use crate::client::account;
 		// This is only ever valid for players right now (only players have the OwnedByAccount component),
@@ -130,10 +171,21 @@ impl Handler {
 		};
 
 		builder.add(component::network::Replicated::new_client(server_entity));
+		self.record_position(server_entity, &builder);
 
 		// If this is first spawn and the entity is owned by the client, spawn the client-only components as well.
 		if self.is_builder_locally_owned(&builder) {
 			builder = archetype::player::Client::apply_to(builder);
+		} else {
+			// Entities controlled by the server or other clients only receive discrete position updates,
+			// so their rendered position is smoothed between those updates.
+			let now = std::time::Instant::now();
+			let interpolated = builder
+				.get::<&Position>()
+				.map(|position| InterpolatedPosition::new(&position, now));
+			if let Some(interpolated) = interpolated {
+				builder.add(interpolated);
+			}
 		}
 
 		let client_entity = {
@@ -149,10 +201,14 @@ impl Handler {
 	/// update any existing components with the same types with the new data,
 	/// spawn any missing components that were replicated,
 	/// and destroy any components marked as replicated that are present locally but not replicated.
+	/// Replicates the components of the serialized entity into the existing client entity.
+	/// If `is_complete`, the serialized entity has every replicated component,
+	/// and replicated components on the client entity which are not included are removed.
 	fn update_entity(
-		&self,
+		&mut self,
 		client_entity: hecs::Entity,
 		serialized: SerializedEntity,
+		is_complete: bool,
 	) -> Result<()> {
 		let _profiling_tag = format!(
 			"server_entity={} client_entity={}",
@@ -161,7 +217,10 @@ impl Handler {
 		);
 		profiling::scope!("update_entity", &_profiling_tag);
 		let registry = component::Registry::read();
-		let (_server_entity, builder) = serialized.into_builder(&registry)?;
+		let (server_entity, builder) = serialized.into_builder(&registry)?;
+		if is_complete || builder.has::<Position>() {
+			self.record_position(server_entity, &builder);
+		}
 
 		let arc_world = self.entity_world()?;
 		let mut world = arc_world.write().unwrap();
@@ -169,7 +228,7 @@ impl Handler {
 		// Remove all components registered with the network extension (i.e. replicatable)
 		// which are on the local entity but not the replicated builder
 		// (i.e. they were previously created via a replication but no longer exist on the server).
-		{
+		if is_complete {
 			profiling::scope!("remove-components", &_profiling_tag);
 			let iter_to_remove = world
 				.entity(client_entity)?
@@ -206,6 +265,17 @@ impl Handler {
 			// Get the Replicatable registration extension for the component type
 			let network_ext = registered.get_ext_ok::<component::network::Registration>()?;
 
+			// The entity the local player controls is predicted,
+			// so the server's position is reconciled with instead of replacing the predicted position.
+			if type_id == std::any::TypeId::of::<Position>() {
+				if let Some(mut prediction) = entity_ref.get::<&mut Prediction>() {
+					if let Some(position) = builder.get::<&Position>() {
+						prediction.receive(position.world_position());
+					}
+					continue;
+				}
+			}
+
 			// Read the data from the replicated component into the existing entity.
 			if !registered.is_in_entity(&entity_ref) {
 				// cache the missing component to the builder for adding all missing components at once
@@ -224,7 +294,33 @@ impl Handler {
 		Ok(())
 	}
 
+	/// Moves an existing entity by a position delta relative to the last replicated position.
+	fn move_entity(
+		&mut self,
+		server_entity: hecs::Entity,
+		client_entity: hecs::Entity,
+		delta: PositionDelta,
+	) -> Result<()> {
+		let previous = self
+			.replicated_positions
+			.get(&server_entity)
+			.ok_or(Error::NoReplicatedPosition(server_entity))?;
+		let position = delta.apply(previous);
+		self.replicated_positions.insert(server_entity, position);
+
+		let arc_world = self.entity_world()?;
+		let world = arc_world.read().unwrap();
+		if let Ok(mut prediction) = world.get::<&mut Prediction>(client_entity) {
+			prediction.receive(position);
+			return Ok(());
+		}
+		let mut component = world.get::<&mut Position>(client_entity)?;
+		component.set_world_position(position);
+		Ok(())
+	}
+
 	fn despawn_entity(&mut self, server_entity: hecs::Entity) -> Result<()> {
+		self.replicated_positions.remove(&server_entity);
 		let client_match = self.entity_map_s2c.remove(&server_entity);
 		assert!(client_match.is_some());
 		if let Some(client_entity) = client_match {
@@ -240,4 +336,6 @@ impl Handler {
 enum Error {
 	#[error("Entity World is invalid")]
 	InvalidEntityWorld,
+	#[error("Received a position delta for entity {0:?}, which has never replicated its position")]
+	NoReplicatedPosition(hecs::Entity),
 }
@@ -0,0 +1,209 @@
+use crate::entity::component::{
+	binary::{self, SerializedComponent, SerializedEntity},
+	physics::linear::Position,
+	Component,
+};
+use anyhow::Result;
+use engine::math::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+use super::Update;
+
+/// The number of steps each block is divided into when quantizing a [`PositionDelta`].
+/// Positions are replicated with a precision of 1/256th of a block.
+pub static QUANTIZATION_STEPS_PER_BLOCK: f64 = 256.0;
+
+/// A compact change in an entity's position (in blocks),
+/// quantized to [`QUANTIZATION_STEPS_PER_BLOCK`] so that each axis fits in 2 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionDelta([i16; 3]);
+
+impl PositionDelta {
+	/// Returns the quantized delta which moves `from` as close as possible to `to`,
+	/// or None if the positions are too far apart to be represented as a delta.
+	pub fn between(from: &Point3<f64>, to: &Point3<f64>) -> Option<Self> {
+		let mut steps = [0i16; 3];
+		for i in 0..3 {
+			let axis_steps = ((to[i] - from[i]) * QUANTIZATION_STEPS_PER_BLOCK).round();
+			if axis_steps < i16::MIN as f64 || axis_steps > i16::MAX as f64 {
+				return None;
+			}
+			steps[i] = axis_steps as i16;
+		}
+		Some(Self(steps))
+	}
+
+	/// Returns the position which results from moving `from` by this delta.
+	/// Both the server and client use this to determine the replicated position,
+	/// so quantization error does not accumulate over successive deltas.
+	pub fn apply(&self, from: &Point3<f64>) -> Point3<f64> {
+		let offset = Vector3::new(self.0[0], self.0[1], self.0[2]).cast::<f64>();
+		from + offset / QUANTIZATION_STEPS_PER_BLOCK
+	}
+}
+
+/// The last state of an entity which was replicated to a client,
+/// used to determine if the next update can be sent as a [`PositionDelta`].
+pub struct Baseline {
+	/// The position of the entity as the client knows it.
+	position: Point3<f64>,
+	/// All of the replicated components other than [`Position`].
+	other_components: Vec<SerializedComponent>,
+}
+
+impl Baseline {
+	/// Creates the baseline from a full snapshot which has been sent to the client.
+	/// Returns None if the entity has no position (and can therefore never be sent as a delta).
+	pub fn from_snapshot(serialized: &SerializedEntity) -> Result<Option<Self>> {
+		let (position, other_components) = Self::split(serialized)?;
+		Ok(position.map(|position| Self {
+			position,
+			other_components,
+		}))
+	}
+
+	/// Separates the world position of an entity from the rest of its components.
+	fn split(
+		serialized: &SerializedEntity,
+	) -> Result<(Option<Point3<f64>>, Vec<SerializedComponent>)> {
+		let mut position = None;
+		let mut other_components = Vec::with_capacity(serialized.components.len());
+		for component in serialized.components.iter() {
+			if component.id == Position::unique_id() {
+				let value =
+					<Position as binary::Serializable>::deserialize(component.data.clone())?;
+				position = Some(value.world_position());
+			} else {
+				other_components.push(component.clone());
+			}
+		}
+		Ok((position, other_components))
+	}
+
+	/// Returns the update which brings the client from this baseline to the state of the serialized entity,
+	/// updating the baseline to match what the client will have after receiving it.
+	///
+	/// If only the position has changed (and the move is small enough), this is a [`Update::Moved`],
+	/// otherwise the full snapshot is sent as an [`Update::Update`].
+	pub fn next_update(&mut self, serialized: &SerializedEntity) -> Result<Update> {
+		let (position, other_components) = Self::split(serialized)?;
+		let position = match position {
+			Some(position) => position,
+			None => return Ok(Update::Update(serialized.clone())),
+		};
+		if Self::same_components(&self.other_components, &other_components) {
+			if let Some(delta) = PositionDelta::between(&self.position, &position) {
+				self.position = delta.apply(&self.position);
+				return Ok(Update::Moved(serialized.entity, delta));
+			}
+		}
+		self.position = position;
+		self.other_components = other_components;
+		Ok(Update::Update(serialized.clone()))
+	}
+
+	fn same_components(a: &Vec<SerializedComponent>, b: &Vec<SerializedComponent>) -> bool {
+		a.len() == b.len()
+			&& a.iter()
+				.zip(b.iter())
+				.all(|(a, b)| a.id == b.id && a.data == b.data)
+	}
+}
+
+#[cfg(test)]
+mod baseline {
+	use super::*;
+	use crate::entity::archetype::test::Label;
+
+	fn snapshot(entity: hecs::Entity, position: Point3<f64>) -> SerializedEntity {
+		let mut component = Position::default();
+		component.set_world_position(position);
+		SerializedEntity {
+			entity,
+			components: vec![
+				SerializedComponent {
+					id: Position::unique_id().to_owned(),
+					data: binary::serialize(&component).unwrap(),
+				},
+				SerializedComponent {
+					id: Label::unique_id().to_owned(),
+					data: binary::serialize(&Label::from(&Point3::origin())).unwrap(),
+				},
+			],
+		}
+	}
+
+	fn entity() -> hecs::Entity {
+		hecs::World::new().spawn(())
+	}
+
+	#[test]
+	fn small_move_is_smaller_than_snapshot() {
+		let entity = entity();
+		let first = snapshot(entity, Point3::new(3.5, 0.0, 0.5));
+		let mut baseline = Baseline::from_snapshot(&first).unwrap().unwrap();
+
+		let moved = snapshot(entity, Point3::new(3.75, 0.0, 0.25));
+		let update = baseline.next_update(&moved).unwrap();
+		let delta = match &update {
+			Update::Moved(moved_entity, delta) => {
+				assert_eq!(*moved_entity, entity);
+				*delta
+			}
+			update => panic!("expected a position delta, found {:?}", update),
+		};
+		assert_eq!(
+			delta.apply(&Point3::new(3.5, 0.0, 0.5)),
+			Point3::new(3.75, 0.0, 0.25)
+		);
+
+		let delta_size = bincode::serialize(&update).unwrap().len();
+		let snapshot_size = bincode::serialize(&Update::Update(moved)).unwrap().len();
+		assert!(delta_size < snapshot_size);
+	}
+
+	#[test]
+	fn large_move_is_snapshot() {
+		let entity = entity();
+		let first = snapshot(entity, Point3::origin());
+		let mut baseline = Baseline::from_snapshot(&first).unwrap().unwrap();
+		let moved = snapshot(entity, Point3::new(500.0, 0.0, 0.0));
+		assert!(matches!(
+			baseline.next_update(&moved).unwrap(),
+			Update::Update(_)
+		));
+	}
+
+	#[test]
+	fn component_change_is_snapshot() {
+		let entity = entity();
+		let first = snapshot(entity, Point3::origin());
+		let mut baseline = Baseline::from_snapshot(&first).unwrap().unwrap();
+		let mut changed = snapshot(entity, Point3::new(1.0, 0.0, 0.0));
+		changed.components[1].data =
+			binary::serialize(&Label::from(&Point3::new(1.0, 0.0, 0.0))).unwrap();
+		assert!(matches!(
+			baseline.next_update(&changed).unwrap(),
+			Update::Update(_)
+		));
+	}
+
+	#[test]
+	fn quantization_error_does_not_accumulate() {
+		let entity = entity();
+		let mut baseline = Baseline::from_snapshot(&snapshot(entity, Point3::origin()))
+			.unwrap()
+			.unwrap();
+		let mut client_position = Point3::origin();
+		for step in 1..=100 {
+			let target = Point3::new(step as f64 * 0.001, 0.0, 0.0);
+			if let Update::Moved(_, delta) =
+				baseline.next_update(&snapshot(entity, target)).unwrap()
+			{
+				client_position = delta.apply(&client_position);
+			}
+		}
+		let error = (client_position.x - 0.1).abs();
+		assert!(error <= 0.5 / QUANTIZATION_STEPS_PER_BLOCK);
+	}
+}
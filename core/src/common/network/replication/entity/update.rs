@@ -13,7 +13,14 @@ pub enum Update {
 	/// An entity is now relevant to the client and should be replicated.
 	Relevant(SerializedEntity),
 	/// A relevant entity has changed and should be replicated.
+	/// Contains every replicated component, so any component the client has which is not included has been removed.
 	Update(SerializedEntity),
+	/// Some components of a relevant entity have changed.
+	/// Only the changed components are included, components which are not included are unchanged.
+	Changed(SerializedEntity),
+	/// A relevant entity has moved, but none of its other components have changed.
+	/// The delta is relative to the position in the last update the client received for the entity.
+	Moved(hecs::Entity, super::PositionDelta),
 	/// An entity was relevant and is no longer relevant to that client.
 	Irrelevant(hecs::Entity),
 	/// An entity was destroyed while it was relevant to some client.
@@ -29,6 +36,10 @@ impl std::fmt::Debug for Update {
 			Self::Update(serialized) => {
 				write!(f, "Update({})", serialized.entity.id())
 			}
+			Self::Changed(serialized) => {
+				write!(f, "Changed({})", serialized.entity.id())
+			}
+			Self::Moved(entity, _delta) => write!(f, "Moved({})", entity.id()),
 			Self::Irrelevant(entity) => write!(f, "Irrelevant({})", entity.id()),
 			Self::Destroyed(entity) => write!(f, "Destroyed({})", entity.id()),
 		}
@@ -9,9 +9,10 @@ use socknet::stream::Registry;
 use crate::{
 	common::network::Storage,
 	entity::system::replicator::relevancy::{Relevance, WorldUpdate},
-	server::world::chunk::Chunk,
 };
 
+mod backlog;
+pub use backlog::*;
 pub mod chunk;
 pub mod relevancy;
 
@@ -21,9 +22,9 @@ pub type SendUpdate = Sender<WorldUpdate>;
 pub type RecvUpdate = Receiver<WorldUpdate>;
 
 /// Async channel for sending chunks to one of the chunk replication async tasks.
-pub type SendChunks = Sender<Weak<RwLock<Chunk>>>;
+pub type SendChunks = Sender<QueuedChunk>;
 /// Async channel for receiving chunks in one of the chunk replication async tasks.
-pub type RecvChunks = Receiver<Weak<RwLock<Chunk>>>;
+pub type RecvChunks = Receiver<QueuedChunk>;
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
 /// Client-Initiated stream which handles the authentication protocol.
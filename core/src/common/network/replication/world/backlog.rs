@@ -0,0 +1,61 @@
+use crate::server::world::chunk::Chunk;
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Arc, RwLock, Weak,
+};
+
+/// A chunk which has been queued for replication to a client,
+/// and the number of bytes it was expected to take when it was queued.
+pub struct QueuedChunk {
+	pub chunk: Weak<RwLock<Chunk>>,
+	pub size: usize,
+}
+
+impl QueuedChunk {
+	pub fn new(chunk: Weak<RwLock<Chunk>>) -> Self {
+		let size = match chunk.upgrade() {
+			Some(arc) => arc.read().unwrap().chunk.replicated_size(),
+			None => 0,
+		};
+		Self { chunk, size }
+	}
+}
+
+/// The chunks which have been handed to the replication streams of a connection,
+/// but have not yet been written to it.
+///
+/// Shared between the [`replication handle`](crate::entity::system::replicator::Handle)
+/// and the chunk streams, so the replicator can stop queuing chunks for a connection
+/// which cannot keep up.
+#[derive(Clone, Default)]
+pub struct Backlog(Arc<Counters>);
+
+#[derive(Default)]
+struct Counters {
+	chunks: AtomicUsize,
+	bytes: AtomicUsize,
+}
+
+impl Backlog {
+	/// Marks a chunk as queued for replication.
+	pub fn push(&self, size: usize) {
+		self.0.chunks.fetch_add(1, Ordering::Relaxed);
+		self.0.bytes.fetch_add(size, Ordering::Relaxed);
+	}
+
+	/// Marks a queued chunk as written (or discarded).
+	pub fn pop(&self, size: usize) {
+		self.0.chunks.fetch_sub(1, Ordering::Relaxed);
+		self.0.bytes.fetch_sub(size, Ordering::Relaxed);
+	}
+
+	/// The number of chunks which are queued but not yet written.
+	pub fn chunks(&self) -> usize {
+		self.0.chunks.load(Ordering::Relaxed)
+	}
+
+	/// The number of bytes of chunk data which are queued but not yet written.
+	pub fn bytes(&self) -> usize {
+		self.0.bytes.load(Ordering::Relaxed)
+	}
+}
@@ -2,7 +2,7 @@
 //! There is a fixed-size pool of chunk replication streams created when a client is authenticated.
 //!
 //! See [Identifier] for stream graph.
-use crate::common::network::replication::world::RecvChunks;
+use crate::common::network::replication::world::{Backlog, RecvChunks};
 use socknet::{connection::Connection, stream};
 use std::sync::Weak;
 
@@ -16,11 +16,13 @@ pub mod client;
 pub mod server;
 
 /// Creates a chunk replication stream for the provided connection,
-/// given the proper channel for cross-thread communication.
+/// given the proper channel for cross-thread communication,
+/// and the backlog of the connection which is drained as chunks are written.
 pub fn spawn(
 	connection: Weak<Connection>,
 	index: usize,
 	recv_chunks: RecvChunks,
+	backlog: Backlog,
 ) -> anyhow::Result<()> {
 	let arc = Connection::upgrade(&connection)?;
 	let log = format!(
@@ -31,7 +33,9 @@ pub fn spawn(
 	arc.spawn(log, async move {
 		use stream::handler::Initiator;
 		let mut stream = server::Sender::open(&connection)?.await?;
-		stream.send_until_closed(index, recv_chunks).await?;
+		stream
+			.send_until_closed(index, recv_chunks, backlog)
+			.await?;
 		Ok(())
 	});
 	Ok(())
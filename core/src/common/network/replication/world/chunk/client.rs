@@ -84,13 +84,15 @@ impl Handler {
 		use stream::kind::Read;
 		let start_time = Instant::now();
 
+		let version = self.recv.read::<u64>().await?;
 		let block_count = self.recv.read_size().await?;
 		let mut contents = Vec::with_capacity(block_count);
 		for _ in 0..block_count {
 			let offset = self.recv.read::<Point3<u8>>().await?;
 			let offset = offset.cast::<usize>();
 			let block_id = self.recv.read::<block::LookupId>().await?;
-			contents.push((offset, block_id));
+			let state = self.recv.read::<block::State>().await?;
+			contents.push((offset, block_id, state));
 		}
 
 		let end_time = Instant::now();
@@ -124,7 +126,7 @@ impl Handler {
 
 		self.context
 			.client_chunk_sender()?
-			.try_send(chunk::Operation::Insert(coord, contents))?;
+			.try_send(chunk::Operation::Insert(coord, version, contents))?;
 
 		Ok(())
 	}
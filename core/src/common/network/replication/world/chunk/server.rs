@@ -1,5 +1,6 @@
 use crate::{
-	common::network::replication::world::RecvChunks, server::world::chunk::Chunk as ServerChunk,
+	common::network::replication::world::{Backlog, RecvChunks},
+	server::world::chunk::Chunk as ServerChunk,
 };
 use anyhow::Result;
 use socknet::{
@@ -48,16 +49,27 @@ impl Sender {
 	/// When it is, only one of the streams takes ownership of that chunk and performs the entire replication for it.
 	///
 	/// When a replication is complete, the stream goes back to being idle.
-	pub async fn send_until_closed(&mut self, index: usize, recv_chunks: RecvChunks) -> Result<()> {
+	/// Every chunk received is removed from the connection's backlog, whether it was written or not.
+	pub async fn send_until_closed(
+		&mut self,
+		index: usize,
+		recv_chunks: RecvChunks,
+		backlog: Backlog,
+	) -> Result<()> {
 		use stream::kind::Write;
 		self.send.write_size(index).await?;
-		while let Ok(weak_server_chunk) = recv_chunks.recv().await {
-			let arc_server_chunk = match weak_server_chunk.upgrade() {
+		while let Ok(queued) = recv_chunks.recv().await {
+			let arc_server_chunk = match queued.chunk.upgrade() {
 				Some(arc) => arc,
 				// If the chunk has been unloaded, then we dont need to replicated it.
-				None => return Ok(()),
+				None => {
+					backlog.pop(queued.size);
+					continue;
+				}
 			};
-			self.write_chunk(arc_server_chunk).await?;
+			let result = self.write_chunk(arc_server_chunk).await;
+			backlog.pop(queued.size);
+			result?;
 		}
 		Ok(())
 	}
@@ -72,12 +84,15 @@ impl Sender {
 
 		self.send.write(&chunk.coordinate).await?;
 
+		self.send.write(&chunk.version).await?;
+
 		self.send.write_size(chunk.block_ids.len()).await?;
 
-		for (offset, block_id) in chunk.block_ids.into_iter() {
+		for (offset, block_id, state) in chunk.blocks().into_iter() {
 			let offset = offset.cast::<u8>();
 			self.send.write(&offset).await?;
 			self.send.write(&block_id).await?;
+			self.send.write(&state).await?;
 		}
 
 		Ok(())
@@ -4,8 +4,16 @@
 //! what chunks to discard locally (were previously relevant and are no longer relevant)
 //! and what chunks to expect in the chunk replication streams (no previously relevant and are now relevant).
 //!
+//! Changes to blocks in chunks the client already has are also sent on this stream,
+//! so they arrive in order with the relevance they were filtered by.
+//!
 //! See [Identifier] for stream graph.
-use crate::common::network::replication::world::{RecvUpdate, SendChunks};
+use crate::{
+	block,
+	common::network::replication::world::{RecvUpdate, SendChunks},
+	entity::system::replicator::relevancy::Relevance,
+};
+use serde::{Deserialize, Serialize};
 use socknet::connection::Connection;
 use std::sync::Weak;
 
@@ -18,12 +26,28 @@ pub mod client;
 /// Context & Handler for the server/sender.
 pub mod server;
 
+/// A message written by the server to the world relevancy stream.
+#[derive(Serialize, Deserialize)]
+pub enum Message {
+	/// The seed of the world the client is in, sent before anything else
+	/// and again whenever the client is moved into another world.
+	/// Not acknowledged.
+	Seed(u64),
+	/// The relevance of the client changed. The client acknowledges it before any new chunks are sent.
+	Relevance(Relevance),
+	/// Blocks changed in chunks the client was already sent, in the order they changed,
+	/// with the [`version`](crate::common::world::chunk::Chunk::version) of the chunk after each change.
+	/// Not acknowledged.
+	BlockChanges(Vec<(block::Point, Option<(block::LookupId, block::State)>, u64)>),
+}
+
 /// Creates a world relevancy stream for the provided connection,
 /// given the proper channels for cross-thread communication.
 pub fn spawn(
 	connection: Weak<Connection>,
 	channel: RecvUpdate,
 	send_chunks: SendChunks,
+	world_seed: u64,
 ) -> anyhow::Result<()> {
 	use socknet::stream;
 	let arc = Connection::upgrade(&connection)?;
@@ -31,7 +55,9 @@ pub fn spawn(
 	arc.spawn(log, async move {
 		use stream::handler::Initiator;
 		let mut stream = server::Sender::open(&connection)?.await?;
-		stream.send_until_closed(channel, send_chunks).await?;
+		stream
+			.send_until_closed(world_seed, channel, send_chunks)
+			.await?;
 		Ok(())
 	});
 	Ok(())
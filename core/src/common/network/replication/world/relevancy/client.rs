@@ -40,6 +40,20 @@ impl AppContext {
 		let client = arc.read().map_err(|_| FailedToReadClient)?;
 		Ok(client.chunk_sender().clone())
 	}
+
+	/// Records the radius of the client's relevance,
+	/// so the [`voxel fog`](crate::graphics::voxel::camera::Fog) ends where chunks stop being relevant.
+	fn set_client_relevance_radius(&self, radius: Option<u64>) -> Result<()> {
+		use crate::common::network::Error::{
+			FailedToReadStorage, FailedToWriteClient, InvalidClient, InvalidStorage,
+		};
+		let arc_storage = self.storage.upgrade().ok_or(InvalidStorage)?;
+		let storage = arc_storage.read().map_err(|_| FailedToReadStorage)?;
+		let arc = storage.client().as_ref().ok_or(InvalidClient)?;
+		let mut client = arc.write().map_err(|_| FailedToWriteClient)?;
+		client.set_relevance_radius(radius);
+		Ok(())
+	}
 }
 
 /// The stream handler for the client/receiver of a world-relevancy stream.
@@ -69,20 +83,60 @@ impl stream::handler::Receiver for Handler {
 		self.connection.clone().spawn(log.clone(), async move {
 			use stream::kind::{Read, Write};
 
-			// Read any incoming relevancy until the client is disconnected.
-			while let Ok(relevance) = self.recv.read::<relevancy::Relevance>().await {
-				// Get the set of chunks which are only in the old relevance,
+			// Read any incoming relevancy until the client is disconnected
+			// (or the server sends relevance with an unsupported schema version).
+			loop {
+				let relevance = match self.recv.read::<super::Message>().await {
+					Ok(super::Message::Relevance(relevance)) => relevance,
+					Ok(super::Message::Seed(seed)) => {
+						if let Ok(sender) = self.context.client_chunk_sender() {
+							sender.try_send(chunk::Operation::Seed(seed))?;
+						}
+						continue;
+					}
+					Ok(super::Message::BlockChanges(changes)) => {
+						// The instance buffer defers changes to chunks it has not received yet.
+						if let Ok(sender) = self.context.client_chunk_sender() {
+							sender.try_send(chunk::Operation::SetBlocks(changes))?;
+						}
+						continue;
+					}
+					Err(error) => {
+						log::debug!(target: &log, "Stopped reading relevance: {:?}", error);
+						break;
+					}
+				};
+				// Get the set of chunks which are only in the old relevance (and those only in the new relevance),
 				// and write the new relevance to the shared list.
-				let old_chunk_cuboids = {
+				let (old_chunk_cuboids, new_chunk_cuboids) = {
 					// Contain the write-lock on local relevance to only this block
 					// so it doesn't get held after the acknowledgement is sent.
 					let mut local_relevance = self.context.local_relevance.write().unwrap();
 					// Compare old relevance with new relevance to determine what chunks are no longer relevant
-					let cuboids = local_relevance.difference(&relevance);
+					let old_cuboids = local_relevance.moved_difference(&relevance);
+					let new_cuboids = relevance.moved_difference(&local_relevance);
 					// Save new relevance (before sending acknowledgement) so that the incoming chunk packets are actually processed
 					*local_relevance = relevance.clone();
-					cuboids
+					(old_cuboids, new_cuboids)
 				};
+				let _ = self
+					.context
+					.set_client_relevance_radius(relevance.max_radius());
+
+				// Predict the newly relevant chunks before the server starts sending them,
+				// so they can be displayed while the authoritative chunks are replicated.
+				// They are queued before the acknowledgement, so they are ahead of any authoritative chunks.
+				if let Ok(sender) = self.context.client_chunk_sender() {
+					let mut new_chunks = Vec::new();
+					for cuboid in new_chunk_cuboids.into_iter() {
+						let cuboid_coords: HashSet<Point3<i64>> = cuboid.into();
+						new_chunks.extend(cuboid_coords.into_iter());
+					}
+					relevance.sort_vec_by_sig_dist(&mut new_chunks);
+					for coord in new_chunks.into_iter() {
+						sender.try_send(chunk::Operation::Predict(coord))?;
+					}
+				}
 
 				// Acknowledge that the relevancy was received and we are
 				// ready to receive the individual streams for chunk data.
@@ -114,6 +168,7 @@ impl stream::handler::Receiver for Handler {
 			if let Ok(mut local) = self.context.local_relevance.write() {
 				*local = relevancy::Relevance::default();
 			}
+			let _ = self.context.set_client_relevance_radius(None);
 
 			Ok(())
 		});
@@ -13,6 +13,8 @@ use crate::common::network::replication::world::relevancy::{client, server};
 /// 	autonumber
 /// 	participant S as Server
 /// 	participant C as Client
+/// 	S->>C: World Seed (not acknowledged)
+/// 	Note over C: Predict new chunks with the seed
 /// 	loop server::Sender::send_until_closed
 /// 		S->>C: Update Relevance (list of spheres)
 /// 		Note over C: Perform relevancy diff
@@ -21,6 +23,10 @@ use crate::common::network::replication::world::relevancy::{client, server};
 /// 		Note over S: Enqueue chunks to be replicated by pool
 /// 		Note over C: Sort old chunks by significant distance
 /// 		Note over C: Enqueue old chunks to be discarded
+/// 		opt Blocks changed in chunks the client has
+/// 			S->>C: Block Changes (not acknowledged)
+/// 			Note over C: Enqueue changes (deferring those to chunks not yet received)
+/// 		end
 /// 	end
 /// ```
 pub struct Identifier {
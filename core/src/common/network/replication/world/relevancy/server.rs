@@ -46,11 +46,18 @@ impl Sender {
 	/// Ongoing async task which dispatches relevancy updates to the client.
 	/// When each update is acknowledged, the relevant chunks are sent
 	/// through the provided send channel to be replicated.
+	///
+	/// The seed of the client's world is sent first, so the client can predict chunks before they are sent.
 	pub async fn send_until_closed(
 		&mut self,
+		world_seed: u64,
 		channel: RecvUpdate,
 		send_chunks: SendChunks,
 	) -> Result<()> {
+		{
+			use stream::kind::Write;
+			self.send.write(&super::Message::Seed(world_seed)).await?;
+		}
 		while let Ok(update) = channel.recv().await {
 			match update {
 				relevancy::WorldUpdate::Relevance(relevance) => {
@@ -63,6 +70,16 @@ impl Sender {
 						send_chunks.send(chunk).await?;
 					}
 				}
+				relevancy::WorldUpdate::Seed(seed) => {
+					use stream::kind::Write;
+					self.send.write(&super::Message::Seed(seed)).await?;
+				}
+				relevancy::WorldUpdate::BlockChanges(changes) => {
+					use stream::kind::Write;
+					self.send
+						.write(&super::Message::BlockChanges(changes))
+						.await?;
+				}
 			}
 		}
 		Ok(())
@@ -73,7 +90,9 @@ impl Sender {
 		use stream::kind::{Read, Write};
 
 		// Send a net relevancy notification
-		self.send.write(&relevance).await?;
+		self.send
+			.write(&super::Message::Relevance(relevance))
+			.await?;
 
 		// Wait for acknowledgement byte from client
 		let _ = self.recv.read_size().await?;
@@ -1,6 +1,6 @@
 use crate::{
 	app::state::ArcLockMachine, common::network::connection, common::network::mode,
-	entity::ArcLockEntityWorld,
+	common::physics, entity::ArcLockEntityWorld,
 };
 use anyhow::Result;
 use socknet::endpoint::{Config, Endpoint};
@@ -16,6 +16,10 @@ pub struct Storage {
 	server: Option<ArcLockServer>,
 	endpoint: Option<Arc<Endpoint>>,
 	connection_list: Option<Arc<RwLock<connection::List>>>,
+	/// The settings the physics simulation runs with, which are those of the default world while a server is running.
+	physics: physics::ArcLockSettings,
+	/// The voxels which entities collide with, which are those of each world while a server is running.
+	terrain: physics::ArcLockTerrain,
 }
 
 impl Storage {
@@ -31,7 +35,7 @@ impl Storage {
 			app_state.write().unwrap().add_callback(
 				OperationKey(None, Some(Enter), Some(Disconnecting)),
 				move |_operation| {
-					assert!(mode::get() == mode::Kind::Client);
+					assert!(mode::is_dedicated_client(mode::get()));
 					mode::set(mode::Set::empty());
 					if let Ok(mut storage) = callback_storage.write() {
 						storage.client = None;
@@ -69,6 +73,8 @@ impl Storage {
 						storage.client = None;
 						storage.endpoint = None;
 						storage.connection_list = None;
+						*storage.physics.write().unwrap() = physics::Settings::default();
+						*storage.terrain.write().unwrap() = physics::Terrain::default();
 					}
 				},
 			);
@@ -146,6 +152,10 @@ impl Storage {
 		self.endpoint = Some(endpoint);
 	}
 
+	pub fn endpoint(&self) -> Option<&Arc<Endpoint>> {
+		self.endpoint.as_ref()
+	}
+
 	pub fn set_connection_list(&mut self, list: Arc<RwLock<connection::List>>) {
 		self.connection_list = Some(list);
 	}
@@ -154,15 +164,51 @@ impl Storage {
 		self.connection_list.as_ref().unwrap()
 	}
 
+	pub fn physics_settings(&self) -> &physics::ArcLockSettings {
+		&self.physics
+	}
+
+	pub fn physics_terrain(&self) -> &physics::ArcLockTerrain {
+		&self.terrain
+	}
+
 	pub fn start_loading(&self, entity_world: &ArcLockEntityWorld) -> anyhow::Result<()> {
 		if let Some(arc_server) = self.server.as_ref() {
 			if let Ok(mut server) = arc_server.write() {
 				server.start_loading_world()?;
 				server.initialize_systems(&entity_world);
+				use crate::server::network::DEFAULT_WORLD;
+				if let Some(arc_database) = server.world(DEFAULT_WORLD) {
+					let settings = *arc_database.read().unwrap().settings().physics();
+					*self.physics.write().unwrap() = settings;
+				}
+				let names = server.world_names().cloned().collect::<Vec<_>>();
+				for name in names.into_iter() {
+					self.add_physics_terrain(&server, &name);
+				}
 			}
 		}
 		Ok(())
 	}
+
+	/// Loads (or creates) a world on the hosted server while it is running,
+	/// so players can be moved into it.
+	pub fn load_server_world(&self, name: &str) -> anyhow::Result<()> {
+		let arc_server = self.server.as_ref().ok_or(Error::InvalidServer)?;
+		let mut server = arc_server.write().map_err(|_| Error::FailedToWriteServer)?;
+		server.load_world(name)?;
+		self.add_physics_terrain(&server, name);
+		Ok(())
+	}
+
+	/// Makes the entities in a world of the hosted server collide with its voxels.
+	fn add_physics_terrain(&self, server: &crate::server::network::Storage, name: &str) {
+		if let Some(arc_database) = server.world(name) {
+			let voxels: Arc<dyn physics::Voxels> =
+				arc_database.read().unwrap().chunk_cache().clone();
+			self.terrain.write().unwrap().insert(name, voxels);
+		}
+	}
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -1,10 +1,7 @@
 use super::Instruction;
 use crate::{
 	app::{self, state::ArcLockMachine},
-	common::{
-		network::{connection, mode, Storage},
-		utility::get_named_arg,
-	},
+	common::network::{connection, mode, NetworkConfig, Storage},
 	entity::{self, ArcLockEntityWorld},
 	server::network::Storage as ServerStorage,
 };
@@ -24,11 +21,15 @@ pub fn load_dedicated_server(
 		&entity_world,
 		&Instruction {
 			mode: mode::Kind::Server.into(),
-			port: get_named_arg("host_port"),
+			network: NetworkConfig::from_args("host_port"),
 			world_name: Some("tmp".to_owned()),
 			server_url: None,
 		},
 	)?;
+	#[cfg(feature = "metrics")]
+	crate::server::metrics::start_endpoint(
+		crate::common::utility::get_named_arg("metrics_port").unwrap_or(9100),
+	);
 	app_state
 		.write()
 		.unwrap()
@@ -80,7 +81,7 @@ pub fn add_load_network_listener(
 					if instruction.mode.contains(mode::Kind::Client) {
 						use crate::common::network::handshake::client::Handshake;
 						use socknet::stream::handler::Initiator;
-						let url = match instruction.mode == mode::Kind::Client {
+						let url = match mode::is_dedicated_client(instruction.mode) {
 							true => instruction.server_url.unwrap().parse()?,
 							false => endpoint.address(),
 						};
@@ -113,11 +114,15 @@ fn load_network(
 		storage.write().unwrap().set_client(Default::default());
 	}
 
-	let socknet_port = instruction.port.unwrap_or(25565);
+	let heartbeat = Arc::new(crate::common::network::heartbeat::AppContext {
+		storage: Arc::downgrade(&storage),
+		trackers: Default::default(),
+	});
 	let endpoint = {
-		use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 		let endpoint_config = storage.read().unwrap().create_config()?;
-		let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), socknet_port);
+		// Invalid token lengths are reported before the server starts, rather than when a client first connects.
+		let auth_token = crate::common::network::handshake::TokenConfig::from_args()?;
+		let address = instruction.network.address();
 		let network_config = Config {
 			endpoint: endpoint_config,
 			address,
@@ -132,9 +137,16 @@ fn load_network(
 					server: Arc::new(handshake::server::AppContext {
 						storage: Arc::downgrade(&storage),
 						entity_world: entity_world.clone(),
+						token: auth_token,
 					}),
 				});
 				registry.register(client_joined::Identifier::default());
+				registry.register(client_left::Identifier::new(Arc::new(
+					client_left::AppContext {
+						storage: Arc::downgrade(&storage),
+					},
+				)));
+				registry.register(world_ready::Identifier::new(Arc::downgrade(&app_state)));
 				registry.register(replication::entity::Identifier {
 					server: Arc::default(),
 					client: Arc::new(replication::entity::client::AppContext {
@@ -149,10 +161,15 @@ fn load_network(
 						sequencer: Default::default(),
 					}),
 				});
+				registry.register(heartbeat::Identifier::new(heartbeat.clone()));
+				registry.register(clock::Identifier::new(Arc::downgrade(&storage)));
 				registry
 			}),
 		};
-		let endpoint = network_config.build()?;
+		// The address is only checked when the endpoint binds to it, so the port can't be taken in between.
+		let endpoint = network_config
+			.build()
+			.map_err(|err| instruction.network.bind_error(err))?;
 
 		if let Ok(mut storage) = storage.write() {
 			storage.set_endpoint(endpoint.clone());
@@ -166,6 +183,20 @@ fn load_network(
 			endpoint.connection_receiver().clone(),
 		));
 		storage.start_loading(&entity_world.upgrade().unwrap())?;
+		if let Some(arc_server) = storage.server() {
+			let send_heartbeats = crate::common::network::heartbeat::SendHeartbeats::new(
+				&heartbeat,
+				storage.connection_list(),
+			);
+			let mut server = arc_server.write().unwrap();
+			server.add_system(send_heartbeats);
+			let replicate_clock = crate::common::network::clock::ReplicateClock::new(
+				server.clock(),
+				storage.connection_list(),
+				crate::server::tick::ticks_per_second(),
+			);
+			server.add_system(replicate_clock);
+		}
 	}
 
 	Ok(endpoint)
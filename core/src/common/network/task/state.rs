@@ -1,9 +1,10 @@
-use crate::common::network::mode;
+use crate::common::network::{mode, NetworkConfig};
 
 #[derive(Clone)]
 pub struct Instruction {
 	pub mode: mode::Set,
-	pub port: Option<u16>,
+	/// Where the local endpoint is bound.
+	pub network: NetworkConfig,
 	pub world_name: Option<String>,
 	pub server_url: Option<String>,
 }
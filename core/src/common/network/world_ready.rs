@@ -0,0 +1,234 @@
+//! Stream sent by the server once the world around a newly authenticated client
+//! has been replicated, so the client knows it is safe to leave the loading screen.
+use crate::{
+	app::state::{self, ArcLockMachine, State},
+	common::network::{mode, CloseCode},
+};
+use anyhow::Result;
+use socknet::{
+	connection::{self, Connection},
+	stream,
+};
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+/// How long a dedicated client will wait on the loading screen for the world to be ready,
+/// before giving up and returning to the main menu.
+pub static TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The reason a client gives the server when it closes the connection after [`TIMEOUT`].
+pub static WORLD_NOT_READY: &'static str = "the world was not ready in time";
+
+pub struct Identifier(Arc<AppContext>);
+impl Identifier {
+	pub fn new(app_state: Weak<RwLock<state::Machine>>) -> Self {
+		Self(Arc::new(AppContext { app_state }))
+	}
+}
+impl stream::Identifier for Identifier {
+	type SendBuilder = AppContext;
+	type RecvBuilder = AppContext;
+	fn unique_id() -> &'static str {
+		"world_ready"
+	}
+	fn send_builder(&self) -> &Arc<Self::SendBuilder> {
+		&self.0
+	}
+	fn recv_builder(&self) -> &Arc<Self::RecvBuilder> {
+		&self.0
+	}
+}
+
+pub struct AppContext {
+	/// The client's application state, so it can be transitioned into the game once the world is ready.
+	app_state: Weak<RwLock<state::Machine>>,
+}
+impl stream::send::AppContext for AppContext {
+	type Opener = stream::uni::Opener;
+}
+impl stream::recv::AppContext for AppContext {
+	type Extractor = stream::uni::Extractor;
+	type Receiver = Receiver;
+}
+
+/// Opens the stream to the provided client, telling it that the world is ready.
+pub fn send(connection: Weak<Connection>) -> Result<()> {
+	let arc = Connection::upgrade(&connection)?;
+	let log = <Identifier as stream::Identifier>::log_category("server", &arc);
+	arc.spawn(log, async move {
+		use stream::handler::Initiator;
+		let stream = Sender::open(&connection)?.await?;
+		stream.send().await?;
+		Ok(())
+	});
+	Ok(())
+}
+
+pub struct Sender {
+	#[allow(dead_code)]
+	context: Arc<AppContext>,
+	#[allow(dead_code)]
+	connection: Arc<Connection>,
+	send: stream::kind::send::Ongoing,
+}
+impl From<stream::send::Context<AppContext>> for Sender {
+	fn from(context: stream::send::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			send: context.stream,
+		}
+	}
+}
+impl stream::handler::Initiator for Sender {
+	type Identifier = Identifier;
+}
+impl Sender {
+	pub async fn send(mut self) -> Result<()> {
+		use stream::kind::{Send, Write};
+		self.send.write(&true).await?;
+		self.send.finish().await?;
+		Ok(())
+	}
+}
+
+pub struct Receiver {
+	context: Arc<AppContext>,
+	connection: Arc<Connection>,
+	recv: stream::kind::recv::Ongoing,
+}
+impl From<stream::recv::Context<AppContext>> for Receiver {
+	fn from(context: stream::recv::Context<AppContext>) -> Self {
+		Self {
+			context: context.builder,
+			connection: context.connection,
+			recv: context.stream,
+		}
+	}
+}
+impl stream::handler::Receiver for Receiver {
+	type Identifier = Identifier;
+	fn receive(mut self) {
+		use connection::Active;
+		let log = format!(
+			"{}[{}]",
+			<Identifier as stream::Identifier>::unique_id(),
+			self.connection.remote_address()
+		);
+		self.connection.clone().spawn(log.clone(), async move {
+			use stream::kind::Read;
+			let _ready = self.recv.read::<bool>().await?;
+			log::info!(target: &log, "World is ready");
+			if let Some(app_state) = self.context.app_state.upgrade() {
+				enter_world(&app_state);
+			}
+			Ok(())
+		});
+	}
+}
+
+fn is_waiting_for_world(app_state: &state::Machine) -> bool {
+	let is_loading = matches!(app_state.get(), State::Connecting | State::LoadingWorld);
+	is_loading && !app_state.has_next_transition()
+}
+
+/// Called once the client has been authenticated by the server.
+///
+/// Dedicated clients stay on the loading screen until the server sends the world ready signal,
+/// giving up after [`TIMEOUT`] and closing the connection with the reason why.
+/// Integrated clients share the server's world, so they enter the game immediately.
+pub fn wait_for_world(app_state: &ArcLockMachine, connection: &Arc<Connection>) {
+	if !begin_waiting(app_state, mode::get()) {
+		return;
+	}
+	let weak_app_state = Arc::downgrade(app_state);
+	let weak_connection = Arc::downgrade(connection);
+	let log = <Identifier as stream::Identifier>::log_category("client", connection);
+	connection.spawn(log, async move {
+		tokio::time::sleep(TIMEOUT).await;
+		// The timeout only applies to the connection it was started for,
+		// so it must not affect the client if it has since disconnected (or joined another server).
+		let arc_connection = match Connection::upgrade(&weak_connection) {
+			Ok(arc) => arc,
+			Err(_) => return Ok(()),
+		};
+		if let Some(app_state) = weak_app_state.upgrade() {
+			if abandon_world(&app_state) {
+				use connection::Active;
+				arc_connection.close(CloseCode::WorldNotReady as u32, WORLD_NOT_READY.as_bytes());
+			}
+		}
+		Ok(())
+	});
+}
+
+/// Enters the game immediately if the client does not need to wait for the world to be replicated,
+/// because integrated clients share the server's world.
+/// Returns true if the client must wait for the world ready signal.
+fn begin_waiting(app_state: &ArcLockMachine, mode: mode::Set) -> bool {
+	if !mode::is_dedicated_client(mode) {
+		enter_world(app_state);
+		return false;
+	}
+	true
+}
+
+/// Transitions the client into the game, if it is still waiting on the world to be ready.
+/// Returns true if the transition was enqueued.
+pub fn enter_world(app_state: &ArcLockMachine) -> bool {
+	let mut app_state = app_state.write().unwrap();
+	if !is_waiting_for_world(&app_state) {
+		return false;
+	}
+	app_state.transition_to(State::InGame, None);
+	true
+}
+
+/// Leaves the server if the client is still waiting on the world to be ready,
+/// returning to the main menu so the player can try to join again.
+/// Returns true if the transition was enqueued.
+pub fn abandon_world(app_state: &ArcLockMachine) -> bool {
+	let mut app_state = app_state.write().unwrap();
+	if !is_waiting_for_world(&app_state) {
+		return false;
+	}
+	log::error!(
+		target: "world_ready",
+		"Server did not finish sending the world within {}s, disconnecting.",
+		TIMEOUT.as_secs()
+	);
+	// Only dedicated clients wait for the world, so there is never a local server to unload.
+	app_state.transition_to(State::Disconnecting, None);
+	true
+}
+
+#[cfg(test)]
+mod waiting {
+	use super::*;
+
+	#[test]
+	fn authenticated_client_waits_for_signal() {
+		let app_state = state::Machine::new(State::Connecting).arclocked();
+		assert!(begin_waiting(&app_state, mode::Kind::Client.into()));
+		assert!(!app_state.read().unwrap().has_next_transition());
+		assert!(enter_world(&app_state));
+		assert!(app_state.read().unwrap().has_next_transition());
+	}
+
+	#[test]
+	fn integrated_client_enters_immediately() {
+		let app_state = state::Machine::new(State::LoadingWorld).arclocked();
+		let mode = mode::Kind::Server | mode::Kind::Client;
+		assert!(!begin_waiting(&app_state, mode));
+		assert!(app_state.read().unwrap().has_next_transition());
+	}
+
+	#[test]
+	fn signal_is_ignored_once_in_game() {
+		let app_state = state::Machine::new(State::InGame).arclocked();
+		assert!(!enter_world(&app_state));
+		assert!(!abandon_world(&app_state));
+	}
+}
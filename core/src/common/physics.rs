@@ -0,0 +1,68 @@
+//! Settings for the physics simulation which are chosen when a world is created,
+//! and saved with the world's [`settings`](crate::server::world::Settings),
+//! and the [`collision`](move_and_collide) of entities with the voxels of the world.
+use engine::math::nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+mod collision;
+pub use collision::*;
+
+/// Alias for Arc<RwLock<[`Settings`]>>, shared between the world which owns the settings and the physics system.
+pub type ArcLockSettings = Arc<RwLock<Settings>>;
+
+/// Standard gravity, in meters per second squared.
+pub static DEFAULT_GRAVITY: Vector3<f32> = Vector3::new(0.0, -9.81, 0.0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+	/// The acceleration of every [`dynamic`](crate::entity::component::physics::Dynamic) body,
+	/// in meters per second squared.
+	#[serde(default = "Settings::default_gravity")]
+	gravity: Vector3<f32>,
+	/// How many world units (blocks) make up a meter.
+	#[serde(default = "Settings::default_units_per_meter")]
+	units_per_meter: f32,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			gravity: Self::default_gravity(),
+			units_per_meter: Self::default_units_per_meter(),
+		}
+	}
+}
+
+impl Settings {
+	fn default_gravity() -> Vector3<f32> {
+		DEFAULT_GRAVITY
+	}
+
+	fn default_units_per_meter() -> f32 {
+		1.0
+	}
+
+	pub fn with_gravity(mut self, gravity: Vector3<f32>) -> Self {
+		self.gravity = gravity;
+		self
+	}
+
+	pub fn with_units_per_meter(mut self, units_per_meter: f32) -> Self {
+		self.units_per_meter = units_per_meter;
+		self
+	}
+
+	pub fn gravity(&self) -> &Vector3<f32> {
+		&self.gravity
+	}
+
+	pub fn units_per_meter(&self) -> f32 {
+		self.units_per_meter
+	}
+
+	/// The acceleration of gravity in world units per second squared.
+	pub fn acceleration(&self) -> Vector3<f32> {
+		self.gravity * self.units_per_meter
+	}
+}
@@ -0,0 +1,287 @@
+use crate::{
+	block::{self, Collider, Shape},
+	common::world::chunk::SIZE_I,
+	entity::component::physics::groups::Groups,
+};
+use engine::math::nalgebra::{Point3, Vector3};
+use std::{
+	collections::HashMap,
+	sync::{Arc, RwLock},
+};
+
+/// Alias for Arc<RwLock<[`Terrain`]>>, shared between the worlds which are loaded (if any) and the physics system.
+pub type ArcLockTerrain = Arc<RwLock<Terrain>>;
+
+/// The voxels of each world which is loaded, by world name.
+/// Empty when no worlds are loaded, such as on clients, which don't know the voxels of the world.
+#[derive(Default, Clone)]
+pub struct Terrain(HashMap<String, Arc<dyn Voxels>>);
+
+impl Terrain {
+	pub fn insert(&mut self, world: &str, voxels: Arc<dyn Voxels>) {
+		self.0.insert(world.to_owned(), voxels);
+	}
+
+	/// Returns the voxels of a world, if it is loaded.
+	pub fn get(&self, world: &str) -> Option<&Arc<dyn Voxels>> {
+		self.0.get(world)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+/// The voxels of a world, which the colliders of entities are stopped against.
+pub trait Voxels: Send + Sync {
+	/// Returns the world-space colliders of the voxel at a point (which is empty for air),
+	/// or None if the chunk containing the point is not loaded.
+	fn colliders_at(&self, point: &block::Point) -> Option<Vec<Collider>>;
+}
+
+/// The axes a box is moved along, vertical first so that bodies land before they slide.
+static AXIS_ORDER: [usize; 3] = [1, 0, 2];
+
+/// How close (in blocks) two faces are considered to be touching, to absorb floating point error.
+static EPSILON: f64 = 1e-6;
+
+/// Moves a box of `size` (whose position is the center of its bottom face) by `displacement`, one axis at a time,
+/// stopping it against the colliders of any voxels in the way which interact with the box's `groups`.
+/// Voxels in chunks which are not loaded are treated as solid, so bodies never move into the unknown.
+///
+/// Returns the position the box was moved to, and which axes it was stopped on.
+pub fn move_and_collide(
+	voxels: &dyn Voxels,
+	position: Point3<f64>,
+	size: &Vector3<f32>,
+	groups: &Groups,
+	displacement: Vector3<f64>,
+) -> (Point3<f64>, Vector3<bool>) {
+	let mut position = position;
+	let mut stopped = Vector3::new(false, false, false);
+	for &axis in AXIS_ORDER.iter() {
+		let delta = displacement[axis];
+		if delta == 0.0 {
+			continue;
+		}
+		let (min, max) = bounds(&position, size);
+
+		// The voxels the box could pass through while moving along the axis.
+		let (mut region_min, mut region_max) = (min, max);
+		match delta > 0.0 {
+			true => region_max[axis] += delta,
+			false => region_min[axis] += delta,
+		}
+		let first = region_min.map(|v| (v + EPSILON).floor() as i64);
+		let last = region_max.map(|v| (v - EPSILON).floor() as i64);
+
+		let mut allowed = delta;
+		for x in first.x..=last.x {
+			for y in first.y..=last.y {
+				for z in first.z..=last.z {
+					let point = block_point(Point3::new(x, y, z));
+					let colliders = voxels
+						.colliders_at(&point)
+						.unwrap_or_else(|| Shape::cube().colliders_at(&point));
+					for collider in colliders.into_iter() {
+						if !groups.interacts_with(&collider.groups) {
+							continue;
+						}
+						let half_extents = collider.half_extents.cast::<f64>();
+						let (other_min, other_max) = (
+							collider.center - half_extents,
+							collider.center + half_extents,
+						);
+						// Colliders which are only touching the box on another axis are not in the way.
+						let is_overlapping = (0..3).filter(|&i| i != axis).all(|i| {
+							other_min[i] < max[i] - EPSILON && min[i] + EPSILON < other_max[i]
+						});
+						if !is_overlapping {
+							continue;
+						}
+						allowed = match delta > 0.0 {
+							true if other_min[axis] >= max[axis] - EPSILON => {
+								allowed.min(other_min[axis] - max[axis])
+							}
+							false if other_max[axis] <= min[axis] + EPSILON => {
+								allowed.max(other_max[axis] - min[axis])
+							}
+							// The box is already inside the collider, which does not stop it from moving out.
+							_ => allowed,
+						};
+					}
+				}
+			}
+		}
+		// Boxes touching a collider are stopped where they are, instead of being pushed back by floating point error.
+		let allowed = match delta > 0.0 {
+			true => allowed.max(0.0),
+			false => allowed.min(0.0),
+		};
+		position[axis] += allowed;
+		stopped[axis] = allowed != delta;
+	}
+	(position, stopped)
+}
+
+/// Returns the minimum and maximum corners of a box of `size`, whose position is the center of its bottom face.
+pub fn bounds(position: &Point3<f64>, size: &Vector3<f32>) -> (Point3<f64>, Point3<f64>) {
+	let size = size.cast::<f64>();
+	let half_width = Vector3::new(size.x * 0.5, 0.0, size.z * 0.5);
+	(
+		position - half_width,
+		position + half_width + Vector3::new(0.0, size.y, 0.0),
+	)
+}
+
+/// Returns the point of the voxel at a world-space block coordinate.
+fn block_point(coordinate: Point3<i64>) -> block::Point {
+	let mut chunk = Point3::origin();
+	let mut offset = Point3::origin();
+	for i in 0..3 {
+		let size = SIZE_I[i] as i64;
+		chunk[i] = coordinate[i].div_euclid(size);
+		offset[i] = coordinate[i].rem_euclid(size) as i8;
+	}
+	block::Point::new(chunk, offset)
+}
+
+#[cfg(test)]
+mod move_and_collide {
+	use super::*;
+
+	/// A world where the voxels at the coordinates matching a predicate are full blocks,
+	/// and every chunk is loaded.
+	struct Solid(fn(&Point3<i64>) -> bool);
+
+	impl Voxels for Solid {
+		fn colliders_at(&self, point: &block::Point) -> Option<Vec<Collider>> {
+			let mut coordinate = Point3::origin();
+			for i in 0..3 {
+				coordinate[i] = point.chunk()[i] * SIZE_I[i] as i64 + point.offset()[i] as i64;
+			}
+			Some(match (self.0)(&coordinate) {
+				true => Shape::cube().colliders_at(point),
+				false => Vec::new(),
+			})
+		}
+	}
+
+	fn player_size() -> Vector3<f32> {
+		Vector3::new(0.6, 1.8, 0.6)
+	}
+
+	#[test]
+	fn lands_on_the_floor() {
+		let (position, stopped) = move_and_collide(
+			&Solid(|coordinate| coordinate.y < 0),
+			Point3::new(0.5, 0.25, 0.5),
+			&player_size(),
+			&Groups::player(),
+			Vector3::new(0.0, -1.0, 0.0),
+		);
+		assert_eq!(position, Point3::new(0.5, 0.0, 0.5));
+		assert_eq!(stopped, Vector3::new(false, true, false));
+	}
+
+	#[test]
+	fn falls_through_air() {
+		let (position, stopped) = move_and_collide(
+			&Solid(|coordinate| coordinate.y < -10),
+			Point3::new(0.5, 0.0, 0.5),
+			&player_size(),
+			&Groups::player(),
+			Vector3::new(0.0, -1.5, 0.0),
+		);
+		assert_eq!(position, Point3::new(0.5, -1.5, 0.5));
+		assert_eq!(stopped, Vector3::new(false, false, false));
+	}
+
+	#[test]
+	fn walks_along_the_floor() {
+		// Standing on the floor does not stop horizontal movement, even when crossing block boundaries.
+		let (position, stopped) = move_and_collide(
+			&Solid(|coordinate| coordinate.y < 0),
+			Point3::new(-0.5, 0.0, 0.5),
+			&player_size(),
+			&Groups::player(),
+			Vector3::new(2.0, 0.0, 0.0),
+		);
+		assert_eq!(position, Point3::new(1.5, 0.0, 0.5));
+		assert_eq!(stopped, Vector3::new(false, false, false));
+	}
+
+	#[test]
+	fn stopped_by_walls() {
+		// A wall at x=2, which is walked into diagonally, only stops movement along x.
+		let (position, stopped) = move_and_collide(
+			&Solid(|coordinate| coordinate.y < 0 || coordinate.x >= 2),
+			Point3::new(0.5, 0.0, 0.5),
+			&player_size(),
+			&Groups::player(),
+			Vector3::new(2.0, 0.0, 1.0),
+		);
+		assert!((position.x - 1.7).abs() < 1e-9, "stopped at {}", position.x);
+		assert_eq!(position.z, 1.5);
+		assert_eq!(stopped, Vector3::new(true, false, false));
+	}
+
+	#[test]
+	fn passes_through_colliders_it_does_not_interact_with() {
+		// Voxels whose colliders are only in the item layer, which items do not collide with.
+		struct Items;
+		impl Voxels for Items {
+			fn colliders_at(&self, point: &block::Point) -> Option<Vec<Collider>> {
+				let colliders = Shape::cube().colliders_at(point);
+				Some(
+					colliders
+						.into_iter()
+						.map(|collider| Collider {
+							groups: Groups::item(),
+							..collider
+						})
+						.collect(),
+				)
+			}
+		}
+		let displacement = Vector3::new(0.0, -1.5, 0.0);
+		let (position, stopped) = move_and_collide(
+			&Items,
+			Point3::new(0.5, 0.0, 0.5),
+			&Vector3::new(0.25, 0.25, 0.25),
+			&Groups::item(),
+			displacement,
+		);
+		assert_eq!(position, Point3::new(0.5, -1.5, 0.5));
+		assert_eq!(stopped, Vector3::new(false, false, false));
+
+		// Players do collide with items.
+		let (position, _) = move_and_collide(
+			&Items,
+			Point3::new(0.5, 0.0, 0.5),
+			&player_size(),
+			&Groups::player(),
+			displacement,
+		);
+		assert_eq!(position, Point3::new(0.5, 0.0, 0.5));
+	}
+
+	#[test]
+	fn unloaded_chunks_are_solid() {
+		struct Unloaded;
+		impl Voxels for Unloaded {
+			fn colliders_at(&self, _point: &block::Point) -> Option<Vec<Collider>> {
+				None
+			}
+		}
+		let (position, stopped) = move_and_collide(
+			&Unloaded,
+			Point3::new(0.5, 0.0, 0.5),
+			&player_size(),
+			&Groups::player(),
+			Vector3::new(0.0, -3.0, 0.0),
+		);
+		assert_eq!(position, Point3::new(0.5, 0.0, 0.5));
+		assert_eq!(stopped, Vector3::new(false, true, false));
+	}
+}
@@ -1,9 +1,15 @@
 mod data_file;
 pub use data_file::*;
 
+mod dirty_set;
+pub use dirty_set::*;
+
 mod multi_hash_map;
 pub use multi_hash_map::*;
 
+pub mod versioned;
+pub use versioned::Versioned;
+
 pub fn get_named_arg(name: &str) -> Option<u16> {
 	std::env::args().find_map(|arg| {
 		let prefix = format!("-{}=", name);
@@ -0,0 +1,180 @@
+use std::{cmp::Ordering, collections::HashSet, hash::Hash};
+
+/// A set of keys which have changed and are waiting to be processed
+/// (e.g. chunks to replicate, or voxels whose faces need to be recalculated).
+///
+/// Marking a key which is already dirty has no effect, so each key is processed at most once per drain.
+/// Keys are kept in the order they were first marked, unless they are marked at a specific index
+/// or the set is [`sorted`](DirtySet::sort_by) by some other priority.
+pub struct DirtySet<K: Hash> {
+	keys: HashSet<K>,
+	order: Vec<K>,
+}
+
+impl<K> Default for DirtySet<K>
+where
+	K: Hash,
+{
+	fn default() -> Self {
+		Self {
+			keys: HashSet::new(),
+			order: Vec::new(),
+		}
+	}
+}
+
+impl<K> DirtySet<K>
+where
+	K: Hash + Eq + Clone,
+{
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.order.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.order.is_empty()
+	}
+
+	pub fn contains(&self, key: &K) -> bool {
+		self.keys.contains(key)
+	}
+
+	/// Marks the key as dirty, appending it to the end of the order.
+	/// Returns false if the key was already dirty.
+	pub fn mark(&mut self, key: K) -> bool {
+		let idx = self.order.len();
+		self.mark_at(idx, key)
+	}
+
+	/// Marks the key as dirty, inserting it at a specific position in the order.
+	/// Returns false if the key was already dirty (in which case its position is unchanged).
+	pub fn mark_at(&mut self, idx: usize, key: K) -> bool {
+		if !self.keys.insert(key.clone()) {
+			return false;
+		}
+		self.order.insert(idx, key);
+		true
+	}
+
+	/// Iterates over the dirty keys in order, without clearing them.
+	pub fn iter(&self) -> std::slice::Iter<'_, K> {
+		self.order.iter()
+	}
+
+	/// Removes and returns every dirty key in order, leaving the set empty.
+	pub fn drain(&mut self) -> std::vec::Drain<'_, K> {
+		self.keys.clear();
+		self.order.drain(..)
+	}
+
+	/// Removes and returns the first dirty key.
+	pub fn pop_front(&mut self) -> Option<K> {
+		if self.order.is_empty() {
+			return None;
+		}
+		let key = self.order.remove(0);
+		self.keys.remove(&key);
+		Some(key)
+	}
+
+	/// Removes and returns the last dirty key.
+	pub fn pop_back(&mut self) -> Option<K> {
+		let key = self.order.pop()?;
+		self.keys.remove(&key);
+		Some(key)
+	}
+
+	/// Clears any keys which no longer need to be processed.
+	pub fn retain<F>(&mut self, mut f: F)
+	where
+		F: FnMut(&K) -> bool,
+	{
+		self.keys.retain(|key| f(key));
+		let keys = &self.keys;
+		self.order.retain(|key| keys.contains(key));
+	}
+
+	/// Reorders the dirty keys by some priority.
+	pub fn sort_by<F>(&mut self, compare: F)
+	where
+		F: FnMut(&K, &K) -> Ordering,
+	{
+		self.order.sort_by(compare);
+	}
+
+	/// Binary searches the order of the dirty keys, which must already be sorted by the same priority as `f`.
+	/// See [`slice::binary_search_by`].
+	pub fn binary_search_by<F>(&self, f: F) -> Result<usize, usize>
+	where
+		F: FnMut(&K) -> Ordering,
+	{
+		self.order.binary_search_by(f)
+	}
+
+	/// Returns the dirty keys in order.
+	pub fn into_vec(self) -> Vec<K> {
+		self.order
+	}
+}
+
+impl<K> std::iter::FromIterator<K> for DirtySet<K>
+where
+	K: Hash + Eq + Clone,
+{
+	fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+		let mut set = Self::new();
+		for key in iter {
+			set.mark(key);
+		}
+		set
+	}
+}
+
+#[cfg(test)]
+mod dirty_set {
+	use super::*;
+
+	#[test]
+	fn mark_is_unique() {
+		let mut set = DirtySet::new();
+		assert!(set.mark(1));
+		assert!(set.mark(2));
+		assert!(!set.mark(1));
+		assert_eq!(set.len(), 2);
+		assert!(set.contains(&1));
+		assert!(!set.contains(&3));
+	}
+
+	#[test]
+	fn drain_clears_in_marked_order() {
+		let mut set = DirtySet::new();
+		for key in [3, 1, 2, 1, 3].iter() {
+			set.mark(*key);
+		}
+		assert_eq!(set.drain().collect::<Vec<_>>(), vec![3, 1, 2]);
+		assert!(set.is_empty());
+		assert!(!set.contains(&1));
+		// Keys can be marked again once drained.
+		assert!(set.mark(1));
+		assert_eq!(set.iter().cloned().collect::<Vec<_>>(), vec![1]);
+	}
+
+	#[test]
+	fn priority_order() {
+		let mut set = vec![5, 1, 4].into_iter().collect::<DirtySet<_>>();
+		set.sort_by(|a, b| a.cmp(b));
+		let idx = set
+			.binary_search_by(|key| key.cmp(&3))
+			.unwrap_or_else(|idx| idx);
+		assert!(set.mark_at(idx, 3));
+		assert!(!set.mark_at(0, 5));
+		assert_eq!(set.pop_back(), Some(5));
+		assert_eq!(set.pop_front(), Some(1));
+		set.retain(|key| *key != 4);
+		assert_eq!(set.into_vec(), vec![3]);
+	}
+}
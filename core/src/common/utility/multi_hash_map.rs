@@ -111,6 +111,14 @@ where
 		found_in
 	}
 
+	/// Returns true if the value is in the set of the key.
+	pub fn contains(&self, key: &K, value: &V) -> bool {
+		match self.0.get(key) {
+			Some(set) => set.contains(value),
+			None => false,
+		}
+	}
+
 	pub fn keys<'a>(&'a self) -> Keys<'a, K, HashSet<V>> {
 		self.0.keys()
 	}
@@ -119,6 +127,16 @@ where
 		self.0
 	}
 
+	/// Returns the number of keys which contain the value.
+	pub fn count_value(&self, value: &V) -> usize {
+		self.0.values().filter(|set| set.contains(value)).count()
+	}
+
+	/// Returns the total number of key-value pairs in the set.
+	pub fn total_len(&self) -> usize {
+		self.0.values().map(|values| values.len()).sum()
+	}
+
 	pub fn difference(&self, other: &Self) -> Self
 	where
 		K: Clone,
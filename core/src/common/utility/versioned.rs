@@ -0,0 +1,122 @@
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The bytes at the start of every versioned file, which are followed by the version of the format.
+/// Files which do not start with these bytes were written before files were versioned (version 0).
+static HEADER: &'static [u8; 4] = b"CSVF";
+
+pub type Version = u16;
+
+/// A binary format which is written with its version,
+/// so that data saved by an older version of the application can be upgraded when it is loaded.
+pub trait Versioned: Serialize + DeserializeOwned {
+	/// The version that data is currently written as.
+	const VERSION: Version;
+
+	/// Reads data written by an older version of the format, upgrading it to the current version.
+	fn migrate(version: Version, bytes: &[u8]) -> Result<Self>;
+
+	fn to_versioned_bytes(&self) -> Result<Vec<u8>> {
+		let mut bytes = HEADER.to_vec();
+		bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+		bytes.append(&mut bincode::serialize(&self)?);
+		Ok(bytes)
+	}
+
+	fn from_versioned_bytes(bytes: &[u8]) -> Result<Self> {
+		let (version, data) = split_version(bytes)?;
+		if version == Self::VERSION {
+			Ok(bincode::deserialize(data)?)
+		} else if version < Self::VERSION {
+			Self::migrate(version, data)
+		} else {
+			Err(Error::UnsupportedVersion(version, Self::VERSION))?
+		}
+	}
+}
+
+/// Returns the version of some versioned data, and the data following the version header.
+pub fn split_version(bytes: &[u8]) -> Result<(Version, &[u8]), Error> {
+	let data = match bytes.strip_prefix(&HEADER[..]) {
+		Some(data) => data,
+		None => return Ok((0, bytes)),
+	};
+	if data.len() < std::mem::size_of::<Version>() {
+		return Err(Error::TruncatedHeader);
+	}
+	let (version, data) = data.split_at(std::mem::size_of::<Version>());
+	let version = Version::from_le_bytes([version[0], version[1]]);
+	Ok((version, data))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("data was saved with version {0}, but only versions up to {1} are supported")]
+	UnsupportedVersion(Version, Version),
+	#[error("there is no migration from version {0} to version {1}")]
+	NoMigration(Version, Version),
+	#[error("the version header is incomplete")]
+	TruncatedHeader,
+}
+
+#[cfg(test)]
+mod versioned {
+	use super::*;
+	use serde::Deserialize;
+
+	#[derive(Serialize, Deserialize, Debug, PartialEq)]
+	struct Named {
+		name: String,
+		count: u32,
+	}
+
+	impl Versioned for Named {
+		const VERSION: Version = 1;
+
+		fn migrate(version: Version, bytes: &[u8]) -> Result<Self> {
+			match version {
+				// Version 0 only had a name.
+				0 => Ok(Self {
+					name: bincode::deserialize(bytes)?,
+					count: 0,
+				}),
+				_ => Err(Error::NoMigration(version, Self::VERSION))?,
+			}
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let value = Named {
+			name: "stone".to_owned(),
+			count: 3,
+		};
+		let bytes = value.to_versioned_bytes().unwrap();
+		assert_eq!(split_version(&bytes).unwrap().0, 1);
+		assert_eq!(Named::from_versioned_bytes(&bytes).unwrap(), value);
+	}
+
+	#[test]
+	fn unversioned_data_is_migrated() {
+		let bytes = bincode::serialize(&"stone".to_owned()).unwrap();
+		assert_eq!(
+			Named::from_versioned_bytes(&bytes).unwrap(),
+			Named {
+				name: "stone".to_owned(),
+				count: 0
+			}
+		);
+	}
+
+	#[test]
+	fn newer_version_is_rejected() {
+		let mut bytes = HEADER.to_vec();
+		bytes.extend_from_slice(&2u16.to_le_bytes());
+		bytes.append(&mut bincode::serialize(&"stone".to_owned()).unwrap());
+		let err = Named::from_versioned_bytes(&bytes).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<Error>(),
+			Some(Error::UnsupportedVersion(2, 1))
+		));
+	}
+}
@@ -1,2 +1,6 @@
 pub mod chunk;
+pub mod clock;
 pub mod generator;
+pub mod light;
+pub mod raycast;
+pub mod schematic;
@@ -1,10 +1,25 @@
 //! Contains all world chunk structures around submitting chunk tickets, data contained in a chunk, and how chunks are loaded.
 
-use engine::math::nalgebra::Vector3;
+use engine::math::nalgebra::{Point3, Vector3};
 pub static DIAMETER: usize = 16;
 pub static RADIUS: i8 = 8;
 pub static SIZE_I: Vector3<usize> = Vector3::new(DIAMETER, DIAMETER, DIAMETER);
 pub static SIZE: Vector3<f32> = Vector3::new(16.0, 16.0, 16.0);
+/// The number of blocks in a chunk.
+pub static VOLUME: usize = DIAMETER * DIAMETER * DIAMETER;
+
+/// Returns the index of a block offset in the dense layout of a chunk,
+/// where the x-axis changes fastest, then the z-axis, and the y-axis slowest.
+pub fn offset_index(offset: &Point3<usize>) -> usize {
+	offset.x + offset.z * SIZE_I.x + offset.y * SIZE_I.x * SIZE_I.z
+}
+
+/// Returns the block offset at an index in the dense layout of a chunk.
+/// The inverse of [`offset_index`].
+pub fn index_offset(index: usize) -> Point3<usize> {
+	let layer = SIZE_I.x * SIZE_I.z;
+	Point3::new(index % SIZE_I.x, index / layer, (index % layer) / SIZE_I.x)
+}
 
 mod chunk;
 pub use chunk::*;
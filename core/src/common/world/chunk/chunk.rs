@@ -1,4 +1,10 @@
-use crate::block;
+use crate::{
+	block,
+	common::{
+		utility::versioned::{self, Version, Versioned},
+		world::light::{self, LightMap},
+	},
+};
 use engine::{asset, math::nalgebra::Point3};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -8,6 +14,16 @@ pub struct Chunk {
 	/// The coordinate of the chunk in the world.
 	pub(crate) coordinate: Point3<i64>,
 	pub(crate) block_ids: HashMap<Point3<usize>, block::LookupId>,
+	/// The state of each block whose state is not the [`default`](block::DEFAULT_STATE).
+	#[serde(default)]
+	pub(crate) block_states: HashMap<Point3<usize>, block::State>,
+	/// Incremented every time a block in the chunk changes (and never reset, even when the chunk is reloaded),
+	/// so copies of the chunk (and changes to it) can be ordered.
+	pub(crate) version: u64,
+	/// The light of every voxel in the chunk, from the blocks in the chunk which emit light.
+	/// Not saved, it is [`recalculated`](Self::relight) when the chunk is generated or loaded.
+	#[serde(skip)]
+	pub(crate) light: LightMap,
 }
 
 impl Chunk {
@@ -15,6 +31,9 @@ impl Chunk {
 		Self {
 			coordinate,
 			block_ids: HashMap::new(),
+			block_states: HashMap::new(),
+			version: 0,
+			light: LightMap::default(),
 		}
 	}
 
@@ -26,6 +45,78 @@ impl Chunk {
 		&self.block_ids
 	}
 
+	/// The number of times a block in the chunk has changed since it was first created.
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+
+	/// Moves the version forward to at least `version`, e.g. when a saved chunk is known to have been changed more times than its blocks show.
+	pub(crate) fn advance_version(&mut self, version: u64) {
+		self.version = self.version.max(version);
+	}
+
+	/// The light of every voxel in the chunk.
+	/// Light only spreads within the chunk, light from the blocks of neighboring chunks is not included.
+	pub fn light(&self) -> &LightMap {
+		&self.light
+	}
+
+	/// Recalculates the light of every voxel in the chunk from the blocks in the chunk which emit light.
+	pub fn relight(&mut self) {
+		profiling::scope!("relight-chunk");
+		self.light = LightMap::default();
+		let coordinate = self.coordinate;
+		let emitters = self
+			.block_ids
+			.iter()
+			.filter(|(_offset, id)| block::Lookup::light_emission_of(**id) > 0)
+			.map(|(offset, _id)| block::Point::new(coordinate, offset.cast::<i8>()))
+			.collect::<Vec<_>>();
+		light::light_chunk(self, coordinate, emitters);
+	}
+
+	/// Updates the light around a point in the chunk after the block at that point has changed.
+	pub fn update_light(&mut self, offset: Point3<usize>) {
+		let point = block::Point::new(self.coordinate, offset.cast::<i8>());
+		light::update_block(self, point);
+	}
+
+	/// Returns the state of the block at a point, or the default state if the point has no state or is empty (air).
+	pub fn block_state(&self, point: &Point3<usize>) -> block::State {
+		self.block_states
+			.get(point)
+			.cloned()
+			.unwrap_or(block::DEFAULT_STATE)
+	}
+
+	/// Returns the block-type and state of every non-empty point in the chunk.
+	pub fn blocks(&self) -> Vec<(Point3<usize>, block::LookupId, block::State)> {
+		self.block_ids
+			.iter()
+			.map(|(offset, id)| (*offset, *id, self.block_state(offset)))
+			.collect()
+	}
+
+	/// Returns the approximate number of bytes it takes to replicate the chunk to a client.
+	/// Mirrors the layout written by the chunk replication stream.
+	pub fn replicated_size(&self) -> usize {
+		use std::mem::size_of;
+		let header = size_of::<Point3<i64>>() + size_of::<u64>() + size_of::<usize>();
+		let per_block =
+			size_of::<Point3<u8>>() + size_of::<block::LookupId>() + size_of::<block::State>();
+		header + self.block_ids.len() * per_block
+	}
+
+	/// Returns the block ids of the chunk as a dense array,
+	/// where each block is at the [`index of its offset`](super::offset_index).
+	pub fn block_id_array(&self) -> Vec<Option<block::LookupId>> {
+		let mut ids = vec![None; super::VOLUME];
+		for (offset, id) in self.block_ids.iter() {
+			ids[super::offset_index(offset)] = Some(*id);
+		}
+		ids
+	}
+
 	pub fn set_block(&mut self, point: Point3<usize>, id: Option<&asset::Id>) {
 		let id = match id {
 			Some(asset_id) => match block::Lookup::lookup_value(&asset_id) {
@@ -37,14 +128,161 @@ impl Chunk {
 		self.set_block_id(point, id);
 	}
 
+	/// Sets the block-type at a point, resetting its state to the [`default`](block::DEFAULT_STATE).
 	pub fn set_block_id(&mut self, point: Point3<usize>, id: Option<block::LookupId>) {
-		match id {
-			Some(block_id) => {
+		self.set_block_id_with_state(point, id.map(|id| (id, block::DEFAULT_STATE)));
+	}
+
+	pub fn set_block_id_with_state(
+		&mut self,
+		point: Point3<usize>,
+		block: Option<(block::LookupId, block::State)>,
+	) {
+		self.version += 1;
+		match block {
+			Some((block_id, state)) => {
 				self.block_ids.insert(point, block_id);
+				match state {
+					block::DEFAULT_STATE => self.block_states.remove(&point),
+					state => self.block_states.insert(point, state),
+				};
 			}
 			None => {
 				self.block_ids.remove(&point);
+				self.block_states.remove(&point);
 			}
 		}
 	}
 }
+
+/// The voxels of a single chunk, which light is spread through without leaving the chunk.
+impl light::Volume for Chunk {
+	fn light(&self, point: &block::Point) -> Option<light::Light> {
+		match *point.chunk() == self.coordinate {
+			true => Some(self.light.get(&point.offset().cast::<usize>())),
+			false => None,
+		}
+	}
+
+	fn set_light(&mut self, point: &block::Point, light: light::Light) {
+		if *point.chunk() == self.coordinate {
+			self.light.set(&point.offset().cast::<usize>(), light);
+		}
+	}
+
+	fn emission(&self, point: &block::Point) -> u8 {
+		match self.block_ids.get(&point.offset().cast::<usize>()) {
+			Some(id) if *point.chunk() == self.coordinate => block::Lookup::light_emission_of(*id),
+			_ => 0,
+		}
+	}
+
+	fn is_opaque(&self, point: &block::Point) -> bool {
+		match self.block_ids.get(&point.offset().cast::<usize>()) {
+			Some(id) if *point.chunk() == self.coordinate => block::Lookup::is_opaque_of(*id),
+			_ => false,
+		}
+	}
+}
+
+/// The layout of a chunk before blocks had states.
+#[derive(Deserialize)]
+struct ChunkV0 {
+	coordinate: Point3<i64>,
+	block_ids: HashMap<Point3<usize>, block::LookupId>,
+}
+
+/// The layout of a chunk before it had a version.
+#[derive(Deserialize)]
+struct ChunkV1 {
+	coordinate: Point3<i64>,
+	block_ids: HashMap<Point3<usize>, block::LookupId>,
+	block_states: HashMap<Point3<usize>, block::State>,
+}
+
+impl Versioned for Chunk {
+	const VERSION: Version = 2;
+
+	fn migrate(version: Version, bytes: &[u8]) -> anyhow::Result<Self> {
+		match version {
+			0 => {
+				let chunk: ChunkV0 = bincode::deserialize(bytes)?;
+				Ok(Self {
+					coordinate: chunk.coordinate,
+					block_ids: chunk.block_ids,
+					block_states: HashMap::new(),
+					version: 0,
+					light: LightMap::default(),
+				})
+			}
+			1 => {
+				let chunk: ChunkV1 = bincode::deserialize(bytes)?;
+				Ok(Self {
+					coordinate: chunk.coordinate,
+					block_ids: chunk.block_ids,
+					block_states: chunk.block_states,
+					version: 0,
+					light: LightMap::default(),
+				})
+			}
+			_ => Err(versioned::Error::NoMigration(version, Self::VERSION))?,
+		}
+	}
+}
+
+#[cfg(test)]
+mod serialization {
+	use super::*;
+	use serde::Serialize;
+
+	#[test]
+	fn migrates_v0() {
+		#[derive(Serialize)]
+		struct WriteV0 {
+			coordinate: Point3<i64>,
+			block_ids: HashMap<Point3<usize>, block::LookupId>,
+		}
+		let v0 = WriteV0 {
+			coordinate: Point3::new(1, -2, 3),
+			block_ids: HashMap::from([(Point3::new(0, 0, 0), 4), (Point3::new(5, 6, 7), 2)]),
+		};
+		let bytes = bincode::serialize(&v0).unwrap();
+
+		let chunk = Chunk::from_versioned_bytes(&bytes).unwrap();
+		assert_eq!(chunk.coordinate, v0.coordinate);
+		assert_eq!(chunk.block_ids, v0.block_ids);
+		assert_eq!(
+			chunk.block_state(&Point3::new(5, 6, 7)),
+			block::DEFAULT_STATE
+		);
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut chunk = Chunk::new(Point3::new(0, 1, 0));
+		chunk.set_block_id(Point3::new(1, 2, 3), Some(1));
+		chunk.set_block_id_with_state(Point3::new(4, 5, 6), Some((2, 7)));
+
+		let bytes = chunk.to_versioned_bytes().unwrap();
+		let loaded = Chunk::from_versioned_bytes(&bytes).unwrap();
+		assert_eq!(loaded.coordinate, chunk.coordinate);
+		assert_eq!(loaded.block_ids, chunk.block_ids);
+		assert_eq!(loaded.block_state(&Point3::new(4, 5, 6)), 7);
+		assert_eq!(loaded.version(), 2);
+	}
+
+	#[test]
+	fn edits_increment_version() {
+		let mut chunk = Chunk::new(Point3::new(0, 0, 0));
+		assert_eq!(chunk.version(), 0);
+		chunk.set_block_id(Point3::new(1, 2, 3), Some(1));
+		chunk.set_block_id(Point3::new(1, 2, 3), None);
+		chunk.set_block_id_with_state(Point3::new(4, 5, 6), Some((2, 7)));
+		assert_eq!(chunk.version(), 3);
+		// The version never moves backwards.
+		chunk.advance_version(1);
+		assert_eq!(chunk.version(), 3);
+		chunk.advance_version(10);
+		assert_eq!(chunk.version(), 10);
+	}
+}
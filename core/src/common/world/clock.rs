@@ -0,0 +1,292 @@
+//! The time of day, which is advanced by the server each tick and [`replicated`](crate::common::network::clock) to clients.
+//!
+//! A day starts at sunrise, is noon a quarter of the way through, sunset half way through, and midnight three quarters of the way through.
+use engine::math::nalgebra::Vector4;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// The number of ticks in a full day (20 minutes at the default tick rate).
+pub static TICKS_PER_DAY: u64 = 24000;
+
+/// The time of day a new clock starts at (shortly after sunrise).
+pub static DEFAULT_TIME_OF_DAY: u64 = 1000;
+
+/// The brightness of voxels (without block light) at midnight, where 1.0 is the brightness at noon.
+pub static MIN_AMBIENT: f32 = 0.2;
+
+/// The sky color at noon.
+static DAY_SKY: [f32; 3] = [0.47, 0.65, 1.0];
+/// The sky color at midnight.
+static NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.05];
+
+/// Named times of day which commands can set the clock to.
+pub fn named_time_of_day(name: &str) -> Option<u64> {
+	match name {
+		"day" | "sunrise" => Some(0),
+		"noon" => Some(TICKS_PER_DAY / 4),
+		"sunset" => Some(TICKS_PER_DAY / 2),
+		"night" | "midnight" => Some(TICKS_PER_DAY * 3 / 4),
+		_ => None,
+	}
+}
+
+/// The time a clock was at, as sent from the server to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+	/// The number of ticks since the start of the first day.
+	pub time: u64,
+	/// If true, time is not advancing.
+	pub frozen: bool,
+	/// How many ticks the server runs each second, which the client advances its estimate of the time by.
+	pub ticks_per_second: u32,
+}
+
+/// The server's authoritative time of day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clock {
+	time: u64,
+	frozen: bool,
+	/// Incremented whenever the time is set or frozen (instead of advancing normally),
+	/// so the new time can be replicated right away.
+	revision: u32,
+}
+
+impl Default for Clock {
+	fn default() -> Self {
+		Self {
+			time: DEFAULT_TIME_OF_DAY,
+			frozen: false,
+			revision: 0,
+		}
+	}
+}
+
+impl Clock {
+	/// The number of ticks since the start of the first day.
+	pub fn time(&self) -> u64 {
+		self.time
+	}
+
+	/// The number of ticks since the start of the current day.
+	pub fn time_of_day(&self) -> u64 {
+		self.time % TICKS_PER_DAY
+	}
+
+	/// The number of full days which have passed.
+	pub fn day(&self) -> u64 {
+		self.time / TICKS_PER_DAY
+	}
+
+	pub fn is_frozen(&self) -> bool {
+		self.frozen
+	}
+
+	/// Stops (or resumes) time from advancing.
+	pub fn set_frozen(&mut self, frozen: bool) {
+		self.frozen = frozen;
+		self.revision = self.revision.wrapping_add(1);
+	}
+
+	/// Changes each time the clock is set or frozen.
+	pub fn revision(&self) -> u32 {
+		self.revision
+	}
+
+	/// Advances time by a number of ticks, unless the clock is frozen.
+	pub fn advance(&mut self, ticks: u64) {
+		if !self.frozen {
+			self.time += ticks;
+		}
+	}
+
+	/// Sets the time of day, without changing the day.
+	pub fn set_time_of_day(&mut self, time_of_day: u64) {
+		self.time = self.day() * TICKS_PER_DAY + time_of_day % TICKS_PER_DAY;
+		self.revision = self.revision.wrapping_add(1);
+	}
+
+	pub fn sample(&self, ticks_per_second: u32) -> Sample {
+		Sample {
+			time: self.time,
+			frozen: self.frozen,
+			ticks_per_second,
+		}
+	}
+}
+
+impl std::fmt::Display for Clock {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"day {}, time {}{}",
+			self.day(),
+			self.time_of_day(),
+			match self.frozen {
+				true => " (frozen)",
+				false => "",
+			}
+		)
+	}
+}
+
+/// How long a client takes to smooth out the difference between
+/// its estimate of the time and a sample it receives from the server.
+pub static CORRECTION_DURATION: Duration = Duration::from_secs(1);
+
+/// The largest difference (in ticks) between the client's estimate and a sample which is smoothed out.
+/// Larger differences (e.g. the time being set by a command) are applied immediately.
+pub static MAX_CORRECTION: f64 = 200.0;
+
+/// The client's estimate of the server's clock, which advances between samples
+/// and blends towards each new sample so the sky does not visibly jump when one is received.
+#[derive(Clone, Debug)]
+pub struct Interpolated {
+	sample: Sample,
+	received_at: Instant,
+	/// How far ahead (or behind, if negative) the estimate was of the latest sample when it was received.
+	/// This fades out over the [`CORRECTION_DURATION`].
+	correction: f64,
+}
+
+impl Interpolated {
+	pub fn new(sample: Sample, now: Instant) -> Self {
+		Self {
+			sample,
+			received_at: now,
+			correction: 0.0,
+		}
+	}
+
+	/// Updates the estimate with a sample received from the server.
+	pub fn receive(&mut self, sample: Sample, now: Instant) {
+		let estimate = self.time(now);
+		let error = estimate - sample.time as f64;
+		let can_blend = !self.sample.frozen && !sample.frozen && error.abs() <= MAX_CORRECTION;
+		*self = Self {
+			sample,
+			received_at: now,
+			correction: match can_blend {
+				true => error,
+				false => 0.0,
+			},
+		};
+	}
+
+	/// The estimated number of ticks since the start of the first day.
+	pub fn time(&self, now: Instant) -> f64 {
+		let elapsed = now.saturating_duration_since(self.received_at);
+		let advanced = match self.sample.frozen {
+			true => 0.0,
+			false => elapsed.as_secs_f64() * self.sample.ticks_per_second as f64,
+		};
+		let remaining = 1.0 - (elapsed.as_secs_f64() / CORRECTION_DURATION.as_secs_f64()).min(1.0);
+		self.sample.time as f64 + advanced + self.correction * remaining
+	}
+
+	/// The estimated fraction (0..1) of the current day which has passed.
+	pub fn fraction_of_day(&self, now: Instant) -> f32 {
+		(self.time(now).rem_euclid(TICKS_PER_DAY as f64) / TICKS_PER_DAY as f64) as f32
+	}
+}
+
+/// The color of the sky and the brightness of the world at a time of day.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+	/// The color of the sky (RGBA), which distant voxels fade into.
+	pub color: Vector4<f32>,
+	/// How bright voxels are without any block light, in the range [`MIN_AMBIENT`]..=1.
+	pub ambient: f32,
+}
+
+impl Sky {
+	/// Returns the sky at a fraction (0..1) of the way through a day.
+	pub fn at(fraction_of_day: f32) -> Self {
+		// Fully bright for most of the day, fading over the hour or so around sunrise and sunset.
+		let angle = fraction_of_day * std::f32::consts::PI * 2.0;
+		let daylight = (angle.sin() * 2.0 + 0.5).clamp(0.0, 1.0);
+		let mix = |night: f32, day: f32| night + (day - night) * daylight;
+		Self {
+			color: Vector4::new(
+				mix(NIGHT_SKY[0], DAY_SKY[0]),
+				mix(NIGHT_SKY[1], DAY_SKY[1]),
+				mix(NIGHT_SKY[2], DAY_SKY[2]),
+				1.0,
+			),
+			ambient: mix(MIN_AMBIENT, 1.0),
+		}
+	}
+}
+
+impl Default for Sky {
+	fn default() -> Self {
+		Self::at(0.25)
+	}
+}
+
+#[cfg(test)]
+mod clock {
+	use super::*;
+
+	fn sample(time: u64, frozen: bool) -> Sample {
+		Sample {
+			time,
+			frozen,
+			ticks_per_second: 20,
+		}
+	}
+
+	#[test]
+	fn advances_and_wraps_days() {
+		let mut clock = Clock::default();
+		clock.set_time_of_day(TICKS_PER_DAY - 10);
+		clock.advance(25);
+		assert_eq!((clock.day(), clock.time_of_day()), (1, 15));
+		clock.set_frozen(true);
+		clock.advance(100);
+		assert_eq!(clock.time(), TICKS_PER_DAY + 15);
+		// Setting the time of day keeps the day.
+		clock.set_time_of_day(named_time_of_day("noon").unwrap());
+		assert_eq!(clock.time(), TICKS_PER_DAY + 6000);
+		assert_eq!(clock.to_string(), "day 1, time 6000 (frozen)");
+	}
+
+	#[test]
+	fn interpolation_advances_between_samples() {
+		let start = Instant::now();
+		let clock = Interpolated::new(sample(1000, false), start);
+		assert_eq!(clock.time(start + Duration::from_millis(500)), 1010.0);
+		let frozen = Interpolated::new(sample(1000, true), start);
+		assert_eq!(frozen.time(start + Duration::from_secs(5)), 1000.0);
+	}
+
+	#[test]
+	fn interpolation_blends_small_corrections() {
+		let start = Instant::now();
+		let mut clock = Interpolated::new(sample(1000, false), start);
+		// The client's estimate is 40 ticks ahead (2s at 20 ticks/s = 1040) of the sample it receives.
+		let received_at = start + Duration::from_secs(2);
+		clock.receive(sample(1000, false), received_at);
+		// The estimate doesn't jump when the sample is received...
+		assert_eq!(clock.time(received_at), 1040.0);
+		// ...and is half way to the server's time after half of the correction duration.
+		assert_eq!(clock.time(received_at + CORRECTION_DURATION / 2), 1030.0);
+		assert_eq!(clock.time(received_at + CORRECTION_DURATION), 1020.0);
+
+		// Large differences (e.g. the time being set) are applied immediately.
+		let later = received_at + CORRECTION_DURATION;
+		clock.receive(sample(12000, false), later);
+		assert_eq!(clock.time(later), 12000.0);
+	}
+
+	#[test]
+	fn sky_is_brightest_at_noon() {
+		let noon = Sky::at(0.25);
+		let midnight = Sky::at(0.75);
+		assert_eq!(noon.ambient, 1.0);
+		assert_eq!(midnight.ambient, MIN_AMBIENT);
+		assert!(noon.color.x > midnight.color.x);
+		// Sunrise is part way between night and day.
+		let sunrise = Sky::at(0.0);
+		assert!(sunrise.ambient > MIN_AMBIENT && sunrise.ambient < 1.0);
+	}
+}
@@ -1,2 +1,9 @@
+mod decorator;
+pub use decorator::*;
 mod flat;
 pub use flat::*;
+mod ore;
+pub use ore::*;
+mod pipeline;
+pub use pipeline::*;
+pub mod preview;
@@ -0,0 +1,133 @@
+use crate::{
+	block,
+	common::world::chunk::{self, Chunk},
+};
+use engine::math::nalgebra::{Point3, Vector3};
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+/// A stage of chunk generation which is run after the base [`terrain`](super::Terrain) has been generated,
+/// such as placing ores or decorating the surface with foliage and structures.
+///
+/// Decorators are run in the order they are added to a [`Pipeline`](super::Pipeline),
+/// and are provided with a deterministic random number generator per chunk,
+/// so generating the same chunk with the same world seed always produces the same blocks.
+pub trait Decorator: Send + Sync {
+	fn name(&self) -> &'static str;
+	fn decorate(&self, context: &mut Context);
+}
+
+impl std::fmt::Debug for dyn Decorator + 'static {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Decorator({})", self.name())
+	}
+}
+
+/// A block which a decorator placed outside of the chunk being generated.
+/// Placements are deferred until the chunk they are in is generated or loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+	pub offset: Point3<usize>,
+	pub id: Option<block::LookupId>,
+	/// The block the base terrain generated at the offset, which the decorator saw when it made the placement.
+	pub replaces: Option<block::LookupId>,
+}
+
+impl Placement {
+	/// Returns true if the block at the offset is still the one the decorator saw.
+	/// Blocks which have changed since (e.g. a player broke or placed a block there) are never overwritten.
+	pub fn applies_to(&self, chunk: &Chunk) -> bool {
+		chunk.block_ids().get(&self.offset).cloned() == self.replaces
+	}
+}
+
+/// The chunk being decorated and the data a [`Decorator`] can use to decorate it.
+///
+/// Blocks are addressed by their offset relative to the origin of the chunk being decorated.
+/// Offsets outside of the chunk can be read from (returning the base terrain of the neighboring chunk)
+/// and written to (deferring the placement until the neighbor is generated or loaded).
+pub struct Context<'generator> {
+	chunk: &'generator mut Chunk,
+	terrain: &'generator dyn super::Terrain,
+	neighbors: HashMap<Point3<i64>, Chunk>,
+	deferred: Vec<(Point3<i64>, Placement)>,
+	rng: StdRng,
+}
+
+impl<'generator> Context<'generator> {
+	pub(super) fn new(
+		chunk: &'generator mut Chunk,
+		terrain: &'generator dyn super::Terrain,
+		rng: StdRng,
+	) -> Self {
+		Self {
+			chunk,
+			terrain,
+			neighbors: HashMap::new(),
+			deferred: Vec::new(),
+			rng,
+		}
+	}
+
+	pub fn coordinate(&self) -> Point3<i64> {
+		*self.chunk.coordinate()
+	}
+
+	pub fn rng(&mut self) -> &mut StdRng {
+		&mut self.rng
+	}
+
+	/// Splits an offset relative to the chunk being decorated into
+	/// the coordinate of the chunk it is in and the offset within that chunk.
+	fn split(&self, offset: Point3<i64>) -> (Point3<i64>, Point3<usize>) {
+		let size = chunk::SIZE_I.cast::<i64>();
+		let chunk_offset = Vector3::new(
+			offset.x.div_euclid(size.x),
+			offset.y.div_euclid(size.y),
+			offset.z.div_euclid(size.z),
+		);
+		let block_offset = Point3::new(
+			offset.x.rem_euclid(size.x) as usize,
+			offset.y.rem_euclid(size.y) as usize,
+			offset.z.rem_euclid(size.z) as usize,
+		);
+		(self.coordinate() + chunk_offset, block_offset)
+	}
+
+	/// Returns the block at an offset from the origin of the chunk being decorated.
+	/// Blocks in neighboring chunks are read from their base terrain, without decorations.
+	pub fn block_id(&mut self, offset: Point3<i64>) -> Option<block::LookupId> {
+		let (coordinate, offset) = self.split(offset);
+		if coordinate == self.coordinate() {
+			return self.chunk.block_ids().get(&offset).cloned();
+		}
+		let terrain = self.terrain;
+		let neighbor = self
+			.neighbors
+			.entry(coordinate)
+			.or_insert_with(|| terrain.generate_chunk(coordinate));
+		neighbor.block_ids().get(&offset).cloned()
+	}
+
+	/// Sets the block at an offset from the origin of the chunk being decorated.
+	/// If the offset is in a neighboring chunk, the placement is deferred until that chunk is generated or loaded,
+	/// and only replaces the block its base terrain generated there.
+	pub fn set_block_id(&mut self, offset: Point3<i64>, id: Option<block::LookupId>) {
+		let (coordinate, block_offset) = self.split(offset);
+		if coordinate == self.coordinate() {
+			self.chunk.set_block_id(block_offset, id);
+		} else {
+			let replaces = self.block_id(offset);
+			let placement = Placement {
+				offset: block_offset,
+				id,
+				replaces,
+			};
+			self.deferred.push((coordinate, placement));
+		}
+	}
+
+	pub(super) fn into_deferred(self) -> Vec<(Point3<i64>, Placement)> {
+		self.deferred
+	}
+}
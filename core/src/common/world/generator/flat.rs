@@ -8,11 +8,15 @@ use std::collections::HashMap;
 #[derive(Default)]
 pub struct Flat {
 	layers: HashMap</*chunk-y*/ i64, HashMap</*block-y*/ usize, block::LookupId>>,
-	glass_id: block::LookupId,
-	debug_id: block::LookupId,
+	/// Replaces some blocks of each layer above the bottom, if the block is registered.
+	glass_id: Option<block::LookupId>,
+	/// Placed above the origin chunk, if the block is registered.
+	debug_id: Option<block::LookupId>,
 }
 
 impl Flat {
+	/// The layers of the default world. Blocks which are not in the [`lookup`](block::Lookup)
+	/// (e.g. their assets have not been loaded) are left out, rather than failing to create the generator.
 	pub fn classic() -> Self {
 		let mut cfg = Self::default();
 
@@ -29,8 +33,8 @@ impl Flat {
 
 		cfg.insert((0, 6), &asset::Id::new("vanilla", "blocks/grass/default"));
 
-		cfg.glass_id = Self::lookup(&asset::Id::new("vanilla", "blocks/glass/clear")).unwrap();
-		cfg.debug_id = Self::lookup(&asset::Id::new("crystal-sphinx", "blocks/debug")).unwrap();
+		cfg.glass_id = Self::lookup(&asset::Id::new("vanilla", "blocks/glass/clear"));
+		cfg.debug_id = Self::lookup(&asset::Id::new("crystal-sphinx", "blocks/debug"));
 
 		cfg
 	}
@@ -51,9 +55,17 @@ impl Flat {
 		chunk_layer.insert(layer.1, id);
 	}
 
+	/// Returns the seed for the randomness of a chunk, so that generating the same chunk
+	/// always produces the same blocks (and clients can predict chunks before the server sends them).
+	fn chunk_seed(coordinate: &Point3<i64>) -> u64 {
+		(coordinate.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+			^ (coordinate.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+			^ (coordinate.z as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+	}
+
 	pub fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk {
 		use rand::prelude::*;
-		let mut rng = rand::thread_rng();
+		let mut rng = StdRng::seed_from_u64(Self::chunk_seed(&coordinate));
 		let mut chunk = Chunk::new(coordinate);
 
 		if let Some(layers) = self.layers.get(&coordinate.y) {
@@ -61,10 +73,10 @@ impl Flat {
 				if let Some(&block_id) = layers.get(&y) {
 					for x in 1..chunk::SIZE_I.x - 1 {
 						for z in 1..chunk::SIZE_I.z - 1 {
-							if y > 0 {
+							if let (true, Some(glass_id)) = (y > 0, self.glass_id) {
 								let chance = rng.gen::<usize>() % 100;
 								if chance < 15 {
-									chunk.set_block_id(Point3::new(x, y, z), Some(self.glass_id));
+									chunk.set_block_id(Point3::new(x, y, z), Some(glass_id));
 									continue;
 								}
 							}
@@ -76,10 +88,24 @@ impl Flat {
 			}
 		}
 
-		if coordinate == Point3::origin() {
-			chunk.set_block_id(Point3::new(8, 10, 8), Some(self.debug_id));
+		if let (true, Some(debug_id)) = (coordinate == Point3::origin(), self.debug_id) {
+			chunk.set_block_id(Point3::new(8, 10, 8), Some(debug_id));
 		}
 
 		chunk
 	}
 }
+
+#[cfg(test)]
+mod classic {
+	use super::*;
+
+	#[test]
+	fn unregistered_blocks_are_left_out() {
+		// No block lookup is initialized in tests, so none of the classic layers are registered.
+		let flat = Flat::classic();
+		assert!(flat.layers.is_empty());
+		let chunk = flat.generate_chunk(Point3::origin());
+		assert!(chunk.block_ids().is_empty());
+	}
+}
@@ -0,0 +1,29 @@
+use super::{Context, Decorator};
+use crate::{block, common::world::chunk};
+use engine::math::nalgebra::Point3;
+
+/// Replaces a number of randomly chosen blocks of one type in each chunk with an ore.
+/// Each block is chosen at most once per chunk, so a chunk entirely made of the `replaces`
+/// block will contain exactly `per_chunk` ore blocks.
+pub struct Ore {
+	pub ore: block::LookupId,
+	pub replaces: block::LookupId,
+	pub per_chunk: usize,
+}
+
+impl Decorator for Ore {
+	fn name(&self) -> &'static str {
+		"ore"
+	}
+
+	fn decorate(&self, context: &mut Context) {
+		let count = self.per_chunk.min(chunk::VOLUME);
+		let indices = rand::seq::index::sample(context.rng(), chunk::VOLUME, count);
+		for index in indices.into_iter() {
+			let offset: Point3<i64> = chunk::index_offset(index).cast::<i64>();
+			if context.block_id(offset) == Some(self.replaces) {
+				context.set_block_id(offset, Some(self.ore));
+			}
+		}
+	}
+}
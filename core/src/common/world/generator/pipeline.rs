@@ -0,0 +1,300 @@
+use super::{Context, Decorator, Placement};
+use crate::common::world::chunk::Chunk;
+use engine::math::nalgebra::Point3;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+/// The most placements which can wait for their chunks to be generated or loaded at once.
+/// Placements beyond this are dropped, so decorating a region which is never visited again cannot grow the queue forever.
+pub static MAX_DEFERRED_PLACEMENTS: usize = 65_536;
+
+/// The first stage of chunk generation, which creates the blocks of a chunk without any decorations.
+pub trait Terrain: Send + Sync {
+	fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk;
+}
+
+impl Terrain for super::Flat {
+	fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk {
+		super::Flat::generate_chunk(self, coordinate)
+	}
+}
+
+/// Generates chunks in stages: the base [`terrain`](Terrain),
+/// followed by each [`decorator`](Decorator) in the order they were added.
+///
+/// Decorators can place blocks outside of the chunk being generated (e.g. a tree on the edge of a chunk).
+/// Those placements are queued until the chunk they belong to is generated,
+/// or [`taken`](Pipeline::take_deferred) by the server to place them in that chunk when it is already loaded or next loaded.
+/// Placements only replace blocks which are still as the base terrain generated them (see [`Placement::applies_to`]).
+/// The queue is [`bounded`](MAX_DEFERRED_PLACEMENTS) and carried over when the pipeline is [`rebuilt`](Pipeline::with_deferred_from),
+/// but is not saved, so placements into chunks which are not loaded again before the server stops are lost.
+pub struct Pipeline {
+	seed: u64,
+	terrain: Box<dyn Terrain>,
+	decorators: Vec<Arc<dyn Decorator>>,
+	deferred: Mutex<HashMap<Point3<i64>, Vec<Placement>>>,
+}
+
+impl Pipeline {
+	pub fn new<T>(seed: u64, terrain: T) -> Self
+	where
+		T: Terrain + 'static,
+	{
+		Self {
+			seed,
+			terrain: Box::new(terrain),
+			decorators: Vec::new(),
+			deferred: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Creates the pipeline that worlds are generated with: [`classic`](super::Flat::classic) terrain,
+	/// decorated by every decorator the active plugins register (in the order they are registered).
+	/// Used by both the server, to generate chunks, and clients, to predict them.
+	pub fn for_world(seed: u64, plugins: &crate::plugin::Manager) -> Self {
+		let mut pipeline = Self::new(seed, super::Flat::classic());
+		let mut decorators = Vec::new();
+		plugins.register_decorators(&mut decorators);
+		for decorator in decorators.into_iter() {
+			pipeline.add_decorator(decorator);
+		}
+		pipeline
+	}
+
+	/// Returns a numerical seed for the world seed in a [`Settings`](crate::server::world::Settings) file.
+	/// Stable across platforms and versions, so a world generates the same chunks wherever it is opened.
+	pub fn seed_from_str(seed: &str) -> u64 {
+		// FNV-1a
+		seed.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+			(hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+		})
+	}
+
+	/// Takes the placements which are still queued in a pipeline this one replaces
+	/// (e.g. when the decorators are registered again after the plugins are reloaded).
+	pub fn with_deferred_from(self, previous: Self) -> Self {
+		*self.deferred.lock().unwrap() = previous.deferred.into_inner().unwrap();
+		self
+	}
+
+	pub fn with_decorator(mut self, decorator: Arc<dyn Decorator>) -> Self {
+		self.add_decorator(decorator);
+		self
+	}
+
+	pub fn add_decorator(&mut self, decorator: Arc<dyn Decorator>) {
+		self.decorators.push(decorator);
+	}
+
+	pub fn decorators(&self) -> &Vec<Arc<dyn Decorator>> {
+		&self.decorators
+	}
+
+	fn decorator_seed(&self, coordinate: &Point3<i64>, stage: usize) -> u64 {
+		self.seed
+			^ (coordinate.x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+			^ (coordinate.y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+			^ (coordinate.z as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+			^ (stage as u64).wrapping_mul(0x27D4_EB2F_1656_67C5)
+	}
+
+	#[profiling::function]
+	pub fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk {
+		use rand::prelude::*;
+		let mut chunk = self.terrain.generate_chunk(coordinate);
+		let mut deferred = Vec::new();
+		for (stage, decorator) in self.decorators.iter().enumerate() {
+			profiling::scope!("decorate", decorator.name());
+			let rng = StdRng::seed_from_u64(self.decorator_seed(&coordinate, stage));
+			let mut context = Context::new(&mut chunk, self.terrain.as_ref(), rng);
+			decorator.decorate(&mut context);
+			deferred.append(&mut context.into_deferred());
+		}
+		self.defer(deferred);
+		self.apply_deferred(&mut chunk);
+		chunk.relight();
+		chunk
+	}
+
+	fn defer(&self, placements: Vec<(Point3<i64>, Placement)>) {
+		if placements.is_empty() {
+			return;
+		}
+		let mut deferred = self.deferred.lock().unwrap();
+		let mut count = deferred.values().map(Vec::len).sum::<usize>();
+		let mut dropped = 0;
+		for (coordinate, placement) in placements.into_iter() {
+			if count >= MAX_DEFERRED_PLACEMENTS {
+				dropped += 1;
+				continue;
+			}
+			deferred.entry(coordinate).or_default().push(placement);
+			count += 1;
+		}
+		if dropped > 0 {
+			log::warn!(
+				target: "world",
+				"Dropped {} decorator placements, there are already {} placements waiting for their chunks to load",
+				dropped,
+				MAX_DEFERRED_PLACEMENTS
+			);
+		}
+	}
+
+	/// Returns the coordinates of the chunks which have placements waiting for them.
+	pub fn deferred_coordinates(&self) -> Vec<Point3<i64>> {
+		self.deferred.lock().unwrap().keys().cloned().collect()
+	}
+
+	/// Removes the placements that decorators of neighboring chunks made in a chunk,
+	/// so they can be placed in it (see [`Placement::applies_to`]).
+	pub fn take_deferred(&self, coordinate: &Point3<i64>) -> Vec<Placement> {
+		self.deferred
+			.lock()
+			.unwrap()
+			.remove(coordinate)
+			.unwrap_or_default()
+	}
+
+	/// Places any blocks that decorators of neighboring chunks placed in this chunk,
+	/// except where the chunk no longer has the block the decorator saw.
+	/// Returns the number of blocks which were placed.
+	pub fn apply_deferred(&self, chunk: &mut Chunk) -> usize {
+		let mut count = 0;
+		for placement in self.take_deferred(chunk.coordinate()).into_iter() {
+			if placement.applies_to(chunk) {
+				chunk.set_block_id(placement.offset, placement.id);
+				count += 1;
+			}
+		}
+		count
+	}
+}
+
+#[cfg(test)]
+mod decorators {
+	use super::*;
+	use crate::{block, common::world::chunk, common::world::generator::Ore};
+
+	static STONE: block::LookupId = 1;
+	static IRON: block::LookupId = 2;
+
+	/// Fills every chunk with stone.
+	struct Solid;
+	impl Terrain for Solid {
+		fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk {
+			let mut chunk = Chunk::new(coordinate);
+			for index in 0..chunk::VOLUME {
+				chunk.set_block_id(chunk::index_offset(index), Some(STONE));
+			}
+			chunk
+		}
+	}
+
+	/// Places a block one past the positive-x edge of every chunk.
+	struct Overhang;
+	impl Decorator for Overhang {
+		fn name(&self) -> &'static str {
+			"overhang"
+		}
+		fn decorate(&self, context: &mut Context) {
+			context.set_block_id(Point3::new(16, 0, 0), Some(IRON));
+		}
+	}
+
+	fn count(chunk: &Chunk, id: block::LookupId) -> usize {
+		chunk
+			.block_ids()
+			.values()
+			.filter(|&&block| block == id)
+			.count()
+	}
+
+	#[test]
+	fn ore_places_expected_count() {
+		let pipeline =
+			Pipeline::new(Pipeline::seed_from_str("ore"), Solid).with_decorator(Arc::new(Ore {
+				ore: IRON,
+				replaces: STONE,
+				per_chunk: 20,
+			}));
+		let chunk = pipeline.generate_chunk(Point3::origin());
+		assert_eq!(count(&chunk, IRON), 20);
+		assert_eq!(count(&chunk, STONE), chunk::VOLUME - 20);
+	}
+
+	#[test]
+	fn same_seed_generates_same_chunk() {
+		let generate = |seed: &str| {
+			Pipeline::new(Pipeline::seed_from_str(seed), Solid)
+				.with_decorator(Arc::new(Ore {
+					ore: IRON,
+					replaces: STONE,
+					per_chunk: 20,
+				}))
+				.generate_chunk(Point3::new(3, -1, 2))
+				.block_ids()
+				.clone()
+		};
+		assert_eq!(generate("fixed"), generate("fixed"));
+		assert_ne!(generate("fixed"), generate("other"));
+	}
+
+	#[test]
+	fn placements_in_neighbors_are_deferred() {
+		let pipeline = Pipeline::new(0, Solid).with_decorator(Arc::new(Overhang));
+		let origin = pipeline.generate_chunk(Point3::origin());
+		assert_eq!(count(&origin, IRON), 0);
+
+		let mut neighbor = Solid.generate_chunk(Point3::new(1, 0, 0));
+		assert_eq!(pipeline.apply_deferred(&mut neighbor), 1);
+		assert_eq!(neighbor.block_ids().get(&Point3::origin()), Some(&IRON));
+		// The placement is only applied once.
+		assert_eq!(pipeline.apply_deferred(&mut neighbor), 0);
+	}
+
+	#[test]
+	fn placements_keep_changed_blocks() {
+		let pipeline = Pipeline::new(0, Solid).with_decorator(Arc::new(Overhang));
+		let _origin = pipeline.generate_chunk(Point3::origin());
+
+		// The block was broken after the neighbor was generated (and saved), so the placement is dropped.
+		let mut neighbor = Solid.generate_chunk(Point3::new(1, 0, 0));
+		neighbor.set_block_id(Point3::origin(), None);
+		assert_eq!(pipeline.apply_deferred(&mut neighbor), 0);
+		assert_eq!(neighbor.block_ids().get(&Point3::origin()), None);
+		assert!(pipeline.deferred_coordinates().is_empty());
+	}
+
+	#[test]
+	fn placements_are_bounded() {
+		let pipeline = Pipeline::new(0, Solid).with_decorator(Arc::new(Overhang));
+		for z in 0..(MAX_DEFERRED_PLACEMENTS as i64 + 10) {
+			pipeline.defer(vec![(
+				Point3::new(1, 0, z),
+				Placement {
+					offset: Point3::origin(),
+					id: Some(IRON),
+					replaces: Some(STONE),
+				},
+			)]);
+		}
+		assert_eq!(
+			pipeline.deferred_coordinates().len(),
+			MAX_DEFERRED_PLACEMENTS
+		);
+	}
+
+	#[test]
+	fn placements_survive_rebuild() {
+		let pipeline = Pipeline::new(0, Solid).with_decorator(Arc::new(Overhang));
+		let _origin = pipeline.generate_chunk(Point3::origin());
+
+		let rebuilt = Pipeline::new(0, Solid).with_deferred_from(pipeline);
+		assert_eq!(rebuilt.deferred_coordinates(), vec![Point3::new(1, 0, 0)]);
+		let mut neighbor = Solid.generate_chunk(Point3::new(1, 0, 0));
+		assert_eq!(rebuilt.apply_deferred(&mut neighbor), 1);
+	}
+}
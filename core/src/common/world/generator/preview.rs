@@ -0,0 +1,205 @@
+use super::Pipeline;
+use crate::{block, common::world::chunk};
+use anyhow::Result;
+use engine::math::nalgebra::{Point2, Point3};
+use std::{io::Write, ops::Range, path::Path};
+
+/// A rectangle of chunk columns to generate a preview of, viewed from above.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+	/// The smallest chunk x and z coordinates in the region.
+	pub min: Point2<i64>,
+	/// The largest chunk x and z coordinates in the region (inclusive).
+	pub max: Point2<i64>,
+	/// The vertical chunks which are generated for each column, the highest block of which is shown in the preview.
+	pub chunk_y: Range<i64>,
+}
+
+impl std::str::FromStr for Region {
+	type Err = Error;
+	/// Parses a region of the form `<min x>,<min z>,<max x>,<max z>`, in chunk coordinates.
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		let coords = value
+			.split(',')
+			.map(|coord| coord.trim().parse::<i64>())
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| Error::InvalidRegion(value.to_owned()))?;
+		match coords[..] {
+			[min_x, min_z, max_x, max_z] if min_x <= max_x && min_z <= max_z => Ok(Self {
+				min: Point2::new(min_x, min_z),
+				max: Point2::new(max_x, max_z),
+				chunk_y: 0..1,
+			}),
+			_ => Err(Error::InvalidRegion(value.to_owned())),
+		}
+	}
+}
+
+impl Region {
+	pub fn with_chunk_y(mut self, chunk_y: Range<i64>) -> Self {
+		self.chunk_y = chunk_y;
+		self
+	}
+
+	/// The number of chunks along the x and z axes.
+	pub fn chunk_size(&self) -> (usize, usize) {
+		(
+			(self.max.x - self.min.x + 1) as usize,
+			(self.max.y - self.min.y + 1) as usize,
+		)
+	}
+
+	/// The width and height of the preview image, one pixel per block column.
+	pub fn image_size(&self) -> (u32, u32) {
+		let (width, height) = self.chunk_size();
+		(
+			(width * chunk::DIAMETER) as u32,
+			(height * chunk::DIAMETER) as u32,
+		)
+	}
+}
+
+/// The highest block in a column of the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Surface {
+	id: block::LookupId,
+	/// The world-space y coordinate of the block.
+	height: i64,
+}
+
+/// Generates the chunks of a region and writes a top-down png of it to a file.
+/// See [`write_preview`].
+pub fn save_preview(pipeline: &Pipeline, region: &Region, path: &Path) -> Result<()> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent)?;
+	}
+	let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+	write_preview(pipeline, region, file)
+}
+
+/// Generates the chunks of a region and writes a top-down png of it,
+/// where each pixel is colored by the block-type at the surface of its column and shaded by its height.
+///
+/// The region is generated and encoded one row of chunks at a time,
+/// so only a single row of chunks is held in memory no matter how large the region is.
+pub fn write_preview<W: Write>(pipeline: &Pipeline, region: &Region, writer: W) -> Result<()> {
+	profiling::scope!("write_preview", &format!("{:?}", region));
+	let (width, height) = region.image_size();
+	let mut encoder = png::Encoder::new(writer, width, height);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	let mut writer = encoder.write_header()?;
+	let mut stream = writer.stream_writer()?;
+
+	let min_height = region.chunk_y.start * chunk::DIAMETER as i64;
+	let max_height = region.chunk_y.end * chunk::DIAMETER as i64;
+	for chunk_z in region.min.y..=region.max.y {
+		let surfaces = generate_row(pipeline, region, chunk_z);
+		let mut pixels = Vec::with_capacity(width as usize * 3);
+		for row in surfaces.chunks(width as usize) {
+			pixels.clear();
+			for surface in row.iter() {
+				pixels.extend_from_slice(&color(surface, min_height..max_height));
+			}
+			stream.write_all(&pixels)?;
+		}
+	}
+	stream.finish()?;
+	Ok(())
+}
+
+/// Generates every chunk in a row of the region (along the x axis),
+/// returning the surface of each column in the row ordered by block z then x.
+fn generate_row(pipeline: &Pipeline, region: &Region, chunk_z: i64) -> Vec<Option<Surface>> {
+	let (chunks_wide, _) = region.chunk_size();
+	let width = chunks_wide * chunk::DIAMETER;
+	let mut surfaces = vec![None; width * chunk::DIAMETER];
+	for (x_index, chunk_x) in (region.min.x..=region.max.x).enumerate() {
+		for chunk_y in region.chunk_y.clone() {
+			let chunk = pipeline.generate_chunk(Point3::new(chunk_x, chunk_y, chunk_z));
+			for (offset, id) in chunk.block_ids().iter() {
+				let height = chunk_y * chunk::DIAMETER as i64 + offset.y as i64;
+				let column = offset.z * width + x_index * chunk::DIAMETER + offset.x;
+				let surface = &mut surfaces[column];
+				if surface
+					.map(|existing: Surface| existing.height < height)
+					.unwrap_or(true)
+				{
+					*surface = Some(Surface { id: *id, height });
+				}
+			}
+		}
+	}
+	surfaces
+}
+
+/// Returns the color of a column, which is unique per block-type and brighter the higher the surface is.
+/// Empty columns are black.
+fn color(surface: &Option<Surface>, heights: Range<i64>) -> [u8; 3] {
+	let surface = match surface {
+		Some(surface) => surface,
+		None => return [0, 0, 0],
+	};
+	let hash = (surface.id as u32)
+		.wrapping_add(1)
+		.wrapping_mul(0x9E37_79B1);
+	let base = [(hash >> 24) as u8, (hash >> 16) as u8, (hash >> 8) as u8];
+	let range = (heights.end - heights.start).max(1) as f32;
+	let brightness = 0.35 + 0.65 * ((surface.height - heights.start) as f32 / range);
+	base.map(|channel| (channel as f32 * brightness.clamp(0.0, 1.0)) as u8)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("\"{0}\" is not a valid region, expected <min x>,<min z>,<max x>,<max z>")]
+	InvalidRegion(String),
+}
+
+#[cfg(test)]
+mod preview {
+	use super::*;
+	use crate::common::world::{chunk::Chunk, generator::Terrain};
+
+	/// A single layer of blocks at the bottom of every chunk, whose type alternates per chunk.
+	struct Floor;
+	impl Terrain for Floor {
+		fn generate_chunk(&self, coordinate: Point3<i64>) -> Chunk {
+			let mut chunk = Chunk::new(coordinate);
+			let id = (coordinate.x + coordinate.z).rem_euclid(2) as block::LookupId;
+			for x in 0..chunk::DIAMETER {
+				for z in 0..chunk::DIAMETER {
+					chunk.set_block_id(Point3::new(x, 0, z), Some(id));
+				}
+			}
+			chunk
+		}
+	}
+
+	#[test]
+	fn parse_region() {
+		let region = "-1,2,3,4".parse::<Region>().unwrap();
+		assert_eq!(region.min, Point2::new(-1, 2));
+		assert_eq!(region.max, Point2::new(3, 4));
+		assert!("3,0,1,0".parse::<Region>().is_err());
+		assert!("0,0,1".parse::<Region>().is_err());
+	}
+
+	#[test]
+	fn small_region_image_dimensions() {
+		let pipeline = Pipeline::new(0, Floor);
+		let region = "0,-1,1,1".parse::<Region>().unwrap().with_chunk_y(0..2);
+		let mut bytes = Vec::new();
+		write_preview(&pipeline, &region, &mut bytes).unwrap();
+
+		let decoder = png::Decoder::new(bytes.as_slice());
+		let mut reader = decoder.read_info().unwrap();
+		let mut image = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut image).unwrap();
+		assert_eq!((info.width, info.height), (32, 48));
+		assert_eq!(info.color_type, png::ColorType::Rgb);
+		// Neighboring chunks have different surface blocks, so their pixels differ.
+		let pixel = |x: usize, y: usize| &image[(y * 32 + x) * 3..(y * 32 + x) * 3 + 3];
+		assert_ne!(pixel(0, 0), pixel(16, 0));
+		assert_eq!(pixel(0, 0), pixel(16, 16));
+	}
+}
@@ -0,0 +1,427 @@
+//! Per-voxel light levels, and the flood-fill which spreads light from emitting blocks.
+//!
+//! Each voxel stores two 4-bit light levels: block light (emitted by blocks like torches) and sky light.
+//! Only block light is propagated at the moment; sky light is always 0.
+use crate::{block, common::world::chunk};
+use engine::math::nalgebra::{Point3, Vector3};
+use std::collections::{HashSet, VecDeque};
+
+/// The brightest light level a voxel can have.
+pub static MAX_LEVEL: u8 = 15;
+
+/// The light of a single voxel, with block light in the lower 4 bits and sky light in the upper 4 bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Light(u8);
+
+impl Light {
+	pub fn block(&self) -> u8 {
+		self.0 & 0x0F
+	}
+
+	pub fn sky(&self) -> u8 {
+		self.0 >> 4
+	}
+
+	pub fn with_block(self, level: u8) -> Self {
+		Self((self.0 & 0xF0) | level.min(MAX_LEVEL))
+	}
+
+	pub fn with_sky(self, level: u8) -> Self {
+		Self((self.0 & 0x0F) | (level.min(MAX_LEVEL) << 4))
+	}
+}
+
+/// The [`Light`] of every voxel in a chunk,
+/// where each voxel is at the [`index of its offset`](chunk::offset_index).
+#[derive(Clone)]
+pub struct LightMap(Vec<Light>);
+
+impl Default for LightMap {
+	fn default() -> Self {
+		Self(vec![Light::default(); chunk::VOLUME])
+	}
+}
+
+impl LightMap {
+	pub fn get(&self, offset: &Point3<usize>) -> Light {
+		self.0[chunk::offset_index(offset)]
+	}
+
+	pub fn set(&mut self, offset: &Point3<usize>, light: Light) {
+		self.0[chunk::offset_index(offset)] = light;
+	}
+}
+
+/// A region of voxels, possibly spanning many chunks, which light can be propagated through.
+pub trait Volume {
+	/// Returns the light at a point, or None if the chunk the point is in is not loaded.
+	/// Light does not spread into chunks which are not loaded.
+	fn light(&self, point: &block::Point) -> Option<Light>;
+	fn set_light(&mut self, point: &block::Point, light: Light);
+	/// Returns the block light level emitted by the block at a point (0 if it is empty).
+	fn emission(&self, point: &block::Point) -> u8;
+	/// Returns true if the block at a point stops light from passing through it.
+	fn is_opaque(&self, point: &block::Point) -> bool;
+}
+
+static DIRECTIONS: [Vector3<i8>; 6] = [
+	Vector3::new(1, 0, 0),
+	Vector3::new(-1, 0, 0),
+	Vector3::new(0, 1, 0),
+	Vector3::new(0, -1, 0),
+	Vector3::new(0, 0, 1),
+	Vector3::new(0, 0, -1),
+];
+
+fn neighbors(point: block::Point) -> impl Iterator<Item = block::Point> {
+	DIRECTIONS.iter().map(move |direction| point + *direction)
+}
+
+fn block_light<V: Volume>(volume: &V, point: &block::Point) -> Option<u8> {
+	volume.light(point).map(|light| light.block())
+}
+
+fn set_block_light<V: Volume>(volume: &mut V, point: &block::Point, level: u8) {
+	if let Some(light) = volume.light(point) {
+		volume.set_light(point, light.with_block(level));
+	}
+}
+
+/// Spreads block light outward from each source (breadth-first),
+/// decreasing by 1 with every step, until it reaches 0 or an opaque block.
+/// The sources must already have their light set.
+/// Returns the points whose light changed.
+pub fn propagate<V: Volume>(volume: &mut V, sources: Vec<block::Point>) -> HashSet<block::Point> {
+	profiling::scope!("propagate-light", &format!("sources={}", sources.len()));
+	let mut changed = HashSet::new();
+	let mut queue = VecDeque::from(sources);
+	while let Some(point) = queue.pop_front() {
+		let level = block_light(volume, &point).unwrap_or(0);
+		if level <= 1 {
+			continue;
+		}
+		for neighbor in neighbors(point) {
+			match block_light(volume, &neighbor) {
+				Some(neighbor_level) if neighbor_level < level - 1 => {
+					if volume.is_opaque(&neighbor) {
+						continue;
+					}
+					set_block_light(volume, &neighbor, level - 1);
+					changed.insert(neighbor);
+					queue.push_back(neighbor);
+				}
+				_ => {}
+			}
+		}
+	}
+	changed
+}
+
+/// Darkens a point and every point which was lit by it.
+/// Returns the points which were darkened, and the points at the edge of the darkened region
+/// which are still lit (by other sources) and need to spread their light back in.
+fn remove<V: Volume>(
+	volume: &mut V,
+	point: block::Point,
+) -> (HashSet<block::Point>, Vec<block::Point>) {
+	let level = block_light(volume, &point).unwrap_or(0);
+	if level == 0 {
+		return (HashSet::new(), Vec::new());
+	}
+	darken(volume, vec![(point, level)])
+}
+
+/// Darkens each point (which had the paired light level) and every point which was lit by them.
+/// See [`remove`].
+fn darken<V: Volume>(
+	volume: &mut V,
+	points: Vec<(block::Point, u8)>,
+) -> (HashSet<block::Point>, Vec<block::Point>) {
+	let mut changed = HashSet::new();
+	let mut sources = Vec::new();
+	for (point, _level) in points.iter() {
+		set_block_light(volume, point, 0);
+		changed.insert(*point);
+	}
+	let mut queue = VecDeque::from(points);
+	while let Some((point, level)) = queue.pop_front() {
+		for neighbor in neighbors(point) {
+			match block_light(volume, &neighbor) {
+				Some(0) | None => {}
+				Some(neighbor_level) if neighbor_level < level => {
+					set_block_light(volume, &neighbor, 0);
+					changed.insert(neighbor);
+					queue.push_back((neighbor, neighbor_level));
+				}
+				Some(_) => sources.push(neighbor),
+			}
+		}
+	}
+	// Emitters in the darkened region still produce their own light
+	for &darkened in changed.iter() {
+		let emission = volume.emission(&darkened);
+		if emission > 0 {
+			set_block_light(volume, &darkened, emission);
+			sources.push(darkened);
+		}
+	}
+	(changed, sources)
+}
+
+/// Lights every emitting block in a newly loaded chunk, and spreads light into the chunk
+/// from its neighbors, and out of the chunk into its neighbors.
+/// Returns the points whose light changed.
+pub fn light_chunk<V: Volume>(
+	volume: &mut V,
+	chunk: Point3<i64>,
+	emitters: Vec<block::Point>,
+) -> HashSet<block::Point> {
+	let mut sources = Vec::new();
+	let mut changed = HashSet::new();
+	for point in emitters.into_iter() {
+		let emission = volume.emission(&point);
+		if emission > 0 {
+			set_block_light(volume, &point, emission);
+			changed.insert(point);
+			sources.push(point);
+		}
+	}
+	for point in block::Point::chunk_offsets(chunk) {
+		for neighbor in neighbors(point) {
+			if *neighbor.chunk() != chunk && block_light(volume, &neighbor).unwrap_or(0) > 0 {
+				sources.push(neighbor);
+			}
+		}
+	}
+	changed.extend(propagate(volume, sources));
+	changed
+}
+
+/// Darkens the light which spread out of a chunk that is being unloaded,
+/// and spreads the light of its neighbors back into the darkened points.
+/// `unload` must remove the chunk from the volume, so that light is no longer spread into it.
+/// Returns the points outside of the chunk whose light changed.
+pub fn unlight_chunk<V: Volume, F: FnOnce(&mut V)>(
+	volume: &mut V,
+	chunk: Point3<i64>,
+	unload: F,
+) -> HashSet<block::Point> {
+	let lit = block::Point::chunk_offsets(chunk)
+		.filter_map(|point| match block_light(volume, &point) {
+			Some(level) if level > 0 => Some((point, level)),
+			_ => None,
+		})
+		.collect::<Vec<_>>();
+	let (mut changed, sources) = darken(volume, lit);
+	unload(volume);
+	let sources = sources
+		.into_iter()
+		.filter(|point| *point.chunk() != chunk)
+		.collect::<Vec<_>>();
+	changed.extend(propagate(volume, sources));
+	changed.retain(|point| *point.chunk() != chunk);
+	changed
+}
+
+/// Updates the light around a point after the block at that point has changed.
+/// Returns the points whose light changed.
+pub fn update_block<V: Volume>(volume: &mut V, point: block::Point) -> HashSet<block::Point> {
+	let (mut changed, mut sources) = remove(volume, point);
+	let emission = volume.emission(&point);
+	if emission > 0 {
+		set_block_light(volume, &point, emission);
+		changed.insert(point);
+		sources.push(point);
+	}
+	for neighbor in neighbors(point) {
+		if block_light(volume, &neighbor).unwrap_or(0) > 0 {
+			sources.push(neighbor);
+		}
+	}
+	changed.extend(propagate(volume, sources));
+	changed
+}
+
+/// Returns the brightest block light on or around a point,
+/// which is the light that falls on the faces of the block at that point.
+pub fn brightness<V: Volume>(volume: &V, point: &block::Point) -> u8 {
+	neighbors(*point)
+		.chain(std::iter::once(*point))
+		.filter_map(|point| block_light(volume, &point))
+		.max()
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod flood_fill {
+	use super::*;
+	use std::collections::HashMap;
+
+	/// A volume of loaded chunks with no blocks except those which are placed.
+	#[derive(Default)]
+	struct TestVolume {
+		chunks: HashMap<Point3<i64>, LightMap>,
+		emitters: HashMap<block::Point, u8>,
+		walls: HashSet<block::Point>,
+	}
+
+	impl TestVolume {
+		fn new(chunks: Vec<Point3<i64>>) -> Self {
+			Self {
+				chunks: chunks
+					.into_iter()
+					.map(|chunk| (chunk, LightMap::default()))
+					.collect(),
+				..Default::default()
+			}
+		}
+
+		fn offset(point: &block::Point) -> Point3<usize> {
+			point.offset().cast::<usize>()
+		}
+	}
+
+	impl Volume for TestVolume {
+		fn light(&self, point: &block::Point) -> Option<Light> {
+			let chunk = self.chunks.get(point.chunk())?;
+			Some(chunk.get(&Self::offset(point)))
+		}
+		fn set_light(&mut self, point: &block::Point, light: Light) {
+			if let Some(chunk) = self.chunks.get_mut(point.chunk()) {
+				chunk.set(&Self::offset(point), light);
+			}
+		}
+		fn emission(&self, point: &block::Point) -> u8 {
+			self.emitters.get(point).cloned().unwrap_or(0)
+		}
+		fn is_opaque(&self, point: &block::Point) -> bool {
+			self.walls.contains(point)
+		}
+	}
+
+	fn at(x: i8, y: i8, z: i8) -> block::Point {
+		block::Point::new(Point3::origin(), Point3::new(x, y, z))
+	}
+
+	fn level(volume: &TestVolume, point: block::Point) -> u8 {
+		volume.light(&point).unwrap().block()
+	}
+
+	#[test]
+	fn packs_block_and_sky_light() {
+		let light = Light::default().with_block(12).with_sky(3);
+		assert_eq!(light.block(), 12);
+		assert_eq!(light.sky(), 3);
+		assert_eq!(light.with_block(20).block(), MAX_LEVEL);
+	}
+
+	#[test]
+	fn placed_emitter_fills_decreasing_levels() {
+		let mut volume = TestVolume::new(vec![Point3::origin()]);
+		let torch = at(8, 8, 8);
+		volume.emitters.insert(torch, 4);
+		let changed = update_block(&mut volume, torch);
+
+		assert_eq!(level(&volume, torch), 4);
+		assert_eq!(level(&volume, at(9, 8, 8)), 3);
+		assert_eq!(level(&volume, at(9, 9, 8)), 2);
+		assert_eq!(level(&volume, at(9, 9, 9)), 1);
+		assert_eq!(level(&volume, at(10, 9, 9)), 0);
+		assert_eq!(level(&volume, at(8, 8, 5)), 1);
+		// 1 + 6 + 18 + 38 points are within a manhattan distance of 3 from the torch
+		assert_eq!(changed.len(), 63);
+	}
+
+	#[test]
+	fn opaque_blocks_stop_light() {
+		let mut volume = TestVolume::new(vec![Point3::origin()]);
+		let torch = at(8, 8, 8);
+		volume.emitters.insert(torch, 6);
+		volume.walls.insert(at(9, 8, 8));
+		update_block(&mut volume, torch);
+		assert_eq!(level(&volume, at(9, 8, 8)), 0);
+		// Light only reaches behind the wall by going around it
+		assert_eq!(level(&volume, at(10, 8, 8)), 2);
+		assert_eq!(level(&volume, at(9, 9, 8)), 4);
+	}
+
+	#[test]
+	fn removed_emitter_darkens_region() {
+		let mut volume = TestVolume::new(vec![Point3::origin()]);
+		let torch = at(8, 8, 8);
+		let lamp = at(12, 8, 8);
+		volume.emitters.insert(torch, 4);
+		volume.emitters.insert(lamp, 2);
+		update_block(&mut volume, torch);
+		update_block(&mut volume, lamp);
+		assert_eq!(level(&volume, at(11, 8, 8)), 1);
+
+		volume.emitters.remove(&torch);
+		update_block(&mut volume, torch);
+		assert_eq!(level(&volume, torch), 0);
+		assert_eq!(level(&volume, at(9, 8, 8)), 0);
+		// The other emitter is still lit
+		assert_eq!(level(&volume, lamp), 2);
+		assert_eq!(level(&volume, at(11, 8, 8)), 1);
+	}
+
+	#[test]
+	fn light_crosses_loaded_chunks() {
+		let neighbor = Point3::new(1, 0, 0);
+		let mut volume = TestVolume::new(vec![Point3::origin(), neighbor]);
+		let torch = at(15, 0, 0);
+		volume.emitters.insert(torch, 3);
+		update_block(&mut volume, torch);
+		let across = block::Point::new(neighbor, Point3::new(1, 0, 0));
+		assert_eq!(level(&volume, across), 1);
+		// Chunks which are not loaded are skipped
+		assert_eq!(volume.light(&at(-1, 0, 0)), None);
+	}
+
+	#[test]
+	fn loaded_chunk_receives_neighbor_light() {
+		let neighbor = Point3::new(1, 0, 0);
+		let mut volume = TestVolume::new(vec![Point3::origin()]);
+		let torch = at(15, 0, 0);
+		volume.emitters.insert(torch, 3);
+		update_block(&mut volume, torch);
+
+		volume.chunks.insert(neighbor, LightMap::default());
+		let changed = light_chunk(&mut volume, neighbor, Vec::new());
+		let across = block::Point::new(neighbor, Point3::new(0, 0, 0));
+		assert_eq!(level(&volume, across), 2);
+		assert!(changed.contains(&across));
+	}
+
+	#[test]
+	fn unloaded_chunk_takes_its_light() {
+		let neighbor = Point3::new(1, 0, 0);
+		let mut volume = TestVolume::new(vec![Point3::origin(), neighbor]);
+		let torch = at(15, 0, 0);
+		let lamp = block::Point::new(neighbor, Point3::new(3, 0, 0));
+		volume.emitters.insert(torch, 4);
+		volume.emitters.insert(lamp, 2);
+		update_block(&mut volume, torch);
+		update_block(&mut volume, lamp);
+		let across = block::Point::new(neighbor, Point3::new(1, 0, 0));
+		assert_eq!(level(&volume, across), 2);
+
+		let changed = unlight_chunk(&mut volume, Point3::origin(), |volume| {
+			volume.chunks.remove(&Point3::origin());
+		});
+		assert_eq!(volume.light(&torch), None);
+		// Only the light of the neighbor's own emitter is left
+		assert_eq!(
+			level(&volume, block::Point::new(neighbor, Point3::new(0, 0, 0))),
+			0
+		);
+		assert_eq!(level(&volume, across), 0);
+		assert_eq!(
+			level(&volume, block::Point::new(neighbor, Point3::new(2, 0, 0))),
+			1
+		);
+		assert_eq!(level(&volume, lamp), 2);
+		assert!(changed.contains(&across));
+		assert!(changed.iter().all(|point| *point.chunk() == neighbor));
+	}
+}
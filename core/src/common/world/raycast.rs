@@ -0,0 +1,253 @@
+//! Finding the block a ray (e.g. the player's view) hits first, by stepping through every block the ray passes through in order.
+//!
+//! The traversal is independent of where blocks are stored, so clients and servers can walk their own copy of the world.
+//! Servers can [`cast`] directly against a loaded [`Database`].
+use crate::{
+	block,
+	common::world::chunk::DIAMETER,
+	entity::component::physics::linear::Position,
+	graphics::voxel::Face,
+	server::world::{edit::split, Database},
+};
+use engine::math::nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// How far (in blocks) from their eyes a player can reach to interact with blocks.
+pub static REACH: f64 = 5.0;
+
+/// How high (in blocks) above a player's position their eyes are, which they look (and reach) from.
+pub static EYE_HEIGHT: f64 = 1.6;
+
+/// Returns the world-space position of the eyes of a player at `position`.
+pub fn eye_position(position: &Position) -> Point3<f64> {
+	position.world_position() + Vector3::new(0.0, EYE_HEIGHT, 0.0)
+}
+
+/// Returns true if any part of the block is within [`REACH`] of the eyes.
+/// Servers use this to reject interactions with blocks the client could not have reached.
+pub fn is_within_reach(eye: &Point3<f64>, target: &block::Point) -> bool {
+	let diameter = DIAMETER as f64;
+	let mut nearest = *eye;
+	for i in 0..3 {
+		let min = target.chunk()[i] as f64 * diameter + target.offset()[i] as f64;
+		nearest[i] = eye[i].clamp(min, min + 1.0);
+	}
+	(nearest - eye).norm() <= REACH
+}
+
+/// The block a ray hit, and the face of the block the ray entered through.
+/// A block placed against the hit goes at `point + face.direction()`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RaycastHit {
+	pub point: block::Point,
+	pub face: Face,
+}
+
+/// How a ray's traversal ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Traversal {
+	Hit(RaycastHit),
+	/// The ray reached a block in a chunk which is not loaded, before it hit anything.
+	Unloaded(block::Point),
+	/// The ray travelled its maximum distance without hitting anything.
+	Miss,
+}
+
+impl Traversal {
+	pub fn hit(self) -> Option<RaycastHit> {
+		match self {
+			Self::Hit(hit) => Some(hit),
+			Self::Unloaded(_) | Self::Miss => None,
+		}
+	}
+}
+
+/// Returns the first non-empty block in a loaded chunk of the world which the ray hits within `max_distance` blocks.
+/// Rays which reach an unloaded chunk stop there, and hit nothing.
+pub fn cast(
+	database: &Database,
+	origin: Point3<f64>,
+	direction: Vector3<f64>,
+	max_distance: f64,
+) -> Option<RaycastHit> {
+	let cache = database.chunk_cache().read().unwrap();
+	traverse(origin, direction, max_distance, |point| {
+		let arc_chunk = cache.find(point.chunk())?.upgrade()?;
+		let chunk = arc_chunk.read().unwrap();
+		let offset = point.offset().map(|v| v as usize);
+		let is_solid = chunk.chunk.block_ids().contains_key(&offset);
+		Some(is_solid)
+	})
+	.hit()
+}
+
+/// Steps through every block the ray passes through (excluding the block containing the origin), in the order they are reached,
+/// until `is_solid` returns true for a block or the ray has travelled `max_distance` blocks.
+/// `is_solid` returns None if the block is not loaded, which ends the traversal.
+pub fn traverse<F>(
+	origin: Point3<f64>,
+	direction: Vector3<f64>,
+	max_distance: f64,
+	mut is_solid: F,
+) -> Traversal
+where
+	F: FnMut(&block::Point) -> Option<bool>,
+{
+	let direction = match direction.try_normalize(f64::EPSILON) {
+		Some(direction) => direction,
+		None => return Traversal::Miss,
+	};
+	let mut cell = origin.map(|v| v.floor() as i64);
+	let step = direction.map(|v| match v {
+		_ if v > 0.0 => 1i64,
+		_ if v < 0.0 => -1i64,
+		_ => 0i64,
+	});
+	// The distance along the ray at which it next crosses a block boundary on each axis,
+	// and the distance between boundaries on each axis.
+	let mut next_boundary = Vector3::from_fn(|i, _| match step[i] {
+		1 => ((cell[i] + 1) as f64 - origin[i]) / direction[i],
+		-1 => (origin[i] - cell[i] as f64) / -direction[i],
+		_ => f64::INFINITY,
+	});
+	let boundary_delta = direction.map(|v| match v {
+		_ if v != 0.0 => 1.0 / v.abs(),
+		_ => f64::INFINITY,
+	});
+
+	loop {
+		let axis = next_boundary.imin();
+		if next_boundary[axis] > max_distance {
+			return Traversal::Miss;
+		}
+		cell[axis] += step[axis];
+		next_boundary[axis] += boundary_delta[axis];
+
+		let (chunk, offset) = split(cell);
+		let point = block::Point::new(chunk, offset.map(|v| v as i8));
+		match is_solid(&point) {
+			None => return Traversal::Unloaded(point),
+			Some(false) => {}
+			Some(true) => {
+				return Traversal::Hit(RaycastHit {
+					point,
+					face: entered_face(axis, step[axis]),
+				})
+			}
+		}
+	}
+}
+
+/// The face of a block which a ray crosses into it through, when stepping along an axis.
+fn entered_face(axis: usize, step: i64) -> Face {
+	match (axis, step > 0) {
+		(0, true) => Face::Left,
+		(0, false) => Face::Right,
+		(1, true) => Face::Down,
+		(1, false) => Face::Up,
+		(_, true) => Face::Front,
+		(_, false) => Face::Back,
+	}
+}
+
+#[cfg(test)]
+mod raycast {
+	use super::*;
+	use std::collections::HashSet;
+
+	fn world_point(x: i64, y: i64, z: i64) -> block::Point {
+		let (chunk, offset) = split(Point3::new(x, y, z));
+		block::Point::new(chunk, offset.map(|v| v as i8))
+	}
+
+	/// Casts a ray through a world where every chunk is loaded and only `solid` blocks are not empty.
+	fn cast_at(solid: &[block::Point], origin: Point3<f64>, direction: Vector3<f64>) -> Traversal {
+		let solid = solid.iter().cloned().collect::<HashSet<_>>();
+		traverse(origin, direction, 16.0, |point| Some(solid.contains(point)))
+	}
+
+	#[test]
+	fn hits_the_face_facing_the_ray() {
+		let target = world_point(3, 0, -1);
+		let hit = cast_at(
+			&[target],
+			Point3::new(0.5, 0.5, -0.5),
+			Vector3::new(1.0, 0.0, 0.0),
+		);
+		assert_eq!(
+			hit,
+			Traversal::Hit(RaycastHit {
+				point: target,
+				face: Face::Left
+			})
+		);
+		// Placing against the hit goes between the origin and the block.
+		assert_eq!(target + Face::Left.direction(), world_point(2, 0, -1));
+
+		// Looking down onto a block crossing a chunk boundary hits its top.
+		let below = world_point(-1, -3, 0);
+		let hit = cast_at(
+			&[below],
+			Point3::new(-0.5, 1.5, 0.2),
+			Vector3::new(0.0, -1.0, 0.0),
+		);
+		assert_eq!(
+			hit.hit().map(|hit| (hit.point, hit.face)),
+			Some((below, Face::Up))
+		);
+	}
+
+	#[test]
+	fn diagonal_rays_visit_the_first_block_in_their_path() {
+		let (near, far) = (world_point(2, 1, 0), world_point(4, 3, 0));
+		let hit = cast_at(
+			&[far, near],
+			Point3::new(0.5, 0.2, 0.5),
+			Vector3::new(1.0, 1.0, 0.0),
+		);
+		// The ray is below the diagonal, so it enters the block through its left face.
+		assert_eq!(
+			hit.hit(),
+			Some(RaycastHit {
+				point: near,
+				face: Face::Left
+			})
+		);
+	}
+
+	#[test]
+	fn reach_is_measured_to_the_nearest_side() {
+		let eye = Point3::new(0.0, 1.6, 0.5);
+		// The block's near side is exactly at the reach.
+		assert!(is_within_reach(&eye, &world_point(5, 1, 0)));
+		assert!(!is_within_reach(&eye, &world_point(6, 1, 0)));
+		// Blocks behind a chunk boundary are measured in world space.
+		assert!(is_within_reach(&eye, &world_point(-4, -2, 0)));
+		assert!(!is_within_reach(&eye, &world_point(-20, 1, 0)));
+	}
+
+	#[test]
+	fn stops_at_unloaded_chunks_and_max_distance() {
+		let unloaded = traverse(
+			Point3::new(15.5, 0.5, 0.5),
+			Vector3::new(1.0, 0.0, 0.0),
+			16.0,
+			|point| match point.chunk().x {
+				0 => Some(false),
+				_ => None,
+			},
+		);
+		assert_eq!(unloaded, Traversal::Unloaded(world_point(16, 0, 0)));
+
+		let out_of_reach = cast_at(
+			&[world_point(0, 20, 0)],
+			Point3::new(0.5, 0.5, 0.5),
+			Vector3::new(0.0, 1.0, 0.0),
+		);
+		assert_eq!(out_of_reach, Traversal::Miss);
+		assert_eq!(
+			cast_at(&[], Point3::new(0.5, 0.5, 0.5), Vector3::zeros()),
+			Traversal::Miss
+		);
+	}
+}
@@ -0,0 +1,363 @@
+use crate::{
+	block,
+	common::{
+		utility::versioned::{self, Version, Versioned},
+		world::chunk,
+	},
+};
+use engine::math::nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, path::Path};
+
+/// The extension of schematic files.
+pub static EXTENSION: &'static str = "schematic";
+
+/// The most blocks a schematic can hold, the volume of a region of 16x16x16 chunks.
+/// Larger schematics cannot be copied or loaded, so a corrupt (or hostile) file cannot make loading allocate without limit.
+pub static MAX_VOLUME: usize = 4096 * chunk::VOLUME;
+
+/// Returns the number of blocks in a region of `size`,
+/// or an error if the region is larger than a schematic can hold.
+pub fn volume(size: &Vector3<usize>) -> Result<usize, Error> {
+	size.x
+		.checked_mul(size.y)
+		.and_then(|area| area.checked_mul(size.z))
+		.filter(|volume| *volume <= MAX_VOLUME)
+		.ok_or(Error::TooLarge(size.x, size.y, size.z))
+}
+
+/// Checks that the name of a schematic is a single file name,
+/// so a schematic can never be read from or written to outside of the directory it is saved in.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+	let is_file_name = !name.is_empty()
+		&& name != "."
+		&& !name.contains("..")
+		&& !name.contains(|c: char| c == '/' || c == '\\' || std::path::is_separator(c));
+	match is_file_name {
+		true => Ok(()),
+		false => Err(Error::InvalidName(name.to_owned())),
+	}
+}
+
+/// A saved region of blocks (e.g. a structure a builder made) which can be pasted into a world.
+///
+/// Saved as a [`versioned`](crate::common::utility::Versioned) binary file,
+/// which is the size of the region followed by the id of every block in it (including air).
+/// Blocks are laid out like a chunk's [`dense block array`](super::chunk::offset_index) (x fastest, then z, then y),
+/// and consecutive blocks of the same type (most often air) are saved as a single [`run`](Run).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Runs", into = "Runs")]
+pub struct Schematic {
+	size: Vector3<usize>,
+	block_ids: Vec<Option<block::LookupId>>,
+}
+
+impl Schematic {
+	/// Creates a schematic of air with the provided size (in blocks).
+	pub fn new(size: Vector3<usize>) -> Self {
+		Self {
+			size,
+			block_ids: vec![None; size.x * size.y * size.z],
+		}
+	}
+
+	pub fn size(&self) -> &Vector3<usize> {
+		&self.size
+	}
+
+	fn index(&self, offset: &Vector3<usize>) -> Option<usize> {
+		match offset.x < self.size.x && offset.y < self.size.y && offset.z < self.size.z {
+			true => Some(offset.x + offset.z * self.size.x + offset.y * self.size.x * self.size.z),
+			false => None,
+		}
+	}
+
+	/// Returns the block at an offset from the minimum corner of the region, or None if it is air or outside the region.
+	pub fn get(&self, offset: &Vector3<usize>) -> Option<block::LookupId> {
+		self.index(offset)
+			.map(|index| self.block_ids[index])
+			.flatten()
+	}
+
+	/// Sets the block at an offset from the minimum corner of the region.
+	/// Offsets outside the region are ignored.
+	pub fn set(&mut self, offset: &Vector3<usize>, id: Option<block::LookupId>) {
+		if let Some(index) = self.index(offset) {
+			self.block_ids[index] = id;
+		}
+	}
+
+	/// Returns every block in the region (including air) with its offset from the minimum corner.
+	pub fn blocks(&self) -> impl Iterator<Item = (Vector3<usize>, Option<block::LookupId>)> + '_ {
+		let size = self.size;
+		self.block_ids.iter().enumerate().map(move |(index, id)| {
+			let layer = size.x * size.z;
+			let offset = Vector3::new(index % size.x, index / layer, (index % layer) / size.x);
+			(offset, *id)
+		})
+	}
+
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let schematic = Self::from_versioned_bytes(&std::fs::read(path)?)?;
+		let volume = volume(&schematic.size)?;
+		if schematic.block_ids.len() != volume {
+			return Err(Error::MismatchedVolume(schematic.block_ids.len(), volume))?;
+		}
+		Ok(schematic)
+	}
+
+	pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(path, self.to_versioned_bytes()?)?;
+		Ok(())
+	}
+}
+
+/// Consecutive blocks (in the order of the dense array) of the same type.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Run {
+	count: u32,
+	id: Option<block::LookupId>,
+}
+
+/// The layout a schematic is saved with.
+#[derive(Clone, Serialize, Deserialize)]
+struct Runs {
+	size: Vector3<usize>,
+	runs: Vec<Run>,
+}
+
+impl From<Schematic> for Runs {
+	fn from(schematic: Schematic) -> Self {
+		let mut runs = Vec::<Run>::new();
+		for id in schematic.block_ids.into_iter() {
+			match runs.last_mut() {
+				Some(run) if run.id == id && run.count < u32::MAX => run.count += 1,
+				_ => runs.push(Run { count: 1, id }),
+			}
+		}
+		Self {
+			size: schematic.size,
+			runs,
+		}
+	}
+}
+
+impl TryFrom<Runs> for Schematic {
+	type Error = Error;
+	/// Fails before any blocks are allocated if the size is too large,
+	/// or if the runs hold more blocks than the size has room for.
+	fn try_from(saved: Runs) -> Result<Self, Self::Error> {
+		let volume = volume(&saved.size)?;
+		let count = saved.runs.iter().fold(0usize, |count, run| {
+			count.saturating_add(run.count as usize)
+		});
+		if count > volume {
+			return Err(Error::MismatchedVolume(count, volume));
+		}
+		let mut block_ids = Vec::with_capacity(count);
+		for run in saved.runs.into_iter() {
+			block_ids.extend(std::iter::repeat(run.id).take(run.count as usize));
+		}
+		Ok(Self {
+			size: saved.size,
+			block_ids,
+		})
+	}
+}
+
+/// The layout of a schematic before consecutive blocks were saved as runs.
+#[derive(Deserialize)]
+struct SchematicV1 {
+	size: Vector3<usize>,
+	block_ids: Vec<Option<block::LookupId>>,
+}
+
+impl Versioned for Schematic {
+	const VERSION: Version = 2;
+
+	fn migrate(version: Version, bytes: &[u8]) -> anyhow::Result<Self> {
+		match version {
+			1 => {
+				let schematic: SchematicV1 = bincode::deserialize(bytes)?;
+				volume(&schematic.size)?;
+				Ok(Self {
+					size: schematic.size,
+					block_ids: schematic.block_ids,
+				})
+			}
+			_ => Err(versioned::Error::NoMigration(version, Self::VERSION))?,
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("the schematic has {0} blocks, but its size has a volume of {1}")]
+	MismatchedVolume(usize, usize),
+	#[error(
+		"the schematic's size of {0}x{1}x{2} is too large, schematics can hold at most {} blocks",
+		MAX_VOLUME
+	)]
+	TooLarge(usize, usize, usize),
+	#[error(
+		"\"{0}\" is not a valid schematic name, names cannot contain path separators or \"..\""
+	)]
+	InvalidName(String),
+}
+
+#[cfg(test)]
+mod schematic {
+	use super::*;
+
+	#[test]
+	fn offsets_round_trip() {
+		let mut schematic = Schematic::new(Vector3::new(3, 2, 4));
+		schematic.set(&Vector3::new(2, 1, 3), Some(5));
+		schematic.set(&Vector3::new(1, 0, 2), Some(6));
+		// Outside the region
+		schematic.set(&Vector3::new(3, 0, 0), Some(7));
+
+		assert_eq!(schematic.get(&Vector3::new(2, 1, 3)), Some(5));
+		assert_eq!(schematic.get(&Vector3::new(1, 0, 2)), Some(6));
+		assert_eq!(schematic.get(&Vector3::new(0, 0, 0)), None);
+		assert_eq!(schematic.get(&Vector3::new(3, 0, 0)), None);
+
+		let blocks = schematic.blocks().collect::<Vec<_>>();
+		assert_eq!(blocks.len(), 3 * 2 * 4);
+		for (offset, id) in blocks.into_iter() {
+			assert_eq!(schematic.get(&offset), id);
+		}
+	}
+
+	#[test]
+	fn air_is_saved_as_runs() {
+		let mut schematic = Schematic::new(Vector3::new(16, 16, 16));
+		schematic.set(&Vector3::new(8, 8, 8), Some(1));
+		let runs = Runs::from(schematic.clone()).runs;
+		assert_eq!(
+			runs,
+			vec![
+				Run {
+					count: 8 + 8 * 16 + 8 * 256,
+					id: None
+				},
+				Run {
+					count: 1,
+					id: Some(1)
+				},
+				Run {
+					count: 4096 - (8 + 8 * 16 + 8 * 256) - 1,
+					id: None
+				},
+			]
+		);
+		let bytes = schematic.to_versioned_bytes().unwrap();
+		assert!(bytes.len() < 100);
+		assert_eq!(Schematic::from_versioned_bytes(&bytes).unwrap(), schematic);
+	}
+
+	#[test]
+	fn migrates_v1() {
+		#[derive(Serialize)]
+		struct WriteV1 {
+			size: Vector3<usize>,
+			block_ids: Vec<Option<block::LookupId>>,
+		}
+		let v1 = WriteV1 {
+			size: Vector3::new(2, 1, 1),
+			block_ids: vec![Some(3), None],
+		};
+		let mut bytes = b"CSVF".to_vec();
+		bytes.extend_from_slice(&1u16.to_le_bytes());
+		bytes.append(&mut bincode::serialize(&v1).unwrap());
+		let schematic = Schematic::from_versioned_bytes(&bytes).unwrap();
+		assert_eq!(*schematic.size(), v1.size);
+		assert_eq!(schematic.get(&Vector3::new(0, 0, 0)), Some(3));
+		assert_eq!(schematic.get(&Vector3::new(1, 0, 0)), None);
+	}
+
+	#[test]
+	fn names_cannot_leave_the_directory() {
+		assert!(validate_name("clipboard").is_ok());
+		assert!(validate_name("tower-2").is_ok());
+		for name in [
+			"",
+			".",
+			"..",
+			"../world/level",
+			"a/b",
+			"a\\b",
+			"/etc/passwd",
+			"a..b",
+		] {
+			assert!(
+				matches!(validate_name(name), Err(Error::InvalidName(_))),
+				"{} was accepted",
+				name
+			);
+		}
+	}
+
+	#[test]
+	fn overflowing_volume_is_rejected() {
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-schematic-{}.schematic",
+			uuid::Uuid::new_v4()
+		));
+		let schematic = Schematic {
+			size: Vector3::new(usize::MAX, 2, 1),
+			block_ids: Vec::new(),
+		};
+		schematic.save(&path).unwrap();
+		// The size is rejected while deserializing, which only keeps the message of the error.
+		let err = Schematic::load(&path).unwrap_err();
+		assert!(err.to_string().contains("too large"), "{}", err);
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn runs_beyond_volume_are_rejected() {
+		// A few runs can claim far more blocks than the size has room for, which are never allocated.
+		let runs = Runs {
+			size: Vector3::new(2, 2, 2),
+			runs: vec![
+				Run {
+					count: u32::MAX,
+					id: Some(1),
+				};
+				4
+			],
+		};
+		assert!(matches!(
+			Schematic::try_from(runs),
+			Err(Error::MismatchedVolume(_, 8))
+		));
+
+		let runs = Runs {
+			size: Vector3::new(MAX_VOLUME, 1, 2),
+			runs: vec![Run { count: 1, id: None }],
+		};
+		assert!(matches!(
+			Schematic::try_from(runs),
+			Err(Error::TooLarge(_, _, _))
+		));
+	}
+
+	#[test]
+	fn mismatched_volume_is_rejected() {
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-schematic-{}.schematic",
+			uuid::Uuid::new_v4()
+		));
+		let mut schematic = Schematic::new(Vector3::new(2, 2, 2));
+		schematic.block_ids.pop();
+		schematic.save(&path).unwrap();
+		assert!(Schematic::load(&path).is_err());
+		let _ = std::fs::remove_file(&path);
+	}
+}
@@ -6,8 +6,17 @@ pub use command_window::*;
 mod entity_inspector;
 pub use entity_inspector::*;
 
+mod entity_snapshot;
+pub use entity_snapshot::*;
+
 mod chunk_inspector;
 pub use chunk_inspector::*;
 
+mod instance_churn;
+pub use instance_churn::*;
+
+mod network_inspector;
+pub use network_inspector::*;
+
 mod panel;
 pub use panel::*;
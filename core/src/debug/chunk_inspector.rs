@@ -1,13 +1,41 @@
-use engine::ui::egui::Element;
+use crate::{
+	block,
+	entity::{self, component, ArcLockEntityWorld},
+	graphics::voxel::{greedy::MeshMode, instance},
+};
+use engine::{math::nalgebra::Point3, ui::egui::Element};
+use std::sync::{Arc, RwLock, Weak};
+
+/// The instance-buffer occupancy of a chunk at the time it was last inspected.
+struct Occupancy {
+	active: usize,
+	inactive: usize,
+	mode: MeshMode,
+	quads: usize,
+	categories: Vec<(Option<block::LookupId>, usize)>,
+}
 
 /// In-Game debug window for examining information about a chunk in the world.
 pub struct ChunkInspector {
+	entity_world: Weak<RwLock<entity::World>>,
 	is_open: bool,
+	follow_local_player: bool,
+	chunk: Point3<i64>,
+	occupancy: Option<Occupancy>,
+	/// The mesh mode selected for the chunk, which is applied the next time the buffer is free.
+	requested_mode: Option<MeshMode>,
 }
 
 impl ChunkInspector {
-	pub fn new() -> Self {
-		Self { is_open: false }
+	pub fn new(entity_world: &ArcLockEntityWorld) -> Self {
+		Self {
+			entity_world: Arc::downgrade(&entity_world),
+			is_open: false,
+			follow_local_player: true,
+			chunk: Point3::origin(),
+			occupancy: None,
+			requested_mode: None,
+		}
 	}
 }
 
@@ -22,8 +50,134 @@ impl Element for ChunkInspector {
 		if !self.is_open {
 			return;
 		}
+		let mut is_open = self.is_open;
 		egui::Window::new("Chunk Inspector")
-			.open(&mut self.is_open)
-			.show(ctx, move |_ui| {});
+			.open(&mut is_open)
+			.show(ctx, |ui| {
+				self.render_selector(ui);
+				self.update_occupancy();
+				self.render_occupancy(ui);
+				self.render_mesh_mode(ui);
+			});
+		self.is_open = is_open;
+	}
+}
+
+impl ChunkInspector {
+	fn find_local_player_chunk(&self) -> Option<Point3<i64>> {
+		use component::{physics::linear::Position, OwnedByAccount};
+		let local_id = crate::client::account::Manager::read()
+			.ok()?
+			.active_account()
+			.ok()?
+			.id();
+		let arc_world = self.entity_world.upgrade()?;
+		let world = arc_world.read().unwrap();
+		let mut query = world.query::<(&OwnedByAccount, &Position)>();
+		let chunk = query
+			.iter()
+			.find(|(_, (owner, _))| *owner.id() == local_id)
+			.map(|(_, (_, position))| *position.chunk());
+		chunk
+	}
+
+	fn render_selector(&mut self, ui: &mut egui::Ui) {
+		ui.checkbox(&mut self.follow_local_player, "Follow local player");
+		if self.follow_local_player {
+			if let Some(chunk) = self.find_local_player_chunk() {
+				self.chunk = chunk;
+			}
+		}
+		ui.add_enabled_ui(!self.follow_local_player, |ui| {
+			ui.horizontal(|ui| {
+				ui.label("Chunk");
+				ui.add(egui::DragValue::new(&mut self.chunk.x).speed(1));
+				ui.add(egui::DragValue::new(&mut self.chunk.y).speed(1));
+				ui.add(egui::DragValue::new(&mut self.chunk.z).speed(1));
+			});
+		});
+	}
+
+	fn update_occupancy(&mut self) {
+		let arc_buffer = match instance::Buffer::active_local() {
+			Some(arc) => arc,
+			None => {
+				self.occupancy = None;
+				return;
+			}
+		};
+		// The instance-update thread can hold the buffer for a number of milliseconds.
+		// Rather than stalling the frame, keep showing the last occupancy until the buffer is free.
+		if let Ok(mut buffer) = arc_buffer.try_lock() {
+			if let Some(mode) = self.requested_mode.take() {
+				if let Err(err) = buffer.set_mesh_mode(self.chunk, mode) {
+					log::error!(target: "chunk-inspector", "{:?}", err);
+				}
+			}
+			self.occupancy = Some(Occupancy {
+				active: buffer.active_count_in(&self.chunk),
+				inactive: buffer.inactive_count_in(&self.chunk),
+				mode: buffer.mesh_mode(&self.chunk),
+				quads: buffer.quad_count_in(&self.chunk),
+				categories: buffer.category_lengths(),
+			});
+		}
+	}
+
+	fn render_occupancy(&self, ui: &mut egui::Ui) {
+		let occupancy = match &self.occupancy {
+			Some(occupancy) => occupancy,
+			None => {
+				ui.label("No voxel instance buffer.");
+				return;
+			}
+		};
+		ui.label(format!("Active voxels: {}", occupancy.active));
+		ui.label(format!("Inactive voxels: {}", occupancy.inactive));
+		if occupancy.mode == MeshMode::Greedy {
+			ui.label(format!("Greedy quads: {}", occupancy.quads));
+		}
+		ui.separator();
+		ui.label("Instance buffer categories");
+		ui.indent("categories", |ui| {
+			for (id, count) in occupancy.categories.iter() {
+				let name = match id {
+					Some(id) => match block::Lookup::lookup_id(*id) {
+						Some(asset_id) => asset_id.to_string(),
+						None => format!("Unknown({})", id),
+					},
+					None => "Unallocated".to_owned(),
+				};
+				// Only show block-types which have instances (and the remaining unallocated space).
+				if *count > 0 || id.is_none() {
+					ui.label(format!("{}: {}", name, count));
+				}
+			}
+		});
+		ui.separator();
+		ui.label(format!(
+			"Uploaded last frame: {} bytes",
+			instance::Buffer::uploaded_bytes()
+		));
+		if instance::Buffer::capacity_exceeded() {
+			ui.label("Instance buffer is full, some voxels are not rendered");
+		}
+	}
+
+	fn render_mesh_mode(&mut self, ui: &mut egui::Ui) {
+		let current = match &self.occupancy {
+			Some(occupancy) => occupancy.mode,
+			None => return,
+		};
+		let mut mode = self.requested_mode.unwrap_or(current);
+		ui.separator();
+		ui.horizontal(|ui| {
+			ui.label("Meshing");
+			ui.radio_value(&mut mode, MeshMode::Instanced, "Per voxel");
+			ui.radio_value(&mut mode, MeshMode::Greedy, "Greedy");
+		});
+		if mode != current {
+			self.requested_mode = Some(mode);
+		}
 	}
 }
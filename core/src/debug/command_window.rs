@@ -1,9 +1,13 @@
 use crate::commands::CommandList;
 use engine::ui::egui::Element;
+use std::sync::mpsc::Sender;
 
 pub struct CommandWindow {
 	is_open: bool,
 	commands: CommandList,
+	/// Submits lines of text to the [`command intake`](crate::commands::Intake).
+	console: Option<Sender<String>>,
+	line: String,
 }
 
 impl CommandWindow {
@@ -11,8 +15,15 @@ impl CommandWindow {
 		Self {
 			is_open: false,
 			commands,
+			console: None,
+			line: String::new(),
 		}
 	}
+
+	pub fn with_console(mut self, sender: Sender<String>) -> Self {
+		self.console = Some(sender);
+		self
+	}
 }
 
 impl super::PanelWindow for CommandWindow {
@@ -27,9 +38,22 @@ impl Element for CommandWindow {
 			return;
 		}
 		let cmds = self.commands.clone();
+		let console = self.console.as_ref();
+		let line = &mut self.line;
 		egui::Window::new("Debug Commands")
 			.open(&mut self.is_open)
 			.show(ctx, move |ui| {
+				if let Some(sender) = console {
+					ui.horizontal(|ui| {
+						let response = ui.text_edit_singleline(line);
+						let submitted =
+							response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+						if (submitted || ui.button("Run").clicked()) && !line.trim().is_empty() {
+							let _ = sender.send(std::mem::take(line));
+						}
+					});
+					ui.separator();
+				}
 				let command_list = cmds.lock().unwrap();
 				for arc_cmd in command_list.iter() {
 					let mut command = arc_cmd.lock().unwrap();
@@ -1,18 +1,9 @@
-use crate::{
-	common::account,
-	entity::{
-		self,
-		component::{self, debug},
-		ArcLockEntityWorld,
-	},
-};
+use super::{EntitySnapshot, SnapshotBuffer, WorldSnapshot};
+use crate::common::account;
 use anyhow::Result;
 use engine::ui::egui::Element;
 use enumset::{EnumSet, EnumSetType};
-use std::{
-	collections::HashSet,
-	sync::{Arc, RwLock, Weak},
-};
+use std::{collections::HashSet, sync::Arc};
 
 #[derive(EnumSetType)]
 enum Selector {
@@ -53,8 +44,11 @@ impl Selector {
 }
 
 /// In-Game debug window for examining information about an entity (like the local player).
+///
+/// Entities are examined through the latest [`snapshot`](WorldSnapshot) of the entity world,
+/// so rendering the window never waits on systems which are writing to the world.
 pub struct EntityInspector {
-	entity_world: Weak<RwLock<entity::World>>,
+	snapshots: Arc<SnapshotBuffer>,
 	is_open: bool,
 	selector: Selector,
 	provided_entity_id: u32,
@@ -62,9 +56,9 @@ pub struct EntityInspector {
 }
 
 impl EntityInspector {
-	pub fn new(entity_world: &ArcLockEntityWorld) -> Self {
+	pub fn new(snapshots: &Arc<SnapshotBuffer>) -> Self {
 		Self {
-			entity_world: Arc::downgrade(&entity_world),
+			snapshots: snapshots.clone(),
 			is_open: false,
 			selector: Selector::LocalOwner,
 			provided_entity_id: 0,
@@ -81,27 +75,13 @@ impl EntityInspector {
 			.map(|account| account.id())
 	}
 
-	fn find_entity(&self) -> Option<hecs::Entity> {
-		use entity::component::OwnedByAccount;
-		let arc_world = self.entity_world.upgrade().unwrap();
-		let world = arc_world.read().unwrap();
+	fn find_entity<'s>(&self, snapshot: &'s WorldSnapshot) -> Option<&'s EntitySnapshot> {
 		match self.selector {
-			Selector::LocalOwner => {
-				let local_id = match Self::local_account_id() {
-					Ok(id) => id,
-					Err(_) => return None,
-				};
-				for (entity, user) in world.query::<&OwnedByAccount>().iter() {
-					if *user.id() == local_id {
-						return Some(entity);
-					}
-				}
-				None
-			}
-			Selector::ProvidedId => world
-				.iter()
-				.find(|e| e.entity().id() == self.provided_entity_id)
-				.map(|e| e.entity().clone()),
+			Selector::LocalOwner => match Self::local_account_id() {
+				Ok(local_id) => snapshot.find_owned_by(&local_id),
+				Err(_) => None,
+			},
+			Selector::ProvidedId => snapshot.find_by_id(self.provided_entity_id),
 		}
 	}
 }
@@ -143,7 +123,8 @@ impl EntityInspector {
 		// TODO: show entity components that are only on the server even if the client is not CotoS?
 		// TODO: ComboBox of component types on the entity. Can select multiple. Those selected are shown in the list, if they have a egui debug trait implemented.
 
-		let entity = match self.find_entity() {
+		let snapshot = self.snapshots.latest();
+		let entity = match self.find_entity(&snapshot) {
 			Some(entity) => entity,
 			None => {
 				ui.label("No entity selected.");
@@ -151,45 +132,35 @@ impl EntityInspector {
 			}
 		};
 
-		let arc_world = self.entity_world.upgrade().unwrap();
-		let world = arc_world.read().unwrap();
-		let entity_ref = match world.entity(entity) {
-			Ok(entity_ref) => entity_ref,
-			Err(_) => {
-				ui.label("Entity not found in world.");
-				return;
-			}
-		};
-
-		let registry = component::Registry::read();
-
 		ui.horizontal(|ui| {
 			ui.label("Components");
 			egui::ComboBox::from_id_source("Components")
 				.selected_text(format!("{} components", self.components_to_show.len()))
 				.show_ui(ui, |ui| {
-					for type_id in entity_ref.component_types() {
-						if let Some(registered) = registry.find(&type_id) {
-							let is_showing = self.components_to_show.contains(&type_id);
-							let can_be_displayed = registered.has_ext::<debug::Registration>();
-							let label =
-								egui::SelectableLabel::new(is_showing, registered.display_name());
-							if ui.add_enabled(can_be_displayed, label).clicked() {
-								match is_showing {
-									true => self.components_to_show.remove(&type_id),
-									false => self.components_to_show.insert(type_id),
-								};
-							}
+					for component in entity.components.iter() {
+						let is_showing = self.components_to_show.contains(&component.type_id);
+						let can_be_displayed = component.description.is_some();
+						let label = egui::SelectableLabel::new(is_showing, component.display_name);
+						if ui.add_enabled(can_be_displayed, label).clicked() {
+							match is_showing {
+								true => self.components_to_show.remove(&component.type_id),
+								false => self.components_to_show.insert(component.type_id),
+							};
 						}
 					}
 				});
 		});
 		for type_id in self.components_to_show.iter() {
-			let registered = registry.find(&type_id).unwrap();
-			if let Some(debug_registration) = registered.get_ext::<debug::Registration>() {
-				ui.label(registered.display_name());
-				ui.indent(registered.id(), |ui| {
-					debug_registration.render(&entity_ref, ui);
+			let component = match entity.component(type_id) {
+				Some(component) => component,
+				None => continue,
+			};
+			if let Some(description) = &component.description {
+				ui.label(component.display_name);
+				ui.indent(component.id, |ui| {
+					for line in description.iter() {
+						ui.label(line);
+					}
 				});
 			}
 		}
@@ -0,0 +1,237 @@
+use crate::{
+	common::account,
+	entity::{
+		self,
+		component::{self, debug, OwnedByAccount},
+		ArcLockEntityWorld,
+	},
+};
+use engine::EngineSystem;
+use std::{
+	any::TypeId,
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+/// How often the [`EntitySnapshotter`] captures a new snapshot of the entity world.
+pub static SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A copy of a component, as it was when its entity was snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentSnapshot {
+	pub type_id: TypeId,
+	pub id: &'static str,
+	pub display_name: &'static str,
+	/// The [`described`](debug::EguiInformation::describe) lines of the component,
+	/// or None if the component-type has no debug registration.
+	pub description: Option<Vec<String>>,
+}
+
+/// A copy of the registered components of an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntitySnapshot {
+	pub entity: hecs::Entity,
+	/// The account which owns the entity, if it is [`owned by an account`](OwnedByAccount).
+	pub owner: Option<account::Id>,
+	pub components: Vec<ComponentSnapshot>,
+}
+
+impl EntitySnapshot {
+	pub fn component(&self, type_id: &TypeId) -> Option<&ComponentSnapshot> {
+		self.components
+			.iter()
+			.find(|component| component.type_id == *type_id)
+	}
+}
+
+/// A copy of every entity in the entity world (and their registered components) at a moment in time.
+/// Lets debug tools examine the world without holding its lock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldSnapshot {
+	pub entities: Vec<EntitySnapshot>,
+}
+
+impl WorldSnapshot {
+	pub fn capture(world: &entity::World) -> Self {
+		profiling::scope!("capture-entity-snapshot");
+		let registry = component::Registry::read();
+		let entities = world
+			.iter()
+			.map(|entity_ref| EntitySnapshot {
+				entity: entity_ref.entity(),
+				owner: entity_ref
+					.get::<&OwnedByAccount>()
+					.map(|owner| owner.id().clone()),
+				components: entity_ref
+					.component_types()
+					.filter_map(|type_id| {
+						let registered = registry.find(&type_id)?;
+						Some(ComponentSnapshot {
+							type_id,
+							id: registered.id(),
+							display_name: registered.display_name(),
+							description: registered
+								.get_ext::<debug::Registration>()
+								.map(|ext| ext.describe(&entity_ref))
+								.flatten(),
+						})
+					})
+					.collect(),
+			})
+			.collect();
+		Self { entities }
+	}
+
+	pub fn find_owned_by(&self, account_id: &account::Id) -> Option<&EntitySnapshot> {
+		self.entities
+			.iter()
+			.find(|entity| entity.owner.as_ref() == Some(account_id))
+	}
+
+	pub fn find_by_id(&self, id: u32) -> Option<&EntitySnapshot> {
+		self.entities.iter().find(|entity| entity.entity.id() == id)
+	}
+}
+
+/// The double-buffered [`WorldSnapshot`] shared by the [`EntitySnapshotter`] and its readers.
+///
+/// Snapshots are captured without holding this lock, and are swapped in as a whole,
+/// so readers only ever wait for a pointer swap (and never for the entity world).
+#[derive(Default)]
+pub struct SnapshotBuffer {
+	latest: RwLock<Arc<WorldSnapshot>>,
+}
+
+impl SnapshotBuffer {
+	pub fn arced(self) -> Arc<Self> {
+		Arc::new(self)
+	}
+
+	/// The most recently captured snapshot, which stays valid even after a newer snapshot is published.
+	pub fn latest(&self) -> Arc<WorldSnapshot> {
+		self.latest.read().unwrap().clone()
+	}
+
+	pub fn publish(&self, snapshot: WorldSnapshot) {
+		*self.latest.write().unwrap() = Arc::new(snapshot);
+	}
+}
+
+/// System which periodically captures a snapshot of the entity world into a [`SnapshotBuffer`].
+///
+/// The world is only read if no other system is writing to it,
+/// otherwise the capture is attempted again on the next update.
+pub struct EntitySnapshotter {
+	world: Weak<RwLock<entity::World>>,
+	buffer: Arc<SnapshotBuffer>,
+	interval: Duration,
+	since_refresh: Duration,
+}
+
+impl EntitySnapshotter {
+	pub fn new(world: &ArcLockEntityWorld, buffer: &Arc<SnapshotBuffer>) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+			buffer: buffer.clone(),
+			interval: SNAPSHOT_INTERVAL,
+			since_refresh: SNAPSHOT_INTERVAL,
+		}
+	}
+
+	pub fn with_interval(mut self, interval: Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+
+	/// Captures and publishes a snapshot if the world is not currently being written to.
+	/// Returns true if a snapshot was published.
+	pub fn refresh(&mut self) -> bool {
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return false,
+		};
+		let snapshot = match arc_world.try_read() {
+			Ok(world) => WorldSnapshot::capture(&world),
+			Err(_) => return false,
+		};
+		self.buffer.publish(snapshot);
+		self.since_refresh = Duration::ZERO;
+		true
+	}
+}
+
+impl EngineSystem for EntitySnapshotter {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!("subsystem:entity_snapshotter");
+		self.since_refresh += delta_time;
+		if self.since_refresh >= self.interval {
+			self.refresh();
+		}
+	}
+}
+
+#[cfg(test)]
+mod refresh {
+	use super::*;
+	use crate::entity::component::physics::linear::{Position, Velocity};
+	use engine::math::nalgebra::Vector3;
+
+	#[test]
+	fn snapshot_reflects_component_added_between_refreshes() {
+		{
+			let mut registry = component::Registry::write();
+			registry.register::<Position>();
+			registry.register::<Velocity>();
+		}
+		let world: ArcLockEntityWorld = Arc::new(RwLock::new(entity::World::new()));
+		let buffer = SnapshotBuffer::default().arced();
+		let mut snapshotter =
+			EntitySnapshotter::new(&world, &buffer).with_interval(Duration::from_secs(1));
+		let entity = world.write().unwrap().spawn((Position::default(),));
+
+		assert!(snapshotter.refresh());
+		let first = buffer.latest();
+		let velocity_type = TypeId::of::<Velocity>();
+		assert!(first.find_by_id(entity.id()).is_some());
+		assert!(first
+			.find_by_id(entity.id())
+			.unwrap()
+			.component(&velocity_type)
+			.is_none());
+
+		let mut velocity = Velocity::default();
+		*velocity = Vector3::new(1.0, 0.0, 0.0);
+		world.write().unwrap().insert_one(entity, velocity).unwrap();
+		// The snapshot is not refreshed until the interval has elapsed.
+		snapshotter.update(Duration::from_millis(500), true);
+		assert_eq!(buffer.latest(), first);
+
+		// Writers block the refresh, which is retried on the next update.
+		{
+			let _writer = world.write().unwrap();
+			snapshotter.update(Duration::from_millis(500), true);
+		}
+		assert_eq!(buffer.latest(), first);
+		snapshotter.update(Duration::ZERO, true);
+
+		let second = buffer.latest();
+		let velocity = second
+			.find_by_id(entity.id())
+			.unwrap()
+			.component(&velocity_type)
+			.unwrap();
+		assert_eq!(velocity.display_name, "Velocity");
+		let description = velocity.description.as_ref().unwrap();
+		assert_eq!(description[0], "<1.00, 0.00, 0.00>");
+		// The earlier snapshot is unchanged, so readers are never affected by a refresh.
+		assert!(first
+			.find_by_id(entity.id())
+			.unwrap()
+			.component(&velocity_type)
+			.is_none());
+	}
+}
@@ -0,0 +1,131 @@
+use crate::{
+	client::model::blender::render::RenderModel,
+	graphics::voxel::instance::{self, ChangeStats},
+};
+use engine::ui::egui::Element;
+use std::collections::VecDeque;
+
+/// The number of frames of history shown in the graphs.
+static HISTORY_LENGTH: usize = 300;
+
+/// In-Game debug window which graphs how much of the voxel and entity instance buffers change each frame.
+///
+/// Each frame, the changed ranges of the local instance data are taken and uploaded to the gpu.
+/// Many small ranges, or a large number of changed indices, show up here as spikes
+/// (e.g. when the entire instance buffer is being rewritten).
+pub struct InstanceChurn {
+	is_open: bool,
+	is_paused: bool,
+	history: VecDeque<Sample>,
+}
+
+/// The changes to each instance buffer during a frame.
+#[derive(Clone, Copy, Default)]
+struct Sample {
+	voxels: ChangeStats,
+	entities: ChangeStats,
+}
+
+impl InstanceChurn {
+	pub fn new() -> Self {
+		Self {
+			is_open: false,
+			is_paused: false,
+			history: VecDeque::with_capacity(HISTORY_LENGTH),
+		}
+	}
+
+	fn sample(&mut self) {
+		if self.is_paused {
+			return;
+		}
+		if self.history.len() == HISTORY_LENGTH {
+			self.history.pop_front();
+		}
+		self.history.push_back(Sample {
+			voxels: instance::Buffer::last_changes(),
+			entities: RenderModel::last_changes(),
+		});
+	}
+}
+
+impl super::PanelWindow for InstanceChurn {
+	fn is_open_mut(&mut self) -> &mut bool {
+		&mut self.is_open
+	}
+}
+
+impl Element for InstanceChurn {
+	fn render(&mut self, ctx: &egui::Context) {
+		if !self.is_open {
+			return;
+		}
+		self.sample();
+		let mut is_open = self.is_open;
+		egui::Window::new("Instance Churn")
+			.open(&mut is_open)
+			.show(ctx, |ui| {
+				ui.checkbox(&mut self.is_paused, "Pause");
+				self.render_latest(ui);
+				self.render_graph(ui, "Changed ranges", |stats| stats.ranges);
+				self.render_graph(ui, "Changed indices", |stats| stats.indices);
+			});
+		self.is_open = is_open;
+	}
+}
+
+impl InstanceChurn {
+	fn render_latest(&self, ui: &mut egui::Ui) {
+		let latest = self.history.back().cloned().unwrap_or_default();
+		let peak = |stats: fn(&Sample) -> &ChangeStats| {
+			self.history
+				.iter()
+				.map(|sample| stats(sample).indices)
+				.max()
+				.unwrap_or_default()
+		};
+		egui::Grid::new("instance_churn_latest").show(ui, |ui| {
+			ui.label("");
+			ui.label("Voxels");
+			ui.label("Entities");
+			ui.end_row();
+			ui.label("Ranges (last frame)");
+			ui.label(format!("{}", latest.voxels.ranges));
+			ui.label(format!("{}", latest.entities.ranges));
+			ui.end_row();
+			ui.label("Indices (last frame)");
+			ui.label(format!("{}", latest.voxels.indices));
+			ui.label(format!("{}", latest.entities.indices));
+			ui.end_row();
+			ui.label(format!("Peak indices ({} frames)", self.history.len()));
+			ui.label(format!("{}", peak(|sample| &sample.voxels)));
+			ui.label(format!("{}", peak(|sample| &sample.entities)));
+			ui.end_row();
+		});
+	}
+
+	fn render_graph(&self, ui: &mut egui::Ui, name: &str, value: fn(&ChangeStats) -> usize) {
+		use egui::plot::{Line, Plot, PlotPoints};
+		let line = |label: &str, stats: fn(&Sample) -> &ChangeStats| {
+			let points = self
+				.history
+				.iter()
+				.enumerate()
+				.map(|(frame, sample)| [frame as f64, value(stats(sample)) as f64])
+				.collect::<PlotPoints>();
+			Line::new(points).name(label)
+		};
+		ui.label(name);
+		Plot::new(name)
+			.height(100.0)
+			.include_y(0.0)
+			.include_x(0.0)
+			.include_x(HISTORY_LENGTH as f64)
+			.allow_drag(false)
+			.allow_zoom(false)
+			.show(ui, |plot_ui| {
+				plot_ui.line(line("Voxels", |sample| &sample.voxels));
+				plot_ui.line(line("Entities", |sample| &sample.entities));
+			});
+	}
+}
@@ -0,0 +1,127 @@
+use crate::{
+	common::{account, network::Storage},
+	entity::system::replicator::{ConnectionStats, Replicator},
+};
+use engine::ui::egui::Element;
+use std::sync::{Arc, RwLock, Weak};
+
+/// In-Game debug window which shows what the server in this process is replicating to each connection,
+/// and which players the client in this process was told have left the server.
+///
+/// Connections with many pending or in-flight chunks are falling behind on the world around them,
+/// which is the first place to look when clients are flooded with (or starved of) chunks.
+pub struct NetworkInspector {
+	storage: Weak<RwLock<Storage>>,
+	is_open: bool,
+	/// The stats of each connection when they were last read, if the server is running in this process.
+	connections: Option<Vec<ConnectionStats>>,
+	/// The players who most recently left the server, if a client is running in this process.
+	departures: Option<Vec<account::Id>>,
+}
+
+impl NetworkInspector {
+	pub fn new(storage: &Arc<RwLock<Storage>>) -> Self {
+		Self {
+			storage: Arc::downgrade(&storage),
+			is_open: false,
+			connections: None,
+			departures: None,
+		}
+	}
+
+	fn update_departures(&mut self) {
+		self.departures = self.storage.upgrade().and_then(|arc_storage| {
+			let storage = arc_storage.read().unwrap();
+			let arc_client = storage.client().as_ref()?;
+			let client = arc_client.read().unwrap();
+			Some(client.departures().iter().cloned().collect())
+		});
+	}
+
+	fn render_departures(&self, ui: &mut egui::Ui) {
+		let departures = match &self.departures {
+			Some(departures) => departures,
+			None => return,
+		};
+		ui.separator();
+		ui.label("Players who left");
+		if departures.is_empty() {
+			ui.label("None.");
+		}
+		// The most recent departure is listed first.
+		for account_id in departures.iter().rev() {
+			ui.label(account_id);
+		}
+	}
+
+	fn update_connections(&mut self) {
+		let arc_replicator = match Replicator::active() {
+			Some(arc) => arc,
+			None => {
+				self.connections = None;
+				return;
+			}
+		};
+		// The replicator is held for the whole of each tick it replicates,
+		// so keep showing the last stats instead of stalling the frame until it is free.
+		if let Ok(replicator) = arc_replicator.try_read() {
+			self.connections = Some(replicator.connection_stats());
+		}
+	}
+
+	fn render_connections(&self, ui: &mut egui::Ui) {
+		let connections = match &self.connections {
+			Some(connections) => connections,
+			None => {
+				ui.label("No server is running in this process.");
+				return;
+			}
+		};
+		if connections.is_empty() {
+			ui.label("No connections.");
+			return;
+		}
+		egui::Grid::new("connections").striped(true).show(ui, |ui| {
+			ui.label("Address");
+			ui.label("State");
+			ui.label("Relevant entities");
+			ui.label("Pending chunks");
+			ui.label("In-flight chunks");
+			ui.label("Pending bytes");
+			ui.end_row();
+			for stats in connections.iter() {
+				ui.label(stats.address.to_string());
+				ui.label(stats.state.to_string());
+				ui.label(stats.relevant_entities.to_string());
+				ui.label(stats.pending_chunks.to_string());
+				ui.label(stats.in_flight_chunks.to_string());
+				ui.label(stats.pending_bytes.to_string());
+				ui.end_row();
+			}
+		});
+	}
+}
+
+impl super::PanelWindow for NetworkInspector {
+	fn is_open_mut(&mut self) -> &mut bool {
+		&mut self.is_open
+	}
+}
+
+impl Element for NetworkInspector {
+	fn render(&mut self, ctx: &egui::Context) {
+		if !self.is_open {
+			return;
+		}
+		self.update_connections();
+		self.update_departures();
+		let mut is_open = self.is_open;
+		egui::Window::new("Network")
+			.open(&mut is_open)
+			.show(ctx, |ui| {
+				self.render_connections(ui);
+				self.render_departures(ui);
+			});
+		self.is_open = is_open;
+	}
+}
@@ -2,6 +2,10 @@ use std::sync::{Arc, RwLock, Weak};
 
 pub mod archetype;
 pub mod component;
+mod persistent_ids;
+pub use persistent_ids::*;
+mod query;
+pub use query::*;
 pub mod system;
 
 pub use hecs::World;
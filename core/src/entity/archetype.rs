@@ -1 +1,4 @@
 pub mod player;
+pub mod spectator;
+#[cfg(test)]
+pub mod test;
@@ -4,12 +4,19 @@ use crate::{
 	entity::component::{
 		chunk,
 		network::Replicated,
-		physics::linear::{Position, Velocity},
-		Camera, Orientation, OwnedByAccount, OwnedByConnection,
+		physics::{
+			linear::{AcknowledgedInput, Position, Prediction, Velocity},
+			Collider, Dynamic,
+		},
+		Camera, Inventory, Orientation, OwnedByAccount, OwnedByConnection, PersistentId,
 	},
 };
+use engine::math::nalgebra::Point3;
 use std::net::SocketAddr;
 
+/// The number of inventory slots players have.
+pub static HOTBAR_SIZE: usize = 9;
+
 pub struct Server(hecs::EntityBuilder);
 impl Server {
 	pub fn new() -> Self {
@@ -17,17 +24,26 @@ impl Server {
 		builder.add(Replicated::new_server());
 		builder.add(Position::default());
 		builder.add(Velocity::default());
+		// Players fall under gravity, and stand on (and walk into) the voxels of the world.
+		builder.add(Dynamic::default());
+		builder.add(Collider::player());
+		builder.add(AcknowledgedInput::default());
 		builder.add(Orientation::default());
+		builder.add(Inventory::new(HOTBAR_SIZE));
 		builder.add(chunk::TicketOwner::default().with_load_radius(5));
 		builder.add(
 			chunk::Relevancy::default()
 				.with_radius(6) // TODO: This radius should match the radius in the graphics instance buffer
+				.with_margin(1)
 				.with_entity_radius(5),
 		);
 		Self(builder)
 	}
 
+	/// Marks the player as owned by an account.
+	/// The player's entity has the same [`persistent id`](PersistentId) every time the account joins.
 	pub fn with_user_id(mut self, id: account::Id) -> Self {
+		self.0.add(PersistentId::for_account(&id));
 		self.0.add(OwnedByAccount::new(id));
 		self
 	}
@@ -37,6 +53,15 @@ impl Server {
 		self
 	}
 
+	/// Places the player at a position in blocks from the world origin,
+	/// such as one found by [`find_safe_spawn`](crate::server::world::spawn::find_safe_spawn).
+	pub fn with_spawn_point(mut self, point: Point3<f32>) -> Self {
+		let mut position = Position::default();
+		position.set_world_position(point.cast::<f64>());
+		self.0.add(position);
+		self
+	}
+
 	pub fn build(self) -> hecs::EntityBuilder {
 		self.0
 	}
@@ -50,6 +75,9 @@ impl Client {
 		use engine::Application;
 		let mut client = Self(builder, false);
 		client.add_opt::<Camera>();
+		client.add_opt::<Prediction>();
+		// Drawn in front of the camera once it holds an item.
+		client.add_opt::<client::model::HeldItem>();
 		client.add_opt_fn(|| {
 			client::model::PlayerModel::new(
 				DescriptorId {
@@ -0,0 +1,54 @@
+use crate::{
+	common::account,
+	entity::component::{
+		chunk,
+		physics::linear::{Position, Velocity},
+		Camera, Orientation, OwnedByAccount, Spectator,
+	},
+};
+use std::net::SocketAddr;
+
+/// The server's entity for a spectating connection.
+/// It loads and replicates chunks like a player, but is never replicated itself.
+pub struct Server(hecs::EntityBuilder);
+impl Server {
+	pub fn new(address: SocketAddr) -> Self {
+		let mut builder = hecs::EntityBuilder::default();
+		builder.add(Spectator::new(address));
+		builder.add(Position::default());
+		builder.add(Velocity::default());
+		builder.add(Orientation::default());
+		// Spectators keep chunks loaded the same as players do.
+		builder.add(chunk::TicketOwner::default().with_load_radius(5));
+		builder.add(
+			chunk::Relevancy::default()
+				.with_radius(6)
+				.with_margin(1)
+				.with_entity_radius(5),
+		);
+		Self(builder)
+	}
+
+	pub fn build(self) -> hecs::EntityBuilder {
+		self.0
+	}
+}
+
+/// The free-flying camera spawned by a spectating client.
+/// The movement of the camera is sent to the server, which moves the spectator's [`Server`] entity to match.
+pub struct Client(hecs::EntityBuilder);
+impl Client {
+	pub fn new(account_id: account::Id) -> Self {
+		let mut builder = hecs::EntityBuilder::default();
+		builder.add(OwnedByAccount::new(account_id));
+		builder.add(Position::default());
+		builder.add(Velocity::default());
+		builder.add(Orientation::default());
+		builder.add(Camera::default());
+		Self(builder)
+	}
+
+	pub fn build(self) -> hecs::EntityBuilder {
+		self.0
+	}
+}
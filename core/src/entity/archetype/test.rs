@@ -0,0 +1,105 @@
+//! Lightweight entities for testing systems which operate on replicated entities
+//! (e.g. the [`Replicator`](crate::entity::system::Replicator)),
+//! without any physics or rendering components.
+
+use crate::entity::component::{
+	binary, debug, network, physics::linear::Position, Component, Registration,
+};
+use anyhow::Result;
+use engine::math::nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+
+/// Builds a replicated entity at a fixed position.
+pub struct Marker(hecs::EntityBuilder);
+impl Marker {
+	/// Creates a marker at a position (in blocks from the world origin).
+	/// The same position always produces the same entity components.
+	pub fn new(position: Point3<f64>) -> Self {
+		let mut builder = hecs::EntityBuilder::default();
+		builder.add(network::Replicated::new_server());
+		let mut component = Position::default();
+		component.set_world_position(position);
+		builder.add(component);
+		builder.add(Label::from(&position));
+		Self(builder)
+	}
+
+	pub fn build(self) -> hecs::EntityBuilder {
+		self.0
+	}
+}
+
+/// A value derived from the position a [`Marker`] was spawned at,
+/// so that replicated markers can be matched to the original entities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label(u64);
+
+impl From<&Point3<f64>> for Label {
+	fn from(position: &Point3<f64>) -> Self {
+		// FNV-1a over the bits of each axis (std's hasher is not stable across releases).
+		let mut hash: u64 = 0xcbf29ce484222325;
+		for axis in position.iter() {
+			for byte in axis.to_bits().to_le_bytes().iter() {
+				hash ^= *byte as u64;
+				hash = hash.wrapping_mul(0x100000001b3);
+			}
+		}
+		Self(hash)
+	}
+}
+
+impl Label {
+	pub fn value(&self) -> u64 {
+		self.0
+	}
+}
+
+impl Component for Label {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::archetype::test::Label"
+	}
+
+	fn display_name() -> &'static str {
+		"Test Label"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Label {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Label({:016x})", self.0)
+	}
+}
+
+impl network::Replicatable for Label {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for Label {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for Label {
+	fn describe(&self) -> Vec<String> {
+		vec![format!("{:016x}", self.0)]
+	}
+}
@@ -1,8 +1,14 @@
 pub mod binary;
+mod block_interaction;
+pub use block_interaction::*;
+mod block_placement;
+pub use block_placement::*;
 mod camera;
 pub use camera::*;
 pub mod chunk;
 pub mod debug;
+mod inventory;
+pub use inventory::*;
 pub mod network;
 mod orientation;
 pub use orientation::*;
@@ -10,9 +16,15 @@ mod owned_by_account;
 pub use owned_by_account::*;
 mod owned_by_connection;
 pub use owned_by_connection::*;
+mod parent;
+pub use parent::*;
+mod persistent_id;
+pub use persistent_id::*;
 pub mod physics;
 mod registry;
 pub use registry::*;
+mod spectator;
+pub use spectator::*;
 
 pub trait Component: hecs::Component {
 	fn unique_id() -> &'static str;
@@ -27,15 +39,31 @@ pub trait Component: hecs::Component {
 
 pub fn register_types() {
 	let mut registry = Registry::write();
+	registry.register::<BlockInteraction>();
+	registry.register::<BlockPlacement>();
 	registry.register::<Camera>();
 	registry.register::<chunk::Relevancy>();
 	registry.register::<chunk::TicketOwner>();
+	registry.register::<Inventory>();
+	registry.register::<network::ConnectionQuality>();
 	registry.register::<network::Replicated>();
 	registry.register::<Orientation>();
 	registry.register::<OwnedByAccount>();
 	registry.register::<OwnedByConnection>();
+	registry.register::<Parent>();
+	registry.register::<physics::Collider>();
+	registry.register::<physics::Dynamic>();
+	registry.register::<physics::Frozen>();
+	registry.register::<physics::linear::AcknowledgedInput>();
+	registry.register::<physics::linear::InterpolatedPosition>();
 	registry.register::<physics::linear::Position>();
+	registry.register::<physics::linear::Prediction>();
 	registry.register::<physics::linear::Velocity>();
+	registry.register::<PersistentId>();
+	registry.register::<Spectator>();
 	registry.register::<crate::client::model::blender::Component>();
 	registry.register::<crate::client::model::PlayerModel>();
+	registry.register::<crate::client::model::HeldItem>();
+	#[cfg(test)]
+	registry.register::<super::archetype::test::Label>();
 }
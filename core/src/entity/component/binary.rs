@@ -16,12 +16,47 @@ pub struct SerializedComponent {
 }
 
 impl SerializedEntity {
+	/// Serializes every component of an entity which is binary serializable,
+	/// such as to save the entity to disk.
+	pub fn from_entity(registry: &Registry, entity_ref: &hecs::EntityRef<'_>) -> Result<Self> {
+		profiling::scope!(
+			"serialize-entity",
+			&format!("entity={}", entity_ref.entity().id())
+		);
+		let mut components = Vec::new();
+		for type_id in entity_ref.component_types() {
+			let binary_registration = registry
+				.find(&type_id)
+				.map(|registered| registered.get_ext::<Registration>())
+				.flatten();
+			if let Some(binary_registration) = binary_registration {
+				if let Some(serialized) = binary_registration.serialize(entity_ref)? {
+					components.push(serialized);
+				}
+			}
+		}
+		Ok(Self {
+			entity: entity_ref.entity(),
+			components,
+		})
+	}
+
 	pub fn into_builder(self, registry: &Registry) -> Result<(hecs::Entity, hecs::EntityBuilder)> {
+		let mut builder = hecs::EntityBuilder::default();
+		let entity = self.add_to(registry, &mut builder)?;
+		Ok((entity, builder))
+	}
+
+	/// Deserializes every component into a builder, replacing any components of the same type it already has.
+	pub fn add_to(
+		self,
+		registry: &Registry,
+		builder: &mut hecs::EntityBuilder,
+	) -> Result<hecs::Entity> {
 		profiling::scope!(
 			"deserialize-entity",
 			&format!("entity={}", self.entity.id())
 		);
-		let mut builder = hecs::EntityBuilder::default();
 		for comp_data in self.components.into_iter() {
 			profiling::scope!(
 				"deserialize-component",
@@ -29,9 +64,9 @@ impl SerializedEntity {
 			);
 			let registered = registry.find_ok(&comp_data.id)?;
 			let binary_registration = registered.get_ext_ok::<Registration>()?;
-			binary_registration.deserialize(comp_data.data, &mut builder)?;
+			binary_registration.deserialize(comp_data.data, builder)?;
 		}
-		Ok((self.entity, builder))
+		Ok(self.entity)
 	}
 }
 
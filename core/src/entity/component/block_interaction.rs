@@ -0,0 +1,113 @@
+use crate::{
+	block,
+	entity::component::{binary, debug, network, Component, Registration},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long the break action must be held on a block to break it.
+pub static BREAK_DURATION: Duration = Duration::from_secs(1);
+
+/// A block which a player is in the middle of breaking.
+///
+/// Server authoritative; added by the server while the owning client holds the break action on a block,
+/// and advanced each tick by the [`BreakBlocks`](crate::entity::system::BreakBlocks) system.
+/// Looking at a different block restarts the progress, and releasing the break action removes the component.
+/// Replicated so that nearby clients can show the block cracking.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlockInteraction {
+	target: block::Point,
+	progress: f32,
+}
+
+impl Component for BlockInteraction {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::BlockInteraction"
+	}
+
+	fn display_name() -> &'static str {
+		"Block Interaction"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>())
+	}
+}
+
+impl BlockInteraction {
+	pub fn new(target: block::Point) -> Self {
+		Self {
+			target,
+			progress: 0.0,
+		}
+	}
+
+	pub fn target(&self) -> &block::Point {
+		&self.target
+	}
+
+	/// How far along breaking the block is, from 0.0 (just started) to 1.0 (broken).
+	pub fn progress(&self) -> f32 {
+		self.progress
+	}
+
+	pub fn is_complete(&self) -> bool {
+		self.progress >= 1.0
+	}
+
+	/// Advances the progress by the amount of time the break action has been held.
+	/// Returns true if this advance completed the break,
+	/// which only happens once no matter how many more times it is advanced.
+	pub fn advance(&mut self, delta_time: Duration) -> bool {
+		if self.is_complete() {
+			return false;
+		}
+		let step = delta_time.as_secs_f32() / BREAK_DURATION.as_secs_f32();
+		self.progress = (self.progress + step).min(1.0);
+		self.is_complete()
+	}
+}
+
+impl std::fmt::Display for BlockInteraction {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"BlockInteraction(target:{}, progress:{:.2})",
+			self.target, self.progress
+		)
+	}
+}
+
+impl network::Replicatable for BlockInteraction {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for BlockInteraction {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for BlockInteraction {
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!("Target: {}", self.target),
+			format!("Progress: {:.0}%", self.progress * 100.0),
+		]
+	}
+}
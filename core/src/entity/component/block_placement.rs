@@ -0,0 +1,61 @@
+use crate::{
+	common::world::raycast::RaycastHit,
+	entity::component::{debug, Component, Registration},
+};
+
+/// A request from a player to place a block against the face of the block they are looking at.
+///
+/// Server only; added by the server when the owning client presses the place action,
+/// and removed by the [`PlaceBlocks`](crate::entity::system::PlaceBlocks) system the next time it updates,
+/// whether or not the block could be placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockPlacement {
+	hit: RaycastHit,
+}
+
+impl Component for BlockPlacement {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::BlockPlacement"
+	}
+
+	fn display_name() -> &'static str {
+		"Block Placement"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl BlockPlacement {
+	pub fn new(hit: RaycastHit) -> Self {
+		Self { hit }
+	}
+
+	pub fn hit(&self) -> &RaycastHit {
+		&self.hit
+	}
+}
+
+impl std::fmt::Display for BlockPlacement {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"BlockPlacement(point={}, face={:?})",
+			self.hit.point, self.hit.face
+		)
+	}
+}
+
+impl debug::EguiInformation for BlockPlacement {
+	fn describe(&self) -> Vec<String> {
+		vec![format!(
+			"Placing against {} ({:?})",
+			self.hit.point, self.hit.face
+		)]
+	}
+}
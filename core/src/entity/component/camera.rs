@@ -81,7 +81,8 @@ pub enum Perspective {
 impl CameraView {
 	/// Return the camera perspective's translation and rotation for a given player orientation.
 	pub fn get_isometry(&self, orientation: &UnitQuaternion<f32>) -> Isometry3<f32> {
-		let eye_offset = Vector3::<f32>::new(0.0, 1.6, 0.0);
+		use crate::common::world::raycast::EYE_HEIGHT;
+		let eye_offset = Vector3::<f32>::new(0.0, EYE_HEIGHT as f32, 0.0);
 		let third_person_offset = 5.0;
 		match self {
 			Self::FirstPerson => Isometry3::from_parts(eye_offset.into(), *orientation),
@@ -120,24 +121,25 @@ impl CameraView {
 }
 
 impl super::debug::EguiInformation for Camera {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!("View: {:?}", self.view));
+	fn describe(&self) -> Vec<String> {
+		let mut lines = vec![format!("View: {:?}", self.view)];
 		match &self.format {
 			Projection::Orthographic(ortho) => {
-				ui.label("Projection: Orthographic");
-				ui.label(format!("Left: {}", ortho.left()));
-				ui.label(format!("Right: {}", ortho.right()));
-				ui.label(format!("Top: {}", ortho.top()));
-				ui.label(format!("Bottom: {}", ortho.bottom()));
-				ui.label(format!("Z-Near: {}", ortho.z_near()));
-				ui.label(format!("Z-Far: {}", ortho.z_far()));
+				lines.push("Projection: Orthographic".to_owned());
+				lines.push(format!("Left: {}", ortho.left()));
+				lines.push(format!("Right: {}", ortho.right()));
+				lines.push(format!("Top: {}", ortho.top()));
+				lines.push(format!("Bottom: {}", ortho.bottom()));
+				lines.push(format!("Z-Near: {}", ortho.z_near()));
+				lines.push(format!("Z-Far: {}", ortho.z_far()));
 			}
 			Projection::Perspective(persp) => {
-				ui.label("Projection: Perspective");
-				ui.label(format!("Vertical FOV: {}", persp.vertical_fov));
-				ui.label(format!("Z-Near: {}", persp.near_plane));
-				ui.label(format!("Z-Far: {}", persp.far_plane));
+				lines.push("Projection: Perspective".to_owned());
+				lines.push(format!("Vertical FOV: {}", persp.vertical_fov));
+				lines.push(format!("Z-Near: {}", persp.near_plane));
+				lines.push(format!("Z-Far: {}", persp.far_plane));
 			}
 		}
+		lines
 	}
 }
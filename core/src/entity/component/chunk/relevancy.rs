@@ -1,10 +1,13 @@
 /// Component added on the server to indicate what chunks are relevant to a given entity.
 /// Chunks which exist inside the radius are replicated, if the entity also has the
-/// [`Owned By Connection`](crate::entity::component::OwnedByConnection) component.
+/// [`Owned By Connection`](crate::entity::component::OwnedByConnection)
+/// or [`Spectator`](crate::entity::component::Spectator) component.
 #[derive(Clone)]
 pub struct Relevancy {
 	/// The radius of chunks around the [`current chunk coordinate`](crate::entity::component::physics::linear::Position::chunk).
 	radius: u64,
+	/// How many chunks beyond the radius chunks stay relevant, once they are relevant.
+	margin: u64,
 	entity_radius: u64,
 }
 
@@ -12,6 +15,7 @@ impl Default for Relevancy {
 	fn default() -> Self {
 		Self {
 			radius: 0,
+			margin: 0,
 			entity_radius: 0,
 		}
 	}
@@ -43,6 +47,17 @@ impl Relevancy {
 		self.radius
 	}
 
+	/// Keeps chunks relevant until they are `margin` chunks beyond the radius,
+	/// so moving back and forth across a chunk boundary doesn't repeatedly replicate and discard the chunks at the edge.
+	pub fn with_margin(mut self, margin: u64) -> Self {
+		self.margin = margin;
+		self
+	}
+
+	pub fn margin(&self) -> u64 {
+		self.margin
+	}
+
 	pub fn with_entity_radius(mut self, radius: u64) -> Self {
 		self.entity_radius = radius;
 		self
@@ -1,6 +1,9 @@
-use crate::server::world::chunk;
+use crate::server::{
+	network::DEFAULT_WORLD,
+	world::{chunk, Database},
+};
 use engine::math::nalgebra::Point3;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock, Weak};
 
 #[derive(Clone)]
 pub(crate) struct ActiveTicket {
@@ -18,6 +21,12 @@ pub struct TicketOwner {
 
 	/// The ticket on the server that keeps chunks around the entity loaded.
 	current_ticket: Option<ActiveTicket>,
+
+	/// The name of the world the entity is in, if not the [`default world`](DEFAULT_WORLD).
+	world_name: Option<String>,
+	/// The world database that tickets are submitted to.
+	/// If None, tickets are submitted to the default world (via [`Ticket::submit`](chunk::Ticket::submit)).
+	world: Option<Weak<RwLock<Database>>>,
 }
 
 impl super::super::Component for TicketOwner {
@@ -52,6 +61,25 @@ impl TicketOwner {
 		self
 	}
 
+	/// Changes the world that chunks are loaded in for the entity.
+	/// The current ticket is dropped, so the chunks are loaded in the new world on the next update.
+	pub(crate) fn set_world(&mut self, name: &str, world: Option<Weak<RwLock<Database>>>) {
+		self.world_name = match name == DEFAULT_WORLD {
+			true => None,
+			false => Some(name.to_owned()),
+		};
+		self.world = world;
+		self.current_ticket = None;
+	}
+
+	/// The name of the world the entity is in, and which its chunks are loaded in.
+	pub fn world_name(&self) -> &str {
+		match &self.world_name {
+			Some(name) => name.as_str(),
+			None => DEFAULT_WORLD,
+		}
+	}
+
 	pub(crate) fn ticket_coordinate(&self) -> Option<Point3<i64>> {
 		self.current_ticket.as_ref().map(|active| active.coordinate)
 	}
@@ -64,7 +92,15 @@ impl TicketOwner {
 			coordinate,
 			level: (chunk::Level::Ticking, self.server_load_radius).into(),
 		};
-		if let Ok(handle) = ticket.submit() {
+		let handle = match &self.world {
+			Some(weak_world) => match weak_world.upgrade() {
+				Some(arc_world) => arc_world.read().unwrap().submit_ticket(ticket),
+				// The world has been unloaded, so there is nowhere to load chunks.
+				None => return,
+			},
+			None => ticket.submit(),
+		};
+		if let Ok(handle) = handle {
 			self.current_ticket = Some(ActiveTicket { coordinate, handle })
 		}
 	}
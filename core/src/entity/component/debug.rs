@@ -1,11 +1,20 @@
 /// Trait implemented by components which allows them to
 /// display information in the [`Entity Inspector`](crate::debug::EntityInspector).
 pub trait EguiInformation {
-	fn render(&self, ui: &mut egui::Ui);
+	/// The lines of text which describe the component.
+	/// These are captured in [`snapshots`](crate::debug::WorldSnapshot) of the entity world,
+	/// so the inspector can show them without reading the live world.
+	fn describe(&self) -> Vec<String>;
+
+	fn render(&self, ui: &mut egui::Ui) {
+		for line in self.describe().into_iter() {
+			ui.label(line);
+		}
+	}
 }
 
 pub struct Registration {
-	render_inspector: Box<dyn Fn(&hecs::EntityRef<'_>, &mut egui::Ui)>,
+	describe: Box<dyn Fn(&hecs::EntityRef<'_>) -> Option<Vec<String>>>,
 }
 impl super::ExtensionRegistration for Registration {
 	fn extension_id() -> &'static str
@@ -21,15 +30,14 @@ impl Registration {
 		T: super::Component + EguiInformation,
 	{
 		Self {
-			render_inspector: Box::new(|e: &hecs::EntityRef<'_>, ui: &mut egui::Ui| {
-				if let Some(component) = e.get::<&T>() {
-					(*component).render(ui);
-				}
+			describe: Box::new(|e: &hecs::EntityRef<'_>| {
+				e.get::<&T>().map(|component| (*component).describe())
 			}),
 		}
 	}
 
-	pub(crate) fn render(&self, entity_ref: &hecs::EntityRef<'_>, ui: &mut egui::Ui) {
-		(self.render_inspector)(entity_ref, ui)
+	/// Returns the description of the component on the entity, if the entity has the component.
+	pub(crate) fn describe(&self, entity_ref: &hecs::EntityRef<'_>) -> Option<Vec<String>> {
+		(self.describe)(entity_ref)
 	}
 }
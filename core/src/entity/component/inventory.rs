@@ -0,0 +1,218 @@
+use crate::block;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of items which can be held in a single inventory slot.
+pub static MAX_STACK_SIZE: u16 = 64;
+
+/// Something which can be held in an inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Item {
+	Block(block::LookupId),
+}
+
+/// Some number of the same item, held in a single inventory slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stack {
+	pub item: Item,
+	pub count: u16,
+}
+
+/// A fixed number of slots which can each hold a [`stack`](Stack) of items.
+/// The backing storage for anything an entity is carrying (e.g. a player's hotbar).
+///
+/// Only replicated to the connection which owns the entity.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+	slots: Vec<Option<Stack>>,
+}
+
+impl super::Component for Inventory {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::Inventory"
+	}
+
+	fn display_name() -> &'static str {
+		"Inventory"
+	}
+
+	fn registration() -> super::Registration<Self>
+	where
+		Self: Sized,
+	{
+		use super::binary::Registration as binary;
+		use super::debug::Registration as debug;
+		use super::network::Registration as network;
+		super::Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>().owner_private())
+	}
+}
+
+impl Inventory {
+	pub fn new(slot_count: usize) -> Self {
+		Self {
+			slots: vec![None; slot_count],
+		}
+	}
+
+	/// The number of slots in the inventory, regardless of whether or not they are empty.
+	pub fn len(&self) -> usize {
+		self.slots.len()
+	}
+
+	pub fn get(&self, slot: usize) -> Option<&Stack> {
+		self.slots.get(slot).map(Option::as_ref).flatten()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Option<Stack>> + '_ {
+		self.slots.iter()
+	}
+
+	/// Replaces the contents of a slot, returning what was previously in it.
+	/// Empty stacks are stored as an empty slot.
+	pub fn set(&mut self, slot: usize, stack: Option<Stack>) -> Option<Stack> {
+		let stack = stack.filter(|stack| stack.count > 0);
+		match self.slots.get_mut(slot) {
+			Some(existing) => std::mem::replace(existing, stack),
+			None => None,
+		}
+	}
+
+	/// Adds some number of an item to the inventory,
+	/// filling stacks of the same item before using empty slots.
+	/// Returns the number of items which did not fit.
+	pub fn insert(&mut self, item: Item, mut count: u16) -> u16 {
+		for stack in self.slots.iter_mut().filter_map(|slot| slot.as_mut()) {
+			if count == 0 {
+				break;
+			}
+			if stack.item == item && stack.count < MAX_STACK_SIZE {
+				let moved = count.min(MAX_STACK_SIZE - stack.count);
+				stack.count += moved;
+				count -= moved;
+			}
+		}
+		for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+			if count == 0 {
+				break;
+			}
+			let moved = count.min(MAX_STACK_SIZE);
+			*slot = Some(Stack { item, count: moved });
+			count -= moved;
+		}
+		count
+	}
+
+	/// Removes up to `count` items from a slot, returning the items which were removed.
+	pub fn remove(&mut self, slot: usize, count: u16) -> Option<Stack> {
+		let existing = self.slots.get_mut(slot)?;
+		let stack = existing.as_mut()?;
+		let removed = Stack {
+			item: stack.item,
+			count: count.min(stack.count),
+		};
+		stack.count -= removed.count;
+		if stack.count == 0 {
+			*existing = None;
+		}
+		Some(removed).filter(|removed| removed.count > 0)
+	}
+
+	/// Returns the first slot holding a block, and the block in it,
+	/// which is the block a player places.
+	pub fn first_block(&self) -> Option<(usize, block::LookupId)> {
+		self.slots
+			.iter()
+			.enumerate()
+			.find_map(|(slot, stack)| match stack {
+				Some(Stack {
+					item: Item::Block(id),
+					..
+				}) => Some((slot, *id)),
+				None => None,
+			})
+	}
+}
+
+impl super::network::Replicatable for Inventory {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = replicated.clone();
+	}
+}
+
+impl super::binary::Serializable for Inventory {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		super::binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		super::binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl super::debug::EguiInformation for Inventory {
+	fn describe(&self) -> Vec<String> {
+		self.slots
+			.iter()
+			.enumerate()
+			.map(|(slot, stack)| match stack {
+				Some(Stack {
+					item: Item::Block(id),
+					count,
+				}) => format!("{}: Block({}) x{}", slot, id, count),
+				None => format!("{}: Empty", slot),
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod stacks {
+	use super::*;
+	use crate::entity::component::binary::Serializable;
+
+	#[test]
+	fn serialization_round_trip() {
+		let mut inventory = Inventory::new(9);
+		assert_eq!(inventory.insert(Item::Block(3), 70), 0);
+		inventory.set(
+			8,
+			Some(Stack {
+				item: Item::Block(1),
+				count: 2,
+			}),
+		);
+
+		let bytes = inventory.serialize().unwrap();
+		let deserialized = Inventory::deserialize(bytes).unwrap();
+		assert_eq!(deserialized, inventory);
+		assert_eq!(deserialized.len(), 9);
+		assert_eq!(
+			deserialized.get(1),
+			Some(&Stack {
+				item: Item::Block(3),
+				count: 6,
+			})
+		);
+		assert_eq!(deserialized.get(2), None);
+	}
+
+	#[test]
+	fn insert_fills_existing_stacks_first() {
+		let mut inventory = Inventory::new(2);
+		inventory.set(
+			1,
+			Some(Stack {
+				item: Item::Block(0),
+				count: 60,
+			}),
+		);
+		assert_eq!(inventory.insert(Item::Block(0), 10), 0);
+		assert_eq!(inventory.get(1).unwrap().count, MAX_STACK_SIZE);
+		assert_eq!(inventory.get(0).unwrap().count, 6);
+		assert_eq!(inventory.insert(Item::Block(1), 1), 1);
+		assert_eq!(inventory.remove(0, 10).unwrap().count, 6);
+		assert_eq!(inventory.get(0), None);
+	}
+}
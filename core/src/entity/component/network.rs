@@ -1,3 +1,5 @@
+mod connection_quality;
+pub use connection_quality::*;
 mod replicated;
 pub use replicated::*;
 mod replicatable;
@@ -0,0 +1,47 @@
+use crate::{common::network::heartbeat::Quality, entity::component::Component};
+
+/// Attached on the client to the entity it views the world from (the one with a [`Camera`](crate::entity::component::Camera)),
+/// with the quality of the client's connection to the server, so it can be shown to the player.
+/// Updated by the [`UpdateConnectionQuality`](crate::entity::system::UpdateConnectionQuality) system.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionQuality(Quality);
+
+impl ConnectionQuality {
+	pub fn new(quality: Quality) -> Self {
+		Self(quality)
+	}
+
+	pub fn quality(&self) -> &Quality {
+		&self.0
+	}
+}
+
+impl Component for ConnectionQuality {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::network::ConnectionQuality"
+	}
+
+	fn display_name() -> &'static str {
+		"Connection Quality"
+	}
+
+	fn registration() -> crate::entity::component::Registration<Self>
+	where
+		Self: Sized,
+	{
+		use crate::entity::component::debug::Registration as debug;
+		crate::entity::component::Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for ConnectionQuality {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "ConnectionQuality({})", self.0)
+	}
+}
+
+impl crate::entity::component::debug::EguiInformation for ConnectionQuality {
+	fn describe(&self) -> Vec<String> {
+		vec![format!("Connection: {}", self.0)]
+	}
+}
@@ -7,6 +7,7 @@ pub trait Replicatable: binary::Serializable {
 }
 
 pub struct Registration {
+	is_owner_private: bool,
 	fn_clone_into: Box<dyn Fn(&hecs::EntityBuilder, &mut hecs::EntityBuilder)>,
 	fn_on_rep: Box<dyn Fn(&hecs::EntityBuilder, &hecs::EntityRef, bool)>,
 }
@@ -26,6 +27,7 @@ impl Registration {
 		T: Component + Replicatable + Clone,
 	{
 		Self {
+			is_owner_private: false,
 			fn_clone_into: Box::new(|src: &hecs::EntityBuilder, dst: &mut hecs::EntityBuilder| {
 				dst.add(src.get::<&T>().unwrap().clone());
 			}),
@@ -39,6 +41,18 @@ impl Registration {
 		}
 	}
 
+	/// Marks the component as only being replicated to the connection which
+	/// [`owns`](crate::entity::component::OwnedByConnection) the entity,
+	/// instead of every connection the entity is relevant to.
+	pub fn owner_private(mut self) -> Self {
+		self.is_owner_private = true;
+		self
+	}
+
+	pub fn is_owner_private(&self) -> bool {
+		self.is_owner_private
+	}
+
 	pub fn clone_into_builder(&self, src: &hecs::EntityBuilder, dst: &mut hecs::EntityBuilder) {
 		(self.fn_clone_into)(src, dst)
 	}
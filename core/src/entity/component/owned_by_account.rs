@@ -65,7 +65,7 @@ impl super::binary::Serializable for OwnedByAccount {
 }
 
 impl super::debug::EguiInformation for OwnedByAccount {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!("Account ID: {}", self.account_id));
+	fn describe(&self) -> Vec<String> {
+		vec![format!("Account ID: {}", self.account_id)]
 	}
 }
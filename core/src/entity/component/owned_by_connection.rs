@@ -63,7 +63,7 @@ impl super::binary::Serializable for OwnedByConnection {
 }
 
 impl super::debug::EguiInformation for OwnedByConnection {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!("IP Address: {}", self.address));
+	fn describe(&self) -> Vec<String> {
+		vec![format!("IP Address: {}", self.address)]
 	}
 }
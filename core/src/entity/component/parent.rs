@@ -0,0 +1,303 @@
+use crate::entity::{
+	component::{debug, physics::linear::Position, Component, Orientation, Registration},
+	World,
+};
+use anyhow::Result;
+use engine::math::nalgebra::{Isometry3, Point3, UnitQuaternion};
+
+/// Attaches an entity to another (e.g. a rider to a mount, or an item to a hand),
+/// so the child's transform is derived from its parent's every tick instead of being simulated on its own.
+///
+/// The [`physics system`](crate::entity::system::Physics) does not step children,
+/// and the replicator makes a child relevant wherever its parent (or the root of its chain of parents) is.
+/// The child's derived [`Position`] and [`Orientation`] replicate like any other,
+/// so the link itself is only known to the server.
+/// If the parent is despawned, its children are detached and stay where they were last placed.
+#[derive(Clone, Copy)]
+pub struct Parent {
+	entity: hecs::Entity,
+	offset: Isometry3<f32>,
+	/// The chunk the child was last made relevant at, which is the chunk of the root of its chain of parents.
+	relevant_chunk: Option<Point3<i64>>,
+}
+
+impl Component for Parent {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::Parent"
+	}
+
+	fn display_name() -> &'static str {
+		"Parent"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Parent {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Parent({:?})", self.entity)
+	}
+}
+
+impl Parent {
+	/// Attaches `child` to `parent`, placing the child at `offset` relative to the parent's position and orientation.
+	/// Attaching an entity which already has a parent moves it to the new parent.
+	/// Returns an error if the parent is not in the world, or if the attachment would create a cycle.
+	pub fn attach(
+		world: &mut World,
+		child: hecs::Entity,
+		parent: hecs::Entity,
+		offset: Isometry3<f32>,
+	) -> Result<()> {
+		if !world.contains(child) {
+			return Err(Error::MissingEntity(child))?;
+		}
+		if !world.contains(parent) {
+			return Err(Error::MissingEntity(parent))?;
+		}
+		let mut next = Some(parent);
+		while let Some(ancestor) = next {
+			if ancestor == child {
+				return Err(Error::Cycle(child, parent))?;
+			}
+			next = Self::parent_of(world, ancestor);
+		}
+		let relevant_chunk = world
+			.get::<&Position>(child)
+			.ok()
+			.and_then(|position| *position.prev_chunk());
+		let component = Self {
+			entity: parent,
+			offset,
+			relevant_chunk,
+		};
+		let _ = world.insert_one(child, component);
+		Ok(())
+	}
+
+	/// Detaches `child` from its parent, leaving it where it currently is.
+	pub fn detach(world: &mut World, child: hecs::Entity) {
+		let _ = world.remove_one::<Self>(child);
+	}
+
+	pub fn entity(&self) -> &hecs::Entity {
+		&self.entity
+	}
+
+	/// The position and rotation of the child relative to its parent.
+	pub fn offset(&self) -> &Isometry3<f32> {
+		&self.offset
+	}
+
+	pub fn relevant_chunk(&self) -> &Option<Point3<i64>> {
+		&self.relevant_chunk
+	}
+
+	pub fn acknowledge_relevant_chunk(&mut self, chunk: Point3<i64>) {
+		self.relevant_chunk = Some(chunk);
+	}
+
+	/// Like [`Position::unacknowledge_chunk`], for the chunk the child is relevant in.
+	pub fn unacknowledge_relevant_chunk(&mut self) {
+		self.relevant_chunk = None;
+	}
+
+	fn parent_of(world: &World, entity: hecs::Entity) -> Option<hecs::Entity> {
+		world.get::<&Self>(entity).ok().map(|parent| parent.entity)
+	}
+
+	/// Returns the entity at the top of the chain of parents `entity` is attached to,
+	/// or None if `entity` has no parent.
+	pub fn root_of(world: &World, entity: hecs::Entity) -> Option<hecs::Entity> {
+		let mut root = Self::parent_of(world, entity)?;
+		while let Some(next) = Self::parent_of(world, root) {
+			root = next;
+		}
+		Some(root)
+	}
+
+	/// Detaches children whose parents have been despawned, then moves every child to its place relative to its parent.
+	/// Children are updated in order of depth so each one is placed relative to where its parent is this tick.
+	pub fn update_children(world: &mut World) {
+		profiling::scope!("update_children");
+
+		// Entities are sorted by id so which entities are detached (and when) does not depend on the iteration order of the world.
+		let mut orphans = world
+			.query::<&Self>()
+			.iter()
+			.filter(|(_, parent)| !world.contains(parent.entity))
+			.map(|(entity, _)| entity)
+			.collect::<Vec<_>>();
+		orphans.sort_by_key(|entity| entity.id());
+		for orphan in orphans.into_iter() {
+			Self::detach(world, orphan);
+		}
+
+		let mut children = world
+			.query::<&Self>()
+			.iter()
+			.map(|(entity, parent)| (entity, parent.entity, parent.offset))
+			.collect::<Vec<_>>();
+		children.sort_by_cached_key(|(entity, _, _)| (Self::depth_of(world, *entity), entity.id()));
+
+		for (child, parent, offset) in children.into_iter() {
+			let parent_position = match world.get::<&Position>(parent) {
+				Ok(position) => position.world_position(),
+				Err(_) => continue,
+			};
+			let parent_rotation = match world.get::<&Orientation>(parent) {
+				Ok(orientation) => *orientation.orientation(),
+				Err(_) => UnitQuaternion::identity(),
+			};
+
+			let translation = parent_rotation * offset.translation.vector;
+			let target = parent_position + translation.cast::<f64>();
+			if let Ok(mut position) = world.get::<&mut Position>(child) {
+				if position.world_position() != target {
+					position.set_world_position(target);
+				}
+			}
+			if let Ok(mut orientation) = world.get::<&mut Orientation>(child) {
+				**orientation = parent_rotation * offset.rotation;
+			}
+		}
+	}
+
+	fn depth_of(world: &World, entity: hecs::Entity) -> usize {
+		let mut depth = 0;
+		let mut next = Self::parent_of(world, entity);
+		while let Some(ancestor) = next {
+			depth += 1;
+			next = Self::parent_of(world, ancestor);
+		}
+		depth
+	}
+}
+
+impl debug::EguiInformation for Parent {
+	fn describe(&self) -> Vec<String> {
+		let translation = &self.offset.translation.vector;
+		vec![
+			format!("Entity: {:?}", self.entity),
+			format!(
+				"Offset: <{:.2}, {:.2}, {:.2}>",
+				translation[0], translation[1], translation[2]
+			),
+		]
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("entity {0:?} is not in the world")]
+	MissingEntity(hecs::Entity),
+	#[error("attaching {0:?} to {1:?} would make the entity its own ancestor")]
+	Cycle(hecs::Entity, hecs::Entity),
+}
+
+#[cfg(test)]
+mod attachment {
+	use super::*;
+	use engine::math::nalgebra::{Translation3, Vector3};
+
+	fn spawn_at(world: &mut World, point: Point3<f64>) -> hecs::Entity {
+		let mut position = Position::default();
+		position.set_world_position(point);
+		world.spawn((position, Orientation::default()))
+	}
+
+	fn position_of(world: &World, entity: hecs::Entity) -> Point3<f64> {
+		world.get::<&Position>(entity).unwrap().world_position()
+	}
+
+	fn offset(x: f32, y: f32, z: f32) -> Isometry3<f32> {
+		Isometry3::from_parts(Translation3::new(x, y, z), UnitQuaternion::identity())
+	}
+
+	#[test]
+	fn child_follows_parent() {
+		let mut world = World::new();
+		let parent = spawn_at(&mut world, Point3::new(0.0, 0.0, 0.0));
+		let child = spawn_at(&mut world, Point3::new(100.0, 0.0, 0.0));
+		**world.get::<&mut Orientation>(parent).unwrap() = UnitQuaternion::identity();
+		Parent::attach(&mut world, child, parent, offset(0.0, 2.0, 0.0)).unwrap();
+
+		Parent::update_children(&mut world);
+		assert_eq!(position_of(&world, child), Point3::new(0.0, 2.0, 0.0));
+
+		world
+			.get::<&mut Position>(parent)
+			.unwrap()
+			.set_world_position(Point3::new(40.0, -8.0, 3.0));
+		Parent::update_children(&mut world);
+		assert_eq!(position_of(&world, child), Point3::new(40.0, -6.0, 3.0));
+	}
+
+	#[test]
+	fn offset_rotates_with_parent() {
+		let mut world = World::new();
+		let parent = spawn_at(&mut world, Point3::new(10.0, 0.0, 0.0));
+		let child = spawn_at(&mut world, Point3::origin());
+		**world.get::<&mut Orientation>(parent).unwrap() =
+			UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 90.0f32.to_radians());
+		Parent::attach(&mut world, child, parent, offset(1.0, 0.0, 0.0)).unwrap();
+
+		Parent::update_children(&mut world);
+		let position = position_of(&world, child);
+		assert!((position - Point3::new(10.0, 0.0, -1.0)).norm() < 1e-5);
+	}
+
+	#[test]
+	fn grandchild_follows_root() {
+		let mut world = World::new();
+		let root = spawn_at(&mut world, Point3::origin());
+		**world.get::<&mut Orientation>(root).unwrap() = UnitQuaternion::identity();
+		let middle = spawn_at(&mut world, Point3::origin());
+		let leaf = spawn_at(&mut world, Point3::origin());
+		// Attached leaf-first, so the world's order does not match the order of the chain.
+		Parent::attach(&mut world, leaf, middle, offset(0.0, 1.0, 0.0)).unwrap();
+		Parent::attach(&mut world, middle, root, offset(0.0, 1.0, 0.0)).unwrap();
+		assert_eq!(Parent::root_of(&world, leaf), Some(root));
+
+		world
+			.get::<&mut Position>(root)
+			.unwrap()
+			.set_world_position(Point3::new(5.0, 5.0, 5.0));
+		Parent::update_children(&mut world);
+		assert_eq!(position_of(&world, leaf), Point3::new(5.0, 7.0, 5.0));
+	}
+
+	#[test]
+	fn reject_cycles() {
+		let mut world = World::new();
+		let a = spawn_at(&mut world, Point3::origin());
+		let b = spawn_at(&mut world, Point3::origin());
+		let c = spawn_at(&mut world, Point3::origin());
+		assert!(Parent::attach(&mut world, a, a, offset(0.0, 0.0, 0.0)).is_err());
+		Parent::attach(&mut world, b, a, offset(0.0, 0.0, 0.0)).unwrap();
+		Parent::attach(&mut world, c, b, offset(0.0, 0.0, 0.0)).unwrap();
+		assert!(Parent::attach(&mut world, a, c, offset(0.0, 0.0, 0.0)).is_err());
+		assert!(world.get::<&Parent>(a).is_err());
+	}
+
+	#[test]
+	fn despawned_parent_detaches_children() {
+		let mut world = World::new();
+		let parent = spawn_at(&mut world, Point3::origin());
+		let child = spawn_at(&mut world, Point3::origin());
+		Parent::attach(&mut world, child, parent, offset(0.0, 3.0, 0.0)).unwrap();
+		Parent::update_children(&mut world);
+		let last_position = position_of(&world, child);
+
+		world.despawn(parent).unwrap();
+		Parent::update_children(&mut world);
+		assert!(world.get::<&Parent>(child).is_err());
+		assert_eq!(position_of(&world, child), last_position);
+	}
+}
@@ -0,0 +1,91 @@
+use crate::{
+	common::account,
+	entity::component::{binary, debug, network, Component, Registration},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// An id for an entity which is stable across server restarts,
+/// unlike [`hecs::Entity`] which is assigned by the world each time the entity is spawned.
+///
+/// Only given to entities which should persist, and saved with the entity
+/// so it is reused when the entity is loaded (see [`PersistentIds`](crate::entity::PersistentIds)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PersistentId(u128);
+
+impl Component for PersistentId {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::PersistentId"
+	}
+
+	fn display_name() -> &'static str {
+		"Persistent Id"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>())
+	}
+}
+
+impl PersistentId {
+	/// Creates a new random id, for an entity which has never been saved.
+	pub fn new() -> Self {
+		Self(uuid::Uuid::new_v4().as_u128())
+	}
+
+	/// The id of the entity for a player's account,
+	/// which is always the same so the player's entity has the same id every time they join.
+	pub fn for_account(id: &account::Id) -> Self {
+		use sha2::{Digest, Sha256};
+		let hash = Sha256::digest(id.as_bytes());
+		let mut bytes = [0u8; 16];
+		bytes.copy_from_slice(&hash[..16]);
+		Self(u128::from_le_bytes(bytes))
+	}
+
+	pub fn value(&self) -> u128 {
+		self.0
+	}
+}
+
+impl From<u128> for PersistentId {
+	fn from(value: u128) -> Self {
+		Self(value)
+	}
+}
+
+impl std::fmt::Display for PersistentId {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:032x}", self.0)
+	}
+}
+
+impl network::Replicatable for PersistentId {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for PersistentId {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for PersistentId {
+	fn describe(&self) -> Vec<String> {
+		vec![self.to_string()]
+	}
+}
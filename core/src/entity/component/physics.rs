@@ -1 +1,8 @@
+mod collider;
+pub use collider::*;
+mod dynamic;
+pub use dynamic::*;
+mod frozen;
+pub use frozen::*;
+pub mod groups;
 pub mod linear;
@@ -0,0 +1,77 @@
+use super::groups::Groups;
+use crate::entity::component::{debug, Component, Registration};
+use engine::math::nalgebra::Vector3;
+
+/// The box an entity occupies, which the [`physics system`](crate::entity::system::Physics)
+/// stops against the colliders of voxels.
+///
+/// The box is centered horizontally on the entity's [`position`](super::linear::Position),
+/// which is at the bottom of the box (the feet of a player).
+/// The box is only stopped by the colliders its [`groups`](Groups) interact with.
+/// Colliders are only simulated by servers, so they are not replicated or saved;
+/// the [`archetype`](crate::entity::archetype) of an entity adds it again when the entity is spawned or loaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collider {
+	size: Vector3<f32>,
+	groups: Groups,
+}
+
+impl Collider {
+	pub fn new(size: Vector3<f32>, groups: Groups) -> Self {
+		Self { size, groups }
+	}
+
+	/// The size of a player, who fits through a gap of 1x2 blocks, in the [`player`](Groups::player) groups.
+	pub fn player() -> Self {
+		Self::new(Vector3::new(0.6, 1.8, 0.6), Groups::player())
+	}
+
+	pub fn size(&self) -> &Vector3<f32> {
+		&self.size
+	}
+
+	pub fn groups(&self) -> &Groups {
+		&self.groups
+	}
+}
+
+impl Component for Collider {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::Collider"
+	}
+
+	fn display_name() -> &'static str {
+		"Collider"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Collider {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"Collider(<{:.2}, {:.2}, {:.2}>)",
+			self.size.x, self.size.y, self.size.z
+		)
+	}
+}
+
+impl debug::EguiInformation for Collider {
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!(
+				"Size: <{:.2}, {:.2}, {:.2}>",
+				self.size.x, self.size.y, self.size.z
+			),
+			format!("Memberships: {:?}", self.groups.memberships()),
+			format!("Filter: {:?}", self.groups.filter()),
+		]
+	}
+}
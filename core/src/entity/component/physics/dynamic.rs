@@ -0,0 +1,76 @@
+use crate::entity::component::{binary, debug, network, Component, Registration};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Marks an entity as a dynamic body, whose [`velocity`](super::linear::Velocity)
+/// is accelerated by the world's [`gravity`](crate::common::physics::Settings::gravity).
+///
+/// A body falls asleep once it is not moving and there is no gravity to move it,
+/// and is not stepped again until it moves or the gravity changes.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Dynamic {
+	is_asleep: bool,
+}
+
+impl Dynamic {
+	pub fn is_asleep(&self) -> bool {
+		self.is_asleep
+	}
+
+	pub fn set_asleep(&mut self, is_asleep: bool) {
+		self.is_asleep = is_asleep;
+	}
+}
+
+impl Component for Dynamic {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::Dynamic"
+	}
+
+	fn display_name() -> &'static str {
+		"Dynamic"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Dynamic {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Dynamic(asleep={})", self.is_asleep)
+	}
+}
+
+impl network::Replicatable for Dynamic {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for Dynamic {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for Dynamic {
+	fn describe(&self) -> Vec<String> {
+		vec![match self.is_asleep {
+			true => "Asleep".to_owned(),
+			false => "Awake".to_owned(),
+		}]
+	}
+}
@@ -0,0 +1,62 @@
+use crate::entity::component::{binary, debug, network, Component, Registration};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Marks an entity as excluded from physics (e.g. paused entities, cutscene actors, or admin-frozen players).
+///
+/// The [`physics system`](crate::entity::system::Physics) does not step frozen entities,
+/// but they stay in the world and their (unchanging) position is still replicated.
+/// Nothing accumulates while an entity is frozen, so once the component is removed
+/// the entity resumes moving from where it was, as if it had only just been spawned there.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Frozen;
+
+impl Component for Frozen {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::Frozen"
+	}
+
+	fn display_name() -> &'static str {
+		"Frozen"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Frozen {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Frozen")
+	}
+}
+
+impl network::Replicatable for Frozen {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for Frozen {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for Frozen {
+	fn describe(&self) -> Vec<String> {
+		vec!["Excluded from physics".to_owned()]
+	}
+}
@@ -0,0 +1,108 @@
+//! Presets for which colliders can interact with each other,
+//! so each caller doesn't have to define its own groups and remember what each bit means.
+use enumset::{EnumSet, EnumSetType};
+
+/// A gameplay layer which a collider can be a member of.
+#[derive(Debug, EnumSetType, Hash)]
+pub enum Layer {
+	Players,
+	Terrain,
+	Projectiles,
+	Sensors,
+	Items,
+}
+
+/// The layers a collider is a member of, and the layers it can interact with.
+///
+/// Matches the semantics of rapier's `InteractionGroups`: two colliders only interact
+/// if each is a member of a layer in the filter of the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Groups {
+	memberships: EnumSet<Layer>,
+	filter: EnumSet<Layer>,
+}
+
+impl Groups {
+	pub fn new(memberships: EnumSet<Layer>, filter: EnumSet<Layer>) -> Self {
+		Self {
+			memberships,
+			filter,
+		}
+	}
+
+	/// Players collide with the world and can be hit by anything, but walk through each other.
+	pub fn player() -> Self {
+		Self::new(
+			Layer::Players.into(),
+			Layer::Terrain | Layer::Projectiles | Layer::Sensors | Layer::Items,
+		)
+	}
+
+	/// Terrain interacts with everything.
+	pub fn terrain() -> Self {
+		Self::new(Layer::Terrain.into(), EnumSet::all())
+	}
+
+	/// Projectiles hit players and terrain, but not other projectiles.
+	pub fn projectile() -> Self {
+		Self::new(Layer::Projectiles.into(), Layer::Players | Layer::Terrain)
+	}
+
+	/// Sensors detect players entering them, and are not collided with by anything else.
+	pub fn sensor() -> Self {
+		Self::new(Layer::Sensors.into(), Layer::Players | Layer::Terrain)
+	}
+
+	/// Items rest on terrain and can be picked up by players, but do not push each other around.
+	pub fn item() -> Self {
+		Self::new(Layer::Items.into(), Layer::Players | Layer::Terrain)
+	}
+
+	pub fn memberships(&self) -> &EnumSet<Layer> {
+		&self.memberships
+	}
+
+	pub fn filter(&self) -> &EnumSet<Layer> {
+		&self.filter
+	}
+
+	/// Returns true if a collider in these groups can interact with one in `other`.
+	pub fn interacts_with(&self, other: &Self) -> bool {
+		!(self.memberships & other.filter).is_empty()
+			&& !(other.memberships & self.filter).is_empty()
+	}
+
+	/// The memberships and filter as bitmasks, to construct a rapier `InteractionGroups`.
+	pub fn bits(&self) -> (u32, u32) {
+		(self.memberships.as_u32(), self.filter.as_u32())
+	}
+}
+
+#[cfg(test)]
+mod interactions {
+	use super::*;
+
+	#[test]
+	fn player_interacts_with_terrain() {
+		assert!(Groups::player().interacts_with(&Groups::terrain()));
+		assert!(Groups::terrain().interacts_with(&Groups::player()));
+	}
+
+	#[test]
+	fn items_do_not_interact() {
+		assert!(!Groups::item().interacts_with(&Groups::item()));
+		assert!(Groups::item().interacts_with(&Groups::terrain()));
+		assert!(Groups::item().interacts_with(&Groups::player()));
+	}
+
+	#[test]
+	fn filter_must_match_both_ways() {
+		// A sensor which only filters for players is not hit by projectiles,
+		// even though projectiles filter for players.
+		assert!(!Groups::projectile().interacts_with(&Groups::sensor()));
+		assert!(!Groups::player().interacts_with(&Groups::player()));
+		let (memberships, filter) = Groups::player().bits();
+		assert_eq!(memberships, 1 << 0);
+		assert_eq!(filter & (1 << 0), 0);
+	}
+}
@@ -1,4 +1,10 @@
+mod acknowledged_input;
+pub use acknowledged_input::*;
+mod interpolated_position;
+pub use interpolated_position::*;
 mod position;
 pub use position::*;
+mod prediction;
+pub use prediction::*;
 mod velocity;
 pub use velocity::*;
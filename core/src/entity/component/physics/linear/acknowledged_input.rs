@@ -0,0 +1,97 @@
+use crate::entity::component::{binary, debug, network, Component, Registration};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The sequence number of the last movement input the server applied to a player,
+/// which is replicated to only the player's client so it can [`predict`](super::Prediction) the inputs the server has not applied yet.
+///
+/// Also holds how long the server has moved the player with that input,
+/// so the client can extrapolate the input from when the server's position was taken.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct AcknowledgedInput {
+	sequence: u32,
+	applied_for: Duration,
+}
+
+impl AcknowledgedInput {
+	pub fn sequence(&self) -> u32 {
+		self.sequence
+	}
+
+	/// Records the latest input the server has applied.
+	/// The time it has been applied for restarts if it is a different input.
+	pub fn set_sequence(&mut self, sequence: u32) {
+		if sequence != self.sequence {
+			self.sequence = sequence;
+			self.applied_for = Duration::ZERO;
+		}
+	}
+
+	/// How long the server has moved the player with the acknowledged input.
+	pub fn applied_for(&self) -> Duration {
+		self.applied_for
+	}
+
+	/// Records that the server has moved the player with the acknowledged input for `delta_time` longer.
+	pub fn advance(&mut self, delta_time: Duration) {
+		self.applied_for += delta_time;
+	}
+}
+
+impl Component for AcknowledgedInput {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::linear::AcknowledgedInput"
+	}
+
+	fn display_name() -> &'static str {
+		"Acknowledged Input"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use binary::Registration as binary;
+		use debug::Registration as debug;
+		use network::Registration as network;
+		Registration::<Self>::default()
+			.with_ext(binary::from::<Self>())
+			.with_ext(debug::from::<Self>())
+			.with_ext(network::from::<Self>().owner_private())
+	}
+}
+
+impl std::fmt::Display for AcknowledgedInput {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"AcknowledgedInput({}, {:?})",
+			self.sequence, self.applied_for
+		)
+	}
+}
+
+impl network::Replicatable for AcknowledgedInput {
+	fn on_replication(&mut self, replicated: &Self, _is_locally_owned: bool) {
+		*self = *replicated;
+	}
+}
+
+impl binary::Serializable for AcknowledgedInput {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		binary::serialize(&self)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		binary::deserialize::<Self>(&bytes)
+	}
+}
+
+impl debug::EguiInformation for AcknowledgedInput {
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!("Sequence: {}", self.sequence),
+			format!("Applied for: {:.2}s", self.applied_for.as_secs_f32()),
+		]
+	}
+}
@@ -0,0 +1,420 @@
+use super::Position;
+use crate::entity::component::{debug, Component, Registration};
+use engine::math::nalgebra::Point3;
+use std::{
+	collections::VecDeque,
+	time::{Duration, Instant},
+};
+
+/// Replicated snapshots which are further apart than this (in blocks) are treated as a teleport,
+/// and are snapped to instead of interpolated.
+pub static TELEPORT_DISTANCE: f64 = 8.0;
+
+/// The shortest the entity can be rendered behind the latest snapshot, even on a perfectly smooth connection.
+pub static MIN_DELAY: Duration = Duration::from_millis(50);
+
+/// The longest the entity can be rendered behind the latest snapshot, no matter how jittery the connection is.
+pub static MAX_DELAY: Duration = Duration::from_millis(1000);
+
+/// How many multiples of the measured [`jitter`](Jitter) the delay covers beyond the average time between snapshots.
+pub static JITTER_MULTIPLIER: f64 = 2.0;
+
+/// How much faster than real time the entity moves while it is further behind than the delay needs it to be.
+/// Larger values shrink the buffer sooner after the connection smooths out, but make the entity visibly speed up.
+pub static CATCH_UP_RATE: f64 = 0.1;
+
+/// Snapshots stop arriving while an entity stands still, so a gap between snapshots longer than this
+/// is the entity having been idle rather than the connection being jittery, and is not measured as [`Jitter`].
+pub static IDLE_GAP: Duration = Duration::from_secs(2);
+
+/// The most snapshots which are buffered, in case the entity has stopped being interpolated.
+static MAX_SNAPSHOTS: usize = 64;
+
+/// Client-only component added to entities replicated from the server (other than those the client owns),
+/// which smooths the discrete [`Position`] updates received from the server into continuous movement.
+///
+/// [`Position`] remains the authoritative location of the entity, this is only the location the entity is rendered at.
+/// It is updated each frame by the [`InterpolatePositions`](crate::entity::system::InterpolatePositions) system.
+///
+/// Snapshots are buffered and the entity is rendered a short delay behind the latest one,
+/// so it keeps moving smoothly when a snapshot arrives late. The delay is sized by the measured [`Jitter`]
+/// of the snapshots, so entities on smooth connections are not rendered any further behind than they need to be.
+#[derive(Clone)]
+pub struct InterpolatedPosition {
+	/// The snapshots which have not been played yet, and the last one which has, oldest first.
+	snapshots: VecDeque<Snapshot>,
+	jitter: Jitter,
+	/// The point in the timeline of snapshots which the entity was last rendered at.
+	playback: Instant,
+	/// When the entity was last interpolated.
+	interpolated_at: Instant,
+	/// The position to render the entity at.
+	rendered: Position,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Snapshot {
+	position: Point3<f64>,
+	/// When the snapshot is played, which is when it arrived unless it arrived
+	/// too soon after the previous snapshot (e.g. in a burst after a latency spike).
+	played_at: Instant,
+}
+
+/// An estimate of how irregularly replicated snapshots arrive,
+/// used to size the delay that [`InterpolatedPosition`] renders entities behind the latest snapshot.
+///
+/// Like the interarrival jitter of RTP (RFC 3550), both the average time between snapshots and
+/// the average deviation from it are smoothed over many snapshots, so the estimate doesn't swing with every packet.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Jitter {
+	last_arrival: Option<Instant>,
+	/// The average time between snapshots, in seconds.
+	interval: f64,
+	/// The average difference between the time between snapshots and the average, in seconds.
+	deviation: f64,
+}
+
+impl Jitter {
+	/// Records the arrival of a snapshot.
+	/// If it arrived more than [`IDLE_GAP`] after the previous snapshot, measuring restarts from it
+	/// (keeping the estimate from before the gap) so the delay doesn't grow every time the entity stops moving.
+	pub fn record(&mut self, arrived_at: Instant) {
+		if let Some(last_arrival) = self.last_arrival {
+			let interval = arrived_at.saturating_duration_since(last_arrival);
+			let interval = interval.as_secs_f64();
+			if interval > IDLE_GAP.as_secs_f64() {
+				// The entity was idle, so there is no interval to measure.
+			} else if self.interval == 0.0 {
+				self.interval = interval;
+			} else {
+				self.deviation += ((interval - self.interval).abs() - self.deviation) / 16.0;
+				self.interval += (interval - self.interval) / 8.0;
+			}
+		}
+		self.last_arrival = Some(arrived_at);
+	}
+
+	/// The average time between snapshots.
+	pub fn interval(&self) -> Duration {
+		Duration::from_secs_f64(self.interval)
+	}
+
+	/// The average deviation of the time between snapshots from the [`interval`](Self::interval).
+	pub fn deviation(&self) -> Duration {
+		Duration::from_secs_f64(self.deviation)
+	}
+
+	/// How far behind the latest snapshot an entity should be rendered, so that the next snapshot
+	/// has almost always arrived by the time the entity reaches the latest one.
+	pub fn delay(&self) -> Duration {
+		let delay = self.interval + self.deviation * JITTER_MULTIPLIER;
+		Duration::from_secs_f64(delay).clamp(MIN_DELAY, MAX_DELAY)
+	}
+}
+
+impl Component for InterpolatedPosition {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::linear::InterpolatedPosition"
+	}
+
+	fn display_name() -> &'static str {
+		"Interpolated Position"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl InterpolatedPosition {
+	pub fn new(position: &Position, now: Instant) -> Self {
+		let snapshot = Snapshot {
+			position: position.world_position(),
+			played_at: now,
+		};
+		let mut jitter = Jitter::default();
+		jitter.record(now);
+		Self {
+			snapshots: VecDeque::from(vec![snapshot]),
+			jitter,
+			playback: now,
+			interpolated_at: now,
+			rendered: *position,
+		}
+	}
+
+	/// The position the entity should be rendered at, as of the last call to [`interpolate`](Self::interpolate).
+	pub fn rendered(&self) -> &Position {
+		&self.rendered
+	}
+
+	/// The most recent authoritative position that has been received.
+	pub fn latest(&self) -> Point3<f64> {
+		self.latest_snapshot().position
+	}
+
+	/// The measured jitter of the snapshots which have been received.
+	pub fn jitter(&self) -> &Jitter {
+		&self.jitter
+	}
+
+	/// How far behind the time it arrived the latest snapshot will be rendered, if snapshots keep arriving regularly.
+	pub fn delay(&self) -> Duration {
+		self.jitter.delay()
+	}
+
+	fn latest_snapshot(&self) -> &Snapshot {
+		self.snapshots.back().unwrap()
+	}
+
+	/// Records a new authoritative position which arrived at `now`.
+	/// If the position is further than [`TELEPORT_DISTANCE`] from the previous one,
+	/// the entity snaps to it instead of moving smoothly.
+	pub fn push_snapshot(&mut self, position: Point3<f64>, now: Instant) {
+		// Snapshots which arrive in a burst are spread out by the usual time between snapshots,
+		// so the entity doesn't rush through them. This grows the buffer until the entity catches up.
+		let latest = *self.latest_snapshot();
+		let played_at = now.max(latest.played_at + self.jitter.interval());
+		self.jitter.record(now);
+		let snapshot = Snapshot {
+			position,
+			played_at,
+		};
+		if (position - latest.position).magnitude() > TELEPORT_DISTANCE {
+			self.snapshots.clear();
+			self.playback = played_at;
+		}
+		self.snapshots.push_back(snapshot);
+		while self.snapshots.len() > MAX_SNAPSHOTS {
+			self.snapshots.pop_front();
+		}
+	}
+
+	/// Returns the point in the timeline of snapshots which the entity is rendered at, at `now`.
+	///
+	/// Playback moves at real time while the entity is [`delay`](Self::delay) behind the latest snapshot,
+	/// and slightly faster while it is further behind (e.g. after a latency spike, or once jitter has subsided).
+	/// It never moves backwards: if the delay grows, the entity waits at its position until it is far enough behind.
+	/// If no new snapshot has arrived by the time the entity reaches the latest snapshot, the entity stays there.
+	fn playback_at(&self, now: Instant) -> Instant {
+		let elapsed = now.saturating_duration_since(self.interpolated_at);
+		let delay = self.jitter.delay();
+		let behind = now.saturating_duration_since(self.playback);
+		let advance = match behind > delay {
+			true => elapsed.mul_f64(1.0 + CATCH_UP_RATE),
+			false => elapsed,
+		};
+		let mut playback = self.playback + advance;
+		if let Some(target) = now.checked_sub(delay) {
+			playback = playback.min(target);
+		}
+		playback
+			.min(self.latest_snapshot().played_at)
+			.max(self.playback)
+	}
+
+	/// Returns the position between the buffered snapshots at `now`.
+	pub fn sample(&self, now: Instant) -> Point3<f64> {
+		let playback = self.playback_at(now);
+		let next = self
+			.snapshots
+			.iter()
+			.position(|snapshot| snapshot.played_at > playback);
+		let (from, to) = match next {
+			Some(0) => return self.snapshots[0].position,
+			Some(next) => (&self.snapshots[next - 1], &self.snapshots[next]),
+			None => return self.latest(),
+		};
+		let interval = to.played_at.duration_since(from.played_at);
+		let elapsed = playback.duration_since(from.played_at);
+		let alpha = elapsed.as_secs_f64() / interval.as_secs_f64();
+		from.position + (to.position - from.position) * alpha
+	}
+
+	/// Updates the [`rendered`](Self::rendered) position to the interpolated position at `now`,
+	/// and drops the snapshots which have been played.
+	pub fn interpolate(&mut self, now: Instant) {
+		self.rendered.set_world_position(self.sample(now));
+		self.playback = self.playback_at(now);
+		self.interpolated_at = now;
+		while self.snapshots.len() > 1 && self.snapshots[1].played_at <= self.playback {
+			self.snapshots.pop_front();
+		}
+	}
+}
+
+impl debug::EguiInformation for InterpolatedPosition {
+	fn describe(&self) -> Vec<String> {
+		let latest = self.latest();
+		let rendered = self.rendered.world_position();
+		vec![
+			format!(
+				"Latest: <{:.2}, {:.2}, {:.2}>",
+				latest[0], latest[1], latest[2]
+			),
+			format!(
+				"Rendered: <{:.2}, {:.2}, {:.2}>",
+				rendered[0], rendered[1], rendered[2]
+			),
+			format!(
+				"Buffered snapshots: {} (delay {}ms, jitter {}ms)",
+				self.snapshots.len(),
+				self.delay().as_millis(),
+				self.jitter.deviation().as_millis()
+			),
+		]
+	}
+}
+
+#[cfg(test)]
+mod interpolation {
+	use super::*;
+	use std::time::Duration;
+
+	fn position_at(x: f64) -> Position {
+		let mut position = Position::default();
+		position.set_world_position(Point3::new(x, 0.0, 0.0));
+		position
+	}
+
+	#[test]
+	fn midpoint_at_half_interval() {
+		let start = Instant::now();
+		let interval = Duration::from_millis(100);
+		let mut interpolated = InterpolatedPosition::new(&position_at(0.0), start);
+		interpolated.push_snapshot(Point3::new(2.0, 0.0, 0.0), start + interval);
+
+		interpolated.interpolate(start + interval + interval / 2);
+		let rendered = interpolated.rendered().world_position();
+		assert!((rendered.x - 1.0).abs() < 1e-4, "{:?}", rendered);
+
+		assert_eq!(
+			interpolated.sample(start + interval * 3),
+			Point3::new(2.0, 0.0, 0.0)
+		);
+	}
+
+	#[test]
+	fn teleports_snap() {
+		let start = Instant::now();
+		let interval = Duration::from_millis(100);
+		let mut interpolated = InterpolatedPosition::new(&position_at(0.0), start);
+		interpolated.push_snapshot(Point3::new(100.0, 0.0, 0.0), start + interval);
+		assert_eq!(
+			interpolated.sample(start + interval),
+			Point3::new(100.0, 0.0, 0.0)
+		);
+	}
+
+	/// Feeds snapshots which arrive after each interval, returning when the last one arrived.
+	fn feed(jitter: &mut Jitter, mut now: Instant, intervals: &[u64]) -> Instant {
+		for interval in intervals.iter() {
+			now += Duration::from_millis(*interval);
+			jitter.record(now);
+		}
+		now
+	}
+
+	#[test]
+	fn delay_tracks_jitter() {
+		let start = Instant::now();
+		let mut jitter = Jitter::default();
+		jitter.record(start);
+
+		// A smooth link only needs to buffer about one snapshot.
+		let now = feed(&mut jitter, start, &[50; 100]);
+		let smooth = jitter.delay();
+		assert!(
+			smooth >= MIN_DELAY && smooth < Duration::from_millis(60),
+			"{:?}",
+			smooth
+		);
+
+		// Snapshots arrive just as often on average, but irregularly.
+		let now = feed(&mut jitter, now, &[20, 80].repeat(100));
+		let jittery = jitter.delay();
+		assert!(jittery > Duration::from_millis(90), "{:?}", jittery);
+		// The delay covers the extra time between snapshots which arrive late.
+		let expected = jitter.interval() + jitter.deviation().mul_f64(JITTER_MULTIPLIER);
+		let error = (jittery.as_secs_f64() - expected.as_secs_f64()).abs();
+		assert!(error < 1e-3, "{:?} != {:?}", jittery, expected);
+
+		// Once the link is smooth again, the delay shrinks back down.
+		feed(&mut jitter, now, &[50; 200]);
+		assert!(
+			jitter.delay() < Duration::from_millis(60),
+			"{:?}",
+			jitter.delay()
+		);
+	}
+
+	#[test]
+	fn idle_gaps_are_not_jitter() {
+		let start = Instant::now();
+		let mut jitter = Jitter::default();
+		jitter.record(start);
+		let now = feed(&mut jitter, start, &[50; 100]);
+		let moving = jitter.delay();
+
+		// The entity stands still for a while, and then starts moving again.
+		let idle = IDLE_GAP.as_millis() as u64 * 3;
+		let now = feed(&mut jitter, now, &[idle]);
+		assert_eq!(jitter.delay(), moving);
+		feed(&mut jitter, now, &[50; 10]);
+		assert!(
+			jitter.delay() < Duration::from_millis(60),
+			"{:?}",
+			jitter.delay()
+		);
+	}
+
+	#[test]
+	fn latency_spikes_grow_the_buffer() {
+		let start = Instant::now();
+		let frame = Duration::from_millis(10);
+		let mut interpolated = InterpolatedPosition::new(&position_at(0.0), start);
+		let mut now = start;
+		let mut x = 0.0;
+		let mut rendered = Vec::new();
+		let mut run = |interpolated: &mut InterpolatedPosition, now: &mut Instant, frames: u32| {
+			for _ in 0..frames {
+				*now += frame;
+				interpolated.interpolate(*now);
+				rendered.push(interpolated.rendered().world_position().x);
+			}
+		};
+
+		// The entity moves 0.5 blocks in each snapshot, which arrive every 50ms.
+		for _ in 0..40 {
+			run(&mut interpolated, &mut now, 5);
+			x += 0.5;
+			interpolated.push_snapshot(Point3::new(x, 0.0, 0.0), now);
+		}
+		let before_spike = interpolated.delay();
+
+		// No snapshots arrive for half a second, and then the snapshots that were held up arrive all at once.
+		run(&mut interpolated, &mut now, 50);
+		for _ in 0..10 {
+			x += 0.5;
+			interpolated.push_snapshot(Point3::new(x, 0.0, 0.0), now);
+		}
+		assert!(interpolated.delay() > before_spike);
+
+		for _ in 0..40 {
+			run(&mut interpolated, &mut now, 5);
+			x += 0.5;
+			interpolated.push_snapshot(Point3::new(x, 0.0, 0.0), now);
+		}
+		run(&mut interpolated, &mut now, 200);
+
+		// The entity never moves backwards or jumps ahead, even while it catches up after the spike.
+		for step in rendered.windows(2).map(|pair| pair[1] - pair[0]) {
+			assert!((0.0..0.2).contains(&step), "{}", step);
+		}
+		assert_eq!(*rendered.last().unwrap(), x);
+	}
+}
@@ -69,6 +69,12 @@ impl Position {
 		self.prev_chunk = Some(self.chunk);
 	}
 
+	/// Makes the replicator treat the entity as if it had just moved into its chunk,
+	/// so which connections it is relevant to is decided again (e.g. after it changes worlds).
+	pub fn unacknowledge_chunk(&mut self) {
+		self.prev_chunk = None;
+	}
+
 	/// Returns the coordinate of the chunk the entity is in.
 	pub fn chunk(&self) -> &Point3<i64> {
 		&self.chunk
@@ -78,6 +84,41 @@ impl Position {
 	pub fn offset(&self) -> &Point3<f32> {
 		&self.offset
 	}
+
+	/// Returns the position of the entity in blocks from the world origin.
+	pub fn world_position(&self) -> Point3<f64> {
+		use crate::common::world::chunk::SIZE;
+		let mut position = Point3::origin();
+		for i in 0..3 {
+			position[i] = (self.chunk[i] as f64) * (SIZE[i] as f64) + (self.offset[i] as f64);
+		}
+		position
+	}
+
+	/// Moves the entity to a position in blocks from the world origin.
+	/// The previous chunk is retained, so systems which react to an entity changing chunks
+	/// (replication relevancy, chunk tickets) handle the move like any other.
+	pub fn set_world_position(&mut self, position: Point3<f64>) {
+		use crate::common::world::chunk::SIZE;
+		for i in 0..3 {
+			let size = SIZE[i] as f64;
+			let chunk = (position[i] / size).floor();
+			let offset = (position[i] - chunk * size) as f32;
+			// Positions just below a chunk boundary can round up to the size of a chunk,
+			// in which case they are at the start of the next chunk instead.
+			match offset < SIZE[i] {
+				true => {
+					self.chunk[i] = chunk as i64;
+					self.offset[i] = offset;
+				}
+				false => {
+					self.chunk[i] = chunk as i64 + 1;
+					self.offset[i] = 0.0;
+				}
+			}
+		}
+		self.has_changed = true;
+	}
 }
 
 impl std::ops::AddAssign<Vector3<f32>> for Position {
@@ -130,14 +171,30 @@ impl binary::Serializable for Position {
 }
 
 impl debug::EguiInformation for Position {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!(
-			"Chunk: <{:04}, {:04}, {:04}>",
-			self.chunk[0], self.chunk[1], self.chunk[2]
-		));
-		ui.label(format!(
-			"Offset: <{:.2}, {:.2}, {:.2}>",
-			self.offset[0], self.offset[1], self.offset[2]
-		));
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!(
+				"Chunk: <{:04}, {:04}, {:04}>",
+				self.chunk[0], self.chunk[1], self.chunk[2]
+			),
+			format!(
+				"Offset: <{:.2}, {:.2}, {:.2}>",
+				self.offset[0], self.offset[1], self.offset[2]
+			),
+		]
+	}
+}
+
+#[cfg(test)]
+mod world_position {
+	use super::*;
+
+	#[test]
+	fn offset_is_within_chunk() {
+		let mut position = Position::default();
+		// The offset of a position this close below zero rounds to 16 as an f32.
+		position.set_world_position(Point3::new(-1e-9, 17.5, -16.0));
+		assert_eq!(*position.chunk(), Point3::new(0, 1, -1));
+		assert_eq!(*position.offset(), Point3::new(0.0, 1.5, 0.0));
 	}
 }
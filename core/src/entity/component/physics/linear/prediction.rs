@@ -0,0 +1,378 @@
+use super::Position;
+use crate::{
+	common::physics::{self, Voxels},
+	entity::component::{debug, physics::Collider, Component, Registration},
+};
+use engine::math::nalgebra::{Point3, Vector3};
+use std::{collections::VecDeque, time::Duration};
+
+/// Corrections which are further than this (in blocks) from the predicted position are snapped to instead of blended.
+pub static SNAP_DISTANCE: f64 = 2.0;
+
+/// How long it takes for most of a small correction to be blended into the predicted position.
+pub static BLEND_DURATION: Duration = Duration::from_millis(100);
+
+/// The most inputs which are buffered while waiting for the server to acknowledge them.
+/// If the server stops acknowledging inputs, the oldest are dropped instead of growing forever.
+pub static MAX_INPUTS: usize = 128;
+
+/// Client-only component added to the entity the local player controls,
+/// which lets the [`Position`] of the entity move as soon as the player gives input,
+/// instead of waiting for the server to move it.
+///
+/// Each input sent to the server is buffered with a sequence number until the server
+/// [`acknowledges`](super::AcknowledgedInput) it. When an authoritative position arrives,
+/// the inputs the server had not applied yet are replayed from that position to find where the player should be now,
+/// stopping the player against the voxels the client has like the server stops it against the voxels of its world.
+/// The latest acknowledged input is kept, because the player keeps moving with it after the server's position was taken.
+#[derive(Clone)]
+pub struct Prediction {
+	snap_distance: f64,
+	blend_duration: Duration,
+	max_inputs: usize,
+	/// The sequence number of the last input sent to the server.
+	sequence: u32,
+	/// The inputs which have been sent but not acknowledged, oldest first.
+	inputs: VecDeque<Input>,
+	/// The latest position replicated from the server, which has not been reconciled with yet.
+	authoritative: Option<Point3<f64>>,
+	/// The part of the last correction which has not been blended into the position yet.
+	correction: Vector3<f64>,
+}
+
+/// A velocity the player moved with, and how long they moved with it before the next input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Input {
+	sequence: u32,
+	velocity: Vector3<f32>,
+	duration: Duration,
+}
+
+/// How the predicted position was changed to agree with the server.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Correction {
+	/// The prediction was too far from the server's position, so it was moved there immediately.
+	Snap(Point3<f64>),
+	/// The prediction was close to the server's position, so this offset is blended in over time.
+	Blend(Vector3<f64>),
+}
+
+impl Default for Prediction {
+	fn default() -> Self {
+		Self {
+			snap_distance: SNAP_DISTANCE,
+			blend_duration: BLEND_DURATION,
+			max_inputs: MAX_INPUTS,
+			sequence: 0,
+			inputs: VecDeque::new(),
+			authoritative: None,
+			correction: Vector3::zeros(),
+		}
+	}
+}
+
+impl Component for Prediction {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::physics::linear::Prediction"
+	}
+
+	fn display_name() -> &'static str {
+		"Prediction"
+	}
+
+	fn registration() -> Registration<Self>
+	where
+		Self: Sized,
+	{
+		use debug::Registration as debug;
+		Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl Prediction {
+	pub fn with_snap_distance(mut self, distance: f64) -> Self {
+		self.snap_distance = distance;
+		self
+	}
+
+	pub fn with_blend_duration(mut self, duration: Duration) -> Self {
+		self.blend_duration = duration;
+		self
+	}
+
+	pub fn with_max_inputs(mut self, count: usize) -> Self {
+		self.max_inputs = count;
+		self
+	}
+
+	/// Buffers a velocity which is being sent to the server, returning the sequence number to send it with.
+	pub fn push_input(&mut self, velocity: Vector3<f32>) -> u32 {
+		self.sequence = self.sequence.wrapping_add(1);
+		self.inputs.push_back(Input {
+			sequence: self.sequence,
+			velocity,
+			duration: Duration::ZERO,
+		});
+		while self.inputs.len() > self.max_inputs {
+			self.inputs.pop_front();
+		}
+		self.sequence
+	}
+
+	/// Records that the player has moved with the latest input for `delta_time` longer.
+	pub fn advance(&mut self, delta_time: Duration) {
+		if let Some(input) = self.inputs.back_mut() {
+			input.duration += delta_time;
+		}
+	}
+
+	/// Records a position replicated from the server, which is reconciled with on the next update of the player's controller.
+	pub fn receive(&mut self, authoritative: Point3<f64>) {
+		self.authoritative = Some(authoritative);
+	}
+
+	/// Returns the position replicated from the server since the last call, if any.
+	pub fn take_authoritative(&mut self) -> Option<Point3<f64>> {
+		self.authoritative.take()
+	}
+
+	/// Drops the inputs the server has finished applying (those before `acknowledged`),
+	/// and returns where the player would be if the remaining inputs were applied from the authoritative position.
+	///
+	/// The server had moved the player with the acknowledged input for `applied_for` when it took its position,
+	/// so that input is extrapolated for the rest of the time the player moved with it.
+	///
+	/// If `collision` is provided, each input moves the player's collider with the same
+	/// [`collision step`](physics::move_and_collide) as the server, so the player is stopped by the voxels the client has.
+	pub fn replay(
+		&mut self,
+		authoritative: Point3<f64>,
+		acknowledged: u32,
+		applied_for: Duration,
+		collision: Option<(&dyn Voxels, &Collider)>,
+	) -> Point3<f64> {
+		// Sequence numbers wrap, so an input is finished if it is behind the acknowledged sequence.
+		while let Some(input) = self.inputs.front() {
+			if (input.sequence.wrapping_sub(acknowledged) as i32) >= 0 {
+				break;
+			}
+			self.inputs.pop_front();
+		}
+		self.inputs.iter().fold(authoritative, |position, input| {
+			let duration = match input.sequence == acknowledged {
+				true => input.duration.saturating_sub(applied_for),
+				false => input.duration,
+			};
+			let displacement = input.velocity.cast::<f64>() * duration.as_secs_f64();
+			match collision {
+				Some((voxels, collider)) => {
+					let (moved_to, _stopped) = physics::move_and_collide(
+						voxels,
+						position,
+						collider.size(),
+						collider.groups(),
+						displacement,
+					);
+					moved_to
+				}
+				None => position + displacement,
+			}
+		})
+	}
+
+	/// Corrects the predicted position after the server sent an authoritative position which includes
+	/// every input before `acknowledged`, and `applied_for` of the acknowledged input.
+	/// The inputs since are replayed against the voxels in `collision` (see [`replay`](Self::replay)).
+	/// Large corrections are snapped to, and small ones are blended in by [`blend`](Self::blend).
+	pub fn reconcile(
+		&mut self,
+		position: &mut Position,
+		authoritative: Point3<f64>,
+		acknowledged: u32,
+		applied_for: Duration,
+		collision: Option<(&dyn Voxels, &Collider)>,
+	) -> Correction {
+		let predicted = self.replay(authoritative, acknowledged, applied_for, collision);
+		let offset = predicted - position.world_position();
+		if offset.magnitude() > self.snap_distance {
+			self.correction = Vector3::zeros();
+			position.set_world_position(predicted);
+			Correction::Snap(predicted)
+		} else {
+			self.correction = offset;
+			Correction::Blend(offset)
+		}
+	}
+
+	/// Moves the position by the part of the last correction which should be blended in over `delta_time`.
+	pub fn blend(&mut self, position: &mut Position, delta_time: Duration) {
+		if self.correction == Vector3::zeros() {
+			return;
+		}
+		let alpha = match self.blend_duration.is_zero() {
+			true => 1.0,
+			false => (delta_time.as_secs_f64() / self.blend_duration.as_secs_f64()).min(1.0),
+		};
+		let mut step = self.correction * alpha;
+		// Stop blending once the remaining correction is too small to see.
+		if (self.correction - step).magnitude_squared() < 1e-8 {
+			step = self.correction;
+		}
+		self.correction -= step;
+		position.set_world_position(position.world_position() + step);
+	}
+}
+
+impl debug::EguiInformation for Prediction {
+	fn describe(&self) -> Vec<String> {
+		vec![
+			format!("Sequence: {}", self.sequence),
+			format!("Unacknowledged inputs: {}", self.inputs.len()),
+			format!(
+				"Correction: <{:.2}, {:.2}, {:.2}>",
+				self.correction[0], self.correction[1], self.correction[2]
+			),
+		]
+	}
+}
+
+#[cfg(test)]
+mod reconciliation {
+	use super::*;
+
+	/// Moves a position by each velocity for its duration, as the server's physics would.
+	fn simulate(start: Point3<f64>, inputs: &[(Vector3<f32>, Duration)]) -> Point3<f64> {
+		let mut position = Position::default();
+		position.set_world_position(start);
+		for (velocity, duration) in inputs.iter() {
+			position += velocity * duration.as_secs_f32();
+		}
+		position.world_position()
+	}
+
+	fn assert_near(a: Point3<f64>, b: Point3<f64>) {
+		assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+	}
+
+	#[test]
+	fn replay_matches_server() {
+		let start = Point3::new(14.0, 2.0, -3.0);
+		let inputs = [
+			(Vector3::new(4.0, 0.0, 0.0), Duration::from_millis(250)),
+			(Vector3::new(0.0, 0.0, -4.0), Duration::from_millis(500)),
+			(Vector3::new(2.0, 1.0, 2.0), Duration::from_millis(100)),
+			(Vector3::new(-4.0, 0.0, 0.0), Duration::from_millis(300)),
+		];
+		let mut prediction = Prediction::default();
+		for (velocity, duration) in inputs.iter() {
+			prediction.push_input(*velocity);
+			prediction.advance(*duration);
+		}
+
+		// The server has applied the first two inputs when it sends its position.
+		let authoritative = simulate(start, &inputs[..2]);
+		let predicted = prediction.replay(authoritative, 2, inputs[1].1, None);
+		assert_near(predicted, simulate(start, &inputs));
+		// The acknowledged input is kept until the next input is acknowledged.
+		assert_eq!(prediction.inputs.len(), 3);
+
+		// Once every input is acknowledged, the server's position is the prediction.
+		let authoritative = simulate(start, &inputs);
+		assert_near(
+			prediction.replay(authoritative, 4, inputs[3].1, None),
+			authoritative,
+		);
+		assert_eq!(prediction.inputs.len(), 1);
+	}
+
+	#[test]
+	fn acknowledged_input_is_extrapolated() {
+		let start = Point3::new(0.0, 0.0, 0.0);
+		let velocity = Vector3::new(4.0, 0.0, 0.0);
+		let mut prediction = Prediction::default();
+		prediction.push_input(velocity);
+		prediction.advance(Duration::from_secs(1));
+
+		// The server took its position after moving the player with the input for less time than the client has.
+		let applied_for = Duration::from_millis(600);
+		let authoritative = simulate(start, &[(velocity, applied_for)]);
+		let predicted = prediction.replay(authoritative, 1, applied_for, None);
+		assert_near(
+			predicted,
+			simulate(start, &[(velocity, Duration::from_secs(1))]),
+		);
+	}
+
+	#[test]
+	fn replay_is_stopped_by_voxels() {
+		use crate::{block, common::world::chunk::SIZE_I};
+
+		/// Every chunk is loaded, and the blocks from x=3 onwards are full blocks.
+		struct Wall;
+		impl Voxels for Wall {
+			fn colliders_at(&self, point: &block::Point) -> Option<Vec<block::Collider>> {
+				let x = point.chunk().x * SIZE_I.x as i64 + point.offset().x as i64;
+				Some(match x >= 3 {
+					true => block::Shape::cube().colliders_at(point),
+					false => Vec::new(),
+				})
+			}
+		}
+
+		let mut prediction = Prediction::default();
+		prediction.push_input(Vector3::new(4.0, 0.0, 0.0));
+		prediction.advance(Duration::from_secs(1));
+
+		let collider = Collider::player();
+		let start = Point3::new(0.5, 0.0, 0.5);
+		let predicted = prediction.replay(start, 0, Duration::ZERO, Some((&Wall, &collider)));
+		// The server would have stopped the player with its side against the wall.
+		let half_width = collider.size().x as f64 / 2.0;
+		assert_near(predicted, Point3::new(3.0 - half_width, 0.0, 0.5));
+		assert_near(
+			prediction.replay(start, 0, Duration::ZERO, None),
+			Point3::new(4.5, 0.0, 0.5),
+		);
+	}
+
+	#[test]
+	fn large_corrections_snap() {
+		let mut position = Position::default();
+		position.set_world_position(Point3::new(0.0, 0.0, 0.0));
+		let mut prediction = Prediction::default();
+		prediction.push_input(Vector3::new(1.0, 0.0, 0.0));
+		prediction.advance(Duration::from_secs(1));
+
+		let correction = prediction.reconcile(
+			&mut position,
+			Point3::new(10.0, 0.0, 0.0),
+			0,
+			Duration::ZERO,
+			None,
+		);
+		assert_eq!(correction, Correction::Snap(Point3::new(11.0, 0.0, 0.0)));
+		assert_near(position.world_position(), Point3::new(11.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn small_corrections_blend() {
+		let mut position = Position::default();
+		position.set_world_position(Point3::new(0.0, 0.0, 0.0));
+		let mut prediction = Prediction::default().with_blend_duration(Duration::from_millis(100));
+
+		let correction = prediction.reconcile(
+			&mut position,
+			Point3::new(0.5, 0.0, 0.0),
+			0,
+			Duration::ZERO,
+			None,
+		);
+		assert_eq!(correction, Correction::Blend(Vector3::new(0.5, 0.0, 0.0)));
+		assert_eq!(position.world_position(), Point3::new(0.0, 0.0, 0.0));
+
+		prediction.blend(&mut position, Duration::from_millis(50));
+		assert_near(position.world_position(), Point3::new(0.25, 0.0, 0.0));
+		prediction.blend(&mut position, Duration::from_millis(100));
+		assert_near(position.world_position(), Point3::new(0.5, 0.0, 0.0));
+	}
+}
@@ -80,18 +80,16 @@ impl binary::Serializable for Velocity {
 }
 
 impl debug::EguiInformation for Velocity {
-	fn render(&self, ui: &mut egui::Ui) {
-		ui.label(format!(
-			"<{:.2}, {:.2}, {:.2}>",
-			self.0[0], self.0[1], self.0[2]
-		));
-
+	fn describe(&self) -> Vec<String> {
 		let direction = self.0.normalize();
 		let speed = self.0.magnitude();
-		ui.label(format!(
-			"Direction: <{:.2}, {:.2}, {:.2}>",
-			direction[0], direction[1], direction[2]
-		));
-		ui.label(format!("Speed: {:.4}", speed));
+		vec![
+			format!("<{:.2}, {:.2}, {:.2}>", self.0[0], self.0[1], self.0[2]),
+			format!(
+				"Direction: <{:.2}, {:.2}, {:.2}>",
+				direction[0], direction[1], direction[2]
+			),
+			format!("Speed: {:.4}", speed),
+		]
 	}
 }
@@ -0,0 +1,52 @@
+use std::net::SocketAddr;
+
+/// Component added on the server to the free-flying camera of a spectator connection.
+///
+/// Unlike players, a spectator's entity is not [`owned`](super::OwnedByConnection) by its connection
+/// and is never replicated. It only exists so the spectator has a position in the world,
+/// which is a source of chunk relevance (and chunk loading tickets) for the spectating connection.
+#[derive(Clone, Copy)]
+pub struct Spectator {
+	/// The address of the connection which is spectating from this entity.
+	address: SocketAddr,
+}
+
+impl super::Component for Spectator {
+	fn unique_id() -> &'static str {
+		"crystal_sphinx::entity::component::Spectator"
+	}
+
+	fn display_name() -> &'static str {
+		"Spectator"
+	}
+
+	fn registration() -> super::Registration<Self>
+	where
+		Self: Sized,
+	{
+		use super::debug::Registration as debug;
+		super::Registration::<Self>::default().with_ext(debug::from::<Self>())
+	}
+}
+
+impl std::fmt::Display for Spectator {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "Spectator(address={})", self.address)
+	}
+}
+
+impl Spectator {
+	pub fn new(address: SocketAddr) -> Self {
+		Self { address }
+	}
+
+	pub fn address(&self) -> &SocketAddr {
+		&self.address
+	}
+}
+
+impl super::debug::EguiInformation for Spectator {
+	fn describe(&self) -> Vec<String> {
+		vec![format!("IP Address: {}", self.address)]
+	}
+}
@@ -0,0 +1,231 @@
+use crate::entity::{
+	component::{binary::SerializedEntity, PersistentId, Registry},
+	World,
+};
+use anyhow::Result;
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+};
+
+pub type ArcLockPersistentIds = Arc<RwLock<PersistentIds>>;
+
+/// The extension of the files which entities are saved to.
+pub static EXTENSION: &'static str = "entity";
+
+/// Server-side map from the [`persistent id`](PersistentId) of each entity
+/// to the [`hecs::Entity`] it currently is in the world.
+#[derive(Default)]
+pub struct PersistentIds {
+	entities: HashMap<PersistentId, hecs::Entity>,
+}
+
+impl PersistentIds {
+	pub fn arclocked(self) -> ArcLockPersistentIds {
+		Arc::new(RwLock::new(self))
+	}
+
+	/// Returns the entity which currently has a persistent id, if it is in the world.
+	pub fn resolve(&self, id: &PersistentId) -> Option<hecs::Entity> {
+		self.entities.get(id).cloned()
+	}
+
+	pub fn len(&self) -> usize {
+		self.entities.len()
+	}
+
+	/// Spawns an entity which should persist.
+	/// If the builder already has a persistent id (i.e. it was loaded from a save) that id is reused,
+	/// otherwise the entity is assigned a new one.
+	pub fn spawn(
+		&mut self,
+		world: &mut World,
+		mut builder: hecs::EntityBuilder,
+	) -> Result<(hecs::Entity, PersistentId)> {
+		let id = match builder.get::<&PersistentId>() {
+			Some(id) => *id,
+			None => {
+				let id = PersistentId::new();
+				builder.add(id);
+				id
+			}
+		};
+		if let Some(existing) = self.resolve(&id) {
+			if world.contains(existing) {
+				return Err(Error::AlreadySpawned(id, existing))?;
+			}
+		}
+		let entity = world.spawn(builder.build());
+		self.entities.insert(id, entity);
+		Ok((entity, id))
+	}
+
+	/// Spawns an entity which was [`saved`](Self::save), reusing its persistent id.
+	/// The saved components are added to `builder`, replacing any of the same type,
+	/// so components which are not saved can be provided by the builder.
+	pub fn load(
+		&mut self,
+		world: &mut World,
+		registry: &Registry,
+		saved: SerializedEntity,
+		mut builder: hecs::EntityBuilder,
+	) -> Result<(hecs::Entity, PersistentId)> {
+		saved.add_to(registry, &mut builder)?;
+		self.spawn(world, builder)
+	}
+
+	/// Forgets entities which have been despawned.
+	/// Entities are only added to the map when they are spawned through it,
+	/// so this does not need to look at the entities which are still in the world.
+	pub fn forget_despawned(&mut self, world: &World) {
+		self.entities.retain(|_id, entity| world.contains(*entity));
+	}
+
+	/// Returns the path that the entity with a persistent id is saved to in a directory.
+	pub fn saved_path(dir: &Path, id: &PersistentId) -> PathBuf {
+		dir.join(format!("{:032x}.{}", id.value(), EXTENSION))
+	}
+
+	/// Saves an entity to a directory, if it has a persistent id, returning the id it was saved with.
+	pub fn save(
+		registry: &Registry,
+		entity_ref: &hecs::EntityRef<'_>,
+		dir: &Path,
+	) -> Result<Option<PersistentId>> {
+		let id = match entity_ref.get::<&PersistentId>() {
+			Some(id) => *id,
+			None => return Ok(None),
+		};
+		let saved = SerializedEntity::from_entity(registry, entity_ref)?;
+		std::fs::create_dir_all(dir)?;
+		std::fs::write(Self::saved_path(dir, &id), bincode::serialize(&saved)?)?;
+		Ok(Some(id))
+	}
+
+	/// Saves every entity in the map to a directory, returning how many were saved.
+	pub fn save_all(&self, world: &World, registry: &Registry, dir: &Path) -> Result<usize> {
+		let mut count = 0;
+		for entity in self.entities.values() {
+			if let Ok(entity_ref) = world.entity(*entity) {
+				if Self::save(registry, &entity_ref, dir)?.is_some() {
+					count += 1;
+				}
+			}
+		}
+		Ok(count)
+	}
+
+	/// Reads the entity with a persistent id from a directory, if it has been saved there.
+	pub fn read_saved(dir: &Path, id: &PersistentId) -> Result<Option<SerializedEntity>> {
+		let path = Self::saved_path(dir, id);
+		if !path.exists() {
+			return Ok(None);
+		}
+		let bytes = std::fs::read(&path)?;
+		Ok(Some(bincode::deserialize(&bytes)?))
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("an entity with persistent id {0} is already in the world ({1:?})")]
+	AlreadySpawned(PersistentId, hecs::Entity),
+}
+
+#[cfg(test)]
+mod save_load {
+	use super::*;
+	use crate::entity::component::physics::linear::Position;
+	use engine::math::nalgebra::Point3;
+
+	fn register_types() {
+		let mut registry = Registry::write();
+		registry.register::<PersistentId>();
+		registry.register::<Position>();
+	}
+
+	fn create_dir() -> PathBuf {
+		let mut dir = std::env::temp_dir();
+		dir.push(format!("crystal-sphinx-entities-{}", uuid::Uuid::new_v4()));
+		dir
+	}
+
+	#[test]
+	fn load_reuses_saved_id() {
+		register_types();
+		let dir = create_dir();
+		let saved_id = {
+			let mut world = World::new();
+			let mut ids = PersistentIds::default();
+			let mut builder = hecs::EntityBuilder::new();
+			let mut position = Position::default();
+			position.set_world_position(Point3::new(1.0, 2.0, 3.0));
+			builder.add(position);
+			let (entity, id) = ids.spawn(&mut world, builder).unwrap();
+			assert_eq!(ids.resolve(&id), Some(entity));
+			assert_eq!(ids.save_all(&world, &Registry::read(), &dir).unwrap(), 1);
+			id
+		};
+
+		// A different world (as if the server had restarted), where other entities have been spawned first.
+		let mut world = World::new();
+		let _other = world.spawn((Position::default(),));
+		let mut ids = PersistentIds::default();
+		let saved = PersistentIds::read_saved(&dir, &saved_id).unwrap().unwrap();
+		// Saved components replace those of the same type in the builder.
+		let mut builder = hecs::EntityBuilder::new();
+		builder.add(Position::default());
+		let (entity, id) = ids
+			.load(&mut world, &Registry::read(), saved, builder)
+			.unwrap();
+		assert_eq!(id, saved_id);
+		assert_eq!(ids.resolve(&saved_id), Some(entity));
+		assert_eq!(*world.get::<&PersistentId>(entity).unwrap(), saved_id);
+		assert_eq!(
+			world.get::<&Position>(entity).unwrap().world_position(),
+			Point3::new(1.0, 2.0, 3.0)
+		);
+
+		// The same entity cannot be loaded twice.
+		let duplicate = {
+			let mut builder = hecs::EntityBuilder::new();
+			builder.add(saved_id);
+			builder
+		};
+		assert!(ids.spawn(&mut world, duplicate).is_err());
+
+		// Entities which were never saved have nothing to load.
+		assert!(PersistentIds::read_saved(&dir, &PersistentId::from(7))
+			.unwrap()
+			.is_none());
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn despawned_entities_are_forgotten() {
+		let mut world = World::new();
+		let mut ids = PersistentIds::default();
+		let (despawned, despawned_id) = ids.spawn(&mut world, hecs::EntityBuilder::new()).unwrap();
+		let (remaining, remaining_id) = ids.spawn(&mut world, hecs::EntityBuilder::new()).unwrap();
+
+		world.despawn(despawned).unwrap();
+		ids.forget_despawned(&world);
+		assert_eq!(ids.resolve(&despawned_id), None);
+		assert_eq!(ids.resolve(&remaining_id), Some(remaining));
+		assert_eq!(ids.len(), 1);
+	}
+
+	#[test]
+	fn account_ids_are_deterministic() {
+		let id = "player-one".to_owned();
+		assert_eq!(
+			PersistentId::for_account(&id),
+			PersistentId::for_account(&id)
+		);
+		assert_ne!(
+			PersistentId::for_account(&id),
+			PersistentId::for_account(&"player-two".to_owned())
+		);
+	}
+}
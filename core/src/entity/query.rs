@@ -0,0 +1,121 @@
+use super::World;
+use hecs::{Query, QueryItem};
+use std::sync::RwLock;
+
+/// Convenience functions for running a [`Query`] over a locked [`World`],
+/// so that callers dont need to manage the lock and query borrows themselves.
+///
+/// The shared variants only acquire the read lock, and rely on hecs's dynamic borrow checking
+/// for any mutable components in the query (the same as [`World::query`]).
+/// The `_mut` variants acquire the write lock, and have exclusive access to the world for the duration of the query.
+pub trait WorldQuery {
+	/// Calls `f` for every entity which matches the query `Q` while holding the read lock.
+	fn for_each_with<Q, F>(&self, f: F)
+	where
+		Q: Query,
+		F: FnMut(hecs::Entity, QueryItem<'_, Q>);
+
+	/// Calls `f` for every entity which matches the query `Q` while holding the write lock.
+	fn for_each_with_mut<Q, F>(&self, f: F)
+	where
+		Q: Query,
+		F: FnMut(hecs::Entity, QueryItem<'_, Q>);
+
+	/// Returns the result of `f` for every entity which matches the query `Q`, gathered while holding the read lock.
+	/// Query items cannot outlive the lock, so `f` must map each item to owned data.
+	fn collect_with<Q, T, F>(&self, mut f: F) -> Vec<T>
+	where
+		Q: Query,
+		F: FnMut(hecs::Entity, QueryItem<'_, Q>) -> T,
+	{
+		let mut items = Vec::new();
+		self.for_each_with::<Q, _>(|entity, item| items.push(f(entity, item)));
+		items
+	}
+}
+
+impl WorldQuery for RwLock<World> {
+	fn for_each_with<Q, F>(&self, mut f: F)
+	where
+		Q: Query,
+		F: FnMut(hecs::Entity, QueryItem<'_, Q>),
+	{
+		let world = self.read().unwrap();
+		let mut query = world.query::<Q>();
+		for (entity, item) in query.iter() {
+			f(entity, item);
+		}
+	}
+
+	fn for_each_with_mut<Q, F>(&self, mut f: F)
+	where
+		Q: Query,
+		F: FnMut(hecs::Entity, QueryItem<'_, Q>),
+	{
+		let mut world = self.write().unwrap();
+		for (entity, item) in world.query_mut::<Q>() {
+			f(entity, item);
+		}
+	}
+}
+
+#[cfg(test)]
+mod world_query {
+	use super::*;
+
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	struct Counter(u32);
+	struct Flag;
+
+	struct Entities {
+		flagged: Vec<hecs::Entity>,
+		counter_only: hecs::Entity,
+	}
+
+	fn create_world() -> (RwLock<World>, Entities) {
+		let mut world = World::new();
+		let flagged = vec![
+			world.spawn((Counter(0), Flag)),
+			world.spawn((Counter(5), Flag)),
+		];
+		let counter_only = world.spawn((Counter(1),));
+		world.spawn((Flag,));
+		let entities = Entities {
+			flagged,
+			counter_only,
+		};
+		(RwLock::new(world), entities)
+	}
+
+	#[test]
+	fn for_each_visits_matching_entities() {
+		let (world, mut entities) = create_world();
+		let mut visited = Vec::new();
+		world.for_each_with::<(&Counter, &Flag), _>(|entity, _| visited.push(entity));
+		visited.sort();
+		entities.flagged.sort();
+		assert_eq!(visited, entities.flagged);
+	}
+
+	#[test]
+	fn collect_maps_matching_entities() {
+		let (world, _entities) = create_world();
+		let mut counters =
+			world.collect_with::<(&Counter, &Flag), _, _>(|_entity, (counter, _)| counter.0);
+		counters.sort();
+		assert_eq!(counters, vec![0, 5]);
+	}
+
+	#[test]
+	fn mutations_persist() {
+		let (world, entities) = create_world();
+		world.for_each_with_mut::<(&mut Counter, &Flag), _>(|_entity, (counter, _)| {
+			counter.0 += 10;
+		});
+		let world = world.read().unwrap();
+		let counter = |entity| *world.get::<&Counter>(entity).unwrap();
+		assert_eq!(counter(entities.flagged[0]), Counter(10));
+		assert_eq!(counter(entities.flagged[1]), Counter(15));
+		assert_eq!(counter(entities.counter_only), Counter(1));
+	}
+}
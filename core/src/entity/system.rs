@@ -1,11 +1,21 @@
 pub mod replicator;
 pub use replicator::Replicator;
+mod break_blocks;
+pub use break_blocks::*;
 mod update_camera;
 pub use update_camera::*;
+mod update_connection_quality;
+pub use update_connection_quality::*;
+mod interpolate_positions;
+pub use interpolate_positions::*;
 mod physics;
 pub use physics::*;
+mod place_blocks;
+pub use place_blocks::*;
 mod player_controller;
 pub use player_controller::*;
+mod track_persistent_ids;
+pub use track_persistent_ids::*;
 mod user_chunk_ticket_updater;
 pub use user_chunk_ticket_updater::*;
 mod owned_by_connection;
@@ -0,0 +1,243 @@
+use crate::{
+	block,
+	entity::{
+		self,
+		component::{chunk::TicketOwner, BlockInteraction, Inventory, Item},
+		ArcLockEntityWorld,
+	},
+	server::{
+		tick::{self, FixedTimestep},
+		world::{chunk, Registry},
+	},
+};
+use engine::{math::nalgebra::Point3, EngineSystem};
+use std::{
+	net::SocketAddr,
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+static LOG: &'static str = "subsystem:break-blocks";
+
+/// A block which has finished being broken by an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenBlock {
+	pub entity: hecs::Entity,
+	/// The connection which owns the entity that broke the block, if any.
+	pub instigator: Option<SocketAddr>,
+	/// The name of the world the block (and the entity) is in.
+	pub world: String,
+	pub target: block::Point,
+}
+
+/// Server system which advances each [`block interaction`](BlockInteraction) once per tick,
+/// removing the block (through the [`plugin validated`](chunk::Chunk::apply_block_change) edit path)
+/// when an interaction completes. The entity which broke the block picks it up into its [`Inventory`].
+///
+/// Blocks are broken in the world the entity breaking them is in.
+pub struct BreakBlocks {
+	world: Weak<RwLock<entity::World>>,
+	worlds: Registry,
+	timestep: FixedTimestep,
+}
+
+impl BreakBlocks {
+	pub fn new(world: &ArcLockEntityWorld, worlds: &Registry) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+			worlds: worlds.clone(),
+			timestep: FixedTimestep::new(tick::ticks_per_second()),
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+}
+
+/// Advances every block interaction in the world by `delta_time`,
+/// returning the blocks which finished being broken.
+///
+/// Interactions whose target cannot be broken (i.e. it is air or its chunk is not loaded in the entity's world) are cancelled,
+/// as are completed interactions, so each block is only ever reported as broken once.
+pub fn advance_interactions<F>(
+	world: &mut entity::World,
+	delta_time: Duration,
+	can_break: F,
+) -> Vec<BrokenBlock>
+where
+	F: Fn(&str, &block::Point) -> bool,
+{
+	use entity::component::OwnedByConnection;
+	let mut finished = Vec::new();
+	let mut broken = Vec::new();
+	for (entity, (interaction, owner, ticket_owner)) in world.query_mut::<(
+		&mut BlockInteraction,
+		Option<&OwnedByConnection>,
+		Option<&TicketOwner>,
+	)>() {
+		let world_name = Registry::world_of(ticket_owner);
+		if !can_break(world_name, interaction.target()) {
+			finished.push(entity);
+			continue;
+		}
+		if interaction.advance(delta_time) {
+			finished.push(entity);
+			broken.push(BrokenBlock {
+				entity,
+				instigator: owner.map(|owner| *owner.address()),
+				world: world_name.to_owned(),
+				target: *interaction.target(),
+			});
+		}
+	}
+	for entity in finished.into_iter() {
+		let _ = world.remove_one::<BlockInteraction>(entity);
+	}
+	broken
+}
+
+impl EngineSystem for BreakBlocks {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!(LOG);
+		let steps = self.timestep.advance(delta_time);
+		if steps == 0 {
+			return;
+		}
+		let arc_world = match self.world.upgrade() {
+			Some(world) => world,
+			None => return,
+		};
+		let chunk_caches = self.worlds.chunk_caches();
+		let find_chunk = |world_name: &str, point: &block::Point| {
+			let chunk_cache = chunk_caches.get(world_name)?.read().unwrap();
+			chunk_cache
+				.find(point.chunk())
+				.map(|weak| weak.upgrade())
+				.flatten()
+		};
+		let offset_of = |point: &block::Point| point.offset().map(|v| v as usize);
+
+		let step = *self.timestep.step();
+		for _ in 0..steps {
+			let broken = {
+				let mut world = arc_world.write().unwrap();
+				advance_interactions(&mut world, step, |world_name, point| {
+					match find_chunk(world_name, point) {
+						Some(arc_chunk) => {
+							let server_chunk = arc_chunk.read().unwrap();
+							server_chunk
+								.chunk
+								.block_ids()
+								.contains_key(&offset_of(point))
+						}
+						None => false,
+					}
+				})
+			};
+			for broken_block in broken.into_iter() {
+				let arc_chunk = match find_chunk(&broken_block.world, &broken_block.target) {
+					Some(arc_chunk) => arc_chunk,
+					None => continue,
+				};
+				let plugins = crate::plugin::Manager::read().unwrap();
+				let (id, result) = {
+					let mut chunk = arc_chunk.write().unwrap();
+					let offset = offset_of(&broken_block.target);
+					let id = chunk.chunk.block_ids().get(&offset).cloned();
+					let result =
+						chunk.apply_block_change(&plugins, broken_block.instigator, offset, None);
+					(id, result)
+				};
+				match (result, id) {
+					// The entity which broke the block picks it up, so it can be placed again
+					// (unless a plugin replaced the block instead of letting it be broken).
+					(Ok(None), Some(id)) => {
+						let mut world = arc_world.write().unwrap();
+						if let Ok(mut inventory) = world.get::<&mut Inventory>(broken_block.entity)
+						{
+							let _ = inventory.insert(Item::Block(id), 1);
+						}
+					}
+					(Ok(_), _) => {}
+					(Err(denied), _) => {
+						log::debug!(target: LOG, "Breaking {} was denied: {}", broken_block.target, denied);
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod progress {
+	use super::*;
+	use crate::{entity::component::BREAK_DURATION, server::network::DEFAULT_WORLD};
+
+	#[test]
+	fn accumulates_at_rate_and_breaks_once() {
+		let target = block::Point::new(Point3::new(0, 0, 0), Point3::new(1, 2, 3));
+		let mut world = entity::World::new();
+		let entity = world.spawn((BlockInteraction::new(target),));
+		// Four ticks to break the block.
+		let step = BREAK_DURATION / 4;
+
+		for tick in 1..4 {
+			let broken = advance_interactions(&mut world, step, |_, _| true);
+			assert!(broken.is_empty());
+			let interaction = world.get::<&BlockInteraction>(entity).unwrap();
+			assert!((interaction.progress() - tick as f32 * 0.25).abs() < 1e-5);
+		}
+
+		let broken = advance_interactions(&mut world, step, |_, _| true);
+		assert_eq!(
+			broken,
+			vec![BrokenBlock {
+				entity,
+				instigator: None,
+				world: DEFAULT_WORLD.to_owned(),
+				target,
+			}]
+		);
+		assert!(world.get::<&BlockInteraction>(entity).is_err());
+		assert!(advance_interactions(&mut world, step, |_, _| true).is_empty());
+	}
+
+	#[test]
+	fn cancelled_when_target_cannot_be_broken() {
+		let target = block::Point::new(Point3::new(0, 0, 0), Point3::new(1, 2, 3));
+		let mut world = entity::World::new();
+		let entity = world.spawn((BlockInteraction::new(target),));
+		assert!(advance_interactions(&mut world, BREAK_DURATION, |_, _| false).is_empty());
+		assert!(world.get::<&BlockInteraction>(entity).is_err());
+	}
+
+	#[test]
+	fn broken_in_the_world_of_the_entity() {
+		use crate::entity::component::chunk::TicketOwner;
+		let target = block::Point::new(Point3::new(0, 0, 0), Point3::new(1, 2, 3));
+		let mut world = entity::World::new();
+		let mut ticket_owner = TicketOwner::default();
+		ticket_owner.set_world("arena", None);
+		let entity = world.spawn((BlockInteraction::new(target), ticket_owner));
+		let broken = advance_interactions(&mut world, BREAK_DURATION, |world, _| world == "arena");
+		assert_eq!(
+			broken,
+			vec![BrokenBlock {
+				entity,
+				instigator: None,
+				world: "arena".to_owned(),
+				target,
+			}]
+		);
+	}
+
+	#[test]
+	fn completed_interaction_does_not_advance() {
+		let target = block::Point::new(Point3::new(0, 0, 0), Point3::new(0, 0, 0));
+		let mut interaction = BlockInteraction::new(target);
+		assert!(interaction.advance(BREAK_DURATION * 2));
+		assert_eq!(interaction.progress(), 1.0);
+		assert!(!interaction.advance(BREAK_DURATION));
+	}
+}
@@ -0,0 +1,49 @@
+use crate::entity::{self, component, ArcLockEntityWorld, WorldQuery};
+use engine::EngineSystem;
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Instant,
+};
+
+type Query<'c> = (
+	&'c component::physics::linear::Position,
+	&'c mut component::physics::linear::InterpolatedPosition,
+);
+
+/// Client system which moves the rendered position of replicated entities
+/// smoothly between the positions replicated by the server.
+pub struct InterpolatePositions {
+	world: Weak<RwLock<entity::World>>,
+}
+
+impl InterpolatePositions {
+	pub fn new(world: &ArcLockEntityWorld) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+}
+
+impl EngineSystem for InterpolatePositions {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!("subsystem:interpolate_positions");
+
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		let now = Instant::now();
+		arc_world.for_each_with_mut::<Query, _>(|_entity, (position, interpolated)| {
+			// Any change to the authoritative position is a new snapshot from the server.
+			let world_position = position.world_position();
+			if world_position != interpolated.latest() {
+				interpolated.push_snapshot(world_position, now);
+			}
+			interpolated.interpolate(now);
+		});
+	}
+}
@@ -1,14 +1,15 @@
 use crate::{
 	app::state,
-	common::network::connection,
-	common::network::Storage,
-	entity::{self},
+	common::account,
+	common::network::{client_left, connection, Broadcast, Storage},
+	entity::{self, component},
 };
 use engine::channels::broadcast::BusReader;
 use engine::{Engine, EngineSystem};
 use std::{
 	collections::HashSet,
 	net::SocketAddr,
+	path::{Path, PathBuf},
 	sync::{Arc, RwLock, Weak},
 };
 
@@ -17,13 +18,19 @@ static LOG: &'static str = "subsystem:OwnedByConnection";
 /// System run on (integrated or dedicated) servers to
 /// remove entities from the world when they are owned by
 /// a connection which gets dropped (user disconnects).
+/// The remaining clients are notified (via [`client_left`]) of any players whose entities were removed.
+/// Entities with a [`persistent id`](component::PersistentId) are saved before they are removed,
+/// so players continue from where they left off when they rejoin.
 ///
 /// This does not handle updating the [`entity-world`](entity::World)
 /// when the application leaves the [`InGame`](state::State::InGame) state.
 /// See [`entity::add_state_listener`](entity::add_state_listener) for that functionality.
 pub struct OwnedByConnection {
 	world: Weak<RwLock<entity::World>>,
+	connection_list: Weak<RwLock<connection::List>>,
 	receiver: BusReader<connection::Event>,
+	/// The directory the entities of disconnected connections are saved to.
+	entities_dir: Option<PathBuf>,
 }
 
 impl OwnedByConnection {
@@ -57,14 +64,18 @@ impl OwnedByConnection {
 				log::info!(target: LOG, "Initializing");
 
 				let world = callback_world.clone();
-				let receiver = match callback_storage.upgrade() {
+				let (connection_list, receiver, entities_dir) = match callback_storage.upgrade() {
 					Some(arc_storage) => {
-						let arc_connection_list = {
+						let (arc_connection_list, entities_dir) = {
 							let storage = arc_storage.read().unwrap();
-							storage.connection_list().clone()
+							let entities_dir = storage
+								.server()
+								.as_ref()
+								.map(|server| server.read().unwrap().get_entities_dir_path());
+							(storage.connection_list().clone(), entities_dir)
 						};
-						let mut connection_list = arc_connection_list.write().unwrap();
-						connection_list.add_recv()
+						let receiver = arc_connection_list.write().unwrap().add_recv();
+						(Arc::downgrade(&arc_connection_list), receiver, entities_dir)
 					}
 					None => {
 						log::error!(target: LOG, "Failed to find storage");
@@ -72,7 +83,12 @@ impl OwnedByConnection {
 					}
 				};
 
-				let arc_self = Arc::new(RwLock::new(Self { world, receiver }));
+				let arc_self = Arc::new(RwLock::new(Self {
+					world,
+					connection_list,
+					receiver,
+					entities_dir,
+				}));
 
 				if let Ok(mut engine) = Engine::get().write() {
 					engine.add_weak_system(Arc::downgrade(&arc_self));
@@ -92,16 +108,28 @@ impl EngineSystem for OwnedByConnection {
 			return;
 		}
 
-		let entities = self.gather_owned_entities(disconnected);
-		if entities.is_empty() {
-			return;
-		}
+		// The world can already be gone if the server is shutting down
+		// (e.g. the host of an integrated server disconnected), in which case there is nothing to clean up.
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		let departed_accounts = {
+			let mut world = arc_world.write().unwrap();
+			if let Some(entities_dir) = self.entities_dir.as_ref() {
+				save_owned_entities(&world, &disconnected, entities_dir);
+			}
+			despawn_owned_entities(&mut world, &disconnected)
+		};
 
-		self.remove_entities(entities);
+		self.broadcast_departures(departed_accounts);
 	}
 }
 
-type QueryBundle<'c> = hecs::PreparedQuery<&'c entity::component::OwnedByConnection>;
+type QueryBundle<'c> = hecs::PreparedQuery<(
+	&'c component::OwnedByConnection,
+	Option<&'c component::OwnedByAccount>,
+)>;
 
 impl OwnedByConnection {
 	#[profiling::function]
@@ -131,48 +159,172 @@ impl OwnedByConnection {
 		dropped_connections
 	}
 
+	/// Notifies all remaining connections that the players with the provided accounts have left.
 	#[profiling::function]
-	fn gather_owned_entities(
-		&self,
-		owners: HashSet<SocketAddr>,
-	) -> Vec<(hecs::Entity, SocketAddr)> {
-		let mut entities = Vec::new();
-		let mut query_bundle = QueryBundle::new();
-		let arc_world = self.world.upgrade().unwrap();
-		let world = arc_world.read().unwrap();
-		for (entity, net_owner) in query_bundle.query(&world).iter() {
-			let address = *net_owner.address();
-			if owners.contains(&address) {
-				entities.push((entity, address));
-			}
+	fn broadcast_departures(&self, accounts: Vec<account::Id>) {
+		let connection_list = match self.connection_list.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		for account_id in accounts.into_iter() {
+			Broadcast::<client_left::Sender>::new(connection_list.clone())
+				.with_on_established(move |client_left: client_left::Sender| {
+					let account_id = account_id.clone();
+					Box::pin(async move {
+						client_left.send(account_id).await?;
+						Ok(())
+					})
+				})
+				.open();
 		}
-		entities
 	}
+}
 
-	#[profiling::function]
-	fn remove_entities(&self, entities: Vec<(hecs::Entity, SocketAddr)>) {
-		let arc_world = self.world.upgrade().unwrap();
-		let mut world = arc_world.write().unwrap();
-		for (entity, address) in entities.into_iter() {
-			match world.despawn(entity) {
-				Ok(_) => {
-					log::trace!(
-						target: LOG,
-						"Successfully despawned entity({}) because its owner({}) disconnected.",
-						entity.id(),
-						address
-					);
-				}
-				Err(err) => {
-					log::error!(
-						target: LOG,
-						"Failed to despawn entity({}) when its owner({}) disconnected, {:?}",
-						entity.id(),
-						address,
-						err
-					);
+/// Saves every entity with a persistent id which is owned by one of the provided connection addresses.
+#[profiling::function]
+fn save_owned_entities(world: &entity::World, owners: &HashSet<SocketAddr>, dir: &Path) {
+	let registry = component::Registry::read();
+	for (entity, net_owner) in world.query::<&component::OwnedByConnection>().iter() {
+		if !owners.contains(net_owner.address()) {
+			continue;
+		}
+		let entity_ref = match world.entity(entity) {
+			Ok(entity_ref) => entity_ref,
+			Err(_) => continue,
+		};
+		if let Err(err) = entity::PersistentIds::save(&registry, &entity_ref, dir) {
+			log::error!(
+				target: LOG,
+				"Failed to save entity({}) when its owner({}) disconnected, {:?}",
+				entity.id(),
+				net_owner.address(),
+				err
+			);
+		}
+	}
+}
+
+/// Despawns every entity owned by one of the provided connection addresses (and the cameras of spectating connections),
+/// returning the accounts of the players whose entities were despawned.
+#[profiling::function]
+fn despawn_owned_entities(
+	world: &mut entity::World,
+	owners: &HashSet<SocketAddr>,
+) -> Vec<account::Id> {
+	let mut entities = Vec::new();
+	let mut query_bundle = QueryBundle::new();
+	for (entity, (net_owner, account_owner)) in query_bundle.query_mut(world) {
+		let address = *net_owner.address();
+		if owners.contains(&address) {
+			let account_id = account_owner.map(|owner| owner.id().clone());
+			entities.push((entity, address, account_id));
+		}
+	}
+	// Spectators did not join as players, so they are not included in the departed accounts.
+	for (entity, spectator) in world.query_mut::<&component::Spectator>() {
+		if owners.contains(spectator.address()) {
+			entities.push((entity, *spectator.address(), None));
+		}
+	}
+
+	let mut departed_accounts = Vec::new();
+	for (entity, address, account_id) in entities.into_iter() {
+		match world.despawn(entity) {
+			Ok(_) => {
+				log::trace!(
+					target: LOG,
+					"Successfully despawned entity({}) because its owner({}) disconnected.",
+					entity.id(),
+					address
+				);
+				if let Some(account_id) = account_id {
+					departed_accounts.push(account_id);
 				}
 			}
+			Err(err) => {
+				log::error!(
+					target: LOG,
+					"Failed to despawn entity({}) when its owner({}) disconnected, {:?}",
+					entity.id(),
+					address,
+					err
+				);
+			}
 		}
 	}
+	departed_accounts
+}
+
+#[cfg(test)]
+mod despawn {
+	use super::*;
+	use component::{OwnedByAccount, OwnedByConnection};
+
+	fn address(port: u16) -> SocketAddr {
+		SocketAddr::from(([127, 0, 0, 1], port))
+	}
+
+	#[test]
+	fn dropped_connection_despawns_owned_entities() {
+		let mut world = entity::World::new();
+		let departing = world.spawn((
+			OwnedByConnection::new(address(1000)),
+			OwnedByAccount::new("departing".to_owned()),
+		));
+		let remaining = world.spawn((
+			OwnedByConnection::new(address(2000)),
+			OwnedByAccount::new("remaining".to_owned()),
+		));
+
+		let departed = despawn_owned_entities(&mut world, &HashSet::from([address(1000)]));
+
+		// Only the account of the dropped connection is broadcast to the remaining clients.
+		assert_eq!(departed, vec!["departing".to_owned()]);
+		assert!(!world.contains(departing));
+		assert!(world.contains(remaining));
+	}
+
+	#[test]
+	fn unowned_by_account_is_despawned_silently() {
+		let mut world = entity::World::new();
+		let entity = world.spawn((OwnedByConnection::new(address(1000)),));
+		let departed = despawn_owned_entities(&mut world, &HashSet::from([address(1000)]));
+		assert!(departed.is_empty());
+		assert!(!world.contains(entity));
+	}
+
+	#[test]
+	fn owned_entities_are_saved() {
+		{
+			let mut registry = component::Registry::write();
+			registry.register::<OwnedByConnection>();
+			registry.register::<component::PersistentId>();
+		}
+		let mut dir = std::env::temp_dir();
+		dir.push(format!("crystal-sphinx-departed-{}", uuid::Uuid::new_v4()));
+		let mut world = entity::World::new();
+		let departing = component::PersistentId::from(1);
+		let remaining = component::PersistentId::from(2);
+		world.spawn((OwnedByConnection::new(address(1000)), departing));
+		world.spawn((OwnedByConnection::new(address(2000)), remaining));
+
+		save_owned_entities(&world, &HashSet::from([address(1000)]), &dir);
+		let saved = entity::PersistentIds::read_saved(&dir, &departing).unwrap();
+		assert!(saved.is_some());
+		assert!(entity::PersistentIds::read_saved(&dir, &remaining)
+			.unwrap()
+			.is_none());
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn spectator_is_despawned_silently() {
+		let mut world = entity::World::new();
+		let entity = world.spawn((component::Spectator::new(address(1000)),));
+		let remaining = world.spawn((component::Spectator::new(address(2000)),));
+		let departed = despawn_owned_entities(&mut world, &HashSet::from([address(1000)]));
+		assert!(departed.is_empty());
+		assert!(!world.contains(entity));
+		assert!(world.contains(remaining));
+	}
 }
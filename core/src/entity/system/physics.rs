@@ -1,23 +1,96 @@
-use crate::entity::{self, component, ArcLockEntityWorld};
-use engine::EngineSystem;
+use crate::{
+	common::{network::mode, physics},
+	entity::{self, component, ArcLockEntityWorld, WorldQuery},
+	graphics::voxel::instance,
+	server::world::Registry,
+};
+use engine::{math::nalgebra::Vector3, EngineSystem};
 use std::sync::{Arc, RwLock, Weak};
 
-type QueryBundle<'c> = hecs::PreparedQuery<(
-	&'c mut component::physics::linear::Position,
-	&'c component::physics::linear::Velocity,
-)>;
+/// Entities which are [`frozen`](component::physics::Frozen) are not stepped,
+/// nor are children, which are moved with their [`parent`](component::Parent) instead.
+type Query<'c> = hecs::Without<
+	hecs::Without<
+		hecs::Without<
+			(
+				&'c mut component::physics::linear::Position,
+				&'c component::physics::linear::Velocity,
+			),
+			&'c component::physics::Frozen,
+		>,
+		&'c component::Parent,
+	>,
+	&'c component::physics::Collider,
+>;
+
+/// Entities with a [`Collider`](component::physics::Collider), which are stopped by the voxels of the world they are in.
+type CollidingQuery<'c> = hecs::Without<
+	hecs::Without<
+		(
+			&'c mut component::physics::linear::Position,
+			&'c mut component::physics::linear::Velocity,
+			&'c component::physics::Collider,
+			Option<&'c component::chunk::TicketOwner>,
+		),
+		&'c component::physics::Frozen,
+	>,
+	&'c component::Parent,
+>;
+
+/// [`Dynamic`](component::physics::Dynamic) bodies which are stepped, which are accelerated by gravity.
+type DynamicQuery<'c> = hecs::Without<
+	hecs::Without<
+		(
+			&'c mut component::physics::linear::Velocity,
+			&'c mut component::physics::Dynamic,
+		),
+		&'c component::physics::Frozen,
+	>,
+	&'c component::Parent,
+>;
+
+/// Players whose movement input has been acknowledged by the server.
+type AcknowledgedQuery<'c> = hecs::Without<
+	(
+		&'c component::physics::linear::Velocity,
+		&'c mut component::physics::linear::AcknowledgedInput,
+	),
+	&'c component::physics::Frozen,
+>;
 
 pub struct Physics {
 	world: Weak<RwLock<entity::World>>,
+	settings: physics::ArcLockSettings,
+	terrain: physics::ArcLockTerrain,
+	/// The acceleration of gravity during the last step,
+	/// so sleeping bodies can be woken when it changes.
+	acceleration: Vector3<f32>,
 }
 
 impl Physics {
 	pub fn new(world: &ArcLockEntityWorld) -> Self {
+		let settings = physics::ArcLockSettings::default();
+		let acceleration = settings.read().unwrap().acceleration();
 		Self {
 			world: Arc::downgrade(&world),
+			settings,
+			terrain: physics::ArcLockTerrain::default(),
+			acceleration,
 		}
 	}
 
+	/// Uses settings which can be changed while the simulation is running (e.g. by the world which is loaded).
+	pub fn with_settings(mut self, settings: &physics::ArcLockSettings) -> Self {
+		self.settings = settings.clone();
+		self
+	}
+
+	/// Collides entities with the voxels of the world each is in (if it is loaded).
+	pub fn with_terrain(mut self, terrain: &physics::ArcLockTerrain) -> Self {
+		self.terrain = terrain.clone();
+		self
+	}
+
 	pub fn arclocked(self) -> Arc<RwLock<Self>> {
 		Arc::new(RwLock::new(self))
 	}
@@ -31,13 +104,306 @@ impl EngineSystem for Physics {
 			Some(arc) => arc,
 			None => return,
 		};
-		let mut world = arc_world.write().unwrap();
-		let mut query_bundle = QueryBundle::new();
-		for (_entity, (position, velocity)) in query_bundle.query_mut(&mut world) {
+		let acceleration = self.settings.read().unwrap().acceleration();
+		// Bodies which fell asleep while there was no gravity would never respond to new gravity.
+		let wake_all = acceleration != self.acceleration;
+		self.acceleration = acceleration;
+		let terrain = self.terrain.read().unwrap().clone();
+		// Clients which are not also the server stop colliders against the chunks they have been sent,
+		// so the local player is predicted to stop where the server stops it.
+		let local_voxels: Option<Arc<dyn physics::Voxels>> = match terrain.is_empty() {
+			true => {
+				instance::Buffer::active_local().map(|buffer| buffer as Arc<dyn physics::Voxels>)
+			}
+			false => None,
+		};
+		// Without the voxels of the world, bodies would fall straight through it.
+		// Clients don't know the voxels of the whole world, so they leave falling to the server and are told where bodies landed.
+		if !terrain.is_empty() {
+			arc_world.for_each_with_mut::<DynamicQuery, _>(|_entity, (velocity, dynamic)| {
+				let is_moving = velocity.magnitude_squared() > 0.0;
+				if dynamic.is_asleep() && !is_moving && !wake_all {
+					return;
+				}
+				**velocity += acceleration * delta_time.as_secs_f32();
+				let is_at_rest = velocity.magnitude_squared() == 0.0;
+				dynamic.set_asleep(is_at_rest && acceleration.magnitude_squared() == 0.0);
+			});
+		}
+		arc_world.for_each_with_mut::<Query, _>(|_entity, (position, velocity)| {
 			let velocity_vec = **velocity;
 			if velocity_vec.magnitude_squared() > 0.0 {
 				*position += velocity_vec * delta_time.as_secs_f32();
 			}
+		});
+		arc_world.for_each_with_mut::<CollidingQuery, _>(
+			|_entity, (position, velocity, collider, ticket_owner)| {
+				let velocity_vec = **velocity;
+				if velocity_vec.magnitude_squared() == 0.0 {
+					return;
+				}
+				let displacement = velocity_vec * delta_time.as_secs_f32();
+				let voxels = match terrain
+					.get(Registry::world_of(ticket_owner))
+					.or(local_voxels.as_ref())
+				{
+					Some(voxels) => voxels,
+					None => {
+						*position += displacement;
+						return;
+					}
+				};
+				let (moved_to, stopped) = physics::move_and_collide(
+					voxels.as_ref(),
+					position.world_position(),
+					collider.size(),
+					collider.groups(),
+					displacement.cast::<f64>(),
+				);
+				position.set_world_position(moved_to);
+				// Only falling (or rising) is stopped. Horizontal velocity is kept,
+				// so bodies slide along walls and keep moving once they are past them.
+				if stopped.y {
+					velocity.y = 0.0;
+				}
+			},
+		);
+		component::Parent::update_children(&mut arc_world.write().unwrap());
+		// The server tells each client how long it has moved them with their latest input,
+		// so the client can predict where they are from when the server's position was taken.
+		if mode::get().contains(mode::Kind::Server) {
+			arc_world.for_each_with_mut::<AcknowledgedQuery, _>(
+				|_entity, (velocity, acknowledged)| {
+					if velocity.magnitude_squared() > 0.0 {
+						acknowledged.advance(delta_time);
+					}
+				},
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod frozen {
+	use super::*;
+	use component::physics::{
+		linear::{Position, Velocity},
+		Frozen,
+	};
+	use engine::math::nalgebra::Point3;
+	use std::time::Duration;
+
+	fn position_of(world: &ArcLockEntityWorld, entity: hecs::Entity) -> Point3<f64> {
+		let world = world.read().unwrap();
+		let position = world.get::<&Position>(entity).unwrap();
+		position.world_position()
+	}
+
+	#[test]
+	fn frozen_body_does_not_move() {
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let mut velocity = Velocity::default();
+		*velocity = [0.0, -2.0, 0.0].into();
+		let (frozen, moving) = {
+			let mut world = arc_world.write().unwrap();
+			(
+				world.spawn((Position::default(), velocity, Frozen)),
+				world.spawn((Position::default(), velocity)),
+			)
+		};
+		let start = position_of(&arc_world, frozen);
+		let mut physics = Physics::new(&arc_world);
+
+		physics.update(Duration::from_secs(1), false);
+		assert_eq!(position_of(&arc_world, frozen), start);
+		assert_eq!(position_of(&arc_world, moving).y, start.y - 2.0);
+
+		// Once unfrozen, the body moves by a single step (no time spent frozen is made up for).
+		arc_world
+			.write()
+			.unwrap()
+			.remove_one::<Frozen>(frozen)
+			.unwrap();
+		physics.update(Duration::from_secs(1), false);
+		assert_eq!(position_of(&arc_world, frozen).y, start.y - 2.0);
+		assert_eq!(position_of(&arc_world, moving).y, start.y - 4.0);
+	}
+}
+
+#[cfg(test)]
+mod gravity {
+	use super::*;
+	use crate::{block, common::world::chunk, server::network::DEFAULT_WORLD};
+	use component::physics::{
+		linear::{Position, Velocity},
+		Collider, Dynamic,
+	};
+	use engine::math::nalgebra::Point3;
+	use std::time::Duration;
+
+	fn position_of(world: &ArcLockEntityWorld, entity: hecs::Entity) -> Point3<f64> {
+		let world = world.read().unwrap();
+		let position = world.get::<&Position>(entity).unwrap();
+		position.world_position()
+	}
+
+	/// Returns a dynamic body which has just been dropped (is not moving).
+	fn drop_body(world: &ArcLockEntityWorld) -> hecs::Entity {
+		let mut world = world.write().unwrap();
+		world.spawn((Position::default(), Velocity::default(), Dynamic::default()))
+	}
+
+	/// A world where the voxels below a height are full blocks, and every chunk is loaded.
+	struct Floor(i64);
+
+	impl physics::Voxels for Floor {
+		fn colliders_at(&self, point: &block::Point) -> Option<Vec<block::Collider>> {
+			let y = point.chunk().y * chunk::SIZE_I.y as i64 + point.offset().y as i64;
+			Some(match y < self.0 {
+				true => block::Shape::cube().colliders_at(point),
+				false => Vec::new(),
+			})
+		}
+	}
+
+	fn terrain(floor: i64) -> physics::ArcLockTerrain {
+		let mut terrain = physics::Terrain::default();
+		terrain.insert(DEFAULT_WORLD, Arc::new(Floor(floor)));
+		Arc::new(RwLock::new(terrain))
+	}
+
+	/// The world has no floor within the distance bodies fall in these tests.
+	fn open_air() -> physics::ArcLockTerrain {
+		terrain(i64::MIN)
+	}
+
+	#[test]
+	fn zero_gravity_leaves_body_stationary() {
+		let zero_gravity = Arc::new(RwLock::new(
+			physics::Settings::default().with_gravity(Vector3::zeros()),
+		));
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let body = drop_body(&arc_world);
+		let start = position_of(&arc_world, body);
+		Physics::new(&arc_world)
+			.with_settings(&zero_gravity)
+			.with_terrain(&open_air())
+			.update(Duration::from_secs(1), false);
+		assert_eq!(position_of(&arc_world, body), start);
+
+		// The same body falls with the default gravity.
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let body = drop_body(&arc_world);
+		Physics::new(&arc_world)
+			.with_terrain(&open_air())
+			.update(Duration::from_secs(1), false);
+		let fallen = start.y - position_of(&arc_world, body).y;
+		assert!((fallen - 9.81).abs() < 1e-4, "fell {}", fallen);
+	}
+
+	#[test]
+	fn changing_gravity_wakes_sleeping_bodies() {
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let body = drop_body(&arc_world);
+		let start = position_of(&arc_world, body);
+		let settings = Arc::new(RwLock::new(
+			physics::Settings::default().with_gravity(Vector3::zeros()),
+		));
+		let mut physics = Physics::new(&arc_world)
+			.with_settings(&settings)
+			.with_terrain(&open_air());
+
+		physics.update(Duration::from_secs(1), false);
+		let is_asleep = |world: &ArcLockEntityWorld| {
+			world
+				.read()
+				.unwrap()
+				.get::<&Dynamic>(body)
+				.unwrap()
+				.is_asleep()
+		};
+		assert!(is_asleep(&arc_world));
+
+		// Two blocks per meter doubles the distance fallen.
+		*settings.write().unwrap() = physics::Settings::default()
+			.with_gravity(Vector3::new(0.0, -1.0, 0.0))
+			.with_units_per_meter(2.0);
+		physics.update(Duration::from_secs(1), false);
+		assert!(!is_asleep(&arc_world));
+		assert_eq!(position_of(&arc_world, body).y, start.y - 2.0);
+	}
+
+	#[test]
+	fn bodies_land_on_voxels() {
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let body = {
+			let mut position = Position::default();
+			position.set_world_position(Point3::new(0.5, 3.0, 0.5));
+			let mut world = arc_world.write().unwrap();
+			world.spawn((
+				position,
+				Velocity::default(),
+				Dynamic::default(),
+				Collider::player(),
+			))
+		};
+		let mut physics = Physics::new(&arc_world).with_terrain(&terrain(0));
+		for _ in 0..10 {
+			physics.update(Duration::from_millis(100), false);
+		}
+		// Having fallen 3 blocks, the body rests on top of the voxels instead of falling through them.
+		assert_eq!(position_of(&arc_world, body), Point3::new(0.5, 0.0, 0.5));
+		let world = arc_world.read().unwrap();
+		assert_eq!(world.get::<&Velocity>(body).unwrap().y, 0.0);
+	}
+
+	#[test]
+	fn clients_leave_falling_to_the_server() {
+		// Without the voxels of the world, gravity is not applied.
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let body = drop_body(&arc_world);
+		let start = position_of(&arc_world, body);
+		Physics::new(&arc_world).update(Duration::from_secs(1), false);
+		assert_eq!(position_of(&arc_world, body), start);
+	}
+}
+
+#[cfg(test)]
+mod parented {
+	use super::*;
+	use component::{
+		physics::linear::{Position, Velocity},
+		Parent,
+	};
+	use engine::math::nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion};
+	use std::time::Duration;
+
+	#[test]
+	fn child_follows_moving_parent() {
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let mut velocity = Velocity::default();
+		*velocity = [2.0, 0.0, 0.0].into();
+		let (parent, child) = {
+			let mut world = arc_world.write().unwrap();
+			let parent = world.spawn((Position::default(), velocity));
+			// The child's own velocity is ignored while it is attached.
+			let child = world.spawn((Position::default(), velocity));
+			let offset =
+				Isometry3::from_parts(Translation3::new(0.0, 1.0, 0.0), UnitQuaternion::identity());
+			Parent::attach(&mut world, child, parent, offset).unwrap();
+			(parent, child)
+		};
+		let mut physics = Physics::new(&arc_world);
+
+		for _ in 0..3 {
+			physics.update(Duration::from_secs(1), false);
+			let world = arc_world.read().unwrap();
+			let parent_position = world.get::<&Position>(parent).unwrap().world_position();
+			let child_position = world.get::<&Position>(child).unwrap().world_position();
+			assert_eq!(
+				child_position,
+				parent_position + Point3::new(0.0, 1.0, 0.0).coords
+			);
 		}
 	}
 }
@@ -0,0 +1,192 @@
+use crate::{
+	block,
+	entity::{
+		self,
+		component::{chunk::TicketOwner, BlockPlacement, Inventory, OwnedByConnection},
+		ArcLockEntityWorld,
+	},
+	plugin,
+	server::world::{chunk, edit::Chunks, place, Registry},
+};
+use engine::EngineSystem;
+use std::sync::{Arc, RwLock, Weak};
+
+static LOG: &'static str = "subsystem:place-blocks";
+
+/// Server system which places a block for each [`placement request`](BlockPlacement),
+/// using (and taking one of) the [`first block`](Inventory::first_block) in the inventory of the entity which asked.
+///
+/// Blocks are placed in the world the entity which asked is in.
+pub struct PlaceBlocks {
+	world: Weak<RwLock<entity::World>>,
+	worlds: Registry,
+}
+
+impl PlaceBlocks {
+	pub fn new(world: &ArcLockEntityWorld, worlds: &Registry) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+			worlds: worlds.clone(),
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+}
+
+/// Places a block for every placement request in the world, removing each request.
+/// `find_chunk` returns the chunk containing a block in the named world, if it is loaded.
+///
+/// Returns the points blocks were placed at.
+pub fn place_requested<F>(
+	world: &mut entity::World,
+	plugins: &plugin::Manager,
+	find_chunk: F,
+) -> Vec<block::Point>
+where
+	F: Fn(&str, &block::Point) -> Option<chunk::ArcLock>,
+{
+	let requests = world
+		.query_mut::<(
+			&BlockPlacement,
+			Option<&Inventory>,
+			Option<&OwnedByConnection>,
+			Option<&TicketOwner>,
+		)>()
+		.into_iter()
+		.map(|(entity, (request, inventory, owner, ticket_owner))| {
+			let block = inventory.map(|inventory| inventory.first_block()).flatten();
+			(
+				entity,
+				*request.hit(),
+				block,
+				owner.map(|owner| *owner.address()),
+				Registry::world_of(ticket_owner).to_owned(),
+			)
+		})
+		.collect::<Vec<_>>();
+
+	let mut placed = Vec::new();
+	for (entity, hit, block, instigator, world_name) in requests.into_iter() {
+		let _ = world.remove_one::<BlockPlacement>(entity);
+		let (slot, id) = match block {
+			Some(block) => block,
+			None => {
+				log::debug!(target: LOG, "Ignoring placement by {:?}, which has no blocks", entity);
+				continue;
+			}
+		};
+		let target = hit.point + hit.face.direction();
+		let chunks = Chunks::from_loaded(find_chunk(&world_name, &target).into_iter().collect());
+		match place::place(&chunks, world, plugins, instigator, &hit, id) {
+			Ok(point) => {
+				if let Ok(mut inventory) = world.get::<&mut Inventory>(entity) {
+					let _ = inventory.remove(slot, 1);
+				}
+				placed.push(point);
+			}
+			Err(err) => log::debug!(target: LOG, "{}", err),
+		}
+	}
+	placed
+}
+
+impl EngineSystem for PlaceBlocks {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!(LOG);
+		let arc_world = match self.world.upgrade() {
+			Some(world) => world,
+			None => return,
+		};
+		let chunk_caches = self.worlds.chunk_caches();
+		let mut world = arc_world.write().unwrap();
+		let plugins = plugin::Manager::read().unwrap();
+		let _ = place_requested(&mut world, &plugins, |world_name, point| {
+			let chunk_cache = chunk_caches.get(world_name)?.read().unwrap();
+			chunk_cache
+				.find(point.chunk())
+				.map(|weak| weak.upgrade())
+				.flatten()
+		});
+	}
+}
+
+#[cfg(test)]
+mod requests {
+	use super::*;
+	use crate::{
+		common::world::{chunk::Chunk as CommonChunk, raycast::RaycastHit},
+		entity::component::{Item, Stack},
+		graphics::voxel::Face,
+		server::world::chunk::{Chunk, Level},
+	};
+	use engine::math::nalgebra::Point3;
+	use std::path::PathBuf;
+
+	fn loaded_chunk() -> chunk::ArcLock {
+		let mut chunk = Chunk::new(
+			PathBuf::new(),
+			CommonChunk::new(Point3::origin()),
+			Level::Loaded,
+		);
+		chunk.set_block_id(Point3::new(4, 4, 4), Some(1));
+		let _ = chunk.take_block_changes();
+		Arc::new(RwLock::new(chunk))
+	}
+
+	fn request() -> BlockPlacement {
+		BlockPlacement::new(RaycastHit {
+			point: block::Point::new(Point3::origin(), Point3::new(4, 4, 4)),
+			face: Face::Up,
+		})
+	}
+
+	#[test]
+	fn places_and_takes_from_inventory() {
+		let arc_chunk = loaded_chunk();
+		let mut world = entity::World::new();
+		let mut inventory = Inventory::new(2);
+		inventory.set(
+			1,
+			Some(Stack {
+				item: Item::Block(2),
+				count: 3,
+			}),
+		);
+		let entity = world.spawn((request(), inventory));
+
+		let placed = place_requested(&mut world, &plugin::Manager::default(), |_, _| {
+			Some(arc_chunk.clone())
+		});
+		let above = block::Point::new(Point3::origin(), Point3::new(4, 5, 4));
+		assert_eq!(placed, vec![above]);
+		let chunk = arc_chunk.read().unwrap();
+		assert_eq!(chunk.chunk.block_ids().get(&Point3::new(4, 5, 4)), Some(&2));
+		assert_eq!(
+			world
+				.get::<&Inventory>(entity)
+				.unwrap()
+				.get(1)
+				.unwrap()
+				.count,
+			2
+		);
+		assert!(world.get::<&BlockPlacement>(entity).is_err());
+	}
+
+	#[test]
+	fn nothing_is_placed_without_blocks() {
+		let arc_chunk = loaded_chunk();
+		let mut world = entity::World::new();
+		let entity = world.spawn((request(), Inventory::new(2)));
+
+		let placed = place_requested(&mut world, &plugin::Manager::default(), |_, _| {
+			Some(arc_chunk.clone())
+		});
+		assert!(placed.is_empty());
+		assert!(arc_chunk.write().unwrap().take_block_changes().is_empty());
+		// The request is dropped, instead of being placed once the entity has a block.
+		assert!(world.get::<&BlockPlacement>(entity).is_err());
+	}
+}
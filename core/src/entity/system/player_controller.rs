@@ -1,12 +1,15 @@
 use crate::{
 	app::state::Machine,
+	block,
 	client::network::Storage as ClientStorage,
 	common::network::Storage as CommonStorage,
 	common::{
 		account,
 		network::{mode, move_player},
+		world::raycast,
 	},
 	entity::{self, component},
+	graphics::voxel::instance,
 };
 use chrono::Utc;
 use engine::{
@@ -23,7 +26,14 @@ type QueryBundle<'c> = hecs::PreparedQuery<(
 	&'c component::OwnedByAccount,
 	&'c mut component::physics::linear::Velocity,
 	&'c mut component::Orientation,
-	&'c mut component::network::Replicated,
+	// Spectator cameras are local to the client, and so are not replicated.
+	Option<&'c mut component::network::Replicated>,
+	// Players look at (and break) blocks from their position, while spectator cameras only fly around.
+	Option<&'c component::physics::linear::Position>,
+	// The local player is predicted, while spectator cameras have no server position to reconcile with.
+	Option<&'c mut component::physics::linear::Prediction>,
+	// Players fall under gravity, while spectator cameras fly.
+	Option<&'c component::physics::Dynamic>,
 )>;
 
 enum RotationOrder {
@@ -84,6 +94,12 @@ pub struct PlayerController {
 	look_actions: Vec<LookAction>,
 	move_speed: f32,
 	move_actions: Vec<MoveAction>,
+	break_action: input::action::WeakLockState,
+	/// The block the player is looking at while holding the break action.
+	breaking: Option<block::Point>,
+	place_action: input::action::WeakLockState,
+	/// If the place action was held last update, so a block is only placed once per press.
+	was_place_held: bool,
 }
 
 impl PlayerController {
@@ -122,6 +138,16 @@ impl PlayerController {
 					.active_account()?
 					.id();
 
+				// Spectators are not given a player entity by the server,
+				// so they control a free-flying camera which only exists on the client.
+				if mode::get().contains(mode::Kind::Spectator) {
+					if let Some(arc_world) = callback_world.upgrade() {
+						let mut world = arc_world.write().unwrap();
+						let camera = entity::archetype::spectator::Client::new(account_id.clone());
+						world.spawn(camera.build().build());
+					}
+				}
+
 				let arc_self = Arc::new(RwLock::new(Self::new(
 					callback_world.clone(),
 					account_id,
@@ -181,16 +207,126 @@ impl PlayerController {
 					is_global: true,
 				},
 			],
+			break_action: get_action(crate::input::ACTION_BREAK_BLOCK),
+			breaking: None,
+			place_action: get_action(crate::input::ACTION_PLACE_BLOCK),
+			was_place_held: false,
 		}
 	}
 
 	pub fn arclocked(self) -> Arc<RwLock<Self>> {
 		Arc::new(RwLock::new(self))
 	}
+
+	fn is_break_held(&self) -> bool {
+		match self.break_action.upgrade() {
+			Some(arc_state) => arc_state.read().unwrap().value() > 0.5,
+			None => false,
+		}
+	}
+
+	/// Returns true only for the first update the place action is held.
+	fn take_place_pressed(&mut self) -> bool {
+		let is_held = match self.place_action.upgrade() {
+			Some(arc_state) => arc_state.read().unwrap().value() > 0.5,
+			None => false,
+		};
+		let is_pressed = is_held && !self.was_place_held;
+		self.was_place_held = is_held;
+		is_pressed
+	}
+
+	/// Returns the block the player is looking at (within reach), if any.
+	/// Returns None if the blocks around the player are being updated,
+	/// in which case whatever the player was last looking at should be kept.
+	fn look_at(
+		position: &component::physics::linear::Position,
+		orientation: &UnitQuaternion<f32>,
+	) -> Option<Option<raycast::RaycastHit>> {
+		let arc_buffer = match instance::Buffer::active_local() {
+			Some(arc) => arc,
+			None => return Some(None),
+		};
+		// The instance-update thread can hold the buffer for a number of milliseconds.
+		// Rather than stalling the frame, the caller keeps its last target until the buffer is free.
+		let buffer = arc_buffer.try_lock().ok()?;
+		let forward = orientation * *world::global_forward();
+		let traversal = raycast::traverse(
+			raycast::eye_position(position),
+			forward.cast::<f64>(),
+			raycast::REACH,
+			|point| buffer.is_solid(point),
+		);
+		Some(traversal.hit())
+	}
+
+	/// Finds the block the player is looking at (within reach) while they hold the break action.
+	fn update_breaking(
+		&mut self,
+		is_break_held: bool,
+		position: &component::physics::linear::Position,
+		orientation: &UnitQuaternion<f32>,
+	) {
+		if !is_break_held {
+			self.breaking = None;
+			return;
+		}
+		if let Some(hit) = Self::look_at(position, orientation) {
+			self.breaking = hit.map(|hit| hit.point);
+		}
+	}
+
+	/// Reconciles the predicted position of the local player with the latest position from the server,
+	/// and blends in any small correction from a previous reconciliation.
+	/// Inputs are replayed against the chunks the client has, so the player is stopped by the same blocks as on the server.
+	fn update_predictions(&mut self, delta_time: std::time::Duration) {
+		use crate::common::physics::Voxels;
+		use component::physics::{
+			linear::{AcknowledgedInput, Position, Prediction},
+			Collider,
+		};
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		let local_voxels = instance::Buffer::active_local();
+		let mut world = arc_world.write().unwrap();
+		let query = world.query_mut::<(
+			&component::OwnedByAccount,
+			&mut Position,
+			&mut Prediction,
+			Option<&AcknowledgedInput>,
+			Option<&Collider>,
+		)>();
+		for (_entity, (entity_user, position, prediction, acknowledged, collider)) in query {
+			if *entity_user.id() != self.account_id {
+				continue;
+			}
+			// The physics simulation has moved the player with their latest input since the last update.
+			prediction.advance(delta_time);
+			if let Some(authoritative) = prediction.take_authoritative() {
+				let (sequence, applied_for) = acknowledged
+					.map(|ack| (ack.sequence(), ack.applied_for()))
+					.unwrap_or_default();
+				let collision = match (&local_voxels, collider) {
+					(Some(voxels), Some(collider)) => {
+						Some((voxels.as_ref() as &dyn Voxels, collider))
+					}
+					_ => None,
+				};
+				prediction.reconcile(position, authoritative, sequence, applied_for, collision);
+			}
+			prediction.blend(position, delta_time);
+		}
+	}
 }
 
 impl EngineSystem for PlayerController {
-	fn update(&mut self, _delta_time: std::time::Duration, has_focus: bool) {
+	fn update(&mut self, delta_time: std::time::Duration, has_focus: bool) {
+		// The player keeps moving while the window is not focused,
+		// so the prediction is kept in sync with the server even without input.
+		self.update_predictions(delta_time);
+
 		if !has_focus {
 			return;
 		}
@@ -207,15 +343,25 @@ impl EngineSystem for PlayerController {
 			.iter()
 			.map(|action| action.value())
 			.collect::<Vec<_>>();
+		// Spectators cannot interact with the world.
+		let is_spectator = mode::get().contains(mode::Kind::Spectator);
+		let is_break_held = self.is_break_held() && !is_spectator;
+		let is_place_pressed = self.take_place_pressed() && !is_spectator;
 
 		let arc_world = match self.world.upgrade() {
 			Some(arc) => arc,
 			None => return,
 		};
 		let mut world = arc_world.write().unwrap();
+		// Targets of integrated clients, which share the server's world and so start breaking blocks directly.
+		let mut local_targets = Vec::new();
+		// Placements of integrated clients, which are requested directly for the same reason.
+		let mut local_placements = Vec::new();
 		let mut query_bundle = QueryBundle::new();
-		for (_entity, (entity_user, velocity, orientation, replicated)) in
-			query_bundle.query_mut(&mut world)
+		for (
+			entity,
+			(entity_user, velocity, orientation, replicated, position, prediction, dynamic),
+		) in query_bundle.query_mut(&mut world)
 		{
 			// Only control the entity which is owned by the local player
 			if *entity_user.id() != self.account_id {
@@ -246,8 +392,18 @@ impl EngineSystem for PlayerController {
 			// 2. The relevant components will be authoritatively replicated from the server,
 			//    so there is no risk of client-authority here.
 
-			**velocity = Vector3::new(0.0, 0.0, 0.0);
+			// Dynamic bodies fall under the server's gravity, so only their horizontal movement is controlled
+			// (and they cannot fly).
+			let is_dynamic = dynamic.is_some();
+			let fall_speed = match is_dynamic {
+				true => velocity.y,
+				false => 0.0,
+			};
+			**velocity = Vector3::new(0.0, fall_speed, 0.0);
 			for (move_action, &value) in self.move_actions.iter().zip(move_values.iter()) {
+				if move_action.is_global && is_dynamic {
+					continue;
+				}
 				if value.abs() > std::f32::EPSILON {
 					let mut direction = *move_action.direction;
 					if !move_action.is_global {
@@ -263,7 +419,27 @@ impl EngineSystem for PlayerController {
 				look_action.concat_into(*value, &mut (**orientation));
 			}
 
-			if mode::get() == mode::Kind::Client {
+			let prev_breaking = self.breaking;
+			match position {
+				Some(position) => self.update_breaking(is_break_held, position, &**orientation),
+				None => self.breaking = None,
+			}
+			let has_changed_target = self.breaking != prev_breaking;
+			if has_changed_target && mode::get().contains(mode::Kind::Server) {
+				local_targets.push((entity, self.breaking));
+			}
+			// A press is dropped if the blocks around the player are being updated, and can be pressed again.
+			let placing = match (is_place_pressed, position) {
+				(true, Some(position)) => Self::look_at(position, &**orientation).flatten(),
+				_ => None,
+			};
+			if let Some(hit) = placing {
+				if mode::get().contains(mode::Kind::Server) {
+					local_placements.push((entity, hit));
+				}
+			}
+
+			if mode::is_dedicated_client(mode::get()) {
 				const SIG_VEL_MAGNITUDE: f32 = 0.05;
 				const SIG_ORIENTATION_ANGLE_DIFF: f32 = 0.005;
 
@@ -281,13 +457,24 @@ impl EngineSystem for PlayerController {
 						Some(arc) => arc.is_local(),
 						None => false,
 					};
-					if has_significantly_changed && !is_local {
-						let server_entity = *replicated.get_id_on_server().unwrap();
+					let is_placing = placing.is_some();
+					if (has_significantly_changed || has_changed_target || is_placing) && !is_local
+					{
+						let server_entity = replicated
+							.as_ref()
+							.map(|replicated| *replicated.get_id_on_server().unwrap());
+						let sequence = match prediction {
+							Some(prediction) => prediction.push_input(**velocity),
+							None => 0,
+						};
 						let result = move_player::Datum {
 							timestamp: Utc::now(),
 							server_entity,
 							velocity: **velocity,
 							orientation: **orientation,
+							breaking: self.breaking,
+							placing,
+							sequence,
 						}
 						.send(connection.clone());
 						if let Err(err) = result {
@@ -297,5 +484,16 @@ impl EngineSystem for PlayerController {
 				}
 			}
 		}
+
+		for (entity, target) in local_targets.into_iter() {
+			// Looking at a different block restarts the progress, and releasing the break action cancels it.
+			let _ = world.remove_one::<component::BlockInteraction>(entity);
+			if let Some(target) = target {
+				let _ = world.insert_one(entity, component::BlockInteraction::new(target));
+			}
+		}
+		for (entity, hit) in local_placements.into_iter() {
+			let _ = world.insert_one(entity, component::BlockPlacement::new(hit));
+		}
 	}
 }
@@ -8,11 +8,20 @@ use crate::{
 		component::{self, binary, network},
 		ArcLockEntityWorld,
 	},
-	server::world::chunk::{self, Chunk},
+	server::{
+		network::DEFAULT_WORLD,
+		world::{
+			chunk::{self, Chunk},
+			registry, Registry,
+		},
+	},
 };
 use anyhow::Result;
 use engine::channels::broadcast::BusReader;
-use engine::{math::nalgebra::Point3, Engine, EngineSystem};
+use engine::{
+	math::nalgebra::{Point3, Vector3},
+	Engine, EngineSystem,
+};
 use multimap::MultiMap;
 use socknet::connection::Connection;
 use std::{
@@ -23,22 +32,45 @@ use std::{
 
 static LOG: &'static str = "subsystem:replicator";
 
+mod bandwidth;
+pub use bandwidth::*;
+mod change_versions;
+use change_versions::*;
 mod chunks_by_relevance;
 pub use chunks_by_relevance::*;
 mod handle;
 use handle::*;
 mod instigator;
+pub use instigator::EntityOperation;
 use instigator::*;
+pub mod recording;
 pub mod relevancy;
+mod stats;
+pub use stats::*;
+mod summary;
+pub use summary::*;
 
 /// Replicates entities on the Server to connected Clients while they are net-relevant.
 pub struct Replicator {
 	world: Weak<RwLock<entity::World>>,
-	chunk_cache: chunk::cache::WeakLock,
+	/// The worlds being hosted, whose chunks are sent to the connections in each.
+	/// The runtime limits of each world clamp how far its connections can see,
+	/// and remote clients predict the chunks of their world with its seed until the chunks are sent.
+	worlds: Registry,
 	local_client_chunk_sender: Option<crate::client::world::chunk::OperationSender>,
 	connection_recv: BusReader<connection::Event>,
 	connection_handles: HashMap<SocketAddr, Handle>,
 	entities_relevant: MultiSet<hecs::Entity, SocketAddr>,
+	/// Records everything sent to each connection, if requested by the `-record_replication=<path>` launch argument.
+	recorder: Option<recording::ArcLockRecorder>,
+	/// Replication is sent at the server's tick rate, rather than every frame.
+	timestep: crate::server::tick::FixedTimestep,
+	/// The work done since the last summary was logged.
+	summary: Summary,
+	/// Shares the chunks replicated each tick between connections.
+	bandwidth: Bandwidth,
+	/// When each replicated component last changed, so connections are only sent the components which changed.
+	change_versions: ChangeVersions,
 }
 
 impl Replicator {
@@ -86,13 +118,15 @@ impl Replicator {
 						let mut connection_list = arc_connection_list.write().unwrap();
 						(connection_list.add_recv(), connection_list.all().clone())
 					};
-					let local_client_chunk_sender = match storage.client().as_ref() {
-						Some(arc_client) => {
-							let client = arc_client.read().unwrap();
-							Some(client.chunk_sender().clone())
-						}
-						None => None,
-					};
+					// Only a listen server has a local client, whose connection receives chunks directly.
+					let local_client_chunk_sender =
+						match mode::get().contains(mode::Kind::ListenServer) {
+							true => storage.client().as_ref().map(|arc_client| {
+								let client = arc_client.read().unwrap();
+								client.chunk_sender().clone()
+							}),
+							false => None,
+						};
 					(
 						server,
 						connection_recv,
@@ -101,15 +135,32 @@ impl Replicator {
 					)
 				};
 
-				let chunk_cache = Arc::downgrade(&server.read().unwrap().chunk_cache());
+				let worlds = server.read().unwrap().registry().clone();
 				let world = callback_world.clone();
+				let recorder = match recording::Recorder::requested_path() {
+					Some(path) => match recording::Recorder::create(&path) {
+						Ok(recorder) => Some(recorder.arclocked()),
+						Err(err) => {
+							log::error!(target: LOG, "Failed to start recording replication: {:?}", err);
+							None
+						}
+					},
+					None => None,
+				};
 				let mut replicator = Self {
 					local_client_chunk_sender,
-					chunk_cache,
+					worlds,
 					world,
 					connection_recv,
 					connection_handles: HashMap::new(),
 					entities_relevant: MultiSet::default(),
+					recorder,
+					timestep: crate::server::tick::FixedTimestep::new(
+						crate::server::tick::ticks_per_second(),
+					),
+					summary: Summary::default(),
+					bandwidth: Bandwidth::default(),
+					change_versions: ChangeVersions::default(),
 				};
 				for (address, connection) in connections.into_iter() {
 					if let Err(err) = replicator.add_connection(address, &connection) {
@@ -121,10 +172,93 @@ impl Replicator {
 				if let Ok(mut engine) = Engine::get().write() {
 					engine.add_weak_system(Arc::downgrade(&arc_self));
 				}
+				*Self::active_static() = Some(Arc::downgrade(&arc_self));
 
 				return Ok(Some(arc_self));
 			});
 	}
+
+	fn active_static() -> &'static mut Option<Weak<RwLock<Replicator>>> {
+		static mut ACTIVE: Option<Weak<RwLock<Replicator>>> = None;
+		unsafe { &mut ACTIVE }
+	}
+
+	/// Returns the replicator of the server running in this process, if there is one.
+	/// Used by debug tools to inspect what is being replicated to each connection.
+	pub fn active() -> Option<Arc<RwLock<Self>>> {
+		Self::active_static()
+			.as_ref()
+			.map(|weak| weak.upgrade())
+			.flatten()
+	}
+
+	/// Returns a snapshot of what is being replicated to each connection, ordered by address.
+	pub fn connection_stats(&self) -> Vec<ConnectionStats> {
+		let mut stats = self
+			.connection_handles
+			.iter()
+			.map(|(address, handle)| ConnectionStats {
+				address: *address,
+				state: handle.connection_state(),
+				relevant_entities: self.entities_relevant.count_value(address),
+				pending_chunks: handle.pending_chunks().len(),
+				in_flight_chunks: handle.in_flight_chunks(),
+				pending_bytes: handle.pending_bytes(),
+			})
+			.collect::<Vec<_>>();
+		stats.sort_by_key(|stats| stats.address);
+		stats
+	}
+}
+
+#[cfg(test)]
+impl Replicator {
+	/// Creates a replicator which is not driven by the events of a connection list,
+	/// for the [`in-process server`](crate::test_support::Server) used by end-to-end tests.
+	/// Connections are added with [`add_local_connection`](Self::add_local_connection),
+	/// and everything sent to them is written to `recorder`.
+	pub(crate) fn headless(
+		world: &ArcLockEntityWorld,
+		worlds: &Registry,
+		recorder: recording::ArcLockRecorder,
+	) -> Self {
+		// The bus is dropped immediately, so polling for connection events always finds none.
+		let connection_recv = engine::channels::broadcast::Bus::new(1).add_rx();
+		Self {
+			local_client_chunk_sender: None,
+			worlds: worlds.clone(),
+			world: Arc::downgrade(&world),
+			connection_recv,
+			connection_handles: HashMap::new(),
+			entities_relevant: MultiSet::default(),
+			recorder: Some(recorder),
+			timestep: crate::server::tick::FixedTimestep::new(
+				crate::server::tick::ticks_per_second(),
+			),
+			summary: Summary::default(),
+			bandwidth: Bandwidth::default(),
+			change_versions: ChangeVersions::default(),
+		}
+	}
+
+	/// Adds a connection which shares the server's world, as if it had just been authenticated.
+	pub(crate) fn add_local_connection(
+		&mut self,
+		address: SocketAddr,
+		chunk_sender: crate::client::world::chunk::OperationSender,
+	) -> anyhow::Result<()> {
+		let mut handle = Handle::new_local(&address, chunk_sender)?;
+		if let Some(recorder) = &self.recorder {
+			handle = handle.with_recorder(recorder.clone());
+		}
+		self.connection_handles.insert(address, handle);
+		Ok(())
+	}
+
+	/// The duration of a tick, which is the least amount of time that must pass for the replicator to update.
+	pub(crate) fn tick_duration(&self) -> std::time::Duration {
+		*self.timestep.step()
+	}
 }
 
 #[derive(Default)]
@@ -140,7 +274,12 @@ impl OperationGroup {
 }
 
 impl EngineSystem for Replicator {
-	fn update(&mut self, _delta_time: std::time::Duration, _has_focus: bool) {
+	fn update(&mut self, delta_time: std::time::Duration, _has_focus: bool) {
+		// Replication only needs to happen once per tick, even if multiple ticks elapsed this frame,
+		// because each update sends the latest state of the world.
+		if self.timestep.advance(delta_time) == 0 {
+			return;
+		}
 		profiling::scope!(LOG);
 
 		let arc_world = match self.world.upgrade() {
@@ -148,10 +287,11 @@ impl EngineSystem for Replicator {
 			None => return,
 		};
 
-		let chunk_cache = match self.chunk_cache.upgrade() {
-			Some(arc) => arc,
-			None => return,
-		};
+		let worlds = self.worlds.entries();
+		let chunk_caches = self.worlds.chunk_caches();
+		if chunk_caches.is_empty() {
+			return;
+		}
 
 		// Look for any new network connections so their replication streams can be set up.
 		let _new_connections = self.poll_connections();
@@ -160,15 +300,24 @@ impl EngineSystem for Replicator {
 		// - spawned
 		// - data changed (e.g. moved position)
 		// - destroyed
-		let updates = EntityUpdates::new(&self.entities_relevant);
+		// The max view distance of each world is read every update, so changes to it contract (or expand)
+		// the relevance of already connected clients on the next update.
+		let mut updates = EntityUpdates::new(&self.entities_relevant);
+		for (name, entry) in worlds.iter() {
+			updates =
+				updates.with_max_view_distance(name, entry.chunk_limits().max_view_distance());
+		}
 		let updates = updates.query(&arc_world);
-		let updates = updates.collect_chunks(&chunk_cache, &mut self.connection_handles);
+		let updates = updates.change_worlds(&mut self.connection_handles, &worlds);
+		let updates =
+			updates.collect_chunks(&chunk_caches, &mut self.connection_handles, &self.bandwidth);
 
 		// Entity updates are turned into operations on a given set of connections.
 		// This can result in multiple of the same operation for different connections
 		// depending on what entities are relevant to which connections.
 		let operations =
 			updates.as_operations(&mut self.entities_relevant, &self.connection_handles);
+		self.summary.record(&updates, &operations);
 
 		{
 			profiling::scope!("update-connection-relevance");
@@ -181,6 +330,30 @@ impl EngineSystem for Replicator {
 
 		// Sends the operations to each connection's handle/input stream
 		self.send_entity_updates(&arc_world, operations);
+
+		// Block changes are sent after the relevance updates,
+		// so they are only sent for chunks which are relevant to each client as of this tick.
+		self.send_block_changes(&chunk_caches);
+
+		// Clients wait on the loading screen until the world around them (and the entities they own) have been replicated.
+		let owned_entities = self.owned_entities_awaiting_world(&arc_world);
+		let entities_relevant = &self.entities_relevant;
+		for (address, handle) in self.connection_handles.iter_mut() {
+			let owned_entities_replicated = match owned_entities.get(address) {
+				Some(entities) => entities
+					.iter()
+					.all(|entity| entities_relevant.contains(entity, address)),
+				None => true,
+			};
+			handle.send_world_ready(owned_entities_replicated);
+		}
+
+		crate::server::metrics::Metrics::get()
+			.set_entities_relevant(self.entities_relevant.total_len());
+
+		self.summary
+			.log_every(crate::server::tick::ticks_per_second() * summary::SUMMARY_INTERVAL_SECONDS);
+		self.bandwidth.advance();
 	}
 }
 
@@ -210,7 +383,15 @@ use hecs::Query;
 struct GatherComponents<'c> {
 	position: &'c mut component::physics::linear::Position,
 	owner: Option<&'c component::OwnedByConnection>,
+	// Spectators are a source of relevance for their connection, without the connection owning an entity.
+	spectator: Option<&'c component::Spectator>,
 	relevancy: Option<&'c component::chunk::Relevancy>,
+	// The direction the owner is looking, so chunks in view are sent first.
+	orientation: Option<&'c component::Orientation>,
+	// Children are relevant wherever the root of their chain of parents is, rather than where they are.
+	parent: Option<&'c mut component::Parent>,
+	// Entities are only relevant to the connections in the same world, which is the default world without a ticket owner.
+	ticket_owner: Option<&'c component::chunk::TicketOwner>,
 	// The `Replicated` component here acts as a flag indicating what entities should get replicated to clients.
 	replicated: Option<&'c component::network::Replicated>,
 }
@@ -227,38 +408,74 @@ impl<'c> GatherEntity<'c> {
 		*self.components.position.chunk()
 	}
 
-	fn push_relevance(&self, relevance: &mut RelevanceByConnection) {
-		let owner = match self.components.owner {
-			Some(comp) => comp,
-			None => return,
+	fn world_name(&self) -> &str {
+		Registry::world_of(self.components.ticket_owner)
+	}
+
+	/// Adds the relevance of this entity to its connection,
+	/// with a chunk radius no larger than the `max_view_distances` of the world it is in.
+	fn push_relevance(
+		&self,
+		relevance: &mut RelevanceByConnection,
+		max_view_distances: &HashMap<String, u64>,
+	) {
+		let address = match (self.components.owner, self.components.spectator) {
+			(Some(owner), _) => owner.address(),
+			(None, Some(spectator)) => spectator.address(),
+			(None, None) => return,
 		};
 		let relevancy = match self.components.relevancy {
 			Some(comp) => comp,
 			None => return,
 		};
 
-		let relevance = relevance.get_or_insert_mut(owner.address());
+		let world_name = self.world_name();
+		let max_view_distance = match max_view_distances.get(world_name) {
+			Some(distance) => *distance,
+			None => u64::MAX,
+		};
+		let relevance = relevance.get_or_insert_mut(address);
+		if relevance.world != world_name {
+			relevance.world = world_name.to_owned();
+		}
 		// TODO: relevancy areas or the cuboid diff use radius inclusive to the
 		// current chunk (e.g. from the point 0,0,0) instead of from the boundaries of the chunk.
 		// This means that the radius is always 1 below its intended value on the positive parts of each axis.
-		relevance
-			.chunk
-			.push(relevancy::Area::new(self.chunk(), relevancy.radius()));
+		relevance.chunk.push(
+			relevancy::Area::new(self.chunk(), relevancy.radius().min(max_view_distance))
+				.with_margin(relevancy.margin()),
+		);
 		relevance.entity.push(relevancy::Area::new(
 			self.chunk(),
-			relevancy.entity_radius(),
+			relevancy.entity_radius().min(max_view_distance),
 		));
+		if let Some(orientation) = self.components.orientation {
+			relevance.forward = Some(*orientation.forward());
+		}
 	}
 
 	fn is_entity_replicatable(&self) -> bool {
 		self.components.replicated.is_some()
 	}
 
-	fn get_update(&mut self) -> Option<(Option<SocketAddr>, UpdatedEntity)> {
+	/// `anchors` is the chunk and world of the root entity for each child (see [`EntityUpdates::find_anchors`]).
+	fn get_update(
+		&mut self,
+		anchors: &HashMap<hecs::Entity, (Point3<i64>, String)>,
+	) -> Option<(Option<SocketAddr>, UpdatedEntity)> {
 		// If the entity is marked for replication and its position has changed
 		// (either it was never acknowledged or it has actually changed),
 		// then this will be Some(UpdatedEntity).
-		match UpdatedEntity::acknowledged(&self.entity, self.components.position) {
+		let world_name = Registry::world_of(self.components.ticket_owner);
+		let update = match (&mut self.components.parent, anchors.get(&self.entity)) {
+			(Some(parent), Some((anchor, anchor_world))) => {
+				UpdatedEntity::anchored(&self.entity, parent, self.components.position, *anchor)
+					.map(|update| update.in_world(anchor_world))
+			}
+			_ => UpdatedEntity::acknowledged(&self.entity, self.components.position)
+				.map(|update| update.in_world(world_name)),
+		};
+		match update {
 			Some(update) => {
 				let address = self.components.owner.map(|owner| *owner.address());
 				Some((address, update))
@@ -273,6 +490,13 @@ struct EntityUpdates {
 	updates: MultiMap<Option<SocketAddr>, UpdatedEntity>,
 	destroyed: HashSet<hecs::Entity>,
 	new_chunks: MultiMap<SocketAddr, Weak<RwLock<Chunk>>>,
+	/// The largest relevance radius any connection in each world can have, by world name.
+	/// Connections in worlds without a limit are not clamped.
+	max_view_distances: HashMap<String, u64>,
+	/// The number of entities found by the world query, for the replicator's [`Summary`].
+	entities_queried: usize,
+	/// How long chunks took to collect for each connection, for the replicator's [`Summary`].
+	collect_durations: HashMap<SocketAddr, std::time::Duration>,
 }
 
 impl EntityUpdates {
@@ -283,98 +507,183 @@ impl EntityUpdates {
 			updates: MultiMap::new(),
 			destroyed: relevant_entities.keys().cloned().collect::<HashSet<_>>(),
 			new_chunks: MultiMap::new(),
+			max_view_distances: HashMap::new(),
+			entities_queried: 0,
+			collect_durations: HashMap::new(),
+		}
+	}
+
+	fn with_max_view_distance(mut self, world: &str, distance: u64) -> Self {
+		self.max_view_distances.insert(world.to_owned(), distance);
+		self
+	}
+
+	/// Moves each connection whose entity is in another world than the one it has been sent the chunks of into that world,
+	/// so it discards the chunks of its previous world and is sent those of its new one.
+	/// Connections whose new world is not loaded stay where they are.
+	fn change_worlds(
+		self,
+		connection_handles: &mut HashMap<SocketAddr, Handle>,
+		worlds: &HashMap<String, registry::Entry>,
+	) -> Self {
+		for (address, relevance) in self.relevance.0.iter() {
+			let handle = match connection_handles.get_mut(address) {
+				Some(handle) => handle,
+				None => continue,
+			};
+			if handle.world() == relevance.world {
+				continue;
+			}
+			if let Some(entry) = worlds.get(&relevance.world) {
+				log::debug!(target: LOG, "Moving {} into world \"{}\"", address, relevance.world);
+				handle.change_world(&relevance.world, entry.seed());
+			}
 		}
+		self
 	}
 
+	/// Moves chunks from the pending list of each connection into the chunks to replicate this tick,
+	/// taking them from the cache of the world each connection is in (by world name).
+	/// Chunks are shared fairly between connections, see [`Bandwidth`].
 	fn collect_chunks(
 		mut self,
-		arc_chunk_cache: &chunk::cache::ArcLock,
+		chunk_caches: &HashMap<String, chunk::cache::ArcLock>,
 		connection_handles: &mut HashMap<SocketAddr, Handle>,
+		bandwidth: &Bandwidth,
 	) -> Self {
-		use std::time::{Duration, Instant};
+		use std::time::Instant;
 		profiling::scope!(
 			"entity-updates:collect_chunks",
 			&format!("connections: {}", connection_handles.len())
 		);
-		// Throttles this function to make sure it doesnt exceed a max number of ms.
-		// Needed because the `send-pending` block can consume tens of ms per frame without rate-limiting.
-		static PERF_BUDGET_MS_PER_CONNECTION: Duration = Duration::from_micros(500); // 0.5 ms
 
-		let chunk_cache = match arc_chunk_cache.try_read() {
-			Ok(locked) => locked,
-			Err(_) => return self,
-		};
+		// The chunks of worlds which are being written to (e.g. while chunks are loading) are sent on a later tick.
+		let locked_caches = chunk_caches
+			.iter()
+			.filter_map(|(name, arc_cache)| Some((name.as_str(), arc_cache.try_read().ok()?)))
+			.collect::<HashMap<_, _>>();
 
-		for (handle_addr, handle) in connection_handles.iter_mut() {
-			let perf_budget_start = Instant::now();
+		let mut shares = Vec::with_capacity(connection_handles.len());
+		for handle_addr in bandwidth.order(connection_handles.keys()).into_iter() {
+			let handle = connection_handles.get_mut(&handle_addr).unwrap();
+			let update_start = Instant::now();
 
-			let next_relevance = match self.relevance.0.get(handle_addr) {
+			// Chunks at the edge of relevance stay relevant until the connection moves past the margin of its areas.
+			if let Some(relevance) = self.relevance.0.get_mut(&handle_addr) {
+				let chunk = std::mem::take(&mut relevance.chunk);
+				relevance.chunk = chunk.retaining(handle.chunk_relevance());
+			}
+
+			let next_relevance = match self.relevance.0.get(&handle_addr) {
 				Some(relevance) if *handle.chunk_relevance() != relevance.chunk => {
 					Some(&relevance.chunk)
 				}
 				_ => None,
 			};
 
+			// Pending chunks are sorted again if the connection looked in another direction,
+			// unless they are about to be sorted by the next relevance anyway.
+			if let Some(relevance) = self.relevance.0.get(&handle_addr) {
+				let pending_chunks = handle.pending_chunks_mut();
+				let forward = relevance.forward.unwrap_or_else(Vector3::zeros);
+				if pending_chunks.set_forward(forward) && next_relevance.is_none() {
+					profiling::scope!("sort-pending-by-look-direction");
+					pending_chunks.retain_and_sort_by(&relevance.chunk);
+				}
+			}
+
 			if let Some(next_relevance) = next_relevance {
 				profiling::scope!("update-pending");
 
 				// Only keep chunks in the pending list that are still relevant
-				let new_cuboids = next_relevance.difference(&handle.chunk_relevance());
+				let new_cuboids = next_relevance.moved_difference(&handle.chunk_relevance());
 				let pending_chunks = handle.pending_chunks_mut();
 				pending_chunks.retain_and_sort_by(next_relevance);
 				pending_chunks.insert_cuboids(new_cuboids, next_relevance);
 			}
 
-			if Instant::now().duration_since(perf_budget_start) < PERF_BUDGET_MS_PER_CONNECTION {
-				profiling::scope!(
-					"send-pending",
-					&format!("count:{}", handle.pending_chunks().len())
-				);
-
-				'process_next_chunk: loop {
-					profiling::scope!("send-pending-chunk");
-
-					let coordinate = match handle.pending_chunks_mut().pop_front() {
-						Some(coord) => coord,
-						None => break 'process_next_chunk,
-					};
+			// Connections which cannot keep up with the chunks already sent to them
+			// keep the rest in their pending list until the backlog drains.
+			let mut share = bandwidth::Share::new(
+				handle_addr,
+				handle.chunk_capacity(),
+				bandwidth.perf_budget_per_connection(),
+			);
+			share.add_elapsed(update_start.elapsed());
+			shares.push(share);
+		}
 
+		{
+			profiling::scope!("send-pending");
+			let mut remaining_bytes = bandwidth.tick_byte_budget();
+			// Chunks which are not loaded yet are put back into the pending list once every connection has been served,
+			// so they are not popped again in the same tick.
+			let mut unloaded_chunks = Vec::new();
+			while remaining_bytes > 0 {
+				let share = match bandwidth::next_share(&mut shares) {
+					Some(share) => share,
+					None => break,
+				};
+				let pop_start = Instant::now();
+				let handle = connection_handles.get_mut(&share.address).unwrap();
+				let chunk_cache = match locked_caches.get(handle.world()) {
+					Some(chunk_cache) => chunk_cache,
+					None => {
+						share.is_done = true;
+						continue;
+					}
+				};
+				match handle.pending_chunks_mut().pop_front() {
+					None => share.is_done = true,
 					// If the chunk is in the cache, then the server has it loaded (to some degree).
-					if let Some(weak_chunk) = chunk_cache.find(&coordinate) {
-						self.new_chunks
-							.insert(handle_addr.clone(), weak_chunk.clone());
-					} else {
-						// If chunk is not load or we've exceeded our alloted time/amount for this update,
-						// then the chunk needs to go back on the component for the next update cycle.
-						if let Some(idx) = handle
-							.pending_chunks()
-							.find_insertion_point(&coordinate, handle.chunk_relevance())
-						{
-							handle.pending_chunks_mut().insert(idx, coordinate);
+					Some(coordinate) => match chunk_cache.find(&coordinate) {
+						Some(weak_chunk) => {
+							let size = match weak_chunk.upgrade() {
+								Some(arc_chunk) => {
+									arc_chunk.read().unwrap().chunk.replicated_size()
+								}
+								None => 0,
+							};
+							self.new_chunks
+								.insert(share.address.clone(), weak_chunk.clone());
+							share.take(size);
+							remaining_bytes = remaining_bytes.saturating_sub(size);
 						}
-					}
+						None => unloaded_chunks.push((share.address.clone(), coordinate)),
+					},
+				}
+				share.add_elapsed(pop_start.elapsed());
+			}
 
-					if Instant::now().duration_since(perf_budget_start)
-						>= PERF_BUDGET_MS_PER_CONNECTION
-					{
-						break 'process_next_chunk;
-					}
+			for (address, coordinate) in unloaded_chunks.into_iter() {
+				let handle = connection_handles.get_mut(&address).unwrap();
+				if let Some(idx) = handle
+					.pending_chunks()
+					.find_insertion_point(&coordinate, handle.chunk_relevance())
+				{
+					handle.pending_chunks_mut().insert(idx, coordinate);
 				}
 			}
 		}
+
+		for share in shares.into_iter() {
+			self.collect_durations.insert(share.address, share.elapsed);
+		}
 		self
 	}
 
 	fn query(mut self, arc_world: &Arc<RwLock<hecs::World>>) -> Self {
 		profiling::scope!("entity-updates:query");
 		let mut world = arc_world.write().unwrap();
+		let anchors = Self::find_anchors(&world);
 		for mut entity_query in GatherEntity::query_mut(&mut world) {
-			entity_query.push_relevance(&mut self.relevance);
+			self.entities_queried += 1;
+			entity_query.push_relevance(&mut self.relevance, &self.max_view_distances);
 			if entity_query.is_entity_replicatable() {
 				// Prune all entities from `destroyed_entities` that still exist,
 				// (leaving it only containing the entities which do not still exist).
 				self.destroyed.remove(&entity_query.entity);
-				if let Some((address, update)) = entity_query.get_update() {
+				if let Some((address, update)) = entity_query.get_update(&anchors) {
 					self.updates.insert(address, update);
 				}
 			}
@@ -382,6 +691,25 @@ impl EntityUpdates {
 		self
 	}
 
+	/// Returns the chunk and world of the root of the chain of parents for every child in the world,
+	/// so children are relevant to the same connections as the entity they are attached to.
+	fn find_anchors(world: &hecs::World) -> HashMap<hecs::Entity, (Point3<i64>, String)> {
+		profiling::scope!("entity-updates:find_anchors");
+		let mut anchors = HashMap::new();
+		for (entity, _) in world.query::<&component::Parent>().iter() {
+			let root = match component::Parent::root_of(world, entity) {
+				Some(root) => root,
+				None => continue,
+			};
+			if let Ok(position) = world.get::<&component::physics::linear::Position>(root) {
+				let ticket_owner = world.get::<&component::chunk::TicketOwner>(root).ok();
+				let world_name = Registry::world_of(ticket_owner.as_deref()).to_owned();
+				anchors.insert(entity, (*position.chunk(), world_name));
+			}
+		}
+		anchors
+	}
+
 	#[profiling::function]
 	fn as_operations(
 		&self,
@@ -390,7 +718,7 @@ impl EntityUpdates {
 	) -> OperationGroup {
 		let mut operations = OperationGroup::default();
 		self.gather_destroyed_operations(relevant_entities, &mut operations);
-		self.gather_relevancy_diffs(&connection_handles, &mut operations);
+		self.gather_relevancy_diffs(relevant_entities, &connection_handles, &mut operations);
 		operations
 	}
 
@@ -409,8 +737,11 @@ impl EntityUpdates {
 		}
 	}
 
+	/// An entity was relevant to a connection if the connection has been told about it,
+	/// and is relevant if it is in the same world as (and within the entity relevance of) the connection.
 	fn gather_relevancy_diffs(
 		&self,
+		relevant_entities: &MultiSet<hecs::Entity, SocketAddr>,
 		connection_handles: &HashMap<SocketAddr, Handle>,
 		operations: &mut OperationGroup,
 	) {
@@ -432,14 +763,14 @@ impl EntityUpdates {
 						updated_entity.entity.id()
 					)
 				);
-				for (handle_addr, handle) in connection_handles.iter() {
-					let was_relevant = match updated_entity.old_chunk {
-						Some(old_chunk) => handle.entity_relevance().is_relevant(&old_chunk),
-						None => false,
-					};
+				for handle_addr in connection_handles.keys() {
+					let was_relevant =
+						relevant_entities.contains(&updated_entity.entity, handle_addr);
 					let is_relevant = match self.relevance.0.get(handle_addr) {
-						Some(relevance) => relevance.entity.is_relevant(&updated_entity.new_chunk),
-						None => false,
+						Some(relevance) if relevance.world == updated_entity.world => {
+							relevance.entity.is_relevant(&updated_entity.new_chunk)
+						}
+						_ => false,
 					};
 					match (was_relevant, is_relevant) {
 						// NO-OP: entity wasn't relevant and still isn't relevant
@@ -476,6 +807,7 @@ impl EntityUpdates {
 
 	#[profiling::function]
 	fn into_items(mut self) -> HashMap<SocketAddr, Vec<relevancy::Update>> {
+		use crate::common::network::replication::world::QueuedChunk;
 		use relevancy::{Update::*, WorldUpdate};
 		let relevance = self.relevance.into_inner();
 		let mut items = HashMap::with_capacity(relevance.len());
@@ -484,6 +816,7 @@ impl EntityUpdates {
 			updates.push(Entity(relevance.entity));
 			updates.push(World(WorldUpdate::Relevance(relevance.chunk)));
 			if let Some(new_chunks) = self.new_chunks.remove(&address) {
+				let new_chunks = new_chunks.into_iter().map(QueuedChunk::new).collect();
 				updates.push(World(WorldUpdate::Chunks(new_chunks)));
 			}
 			items.insert(address, updates);
@@ -540,7 +873,18 @@ impl Replicator {
 				let chunk_sender = self.local_client_chunk_sender.as_ref().unwrap();
 				Handle::new_local(&address, chunk_sender.clone())?
 			}
-			false => Handle::new_remote(&address, &connection)?,
+			// Connections begin in the default world, and are moved once their entity is found in another.
+			false => {
+				let world_seed = match self.worlds.get(DEFAULT_WORLD) {
+					Some(entry) => entry.seed(),
+					None => 0,
+				};
+				Handle::new_remote(&address, &connection, world_seed)?
+			}
+		};
+		let handle = match &self.recorder {
+			Some(recorder) => handle.with_recorder(recorder.clone()),
+			None => handle,
 		};
 
 		self.connection_handles.insert(address, handle);
@@ -555,14 +899,65 @@ impl Replicator {
 		self.entities_relevant.remove_value(&address);
 	}
 
+	/// Returns the entities owned by each connection which is still waiting to be told the world is ready.
+	/// The world is only read if there is such a connection.
+	fn owned_entities_awaiting_world(
+		&self,
+		arc_world: &ArcLockEntityWorld,
+	) -> HashMap<SocketAddr, Vec<hecs::Entity>> {
+		let mut owned_entities = HashMap::new();
+		let is_awaiting = self
+			.connection_handles
+			.values()
+			.any(|handle| handle.is_awaiting_world_ready());
+		if !is_awaiting {
+			return owned_entities;
+		}
+		let world = arc_world.read().unwrap();
+		for (entity, owner) in world.query::<&component::OwnedByConnection>().iter() {
+			owned_entities
+				.entry(*owner.address())
+				.or_insert_with(Vec::new)
+				.push(entity);
+		}
+		owned_entities
+	}
+
+	/// Sends the blocks which changed in each loaded chunk (of every world, by world name) since the last update
+	/// to the connections in that world which already have that chunk.
+	#[profiling::function]
+	fn send_block_changes(&mut self, chunk_caches: &HashMap<String, chunk::cache::ArcLock>) {
+		for (world_name, chunk_cache) in chunk_caches.iter() {
+			let changed_chunks = {
+				let cache = chunk_cache.read().unwrap();
+				cache
+					.iter()
+					.filter_map(|(_, weak)| weak.upgrade())
+					.filter(|arc_chunk| arc_chunk.read().unwrap().has_block_changes())
+					.collect::<Vec<_>>()
+			};
+			for arc_chunk in changed_chunks.into_iter() {
+				let changes = arc_chunk.write().unwrap().take_block_changes();
+				for handle in self.connection_handles.values_mut() {
+					if handle.world() == world_name {
+						handle.send_block_changes(&changes);
+					}
+				}
+			}
+		}
+	}
+
 	#[profiling::function]
 	fn send_entity_updates(&mut self, arc_world: &ArcLockEntityWorld, operations: OperationGroup) {
 		// Serialize entities which are being replicated for one or more connections
-		let entity_data = {
+		let mut entity_data = {
 			let world = arc_world.read().unwrap();
+			let registry = component::Registry::read();
 			let entities = operations.entity_ops.keys().cloned().collect();
-			self.serialize_entities(&world, entities)
+			Self::serialize_entities(&registry, &world, entities)
 		};
+		self.change_versions.advance();
+		self.change_versions.stamp(&mut entity_data);
 		// Update relevancy cache
 		for (entity, operations) in operations.entity_ops.into_iter() {
 			for (operation, address) in operations.into_iter() {
@@ -575,29 +970,37 @@ impl Replicator {
 					EntityOperation::Irrelevant => {
 						self.entities_relevant.remove(&entity, &address);
 					}
-					// NO-OP, addresses for dropped are gathered by removing them from the `entities_relevant` map
-					EntityOperation::Destroyed => {}
+					// Addresses for dropped are gathered by removing them from the `entities_relevant` map
+					EntityOperation::Destroyed => {
+						self.change_versions.forget(&entity);
+					}
 				}
 			}
 		}
-		// Send operations to relevant connections
-		for (address, operations) in operations.socket_ops.into_iter() {
-			if let Some(handle) = self.connection_handles.get(&address) {
+		// Send operations to relevant connections, in the same rotating order that chunks are shared in.
+		// Entity updates are never deferred (they are the latest state of each entity), so they are not limited by the byte budget.
+		let mut socket_ops = operations.socket_ops;
+		for address in self
+			.bandwidth
+			.order(self.connection_handles.keys())
+			.into_iter()
+		{
+			if let Some(operations) = socket_ops.remove(&address) {
+				let handle = self.connection_handles.get_mut(&address).unwrap();
 				handle.send_entity_operations(operations, &entity_data);
 			}
 		}
 	}
 
 	fn serialize_entities(
-		&self,
+		registry: &component::Registry,
 		world: &entity::World,
 		entities: HashSet<hecs::Entity>,
-	) -> HashMap<hecs::Entity, binary::SerializedEntity> {
+	) -> SerializedEntities {
 		let count = entities.len();
 		profiling::scope!("serialize_entities", &format!("count={}", count));
-		let mut serialized_entities = HashMap::with_capacity(count);
+		let mut serialized_entities = SerializedEntities::with_capacity(count);
 
-		let registry = component::Registry::read();
 		for entity in entities.into_iter() {
 			let entity_ref = world.entity(entity).unwrap();
 			// Should never happen unless the world is being actively destroyed
@@ -605,9 +1008,29 @@ impl Replicator {
 				continue;
 			}
 
-			match self.serialize_entity(&registry, entity_ref) {
-				Ok(serialized) => {
-					serialized_entities.insert(entity, serialized);
+			// Entities owned by a connection are serialized a second time for their owner,
+			// if they have any components which are only replicated to the owner.
+			let owner = entity_ref
+				.get::<&component::OwnedByConnection>()
+				.map(|owner| *owner.address());
+			let owner_data = match owner {
+				Some(address) if Self::has_owner_private_components(registry, &entity_ref) => Some(
+					Self::serialize_entity(registry, &entity_ref, true).map(|data| (address, data)),
+				),
+				_ => None,
+			};
+			if let Some(id) = entity_ref.get::<&component::PersistentId>() {
+				serialized_entities.persistent_ids.insert(entity, *id);
+			}
+			let result = Self::serialize_entity(registry, &entity_ref, false)
+				.and_then(|public| Ok((public, owner_data.transpose()?)));
+
+			match result {
+				Ok((public, owner_data)) => {
+					serialized_entities.public.insert(entity, public);
+					if let Some(owner_data) = owner_data {
+						serialized_entities.owner_only.insert(entity, owner_data);
+					}
 				}
 				Err(err) => {
 					log::error!(target: "entity-replicator", "Encountered error while serializing entity: {}", err)
@@ -619,11 +1042,83 @@ impl Replicator {
 	}
 }
 
+/// The serialized data of the entities being replicated during an update.
+#[derive(Default)]
+pub struct SerializedEntities {
+	/// The data sent to every connection the entity is relevant to.
+	public: HashMap<hecs::Entity, binary::SerializedEntity>,
+	/// The data sent to the owning connection of entities which have
+	/// [`owner private`](network::Registration::owner_private) components.
+	owner_only: HashMap<hecs::Entity, (SocketAddr, binary::SerializedEntity)>,
+	/// The persistent id of each entity being replicated which has one.
+	persistent_ids: HashMap<hecs::Entity, component::PersistentId>,
+	/// The version of this update, see [`ChangeVersions`].
+	version: u64,
+	/// The version each component of the entities being replicated last changed at.
+	versions: HashMap<hecs::Entity, ComponentVersions>,
+}
+
+impl SerializedEntities {
+	fn with_capacity(capacity: usize) -> Self {
+		Self {
+			public: HashMap::with_capacity(capacity),
+			owner_only: HashMap::new(),
+			persistent_ids: HashMap::new(),
+			version: 0,
+			versions: HashMap::with_capacity(capacity),
+		}
+	}
+
+	/// The version of the replicator update the entities were serialized in.
+	pub fn version(&self) -> u64 {
+		self.version
+	}
+
+	/// Returns the version each replicated component of an entity last changed at.
+	pub fn versions(&self, entity: &hecs::Entity) -> Option<&ComponentVersions> {
+		self.versions.get(entity)
+	}
+
+	/// Returns the persistent id of an entity being replicated, if it has one.
+	pub fn persistent_id(&self, entity: &hecs::Entity) -> Option<&component::PersistentId> {
+		self.persistent_ids.get(entity)
+	}
+
+	/// Returns the data to send to a specific connection for an entity.
+	pub fn get(
+		&self,
+		entity: &hecs::Entity,
+		address: &SocketAddr,
+	) -> Option<&binary::SerializedEntity> {
+		match self.owner_only.get(entity) {
+			Some((owner, serialized)) if owner == address => Some(serialized),
+			_ => self.public.get(entity),
+		}
+	}
+}
+
 impl Replicator {
+	fn has_owner_private_components(
+		registry: &component::Registry,
+		entity_ref: &hecs::EntityRef<'_>,
+	) -> bool {
+		entity_ref.component_types().any(|type_id| {
+			registry
+				.find(&type_id)
+				.map(|registered| registered.get_ext::<network::Registration>())
+				.flatten()
+				.map(|network| network.is_owner_private())
+				.unwrap_or(false)
+		})
+	}
+
+	/// Serializes the replicatable components of an entity.
+	/// Components which are [`owner private`](network::Registration::owner_private) are only included
+	/// if `include_owner_private` is true (i.e. the data is being sent to the owner of the entity).
 	fn serialize_entity(
-		&self,
 		registry: &component::Registry,
-		entity_ref: hecs::EntityRef<'_>,
+		entity_ref: &hecs::EntityRef<'_>,
+		include_owner_private: bool,
 	) -> Result<binary::SerializedEntity> {
 		profiling::scope!(
 			"serialize_entity",
@@ -633,8 +1128,12 @@ impl Replicator {
 		for type_id in entity_ref.component_types() {
 			if let Some(registered) = registry.find(&type_id) {
 				// Skip any components that are not marked as network replicatable.
+				// Skip owner private components unless the data is being sent to the owner.
 				match registered.get_ext::<network::Registration>() {
 					None => continue,
+					Some(network) if network.is_owner_private() && !include_owner_private => {
+						continue
+					}
 					Some(_) => {}
 				}
 				let binary_registration = match registered.get_ext::<binary::Registration>() {
@@ -651,7 +1150,7 @@ impl Replicator {
 				// If `serializable` returns None, it means the component wasn't actually on that entity.
 				// Since the type-id came from the entity itself, the component MUST exist on the entity_ref,
 				// so it should be safe to unwrap directly.
-				let serialized = binary_registration.serialize(&entity_ref)?.unwrap();
+				let serialized = binary_registration.serialize(entity_ref)?.unwrap();
 				serialized_components.push(serialized);
 			}
 		}
@@ -661,3 +1160,568 @@ impl Replicator {
 		})
 	}
 }
+
+#[cfg(test)]
+mod relevancy_diffs {
+	use super::*;
+	use crate::entity::archetype;
+
+	#[test]
+	fn only_in_range_markers_are_relevant() {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let (near, far) = {
+			let mut world = arc_world.write().unwrap();
+			// The player at the origin whose relevancy determines what the connection can see.
+			let _player = world.spawn(
+				archetype::player::Server::new()
+					.with_address(address)
+					.build()
+					.build(),
+			);
+			let near = world.spawn(
+				archetype::test::Marker::new(Point3::new(20.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			let far = world.spawn(
+				archetype::test::Marker::new(Point3::new(2000.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			(near, far)
+		};
+
+		let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+		let mut connection_handles = HashMap::new();
+		connection_handles.insert(address, Handle::new_local(&address, chunk_sender).unwrap());
+
+		let updates = EntityUpdates::new(&MultiSet::default()).query(&arc_world);
+		let mut operations = OperationGroup::default();
+		updates.gather_relevancy_diffs(&MultiSet::default(), &connection_handles, &mut operations);
+
+		let marker_ops = operations
+			.socket_ops
+			.get_vec(&address)
+			.unwrap()
+			.iter()
+			.filter(|(_, entity)| *entity == near || *entity == far)
+			.collect::<Vec<_>>();
+		assert_eq!(marker_ops.len(), 1);
+		assert!(matches!(
+			marker_ops[0],
+			(EntityOperation::Relevant, entity) if *entity == near
+		));
+	}
+
+	#[test]
+	fn children_are_relevant_with_their_parent() {
+		use engine::math::nalgebra::Isometry3;
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let child = {
+			let mut world = arc_world.write().unwrap();
+			let _player = world.spawn(
+				archetype::player::Server::new()
+					.with_address(address)
+					.build()
+					.build(),
+			);
+			let parent = world.spawn(
+				archetype::test::Marker::new(Point3::new(20.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			// The child has not been moved to its parent yet, so only its parent puts it in range.
+			let child = world.spawn(
+				archetype::test::Marker::new(Point3::new(2000.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			component::Parent::attach(&mut world, child, parent, Isometry3::identity()).unwrap();
+			child
+		};
+
+		let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+		let mut connection_handles = HashMap::new();
+		connection_handles.insert(address, Handle::new_local(&address, chunk_sender).unwrap());
+
+		let updates = EntityUpdates::new(&MultiSet::default()).query(&arc_world);
+		let mut operations = OperationGroup::default();
+		updates.gather_relevancy_diffs(&MultiSet::default(), &connection_handles, &mut operations);
+
+		let child_ops = operations
+			.socket_ops
+			.get_vec(&address)
+			.unwrap()
+			.iter()
+			.filter(|(_, entity)| *entity == child)
+			.collect::<Vec<_>>();
+		assert_eq!(child_ops.len(), 1);
+		assert!(matches!(child_ops[0], (EntityOperation::Relevant, _)));
+	}
+
+	#[test]
+	fn entities_in_other_worlds_are_not_relevant() {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let (here, elsewhere) = {
+			let mut world = arc_world.write().unwrap();
+			let _player = world.spawn(
+				archetype::player::Server::new()
+					.with_address(address)
+					.build()
+					.build(),
+			);
+			let here = world.spawn(
+				archetype::test::Marker::new(Point3::new(20.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			// In range of the player, but in a world the player is not in.
+			let elsewhere = world.spawn(
+				archetype::test::Marker::new(Point3::new(20.0, 4.0, -8.0))
+					.build()
+					.build(),
+			);
+			let mut ticket_owner = component::chunk::TicketOwner::default();
+			ticket_owner.set_world("arena", None);
+			world.insert_one(elsewhere, ticket_owner).unwrap();
+			(here, elsewhere)
+		};
+
+		let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+		let mut connection_handles = HashMap::new();
+		connection_handles.insert(address, Handle::new_local(&address, chunk_sender).unwrap());
+
+		// The connection had been told about the entity before it moved to the other world.
+		let mut relevant_entities = MultiSet::default();
+		relevant_entities.insert(&elsewhere, address);
+		let updates = EntityUpdates::new(&relevant_entities).query(&arc_world);
+		let mut operations = OperationGroup::default();
+		updates.gather_relevancy_diffs(&relevant_entities, &connection_handles, &mut operations);
+
+		let marker_ops = operations
+			.socket_ops
+			.get_vec(&address)
+			.unwrap()
+			.iter()
+			.filter(|(_, entity)| *entity == here || *entity == elsewhere)
+			.collect::<Vec<_>>();
+		assert_eq!(marker_ops.len(), 2);
+		assert!(marker_ops
+			.iter()
+			.any(|op| matches!(op, (EntityOperation::Relevant, entity) if *entity == here)));
+		assert!(marker_ops
+			.iter()
+			.any(|op| matches!(op, (EntityOperation::Irrelevant, entity) if *entity == elsewhere)));
+	}
+}
+
+#[cfg(test)]
+mod chunk_backpressure {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		server::world::chunk::{cache::Cache, Level},
+	};
+
+	fn setup() -> (
+		SocketAddr,
+		HashMap<SocketAddr, Handle>,
+		HashMap<String, chunk::cache::ArcLock>,
+		Vec<chunk::ArcLock>,
+	) {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+		let mut handle = Handle::new_local(&address, chunk_sender).unwrap();
+
+		let mut cache = Cache::new();
+		let mut chunks = Vec::new();
+		for x in 0..3 {
+			let coordinate = Point3::new(x, 0, 0);
+			let chunk = Arc::new(RwLock::new(Chunk::new(
+				std::path::PathBuf::new(),
+				CommonChunk::new(coordinate),
+				Level::Ticking,
+			)));
+			cache.insert(coordinate, Arc::downgrade(&chunk));
+			handle.pending_chunks_mut().insert(x as usize, coordinate);
+			chunks.push(chunk);
+		}
+
+		let mut connection_handles = HashMap::new();
+		connection_handles.insert(address, handle);
+		(
+			address,
+			connection_handles,
+			HashMap::from([(DEFAULT_WORLD.to_owned(), Arc::new(RwLock::new(cache)))]),
+			chunks,
+		)
+	}
+
+	#[test]
+	fn queues_chunks_when_not_saturated() {
+		let (address, mut connection_handles, caches, _chunks) = setup();
+		let updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&Bandwidth::default(),
+		);
+		assert_eq!(updates.new_chunks.get_vec(&address).unwrap().len(), 3);
+		assert_eq!(connection_handles[&address].pending_chunks().len(), 0);
+	}
+
+	#[test]
+	fn saturated_connection_defers_chunks() {
+		let (address, mut connection_handles, caches, _chunks) = setup();
+		{
+			let handle = connection_handles.get(&address).unwrap();
+			while handle.chunk_capacity() > 0 {
+				handle.backlog().push(0);
+			}
+		}
+		let updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&Bandwidth::default(),
+		);
+		assert!(updates.new_chunks.get_vec(&address).is_none());
+		assert_eq!(connection_handles[&address].pending_chunks().len(), 3);
+	}
+
+	#[test]
+	fn too_many_pending_bytes_defers_chunks() {
+		let (address, mut connection_handles, caches, _chunks) = setup();
+		connection_handles[&address].backlog().push(usize::MAX / 2);
+		let updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&Bandwidth::default(),
+		);
+		assert!(updates.new_chunks.get_vec(&address).is_none());
+		assert_eq!(connection_handles[&address].in_flight_chunks(), 1);
+		assert_eq!(connection_handles[&address].pending_chunks().len(), 3);
+	}
+
+	#[test]
+	fn world_is_replicated_once_chunks_are_written() {
+		let (address, mut connection_handles, caches, _chunks) = setup();
+		let mut relevance = relevancy::Relevance::default();
+		relevance.push(relevancy::Area::new(Point3::origin(), 1));
+		{
+			let handle = connection_handles.get_mut(&address).unwrap();
+			handle.send_relevance_updates(vec![relevancy::Update::World(
+				relevancy::WorldUpdate::Relevance(relevance),
+			)]);
+			assert!(!handle.is_world_replicated());
+		}
+		let _updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&Bandwidth::default(),
+		);
+		let handle = &connection_handles[&address];
+		assert!(handle.is_world_replicated());
+		handle.backlog().push(0);
+		assert!(!handle.is_world_replicated());
+	}
+
+	#[test]
+	fn ready_to_enter_once_entities_are_replicated() {
+		let (address, mut connection_handles, caches, _chunks) = setup();
+		let mut relevance = relevancy::Relevance::default();
+		relevance.push(relevancy::Area::new(Point3::origin(), 1));
+		connection_handles
+			.get_mut(&address)
+			.unwrap()
+			.send_relevance_updates(vec![relevancy::Update::World(
+				relevancy::WorldUpdate::Relevance(relevance.clone()),
+			)]);
+		let _updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&Bandwidth::default(),
+		);
+		let handle = connection_handles.get_mut(&address).unwrap();
+		assert!(handle.is_world_replicated());
+		// The entities around the client have not been replicated yet.
+		assert!(!handle.is_ready_to_enter(true));
+
+		handle.send_relevance_updates(vec![relevancy::Update::Entity(relevance)]);
+		assert!(!handle.is_ready_to_enter(false));
+		assert!(handle.is_ready_to_enter(true));
+	}
+}
+
+#[cfg(test)]
+mod chunk_fairness {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		server::world::chunk::{cache::Cache, Level},
+	};
+
+	static PENDING_PER_CONNECTION: usize = 30;
+
+	fn pending_handle(port: u16) -> (SocketAddr, Handle) {
+		let address: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+		let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+		let mut handle = Handle::new_local(&address, chunk_sender).unwrap();
+		for x in 0..PENDING_PER_CONNECTION {
+			handle
+				.pending_chunks_mut()
+				.insert(x, Point3::new(x as i64, 0, 0));
+		}
+		(address, handle)
+	}
+
+	fn delivered(updates: &EntityUpdates, address: &SocketAddr) -> usize {
+		updates
+			.new_chunks
+			.get_vec(address)
+			.map(Vec::len)
+			.unwrap_or(0)
+	}
+
+	#[test]
+	fn connections_receive_equal_chunks_per_tick() {
+		let mut cache = Cache::new();
+		let mut chunks = Vec::new();
+		for x in 0..PENDING_PER_CONNECTION {
+			let coordinate = Point3::new(x as i64, 0, 0);
+			let chunk = Arc::new(RwLock::new(Chunk::new(
+				std::path::PathBuf::new(),
+				CommonChunk::new(coordinate),
+				Level::Ticking,
+			)));
+			cache.insert(coordinate, Arc::downgrade(&chunk));
+			chunks.push(chunk);
+		}
+		let caches = HashMap::from([(DEFAULT_WORLD.to_owned(), Arc::new(RwLock::new(cache)))]);
+		let chunk_size = CommonChunk::new(Point3::origin()).replicated_size();
+
+		let mut connection_handles = (0..3)
+			.map(|i| pending_handle(25565 + i))
+			.collect::<HashMap<_, _>>();
+		// Enough bytes for 30 chunks, which is fewer than the 90 pending across all connections.
+		// The time budget is generous, so only the byte budget limits the chunks each connection is given
+		// (no matter how slowly the test runs).
+		let mut bandwidth = Bandwidth::default()
+			.with_tick_byte_budget(chunk_size * 30)
+			.with_perf_budget_per_connection(std::time::Duration::from_secs(60));
+
+		let updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&bandwidth,
+		);
+		for address in connection_handles.keys() {
+			assert_eq!(delivered(&updates, address), 10, "{}", address);
+			assert_eq!(connection_handles[address].pending_chunks().len(), 20);
+		}
+		bandwidth.advance();
+
+		// A connection which joins while the others are mid-stream gets the same share as them.
+		let (joined, handle) = pending_handle(25570);
+		connection_handles.insert(joined, handle);
+		let bandwidth = bandwidth.with_tick_byte_budget(chunk_size * 40);
+		let updates = EntityUpdates::new(&MultiSet::default()).collect_chunks(
+			&caches,
+			&mut connection_handles,
+			&bandwidth,
+		);
+		for address in connection_handles.keys() {
+			assert_eq!(delivered(&updates, address), 10, "{}", address);
+		}
+		assert_eq!(connection_handles[&joined].pending_chunks().len(), 20);
+	}
+
+	#[test]
+	fn order_rotates_each_tick() {
+		let addresses = (0..3)
+			.map(|i| format!("127.0.0.1:{}", 25565 + i).parse().unwrap())
+			.collect::<Vec<SocketAddr>>();
+		let mut bandwidth = Bandwidth::default();
+		let first = bandwidth.order(addresses.iter().rev());
+		assert_eq!(first, addresses);
+		bandwidth.advance();
+		let second = bandwidth.order(addresses.iter());
+		assert_eq!(second[0], addresses[1]);
+		assert_eq!(second[2], addresses[0]);
+	}
+}
+
+#[cfg(test)]
+mod spectators {
+	use super::*;
+	use crate::entity::archetype;
+
+	#[test]
+	fn spectator_is_relevance_source_without_owned_entity() {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		let spectator = {
+			let mut world = arc_world.write().unwrap();
+			world.spawn(archetype::spectator::Server::new(address).build().build())
+		};
+
+		let updates = EntityUpdates::new(&MultiSet::default()).query(&arc_world);
+
+		let relevance = updates.relevance.0.get(&address).unwrap();
+		assert!(relevance.chunk.is_relevant(&Point3::new(0, 0, 0)));
+		assert!(relevance.entity.is_relevant(&Point3::new(0, 0, 0)));
+		// The spectator's camera is not replicated to any connection (including its own).
+		assert!(updates.updates.is_empty());
+
+		let world = arc_world.read().unwrap();
+		assert_eq!(
+			world
+				.query::<&component::OwnedByConnection>()
+				.iter()
+				.count(),
+			0
+		);
+		// Spectators still request chunks to be loaded around them.
+		assert!(world
+			.get::<&component::chunk::TicketOwner>(spectator)
+			.is_ok());
+	}
+}
+
+#[cfg(test)]
+mod owner_private {
+	use super::*;
+	use crate::entity::archetype;
+
+	#[test]
+	fn private_components_are_only_sent_to_owner() {
+		let owner: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let other: SocketAddr = "127.0.0.1:25566".parse().unwrap();
+		let mut registry = component::Registry::default();
+		registry.register::<component::physics::linear::Position>();
+		registry.register::<component::Inventory>();
+		registry.register::<component::OwnedByConnection>();
+
+		let mut world = entity::World::new();
+		let entity = world.spawn(
+			archetype::player::Server::new()
+				.with_address(owner)
+				.build()
+				.build(),
+		);
+		let serialized = Replicator::serialize_entities(&registry, &world, HashSet::from([entity]));
+
+		let has_inventory = |address: &SocketAddr| {
+			let inventory_id = <component::Inventory as component::Component>::unique_id();
+			let serialized = serialized.get(&entity, address).unwrap();
+			assert!(serialized.components.len() > 1);
+			serialized
+				.components
+				.iter()
+				.any(|component| component.id == inventory_id)
+		};
+		assert!(has_inventory(&owner));
+		assert!(!has_inventory(&other));
+	}
+}
+
+#[cfg(test)]
+mod view_distance {
+	use super::*;
+	use crate::entity::archetype;
+
+	#[test]
+	fn relevance_is_clamped_to_max_view_distance() {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let arc_world = Arc::new(RwLock::new(entity::World::new()));
+		arc_world.write().unwrap().spawn(
+			archetype::player::Server::new()
+				.with_address(address)
+				.build()
+				.build(),
+		);
+
+		let updates = EntityUpdates::new(&MultiSet::default()).query(&arc_world);
+		let relevance = updates.relevance.0.get(&address).unwrap();
+		assert!(relevance.chunk.is_relevant(&Point3::new(4, 0, 0)));
+
+		let updates = EntityUpdates::new(&MultiSet::default())
+			.with_max_view_distance(DEFAULT_WORLD, 2)
+			.query(&arc_world);
+		let relevance = updates.relevance.0.get(&address).unwrap();
+		assert!(relevance.chunk.is_relevant(&Point3::new(2, 0, 0)));
+		assert!(!relevance.chunk.is_relevant(&Point3::new(4, 0, 0)));
+		assert!(!relevance.entity.is_relevant(&Point3::new(4, 0, 0)));
+	}
+}
+
+#[cfg(test)]
+mod connection_stats {
+	use super::*;
+	use crate::server::world::chunk::cache::Cache;
+
+	#[test]
+	fn counts_are_per_connection() {
+		let world = Arc::new(RwLock::new(entity::World::new()));
+		let cache = Arc::new(RwLock::new(Cache::new()));
+		let recorder = recording::Recorder::new(std::io::sink()).arclocked();
+		let worlds = Registry::default();
+		worlds.insert(DEFAULT_WORLD, registry::Entry::new(&cache));
+		let mut replicator = Replicator::headless(&world, &worlds, recorder);
+
+		let first: SocketAddr = "127.0.0.1:25566".parse().unwrap();
+		let second: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		for address in [first, second] {
+			let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+			replicator
+				.add_local_connection(address, chunk_sender)
+				.unwrap();
+		}
+
+		let entities = {
+			let mut world = world.write().unwrap();
+			(0..3).map(|i| world.spawn((i,))).collect::<Vec<_>>()
+		};
+		for entity in entities.iter() {
+			replicator.entities_relevant.insert(entity, first);
+		}
+		replicator.entities_relevant.insert(&entities[0], second);
+		{
+			let handle = replicator.connection_handles.get_mut(&first).unwrap();
+			for x in 0..4 {
+				handle
+					.pending_chunks_mut()
+					.insert(x, Point3::new(x as i64, 0, 0));
+			}
+			handle.backlog().push(100);
+			handle.backlog().push(50);
+		}
+
+		assert_eq!(
+			replicator.connection_stats(),
+			vec![
+				ConnectionStats {
+					address: second,
+					state: ConnectionState::Local,
+					relevant_entities: 1,
+					pending_chunks: 0,
+					in_flight_chunks: 0,
+					pending_bytes: 0,
+				},
+				ConnectionStats {
+					address: first,
+					state: ConnectionState::Local,
+					relevant_entities: 3,
+					pending_chunks: 4,
+					in_flight_chunks: 2,
+					pending_bytes: 150,
+				},
+			]
+		);
+
+		// Connections which drop are no longer reported.
+		replicator.remove_connection(&first);
+		assert_eq!(replicator.connection_stats().len(), 1);
+	}
+}
@@ -0,0 +1,127 @@
+use std::{net::SocketAddr, time::Duration};
+
+/// The number of bytes of chunk data which are queued for replication each tick, across all connections.
+pub static TICK_BYTE_BUDGET: usize = 4 * 1024 * 1024; // 4 MiB
+
+/// How long the replicator can spend collecting chunks for a single connection in a tick.
+/// Needed because collecting chunks can consume tens of ms per frame without rate-limiting.
+pub static PERF_BUDGET_PER_CONNECTION: Duration = Duration::from_micros(500); // 0.5 ms
+
+/// Shares the replicator's work each tick fairly between connections,
+/// so no single connection can monopolize a tick.
+///
+/// Chunks are handed out one at a time to whichever connection has been given the fewest bytes so far in the tick,
+/// until the [`byte budget`](TICK_BYTE_BUDGET) of the tick is spent. A connection which joins while others are
+/// in the middle of receiving their chunks gets the same share as everyone else, instead of waiting for them to finish.
+/// Ties are broken by an order of connections which rotates each tick.
+pub struct Bandwidth {
+	tick_byte_budget: usize,
+	perf_budget_per_connection: Duration,
+	/// The number of places the order of connections is rotated by.
+	rotation: usize,
+}
+
+impl Default for Bandwidth {
+	fn default() -> Self {
+		Self {
+			tick_byte_budget: TICK_BYTE_BUDGET,
+			perf_budget_per_connection: PERF_BUDGET_PER_CONNECTION,
+			rotation: 0,
+		}
+	}
+}
+
+impl Bandwidth {
+	pub fn with_tick_byte_budget(mut self, bytes: usize) -> Self {
+		self.tick_byte_budget = bytes;
+		self
+	}
+
+	pub fn tick_byte_budget(&self) -> usize {
+		self.tick_byte_budget
+	}
+
+	pub fn with_perf_budget_per_connection(mut self, duration: Duration) -> Self {
+		self.perf_budget_per_connection = duration;
+		self
+	}
+
+	pub fn perf_budget_per_connection(&self) -> Duration {
+		self.perf_budget_per_connection
+	}
+
+	/// Returns the order connections are served in during the current tick.
+	pub fn order<'a>(&self, addresses: impl Iterator<Item = &'a SocketAddr>) -> Vec<SocketAddr> {
+		let mut order = addresses.cloned().collect::<Vec<_>>();
+		order.sort();
+		if !order.is_empty() {
+			let rotation = self.rotation % order.len();
+			order.rotate_left(rotation);
+		}
+		order
+	}
+
+	/// Rotates the order of connections for the next tick.
+	pub fn advance(&mut self) {
+		self.rotation = self.rotation.wrapping_add(1);
+	}
+}
+
+/// The chunks which have been collected for a connection during a tick.
+pub(super) struct Share {
+	pub address: SocketAddr,
+	/// The number of bytes of chunk data collected for the connection.
+	pub bytes: usize,
+	/// How many more chunks the connection can be sent before it is saturated.
+	pub capacity: usize,
+	/// How long has been spent collecting chunks for the connection.
+	pub elapsed: Duration,
+	/// How long can be spent collecting chunks for the connection before it is done for the tick.
+	pub perf_budget: Duration,
+	/// True if no more chunks can be collected for the connection this tick.
+	pub is_done: bool,
+}
+
+impl Share {
+	pub fn new(address: SocketAddr, capacity: usize, perf_budget: Duration) -> Self {
+		Self {
+			address,
+			bytes: 0,
+			capacity,
+			elapsed: Duration::ZERO,
+			perf_budget,
+			is_done: capacity == 0,
+		}
+	}
+
+	pub fn take(&mut self, size: usize) {
+		self.bytes += size;
+		self.capacity = self.capacity.saturating_sub(1);
+		if self.capacity == 0 {
+			self.is_done = true;
+		}
+	}
+
+	pub fn add_elapsed(&mut self, duration: Duration) {
+		self.elapsed += duration;
+		if self.elapsed >= self.perf_budget {
+			self.is_done = true;
+		}
+	}
+}
+
+/// Returns the share which should be given the next chunk,
+/// which is the connection that has been given the fewest bytes (the earliest in `shares` if tied).
+pub(super) fn next_share(shares: &mut Vec<Share>) -> Option<&mut Share> {
+	let mut next: Option<&mut Share> = None;
+	for share in shares.iter_mut().filter(|share| !share.is_done) {
+		let has_fewer_bytes = next
+			.as_ref()
+			.map(|current| share.bytes < current.bytes)
+			.unwrap_or(true);
+		if has_fewer_bytes {
+			next = Some(share);
+		}
+	}
+	next
+}
@@ -0,0 +1,253 @@
+use super::SerializedEntities;
+use crate::{
+	common::network::replication::entity::Update,
+	entity::component::binary::{SerializedComponent, SerializedEntity},
+};
+use std::collections::HashMap;
+
+/// The version (replicator update) that each replicated component of an entity last changed at.
+pub type ComponentVersions = HashMap<String, u64>;
+
+/// Tracks when each replicated component of each entity last changed,
+/// so connections which already have an entity are only sent the components which changed since they were last synced.
+///
+/// A component has changed if its serialized data differs from the last time it was replicated,
+/// so components do not need to report their own mutations.
+#[derive(Default)]
+pub struct ChangeVersions {
+	version: u64,
+	entities: HashMap<hecs::Entity, HashMap<String, (u64, Vec<u8>)>>,
+}
+
+impl ChangeVersions {
+	/// Moves to the next replicator update, so any changes found are newer than those already sent.
+	pub fn advance(&mut self) {
+		self.version += 1;
+	}
+
+	/// Bumps the version of each component whose data has changed since it was last stamped.
+	fn stamp_entity(&mut self, serialized: &SerializedEntity) {
+		let version = self.version;
+		let components = self.entities.entry(serialized.entity).or_default();
+		for component in serialized.components.iter() {
+			match components.get_mut(&component.id) {
+				Some((_, data)) if *data == component.data => {}
+				Some(stamp) => *stamp = (version, component.data.clone()),
+				None => {
+					components.insert(component.id.clone(), (version, component.data.clone()));
+				}
+			}
+		}
+	}
+
+	/// Stamps every entity being replicated this update,
+	/// and records the version of their components for the connection handles to compare against.
+	pub fn stamp(&mut self, entities: &mut SerializedEntities) {
+		for serialized in entities.public.values() {
+			self.stamp_entity(serialized);
+		}
+		for (_, serialized) in entities.owner_only.values() {
+			self.stamp_entity(serialized);
+		}
+		entities.version = self.version;
+		for entity in entities.public.keys() {
+			if let Some(components) = self.entities.get(entity) {
+				let versions = components
+					.iter()
+					.map(|(id, (version, _))| (id.clone(), *version))
+					.collect();
+				entities.versions.insert(*entity, versions);
+			}
+		}
+	}
+
+	/// Forgets an entity which has been destroyed.
+	pub fn forget(&mut self, entity: &hecs::Entity) {
+		self.entities.remove(entity);
+	}
+}
+
+/// What a connection was last sent of each entity relevant to it.
+#[derive(Default)]
+pub struct SyncedEntities(HashMap<hecs::Entity, Synced>);
+
+struct Synced {
+	/// The version the connection was last sent the entity at.
+	version: u64,
+	/// The components the connection was sent, in the order they were serialized.
+	component_ids: Vec<String>,
+}
+
+impl SyncedEntities {
+	/// Records that the connection has the state of an entity as of `version`
+	/// (e.g. it was sent a full snapshot because the entity just became relevant).
+	pub fn sync(&mut self, serialized: &SerializedEntity, version: u64) {
+		let component_ids = serialized
+			.components
+			.iter()
+			.map(|component| component.id.clone())
+			.collect();
+		self.0.insert(
+			serialized.entity,
+			Synced {
+				version,
+				component_ids,
+			},
+		);
+	}
+
+	pub fn forget(&mut self, entity: &hecs::Entity) {
+		self.0.remove(entity);
+	}
+
+	/// Returns the update which brings the connection up to date with the serialized entity,
+	/// which only includes the components that changed since the connection was last synced.
+	/// Returns None if none of the components have changed.
+	///
+	/// If components were added to or removed from the entity (or it was never synced),
+	/// the full snapshot is sent so the connection knows which components the entity no longer has.
+	pub fn next_update(
+		&mut self,
+		serialized: &SerializedEntity,
+		versions: Option<&ComponentVersions>,
+		version: u64,
+	) -> Option<Update> {
+		let update = match (self.0.get(&serialized.entity), versions) {
+			(Some(synced), Some(versions)) if Self::same_components(synced, serialized) => {
+				let components = serialized
+					.components
+					.iter()
+					.filter(|component| match versions.get(&component.id) {
+						Some(changed_at) => *changed_at > synced.version,
+						None => true,
+					})
+					.cloned()
+					.collect::<Vec<SerializedComponent>>();
+				match components.is_empty() {
+					true => None,
+					false => Some(Update::Changed(SerializedEntity {
+						entity: serialized.entity,
+						components,
+					})),
+				}
+			}
+			_ => Some(Update::Update(serialized.clone())),
+		};
+		self.sync(serialized, version);
+		update
+	}
+
+	fn same_components(synced: &Synced, serialized: &SerializedEntity) -> bool {
+		synced.component_ids.len() == serialized.components.len()
+			&& synced
+				.component_ids
+				.iter()
+				.zip(serialized.components.iter())
+				.all(|(id, component)| *id == component.id)
+	}
+}
+
+#[cfg(test)]
+mod change_versions {
+	use super::*;
+	use crate::entity::{
+		archetype::test::Label,
+		component::{binary, physics::linear::Position, Component},
+	};
+	use engine::math::nalgebra::Point3;
+
+	fn snapshot(
+		entity: hecs::Entity,
+		position: Point3<f64>,
+		label: Point3<f64>,
+	) -> SerializedEntity {
+		let mut component = Position::default();
+		component.set_world_position(position);
+		SerializedEntity {
+			entity,
+			components: vec![
+				SerializedComponent {
+					id: Position::unique_id().to_owned(),
+					data: binary::serialize(&component).unwrap(),
+				},
+				SerializedComponent {
+					id: Label::unique_id().to_owned(),
+					data: binary::serialize(&Label::from(&label)).unwrap(),
+				},
+			],
+		}
+	}
+
+	/// Serializes and stamps the entity as the replicator would in an update.
+	fn replicate(
+		versions: &mut ChangeVersions,
+		serialized: &SerializedEntity,
+	) -> SerializedEntities {
+		versions.advance();
+		let mut entities = SerializedEntities::default();
+		entities
+			.public
+			.insert(serialized.entity, serialized.clone());
+		versions.stamp(&mut entities);
+		entities
+	}
+
+	#[test]
+	fn unchanged_components_are_omitted() {
+		let entity = hecs::World::new().spawn(());
+		let mut versions = ChangeVersions::default();
+		let mut synced = SyncedEntities::default();
+
+		let first = snapshot(entity, Point3::origin(), Point3::origin());
+		let entities = replicate(&mut versions, &first);
+		synced.sync(&first, entities.version);
+
+		// Only the label changes.
+		let second = snapshot(entity, Point3::origin(), Point3::new(1.0, 0.0, 0.0));
+		let entities = replicate(&mut versions, &second);
+		let update = synced.next_update(&second, entities.versions.get(&entity), entities.version);
+		match update {
+			Some(Update::Changed(changed)) => {
+				assert_eq!(changed.components.len(), 1);
+				assert_eq!(changed.components[0].id, Label::unique_id());
+			}
+			update => panic!("expected only the changed components, found {:?}", update),
+		}
+
+		// Nothing changes, so nothing needs to be sent.
+		let entities = replicate(&mut versions, &second);
+		let update = synced.next_update(&second, entities.versions.get(&entity), entities.version);
+		assert!(update.is_none());
+	}
+
+	#[test]
+	fn new_connection_receives_everything() {
+		let entity = hecs::World::new().spawn(());
+		let mut versions = ChangeVersions::default();
+		let first = snapshot(entity, Point3::origin(), Point3::origin());
+		replicate(&mut versions, &first);
+
+		// A connection which has not been synced (e.g. the entity just became relevant) is sent the full snapshot,
+		// even though the components have not changed since they were first stamped.
+		let mut synced = SyncedEntities::default();
+		let entities = replicate(&mut versions, &first);
+		let update = synced.next_update(&first, entities.versions.get(&entity), entities.version);
+		assert!(matches!(update, Some(Update::Update(_))));
+	}
+
+	#[test]
+	fn removed_component_sends_snapshot() {
+		let entity = hecs::World::new().spawn(());
+		let mut versions = ChangeVersions::default();
+		let mut synced = SyncedEntities::default();
+		let first = snapshot(entity, Point3::origin(), Point3::origin());
+		let entities = replicate(&mut versions, &first);
+		synced.sync(&first, entities.version);
+
+		let mut second = first.clone();
+		second.components.pop();
+		let entities = replicate(&mut versions, &second);
+		let update = synced.next_update(&second, entities.versions.get(&entity), entities.version);
+		assert!(matches!(update, Some(Update::Update(_))));
+	}
+}
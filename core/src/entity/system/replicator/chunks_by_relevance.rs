@@ -1,32 +1,56 @@
-use crate::entity::system::replicator::relevancy::{AxisAlignedBoundingBox, Relevance};
-use engine::math::nalgebra::Point3;
+use crate::{
+	common::utility::DirtySet,
+	entity::system::replicator::relevancy::{AxisAlignedBoundingBox, Relevance},
+};
+use engine::math::nalgebra::{Point3, Vector3};
 use std::collections::HashSet;
 
+/// How far the (unit) look direction of a connection needs to move before its pending chunks are sorted again,
+/// roughly the angle in radians between the old and new directions.
+static FORWARD_RESORT_THRESHOLD: f32 = 0.1;
+
 pub struct ChunksByRelevance {
-	unique_set: HashSet<Point3<i64>>,
-	// Vec sorted by relevance, where the start is the least relevant and the end is the most relevant.
-	sorted: Vec<Point3<i64>>,
+	// Sorted by relevance, where the start is the least relevant and the end is the most relevant.
+	pending: DirtySet<Point3<i64>>,
+	/// The look direction the pending chunks are sorted by (see [`Relevance::min_biased_dist`]),
+	/// so chunks in the direction the connection is looking are sent first.
+	forward: Vector3<f32>,
 }
 
 impl ChunksByRelevance {
 	pub fn new() -> Self {
 		Self {
-			unique_set: HashSet::new(),
-			sorted: Vec::new(),
+			pending: DirtySet::new(),
+			forward: Vector3::zeros(),
 		}
 	}
 
 	pub fn len(&self) -> usize {
-		self.unique_set.len()
+		self.pending.len()
+	}
+
+	pub fn contains(&self, coord: &Point3<i64>) -> bool {
+		self.pending.contains(coord)
+	}
+
+	/// Changes the look direction the pending chunks are sorted by (zero if there is none),
+	/// returning true if it changed enough that they should be [`sorted again`](Self::retain_and_sort_by).
+	pub fn set_forward(&mut self, forward: Vector3<f32>) -> bool {
+		if (forward - self.forward).magnitude() < FORWARD_RESORT_THRESHOLD {
+			return false;
+		}
+		self.forward = forward;
+		true
 	}
 
 	fn cmp_relevance(
 		a: &Point3<i64>,
 		b: &Point3<i64>,
 		relevance: &Relevance,
+		forward: &Vector3<f32>,
 	) -> std::cmp::Ordering {
-		let a_dist = relevance.min_dist_to_relevance(&a);
-		let b_dist = relevance.min_dist_to_relevance(&b);
+		let a_dist = relevance.min_biased_dist(&a, forward);
+		let b_dist = relevance.min_biased_dist(&b, forward);
 		b_dist
 			.partial_cmp(&a_dist)
 			.unwrap_or(std::cmp::Ordering::Equal)
@@ -34,16 +58,10 @@ impl ChunksByRelevance {
 
 	#[profiling::function]
 	pub fn retain_and_sort_by(&mut self, relevance: &Relevance) {
-		self.retain(relevance);
-		self.sorted
-			.sort_by(|a, b| Self::cmp_relevance(a, b, relevance));
-	}
-
-	#[profiling::function]
-	fn retain(&mut self, relevance: &Relevance) {
-		self.unique_set
-			.retain(|coord| relevance.is_relevant(&coord));
-		self.sorted.retain(|coord| relevance.is_relevant(&coord));
+		self.pending.retain(|coord| relevance.is_relevant(&coord));
+		let forward = self.forward;
+		self.pending
+			.sort_by(|a, b| Self::cmp_relevance(a, b, relevance, &forward));
 	}
 
 	#[profiling::function]
@@ -68,12 +86,12 @@ impl ChunksByRelevance {
 		coord: &Point3<i64>,
 		relevance: &Relevance,
 	) -> Option<usize> {
-		if self.unique_set.contains(coord) {
+		if self.pending.contains(coord) {
 			return None;
 		}
 		let search_res = self
-			.sorted
-			.binary_search_by(|a| Self::cmp_relevance(a, &coord, relevance));
+			.pending
+			.binary_search_by(|a| Self::cmp_relevance(a, &coord, relevance, &self.forward));
 		Some(match search_res {
 			Ok(idx) => idx,
 			Err(idx) => idx,
@@ -82,23 +100,91 @@ impl ChunksByRelevance {
 
 	#[profiling::function]
 	pub fn insert(&mut self, idx: usize, coord: Point3<i64>) {
-		if self.unique_set.insert(coord) {
-			self.sorted.insert(idx, coord);
-		}
+		self.pending.mark_at(idx, coord);
 	}
 
 	#[profiling::function]
 	pub fn pop_front(&mut self) -> Option<Point3<i64>> {
-		match self.sorted.pop() {
-			Some(coord) => {
-				self.unique_set.remove(&coord);
-				Some(coord)
-			}
-			None => None,
-		}
+		self.pending.pop_back()
 	}
 
 	pub fn into_sorted(self) -> Vec<Point3<i64>> {
-		self.sorted
+		self.pending.into_vec()
+	}
+}
+
+#[cfg(test)]
+mod look_direction {
+	use super::*;
+	use crate::entity::system::replicator::relevancy::Area;
+
+	fn pop_all(forward: Vector3<f32>, coords: &[Point3<i64>]) -> Vec<Point3<i64>> {
+		let mut relevance = Relevance::default();
+		relevance.push(Area::new(Point3::new(0, 0, 0), 4));
+		let mut chunks = ChunksByRelevance::new();
+		chunks.set_forward(forward);
+		for coord in coords.iter() {
+			let idx = chunks.find_insertion_point(coord, &relevance).unwrap();
+			chunks.insert(idx, *coord);
+		}
+		std::iter::from_fn(|| chunks.pop_front()).collect()
+	}
+
+	#[test]
+	fn chunks_being_looked_at_are_first() {
+		let above = Point3::new(0, 2, 0);
+		let below = Point3::new(0, -2, 0);
+		let up = Vector3::new(0.0, 1.0, 0.0);
+		assert_eq!(pop_all(up, &[below, above]), vec![above, below]);
+		assert_eq!(pop_all(-up, &[above, below]), vec![below, above]);
+		// Looking only slightly up still favors the chunk above.
+		let slightly_up = Vector3::new(0.0, 0.5, 0.866);
+		assert_eq!(pop_all(slightly_up, &[below, above])[0], above);
+	}
+
+	#[test]
+	fn chunks_ahead_are_first() {
+		let east = Point3::new(2, 0, 0);
+		let west = Point3::new(-2, 0, 0);
+		let north = Point3::new(0, 0, -2);
+		let forward = Vector3::new(1.0, 0.0, 0.0);
+		assert_eq!(
+			pop_all(forward, &[west, north, east]),
+			vec![east, north, west]
+		);
+		assert_eq!(
+			pop_all(-forward, &[east, north, west]),
+			vec![west, north, east]
+		);
+		// Chunks at the horizon are still sorted by the horizontal look direction.
+		let north_east = Vector3::new(1.0, 0.0, -1.0).normalize();
+		assert_eq!(pop_all(north_east, &[west, east, north])[2], west);
+	}
+
+	#[test]
+	fn no_look_direction_sorts_by_distance() {
+		let coords = [
+			Point3::new(0, -3, 0),
+			Point3::new(2, 0, 0),
+			Point3::new(0, 1, 0),
+			Point3::new(0, 0, -4),
+		];
+		let expected = vec![
+			Point3::new(0, 1, 0),
+			Point3::new(2, 0, 0),
+			Point3::new(0, -3, 0),
+			Point3::new(0, 0, -4),
+		];
+		assert_eq!(pop_all(Vector3::zeros(), &coords), expected);
+	}
+
+	#[test]
+	fn small_look_changes_do_not_resort() {
+		let mut chunks = ChunksByRelevance::new();
+		assert!(!chunks.set_forward(Vector3::new(0.0, 0.05, 0.0)));
+		assert!(chunks.set_forward(Vector3::new(1.0, 0.0, 0.0)));
+		assert!(!chunks.set_forward(Vector3::new(0.998, 0.05, 0.0)));
+		assert!(chunks.set_forward(Vector3::new(0.0, 0.0, 1.0)));
+		assert!(chunks.set_forward(Vector3::zeros()));
 	}
 }
@@ -1,12 +1,30 @@
-use super::{relevancy, EntityOperation};
+use super::{
+	recording::{self, ArcLockRecorder},
+	relevancy, ConnectionState, EntityOperation, SyncedEntities,
+};
 use crate::{
+	block,
 	client::world::chunk::OperationSender as ClientChunkOperationSender,
-	common::network::replication::{self, entity},
-	entity::{component::binary, system::replicator::ChunksByRelevance},
+	common::network::{
+		replication::{self, entity, world::Backlog},
+		world_ready,
+	},
+	entity::{
+		component::PersistentId,
+		system::replicator::{ChunksByRelevance, SerializedEntities},
+	},
+	server::network::DEFAULT_WORLD,
 };
 use socknet::connection::Connection;
 use std::{collections::HashMap, net::SocketAddr, sync::Weak};
 
+/// The maximum number of chunks which can be waiting on the replication streams of a connection
+/// before the replicator stops queuing more.
+const MAX_IN_FLIGHT_CHUNKS: usize = 64;
+/// The maximum number of bytes of chunk data which can be waiting on the replication streams of a connection
+/// before the replicator stops queuing more.
+const MAX_PENDING_BYTES: usize = 2 * 1024 * 1024; // 2 MiB
+
 /// Stateful information about what is relevant to a specific client.
 ///
 /// Also servers as the connective tissue between the
@@ -15,11 +33,31 @@ use std::{collections::HashMap, net::SocketAddr, sync::Weak};
 ///
 /// Its lifetime is owned by the replicator system.
 pub struct Handle {
+	address: SocketAddr,
 	channel: UpdateChannel,
+	/// The name of the world the client has been sent the chunks of.
+	world: String,
 	chunk_relevance: relevancy::Relevance,
 	entity_relevance: relevancy::Relevance,
 	relevancy_log: String,
 	pending_chunks: ChunksByRelevance,
+	/// The last state of each relevant entity that was sent to the client,
+	/// so updates which only move an entity can be sent as a position delta.
+	entity_baselines: HashMap<hecs::Entity, entity::Baseline>,
+	/// The version each relevant entity was last sent at,
+	/// so updates only include the components which changed since then.
+	synced_entities: SyncedEntities,
+	/// The persistent id of each relevant entity which has one,
+	/// so recordings identify those entities the same way across server restarts.
+	persistent_ids: HashMap<hecs::Entity, PersistentId>,
+	/// The chunks which have been sent to the replication streams but not yet written to the connection.
+	backlog: Backlog,
+	/// The connection to notify once the world around the client has been replicated.
+	/// Taken when the world ready signal is sent, so the client is only notified once.
+	/// Always None for local connections, which share the server's world.
+	awaiting_world_ready: Option<Weak<Connection>>,
+	/// Records everything sent to the client, if replication recording is enabled.
+	recorder: Option<ArcLockRecorder>,
 }
 
 enum UpdateChannel {
@@ -40,30 +78,67 @@ impl Handle {
 		Ok(Self::new(address, UpdateChannel::Local(chunk_sender)))
 	}
 
-	pub fn new_remote(address: &SocketAddr, connection: &Weak<Connection>) -> anyhow::Result<Self> {
+	pub fn new_remote(
+		address: &SocketAddr,
+		connection: &Weak<Connection>,
+		world_seed: u64,
+	) -> anyhow::Result<Self> {
+		let backlog = Backlog::default();
 		let (send_world_rel, recv_world_rel) = engine::channels::future::unbounded();
 		let (send_entities, recv_entities) = engine::channels::future::unbounded();
 		let (send_chunks, recv_chunks) = engine::channels::future::unbounded();
 
 		replication::entity::spawn(connection.clone(), recv_entities)?;
-		replication::world::relevancy::spawn(connection.clone(), recv_world_rel, send_chunks)?;
+		replication::world::relevancy::spawn(
+			connection.clone(),
+			recv_world_rel,
+			send_chunks,
+			world_seed,
+		)?;
 		for i in 0..10 {
-			replication::world::chunk::spawn(connection.clone(), i, recv_chunks.clone())?;
+			replication::world::chunk::spawn(
+				connection.clone(),
+				i,
+				recv_chunks.clone(),
+				backlog.clone(),
+			)?;
 		}
 
 		let channel = UpdateChannel::Remote(send_world_rel, send_entities);
 
-		Ok(Self::new(address, channel))
+		let mut handle = Self::new(address, channel);
+		handle.backlog = backlog;
+		handle.awaiting_world_ready = Some(connection.clone());
+		Ok(handle)
 	}
 
 	fn new(address: &SocketAddr, channel: UpdateChannel) -> Self {
 		let relevancy_log = format!("relevancy[{}]", address);
 		Self {
+			address: address.clone(),
 			channel,
+			world: DEFAULT_WORLD.to_owned(),
 			chunk_relevance: relevancy::Relevance::default(),
 			entity_relevance: relevancy::Relevance::default(),
 			relevancy_log,
 			pending_chunks: ChunksByRelevance::new(),
+			entity_baselines: HashMap::new(),
+			synced_entities: SyncedEntities::default(),
+			persistent_ids: HashMap::new(),
+			backlog: Backlog::default(),
+			awaiting_world_ready: None,
+			recorder: None,
+		}
+	}
+
+	pub fn with_recorder(mut self, recorder: ArcLockRecorder) -> Self {
+		self.recorder = Some(recorder);
+		self
+	}
+
+	fn record(&self, event: recording::Event) {
+		if let Some(recorder) = &self.recorder {
+			recorder.lock().unwrap().record(self.address, event);
 		}
 	}
 
@@ -88,25 +163,98 @@ impl Handle {
 					}
 				}
 				relevancy::Update::Entity(relevance) => {
+					self.record(recording::Event::EntityRelevance(relevance.clone()));
 					self.entity_relevance = relevance;
 				}
 			}
 		}
 	}
 
+	/// The name of the world the client has been sent the chunks of.
+	pub fn world(&self) -> &str {
+		&self.world
+	}
+
+	/// Moves the client into another world.
+	/// The client discards every chunk it was sent, and is sent the chunks around it again from the new world,
+	/// which it predicts with `world_seed` until they arrive.
+	pub fn change_world(&mut self, world: &str, world_seed: u64) {
+		self.world = world.to_owned();
+		self.pending_chunks = ChunksByRelevance::new();
+		let relevance = relevancy::Relevance::default();
+		self.send_world_update(relevancy::WorldUpdate::Relevance(relevance.clone()));
+		self.chunk_relevance = relevance;
+		self.send_world_update(relevancy::WorldUpdate::Seed(world_seed));
+	}
+
+	/// Sends the blocks which changed this tick to the client,
+	/// if the chunk they are in is relevant and has already been sent.
+	/// Chunks which are still pending will include the changes when they are sent.
+	pub fn send_block_changes(
+		&mut self,
+		changes: &[(block::Point, Option<(block::LookupId, block::State)>, u64)],
+	) {
+		let changes = changes
+			.iter()
+			.filter(|(point, _, _)| {
+				self.chunk_relevance.is_relevant(point.chunk())
+					&& !self.pending_chunks.contains(point.chunk())
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+		if changes.is_empty() {
+			return;
+		}
+		self.send_world_update(relevancy::WorldUpdate::BlockChanges(changes));
+	}
+
 	fn send_world_update(&mut self, update: relevancy::WorldUpdate) {
 		use engine::channels::future::TrySendError;
+		if self.recorder.is_some() {
+			let event = match &update {
+				relevancy::WorldUpdate::Relevance(relevance) => {
+					Some(recording::Event::ChunkRelevance(relevance.clone()))
+				}
+				relevancy::WorldUpdate::Chunks(new_chunks) => Some(recording::Event::Chunks(
+					new_chunks
+						.iter()
+						.filter_map(|queued| queued.chunk.upgrade())
+						.map(|arc_chunk| arc_chunk.read().unwrap().chunk.coordinate.clone())
+						.collect(),
+				)),
+				relevancy::WorldUpdate::BlockChanges(changes) => {
+					Some(recording::Event::BlockChanges(changes.clone()))
+				}
+				// Recordings are replayed without generating chunks, so the seed is not needed.
+				relevancy::WorldUpdate::Seed(_) => None,
+			};
+			if let Some(event) = event {
+				self.record(event);
+			}
+		}
 		match &self.channel {
 			UpdateChannel::Remote(send_world_rel, _) => {
-				if let Err(err) = send_world_rel.try_send(update) {
-					match err {
+				// Chunks are only counted as in flight once they have actually been queued for the stream.
+				let sizes = match &update {
+					relevancy::WorldUpdate::Chunks(new_chunks) => {
+						new_chunks.iter().map(|queued| queued.size).collect()
+					}
+					_ => Vec::new(),
+				};
+				match send_world_rel.try_send(update) {
+					Ok(()) => {
+						for size in sizes.into_iter() {
+							self.backlog.push(size);
+						}
+					}
+					Err(err) => match err {
 						TrySendError::Full(_) => {
 							log::error!(target: &self.relevancy_log, "Failed to send relevancy delta, unbounded async channel is full. This should never happen.");
 						}
 						TrySendError::Closed(_) => {
 							log::error!(target: &self.relevancy_log, "Failed to send relevancy delta, channel is closed. This should never happen because the channel can only be closed if the stream handle is dropped.");
 						}
-					}
+					},
 				}
 			}
 			UpdateChannel::Local(chunk_sender) => {
@@ -115,7 +263,7 @@ impl Handle {
 					relevancy::WorldUpdate::Relevance(relevance) => {
 						let mut chunks_to_remove = ChunksByRelevance::new();
 						chunks_to_remove.insert_cuboids(
-							self.chunk_relevance.difference(&relevance),
+							self.chunk_relevance.moved_difference(&relevance),
 							&self.chunk_relevance,
 						);
 						for coord in chunks_to_remove.into_sorted().into_iter() {
@@ -123,24 +271,28 @@ impl Handle {
 						}
 					}
 					relevancy::WorldUpdate::Chunks(new_chunks) => {
-						for weak_chunk in new_chunks.into_iter() {
-							let operation = match weak_chunk.upgrade() {
+						for queued in new_chunks.into_iter() {
+							let operation = match queued.chunk.upgrade() {
 								Some(arc_chunk) => {
 									let server_chunk = arc_chunk.read().unwrap();
 									let coord = server_chunk.chunk.coordinate.clone();
-									let updates = server_chunk
-										.chunk
-										.block_ids
-										.iter()
-										.map(|(offset, id)| (*offset, *id))
-										.collect::<Vec<_>>();
-									Operation::Insert(coord, updates)
+									Operation::Insert(
+										coord,
+										server_chunk.chunk.version(),
+										server_chunk.chunk.blocks(),
+									)
 								}
 								None => continue,
 							};
 							let _ = chunk_sender.try_send(operation);
 						}
 					}
+					relevancy::WorldUpdate::BlockChanges(changes) => {
+						let _ = chunk_sender.try_send(Operation::SetBlocks(changes));
+					}
+					relevancy::WorldUpdate::Seed(seed) => {
+						let _ = chunk_sender.try_send(Operation::Seed(seed));
+					}
 				}
 			}
 		}
@@ -154,6 +306,71 @@ impl Handle {
 		&mut self.pending_chunks
 	}
 
+	/// The shared counters of chunks which are waiting to be written to the connection.
+	pub fn backlog(&self) -> &Backlog {
+		&self.backlog
+	}
+
+	/// The number of chunks which have been queued for replication but not yet written to the connection.
+	pub fn in_flight_chunks(&self) -> usize {
+		self.backlog.chunks()
+	}
+
+	/// The number of bytes of chunk data which have been queued for replication but not yet written to the connection.
+	pub fn pending_bytes(&self) -> usize {
+		self.backlog.bytes()
+	}
+
+	/// Returns how many more chunks can be queued for replication before the connection is saturated.
+	/// Always zero if the connection already has too many bytes waiting to be written.
+	pub fn chunk_capacity(&self) -> usize {
+		if self.pending_bytes() >= MAX_PENDING_BYTES {
+			return 0;
+		}
+		MAX_IN_FLIGHT_CHUNKS.saturating_sub(self.in_flight_chunks())
+	}
+
+	/// Returns true if the client has been sent every chunk that is currently relevant to it,
+	/// and all of those chunks have been written to the connection.
+	pub fn is_world_replicated(&self) -> bool {
+		!self.chunk_relevance.is_empty()
+			&& self.pending_chunks.len() == 0
+			&& self.in_flight_chunks() == 0
+	}
+
+	/// Returns true if the client has been sent the world around it (see [`is_world_replicated`](Self::is_world_replicated))
+	/// and the entities around it, including every entity it owns (e.g. its player), so it can enter the game.
+	pub fn is_ready_to_enter(&self, owned_entities_replicated: bool) -> bool {
+		self.is_world_replicated() && !self.entity_relevance.is_empty() && owned_entities_replicated
+	}
+
+	/// Returns true if the client is still waiting to be told it can enter the game.
+	pub fn is_awaiting_world_ready(&self) -> bool {
+		self.awaiting_world_ready.is_some()
+	}
+
+	/// Tells the client that it can enter the game,
+	/// if it has not been told already and it is [`ready to enter`](Self::is_ready_to_enter).
+	/// The entity updates of this tick must have been sent first, so the entities the client owns are replicated before the signal.
+	pub fn send_world_ready(&mut self, owned_entities_replicated: bool) {
+		if self.awaiting_world_ready.is_none() || !self.is_ready_to_enter(owned_entities_replicated)
+		{
+			return;
+		}
+		let connection = self.awaiting_world_ready.take().unwrap();
+		if let Err(err) = world_ready::send(connection) {
+			log::error!(target: &self.relevancy_log, "Failed to send world ready signal: {:?}", err);
+		}
+	}
+
+	pub fn connection_state(&self) -> ConnectionState {
+		match (&self.channel, &self.awaiting_world_ready) {
+			(UpdateChannel::Local(_), _) => ConnectionState::Local,
+			(UpdateChannel::Remote(_, _), Some(_)) => ConnectionState::LoadingWorld,
+			(UpdateChannel::Remote(_, _), None) => ConnectionState::InGame,
+		}
+	}
+
 	pub fn chunk_relevance(&self) -> &relevancy::Relevance {
 		&self.chunk_relevance
 	}
@@ -163,26 +380,32 @@ impl Handle {
 	}
 
 	pub fn send_entity_operations(
-		&self,
+		&mut self,
 		operations: Vec<(EntityOperation, hecs::Entity)>,
-		serialized: &HashMap<hecs::Entity, binary::SerializedEntity>,
+		serialized: &SerializedEntities,
 	) {
 		use engine::channels::future::TrySendError;
-		use replication::entity::Update;
-		if let UpdateChannel::Remote(_, send_entities) = &self.channel {
-			for (operation, entity) in operations.into_iter() {
-				let update = match operation {
-					EntityOperation::Relevant => {
-						let serialized = serialized.get(&entity).unwrap();
-						Update::Relevant(serialized.clone())
-					}
-					EntityOperation::Update => {
-						let serialized = serialized.get(&entity).unwrap();
-						Update::Update(serialized.clone())
-					}
-					EntityOperation::Irrelevant => Update::Irrelevant(entity),
-					EntityOperation::Destroyed => Update::Destroyed(entity),
-				};
+		// Local connections share the server's entity world, so updates are only built if they are sent or recorded.
+		if matches!(self.channel, UpdateChannel::Local(_)) && self.recorder.is_none() {
+			return;
+		}
+		for (operation, entity) in operations.into_iter() {
+			if let Some(id) = serialized.persistent_id(&entity) {
+				self.persistent_ids.insert(entity, *id);
+			}
+			let update = self.next_entity_update(operation, entity, serialized);
+			if self.recorder.is_some() {
+				let payload = update.clone();
+				self.record(match self.persistent_ids.get(&entity) {
+					Some(id) => recording::Event::PersistentEntity(operation, *id, payload),
+					None => recording::Event::Entity(operation, entity, payload),
+				});
+			}
+			if let EntityOperation::Irrelevant | EntityOperation::Destroyed = operation {
+				self.persistent_ids.remove(&entity);
+			}
+			if let (UpdateChannel::Remote(_, send_entities), Some(update)) = (&self.channel, update)
+			{
 				if let Err(err) = send_entities.try_send(update) {
 					match err {
 						TrySendError::Full(update) => {
@@ -196,4 +419,104 @@ impl Handle {
 			}
 		}
 	}
+
+	/// Returns the update to send to the client for an operation on an entity,
+	/// or None if the client is already up to date (or the entity was not serialized).
+	fn next_entity_update(
+		&mut self,
+		operation: EntityOperation,
+		entity: hecs::Entity,
+		serialized: &SerializedEntities,
+	) -> Option<entity::Update> {
+		use replication::entity::Update;
+		Some(match operation {
+			// The first replication of an entity is always a full snapshot,
+			// which becomes the baseline for future position deltas.
+			EntityOperation::Relevant => {
+				let version = serialized.version();
+				let serialized = serialized.get(&entity, &self.address)?;
+				self.synced_entities.sync(serialized, version);
+				match entity::Baseline::from_snapshot(&serialized) {
+					Ok(Some(baseline)) => {
+						self.entity_baselines.insert(entity, baseline);
+					}
+					Ok(None) => {}
+					Err(err) => {
+						log::error!(target: &self.relevancy_log, "Failed to read position of entity {}: {:?}", entity.id(), err);
+					}
+				}
+				Update::Relevant(serialized.clone())
+			}
+			EntityOperation::Update => {
+				let version = serialized.version();
+				let versions = serialized.versions(&entity);
+				let serialized = serialized.get(&entity, &self.address)?;
+				let delta_update = self
+					.entity_baselines
+					.get_mut(&entity)
+					.map(|baseline| baseline.next_update(&serialized));
+				let update = match delta_update {
+					Some(Ok(update)) => update,
+					Some(Err(err)) => {
+						log::error!(target: &self.relevancy_log, "Failed to read position of entity {}: {:?}", entity.id(), err);
+						Update::Update(serialized.clone())
+					}
+					None => Update::Update(serialized.clone()),
+				};
+				match update {
+					// Only the components which changed since the client was last synced need to be sent.
+					Update::Update(_) => self
+						.synced_entities
+						.next_update(serialized, versions, version)?,
+					update => {
+						self.synced_entities.sync(serialized, version);
+						update
+					}
+				}
+			}
+			EntityOperation::Irrelevant => {
+				self.entity_baselines.remove(&entity);
+				self.synced_entities.forget(&entity);
+				Update::Irrelevant(entity)
+			}
+			EntityOperation::Destroyed => {
+				self.entity_baselines.remove(&entity);
+				self.synced_entities.forget(&entity);
+				Update::Destroyed(entity)
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod backlog {
+	use super::*;
+	use crate::common::network::replication::world::QueuedChunk;
+
+	fn chunks(size: usize) -> relevancy::WorldUpdate {
+		relevancy::WorldUpdate::Chunks(vec![QueuedChunk {
+			chunk: Weak::new(),
+			size,
+		}])
+	}
+
+	#[test]
+	fn failed_sends_are_not_counted() {
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let (send_world_rel, recv_world_rel) = engine::channels::future::unbounded();
+		let (send_entities, _recv_entities) = engine::channels::future::unbounded();
+		let mut handle = Handle::new(
+			&address,
+			UpdateChannel::Remote(send_world_rel, send_entities),
+		);
+
+		handle.send_world_update(chunks(100));
+		assert_eq!(handle.backlog().chunks(), 1);
+
+		// The stream is gone, so the chunks are never written and must not count against the connection.
+		drop(recv_world_rel);
+		handle.send_world_update(chunks(50));
+		assert_eq!(handle.backlog().chunks(), 1);
+		assert_eq!(handle.backlog().bytes(), 100);
+	}
 }
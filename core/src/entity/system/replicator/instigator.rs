@@ -1,10 +1,15 @@
-use crate::entity::component::physics::linear::Position;
+use crate::{
+	entity::component::{physics::linear::Position, Parent},
+	server::network::DEFAULT_WORLD,
+};
 use engine::math::nalgebra::Point3;
+use serde::{Deserialize, Serialize};
 
 pub struct UpdatedEntity {
 	pub entity: hecs::Entity,
-	pub old_chunk: Option<Point3<i64>>,
 	pub new_chunk: Point3<i64>,
+	/// The name of the world the entity is in.
+	pub world: String,
 }
 
 impl UpdatedEntity {
@@ -19,13 +24,40 @@ impl UpdatedEntity {
 		}
 		Some(Self {
 			entity: *entity,
-			old_chunk,
 			new_chunk,
+			world: DEFAULT_WORLD.to_owned(),
 		})
 	}
+
+	/// Like [`acknowledged`](Self::acknowledged), but for a child whose relevance follows the chunk
+	/// of the root of its chain of [`parents`](Parent) (`anchor`) instead of its own position.
+	pub fn anchored(
+		entity: &hecs::Entity,
+		parent: &mut Parent,
+		position: &mut Position,
+		anchor: Point3<i64>,
+	) -> Option<Self> {
+		let old_chunk = *parent.relevant_chunk();
+		parent.acknowledge_relevant_chunk(anchor);
+		position.acknowledge_chunk();
+		if old_chunk == Some(anchor) {
+			return None;
+		}
+		Some(Self {
+			entity: *entity,
+			new_chunk: anchor,
+			world: DEFAULT_WORLD.to_owned(),
+		})
+	}
+
+	/// Marks the entity as being in the named world, rather than the default world.
+	pub fn in_world(mut self, name: &str) -> Self {
+		self.world = name.to_owned();
+		self
+	}
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntityOperation {
 	/// Entity was spawned within relevancy range, or has entered relevancy range.
 	/// Entities can enter range when relevancy-owning entities move, changing what chunks are relevant,
@@ -0,0 +1,385 @@
+//! Opt-in recording of everything the [`replicator`](super::Replicator) sends to each connection,
+//! and playback of those recordings to reconstruct what a client was told.
+//!
+//! Recording is enabled by launching the server with `-record_replication=<path>`.
+//! Each line of the recording is a json [`Entry`], in the order the replicator made the decision.
+use super::{relevancy::Relevance, EntityOperation};
+use crate::{
+	block,
+	common::network::replication::entity::{client::Replica, Update},
+	entity::{self, component::PersistentId, ArcLockEntityWorld},
+};
+use anyhow::Result;
+use engine::math::nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, HashSet},
+	fs::File,
+	io::{BufRead, BufReader, BufWriter, Write},
+	net::SocketAddr,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, RwLock},
+	time::{Duration, Instant},
+};
+
+static LOG: &'static str = "replication-recorder";
+
+/// Something the replicator sent to a connection.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Event {
+	/// The chunks which are relevant to the connection changed.
+	ChunkRelevance(Relevance),
+	/// The area in which entities are relevant to the connection changed.
+	EntityRelevance(Relevance),
+	/// Chunks were queued to be sent to the connection.
+	Chunks(Vec<Point3<i64>>),
+	/// Blocks changed in chunks which had already been sent to the connection.
+	BlockChanges(Vec<(block::Point, Option<(block::LookupId, block::State)>, u64)>),
+	/// An entity became relevant, was updated, became irrelevant, or was destroyed.
+	/// Includes the update (and the components it carries) which was sent to the connection,
+	/// or None if the connection was already up to date.
+	Entity(EntityOperation, hecs::Entity, Option<Update>),
+	/// An entity which has a [`persistent id`](PersistentId) became relevant, was updated, became irrelevant, or was destroyed.
+	/// Recorded instead of [`Entity`](Event::Entity) so the entity can be identified across server restarts.
+	PersistentEntity(EntityOperation, PersistentId, Option<Update>),
+}
+
+/// A recorded [`Event`], with the information needed to replay it in exactly the order and timing it happened.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+	/// The order the event was recorded in, across all connections.
+	/// Events recorded in the same instant are replayed in the order of their sequence.
+	pub sequence: u64,
+	/// How long after the recording started the event was recorded.
+	pub elapsed: Duration,
+	pub address: SocketAddr,
+	pub event: Event,
+}
+
+/// Writes the events sent to every connection to a file.
+/// Shared between the [`handles`](super::Handle) of all connections, so their events are interleaved in order.
+pub struct Recorder {
+	start: Instant,
+	next_sequence: u64,
+	writer: Box<dyn Write + Send + Sync>,
+}
+
+pub type ArcLockRecorder = Arc<Mutex<Recorder>>;
+
+impl Recorder {
+	/// Returns the path to record replication to, if recording was requested by the launch arguments.
+	pub fn requested_path() -> Option<PathBuf> {
+		std::env::args().find_map(|arg| {
+			arg.strip_prefix("-record_replication=")
+				.map(|path| PathBuf::from(path))
+		})
+	}
+
+	pub fn create(path: &Path) -> Result<Self> {
+		log::info!(target: LOG, "Recording replication to {}", path.display());
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		Ok(Self::new(BufWriter::new(File::create(path)?)))
+	}
+
+	/// Creates a recorder which writes to any destination (e.g. an in-memory buffer), rather than a file.
+	pub fn new<W>(writer: W) -> Self
+	where
+		W: Write + Send + Sync + 'static,
+	{
+		Self {
+			start: Instant::now(),
+			next_sequence: 0,
+			writer: Box::new(writer),
+		}
+	}
+
+	pub fn arclocked(self) -> ArcLockRecorder {
+		Arc::new(Mutex::new(self))
+	}
+
+	pub fn record(&mut self, address: SocketAddr, event: Event) {
+		let entry = Entry {
+			sequence: self.next_sequence,
+			elapsed: self.start.elapsed(),
+			address,
+			event,
+		};
+		self.next_sequence += 1;
+		if let Err(err) = self.write(&entry) {
+			log::error!(target: LOG, "Failed to record {:?}: {:?}", entry, err);
+		}
+	}
+
+	fn write(&mut self, entry: &Entry) -> Result<()> {
+		serde_json::to_writer(&mut self.writer, entry)?;
+		self.writer.write_all(b"\n")?;
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> Result<()> {
+		Ok(self.writer.flush()?)
+	}
+}
+
+impl Drop for Recorder {
+	fn drop(&mut self) {
+		if let Err(err) = self.flush() {
+			log::error!(target: LOG, "Failed to flush recording: {:?}", err);
+		}
+	}
+}
+
+/// Reads a recording made by a [`Recorder`], returning its entries in the order they were recorded.
+pub fn load(path: &Path) -> Result<Vec<Entry>> {
+	read(BufReader::new(File::open(path)?))
+}
+
+/// Reads the entries of a recording from any source, returning them in the order they were recorded.
+pub fn read<R>(reader: R) -> Result<Vec<Entry>>
+where
+	R: BufRead,
+{
+	let mut entries = Vec::new();
+	for line in reader.lines() {
+		let line = line?;
+		if line.is_empty() {
+			continue;
+		}
+		entries.push(serde_json::from_str::<Entry>(&line)?);
+	}
+	entries.sort_by_key(|entry| entry.sequence);
+	Ok(entries)
+}
+
+/// What a client has been told by the replicator, reconstructed from a recording.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ClientView {
+	pub chunk_relevance: Relevance,
+	pub entity_relevance: Relevance,
+	/// Every chunk which has been sent to the client.
+	pub chunks: HashSet<Point3<i64>>,
+	/// The latest block-type and state of each block the client was told changed, in chunks which are still relevant.
+	pub blocks: HashMap<block::Point, Option<(block::LookupId, block::State)>>,
+	/// The entities which are currently relevant to the client.
+	pub entities: HashSet<hecs::Entity>,
+	/// The entities with a persistent id which are currently relevant to the client.
+	pub persistent_entities: HashSet<PersistentId>,
+}
+
+impl ClientView {
+	pub fn apply(&mut self, event: &Event) {
+		match event {
+			Event::ChunkRelevance(relevance) => {
+				self.chunks.retain(|chunk| relevance.is_relevant(chunk));
+				self.blocks
+					.retain(|point, _| relevance.is_relevant(point.chunk()));
+				self.chunk_relevance = relevance.clone();
+			}
+			Event::EntityRelevance(relevance) => {
+				self.entity_relevance = relevance.clone();
+			}
+			Event::Chunks(chunks) => {
+				self.chunks.extend(chunks.iter().cloned());
+			}
+			Event::BlockChanges(changes) => {
+				self.blocks.extend(
+					changes
+						.iter()
+						.map(|(point, block, _version)| (*point, *block)),
+				);
+			}
+			Event::Entity(operation, entity, _update) => match operation {
+				EntityOperation::Relevant | EntityOperation::Update => {
+					self.entities.insert(*entity);
+				}
+				EntityOperation::Irrelevant | EntityOperation::Destroyed => {
+					self.entities.remove(entity);
+				}
+			},
+			Event::PersistentEntity(operation, id, _update) => match operation {
+				EntityOperation::Relevant | EntityOperation::Update => {
+					self.persistent_entities.insert(*id);
+				}
+				EntityOperation::Irrelevant | EntityOperation::Destroyed => {
+					self.persistent_entities.remove(id);
+				}
+			},
+		}
+	}
+}
+
+/// Feeds the events recorded for a single connection into a [`ClientView`],
+/// and the entity updates into a headless client (an entity world which is replicated to like a real client's).
+pub struct Player {
+	address: SocketAddr,
+	entries: std::vec::IntoIter<Entry>,
+	next: Option<Entry>,
+	view: ClientView,
+	entity_world: ArcLockEntityWorld,
+	replica: Replica,
+}
+
+impl Player {
+	pub fn new(entries: Vec<Entry>, address: SocketAddr) -> Self {
+		let mut entries = entries
+			.into_iter()
+			.filter(|entry| entry.address == address)
+			.collect::<Vec<_>>();
+		entries.sort_by_key(|entry| entry.sequence);
+		let mut entries = entries.into_iter();
+		let next = entries.next();
+		let entity_world = Arc::new(RwLock::new(entity::World::new()));
+		let replica = Replica::new(Arc::downgrade(&entity_world));
+		Self {
+			address,
+			entries,
+			next,
+			view: ClientView::default(),
+			entity_world,
+			replica,
+		}
+	}
+
+	pub fn address(&self) -> &SocketAddr {
+		&self.address
+	}
+
+	pub fn view(&self) -> &ClientView {
+		&self.view
+	}
+
+	/// The entities of the headless client, as they were replicated by the events applied so far.
+	pub fn entity_world(&self) -> &ArcLockEntityWorld {
+		&self.entity_world
+	}
+
+	/// Returns the entity of the headless client which replicates an entity of the server, if it is relevant.
+	pub fn client_entity(&self, server_entity: &hecs::Entity) -> Option<hecs::Entity> {
+		self.replica.get_client_entity(server_entity)
+	}
+
+	fn apply(&mut self, event: Event) {
+		self.view.apply(&event);
+		let update = match event {
+			Event::Entity(_, _, update) | Event::PersistentEntity(_, _, update) => update,
+			_ => None,
+		};
+		if let Some(update) = update {
+			if let Err(err) = self.replica.process_update(LOG, update) {
+				log::warn!(target: LOG, "Failed to replay entity update: {:?}", err);
+			}
+		}
+	}
+
+	/// Applies every event recorded up to `elapsed` after the recording started.
+	/// Returns the number of events which were applied.
+	pub fn advance_to(&mut self, elapsed: Duration) -> usize {
+		let mut count = 0;
+		while let Some(entry) = self.next.take() {
+			if entry.elapsed > elapsed {
+				self.next = Some(entry);
+				break;
+			}
+			self.apply(entry.event);
+			self.next = self.entries.next();
+			count += 1;
+		}
+		count
+	}
+
+	/// Applies every remaining event, returning what the client was told by the end of the recording.
+	pub fn finish(mut self) -> ClientView {
+		self.advance_to(Duration::MAX);
+		self.view
+	}
+}
+
+#[cfg(test)]
+mod replay {
+	use super::*;
+	use crate::entity::{
+		component::binary::SerializedEntity,
+		system::replicator::{relevancy, Handle, SerializedEntities},
+	};
+
+	fn relevance(x: i64, radius: u64) -> Relevance {
+		let mut relevance = Relevance::default();
+		relevance.push(relevancy::Area::new(Point3::new(x, 0, 0), radius));
+		relevance
+	}
+
+	#[test]
+	fn replay_reconstructs_relevance() {
+		let path = std::env::temp_dir().join(format!(
+			"crystal-sphinx-replication-{}.jsonl",
+			uuid::Uuid::new_v4()
+		));
+		let address: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let other: SocketAddr = "127.0.0.1:25566".parse().unwrap();
+		let entity = {
+			let mut world = hecs::World::new();
+			world.spawn((0u8,))
+		};
+		let final_relevance = relevance(1, 2);
+		{
+			let recorder = Recorder::create(&path).unwrap().arclocked();
+			let (chunk_sender, _chunk_receiver) = engine::channels::mpsc::unbounded();
+			let mut handle = Handle::new_local(&address, chunk_sender.clone())
+				.unwrap()
+				.with_recorder(recorder.clone());
+			let mut other_handle = Handle::new_local(&other, chunk_sender)
+				.unwrap()
+				.with_recorder(recorder.clone());
+			let world_update = |relevance: Relevance| {
+				relevancy::Update::World(relevancy::WorldUpdate::Relevance(relevance))
+			};
+			handle.send_relevance_updates(vec![
+				world_update(relevance(0, 2)),
+				relevancy::Update::Entity(relevance(0, 1)),
+			]);
+			other_handle.send_relevance_updates(vec![world_update(relevance(9, 1))]);
+			let mut serialized = SerializedEntities::default();
+			serialized.public.insert(
+				entity,
+				SerializedEntity {
+					entity,
+					components: Vec::new(),
+				},
+			);
+			handle.send_entity_operations(vec![(EntityOperation::Relevant, entity)], &serialized);
+			handle.send_relevance_updates(vec![
+				world_update(final_relevance.clone()),
+				relevancy::Update::Entity(relevance(1, 1)),
+			]);
+		}
+
+		let entries = load(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+		assert_eq!(entries.len(), 6);
+		assert!(entries
+			.iter()
+			.enumerate()
+			.all(|(i, entry)| entry.sequence == i as u64));
+
+		let mut player = Player::new(entries.clone(), address);
+		player.advance_to(Duration::MAX);
+		// The headless client spawned the entity from the recorded update.
+		let client_entity = player.client_entity(&entity).unwrap();
+		assert!(player
+			.entity_world()
+			.read()
+			.unwrap()
+			.contains(client_entity));
+
+		let view = player.finish();
+		assert_eq!(view.chunk_relevance, final_relevance);
+		assert_eq!(view.entity_relevance, relevance(1, 1));
+		assert_eq!(view.entities, HashSet::from([entity]));
+
+		let other_view = Player::new(entries, other).finish();
+		assert_eq!(other_view.chunk_relevance, relevance(9, 1));
+		assert!(other_view.entities.is_empty());
+	}
+}
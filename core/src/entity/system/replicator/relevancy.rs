@@ -1,24 +1,35 @@
-use crate::server::world::chunk::Chunk;
+use crate::{block, common::network::replication::world::QueuedChunk};
 use engine::channels::future::{Receiver, Sender};
 use engine::math::nalgebra::{Point3, Vector3};
-use serde::{Deserialize, Serialize};
-use std::{
-	collections::HashSet,
-	sync::{RwLock, Weak},
-};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
 
+/// A cube of chunks around a center chunk.
+/// Chunks become relevant within the `radius` (the gain radius) of the area,
+/// and once relevant, stay relevant until they are further than `radius + margin` (the [`lose radius`](Area::lose_radius)).
 #[derive(PartialEq, Eq, Serialize, Deserialize, Clone)]
-pub struct Area(Point3<i64>, u64);
+pub struct Area(Point3<i64>, u64, u64);
 
 impl std::fmt::Debug for Area {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		write!(f, "<{}, {}, {}>r{}", self.0.x, self.0.y, self.0.z, self.1)
+		write!(f, "<{}, {}, {}>r{}", self.0.x, self.0.y, self.0.z, self.1)?;
+		if self.2 > 0 {
+			write!(f, "+{}", self.2)?;
+		}
+		Ok(())
 	}
 }
 
 impl Area {
 	pub fn new(point: Point3<i64>, radius: u64) -> Self {
-		Self(point, radius)
+		Self(point, radius, 0)
+	}
+
+	/// Keeps chunks relevant until they are `margin` chunks beyond the radius of the area,
+	/// so moving back and forth across the edge of a chunk doesn't repeatedly gain and lose the chunks at the edge of relevance.
+	pub fn with_margin(mut self, margin: u64) -> Self {
+		self.2 = margin;
+		self
 	}
 
 	pub fn is_relevant(&self, chunk: &Point3<i64>) -> bool {
@@ -28,21 +39,155 @@ impl Area {
 			&& offset.z.abs() as u64 <= self.1;
 	}
 
+	/// The number of chunks from the center of the area (in each direction) which are relevant.
+	pub fn radius(&self) -> u64 {
+		self.1
+	}
+
+	/// The number of chunks from the center of the area (in each direction) which stay relevant once they are relevant.
+	pub fn lose_radius(&self) -> u64 {
+		self.1 + self.2
+	}
+
+	/// Returns true if every chunk relevant to `other` is within the [`lose radius`](Self::lose_radius) of this area.
+	fn retains(&self, other: &Self) -> bool {
+		let offset = other.0 - self.0;
+		(0..3).all(|axis| offset[axis].abs() as u64 + other.1 <= self.lose_radius())
+	}
+
 	pub fn min_dist_to_relevance(&self, chunk: &Point3<i64>) -> f64 {
 		let offset = chunk - self.0;
 		offset.cast::<f64>().magnitude()
 	}
+
+	fn cuboid(&self) -> AxisAlignedBoundingBox {
+		let radius = self.1 as i64;
+		let radius_vec = Vector3::new(radius, radius, radius);
+		let one = Vector3::new(1, 1, 1);
+		AxisAlignedBoundingBox {
+			// inclusive min bound
+			min: self.0 - radius_vec,
+			// exclusive max bound (radius is inclusive, so we must increment by 1)
+			max: self.0 + radius_vec + one,
+		}
+	}
+
+	/// Returns the slabs of chunks which are relevant to `self` but were not relevant to `previous`,
+	/// if both areas have the same radius (i.e. `self` is `previous` after its origin moved).
+	/// Unlike the [`AABB difference`](AxisAlignedBoundingBox::difference), this results in at most one slab per axis.
+	fn entered_slabs(&self, previous: &Self) -> Option<Vec<AxisAlignedBoundingBox>> {
+		if self.1 != previous.1 {
+			return None;
+		}
+		let current = self.cuboid();
+		let previous = previous.cuboid();
+		if !current.intersects(&previous) {
+			return Some(vec![current]);
+		}
+		let mut slabs = Vec::with_capacity(3);
+		// The part of `current` which is not yet covered by a slab.
+		// After each axis, this is bounded by `previous` on that axis,
+		// so slabs on later axes don't overlap the slabs on earlier axes.
+		let mut remaining = current;
+		for axis in 0..3 {
+			let mut slab = remaining;
+			if current.min[axis] > previous.min[axis] {
+				slab.min[axis] = previous.max[axis];
+				remaining.max[axis] = previous.max[axis];
+			} else if current.min[axis] < previous.min[axis] {
+				slab.max[axis] = previous.min[axis];
+				remaining.min[axis] = previous.min[axis];
+			} else {
+				continue;
+			}
+			slabs.push(slab);
+		}
+		Some(slabs)
+	}
 }
 
 #[derive(Default)]
 pub struct PairedRelevance {
+	/// The name of the world the connection's entity is in, which both areas are in.
+	pub world: String,
 	pub chunk: Relevance,
 	pub entity: Relevance,
+	/// The (unit) direction the connection's entity is looking, if it has an orientation.
+	pub forward: Option<Vector3<f32>>,
 }
 
-#[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+/// How much the look direction of a connection affects which pending chunks are sent first.
+/// At `1.0`, a chunk directly in the direction being looked is treated as if it had no distance at all.
+pub static LOOK_BIAS: f64 = 0.5;
+
+/// The version of the serialized layout of [`Relevance`] and the [`Area`]s it contains.
+/// Must be incremented whenever either type changes in a way that affects its serialized form,
+/// so that peers running different versions reject each other's updates instead of misparsing them.
+///
+/// Version 3 sends relevance as a [`Message`](crate::common::network::replication::world::relevancy::Message)
+/// alongside block changes (which carry the state of each block).
+pub static RELEVANCE_SCHEMA_VERSION: u16 = 3;
+
+/// Relevance is serialized as a tuple of ([`schema version`](RELEVANCE_SCHEMA_VERSION), areas).
+#[derive(PartialEq, Eq, Clone, Default)]
 pub struct Relevance(Vec<Area>);
 
+impl Serialize for Relevance {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		use serde::ser::SerializeTuple;
+		let mut tuple = serializer.serialize_tuple(2)?;
+		tuple.serialize_element(&RELEVANCE_SCHEMA_VERSION)?;
+		tuple.serialize_element(&self.0)?;
+		tuple.end()
+	}
+}
+
+impl<'de> Deserialize<'de> for Relevance {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct Visitor;
+		impl<'de> serde::de::Visitor<'de> for Visitor {
+			type Value = Relevance;
+
+			fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+				write!(f, "a schema version followed by a list of areas")
+			}
+
+			fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+			where
+				A: serde::de::SeqAccess<'de>,
+			{
+				use serde::de::Error;
+				let version = seq
+					.next_element::<u16>()?
+					.ok_or_else(|| A::Error::invalid_length(0, &self))?;
+				// The layout of the remaining data is unknown if the version doesn't match,
+				// so it must not be parsed at all.
+				if version != RELEVANCE_SCHEMA_VERSION {
+					return Err(A::Error::custom(UnsupportedVersion(version)));
+				}
+				let areas = seq
+					.next_element::<Vec<Area>>()?
+					.ok_or_else(|| A::Error::invalid_length(1, &self))?;
+				Ok(Relevance(areas))
+			}
+		}
+		deserializer.deserialize_tuple(2, Visitor)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error(
+	"relevance schema version {0} is not supported (expected version {})",
+	RELEVANCE_SCHEMA_VERSION
+)]
+pub struct UnsupportedVersion(pub u16);
+
 impl std::fmt::Debug for Relevance {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(f, "Relevance({:?})", self.0)
@@ -54,20 +199,77 @@ impl Relevance {
 		self.0.push(area);
 	}
 
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns the relevance with the areas of `previous` which are still within the
+	/// [`lose radius`](Area::lose_radius) of an area in `self`, so the chunks relevant to them stay relevant.
+	///
+	/// Areas which are not entirely within the lose radius are dropped (instead of growing the relevance as its areas move),
+	/// so every chunk that is relevant is always within the lose radius of the current areas.
+	pub fn retaining(mut self, previous: &Relevance) -> Self {
+		let retained = previous
+			.0
+			.iter()
+			.filter(|area| !self.0.contains(area))
+			.filter(|area| self.0.iter().any(|current| current.retains(area)))
+			.cloned()
+			.collect::<Vec<_>>();
+		if !retained.is_empty() {
+			self.0.extend(retained);
+			// Areas are in a consistent order, so returning to an earlier set of areas results in an equal relevance.
+			self.0
+				.sort_by_key(|area| (area.0.x, area.0.y, area.0.z, area.1, area.2));
+		}
+		self
+	}
+
+	/// Returns the radius of the largest area, or None if nothing is relevant.
+	pub fn max_radius(&self) -> Option<u64> {
+		self.0.iter().map(Area::radius).max()
+	}
+
+	/// Returns the cuboid of chunks in each area, which may overlap.
+	pub fn iter_cuboids(&self) -> impl Iterator<Item = AxisAlignedBoundingBox> + '_ {
+		self.0.iter().map(|area| area.cuboid())
+	}
+
+	/// Returns true if any two areas overlap or are adjacent,
+	/// in which case [`normalizing`](Self::normalize) them results in fewer cuboids.
+	fn has_overlapping_areas(&self) -> bool {
+		for (i, area) in self.0.iter().enumerate() {
+			let cuboid = area.cuboid();
+			if self.0[i + 1..]
+				.iter()
+				.any(|other| cuboid.touches(&other.cuboid()))
+			{
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Returns a set of disjoint cuboids which cover exactly the chunks that are relevant,
+	/// merging overlapping and adjacent areas so there are as few cuboids as possible.
+	/// Areas whose union is not a box remain as multiple cuboids, rather than a bounding box which would include irrelevant chunks.
 	#[profiling::function]
-	fn as_cuboids(&self) -> HashSet<AxisAlignedBoundingBox> {
-		let mut cuboids = HashSet::new();
-		for area in self.0.iter() {
-			let radius = area.1 as i64;
-			let radius_vec = Vector3::new(radius, radius, radius);
-			let one = Vector3::new(1, 1, 1);
-			cuboids.insert(AxisAlignedBoundingBox {
-				// inclusive min bound
-				min: area.0 - radius_vec,
-				// exclusive max bound (radius is inclusive, so we must increment by 1)
-				max: area.0 + radius_vec + one,
-			});
+	pub fn normalize(&self) -> Vec<AxisAlignedBoundingBox> {
+		let mut cuboids: Vec<AxisAlignedBoundingBox> = Vec::with_capacity(self.0.len());
+		let mut pieces = Vec::new();
+		let mut scratch = Vec::new();
+		for area_cuboid in self.iter_cuboids() {
+			// Only the parts of the area which are not already covered are added.
+			pieces.push(area_cuboid);
+			for existing in cuboids.iter() {
+				for piece in pieces.drain(..) {
+					piece.difference_into(existing, &mut scratch);
+				}
+				std::mem::swap(&mut pieces, &mut scratch);
+			}
+			cuboids.extend(pieces.drain(..));
 		}
+		AxisAlignedBoundingBox::merge_adjacent(&mut cuboids);
 		cuboids
 	}
 
@@ -91,6 +293,27 @@ impl Relevance {
 		dist
 	}
 
+	/// Like [`min_dist_to_relevance`](Self::min_dist_to_relevance), but chunks in the `forward` direction from the origin of an area
+	/// are treated as closer, and chunks behind it as further, by how closely they align with it (see [`LOOK_BIAS`]).
+	/// A zero `forward` (not looking in any direction) results in the unbiased distance.
+	pub fn min_biased_dist(&self, chunk: &Point3<i64>, forward: &Vector3<f32>) -> f64 {
+		let forward = forward.cast::<f64>();
+		let mut dist = f64::MAX;
+		for area in self.0.iter() {
+			let offset = (chunk - area.0).cast::<f64>();
+			let magnitude = offset.magnitude();
+			if magnitude == 0.0 {
+				return 0.0;
+			}
+			let alignment = forward.dot(&offset) / magnitude;
+			let d = magnitude * (1.0 - LOOK_BIAS * alignment);
+			if d < dist {
+				dist = d;
+			}
+		}
+		dist
+	}
+
 	#[profiling::function]
 	pub fn difference(&self, other: &Relevance) -> HashSet<AxisAlignedBoundingBox> {
 		// M1: This has terrible performance: like 20ms+ for a diff between 2 radial areas
@@ -111,20 +334,43 @@ impl Relevance {
 		self_chunks
 		*/
 
-		// M3
-		let mut cuboids = self.as_cuboids();
-		for other_cuboid in other.as_cuboids().into_iter() {
-			let mut resulting_cuboids = HashSet::with_capacity(cuboids.len());
-			for cuboid in cuboids.into_iter() {
-				if let Some(not_in_other) = cuboid.difference(&other_cuboid) {
-					for cuboid in not_in_other.into_iter() {
-						resulting_cuboids.insert(cuboid);
-					}
-				}
+		// M3: Subtract each of the other cuboids from the remaining cuboids of self.
+		// The remaining cuboids are swapped with a scratch list for each of the other cuboids,
+		// so the only allocations are when a cuboid is subdivided into more cuboids than the lists can hold.
+		// Duplicate cuboids (from overlapping areas in self) are removed when the final set is collected.
+		// Overlapping areas are normalized first, so the same chunks are not subtracted (or returned) more than once.
+		let mut cuboids = match self.has_overlapping_areas() {
+			true => self.normalize(),
+			false => self.iter_cuboids().collect::<Vec<_>>(),
+		};
+		let mut scratch = Vec::with_capacity(cuboids.len());
+		let mut subtract = |other_cuboid: &AxisAlignedBoundingBox| {
+			for cuboid in cuboids.drain(..) {
+				cuboid.difference_into(other_cuboid, &mut scratch);
 			}
-			cuboids = resulting_cuboids;
+			std::mem::swap(&mut cuboids, &mut scratch);
+		};
+		match other.has_overlapping_areas() {
+			true => other.normalize().iter().for_each(subtract),
+			false => other
+				.iter_cuboids()
+				.for_each(|other_cuboid| subtract(&other_cuboid)),
 		}
-		cuboids
+		cuboids.into_iter().collect()
+	}
+
+	/// Returns the cuboids of chunks which are relevant to `self` but not to `previous`.
+	/// When both contain a single area of the same radius (e.g. a player walked to another chunk),
+	/// only the slabs which entered relevance on each axis of movement are computed.
+	/// Otherwise this is the general [`difference`](Self::difference).
+	#[profiling::function]
+	pub fn moved_difference(&self, previous: &Relevance) -> HashSet<AxisAlignedBoundingBox> {
+		if let ([current], [previous]) = (&self.0[..], &previous.0[..]) {
+			if let Some(slabs) = current.entered_slabs(previous) {
+				return slabs.into_iter().collect();
+			}
+		}
+		self.difference(previous)
 	}
 
 	/// Returns the minimum significant distance squared by
@@ -173,13 +419,61 @@ impl std::fmt::Display for AxisAlignedBoundingBox {
 impl AxisAlignedBoundingBox {
 	/// AABBxAABB intersection test
 	/// `<https://developer.mozilla.org/en-US/docs/Games/Techniques/3D_collision_detection#aabb_vs._aabb>`
-	fn intersects(&self, other: &Self) -> bool {
+	pub fn intersects(&self, other: &Self) -> bool {
 		let x = self.min.x < other.max.x && other.min.x < self.max.x;
 		let y = self.min.y < other.max.y && other.min.y < self.max.y;
 		let z = self.min.z < other.max.z && other.min.z < self.max.z;
 		return x && y && z;
 	}
 
+	/// Returns true if the cuboids intersect or share a face, edge, or corner.
+	fn touches(&self, other: &Self) -> bool {
+		let x = self.min.x <= other.max.x && other.min.x <= self.max.x;
+		let y = self.min.y <= other.max.y && other.min.y <= self.max.y;
+		let z = self.min.z <= other.max.z && other.min.z <= self.max.z;
+		x && y && z
+	}
+
+	pub fn contains(&self, point: &Point3<i64>) -> bool {
+		(0..3).all(|axis| self.min[axis] <= point[axis] && point[axis] < self.max[axis])
+	}
+
+	/// Returns the union of two disjoint cuboids if it is also a cuboid
+	/// (i.e. they share an entire face).
+	fn merged(&self, other: &Self) -> Option<Self> {
+		for axis in 0..3 {
+			let others_match = (0..3)
+				.filter(|other_axis| *other_axis != axis)
+				.all(|i| self.min[i] == other.min[i] && self.max[i] == other.max[i]);
+			if !others_match {
+				continue;
+			}
+			if self.max[axis] == other.min[axis] || other.max[axis] == self.min[axis] {
+				return Some(Self {
+					min: self.min.inf(&other.min),
+					max: self.max.sup(&other.max),
+				});
+			}
+		}
+		None
+	}
+
+	/// Merges pairs of disjoint cuboids whose union is a cuboid, until no more pairs can be merged.
+	fn merge_adjacent(cuboids: &mut Vec<Self>) {
+		'merging: loop {
+			for i in 0..cuboids.len() {
+				for j in (i + 1)..cuboids.len() {
+					if let Some(merged) = cuboids[i].merged(&cuboids[j]) {
+						cuboids[i] = merged;
+						cuboids.swap_remove(j);
+						continue 'merging;
+					}
+				}
+			}
+			break;
+		}
+	}
+
 	fn overlap(&self, other: &Self) -> Option<Self> {
 		if !self.intersects(other) {
 			return None;
@@ -197,10 +491,25 @@ impl AxisAlignedBoundingBox {
 	/// of cuboids representing the area of self without the overlap.
 	/// If the provided cuboid does not intersect with self, the cuboid itself is returned.
 	/// If the cuboids are identical, None is returned.
-	fn difference(&self, other: &Self) -> Option<HashSet<Self>> {
+	pub fn difference(&self, other: &Self) -> Option<HashSet<Self>> {
+		let mut cuboids = Vec::new();
+		self.difference_into(other, &mut cuboids);
+		match cuboids.is_empty() {
+			true => None,
+			false => Some(cuboids.into_iter().collect()),
+		}
+	}
+
+	/// Pushes the cuboids representing the area of self without its overlap with `other` into `out`.
+	/// If the provided cuboid does not intersect with self, only self is pushed (without subdividing).
+	/// If self is entirely contained by `other`, nothing is pushed.
+	fn difference_into(&self, other: &Self, out: &mut Vec<Self>) {
 		let overlap = match self.overlap(&other) {
 			Some(overlap) => overlap,
-			None => return Some(HashSet::from([*self])),
+			None => {
+				out.push(*self);
+				return;
+			}
 		};
 
 		// This is basically Binary-Space-Partitioning (BSP) but just for cuboids.
@@ -210,19 +519,29 @@ impl AxisAlignedBoundingBox {
 		let lower_mid = self.min.sup(&overlap.min);
 		let upper_mid = self.max.inf(&overlap.max);
 		if lower_mid == self.min && upper_mid == self.max {
-			return None;
+			return;
 		}
 
-		let mut cuboids = Self::subdivide(vec![&self.min, &lower_mid, &upper_mid, &self.max]);
-		let removed = cuboids.remove(&overlap);
-		assert!(removed);
-
-		Some(cuboids)
+		let bounds = [&self.min, &lower_mid, &upper_mid, &self.max];
+		let len_before = out.len();
+		Self::subdivide_into(&bounds, out);
+		let overlap_idx = out[len_before..]
+			.iter()
+			.position(|cuboid| *cuboid == overlap);
+		assert!(overlap_idx.is_some());
+		out.swap_remove(len_before + overlap_idx.unwrap());
 	}
 
+	#[cfg(test)]
 	fn subdivide(bounds: Vec<&Point3<i64>>) -> HashSet<Self> {
+		let mut cuboids = Vec::with_capacity((bounds.len() - 1).pow(3));
+		Self::subdivide_into(&bounds, &mut cuboids);
+		cuboids.into_iter().collect()
+	}
+
+	/// Pushes each of the non-empty cuboids in the grid described by the `bounds` of each axis into `out`.
+	fn subdivide_into(bounds: &[&Point3<i64>], cuboids: &mut Vec<Self>) {
 		let row_len = bounds.len() - 1;
-		let mut cuboids = Vec::with_capacity(row_len.pow(3));
 		for i_y in 0..row_len {
 			if bounds[i_y + 0].y == bounds[i_y + 1].y {
 				continue;
@@ -242,7 +561,6 @@ impl AxisAlignedBoundingBox {
 				}
 			}
 		}
-		cuboids.into_iter().collect()
 	}
 }
 
@@ -682,6 +1000,30 @@ mod axis_aligned_bounding_box {
 	}
 }
 
+#[cfg(test)]
+mod relevance_serialization {
+	use super::*;
+
+	#[test]
+	fn current_version_round_trips() {
+		let mut relevance = Relevance::default();
+		relevance.push(Area::new(Point3::new(0, 0, 0), 5));
+		relevance.push(Area::new(Point3::new(-3, 12, 7), 2));
+		let bytes = bincode::serialize(&relevance).unwrap();
+		let parsed = bincode::deserialize::<Relevance>(&bytes).unwrap();
+		assert_eq!(parsed, relevance);
+	}
+
+	#[test]
+	fn unknown_version_rejected() {
+		let areas = vec![Area::new(Point3::new(1, 2, 3), 4)];
+		let bytes = bincode::serialize(&(RELEVANCE_SCHEMA_VERSION + 1, &areas)).unwrap();
+		let error = bincode::deserialize::<Relevance>(&bytes).unwrap_err();
+		let expected = UnsupportedVersion(RELEVANCE_SCHEMA_VERSION + 1).to_string();
+		assert_eq!(error.to_string(), expected);
+	}
+}
+
 pub type UpdateSender = Sender<Update>;
 pub type UpdateReceiver = Receiver<Update>;
 pub enum Update {
@@ -693,5 +1035,226 @@ pub type WorldUpdateSender = Sender<WorldUpdate>;
 pub type WorldUpdateReceiver = Receiver<WorldUpdate>;
 pub enum WorldUpdate {
 	Relevance(Relevance),
-	Chunks(Vec<Weak<RwLock<Chunk>>>),
+	Chunks(Vec<QueuedChunk>),
+	/// The client was moved into another world, whose chunks are generated with this seed.
+	Seed(u64),
+	/// Blocks changed in chunks the client already has, in the order they changed.
+	BlockChanges(Vec<(block::Point, Option<(block::LookupId, block::State)>, u64)>),
+}
+
+#[cfg(test)]
+mod moved_difference {
+	use super::*;
+
+	fn relevance(point: Point3<i64>, radius: u64) -> Relevance {
+		let mut relevance = Relevance::default();
+		relevance.push(Area::new(point, radius));
+		relevance
+	}
+
+	fn chunks(cuboids: HashSet<AxisAlignedBoundingBox>) -> HashSet<Point3<i64>> {
+		let mut chunks = HashSet::new();
+		for cuboid in cuboids.into_iter() {
+			let cuboid_chunks: HashSet<Point3<i64>> = cuboid.into();
+			chunks.extend(cuboid_chunks);
+		}
+		chunks
+	}
+
+	/// Asserts that the chunks which entered and exited relevance match the full difference.
+	fn assert_matches_difference(previous: &Relevance, current: &Relevance) {
+		assert_eq!(
+			chunks(current.moved_difference(previous)),
+			chunks(current.difference(previous))
+		);
+		assert_eq!(
+			chunks(previous.moved_difference(current)),
+			chunks(previous.difference(current))
+		);
+	}
+
+	#[test]
+	fn single_chunk_move() {
+		let previous = relevance(Point3::new(0, 0, 0), 6);
+		let current = relevance(Point3::new(1, 0, 0), 6);
+		assert_matches_difference(&previous, &current);
+
+		let entered = current.moved_difference(&previous);
+		assert_eq!(
+			entered,
+			HashSet::from([AxisAlignedBoundingBox {
+				min: Point3::new(7, -6, -6),
+				max: Point3::new(8, 7, 7),
+			}])
+		);
+		let exited = previous.moved_difference(&current);
+		assert_eq!(
+			exited,
+			HashSet::from([AxisAlignedBoundingBox {
+				min: Point3::new(-6, -6, -6),
+				max: Point3::new(-5, 7, 7),
+			}])
+		);
+	}
+
+	#[test]
+	fn diagonal_move() {
+		let previous = relevance(Point3::new(0, 0, 0), 6);
+		let current = relevance(Point3::new(-1, 2, 1), 6);
+		assert_matches_difference(&previous, &current);
+		assert_eq!(current.moved_difference(&previous).len(), 3);
+	}
+
+	#[test]
+	fn no_overlap() {
+		let previous = relevance(Point3::new(0, 0, 0), 2);
+		let current = relevance(Point3::new(10, 0, 0), 2);
+		assert_matches_difference(&previous, &current);
+	}
+
+	#[test]
+	fn no_move() {
+		let previous = relevance(Point3::new(3, 3, 3), 6);
+		assert!(previous.moved_difference(&previous.clone()).is_empty());
+	}
+
+	#[test]
+	fn radius_change() {
+		let previous = relevance(Point3::new(0, 0, 0), 4);
+		let current = relevance(Point3::new(1, 0, 0), 6);
+		assert_matches_difference(&previous, &current);
+	}
+}
+
+#[cfg(test)]
+mod normalize {
+	use super::*;
+
+	fn relevance(areas: &[(Point3<i64>, u64)]) -> Relevance {
+		let mut relevance = Relevance::default();
+		for (point, radius) in areas.iter() {
+			relevance.push(Area::new(*point, *radius));
+		}
+		relevance
+	}
+
+	/// Asserts that the normalized cuboids are disjoint and contain exactly the relevant chunks.
+	fn assert_same_relevance(relevance: &Relevance, cuboids: &Vec<AxisAlignedBoundingBox>) {
+		for y in -8..=8 {
+			for z in -8..=8 {
+				for x in -8..=8 {
+					let point = Point3::new(x, y, z);
+					let containing = cuboids
+						.iter()
+						.filter(|cuboid| cuboid.contains(&point))
+						.count();
+					assert!(containing <= 1, "{:?} is in {} cuboids", point, containing);
+					assert_eq!(
+						relevance.is_relevant(&point),
+						containing == 1,
+						"{:?}",
+						point
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn overlapping_areas_merge() {
+		let relevance = relevance(&[(Point3::new(0, 0, 0), 2), (Point3::new(1, 0, 0), 2)]);
+		let cuboids = relevance.normalize();
+		assert!(cuboids.len() < relevance.iter_cuboids().count());
+		assert_eq!(
+			cuboids,
+			vec![AxisAlignedBoundingBox {
+				min: Point3::new(-2, -2, -2),
+				max: Point3::new(4, 3, 3),
+			}]
+		);
+		assert_same_relevance(&relevance, &cuboids);
+	}
+
+	#[test]
+	fn duplicate_areas_are_removed() {
+		let relevance = relevance(&[
+			(Point3::new(0, 0, 0), 2),
+			(Point3::new(0, 0, 0), 2),
+			(Point3::new(0, 1, 0), 1),
+		]);
+		let cuboids = relevance.normalize();
+		assert_eq!(cuboids.len(), 1);
+		assert_same_relevance(&relevance, &cuboids);
+	}
+
+	#[test]
+	fn non_box_union_is_not_a_bounding_box() {
+		let relevance = relevance(&[(Point3::new(0, 0, 0), 2), (Point3::new(2, 2, 0), 2)]);
+		let cuboids = relevance.normalize();
+		assert!(cuboids.len() > 1);
+		assert_same_relevance(&relevance, &cuboids);
+	}
+
+	#[test]
+	fn difference_of_overlapping_areas() {
+		let previous = relevance(&[(Point3::new(0, 0, 0), 2)]);
+		let current = relevance(&[(Point3::new(0, 0, 0), 2), (Point3::new(1, 0, 0), 2)]);
+		assert_eq!(
+			current.difference(&previous),
+			HashSet::from([AxisAlignedBoundingBox {
+				min: Point3::new(3, -2, -2),
+				max: Point3::new(4, 3, 3),
+			}])
+		);
+		assert!(previous.difference(&current).is_empty());
+	}
+}
+
+#[cfg(test)]
+mod hysteresis {
+	use super::*;
+
+	fn relevance_at(x: i64, margin: u64, previous: &Relevance) -> Relevance {
+		let mut relevance = Relevance::default();
+		relevance.push(Area::new(Point3::new(x, 0, 0), 2).with_margin(margin));
+		relevance.retaining(previous)
+	}
+
+	#[test]
+	fn crossing_back_and_forth_within_margin() {
+		let edge = Point3::new(-2, 0, 0);
+		let mut relevance = relevance_at(0, 1, &Relevance::default());
+		relevance = relevance_at(1, 1, &relevance);
+		assert!(relevance.is_relevant(&edge));
+		let settled = relevance.clone();
+		for x in [0, 1, 0, 1].iter() {
+			let next = relevance_at(*x, 1, &relevance);
+			assert!(next.is_relevant(&edge));
+			assert!(next.moved_difference(&relevance).is_empty());
+			assert!(relevance.moved_difference(&next).is_empty());
+			assert_eq!(next, settled);
+			relevance = next;
+		}
+
+		// Without a margin, the chunk at the edge is lost as soon as the area moves away from it.
+		let relevance = relevance_at(0, 0, &Relevance::default());
+		assert!(!relevance_at(1, 0, &relevance).is_relevant(&edge));
+	}
+
+	#[test]
+	fn margin_bounds_relevance() {
+		let mut relevance = Relevance::default();
+		for x in 0..=4 {
+			relevance = relevance_at(x, 1, &relevance);
+		}
+		// Only the previous area is within the margin of the current one.
+		assert_eq!(relevance.0.len(), 2);
+		assert!(relevance.is_relevant(&Point3::new(1, 0, 0)));
+		assert!(!relevance.is_relevant(&Point3::new(0, 0, 0)));
+		for area in relevance.0.iter() {
+			assert!(Area::new(Point3::new(4, 0, 0), 2)
+				.with_margin(1)
+				.retains(area));
+		}
+	}
 }
@@ -0,0 +1,39 @@
+use std::net::SocketAddr;
+
+/// A read-only snapshot of what the replicator is sending to a connection,
+/// shown in the [`Network`](crate::debug::NetworkInspector) debug window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionStats {
+	pub address: SocketAddr,
+	pub state: ConnectionState,
+	/// The number of entities which are relevant to (and have been replicated to) the connection.
+	pub relevant_entities: usize,
+	/// The number of relevant chunks which are waiting to be queued for replication.
+	pub pending_chunks: usize,
+	/// The number of chunks which have been queued for replication but not yet written to the connection.
+	pub in_flight_chunks: usize,
+	/// The number of bytes of chunk data which have been queued but not yet written to the connection.
+	pub pending_bytes: usize,
+}
+
+/// How far a connection has gotten through joining the game.
+/// Connections are only replicated to once they have been authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+	/// The client of a listen server, which shares the server's world instead of having it replicated.
+	Local,
+	/// The connection has been authenticated, and the world around it is still being replicated.
+	LoadingWorld,
+	/// The client has been told the world around it is ready, and has entered the game.
+	InGame,
+}
+
+impl std::fmt::Display for ConnectionState {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Local => write!(f, "Local"),
+			Self::LoadingWorld => write!(f, "Loading World"),
+			Self::InGame => write!(f, "In Game"),
+		}
+	}
+}
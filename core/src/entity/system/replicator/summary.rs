@@ -0,0 +1,137 @@
+use super::{EntityOperation, EntityUpdates, OperationGroup, LOG};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+/// The number of seconds worth of ticks which are accumulated into each logged [`Summary`].
+pub static SUMMARY_INTERVAL_SECONDS: u32 = 10;
+
+/// Counts of the work the replicator did over some number of ticks,
+/// periodically logged so an operator can see what replication is doing without a profiler attached.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Summary {
+	/// The number of ticks accumulated into this summary.
+	pub ticks: u32,
+	/// The number of entities found by the world query.
+	pub entities_queried: usize,
+	pub relevant: usize,
+	pub updated: usize,
+	pub irrelevant: usize,
+	pub destroyed: usize,
+	/// The number of chunks queued to be sent to connections.
+	pub chunks_queued: usize,
+	/// How long `collect_chunks` took for each connection, summed across the ticks.
+	pub collect_chunks: HashMap<SocketAddr, Duration>,
+}
+
+impl Summary {
+	/// Adds the work done in a single tick to the summary.
+	/// Must be called after the operations are gathered, but before the updates are turned into items.
+	pub(super) fn record(&mut self, updates: &EntityUpdates, operations: &OperationGroup) {
+		self.ticks += 1;
+		self.entities_queried += updates.entities_queried;
+		self.chunks_queued += updates
+			.new_chunks
+			.iter_all()
+			.map(|(_, chunks)| chunks.len())
+			.sum::<usize>();
+		for (address, duration) in updates.collect_durations.iter() {
+			*self.collect_chunks.entry(*address).or_default() += *duration;
+		}
+		for (_address, ops) in operations.socket_ops.iter_all() {
+			for (operation, _entity) in ops.iter() {
+				match operation {
+					EntityOperation::Relevant => self.relevant += 1,
+					EntityOperation::Update => self.updated += 1,
+					EntityOperation::Irrelevant => self.irrelevant += 1,
+					EntityOperation::Destroyed => self.destroyed += 1,
+				}
+			}
+		}
+	}
+
+	/// Logs the summary if it contains at least `interval` ticks, resetting it for the next interval.
+	pub fn log_every(&mut self, interval: u32) {
+		if self.ticks < interval.max(1) {
+			return;
+		}
+		let summary = std::mem::take(self);
+		let slowest = summary
+			.collect_chunks
+			.iter()
+			.max_by_key(|(_, duration)| **duration)
+			.map(|(address, duration)| {
+				format!("{}@{:.2}ms", address, duration.as_secs_f64() * 1000.0)
+			})
+			.unwrap_or_else(|| "none".to_owned());
+		log::info!(
+			target: LOG,
+			"ticks={} queried={} relevant={} updated={} irrelevant={} destroyed={} chunks_queued={} connections={} slowest_collect_chunks={}",
+			summary.ticks,
+			summary.entities_queried,
+			summary.relevant,
+			summary.updated,
+			summary.irrelevant,
+			summary.destroyed,
+			summary.chunks_queued,
+			summary.collect_chunks.len(),
+			slowest
+		);
+	}
+}
+
+#[cfg(test)]
+mod counts {
+	use super::*;
+	use crate::common::utility::MultiSet;
+
+	#[test]
+	fn summary_counts_match_updates() {
+		let first: SocketAddr = "127.0.0.1:25565".parse().unwrap();
+		let second: SocketAddr = "127.0.0.1:25566".parse().unwrap();
+		let entities = {
+			let mut world = hecs::World::new();
+			(0..3).map(|i| world.spawn((i,))).collect::<Vec<_>>()
+		};
+
+		let mut updates = EntityUpdates::new(&MultiSet::default());
+		updates.entities_queried = 3;
+		updates
+			.collect_durations
+			.insert(first, Duration::from_micros(200));
+		updates
+			.collect_durations
+			.insert(second, Duration::from_micros(50));
+
+		let mut operations = OperationGroup::default();
+		operations.insert(EntityOperation::Relevant, first, entities[0]);
+		operations.insert(EntityOperation::Relevant, second, entities[0]);
+		operations.insert(EntityOperation::Update, first, entities[1]);
+		operations.insert(EntityOperation::Irrelevant, second, entities[1]);
+		operations.insert(EntityOperation::Destroyed, first, entities[2]);
+
+		let mut summary = Summary::default();
+		summary.record(&updates, &operations);
+		summary.record(&updates, &operations);
+		assert_eq!(
+			summary,
+			Summary {
+				ticks: 2,
+				entities_queried: 6,
+				relevant: 4,
+				updated: 2,
+				irrelevant: 2,
+				destroyed: 2,
+				chunks_queued: 0,
+				collect_chunks: HashMap::from([
+					(first, Duration::from_micros(400)),
+					(second, Duration::from_micros(100)),
+				]),
+			}
+		);
+
+		// Not enough ticks have elapsed to log the summary, so it keeps accumulating.
+		summary.log_every(3);
+		assert_eq!(summary.ticks, 2);
+		summary.log_every(2);
+		assert_eq!(summary, Summary::default());
+	}
+}
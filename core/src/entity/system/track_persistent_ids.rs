@@ -0,0 +1,36 @@
+use crate::entity::{self, ArcLockEntityWorld, ArcLockPersistentIds};
+use engine::EngineSystem;
+use std::sync::{Arc, RwLock, Weak};
+
+/// Server system which keeps the map of [`persistent ids`](entity::PersistentIds) in sync with the world,
+/// forgetting entities which have been despawned.
+/// Entities are added to the map when they are spawned (or loaded) through it.
+pub struct TrackPersistentIds {
+	world: Weak<RwLock<entity::World>>,
+	persistent_ids: Weak<RwLock<entity::PersistentIds>>,
+}
+
+impl TrackPersistentIds {
+	pub fn new(world: &ArcLockEntityWorld, persistent_ids: &ArcLockPersistentIds) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+			persistent_ids: Arc::downgrade(&persistent_ids),
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+}
+
+impl EngineSystem for TrackPersistentIds {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!("subsystem:track-persistent-ids");
+		let (arc_world, arc_ids) = match (self.world.upgrade(), self.persistent_ids.upgrade()) {
+			(Some(world), Some(ids)) => (world, ids),
+			_ => return,
+		};
+		let world = arc_world.read().unwrap();
+		arc_ids.write().unwrap().forget_despawned(&world);
+	}
+}
@@ -0,0 +1,64 @@
+use crate::{
+	common::network::{heartbeat::Quality, Storage},
+	entity::{self, component, ArcLockEntityWorld},
+};
+use engine::EngineSystem;
+use std::sync::{Arc, RwLock, Weak};
+
+/// Client system which copies the quality of the connection to the server (reported in each heartbeat)
+/// into the [`ConnectionQuality`](component::network::ConnectionQuality) of the entity the client views the world from.
+pub struct UpdateConnectionQuality {
+	world: Weak<RwLock<entity::World>>,
+	storage: Weak<RwLock<Storage>>,
+	quality: Option<Quality>,
+}
+
+impl UpdateConnectionQuality {
+	pub fn new(world: &ArcLockEntityWorld, storage: &Arc<RwLock<Storage>>) -> Self {
+		Self {
+			world: Arc::downgrade(&world),
+			storage: Arc::downgrade(&storage),
+			quality: None,
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+
+	fn client_quality(&self) -> Option<Quality> {
+		let arc_storage = self.storage.upgrade()?;
+		let storage = arc_storage.read().unwrap();
+		let arc_client = storage.client().as_ref()?;
+		let quality = arc_client.read().unwrap().connection_quality();
+		quality
+	}
+}
+
+impl EngineSystem for UpdateConnectionQuality {
+	fn update(&mut self, _delta_time: std::time::Duration, _: bool) {
+		profiling::scope!("subsystem:update_connection_quality");
+		let quality = match self.client_quality() {
+			Some(quality) => quality,
+			None => return,
+		};
+		let arc_world = match self.world.upgrade() {
+			Some(arc) => arc,
+			None => return,
+		};
+		let mut world = arc_world.write().unwrap();
+		let viewers = world
+			.query::<(
+				&component::Camera,
+				Option<&component::network::ConnectionQuality>,
+			)>()
+			.iter()
+			.filter(|(_, (_, current))| self.quality != Some(quality) || current.is_none())
+			.map(|(entity, _)| entity)
+			.collect::<Vec<_>>();
+		for entity in viewers.into_iter() {
+			let _ = world.insert_one(entity, component::network::ConnectionQuality::new(quality));
+		}
+		self.quality = Some(quality);
+	}
+}
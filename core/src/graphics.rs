@@ -2,5 +2,9 @@ pub mod chunk_boundary;
 pub mod model;
 pub mod voxel;
 
+mod antialiasing;
+pub use antialiasing::*;
+mod growable;
+pub(crate) use growable::*;
 mod procedure_config;
 pub use procedure_config::*;
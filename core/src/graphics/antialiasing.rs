@@ -0,0 +1,161 @@
+use engine::graphics::{
+	flags::{ImageSampleKind, SampleCount},
+	resource::ColorBuffer,
+	Chain,
+};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+static LOG: &'static str = "antialiasing";
+
+/// The number of samples per pixel (MSAA) the player can choose from, in increasing order.
+/// A single sample disables antialiasing.
+pub static SUPPORTED_SAMPLE_COUNTS: [u8; 4] = [1, 2, 4, 8];
+
+/// The sample count chosen by the player, or 0 if they have not chosen one.
+static REQUESTED_SAMPLES: AtomicU8 = AtomicU8::new(0);
+
+/// If the phases of the chain were built to resolve a multisampled color buffer.
+static MULTISAMPLED_PHASES: AtomicBool = AtomicBool::new(false);
+
+/// Returns the sample count the player has chosen, either while playing or with the `-msaa=<samples>` launch argument.
+/// If None, the chain uses the most samples the device supports.
+pub fn requested_samples() -> Option<u8> {
+	match REQUESTED_SAMPLES.load(Ordering::Relaxed) {
+		0 => crate::common::utility::get_named_arg("msaa")
+			.map(|samples| samples.min(u8::MAX as u16) as u8),
+		samples => Some(samples),
+	}
+}
+
+/// Chooses the sample count of the chain's color buffer.
+/// The chain must be [`reconstructed`](crate::commands::Antialiasing) for the change to take effect.
+pub fn set_requested_samples(samples: u8) {
+	REQUESTED_SAMPLES.store(samples, Ordering::Relaxed);
+}
+
+/// Records whether the [`phases`](super::Phases) of the chain resolve a multisampled color buffer,
+/// which is decided when they are built.
+pub(super) fn set_multisampled_phases(is_multisampled: bool) {
+	MULTISAMPLED_PHASES.store(is_multisampled, Ordering::Relaxed);
+}
+
+/// Returns true if `samples` can be used by reconstructing the chain.
+///
+/// A single sample is drawn straight to the frame, and more samples are resolved to it by another phase.
+/// Switching between the two changes which attachments the phases use,
+/// and the phases are only built when the game starts (renderers and ui systems are attached to them),
+/// so that switch only takes effect on the next launch.
+pub fn fits_phases(samples: u8) -> bool {
+	fits_phases_of(MULTISAMPLED_PHASES.load(Ordering::Relaxed), samples)
+}
+
+fn fits_phases_of(multisampled_phases: bool, samples: u8) -> bool {
+	multisampled_phases == (samples > 1)
+}
+
+/// Returns the [`supported`](SUPPORTED_SAMPLE_COUNTS) sample count, no larger than `max_supported`,
+/// which is nearest to `requested`. If two are equally near, the smaller is chosen.
+pub fn clamp_sample_count(requested: u8, max_supported: u8) -> u8 {
+	SUPPORTED_SAMPLE_COUNTS
+		.iter()
+		.cloned()
+		.filter(|&samples| samples == 1 || samples <= max_supported)
+		.min_by_key(|&samples| ((samples as i16 - requested as i16).abs(), samples))
+		.unwrap()
+}
+
+fn to_flag(samples: u8) -> SampleCount {
+	match samples {
+		1 => SampleCount::_1,
+		2 => SampleCount::_2,
+		4 => SampleCount::_4,
+		_ => SampleCount::_8,
+	}
+}
+
+fn from_flag(flag: SampleCount) -> u8 {
+	match flag {
+		SampleCount::_1 => 1,
+		SampleCount::_2 => 2,
+		SampleCount::_4 => 4,
+		SampleCount::_8 => 8,
+		// Any larger count is more than the player can choose.
+		_ => u8::MAX,
+	}
+}
+
+/// Returns the number of samples a `requested` count results in,
+/// given the most samples the device supports for both color and depth attachments.
+fn resolve_sample_count(requested: Option<u8>, max_supported: u8) -> u8 {
+	match requested {
+		Some(requested) => {
+			let samples = clamp_sample_count(requested, max_supported);
+			if samples != requested {
+				log::warn!(
+					target: LOG,
+					"{} samples are not supported, using {} instead",
+					requested,
+					samples
+				);
+			}
+			samples
+		}
+		None => clamp_sample_count(max_supported, max_supported),
+	}
+}
+
+/// Returns the sample count the [`chain's`](super::ChainConfig) color and depth attachments should be created with.
+pub fn chain_sample_count(chain: &Chain) -> anyhow::Result<SampleCount> {
+	let max_supported = chain
+		.physical()?
+		.max_common_sample_count(ImageSampleKind::Color | ImageSampleKind::Depth)
+		.map(from_flag)
+		.unwrap_or(1);
+	Ok(to_flag(resolve_sample_count(
+		requested_samples(),
+		max_supported,
+	)))
+}
+
+/// Returns the sample count of the chain's color buffer, which the pipelines that draw to it must be created with.
+/// Without antialiasing there is no separate color buffer, and pipelines draw directly to the frame with a single sample.
+pub fn color_sample_count(chain: &Chain) -> SampleCount {
+	match chain.resources().get::<ColorBuffer>() {
+		Ok(arc) => arc.read().unwrap().sample_count(),
+		Err(_) => SampleCount::_1,
+	}
+}
+
+#[cfg(test)]
+mod sample_count {
+	use super::*;
+
+	#[test]
+	fn clamp_to_nearest_supported() {
+		assert_eq!(clamp_sample_count(4, 8), 4);
+		assert_eq!(clamp_sample_count(3, 8), 2);
+		assert_eq!(clamp_sample_count(6, 8), 4);
+		assert_eq!(clamp_sample_count(16, 8), 8);
+		assert_eq!(clamp_sample_count(8, 4), 4);
+		assert_eq!(clamp_sample_count(0, 8), 1);
+		assert_eq!(clamp_sample_count(2, 1), 1);
+	}
+
+	#[test]
+	fn chosen_count_is_used_by_the_chain() {
+		assert_eq!(resolve_sample_count(None, 8), 8);
+		assert_eq!(resolve_sample_count(Some(2), 8), 2);
+		assert_eq!(from_flag(to_flag(resolve_sample_count(Some(2), 8))), 2);
+		assert_eq!(resolve_sample_count(Some(8), 4), 4);
+		assert_eq!(resolve_sample_count(None, 1), 1);
+	}
+
+	#[test]
+	fn single_sample_needs_other_phases() {
+		assert!(fits_phases_of(true, 2));
+		assert!(fits_phases_of(true, 8));
+		assert!(!fits_phases_of(true, 1));
+		assert!(fits_phases_of(false, 1));
+		assert!(!fits_phases_of(false, 4));
+	}
+}
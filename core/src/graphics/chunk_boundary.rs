@@ -12,7 +12,6 @@ use engine::{
 		chain::{operation::RequiresRecording, Chain, Operation},
 		command, flags, pipeline,
 		procedure::Phase,
-		resource::ColorBuffer,
 		types::{Mat4, Vec3, Vec4},
 		utility::NamedObject,
 		vertex_object, Drawable, GpuOperationBuilder, Uniform,
@@ -24,11 +23,15 @@ use engine::{
 use enumset::{EnumSet, EnumSetType};
 use std::{
 	collections::HashMap,
+	ops::RangeInclusive,
 	sync::{Arc, RwLock, Weak},
 };
 
 static ID: &'static str = "render-chunk-boundary";
 
+/// The width (in pixels) of debug lines if no width is provided via the `-debug_line_width=` argument.
+static DEFAULT_LINE_WIDTH: f32 = 1.0;
+
 struct LineSegment {
 	pos1: Point3<f32>,
 	pos2: Point3<f32>,
@@ -59,6 +62,8 @@ impl From<((f32, f32, f32), (f32, f32, f32), Vector4<f32>, Vector4<f32>)> for Li
 struct BoundaryControl {
 	kind: Type,
 	weak_action: input::action::WeakLockState,
+	show_orientation_gadget: bool,
+	weak_gadget_action: input::action::WeakLockState,
 }
 #[derive(Debug, EnumSetType, Hash)]
 enum Type {
@@ -68,14 +73,67 @@ enum Type {
 	FaceGrid,
 }
 impl BoundaryControl {
-	fn create(kind: Type, weak_action: input::action::WeakLockState) -> Arc<RwLock<Self>> {
+	fn new(
+		kind: Type,
+		weak_action: input::action::WeakLockState,
+		weak_gadget_action: input::action::WeakLockState,
+	) -> Self {
+		Self {
+			kind,
+			weak_action,
+			// The gadget is shown by default so that world orientation is always visible during development.
+			show_orientation_gadget: true,
+			weak_gadget_action,
+		}
+	}
+
+	fn create(
+		kind: Type,
+		weak_action: input::action::WeakLockState,
+		weak_gadget_action: input::action::WeakLockState,
+	) -> Arc<RwLock<Self>> {
 		log::trace!(target: ID, "Creating action listener");
-		let control = Arc::new(RwLock::new(Self { kind, weak_action }));
+		let control = Arc::new(RwLock::new(Self::new(
+			kind,
+			weak_action,
+			weak_gadget_action,
+		)));
 		if let Ok(mut engine) = Engine::get().write() {
 			engine.add_weak_system(Arc::downgrade(&control));
 		}
 		control
 	}
+
+	fn toggle_orientation_gadget(&mut self) {
+		self.show_orientation_gadget = !self.show_orientation_gadget;
+	}
+
+	/// The set of things which should be drawn, based on the current controls.
+	fn rendered_types(&self) -> RecordedTypes {
+		RecordedTypes {
+			kind: self.kind,
+			show_orientation_gadget: self.show_orientation_gadget,
+		}
+	}
+}
+
+/// Enables the optional device features which debug lines are drawn with, if the physical device supports them.
+/// Called by the window as it creates the logical device; without `wideLines`, lines are always 1 pixel wide.
+pub fn enable_device_features(
+	supported: &graphics::device::physical::Features,
+	enabled: &mut graphics::device::physical::Features,
+) {
+	enabled.wide_lines = supported.wide_lines;
+}
+
+/// Returns the width debug lines should be drawn at,
+/// given the desired width and the range of widths supported by the device.
+/// If the device does not support wide lines (`supported` is None), lines can only be 1 pixel wide.
+fn clamp_line_width(desired: f32, supported: Option<RangeInclusive<f32>>) -> f32 {
+	match supported {
+		Some(range) => desired.max(*range.start()).min(*range.end()),
+		None => 1.0,
+	}
 }
 impl Type {
 	fn rendered_kinds(&self) -> Vec<Self> {
@@ -169,9 +227,26 @@ impl EngineSystem for BoundaryControl {
 				}
 			}
 		}
+		let gadget_pressed = match self.weak_gadget_action.upgrade() {
+			Some(arc_state) => match arc_state.read() {
+				Ok(state) => state.on_button_pressed(),
+				Err(_) => false,
+			},
+			None => false,
+		};
+		if gadget_pressed {
+			self.toggle_orientation_gadget();
+		}
 	}
 }
 
+/// What was drawn in a recorded frame, so that frames can be re-recorded when the controls change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RecordedTypes {
+	kind: Type,
+	show_orientation_gadget: bool,
+}
+
 #[vertex_object]
 #[derive(Debug, Default, Clone)]
 pub struct Vertex {
@@ -296,7 +371,8 @@ pub struct Render {
 	drawable: Drawable,
 
 	control: Arc<RwLock<BoundaryControl>>,
-	recorded_kind: Vec<Type>,
+	recorded_types: Vec<RecordedTypes>,
+	desired_line_width: f32,
 	type_settings: HashMap<RenderType, TypeSettings>,
 	vertex_buffer: Arc<buffer::Buffer>,
 	index_buffer: Arc<buffer::Buffer>,
@@ -331,6 +407,9 @@ impl Render {
 		let callback_action =
 			input::User::get_action_in(&arc_user, crate::input::ACTION_TOGGLE_CHUNK_BOUNDARIES)
 				.unwrap();
+		let callback_gadget_action =
+			input::User::get_action_in(&arc_user, crate::input::ACTION_TOGGLE_ORIENTATION_GADGET)
+				.unwrap();
 		Storage::<ArcLockRender>::default()
 			// On Enter InGame => create Self and hold ownership in `storage`
 			.with_event(Create, OperationKey(None, Some(Enter), Some(InGame)))
@@ -342,8 +421,13 @@ impl Render {
 				let arc_chain = callback_chain.upgrade().unwrap();
 				let arc_camera = callback_camera.upgrade().unwrap();
 				let arc_phase = callback_phase.upgrade().unwrap();
+				let control = BoundaryControl::create(
+					Type::None,
+					callback_action.clone(),
+					callback_gadget_action.clone(),
+				);
 				Ok(
-					match Self::create(arc_chain, &arc_phase, arc_camera, callback_action.clone()) {
+					match Self::create(arc_chain, &arc_phase, arc_camera, control) {
 						Ok(arclocked) => Some(arclocked),
 						Err(err) => {
 							log::error!(target: ID, "{}", err);
@@ -358,11 +442,11 @@ impl Render {
 		chain: Arc<RwLock<Chain>>,
 		phase: &Arc<Phase>,
 		camera: Arc<RwLock<camera::Camera>>,
-		weak_action: input::action::WeakLockState,
+		control: Arc<RwLock<BoundaryControl>>,
 	) -> Result<ArcLockRender> {
 		log::info!(target: ID, "Initializing");
 		let mut chain = chain.write().unwrap();
-		let render_chunks = Self::new(&chain, camera, weak_action)?.arclocked();
+		let render_chunks = Self::new(&chain, camera, control)?.arclocked();
 		chain.add_operation(phase, Arc::downgrade(&render_chunks), None)?;
 		Ok(render_chunks)
 	}
@@ -370,7 +454,7 @@ impl Render {
 	fn new(
 		chain: &Chain,
 		camera: Arc<RwLock<camera::Camera>>,
-		weak_action: input::action::WeakLockState,
+		control: Arc<RwLock<BoundaryControl>>,
 	) -> Result<Self> {
 		log::trace!(target: ID, "Creating renderer");
 
@@ -470,13 +554,16 @@ impl Render {
 			chain.view_count(),
 		)?;
 
-		let control = BoundaryControl::create(Type::None, weak_action);
+		let desired_line_width = crate::common::utility::get_named_arg("debug_line_width")
+			.map(|width| width as f32)
+			.unwrap_or(DEFAULT_LINE_WIDTH);
 
 		log::trace!(target: ID, "Finalizing construction");
 		Ok(Self {
 			drawable,
 			control,
-			recorded_kind: Vec::new(),
+			recorded_types: Vec::new(),
+			desired_line_width,
 			type_settings,
 			vertex_buffer,
 			index_buffer,
@@ -504,8 +591,8 @@ impl Operation for Render {
 		self.camera_uniform
 			.write_descriptor_sets(&*chain.logical()?);
 
-		let control_kind = self.control.read().unwrap().kind;
-		self.recorded_kind = vec![control_kind; chain.view_count()];
+		let rendered_types = self.control.read().unwrap().rendered_types();
+		self.recorded_types = vec![rendered_types; chain.view_count()];
 		Ok(())
 	}
 
@@ -513,11 +600,23 @@ impl Operation for Render {
 	fn construct(&mut self, chain: &Chain, subpass_index: usize) -> anyhow::Result<()> {
 		use graphics::pipeline::{state::*, Pipeline};
 
-		let sample_count = {
-			let arc = chain.resources().get::<ColorBuffer>()?;
-			let color_buffer = arc.read().unwrap();
-			color_buffer.sample_count()
+		let sample_count = crate::graphics::color_sample_count(chain);
+
+		// Line widths other than 1 require the `wideLines` device feature,
+		// which is only enabled (by `enable_device_features`) if the device supports it.
+		let supported_widths = match chain.logical()?.enabled_features().wide_lines {
+			true => chain.physical()?.line_width_range(),
+			false => None,
 		};
+		let line_width = clamp_line_width(self.desired_line_width, supported_widths);
+		if line_width != self.desired_line_width {
+			log::warn!(
+				target: ID,
+				"Debug line width {} is not supported by the device, using {} instead.",
+				self.desired_line_width,
+				line_width
+			);
+		}
 
 		self.drawable.create_pipeline(
 			&chain.logical()?,
@@ -532,6 +631,7 @@ impl Operation for Render {
 				.with_topology(
 					Topology::default().with_primitive(flags::PrimitiveTopology::LINE_LIST),
 				)
+				.with_rasterization(Rasterization::default().with_line_width(line_width))
 				.with_multisampling(Multisampling::default().with_sample_count(sample_count))
 				.set_color_blending(
 					color_blend::ColorBlend::default()
@@ -566,9 +666,9 @@ impl Operation for Render {
 			.as_uniform_data(&chain.resolution());
 		self.camera_uniform.write_data(frame_image, &data)?;
 
-		let control_kind = self.control.read().unwrap().kind;
-		if self.recorded_kind[frame_image] != control_kind {
-			self.recorded_kind[frame_image] = control_kind;
+		let rendered_types = self.control.read().unwrap().rendered_types();
+		if self.recorded_types[frame_image] != rendered_types {
+			self.recorded_types[frame_image] = rendered_types;
 			Ok(RequiresRecording::CurrentFrame)
 		} else {
 			Ok(RequiresRecording::NotRequired)
@@ -591,12 +691,16 @@ impl Operation for Render {
 			buffer.bind_vertex_buffers(1, vec![&self.instance_buffer], vec![0]);
 			buffer.bind_index_buffer(&self.index_buffer, 0);
 
-			let mut render_types = self.recorded_kind[buffer_index]
+			let recorded = self.recorded_types[buffer_index];
+			let mut render_types = recorded
+				.kind
 				.rendered_kinds()
 				.into_iter()
 				.map(RenderType::from)
 				.collect::<Vec<_>>();
-			render_types.push(RenderType::OrientationGadget);
+			if recorded.show_orientation_gadget {
+				render_types.push(RenderType::OrientationGadget);
+			}
 			for render_type in render_types.into_iter() {
 				if let Some(settings) = self.type_settings.get(&render_type) {
 					buffer.draw(
@@ -614,3 +718,37 @@ impl Operation for Render {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod debug_lines {
+	use super::*;
+
+	#[test]
+	fn toggle_orientation_gadget() {
+		let mut control = BoundaryControl::new(Type::Cube, Weak::new(), Weak::new());
+		assert!(control.rendered_types().show_orientation_gadget);
+		control.toggle_orientation_gadget();
+		assert_eq!(
+			control.rendered_types(),
+			RecordedTypes {
+				kind: Type::Cube,
+				show_orientation_gadget: false,
+			}
+		);
+		control.toggle_orientation_gadget();
+		assert!(control.rendered_types().show_orientation_gadget);
+	}
+
+	#[test]
+	fn line_width_clamped_to_supported_range() {
+		assert_eq!(clamp_line_width(4.0, Some(1.0..=8.0)), 4.0);
+		assert_eq!(clamp_line_width(16.0, Some(1.0..=8.0)), 8.0);
+		assert_eq!(clamp_line_width(0.5, Some(1.0..=8.0)), 1.0);
+	}
+
+	#[test]
+	fn line_width_without_wide_lines() {
+		assert_eq!(clamp_line_width(4.0, None), 1.0);
+		assert_eq!(clamp_line_width(1.0, None), 1.0);
+	}
+}
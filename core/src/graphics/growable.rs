@@ -0,0 +1,136 @@
+/// A gpu buffer which is replaced by a larger one when more instances need to be written than it can hold.
+///
+/// If a larger buffer cannot be allocated (e.g. the device is out of memory),
+/// the last good buffer is kept and the instances which do not fit are dropped,
+/// until a later submission needs fewer instances or a larger buffer can be allocated.
+pub(crate) struct Growable<T> {
+	/// The log target for failed grows.
+	name: &'static str,
+	current: T,
+	/// The number of instances the current buffer can hold.
+	capacity: usize,
+	/// The buffers replaced by recent grows, and how many reservations have been made since each was replaced.
+	/// Each is kept alive until every frame which could still be reading from it is done.
+	retired: Vec<(T, usize)>,
+	/// The number of frames which can be in flight at once (the number of views of the chain).
+	/// Reservations are made at most once per frame, so a buffer retired this many reservations ago is no longer in use.
+	frames_in_flight: usize,
+	capacity_exceeded: bool,
+}
+
+impl<T> Growable<T> {
+	pub fn new(name: &'static str, current: T, capacity: usize, frames_in_flight: usize) -> Self {
+		Self {
+			name,
+			current,
+			capacity,
+			retired: Vec::new(),
+			frames_in_flight,
+			capacity_exceeded: false,
+		}
+	}
+
+	pub fn current(&self) -> &T {
+		&self.current
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	pub fn capacity_exceeded(&self) -> bool {
+		self.capacity_exceeded
+	}
+
+	/// Ensures the buffer can hold `required` instances, allocating a larger buffer if it cannot.
+	/// Returns the number of instances which can be written to the [`current`](Self::current) buffer.
+	pub fn reserve<F>(&mut self, required: usize, allocate: F) -> usize
+	where
+		F: FnOnce(usize) -> anyhow::Result<T>,
+	{
+		let frames_in_flight = self.frames_in_flight;
+		for (_, reservations) in self.retired.iter_mut() {
+			*reservations += 1;
+		}
+		self.retired
+			.retain(|(_, reservations)| *reservations < frames_in_flight);
+
+		if required <= self.capacity {
+			self.capacity_exceeded = false;
+			return required;
+		}
+		let capacity = required.next_power_of_two();
+		match allocate(capacity) {
+			Ok(buffer) => {
+				let retired = std::mem::replace(&mut self.current, buffer);
+				self.retired.push((retired, 0));
+				self.capacity = capacity;
+				self.capacity_exceeded = false;
+				required
+			}
+			Err(err) => {
+				log::error!(
+					target: self.name,
+					"Failed to grow instance buffer from {} to {} instances, {} instances will not be rendered: {:?}",
+					self.capacity,
+					capacity,
+					required - self.capacity,
+					err
+				);
+				self.capacity_exceeded = true;
+				self.capacity
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod growable {
+	use super::*;
+
+	#[derive(thiserror::Error, Debug)]
+	#[error("out of device memory")]
+	struct OutOfDeviceMemory;
+
+	#[test]
+	fn failed_grow_keeps_previous_buffer() {
+		let mut buffer = Growable::new("test", "initial", 4, 2);
+		assert_eq!(buffer.reserve(10, |_| Err(OutOfDeviceMemory.into())), 4);
+		assert_eq!(*buffer.current(), "initial");
+		assert_eq!(buffer.capacity(), 4);
+		assert!(buffer.capacity_exceeded());
+
+		assert_eq!(buffer.reserve(3, |_| unreachable!()), 3);
+		assert!(!buffer.capacity_exceeded());
+	}
+
+	#[test]
+	fn grow_retains_retired_buffer() {
+		let mut buffer = Growable::new("test", "initial", 4, 2);
+		assert_eq!(
+			buffer.reserve(10, |capacity| {
+				assert_eq!(capacity, 16);
+				Ok("grown")
+			}),
+			10
+		);
+		assert_eq!(*buffer.current(), "grown");
+		assert_eq!(buffer.retired, vec![("initial", 0)]);
+		assert_eq!(buffer.capacity(), 16);
+		assert!(!buffer.capacity_exceeded());
+	}
+
+	#[test]
+	fn retired_buffers_outlive_frames_in_flight() {
+		let mut buffer = Growable::new("test", "initial", 4, 2);
+		buffer.reserve(5, |_| Ok("second"));
+		// Growing again before the first buffer is done keeps both retired buffers.
+		buffer.reserve(9, |_| Ok("third"));
+		assert_eq!(buffer.retired, vec![("initial", 1), ("second", 0)]);
+
+		buffer.reserve(1, |_| unreachable!());
+		assert_eq!(buffer.retired, vec![("second", 1)]);
+		buffer.reserve(1, |_| unreachable!());
+		assert!(buffer.retired.is_empty());
+	}
+}
@@ -1,8 +1,8 @@
 use engine::graphics::{
 	chain::procedure::{AttachmentConfig, PhaseConfig, ProcedureConfig, ResourceConfig},
 	flags::{
-		Access, AttachmentKind, AttachmentOps, ImageLayout, ImageSampleKind, LoadOp, PipelineStage,
-		SampleCount, StoreOp,
+		Access, AttachmentKind, AttachmentOps, ImageLayout, LoadOp, PipelineStage, SampleCount,
+		StoreOp,
 	},
 	procedure::*,
 	renderpass::ClearValue,
@@ -20,64 +20,85 @@ impl ProcedureConfig for ChainConfig {
 
 pub struct Attachments {
 	frame: Arc<Attachment>,
+	/// The multisampled attachment the world is drawn to, which is resolved to the frame.
+	/// Without antialiasing, this is the frame itself.
 	color_buffer: Arc<Attachment>,
+	sample_count: SampleCount,
 	depth_buffer: Arc<Attachment>,
+	/// Cleared before the viewmodel is drawn, so it is not hidden by the world behind it.
+	viewmodel_depth_buffer: Arc<Attachment>,
 	depth_query: QueryResult,
+	viewmodel_depth_query: QueryResult,
 }
 
 impl AttachmentConfig for Attachments {
 	fn new(chain: &Chain) -> anyhow::Result<Self> {
 		let viewport_format = chain.swapchain_image_format();
-		let max_common_samples = chain
-			.physical()?
-			.max_common_sample_count(ImageSampleKind::Color | ImageSampleKind::Depth)
-			.unwrap_or(SampleCount::_1);
+		// The sample count the player chose, clamped to what the device supports.
+		let sample_count = super::chain_sample_count(chain)?;
+		let is_multisampled = sample_count != SampleCount::_1;
 
 		let frame = Arc::new(
 			Attachment::default()
 				.with_format(viewport_format)
 				.with_general_ops(AttachmentOps {
-					load: LoadOp::DontCare,
+					// The world is drawn directly to the frame if there is no color buffer to resolve.
+					load: match is_multisampled {
+						true => LoadOp::DontCare,
+						false => LoadOp::Clear,
+					},
 					store: StoreOp::Store,
 				})
 				.with_final_layout(ImageLayout::PresentSrc)
 				.with_clear_value(ClearValue::Color([0.0, 0.0, 0.0, 1.0])),
 		);
 
-		let color_buffer = Arc::new(
-			Attachment::default()
-				.with_format(viewport_format)
-				.with_sample_count(max_common_samples)
-				.with_general_ops(AttachmentOps {
-					load: LoadOp::Clear,
-					store: StoreOp::Store,
-				})
-				.with_final_layout(ImageLayout::ColorAttachmentOptimal)
-				.with_clear_value(ClearValue::Color([0.0, 0.0, 0.0, 1.0])),
-		);
+		let color_buffer = match is_multisampled {
+			true => Arc::new(
+				Attachment::default()
+					.with_format(viewport_format)
+					.with_sample_count(sample_count)
+					.with_general_ops(AttachmentOps {
+						load: LoadOp::Clear,
+						store: StoreOp::Store,
+					})
+					.with_final_layout(ImageLayout::ColorAttachmentOptimal)
+					.with_clear_value(ClearValue::Color([0.0, 0.0, 0.0, 1.0])),
+			),
+			false => frame.clone(),
+		};
 
-		let depth_query = DepthBuffer::classic_format_query().query(&chain.physical()?)?;
-		let depth_buffer = Arc::new(
-			Attachment::default()
-				.with_format(depth_query.format())
-				.with_sample_count(max_common_samples)
-				.with_general_ops(AttachmentOps {
-					load: LoadOp::Clear,
-					store: StoreOp::DontCare,
-				})
-				.with_stencil_ops(AttachmentOps {
-					load: LoadOp::DontCare,
-					store: StoreOp::DontCare,
-				})
-				.with_final_layout(ImageLayout::DepthStencilAttachmentOptimal)
-				.with_clear_value(ClearValue::DepthStencil(1.0, 0)),
-		);
+		let physical = chain.physical()?;
+		let depth_query = DepthBuffer::classic_format_query().query(&physical)?;
+		let viewmodel_depth_query = DepthBuffer::classic_format_query().query(&physical)?;
+		let create_depth_buffer = || {
+			Arc::new(
+				Attachment::default()
+					.with_format(depth_query.format())
+					.with_sample_count(sample_count)
+					.with_general_ops(AttachmentOps {
+						load: LoadOp::Clear,
+						store: StoreOp::DontCare,
+					})
+					.with_stencil_ops(AttachmentOps {
+						load: LoadOp::DontCare,
+						store: StoreOp::DontCare,
+					})
+					.with_final_layout(ImageLayout::DepthStencilAttachmentOptimal)
+					.with_clear_value(ClearValue::DepthStencil(1.0, 0)),
+			)
+		};
+		let depth_buffer = create_depth_buffer();
+		let viewmodel_depth_buffer = create_depth_buffer();
 
 		Ok(Self {
 			frame,
 			color_buffer,
+			sample_count,
 			depth_buffer,
+			viewmodel_depth_buffer,
 			depth_query,
+			viewmodel_depth_query,
 		})
 	}
 
@@ -86,15 +107,24 @@ impl AttachmentConfig for Attachments {
 	}
 }
 
+impl Attachments {
+	fn is_multisampled(&self) -> bool {
+		self.sample_count != SampleCount::_1
+	}
+}
+
 pub struct Phases {
 	pub world: Arc<Phase>,
 	pub debug: Arc<Phase>,
+	/// Draws objects in front of the camera (e.g. the held item) on top of the world.
+	pub viewmodel: Arc<Phase>,
 	pub resolve_antialiasing: Arc<Phase>,
 	pub ui: Arc<Phase>,
 	pub egui: Arc<Phase>,
 }
 impl PhaseConfig<Attachments> for Phases {
 	fn new(attachments: &Attachments) -> anyhow::Result<Self> {
+		super::antialiasing::set_multisampled_phases(attachments.is_multisampled());
 		let world = Arc::new(
 			Phase::new("World")
 				.with_dependency(
@@ -154,8 +184,8 @@ impl PhaseConfig<Attachments> for Phases {
 				),
 		);
 
-		let resolve_antialiasing = Arc::new(
-			Phase::new("Resolve Antialiasing")
+		let viewmodel = Arc::new(
+			Phase::new("Viewmodel")
 				.with_dependency(
 					Dependency::new(Some(&debug))
 						.first(
@@ -166,7 +196,9 @@ impl PhaseConfig<Attachments> for Phases {
 						.then(
 							PhaseAccess::default()
 								.with_stage(PipelineStage::ColorAttachmentOutput)
-								.with_access(Access::ColorAttachmentWrite),
+								.with_stage(PipelineStage::EarlyFragmentTests)
+								.with_access(Access::ColorAttachmentWrite)
+								.with_access(Access::DepthStencilAttachmentWrite),
 						),
 				)
 				.with_attachment(
@@ -174,13 +206,43 @@ impl PhaseConfig<Attachments> for Phases {
 						.with_kind(AttachmentKind::Color)
 						.with_layout(ImageLayout::ColorAttachmentOptimal),
 				)
+				// Not the world's depth buffer, so the viewmodel is drawn over the world regardless of its depth.
 				.with_attachment(
-					attachment::Reference::from(&attachments.frame)
-						.with_kind(AttachmentKind::Resolve)
-						.with_layout(ImageLayout::ColorAttachmentOptimal),
+					attachment::Reference::from(&attachments.viewmodel_depth_buffer)
+						.with_kind(AttachmentKind::DepthStencil)
+						.with_layout(ImageLayout::DepthStencilAttachmentOptimal),
 				),
 		);
 
+		let mut resolve_antialiasing = Phase::new("Resolve Antialiasing")
+			.with_dependency(
+				Dependency::new(Some(&viewmodel))
+					.first(
+						PhaseAccess::default()
+							.with_stage(PipelineStage::ColorAttachmentOutput)
+							.with_access(Access::ColorAttachmentWrite),
+					)
+					.then(
+						PhaseAccess::default()
+							.with_stage(PipelineStage::ColorAttachmentOutput)
+							.with_access(Access::ColorAttachmentWrite),
+					),
+			)
+			.with_attachment(
+				attachment::Reference::from(&attachments.color_buffer)
+					.with_kind(AttachmentKind::Color)
+					.with_layout(ImageLayout::ColorAttachmentOptimal),
+			);
+		// A single-sampled color buffer cannot be resolved, but it is already the frame.
+		if attachments.is_multisampled() {
+			resolve_antialiasing = resolve_antialiasing.with_attachment(
+				attachment::Reference::from(&attachments.frame)
+					.with_kind(AttachmentKind::Resolve)
+					.with_layout(ImageLayout::ColorAttachmentOptimal),
+			);
+		}
+		let resolve_antialiasing = Arc::new(resolve_antialiasing);
+
 		let ui = Arc::new(
 			Phase::new("UI")
 				.with_dependency(
@@ -228,6 +290,7 @@ impl PhaseConfig<Attachments> for Phases {
 		Ok(Self {
 			world,
 			debug,
+			viewmodel,
 			resolve_antialiasing,
 			ui,
 			egui,
@@ -237,6 +300,7 @@ impl PhaseConfig<Attachments> for Phases {
 	fn apply_to(&self, procedure: &mut Procedure) -> anyhow::Result<()> {
 		procedure.add_phase(self.world.clone())?;
 		procedure.add_phase(self.debug.clone())?;
+		procedure.add_phase(self.viewmodel.clone())?;
 		procedure.add_phase(self.resolve_antialiasing.clone())?;
 		procedure.add_phase(self.ui.clone())?;
 		procedure.add_phase(self.egui.clone())?;
@@ -247,15 +311,24 @@ impl PhaseConfig<Attachments> for Phases {
 pub struct Resources;
 impl ResourceConfig<Attachments> for Resources {
 	fn create_resources(attachments: Attachments, resources: &mut Registry) -> anyhow::Result<()> {
+		// Without antialiasing, the world is drawn to the frame which is owned by the swapchain.
+		if attachments.is_multisampled() {
+			resources.add(
+				ColorBuffer::builder()
+					.with_attachment(attachments.color_buffer)
+					.build(),
+			);
+		}
 		resources.add(
-			ColorBuffer::builder()
-				.with_attachment(attachments.color_buffer)
+			DepthBuffer::builder()
+				.with_query(attachments.depth_query)
+				.with_attachment(attachments.depth_buffer)
 				.build(),
 		);
 		resources.add(
 			DepthBuffer::builder()
-				.with_query(attachments.depth_query)
-				.with_attachment(attachments.depth_buffer)
+				.with_query(attachments.viewmodel_depth_query)
+				.with_attachment(attachments.viewmodel_depth_buffer)
 				.build(),
 		);
 		Ok(())
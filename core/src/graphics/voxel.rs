@@ -1,5 +1,10 @@
+/// The number of chunks in each direction from the camera's chunk which are rendered.
+// TODO: Get this value from settings
+pub static VIEW_DISTANCE: usize = 6;
+
 pub mod atlas;
 pub mod camera;
+pub mod greedy;
 pub mod model;
 
 mod face;
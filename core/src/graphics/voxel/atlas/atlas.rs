@@ -9,18 +9,46 @@ use engine::{
 	},
 	math::nalgebra::{Point2, Vector2},
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::{Arc, RwLock},
+};
 
 struct Entry {
 	coord: Point2<usize>,
+	/// The size of the whole texture (all of its frames).
 	size: Vector2<usize>,
+	/// The number of cells the texture is stitched into, one for each frame of its vertical strip.
+	/// Frames are stitched left to right in the same row, starting at `coord`.
+	frame_count: usize,
 	uv: Point2<f32>,
+	/// The size of a single frame in the atlas.
 	size_in_atlas: Vector2<f32>,
 	binary: Vec<u8>,
 }
 
 type EntryMap = HashMap<asset::Id, Entry>;
 
+/// The pixel data of a texture which can be stitched into an atlas.
+///
+/// Textures which are taller than a cell are vertical strips of animation frames,
+/// and are stitched into a cell for each frame.
+pub trait Stitch {
+	fn size(&self) -> &Vector2<usize>;
+	/// The RGBA pixels of the texture, in rows.
+	fn binary(&self) -> &Vec<u8>;
+}
+
+impl Stitch for Texture {
+	fn size(&self) -> &Vector2<usize> {
+		Texture::size(self)
+	}
+
+	fn binary(&self) -> &Vec<u8> {
+		Texture::binary(self)
+	}
+}
+
 pub struct Builder {
 	size: Vector2<usize>,
 	cell_size: Vector2<usize>,
@@ -81,9 +109,13 @@ impl Builder {
 		}
 	}
 
+	pub fn contains(&self, id: &asset::Id) -> bool {
+		self.entries.contains_key(id)
+	}
+
 	/// Returns true if the atlas either already
 	/// contains or can fit all of the provided textures.
-	pub fn contains_or_fits_all(&self, textures: &HashMap<&asset::Id, &Box<Texture>>) -> bool {
+	pub fn contains_or_fits_all<T: Stitch>(&self, textures: &HashMap<&asset::Id, &Box<T>>) -> bool {
 		let texture_to_fit = textures.iter().filter_map(|(id, texture)| {
 			match self.entries.contains_key(&id) {
 				// we dont need to check if it fits if its already stitched
@@ -100,9 +132,9 @@ impl Builder {
 		true
 	}
 
-	pub fn insert_all(
+	pub fn insert_all<T: Stitch>(
 		&mut self,
-		textures: &HashMap<&asset::Id, &Box<Texture>>,
+		textures: &HashMap<&asset::Id, &Box<T>>,
 	) -> std::result::Result<(), InsertionError> {
 		for (id, texture) in textures.iter() {
 			if !self.entries.contains_key(&id) {
@@ -112,53 +144,80 @@ impl Builder {
 		Ok(())
 	}
 
-	pub fn insert(
+	pub fn insert<T: Stitch>(
 		&mut self,
 		id: &asset::Id,
-		texture: &Texture,
+		texture: &T,
 	) -> std::result::Result<Point2<usize>, InsertionError> {
 		use InsertionError::*;
 		let size = texture.size();
-		// All items must be the same size.
-		if *size != self.cell_size {
+		// All items must be the same size (or a vertical strip of frames which are each that size).
+		let is_strip = size.y > 0 && size.y % self.cell_size.y == 0;
+		if size.x != self.cell_size.x || !is_strip {
 			return Err(DoesNotMatchAtlasCellSize(id.clone(), *size, self.cell_size));
 		}
-		// Cannot fit any more if the next cell is outside of the atlas.
-		if self.next_coord.x == self.size.x && self.next_coord.y == self.size.y {
+		let frame_count = size.y / self.cell_size.y;
+		if frame_count > self.size.x / self.cell_size.x {
+			return Err(TooManyFrames(id.clone(), frame_count));
+		}
+		// All of the frames must be in the same row, so the shader can offset the texture coordinate horizontally.
+		// If they don't fit in the rest of the row, the remaining cells in the row are skipped.
+		let mut next_coord = self.next_coord;
+		if next_coord.x + frame_count * self.cell_size.x > self.size.x {
+			next_coord.x = 0;
+			next_coord.y += self.cell_size.y;
+		}
+		// Cannot fit any more if the next cell is outside of the atlas
+		// (the column wraps to 0 when a row is filled, so only the row needs to be checked).
+		if next_coord.y >= self.size.y {
 			return Err(OutOfSpace(id.clone()));
 		}
 
 		// Allocate the coordinate and texture data
-		let coord = self.next_coord.clone();
+		let coord = next_coord;
 		// But don't save entries if this is a stub.
 		if self.save_entries {
 			let entry = Entry {
 				coord: coord.clone(),
 				size: texture.size().clone(),
+				frame_count,
 				uv: Point2::new(
 					/*0.0,*/ coord.x as f32 / self.size.x as f32,
 					/*0.0,*/ coord.y as f32 / self.size.y as f32,
 				),
 				size_in_atlas: Vector2::new(
-					/*1.0,*/ texture.size().x as f32 / self.size.x as f32,
-					/*1.0,*/ texture.size().y as f32 / self.size.y as f32,
+					/*1.0,*/ self.cell_size.x as f32 / self.size.x as f32,
+					/*1.0,*/ self.cell_size.y as f32 / self.size.y as f32,
 				),
 				binary: texture.binary().clone(),
 			};
 			self.entries.insert(id.clone(), entry);
 		}
 
-		// It fits, lets bump the next coord to the next column.
-		self.next_coord.x += size.x;
+		// It fits, lets bump the next coord to the column after the last frame.
+		next_coord.x += frame_count * self.cell_size.x;
 		// If the next column is outside the size,
 		// jump to the first column of the next row.
-		if self.next_coord.x == self.size.x {
-			self.next_coord.x = 0;
-			self.next_coord.y += size.y;
+		if next_coord.x == self.size.x {
+			next_coord.x = 0;
+			next_coord.y += self.cell_size.y;
 		}
+		self.next_coord = next_coord;
 		Ok(coord)
 	}
 
+	/// Returns the coordinate of the (first frame of the) texture in the atlas.
+	pub fn get(&self, id: &asset::Id) -> Option<super::AtlasTexCoord> {
+		match self.entries.get(&id) {
+			Some(entry) => Some(super::AtlasTexCoord {
+				offset: entry.uv.clone(),
+				size: entry.size_in_atlas.clone(),
+				frame_count: entry.frame_count,
+			}),
+			None => None,
+		}
+	}
+
 	fn as_binary(&self) -> Vec<u8> {
 		// 4 per pixel for each RGBA channel
 		let size = self.size.x * self.size.y * 4;
@@ -169,7 +228,11 @@ impl Builder {
 				for x in 0..entry.size.x {
 					for channel in 0..4 {
 						let src = Vector2::new(x, y);
-						let dst = entry.coord + src;
+						// Each frame of the strip is stitched into the cell to the right of the previous frame.
+						let frame = y / self.cell_size.y;
+						let frame_offset = Vector2::new(frame * self.cell_size.x, 0);
+						let dst =
+							entry.coord + frame_offset + Vector2::new(x, y % self.cell_size.y);
 						let src_pixel = (src.y * entry.size.x * 4) + (src.x * 4) + channel;
 						let dst_pixel = (dst.y * self.size.x * 4) + (dst.x * 4) + channel;
 						binary[dst_pixel] = entry.binary[src_pixel];
@@ -180,6 +243,45 @@ impl Builder {
 		binary
 	}
 
+	/// Replaces the pixels of a texture which has already been stitched.
+	/// Returns false if the texture is not in the atlas.
+	fn replace<T: Stitch>(
+		&mut self,
+		id: &asset::Id,
+		texture: &T,
+	) -> std::result::Result<bool, InsertionError> {
+		let entry = match self.entries.get_mut(&id) {
+			Some(entry) => entry,
+			None => return Ok(false),
+		};
+		if *texture.size() != entry.size {
+			return Err(InsertionError::DoesNotMatchAtlasCellSize(
+				id.clone(),
+				*texture.size(),
+				entry.size,
+			));
+		}
+		entry.binary = texture.binary().clone();
+		Ok(true)
+	}
+
+	fn upload(
+		&self,
+		image: &Arc<image::Image>,
+		context: &impl GpuOpContext,
+		signal_sender: &Sender<Arc<command::Semaphore>>,
+	) -> Result<()> {
+		GpuOperationBuilder::new(format!("Write({})", image.name()), context)?
+			.begin()?
+			.format_image_for_write(&image)
+			.stage(&self.as_binary()[..])?
+			.copy_stage_to_image(&image)
+			.format_image_for_read(&image)
+			.send_signal_to(signal_sender)?
+			.end()?;
+		Ok(())
+	}
+
 	pub fn build(
 		self,
 		context: &impl GpuOpContext,
@@ -197,19 +299,12 @@ impl Builder {
 			},
 		)?);
 
-		GpuOperationBuilder::new(format!("Create({})", image.name()), context)?
-			.begin()?
-			.format_image_for_write(&image)
-			.stage(&self.as_binary()[..])?
-			.copy_stage_to_image(&image)
-			.format_image_for_read(&image)
-			.send_signal_to(signal_sender)?
-			.end()?;
+		self.upload(&image, context, signal_sender)?;
 
 		let view = Arc::new(
 			image_view::View::builder()
 				.with_name(format!("{}.View", name))
-				.for_image(image)
+				.for_image(image.clone())
 				.with_view_type(flags::ImageViewType::TYPE_2D)
 				.with_range(
 					structs::subresource::Range::default().with_aspect(flags::ImageAspect::COLOR),
@@ -219,7 +314,8 @@ impl Builder {
 
 		Ok(Atlas {
 			size: self.size,
-			entries: self.entries,
+			stitched: RwLock::new(self),
+			image,
 			view,
 		})
 	}
@@ -227,7 +323,10 @@ impl Builder {
 
 pub struct Atlas {
 	size: Vector2<usize>,
-	entries: EntryMap,
+	/// The textures stitched into the atlas (and the cells which are still free).
+	/// Kept after the atlas is built so that textures can be restitched at runtime.
+	stitched: RwLock<Builder>,
+	image: Arc<image::Image>,
 	view: Arc<image_view::View>,
 }
 impl Atlas {
@@ -235,6 +334,11 @@ impl Atlas {
 		Builder::default().with_size(Vector2::new(2048, 2048))
 	}
 
+	/// Creates a builder which spills textures onto as many 2k atlases as are needed.
+	pub fn pages_2k() -> PageBuilder {
+		PageBuilder::new(Vector2::new(2048, 2048))
+	}
+
 	pub fn size(&self) -> &Vector2<usize> {
 		&self.size
 	}
@@ -243,20 +347,121 @@ impl Atlas {
 		&self.view
 	}
 
+	/// Stitches the provided textures into the atlas, replacing the pixels of textures
+	/// which are already stitched and allocating free cells for those which are not,
+	/// then re-uploads the atlas image.
+	///
+	/// The atlas is written to the existing image (instead of reallocating it),
+	/// so descriptor sets which are bound to the atlas view remain valid.
+	/// If the textures do not fit in the remaining space, the atlas is left unchanged.
+	pub fn restitch(
+		&self,
+		textures: &HashMap<&asset::Id, &Box<Texture>>,
+		context: &impl GpuOpContext,
+		signal_sender: &Sender<Arc<command::Semaphore>>,
+	) -> Result<()> {
+		let mut stitched = self.stitched.write().unwrap();
+		if !stitched.contains_or_fits_all(&textures) {
+			return Err(RestitchError::OutOfSpace.into());
+		}
+		for (id, texture) in textures.iter() {
+			if !stitched.replace(&id, &texture)? {
+				let _ = stitched.insert(&id, &texture)?;
+			}
+		}
+		stitched.upload(&self.image, context, signal_sender)?;
+		Ok(())
+	}
+
 	pub fn get(&self, id: &asset::Id) -> Option<super::AtlasTexCoord> {
-		match self.entries.get(&id) {
-			Some(entry) => Some(super::AtlasTexCoord {
-				offset: entry.uv.clone(),
-				size: entry.size_in_atlas.clone(),
-			}),
-			None => None,
+		self.stitched.read().unwrap().get(id)
+	}
+}
+
+/// Stitches textures into as many atlas pages as are needed to fit them,
+/// where all of the textures of a block are stitched into the same page,
+/// so each block model only needs to bind a single atlas.
+///
+/// Textures which are shared by blocks on different pages are stitched into each of those pages.
+pub struct PageBuilder {
+	size: Vector2<usize>,
+	pages: Vec<Builder>,
+}
+
+impl PageBuilder {
+	/// Creates a builder with a single empty page of the provided size.
+	pub fn new(size: Vector2<usize>) -> Self {
+		Self {
+			size,
+			pages: vec![Builder::default().with_size(size)],
+		}
+	}
+
+	pub fn page_count(&self) -> usize {
+		self.pages.len()
+	}
+
+	pub fn page(&self, index: usize) -> Option<&Builder> {
+		self.pages.get(index)
+	}
+
+	/// Stitches a group of textures (e.g. all of the textures of a block) into the same page,
+	/// returning the index of that page.
+	///
+	/// The textures are put in the first page which already contains or can fit all of them.
+	/// If none of the pages have enough space, a new page is allocated.
+	/// Fails if the textures cannot fit in a single page, even when it is empty.
+	pub fn insert_all<T: Stitch>(
+		&mut self,
+		textures: &HashMap<&asset::Id, &Box<T>>,
+	) -> std::result::Result<usize, InsertionError> {
+		let index = match self
+			.pages
+			.iter()
+			.position(|page| page.contains_or_fits_all(&textures))
+		{
+			Some(index) => index,
+			None => {
+				let page = Builder::default().with_size(self.size);
+				if !page.contains_or_fits_all(&textures) {
+					// An empty set of textures always fits, so there is at least one texture.
+					let id = (*textures.keys().next().unwrap()).clone();
+					return Err(InsertionError::OutOfSpace(id));
+				}
+				self.pages.push(page);
+				self.pages.len() - 1
+			}
+		};
+		self.pages[index].insert_all(&textures)?;
+		Ok(index)
+	}
+
+	/// Builds and uploads an atlas for each page, in page order.
+	/// Each atlas is named `<name>.<page index>`.
+	pub fn build(
+		self,
+		context: &impl GpuOpContext,
+		signal_sender: &Sender<Arc<command::Semaphore>>,
+		name: String,
+	) -> Result<Vec<Atlas>> {
+		let mut atlases = Vec::with_capacity(self.pages.len());
+		for (index, page) in self.pages.into_iter().enumerate() {
+			atlases.push(page.build(context, signal_sender, format!("{}.{}", name, index))?);
 		}
+		Ok(atlases)
 	}
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum RestitchError {
+	#[error("the textures do not fit in the space remaining in the atlas")]
+	OutOfSpace,
+}
+
 pub enum InsertionError {
 	DoesNotMatchAtlasCellSize(asset::Id, Vector2<usize>, Vector2<usize>),
 	OutOfSpace(asset::Id),
+	TooManyFrames(asset::Id, usize),
 }
 impl std::error::Error for InsertionError {}
 impl std::fmt::Debug for InsertionError {
@@ -272,6 +477,127 @@ impl std::fmt::Display for InsertionError {
 				)
 			,
 			Self::OutOfSpace(id) => write!(f, "Failed to insert {}, atlas is out of space.", id),
+			Self::TooManyFrames(id, frame_count) => write!(f, "Failed to insert {}, its {} animation frames do not fit in a row of the atlas.", id, frame_count),
+		}
+	}
+}
+
+#[cfg(test)]
+mod pages {
+	use super::*;
+
+	struct Blank(Vector2<usize>, Vec<u8>);
+	impl Blank {
+		fn boxed() -> Box<Self> {
+			Self::strip(1)
 		}
+
+		/// A vertical strip of animation frames, where every pixel of a frame is the index of the frame.
+		fn strip(frame_count: usize) -> Box<Self> {
+			let binary = (0..frame_count)
+				.flat_map(|frame| std::iter::repeat(frame as u8).take(16 * 16 * 4))
+				.collect();
+			Box::new(Self(Vector2::new(16, 16 * frame_count), binary))
+		}
+	}
+	impl Stitch for Blank {
+		fn size(&self) -> &Vector2<usize> {
+			&self.0
+		}
+		fn binary(&self) -> &Vec<u8> {
+			&self.1
+		}
+	}
+
+	fn ids(names: &[&str]) -> Vec<asset::Id> {
+		names
+			.iter()
+			.map(|name| asset::Id::new("test", name))
+			.collect()
+	}
+
+	#[test]
+	fn overflow_allocates_second_page() {
+		let texture = Blank::boxed();
+		// Each page fits 4 textures.
+		let mut pages = PageBuilder::new(Vector2::new(32, 32));
+		let mut insert = |ids: &Vec<asset::Id>| {
+			let textures = ids.iter().map(|id| (id, &texture)).collect();
+			pages.insert_all(&textures).unwrap()
+		};
+
+		let stone = ids(&["stone_top", "stone_side", "stone_bottom"]);
+		let grass = ids(&["grass_top", "grass_side"]);
+		let dirt = ids(&["dirt"]);
+		let shared = ids(&["stone_top", "grass_top"]);
+		let stone_page = insert(&stone);
+		// Only one cell remains in the first page, so the textures of the next block go on a new page.
+		let grass_page = insert(&grass);
+		let dirt_page = insert(&dirt);
+		let shared_page = insert(&shared);
+		assert_eq!((stone_page, grass_page, dirt_page), (0, 1, 0));
+		// Neither page contains both textures, so they are stitched into the page with space for the missing one.
+		assert_eq!(shared_page, 1);
+		assert_eq!(pages.page_count(), 2);
+
+		let first = pages.page(0).unwrap();
+		let second = pages.page(1).unwrap();
+		assert!(stone.iter().chain(dirt.iter()).all(|id| first.contains(id)));
+		assert!(grass
+			.iter()
+			.chain(shared.iter())
+			.all(|id| second.contains(id)));
+		assert!(!first.contains(&grass[0]));
+	}
+
+	#[test]
+	fn animated_texture_reserves_a_cell_per_frame() {
+		// Each row fits 4 cells, and there are 2 rows.
+		let mut atlas = Builder::default().with_size(Vector2::new(64, 32));
+		let ids = ids(&["stone", "water", "lava"]);
+		assert_eq!(
+			atlas.insert(&ids[0], &*Blank::boxed()).unwrap(),
+			Point2::new(0, 0)
+		);
+		// The 4 frames don't fit in the 3 remaining cells of the first row, so they fill the second row.
+		assert_eq!(
+			atlas.insert(&ids[1], &*Blank::strip(4)).unwrap(),
+			Point2::new(0, 16)
+		);
+		assert_eq!(atlas.entries[&ids[1]].frame_count, 4);
+		assert_eq!(atlas.next_coord, Point2::new(0, 32));
+		assert!(atlas.insert(&ids[2], &*Blank::boxed()).is_err());
+
+		// The texture coordinate is the first frame, and is the size of a single frame.
+		let coord = atlas.get(&ids[1]).unwrap();
+		assert_eq!(coord.frame_count, 4);
+		assert_eq!(coord.offset, Point2::new(0.0, 0.5));
+		assert_eq!(coord.size, Vector2::new(0.25, 0.5));
+
+		// Each frame is stitched into the cell to the right of the previous frame.
+		let binary = atlas.as_binary();
+		for frame in 0..4 {
+			let pixel = Point2::new(frame * 16 + 8, 16 + 8);
+			assert_eq!(binary[(pixel.y * 64 + pixel.x) * 4], frame as u8);
+		}
+	}
+
+	#[test]
+	fn too_many_frames_fail() {
+		let mut atlas = Builder::default().with_size(Vector2::new(32, 32));
+		let id = asset::Id::new("test", "water");
+		assert!(atlas.insert(&id, &*Blank::strip(3)).is_err());
+		assert!(!atlas.contains(&id));
+		assert!(atlas.insert(&id, &*Blank::strip(2)).is_ok());
+	}
+
+	#[test]
+	fn textures_larger_than_a_page_fail() {
+		let texture = Blank::boxed();
+		let mut pages = PageBuilder::new(Vector2::new(16, 16));
+		let ids = ids(&["a", "b"]);
+		let textures = ids.iter().map(|id| (id, &texture)).collect();
+		assert!(pages.insert_all(&textures).is_err());
+		assert_eq!(pages.page_count(), 1);
 	}
 }
@@ -4,6 +4,8 @@ use engine::math::nalgebra::{Point2, Vector2};
 pub struct AtlasTexCoord {
 	pub(crate) offset: Point2<f32>,
 	pub(crate) size: Vector2<f32>,
+	/// The number of animation frames, each of which is `size.x` to the right of the previous frame.
+	pub(crate) frame_count: usize,
 }
 
 impl std::fmt::Debug for AtlasTexCoord {
@@ -16,8 +18,8 @@ impl std::fmt::Display for AtlasTexCoord {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(
 			f,
-			"AtlasCoord(offset=<{}, {}> size=<{}, {}>)",
-			self.offset.x, self.offset.y, self.size.x, self.size.y
+			"AtlasCoord(offset=<{}, {}> size=<{}, {}> frames={})",
+			self.offset.x, self.offset.y, self.size.x, self.size.y, self.frame_count
 		)
 	}
 }
@@ -2,7 +2,7 @@ pub use camera::{OrthographicBounds, PerspectiveProjection, Projection};
 use engine::{
 	graphics::camera,
 	math::nalgebra::{
-		self, point, Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector2,
+		self, point, Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector2, Vector4,
 	},
 };
 use std::sync::{Arc, RwLock};
@@ -14,6 +14,9 @@ pub struct Camera {
 	pub position: Point3<f32>,
 	pub orientation: UnitQuaternion<f32>,
 	pub projection: camera::Projection,
+	pub fog: Fog,
+	/// How bright voxels are without any block light, which changes with the time of day (see [`Sky`](crate::common::world::clock::Sky)).
+	pub ambient: f32,
 }
 
 impl Default for Camera {
@@ -27,6 +30,8 @@ impl Default for Camera {
 				near_plane: 0.1,
 				far_plane: 1000.0,
 			}),
+			fog: Fog::from_view_distance(super::VIEW_DISTANCE),
+			ambient: 1.0,
 		}
 	}
 }
@@ -57,17 +62,95 @@ impl Camera {
 			projection: self.projection_matrix(resolution),
 			chunk_coordinate: self.chunk_coordinate,
 			inv_rotation,
+			_padding: 0.0,
+			fog_color: self.fog.color,
+			fog_range: Vector2::new(self.fog.start, self.fog.end),
+			fog_enabled: match self.fog.enabled {
+				true => 1.0,
+				false => 0.0,
+			},
+			time: 0.0,
+			ambient: self.ambient,
 		}
 	}
 }
 
+/// Distance fog applied to voxels, so chunks at the edge of the view distance fade in instead of popping in.
+/// Once the local player's relevance is known, the fog ends at the edge of the relevance radius
+/// so chunks fade in as they are replicated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+	/// If false, fragments are drawn without any fog (e.g. for screenshots).
+	/// Defaults to true unless the game is launched with `-fog=0`.
+	pub enabled: bool,
+	/// The color fragments fade towards (RGBA), which is the color of the sky at the current time of day.
+	pub color: Vector4<f32>,
+	/// The distance (in blocks) from the camera at which fog begins.
+	pub start: f32,
+	/// The distance (in blocks) from the camera at which fragments are fully fogged.
+	pub end: f32,
+}
+
+impl Fog {
+	/// The fraction of the fog's end distance at which the fog starts.
+	const START_RATIO: f32 = 0.75;
+
+	/// Creates fog which fully obscures chunks at the edge of the view distance (in chunks).
+	pub fn from_view_distance(view_distance: usize) -> Self {
+		Self::from_relevance_radius(view_distance as u64)
+	}
+
+	/// Creates fog which fully obscures chunks at the edge of a relevance radius (in chunks),
+	/// which is where chunks stream in as they become relevant.
+	pub fn from_relevance_radius(radius: u64) -> Self {
+		let mut fog = Self {
+			enabled: crate::common::utility::get_named_arg("fog") != Some(0),
+			color: Vector4::new(0.0, 0.0, 0.0, 1.0),
+			start: 0.0,
+			end: 0.0,
+		};
+		fog.set_relevance_radius(radius);
+		fog
+	}
+
+	/// Moves the fog to the edge of a relevance radius (in chunks), keeping its color and whether it is enabled.
+	pub fn set_relevance_radius(&mut self, radius: u64) {
+		use crate::common::world::chunk::SIZE;
+		// The camera can be anywhere in its chunk, so the closest chunk which is not relevant
+		// is at least `radius` chunks away.
+		self.end = radius as f32 * SIZE.x;
+		self.start = self.end * Self::START_RATIO;
+	}
+}
+
+/// The camera data sent to shaders.
+/// MIRRORS: `CameraUniform` in the shaders (using std140 layout).
 #[allow(dead_code)]
+#[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct UniformData {
 	view: Matrix4<f32>,
 	projection: Matrix4<f32>,
 	inv_rotation: Matrix4<f32>,
 	chunk_coordinate: Point3<f32>,
+	// std140 aligns vec4 to 16 bytes, and the vec3 chunk coordinate only uses 12.
+	_padding: f32,
+	fog_color: Vector4<f32>,
+	/// The start and end distance of the fog.
+	fog_range: Vector2<f32>,
+	/// 1.0 if fog is applied, 0.0 if not.
+	fog_enabled: f32,
+	/// Seconds since rendering started, which animated textures use to pick their frame.
+	time: f32,
+	/// The brightness of voxels without any block light.
+	ambient: f32,
+}
+
+impl UniformData {
+	pub fn with_time(mut self, time: f32) -> Self {
+		self.time = time;
+		self
+	}
 }
 
 impl Default for UniformData {
@@ -77,6 +160,69 @@ impl Default for UniformData {
 			projection: Matrix4::identity(),
 			chunk_coordinate: point![0.0, 0.0, 0.0],
 			inv_rotation: Matrix4::identity(),
+			_padding: 0.0,
+			fog_color: Vector4::new(0.0, 0.0, 0.0, 1.0),
+			fog_range: Vector2::new(0.0, 0.0),
+			fog_enabled: 0.0,
+			time: 0.0,
+			ambient: 1.0,
 		}
 	}
 }
+
+#[cfg(test)]
+mod fog {
+	use super::*;
+
+	#[test]
+	fn uniform_fog_from_view_distance() {
+		let camera = Camera {
+			fog: Fog::from_view_distance(6),
+			..Default::default()
+		};
+		let data = camera.as_uniform_data(&Vector2::new(1280.0, 720.0));
+		// 6 chunks of 16 blocks
+		assert_eq!(data.fog_range, Vector2::new(72.0, 96.0));
+		assert_eq!(data.fog_color, camera.fog.color);
+	}
+
+	#[test]
+	fn uniform_fog_from_relevance_radius() {
+		let mut camera = Camera::default();
+		camera.fog.enabled = true;
+		camera.fog.set_relevance_radius(8);
+		let data = camera.as_uniform_data(&Vector2::new(1280.0, 720.0));
+		// 8 chunks of 16 blocks, starting at 3/4 of the distance
+		assert_eq!(data.fog_range, Vector2::new(96.0, 128.0));
+		assert_eq!(data.fog_enabled, 1.0);
+		assert_eq!(camera.fog, {
+			let mut fog = Fog::from_relevance_radius(8);
+			fog.enabled = true;
+			fog
+		});
+
+		camera.fog.enabled = false;
+		let data = camera.as_uniform_data(&Vector2::new(1280.0, 720.0));
+		assert_eq!(data.fog_enabled, 0.0);
+		// The range is kept so fog can be re-enabled without knowing the radius again.
+		assert_eq!(data.fog_range, Vector2::new(96.0, 128.0));
+	}
+
+	#[test]
+	fn fog_color_is_std140_aligned() {
+		let data = UniformData::default();
+		let base = &data as *const UniformData as usize;
+		let fog_color = &data.fog_color as *const Vector4<f32> as usize;
+		// 3 mat4, then the vec3 chunk coordinate padded to 16 bytes
+		let expected_offset = 3 * std::mem::size_of::<Matrix4<f32>>() + 16;
+		assert_eq!(fog_color - base, expected_offset);
+		// The vec2 range is followed directly by the float flag.
+		let fog_enabled = &data.fog_enabled as *const f32 as usize;
+		assert_eq!(fog_enabled - base, expected_offset + 16 + 8);
+		// The time fills the rest of the vec4 after the fog flag.
+		let time = &data.time as *const f32 as usize;
+		assert_eq!(time - base, expected_offset + 16 + 12);
+		let ambient = &data.ambient as *const f32 as usize;
+		assert_eq!(ambient - base, expected_offset + 32);
+	}
+}
@@ -0,0 +1,348 @@
+//! An alternative to rendering one [`instance`](super::Instance) per voxel,
+//! where the visible faces of a chunk are merged into as few quads as possible.
+//!
+//! Adjacent coplanar faces of the same block-type (and state) are combined into a single rectangle,
+//! so large flat areas (e.g. the ground) become a handful of quads instead of hundreds of instances.
+//! Meshes are kept per chunk, and a block edit only re-meshes the layers whose faces it can affect.
+//!
+//! Each quad is drawn as an instance of the face of its block-type's model, stretched across the quad
+//! (see [`Instance::from_quad`](super::Instance::from_quad)), so the textures of merged faces are stretched as well.
+use crate::{
+	block,
+	common::world::chunk::{self, DIAMETER},
+	graphics::voxel::Face,
+};
+use engine::math::nalgebra::Point3;
+use enumset::EnumSet;
+use std::collections::{HashMap, HashSet};
+
+/// Returns if the model for a block-type is fully opaque, or None if there is no model for the block-type.
+pub type FnIsOpaque<'a> = &'a dyn Fn(&block::LookupId) -> Option<bool>;
+
+/// How the blocks of a chunk are turned into draw primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshMode {
+	/// One instance per voxel with visible faces, via the [`IntegratedBuffer`](super::local::IntegratedBuffer).
+	Instanced,
+	/// Visible faces are merged into quads by a [`ChunkMesh`].
+	Greedy,
+}
+
+impl Default for MeshMode {
+	fn default() -> Self {
+		Self::Instanced
+	}
+}
+
+/// A rectangle of merged faces, all of the same block-type and state, facing the same direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quad {
+	pub face: Face,
+	pub id: block::LookupId,
+	pub state: block::State,
+	/// The offset (in the chunk) of the block at the minimum corner of the quad.
+	pub origin: Point3<usize>,
+	/// The number of blocks the quad spans along the first and second [`tangent axes`](axes) of its face.
+	pub size: (usize, usize),
+}
+
+/// Returns the index of the axis a face points along, followed by the two axes which lie in the plane of the face.
+pub fn axes(face: Face) -> (usize, usize, usize) {
+	match face {
+		Face::Left | Face::Right => (0, 2, 1),
+		Face::Down | Face::Up => (1, 0, 2),
+		Face::Front | Face::Back => (2, 0, 1),
+	}
+}
+
+/// A single slice of a chunk, perpendicular to the direction of a face.
+type Layer = (Face, usize);
+
+/// The greedy mesh of a single chunk.
+///
+/// Faces on the boundary of the chunk are always treated as visible,
+/// the same as faces next to a chunk which isn't loaded in the instanced approach.
+pub struct ChunkMesh {
+	blocks: Vec<Option<(block::LookupId, block::State)>>,
+	layers: HashMap<Layer, Vec<Quad>>,
+	dirty: HashSet<Layer>,
+}
+
+impl ChunkMesh {
+	pub fn new(blocks: Vec<(Point3<usize>, block::LookupId, block::State)>) -> Self {
+		let mut mesh = Self {
+			blocks: vec![None; chunk::VOLUME],
+			layers: HashMap::new(),
+			dirty: HashSet::new(),
+		};
+		for (offset, id, state) in blocks.into_iter() {
+			mesh.blocks[chunk::offset_index(&offset)] = Some((id, state));
+		}
+		for face in EnumSet::<Face>::all().iter() {
+			for layer in 0..DIAMETER {
+				mesh.dirty.insert((face, layer));
+			}
+		}
+		mesh
+	}
+
+	/// Changes the block at an offset in the chunk, marking the layers whose faces it affects as needing to be re-meshed.
+	/// For each face, that is the layer containing the block and the layer whose faces touch the block.
+	pub fn set_block(
+		&mut self,
+		offset: Point3<usize>,
+		block: Option<(block::LookupId, block::State)>,
+	) {
+		let index = chunk::offset_index(&offset);
+		if self.blocks[index] == block {
+			return;
+		}
+		self.blocks[index] = block;
+		for face in EnumSet::<Face>::all().iter() {
+			let (normal, _, _) = axes(face);
+			let layer = offset[normal];
+			self.dirty.insert((face, layer));
+			let touching = layer as i64 - face.direction()[normal] as i64;
+			if touching >= 0 && (touching as usize) < DIAMETER {
+				self.dirty.insert((face, touching as usize));
+			}
+		}
+	}
+
+	/// Returns true if any layers need to be re-meshed before the quads are up to date.
+	pub fn is_dirty(&self) -> bool {
+		!self.dirty.is_empty()
+	}
+
+	/// Re-meshes every layer which has changed since the last call, returning the number of layers which were re-meshed.
+	pub fn remesh(&mut self, is_opaque: FnIsOpaque) -> usize {
+		profiling::scope!("greedy-remesh", &format!("layers={}", self.dirty.len()));
+		let dirty = std::mem::take(&mut self.dirty);
+		for (face, layer) in dirty.iter() {
+			let quads = self.mesh_layer(*face, *layer, is_opaque);
+			self.layers.insert((*face, *layer), quads);
+		}
+		dirty.len()
+	}
+
+	/// All of the quads in the mesh, as of the last call to [`remesh`](Self::remesh).
+	pub fn quads(&self) -> impl Iterator<Item = &Quad> + '_ {
+		self.layers.values().flatten()
+	}
+
+	pub fn quad_count(&self) -> usize {
+		self.layers.values().map(Vec::len).sum()
+	}
+
+	fn get(&self, offset: &Point3<i64>) -> Option<(block::LookupId, block::State)> {
+		let in_chunk = (0..3).all(|axis| offset[axis] >= 0 && offset[axis] < DIAMETER as i64);
+		match in_chunk {
+			true => self.blocks[chunk::offset_index(&offset.map(|v| v as usize))],
+			false => None,
+		}
+	}
+
+	/// Returns the block whose face at `offset` is visible, if any.
+	/// Mirrors how the instanced buffer determines face visibility.
+	fn visible_face(
+		&self,
+		offset: Point3<usize>,
+		face: Face,
+		is_opaque: FnIsOpaque,
+	) -> Option<(block::LookupId, block::State)> {
+		let block = self.blocks[chunk::offset_index(&offset)]?;
+		let adjacent = offset.cast::<i64>() + face.direction().cast::<i64>();
+		let is_visible = match self.get(&adjacent) {
+			None => true,
+			Some(other) => match is_opaque(&other.0) {
+				Some(true) => false,
+				// Adjacent translucent blocks of the same type and state hide their touching faces.
+				Some(false) => other != block,
+				None => true,
+			},
+		};
+		Some(block).filter(|_| is_visible)
+	}
+
+	fn mesh_layer(&self, face: Face, layer: usize, is_opaque: FnIsOpaque) -> Vec<Quad> {
+		let (normal, u_axis, v_axis) = axes(face);
+		let offset_at = |u: usize, v: usize| {
+			let mut offset = Point3::new(0, 0, 0);
+			offset[normal] = layer;
+			offset[u_axis] = u;
+			offset[v_axis] = v;
+			offset
+		};
+
+		let mut mask = vec![None; DIAMETER * DIAMETER];
+		for v in 0..DIAMETER {
+			for u in 0..DIAMETER {
+				mask[v * DIAMETER + u] = self.visible_face(offset_at(u, v), face, is_opaque);
+			}
+		}
+
+		let mut quads = Vec::new();
+		for v in 0..DIAMETER {
+			let mut u = 0;
+			while u < DIAMETER {
+				let block = match mask[v * DIAMETER + u] {
+					Some(block) => block,
+					None => {
+						u += 1;
+						continue;
+					}
+				};
+
+				let mut width = 1;
+				while u + width < DIAMETER && mask[v * DIAMETER + u + width] == Some(block) {
+					width += 1;
+				}
+
+				let mut height = 1;
+				'grow: while v + height < DIAMETER {
+					for du in 0..width {
+						if mask[(v + height) * DIAMETER + u + du] != Some(block) {
+							break 'grow;
+						}
+					}
+					height += 1;
+				}
+
+				for dv in 0..height {
+					for du in 0..width {
+						mask[(v + dv) * DIAMETER + u + du] = None;
+					}
+				}
+
+				quads.push(Quad {
+					face,
+					id: block.0,
+					state: block.1,
+					origin: offset_at(u, v),
+					size: (width, height),
+				});
+				u += width;
+			}
+		}
+		quads
+	}
+}
+
+/// The greedy meshes of every chunk which is using [`MeshMode::Greedy`].
+/// Chunks which are not in the collection use the instanced approach.
+#[derive(Default)]
+pub struct Meshes {
+	chunks: HashMap<Point3<i64>, ChunkMesh>,
+}
+
+impl Meshes {
+	pub fn mode(&self, chunk: &Point3<i64>) -> MeshMode {
+		match self.chunks.contains_key(chunk) {
+			true => MeshMode::Greedy,
+			false => MeshMode::Instanced,
+		}
+	}
+
+	/// Changes how a chunk is meshed, building its greedy mesh from its blocks if it is switching to greedy meshing.
+	pub fn set_mode(
+		&mut self,
+		chunk: Point3<i64>,
+		mode: MeshMode,
+		blocks: impl FnOnce() -> Vec<(Point3<usize>, block::LookupId, block::State)>,
+	) {
+		match mode {
+			MeshMode::Greedy => {
+				self.chunks
+					.entry(chunk)
+					.or_insert_with(|| ChunkMesh::new(blocks()));
+			}
+			MeshMode::Instanced => {
+				self.chunks.remove(&chunk);
+			}
+		}
+	}
+
+	pub fn get(&self, chunk: &Point3<i64>) -> Option<&ChunkMesh> {
+		self.chunks.get(chunk)
+	}
+
+	pub fn get_mut(&mut self, chunk: &Point3<i64>) -> Option<&mut ChunkMesh> {
+		self.chunks.get_mut(chunk)
+	}
+
+	/// Forwards a block edit to the mesh of its chunk, if the chunk is greedy meshed.
+	pub fn set_block(
+		&mut self,
+		point: &block::Point,
+		block: Option<(block::LookupId, block::State)>,
+	) {
+		if let Some(mesh) = self.chunks.get_mut(point.chunk()) {
+			mesh.set_block(point.offset().cast::<usize>(), block);
+		}
+	}
+
+	/// Re-meshes the changed layers of every greedy meshed chunk.
+	pub fn remesh(&mut self, is_opaque: FnIsOpaque) {
+		for mesh in self.chunks.values_mut().filter(|mesh| mesh.is_dirty()) {
+			mesh.remesh(is_opaque);
+		}
+	}
+}
+
+#[cfg(test)]
+mod merging {
+	use super::*;
+
+	fn opaque(_id: &block::LookupId) -> Option<bool> {
+		Some(true)
+	}
+
+	fn plane(id: block::LookupId) -> Vec<(Point3<usize>, block::LookupId, block::State)> {
+		let mut blocks = Vec::new();
+		for x in 0..DIAMETER {
+			for z in 0..DIAMETER {
+				blocks.push((Point3::new(x, 0, z), id, block::DEFAULT_STATE));
+			}
+		}
+		blocks
+	}
+
+	#[test]
+	fn flat_plane_is_one_quad_per_direction() {
+		let mut mesh = ChunkMesh::new(plane(1));
+		mesh.remesh(&opaque);
+		assert_eq!(mesh.quad_count(), 6);
+		for face in EnumSet::<Face>::all().iter() {
+			let quads = mesh
+				.quads()
+				.filter(|quad| quad.face == face)
+				.collect::<Vec<_>>();
+			assert_eq!(quads.len(), 1, "{}", face);
+			let expected_size = match face {
+				Face::Up | Face::Down => (DIAMETER, DIAMETER),
+				_ => (DIAMETER, 1),
+			};
+			assert_eq!(quads[0].size, expected_size, "{}", face);
+		}
+	}
+
+	#[test]
+	fn edit_only_remeshes_affected_layers() {
+		let mut mesh = ChunkMesh::new(plane(1));
+		mesh.remesh(&opaque);
+		assert!(!mesh.is_dirty());
+
+		mesh.set_block(Point3::new(4, 0, 4), Some((2, block::DEFAULT_STATE)));
+		// Each face re-meshes the block's layer and the neighboring layer which faces it,
+		// except for the up face, whose neighboring layer would be below the chunk.
+		let remeshed = mesh.remesh(&opaque);
+		assert_eq!(remeshed, 2 + 1 + 2 * 4);
+		assert!(mesh.quad_count() > 6);
+		assert_eq!(
+			mesh.quads()
+				.filter(|quad| quad.face == Face::Up && quad.id == 2)
+				.count(),
+			1
+		);
+	}
+}
@@ -1,8 +1,10 @@
 use crate::{
-	client::world::chunk::{Operation, OperationReceiver as ChunkOperationReceiver},
-	common::{world::chunk, utility::ThreadHandle},
+	client::world::chunk::{
+		BlockChange, Operation, OperationReceiver as ChunkOperationReceiver, PredictionCache,
+	},
+	common::{utility::ThreadHandle, world::chunk},
 	graphics::voxel::{
-		instance::{local, submitted, Instance},
+		instance::{local, submitted, ChangeStats, Instance},
 		model,
 	},
 };
@@ -11,10 +13,21 @@ use engine::{
 	graphics::{alloc, Chain},
 	utility::{self},
 };
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{
+	atomic::{AtomicBool, AtomicUsize, Ordering},
+	Arc, Mutex, Weak,
+};
 
 static LOG: &'static str = "voxel-instance-buffer";
 
+/// The number of bytes of instance data uploaded to the gpu during the most recent frame.
+static UPLOADED_BYTES: AtomicUsize = AtomicUsize::new(0);
+/// The size of the changes taken from the local instance data during the most recent frame.
+static CHANGED_RANGES: AtomicUsize = AtomicUsize::new(0);
+static CHANGED_INDICES: AtomicUsize = AtomicUsize::new(0);
+/// If the most recent submission had more instances than the gpu buffer could hold.
+static CAPACITY_EXCEEDED: AtomicBool = AtomicBool::new(false);
+
 /// Controls the instance buffer data for rendering voxels.
 /// Keeps track of what chunks and blocks are old and updates the instances accordingly.
 pub struct Buffer {
@@ -28,9 +41,9 @@ impl Buffer {
 		allocator: &Arc<alloc::Allocator>,
 		model_cache: Weak<model::Cache>,
 		chunk_receiver: ChunkOperationReceiver,
+		frames_in_flight: usize,
 	) -> Result<Self> {
-		// TODO: Get this value from settings
-		let render_radius = 6;
+		let render_radius = crate::graphics::voxel::VIEW_DISTANCE;
 		// square diameter of the cube surrounding the player
 		let render_diameter = render_radius * 2 + 1;
 		let rendered_chunk_count = render_diameter * render_diameter * render_diameter;
@@ -51,24 +64,27 @@ impl Buffer {
 		*/
 		let chunk_volume = chunk::SIZE_I.x * chunk::SIZE_I.y * chunk::SIZE_I.z;
 		let max_rendered_instances = rendered_chunk_count * chunk_volume;
-		let instance_buffer_size = max_rendered_instances * std::mem::size_of::<Instance>();
+		// The gpu buffer starts at the likely size, and grows if more instances are rendered.
+		let initial_capacity = max_rendered_instances / 2;
 
 		log::info!(
 			target: LOG,
 			"Initializing with chunk_radius={} total_chunk_count={} buffer_size={}(bytes)",
 			render_radius,
 			rendered_chunk_count,
-			instance_buffer_size
+			initial_capacity * std::mem::size_of::<Instance>()
 		);
 
 		let local_integrated_buffer = Arc::new(Mutex::new(local::IntegratedBuffer::new(
 			max_rendered_instances,
 			model_cache.clone(),
 		)));
-		let submitted_description = submitted::Description::new(allocator, instance_buffer_size)?;
+		let submitted_description =
+			submitted::Description::new(allocator, initial_capacity, frames_in_flight)?;
 
 		let _thread_handle =
 			Self::start_thread(chunk_receiver, Arc::downgrade(&local_integrated_buffer))?;
+		*Self::local_static() = Some(Arc::downgrade(&local_integrated_buffer));
 
 		Ok(Self {
 			_thread_handle,
@@ -88,6 +104,7 @@ impl Buffer {
 			use std::time::Duration;
 			static LOG: &'static str = "_";
 			log::info!(target: LOG, "Starting thread");
+			let mut predictions = PredictionCache::default();
 			while weak_handle.strong_count() > 0 {
 				let unable_to_lock_delay_ms = 1;
 				let no_chunks_to_proccess_delay_ms = 1000;
@@ -112,6 +129,7 @@ impl Buffer {
 						while let Ok(operation) = chunk_receiver.try_recv() {
 							let res = match operation {
 								Operation::Remove(coord) => {
+									predictions.remove(&coord);
 									let res = description.remove_chunk(&coord);
 									res.with_context(|| {
 										format!(
@@ -120,8 +138,23 @@ impl Buffer {
 										)
 									})
 								}
-								Operation::Insert(coord, updates) => {
-									let res = description.insert_chunk(coord, updates);
+								// The client already has a newer copy of the chunk.
+								Operation::Insert(coord, version, _)
+									if predictions.is_stale(&coord, version) =>
+								{
+									Ok(())
+								}
+								Operation::Insert(coord, version, updates) => {
+									// If the chunk was predicted, only the blocks which
+									// differ from the prediction need to be updated.
+									let res = match predictions.receive(coord, version) {
+										true => description.reconcile_chunk(coord, updates),
+										false => description.insert_chunk(coord, updates),
+									};
+									let res = res.and_then(|_| {
+										let changes = predictions.take_deferred_changes(&coord);
+										Self::set_changed_blocks(&mut description, changes)
+									});
 									res.with_context(|| {
 										format!(
 											"insert chunk <{}, {}, {}>",
@@ -129,6 +162,37 @@ impl Buffer {
 										)
 									})
 								}
+								Operation::Seed(seed) => {
+									predictions.set_seed(seed);
+									Ok(())
+								}
+								Operation::Predict(coord) => match predictions.predict(coord) {
+									Some(updates) => {
+										let res = description.insert_chunk(coord, updates);
+										res.with_context(|| {
+											format!(
+												"predict chunk <{}, {}, {}>",
+												coord.x, coord.y, coord.z
+											)
+										})
+									}
+									None => Ok(()),
+								},
+								Operation::SetBlocks(changes) => {
+									let mut accepted = Vec::with_capacity(changes.len());
+									for change in changes.into_iter() {
+										let coord = *change.0.chunk();
+										if !predictions.is_received(&coord) {
+											// The chunk may have been sent before the change was made,
+											// so the change is applied once the chunk arrives.
+											predictions.defer_change(change);
+										} else if predictions.accept_change(&coord, change.2) {
+											accepted.push(change);
+										}
+									}
+									Self::set_changed_blocks(&mut description, accepted)
+										.context("set changed blocks")
+								}
 							};
 							if let Err(err) = res {
 								log::error!(target: "thread", "{:?}", err);
@@ -152,25 +216,87 @@ impl Buffer {
 		Ok(ThreadHandle::new(handle, join_handle))
 	}
 
+	fn set_changed_blocks(
+		description: &mut local::IntegratedBuffer,
+		changes: Vec<BlockChange>,
+	) -> Result<()> {
+		for (point, block, _version) in changes.into_iter() {
+			description.set_id_for(&point, block)?;
+		}
+		Ok(())
+	}
+
+	fn local_static() -> &'static mut Option<Weak<Mutex<local::IntegratedBuffer>>> {
+		static mut LOCAL: Option<Weak<Mutex<local::IntegratedBuffer>>> = None;
+		unsafe { &mut LOCAL }
+	}
+
+	/// Returns the local instance data of the buffer currently being rendered, if there is one.
+	/// Used by debug tools to inspect the state of the buffer, and to raycast against the blocks the client can see.
+	pub fn active_local() -> Option<Arc<Mutex<local::IntegratedBuffer>>> {
+		Self::local_static()
+			.as_ref()
+			.map(|weak| weak.upgrade())
+			.flatten()
+	}
+
 	pub fn submitted(&self) -> &submitted::Description {
 		&self.submitted_description
 	}
 
+	/// Returns true if the most recent submission had more instances than the gpu buffer could hold,
+	/// and a larger buffer could not be allocated. The instances which did not fit are not rendered.
+	pub fn capacity_exceeded() -> bool {
+		CAPACITY_EXCEEDED.load(Ordering::Relaxed)
+	}
+
+	/// Returns the number of bytes of instance data which were uploaded to the gpu
+	/// by the most recent call to [`submit_pending_changes`](Self::submit_pending_changes) (i.e. the last frame).
+	pub fn uploaded_bytes() -> usize {
+		UPLOADED_BYTES.load(Ordering::Relaxed)
+	}
+
+	/// Returns the size of the changes taken from the local instance data
+	/// by the most recent call to [`submit_pending_changes`](Self::submit_pending_changes) which could lock it.
+	pub fn last_changes() -> ChangeStats {
+		ChangeStats {
+			ranges: CHANGED_RANGES.load(Ordering::Relaxed),
+			indices: CHANGED_INDICES.load(Ordering::Relaxed),
+		}
+	}
+
 	pub fn submit_pending_changes(&mut self, chain: &Chain) -> Result<bool> {
 		profiling::scope!("update_voxel_instances");
 		let mut was_changed = false;
+		let mut uploaded_bytes = 0;
+		// If the local instances are busy, the changes are taken next frame and the last frame's stats are kept.
 		if let Ok(mut local_description) = self.local_integrated_buffer.try_lock() {
-			if let Some((changed_ranges, total_count)) = local_description.take_changed_ranges() {
+			let taken = local_description.take_changed_ranges();
+			let changes = local_description.last_taken();
+			CHANGED_RANGES.store(changes.ranges, Ordering::Relaxed);
+			CHANGED_INDICES.store(changes.indices, Ordering::Relaxed);
+			if let Some((changed_ranges, _total_count)) = taken {
 				was_changed = true;
-				self.submitted_description.submit(
+				profiling::scope!("upload");
+				uploaded_bytes = self.submitted_description.submit(
 					changed_ranges,
-					total_count,
 					&local_description,
 					chain,
 					chain.signal_sender(),
 				)?;
+				CAPACITY_EXCEEDED.store(
+					self.submitted_description.capacity_exceeded(),
+					Ordering::Relaxed,
+				);
 			}
 		}
+		UPLOADED_BYTES.store(uploaded_bytes, Ordering::Relaxed);
 		Ok(was_changed)
 	}
 }
+
+impl Drop for Buffer {
+	fn drop(&mut self) {
+		*Self::local_static() = None;
+	}
+}
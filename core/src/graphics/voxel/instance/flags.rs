@@ -1,9 +1,13 @@
-use crate::graphics::voxel::Face;
+use crate::{block, graphics::voxel::Face};
 use engine::math::nalgebra::Vector4;
 use enumset::EnumSet;
 
 pub struct Flags {
 	pub faces: EnumSet<Face>,
+	/// The state of the block, so the shader can select the variant of the block-type's model.
+	pub state: block::State,
+	/// The block light falling on the block, so the shader can brighten its faces.
+	pub light: u8,
 }
 
 impl Flags {
@@ -16,6 +20,8 @@ impl Flags {
 		}
 		// Convert the bits of the face flag int to the f32 for the shader
 		flags[0] = unsafe { std::mem::transmute(faces_enabled_bitfield) };
+		flags[1] = unsafe { std::mem::transmute(self.state as u32) };
+		flags[2] = unsafe { std::mem::transmute(self.light as u32) };
 
 		flags
 	}
@@ -24,8 +30,12 @@ impl Flags {
 impl From<Vector4<f32>> for Flags {
 	fn from(flags: Vector4<f32>) -> Self {
 		let faces_enabled_bitfield = unsafe { std::mem::transmute(flags[0]) };
+		let state: u32 = unsafe { std::mem::transmute(flags[1]) };
+		let light: u32 = unsafe { std::mem::transmute(flags[2]) };
 		Self {
 			faces: Face::parse_model_bit(faces_enabled_bitfield),
+			state: state as block::State,
+			light: light as u8,
 		}
 	}
 }
@@ -1,4 +1,4 @@
-use super::super::Face;
+use super::super::{greedy, Face};
 use crate::block;
 use engine::{
 	graphics::{
@@ -6,7 +6,7 @@ use engine::{
 		types::{Mat4, Vec3, Vec4},
 		vertex_object,
 	},
-	math::nalgebra::{Point3, Translation3},
+	math::nalgebra::{Matrix4, Point3, Translation3, Vector3},
 };
 use enumset::EnumSet;
 
@@ -25,8 +25,12 @@ pub struct Instance {
 }
 
 impl Instance {
-	pub fn from(point: &block::Point, faces: EnumSet<Face>) -> Self {
-		let flags = super::Flags { faces };
+	pub fn from(point: &block::Point, state: block::State, faces: EnumSet<Face>) -> Self {
+		let flags = super::Flags {
+			faces,
+			state,
+			light: 0,
+		};
 		Self {
 			chunk_coordinate: point.chunk().coords.cast::<f32>().into(),
 			model_matrix: Translation3::from(point.offset().coords.cast::<f32>())
@@ -36,6 +40,27 @@ impl Instance {
 		}
 	}
 
+	/// An instance which draws the face of a [`greedy meshed quad`](greedy::Quad),
+	/// by stretching the face of its block along the tangent axes of the face to the size of the quad.
+	/// The [`point`](Self::point) of the instance is the block at the minimum corner of the quad.
+	pub fn from_quad(chunk: &Point3<i64>, quad: &greedy::Quad, light: u8) -> Self {
+		let (_normal, u_axis, v_axis) = greedy::axes(quad.face);
+		let mut scale = Vector3::new(1.0, 1.0, 1.0);
+		scale[u_axis] = quad.size.0 as f32;
+		scale[v_axis] = quad.size.1 as f32;
+		let flags = super::Flags {
+			faces: EnumSet::only(quad.face),
+			state: quad.state,
+			light,
+		};
+		let translation = Translation3::from(quad.origin.coords.cast::<f32>()).to_homogeneous();
+		Self {
+			chunk_coordinate: chunk.coords.cast::<f32>().into(),
+			model_matrix: (translation * Matrix4::new_nonuniform_scaling(&scale)).into(),
+			instance_flags: flags.build().into(),
+		}
+	}
+
 	fn chunk(&self) -> Point3<f32> {
 		(*self.chunk_coordinate).into()
 	}
@@ -58,9 +83,25 @@ impl Instance {
 		Face::parse_model_bit(faces_enabled_bitfield)
 	}
 
+	pub fn state(&self) -> block::State {
+		let state: u32 = unsafe { std::mem::transmute(self.instance_flags[1]) };
+		state as block::State
+	}
+
 	pub fn set_faces(&mut self, faces: EnumSet<Face>) {
 		let mut flags = super::Flags::from(*self.instance_flags);
 		flags.faces = faces;
 		self.instance_flags = flags.build().into();
 	}
+
+	/// The block light falling on the faces of the voxel.
+	pub fn light(&self) -> u8 {
+		super::Flags::from(*self.instance_flags).light
+	}
+
+	pub fn set_light(&mut self, light: u8) {
+		let mut flags = super::Flags::from(*self.instance_flags);
+		flags.light = light;
+		self.instance_flags = flags.build().into();
+	}
 }
@@ -1,9 +1,15 @@
 use crate::{
 	block,
+	common::{
+		physics,
+		utility::DirtySet,
+		world::light::{self, LightMap},
+	},
 	graphics::voxel::{
+		greedy::{self, MeshMode},
 		instance::{
 			category::{self, Category},
-			Instance, RangeSet,
+			ChangeStats, Instance, RangeSet,
 		},
 		model, Face,
 	},
@@ -12,7 +18,7 @@ use engine::math::nalgebra::Point3;
 use enumset::EnumSet;
 use std::{
 	collections::{HashMap, HashSet},
-	sync::{Arc, Weak},
+	sync::{Mutex, Weak},
 };
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -21,8 +27,94 @@ enum IdPhase {
 	Inactive,
 }
 
+/// The source of block model data used to determine which faces of a voxel are visible.
+enum ModelSource {
+	Cache(Weak<model::Cache>),
+	/// Fixed properties per block-type, for using the buffer without a graphics context.
+	#[cfg(test)]
+	Properties(HashMap<block::LookupId, model::Properties>),
+}
+
+/// Returns if the model for a block-type is fully opaque, or None if there is no model for the block-type.
+type FnIsOpaque = Box<dyn Fn(&block::LookupId) -> Option<bool>>;
+/// Returns the level of light emitted by a block-type, or None if there is no model for the block-type.
+type FnEmission = Box<dyn Fn(&block::LookupId) -> Option<u8>>;
+
+impl ModelSource {
+	fn opacity(&self) -> Result<FnIsOpaque, Error> {
+		match self {
+			Self::Cache(model_cache) => {
+				let model_cache = model_cache.upgrade().ok_or(Error::InvalidModelCache)?;
+				Ok(Box::new(move |id| model_cache.is_opaque(id)))
+			}
+			#[cfg(test)]
+			Self::Properties(properties) => {
+				let properties = properties.clone();
+				Ok(Box::new(move |id| properties.get(id).map(|p| p.is_opaque)))
+			}
+		}
+	}
+
+	fn emission(&self) -> Result<FnEmission, Error> {
+		match self {
+			Self::Cache(model_cache) => {
+				let model_cache = model_cache.upgrade().ok_or(Error::InvalidModelCache)?;
+				Ok(Box::new(move |id| model_cache.light_emission(id)))
+			}
+			#[cfg(test)]
+			Self::Properties(properties) => {
+				let properties = properties.clone();
+				Ok(Box::new(move |id| {
+					properties.get(id).map(|p| p.light_emission)
+				}))
+			}
+		}
+	}
+}
+
+/// The light of the chunks in a buffer, paired with the blocks in the buffer which light is propagated through.
+struct LitVolume<'buffer> {
+	buffer: &'buffer IntegratedBuffer,
+	light: &'buffer mut HashMap<Point3<i64>, LightMap>,
+	is_opaque: FnIsOpaque,
+	emission: FnEmission,
+}
+
+impl<'buffer> LitVolume<'buffer> {
+	fn offset(point: &block::Point) -> Point3<usize> {
+		point.offset().cast::<usize>()
+	}
+}
+
+impl<'buffer> light::Volume for LitVolume<'buffer> {
+	fn light(&self, point: &block::Point) -> Option<light::Light> {
+		let chunk_light = self.light.get(point.chunk())?;
+		Some(chunk_light.get(&Self::offset(point)))
+	}
+
+	fn set_light(&mut self, point: &block::Point, light: light::Light) {
+		if let Some(chunk_light) = self.light.get_mut(point.chunk()) {
+			chunk_light.set(&Self::offset(point), light);
+		}
+	}
+
+	fn emission(&self, point: &block::Point) -> u8 {
+		self.buffer
+			.get_block(point)
+			.and_then(|(_phase, id, _state)| (self.emission)(&id))
+			.unwrap_or(0)
+	}
+
+	fn is_opaque(&self, point: &block::Point) -> bool {
+		self.buffer
+			.get_block(point)
+			.and_then(|(_phase, id, _state)| (self.is_opaque)(&id))
+			.unwrap_or(false)
+	}
+}
+
 pub struct IntegratedBuffer {
-	model_cache: Weak<model::Cache>,
+	models: ModelSource,
 	/// The ordered list of all instances in the buffer.
 	/// Some of these may be garbage data.
 	/// USe `category_keys` and `categories` to determine which instances belong to which category.
@@ -32,26 +124,54 @@ pub struct IntegratedBuffer {
 	categories: Vec<Category>,
 	/// Mapping of block::Point to the block-type it is.
 	/// If it is not in this mapping, the point is either empty (air), or exists in `inactive_instances` as a block without rendered faces.
+	/// The state of each block is stored in its instance.
 	active_points: HashMap<Point3<i64>, HashMap<Point3<i8>, (block::LookupId, usize)>>,
 	/// Mapping of block::Point to its instance, if the point cannot render any faces.
 	/// Does not include points which are empty (air).
 	inactive_points: HashMap<Point3<i64>, HashMap<Point3<i8>, (block::LookupId, Instance)>>,
+	/// The light level of every voxel in each chunk in the buffer.
+	/// The light falling on each block is copied into its instance.
+	light: HashMap<Point3<i64>, LightMap>,
+	/// The greedy meshes of the chunks which render merged quads instead of one instance per voxel.
+	/// The voxels of those chunks are always inactive, and the instances of their quads are in `quads`.
+	meshes: greedy::Meshes,
+	/// The quads (and their instance index) of each greedy meshed chunk,
+	/// keyed by the offset of the block at the minimum corner of the quad and the direction the quad faces.
+	quads: HashMap<Point3<i64>, HashMap<(Point3<i8>, Face), (greedy::Quad, usize)>>,
 	changed_ranges: RangeSet,
+	/// The size of the changes returned by the last call to `take_changed_ranges`.
+	last_taken: ChangeStats,
 }
 
 impl IntegratedBuffer {
 	pub fn new(instance_capacity: usize, model_cache: Weak<model::Cache>) -> Self {
 		let block_type_count = block::Lookup::get().unwrap().count();
+		Self::with_models(
+			block_type_count,
+			instance_capacity,
+			ModelSource::Cache(model_cache),
+		)
+	}
+
+	fn with_models(
+		block_type_count: block::LookupId,
+		instance_capacity: usize,
+		models: ModelSource,
+	) -> Self {
 		let categories = Self::create_categories(block_type_count, instance_capacity);
 		let instances = vec![Instance::default(); instance_capacity];
 		Self {
-			model_cache,
+			models,
 			instances,
 			block_type_count,
 			categories,
 			active_points: HashMap::new(),
 			inactive_points: HashMap::new(),
+			light: HashMap::new(),
+			meshes: greedy::Meshes::default(),
+			quads: HashMap::new(),
 			changed_ranges: RangeSet::default(),
+			last_taken: ChangeStats::default(),
 		}
 	}
 
@@ -71,10 +191,21 @@ impl IntegratedBuffer {
 impl IntegratedBuffer {
 	#[profiling::function]
 	pub fn take_changed_ranges(&mut self) -> Option<(Vec<std::ops::Range<usize>>, usize)> {
-		match self.changed_ranges.is_empty() {
+		let changes = match self.changed_ranges.is_empty() {
 			true => None,
 			false => Some(self.changed_ranges.take()),
-		}
+		};
+		self.last_taken = changes
+			.as_ref()
+			.map(|(ranges, _)| ChangeStats::of(ranges))
+			.unwrap_or_default();
+		changes
+	}
+
+	/// The number of ranges and indices returned by the last call to
+	/// [`take_changed_ranges`](Self::take_changed_ranges) (zero if nothing had changed).
+	pub fn last_taken(&self) -> ChangeStats {
+		self.last_taken
 	}
 
 	pub fn instances(&self) -> &Vec<Instance> {
@@ -85,10 +216,97 @@ impl IntegratedBuffer {
 		&self.categories
 	}
 
+	/// Returns the number of voxels in a chunk which have at least one face to render.
+	pub fn active_count_in(&self, chunk: &Point3<i64>) -> usize {
+		self.active_points
+			.get(chunk)
+			.map(|points| points.len())
+			.unwrap_or(0)
+	}
+
+	/// Returns the number of voxels in a chunk which are not air, but have no faces to render.
+	pub fn inactive_count_in(&self, chunk: &Point3<i64>) -> usize {
+		self.inactive_points
+			.get(chunk)
+			.map(|points| points.len())
+			.unwrap_or(0)
+	}
+
+	/// Returns the number of quads rendered for a chunk, if it is greedy meshed.
+	pub fn quad_count_in(&self, chunk: &Point3<i64>) -> usize {
+		self.quads.get(chunk).map(|quads| quads.len()).unwrap_or(0)
+	}
+
+	pub fn mesh_mode(&self, chunk: &Point3<i64>) -> MeshMode {
+		self.meshes.mode(chunk)
+	}
+
+	/// Changes how the blocks of a chunk in the buffer are rendered.
+	/// Greedy meshed chunks render the quads of their [`mesh`](greedy::ChunkMesh) instead of one instance per voxel,
+	/// until they are switched back or the chunk is removed from the buffer.
+	pub fn set_mesh_mode(&mut self, chunk: Point3<i64>, mode: MeshMode) -> anyhow::Result<()> {
+		use anyhow::Context;
+		// Every chunk in the buffer has a light map, even if it has no blocks.
+		if !self.light.contains_key(&chunk) || self.meshes.mode(&chunk) == mode {
+			return Ok(());
+		}
+		let blocks = self
+			.chunk_block_ids(&chunk)
+			.into_iter()
+			.map(|(offset, (id, state))| (offset.cast::<usize>(), id, state))
+			.collect::<Vec<_>>();
+		match mode {
+			MeshMode::Greedy => {
+				self.meshes.set_mode(chunk, mode, || blocks);
+				let active = match self.active_points.get(&chunk) {
+					Some(chunk_points) => chunk_points.keys().cloned().collect::<Vec<_>>(),
+					None => Vec::new(),
+				};
+				for offset in active.into_iter() {
+					let point = block::Point::new(chunk, offset);
+					self.change_phase(&point, IdPhase::Active, IdPhase::Inactive)
+						.with_context(|| format!("greedy mesh chunk {chunk}"))?;
+				}
+				self.update_quads(&chunk)
+					.with_context(|| format!("greedy mesh chunk {chunk}"))?;
+			}
+			MeshMode::Instanced => {
+				self.remove_quads(&chunk)
+					.with_context(|| format!("instance chunk {chunk}"))?;
+				self.meshes.set_mode(chunk, mode, Vec::new);
+				let mut points = DirtySet::new();
+				for (offset, _id, _state) in blocks.into_iter() {
+					points.mark(block::Point::new(chunk, offset.cast::<i8>()));
+				}
+				self.update_faces(points)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Returns if the block at a point is not air, or None if its chunk is not in the buffer.
+	/// Used to raycast against the blocks the client can see.
+	pub fn is_solid(&self, point: &block::Point) -> Option<bool> {
+		// Every chunk in the buffer has a light map, even if it has no blocks.
+		if !self.light.contains_key(point.chunk()) {
+			return None;
+		}
+		Some(self.get_block(point).is_some())
+	}
+
+	/// Returns the number of instances allocated to each block-type across all chunks.
+	/// The last entry (whose id is None) is the number of unallocated instances remaining in the buffer.
+	pub fn category_lengths(&self) -> Vec<(Option<block::LookupId>, usize)> {
+		self.categories
+			.iter()
+			.map(|category| (category.id, category.count()))
+			.collect()
+	}
+
 	pub fn insert_chunk(
 		&mut self,
 		chunk: Point3<i64>,
-		block_ids: Vec<(Point3<usize>, block::LookupId)>,
+		block_ids: Vec<(Point3<usize>, block::LookupId, block::State)>,
 	) -> anyhow::Result<()> {
 		use anyhow::Context;
 		profiling::scope!(
@@ -102,20 +320,88 @@ impl IntegratedBuffer {
 			)
 		);
 
-		let mut points = HashSet::with_capacity(block_ids.len());
-		for (point, block_id) in block_ids.into_iter() {
+		let mut points = DirtySet::new();
+		for (point, block_id, state) in block_ids.into_iter() {
 			let point = block::Point::new(chunk, point.cast::<i8>());
-			self.insert_inactive(&point, block_id, Instance::from(&point, EnumSet::empty()))
+			let instance = Instance::from(&point, state, EnumSet::empty());
+			self.insert_inactive(&point, block_id, instance)
 				.with_context(|| format!("insert chunk <{}, {}, {}>", chunk.x, chunk.y, chunk.z))?;
-			points.insert(point);
+			points.mark(point);
+		}
+		let inserted = points.iter().cloned().collect::<Vec<_>>();
+		self.update_faces(points)?;
+
+		self.light.insert(chunk, LightMap::default());
+		let emitters = inserted.clone();
+		self.update_light(inserted, |volume| {
+			light::light_chunk(volume, chunk, emitters)
+		})?;
+
+		Ok(())
+	}
+
+	/// Replaces the blocks of a chunk already in the buffer (e.g. a predicted chunk) with the provided blocks.
+	/// Only the points whose block-type or state differs (or which were added or removed) are changed,
+	/// so reconciling a chunk with identical blocks does not change any instances.
+	pub fn reconcile_chunk(
+		&mut self,
+		chunk: Point3<i64>,
+		block_ids: Vec<(Point3<usize>, block::LookupId, block::State)>,
+	) -> anyhow::Result<()> {
+		use anyhow::Context;
+		profiling::scope!(
+			"reconcile_chunk",
+			&format!("chunk=<{}, {}, {}>", chunk.x, chunk.y, chunk.z)
+		);
+
+		let mut prev_ids = self.chunk_block_ids(&chunk);
+		let mut points = DirtySet::new();
+		for (offset, block_id, state) in block_ids.into_iter() {
+			let offset = offset.cast::<i8>();
+			if prev_ids.remove(&offset) == Some((block_id, state)) {
+				continue;
+			}
+			let point = block::Point::new(chunk, offset);
+			let instance = Instance::from(&point, state, EnumSet::empty());
+			self.insert_inactive(&point, block_id, instance)
+				.with_context(|| format!("reconcile chunk {chunk}"))?;
+			points.mark(point);
 		}
+		// Any remaining points are not in the reconciled chunk, and are now empty (air).
+		// They are included in the face update so that the faces of their neighbors are shown.
+		for (offset, _block_id) in prev_ids.into_iter() {
+			let point = block::Point::new(chunk, offset);
+			self.remove_point(&point)
+				.with_context(|| format!("reconcile chunk {chunk}"))?;
+			points.mark(point);
+		}
+		let changed = points.iter().cloned().collect::<Vec<_>>();
 		self.update_faces(points)?;
+		self.update_mesh(&changed)?;
+
+		let updated = changed.clone();
+		self.update_light(changed, |volume| {
+			let mut changed = HashSet::new();
+			for point in updated.into_iter() {
+				changed.extend(light::update_block(volume, point));
+			}
+			changed
+		})?;
 
 		Ok(())
 	}
 
 	pub fn remove_chunk(&mut self, coord: &Point3<i64>) -> anyhow::Result<()> {
 		use anyhow::Context;
+		// Light which spread from the chunk into its neighbors is darkened,
+		// while the blocks of the chunk are still in the buffer to spread the light of the neighbors back in.
+		let chunk = *coord;
+		self.update_light(Vec::new(), |volume| {
+			light::unlight_chunk(volume, chunk, |volume| {
+				let _ = volume.light.remove(&chunk);
+			})
+		})?;
+
 		if let Some(active_points) = self.active_points.get(&coord).cloned() {
 			for (point_offset, (block_id, _instance_idx)) in active_points.into_iter() {
 				let point = block::Point::new(*coord, point_offset);
@@ -125,6 +411,10 @@ impl IntegratedBuffer {
 			assert_eq!(self.active_points.get(&coord).unwrap().len(), 0);
 		}
 
+		self.remove_quads(coord)
+			.with_context(|| format!("removing chunk {coord}"))?;
+		self.meshes.set_mode(*coord, MeshMode::Instanced, Vec::new);
+
 		let _ = self.active_points.remove(&coord);
 		let _ = self.inactive_points.remove(&coord);
 		Ok(())
@@ -133,20 +423,31 @@ impl IntegratedBuffer {
 	pub fn set_id_for(
 		&mut self,
 		point: &block::Point,
-		id: Option<block::LookupId>,
+		id: Option<(block::LookupId, block::State)>,
 	) -> anyhow::Result<()> {
 		use anyhow::Context;
-		match self.get_block_id(&point) {
-			Some((_phase, prev_block_id)) => match id {
-				Some(next_block_id) => self.change_id(&point, prev_block_id, next_block_id),
-				None => self.remove(&point, prev_block_id),
+		match self.get_block(&point) {
+			Some((_phase, prev_id, prev_state)) => match id {
+				Some(next) if next == (prev_id, prev_state) => return Ok(()),
+				// Replacing the block (instead of only changing its category) also
+				// recalculates its faces, which depend on the block-type and state.
+				Some((next_id, next_state)) => self.insert(&point, next_id, next_state),
+				// The block may be inactive (e.g. in a greedy meshed chunk), and its neighbors' faces which touch it are now visible.
+				None => self
+					.remove_point(&point)
+					.and_then(|_| Ok(self.update_faces(std::iter::once(*point).collect())?)),
 			},
 			None => match id {
-				Some(id) => self.insert(&point, id),
-				None => Ok(()),
+				Some((id, state)) => self.insert(&point, id, state),
+				None => return Ok(()),
 			},
 		}
-		.with_context(|| format!("set id of {point} to {id:?}"))
+		.with_context(|| format!("set id of {point} to {id:?}"))?;
+		self.update_mesh(&[*point])?;
+
+		let point = *point;
+		self.update_light(vec![point], |volume| light::update_block(volume, point))?;
+		Ok(())
 	}
 }
 
@@ -172,28 +473,17 @@ impl IntegratedBuffer {
 		&mut self.categories[idx]
 	}
 
-	fn insert(&mut self, point: &block::Point, next_id: block::LookupId) -> anyhow::Result<()> {
-		use anyhow::Context;
-		self.insert_inactive(&point, next_id, Instance::from(&point, EnumSet::empty()))
-			.with_context(|| format!("insert {next_id} at {point}"))?;
-		self.update_faces(HashSet::from([*point]))?;
-		Ok(())
-	}
-
-	fn change_id(
+	fn insert(
 		&mut self,
 		point: &block::Point,
-		prev_id: block::LookupId,
 		next_id: block::LookupId,
+		state: block::State,
 	) -> anyhow::Result<()> {
 		use anyhow::Context;
-		let _ = self
-			.change_category(
-				&point,
-				category::Key::Id(prev_id),
-				category::Key::Id(next_id),
-			)
-			.with_context(|| format!("change point {point} from {prev_id} -> {next_id}."))?;
+		let instance = Instance::from(&point, state, EnumSet::empty());
+		self.insert_inactive(&point, next_id, instance)
+			.with_context(|| format!("insert {next_id} at {point}"))?;
+		self.update_faces(std::iter::once(*point).collect())?;
 		Ok(())
 	}
 
@@ -247,15 +537,37 @@ impl IntegratedBuffer {
 		Ok(())
 	}
 
-	fn get_block_id(&self, point: &block::Point) -> Option<(IdPhase, block::LookupId)> {
+	/// Returns the block-type and state of every point in the chunk, both active and inactive.
+	fn chunk_block_ids(
+		&self,
+		chunk: &Point3<i64>,
+	) -> HashMap<Point3<i8>, (block::LookupId, block::State)> {
+		let mut ids = HashMap::new();
+		if let Some(chunk_points) = self.active_points.get(chunk) {
+			ids.extend(chunk_points.iter().map(|(offset, (id, instance_idx))| {
+				(*offset, (*id, self.instances[*instance_idx].state()))
+			}));
+		}
+		if let Some(chunk_points) = self.inactive_points.get(chunk) {
+			ids.extend(
+				chunk_points
+					.iter()
+					.map(|(offset, (id, instance))| (*offset, (*id, instance.state()))),
+			);
+		}
+		ids
+	}
+
+	fn get_block(&self, point: &block::Point) -> Option<(IdPhase, block::LookupId, block::State)> {
 		if let Some(chunk_points) = self.inactive_points.get(&point.chunk()) {
-			if let Some((id, _instance)) = chunk_points.get(&point.offset()) {
-				return Some((IdPhase::Inactive, *id));
+			if let Some((id, instance)) = chunk_points.get(&point.offset()) {
+				return Some((IdPhase::Inactive, *id, instance.state()));
 			}
 		}
 		if let Some(chunk_points) = self.active_points.get(&point.chunk()) {
-			if let Some((id, _instance_idx)) = chunk_points.get(&point.offset()) {
-				return Some((IdPhase::Active, *id));
+			if let Some((id, instance_idx)) = chunk_points.get(&point.offset()) {
+				let state = self.instances[*instance_idx].state();
+				return Some((IdPhase::Active, *id, state));
 			}
 		}
 		None
@@ -267,7 +579,7 @@ impl IntegratedBuffer {
 		start: category::Key,
 		destination: category::Key,
 	) -> Result<usize, Error> {
-		let mut instance_idx = match start {
+		let instance_idx = match start {
 			category::Key::Unallocated => self.get_category(start).start(),
 			_ => match self.active_points.get_mut(&point.chunk()) {
 				Some(chunk_points) => match chunk_points.remove(&point.offset()) {
@@ -286,9 +598,31 @@ impl IntegratedBuffer {
 			},
 		};
 
+		let instance_idx = self.move_instance(instance_idx, start, destination);
+
+		if let category::Key::Id(block_id) = destination {
+			if !self.active_points.contains_key(&point.chunk()) {
+				self.active_points.insert(*point.chunk(), HashMap::new());
+			}
+			let chunk_points = self.active_points.get_mut(&point.chunk()).unwrap();
+			let _ = chunk_points.insert(*point.offset(), (block_id, instance_idx));
+		}
+
+		Ok(instance_idx)
+	}
+
+	/// Moves the instance at `instance_idx` from the `start` category to the `destination` category,
+	/// by swapping it with the instances at the edges of the categories in between.
+	/// Returns the index of the instance once it is in the `destination` category.
+	fn move_instance(
+		&mut self,
+		mut instance_idx: usize,
+		start: category::Key,
+		destination: category::Key,
+	) -> usize {
 		let path = match category::Key::new_path(start, destination, self.max_block_id()) {
 			Some(path) => path,
-			None => return Ok(instance_idx),
+			None => return instance_idx,
 		};
 
 		let direction = category::Direction::from(&start, &destination);
@@ -307,8 +641,7 @@ impl IntegratedBuffer {
 					.get_category(next_key)
 					.index_at_position(direction.target_position());
 
-				let target_point = self.instances[target_idx].point();
-				self.set_point_index(&target_point, instance_idx);
+				self.set_instance_index(target_idx, instance_idx);
 
 				self.swap_instances(&mut instance_idx, target_idx);
 			}
@@ -316,18 +649,88 @@ impl IntegratedBuffer {
 			prev_key = next_key;
 		}
 
-		if let category::Key::Id(block_id) = destination {
-			if !self.active_points.contains_key(&point.chunk()) {
-				self.active_points.insert(*point.chunk(), HashMap::new());
+		instance_idx
+	}
+
+	/// Changes the light of the chunks in the buffer (via `update`),
+	/// then copies the light falling on each changed block (and the provided `points`) into their instances.
+	fn update_light<F>(&mut self, points: Vec<block::Point>, update: F) -> Result<(), Error>
+	where
+		F: FnOnce(&mut LitVolume) -> HashSet<block::Point>,
+	{
+		profiling::scope!("update_light");
+		let is_opaque = self.models.opacity()?;
+		let emission = self.models.emission()?;
+		let mut chunk_light = std::mem::take(&mut self.light);
+		let brightness = {
+			let mut volume = LitVolume {
+				buffer: &*self,
+				light: &mut chunk_light,
+				is_opaque,
+				emission,
+			};
+			let changed = update(&mut volume);
+			// The light falling on a block is the light of the voxels around it.
+			let mut affected = points.into_iter().collect::<HashSet<_>>();
+			for point in changed.into_iter() {
+				affected.extend(
+					EnumSet::<Face>::all()
+						.iter()
+						.map(|face| point + face.direction()),
+				);
+				affected.insert(point);
 			}
-			let chunk_points = self.active_points.get_mut(&point.chunk()).unwrap();
-			let _ = chunk_points.insert(*point.offset(), (block_id, instance_idx));
+			affected
+				.into_iter()
+				.filter(|point| self.get_block(point).is_some())
+				.map(|point| (point, light::brightness(&volume, &point)))
+				.collect::<Vec<_>>()
+		};
+		self.light = chunk_light;
+		for (point, level) in brightness.into_iter() {
+			self.set_instance_light(&point, level);
 		}
+		Ok(())
+	}
 
-		Ok(instance_idx)
+	fn set_instance_light(&mut self, point: &block::Point, level: u8) {
+		let phase = match self.get_block(point) {
+			Some((phase, _id, _state)) => phase,
+			None => return,
+		};
+		if let Some((idx, instance)) = self.get_instance_mut(point, phase) {
+			if instance.light() != level {
+				instance.set_light(level);
+				if let Some(idx) = idx {
+					self.changed_ranges.insert(idx);
+				}
+			}
+		}
+		// Quads are lit by the light falling on the block at their minimum corner.
+		if let Some(chunk_quads) = self.quads.get(point.chunk()) {
+			for face in EnumSet::<Face>::all().iter() {
+				if let Some((_quad, idx)) = chunk_quads.get(&(*point.offset(), face)) {
+					if self.instances[*idx].light() != level {
+						self.instances[*idx].set_light(level);
+						self.changed_ranges.insert(*idx);
+					}
+				}
+			}
+		}
 	}
 
-	fn set_point_index(&mut self, point: &block::Point, idx: usize) {
+	/// Records that the instance at `target_idx` (of a voxel or a greedy meshed quad) is moving to `idx`.
+	fn set_instance_index(&mut self, target_idx: usize, idx: usize) {
+		let point = self.instances[target_idx].point();
+		if let Some(chunk_quads) = self.quads.get_mut(point.chunk()) {
+			// Greedy meshed chunks have no active points, and each quad draws a single face.
+			for face in self.instances[target_idx].faces().iter() {
+				if let Some((_quad, quad_idx)) = chunk_quads.get_mut(&(*point.offset(), face)) {
+					*quad_idx = idx;
+				}
+			}
+			return;
+		}
 		if let Some(chunk_points) = self.active_points.get_mut(&point.chunk()) {
 			if let Some((_id, instance_idx)) = chunk_points.get_mut(&point.offset()) {
 				*instance_idx = idx;
@@ -372,8 +775,10 @@ impl IntegratedBuffer {
 	}
 
 	#[profiling::function]
-	fn update_faces(&mut self, points: HashSet<block::Point>) -> Result<(), Error> {
-		let model_cache = self.model_cache.upgrade().ok_or(Error::InvalidModelCache)?;
+	/// Recalculates the faces of each dirty point (and the faces of its neighbors which face it),
+	/// in the order the points were marked.
+	fn update_faces(&mut self, points: DirtySet<block::Point>) -> Result<(), Error> {
+		let is_opaque = self.models.opacity()?;
 
 		let mut changes = Vec::new();
 
@@ -389,11 +794,13 @@ impl IntegratedBuffer {
 				// Get the block::Point of the block on that face of the primary point
 				let secondary_point = primary_point + primary_point_face.direction();
 				// And get the block-type of that adjacent point
-				let secondary_point_id = self.get_block_id(&secondary_point);
+				let secondary_point_block = self.get_block(&secondary_point);
 				// Save off the adjacent block information
 				face_ids.push((primary_point_face, secondary_point));
 				// The secondary point could be empty (air). If it is, then it doesnt have a block-id.
-				if let Some((secondary_point_phase, secondary_point_id)) = secondary_point_id {
+				if let Some((secondary_point_phase, secondary_point_id, secondary_point_state)) =
+					secondary_point_block
+				{
 					// If the adjacent point is not a primary point, the face that
 					// is adjacent to the primary point should also be updated.
 					// If it IS a primary point, it has either already been
@@ -403,9 +810,9 @@ impl IntegratedBuffer {
 						let desired_phase = self.recalculate_faces(
 							secondary_point,
 							secondary_point_phase,
-							secondary_point_id,
+							(secondary_point_id, secondary_point_state),
 							vec![(secondary_point_face, primary_point)],
-							&model_cache,
+							&is_opaque,
 						);
 						if desired_phase != secondary_point_phase {
 							changes.push((secondary_point, secondary_point_phase, desired_phase));
@@ -414,14 +821,15 @@ impl IntegratedBuffer {
 				}
 			}
 			// Update the faces for this primary point
-			if let Some((primary_point_phase, primary_point_id)) = self.get_block_id(&primary_point)
+			if let Some((primary_point_phase, primary_point_id, primary_point_state)) =
+				self.get_block(&primary_point)
 			{
 				let desired_phase = self.recalculate_faces(
 					primary_point,
 					primary_point_phase,
-					primary_point_id,
+					(primary_point_id, primary_point_state),
 					face_ids,
-					&model_cache,
+					&is_opaque,
 				);
 				if desired_phase != primary_point_phase {
 					changes.push((primary_point, primary_point_phase, desired_phase));
@@ -433,6 +841,10 @@ impl IntegratedBuffer {
 			use anyhow::Context;
 			profiling::scope!("apply-phase-changes");
 			for (point, phase, desired_phase) in changes.into_iter() {
+				// The voxels of greedy meshed chunks are drawn by their quads instead.
+				if desired_phase == IdPhase::Active && self.meshes.get(point.chunk()).is_some() {
+					continue;
+				}
 				let res = self.change_phase(&point, phase, desired_phase);
 				let res = res.with_context(|| {
 					format!("update phase {phase:?} -> {desired_phase:?} when updating faces")
@@ -450,9 +862,9 @@ impl IntegratedBuffer {
 		&mut self,
 		point: block::Point,
 		phase: IdPhase,
-		id: block::LookupId,
+		(id, state): (block::LookupId, block::State),
 		faces: Vec<(Face, block::Point)>,
-		model_cache: &Arc<model::Cache>,
+		is_opaque: &FnIsOpaque,
 	) -> IdPhase {
 		profiling::scope!(
 			"recalculate_faces",
@@ -461,7 +873,7 @@ impl IntegratedBuffer {
 
 		let faces = faces
 			.into_iter()
-			.map(|(face, adj_point)| (face, self.get_block_id(&adj_point)))
+			.map(|(face, adj_point)| (face, self.get_block(&adj_point)))
 			.collect::<Vec<_>>();
 
 		let mut desired_phase = phase;
@@ -471,17 +883,18 @@ impl IntegratedBuffer {
 				let face_is_enabled = match block_id {
 					// Block doesnt exist at this point (its air/empty) or the chunk isn't loaded.
 					None => true,
-					Some((_phase, block_id)) => match model_cache.get(&block_id) {
+					Some((_phase, block_id, block_state)) => match is_opaque(&block_id) {
 						// Found a model, can base face visibility based on if the model is fully-opaque
-						Some((model, _, _)) => {
+						Some(is_opaque) => {
 							// The other block is opaque, our face should be shown.
-							if model.is_opaque() {
+							if is_opaque {
 								false
 							}
 							// The other block is not opaque, show our face only if the types are not the same.
-							// i.e. two adjacent glass blocks should not show their touching faces
+							// i.e. two adjacent glass blocks should not show their touching faces,
+							// unless they are different variants of the block (i.e. have different states).
 							else {
-								block_id != id
+								(block_id, block_state) != (id, state)
 							}
 						}
 						// No model matches the id... x_x
@@ -514,6 +927,104 @@ impl IntegratedBuffer {
 		desired_phase
 	}
 
+	/// Forwards changed blocks to the meshes of their chunks (if they are greedy meshed),
+	/// then replaces the quads of the affected layers.
+	fn update_mesh(&mut self, points: &[block::Point]) -> anyhow::Result<()> {
+		let mut chunks = HashSet::new();
+		for point in points.iter() {
+			if self.meshes.get(point.chunk()).is_none() {
+				continue;
+			}
+			let block = self.get_block(point).map(|(_phase, id, state)| (id, state));
+			self.meshes.set_block(point, block);
+			chunks.insert(*point.chunk());
+		}
+		for chunk in chunks.into_iter() {
+			self.update_quads(&chunk)?;
+		}
+		Ok(())
+	}
+
+	/// Re-meshes the changed layers of a greedy meshed chunk,
+	/// allocating instances for its new quads and deallocating the instances of quads which no longer exist.
+	fn update_quads(&mut self, chunk: &Point3<i64>) -> anyhow::Result<()> {
+		let is_opaque = self.models.opacity()?;
+		let quads = match self.meshes.get_mut(chunk) {
+			Some(mesh) => {
+				mesh.remesh(&*is_opaque);
+				mesh.quads()
+					.map(|quad| ((quad.origin.cast::<i8>(), quad.face), *quad))
+					.collect::<HashMap<_, _>>()
+			}
+			None => return Ok(()),
+		};
+
+		let stale = match self.quads.get(chunk) {
+			Some(chunk_quads) => chunk_quads
+				.iter()
+				.filter(|(key, (quad, _idx))| quads.get(key) != Some(quad))
+				.map(|(key, _)| *key)
+				.collect::<Vec<_>>(),
+			None => Vec::new(),
+		};
+		for key in stale.into_iter() {
+			self.remove_quad(chunk, &key)?;
+		}
+
+		self.quads.entry(*chunk).or_insert_with(HashMap::new);
+		for (key, quad) in quads.into_iter() {
+			if self.quads[chunk].contains_key(&key) {
+				continue;
+			}
+			let origin = block::Point::new(*chunk, key.0);
+			let light = match self.get_instance_mut(&origin, IdPhase::Inactive) {
+				Some((_idx, instance)) => instance.light(),
+				None => 0,
+			};
+			let idx = self.get_category(category::Key::Unallocated).start();
+			let idx =
+				self.move_instance(idx, category::Key::Unallocated, category::Key::Id(quad.id));
+			self.instances[idx] = Instance::from_quad(chunk, &quad, light);
+			self.changed_ranges.insert(idx);
+			self.quads.get_mut(chunk).unwrap().insert(key, (quad, idx));
+		}
+		Ok(())
+	}
+
+	/// Deallocates the instances of every quad of a greedy meshed chunk.
+	fn remove_quads(&mut self, chunk: &Point3<i64>) -> Result<(), Error> {
+		let keys = match self.quads.get(chunk) {
+			Some(chunk_quads) => chunk_quads.keys().cloned().collect::<Vec<_>>(),
+			None => return Ok(()),
+		};
+		for key in keys.into_iter() {
+			self.remove_quad(chunk, &key)?;
+		}
+		let _ = self.quads.remove(chunk);
+		Ok(())
+	}
+
+	fn remove_quad(&mut self, chunk: &Point3<i64>, key: &(Point3<i8>, Face)) -> Result<(), Error> {
+		// The quad is removed from the mapping first, so moving other quads does not change its index.
+		let (quad, idx) = match self
+			.quads
+			.get_mut(chunk)
+			.map(|quads| quads.remove(key))
+			.flatten()
+		{
+			Some(entry) => entry,
+			None => {
+				return Err(Error::PointNotAllocatedInChunk(block::Point::new(
+					*chunk, key.0,
+				)))
+			}
+		};
+		let idx = self.move_instance(idx, category::Key::Id(quad.id), category::Key::Unallocated);
+		self.instances[idx] = Instance::default();
+		self.changed_ranges.insert(idx);
+		Ok(())
+	}
+
 	fn change_phase(
 		&mut self,
 		point: &block::Point,
@@ -592,6 +1103,22 @@ impl IntegratedBuffer {
 	}
 }
 
+/// The voxels of the chunks the client has received (and those it predicted while waiting for them),
+/// which the client's prediction of its player is stopped against, like the server stops the player against its chunks.
+impl physics::Voxels for Mutex<IntegratedBuffer> {
+	fn colliders_at(&self, point: &block::Point) -> Option<Vec<block::Collider>> {
+		let buffer = self.lock().unwrap();
+		// Every chunk in the buffer has a light map, even if it has no blocks.
+		if !buffer.light.contains_key(point.chunk()) {
+			return None;
+		}
+		Some(match buffer.get_block(point) {
+			Some((_, id, _)) => block::Lookup::collider_of(id).colliders_at(point),
+			None => Vec::new(),
+		})
+	}
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
 	#[error("Chunk <{0}, {1}, {2}> has no allocations.")]
@@ -610,3 +1137,338 @@ pub enum Error {
 	#[error("Model cache was dropped.")]
 	InvalidModelCache,
 }
+
+#[cfg(test)]
+mod integrated_buffer {
+	use super::*;
+
+	static OPAQUE: block::LookupId = 0;
+	static TRANSLUCENT: block::LookupId = 1;
+	static LAMP: block::LookupId = 2;
+
+	fn properties(is_opaque: bool, light_emission: u8) -> model::Properties {
+		model::Properties {
+			is_opaque,
+			light_emission,
+		}
+	}
+
+	fn create_buffer(instance_capacity: usize) -> IntegratedBuffer {
+		let properties = HashMap::from([
+			(OPAQUE, properties(true, 0)),
+			(TRANSLUCENT, properties(false, 0)),
+		]);
+		IntegratedBuffer::with_models(2, instance_capacity, ModelSource::Properties(properties))
+	}
+
+	fn create_lit_buffer(instance_capacity: usize) -> IntegratedBuffer {
+		let properties = HashMap::from([
+			(OPAQUE, properties(true, 0)),
+			(TRANSLUCENT, properties(false, 0)),
+			(LAMP, properties(true, 3)),
+		]);
+		IntegratedBuffer::with_models(3, instance_capacity, ModelSource::Properties(properties))
+	}
+
+	fn cube(
+		size: usize,
+		id: block::LookupId,
+	) -> Vec<(Point3<usize>, block::LookupId, block::State)> {
+		let mut points = Vec::with_capacity(size * size * size);
+		for x in 0..size {
+			for y in 0..size {
+				for z in 0..size {
+					points.push((Point3::new(x, y, z), id, block::DEFAULT_STATE));
+				}
+			}
+		}
+		points
+	}
+
+	fn instance_at(buffer: &IntegratedBuffer, point: &block::Point) -> Instance {
+		match buffer.get_block(point) {
+			Some((IdPhase::Active, _, _)) => {
+				let (_id, idx) = buffer.active_points[point.chunk()][point.offset()];
+				buffer.instances[idx].clone()
+			}
+			Some((IdPhase::Inactive, _, _)) => buffer.inactive_points[point.chunk()]
+				[point.offset()]
+			.1
+			.clone(),
+			None => panic!("no block at {point}"),
+		}
+	}
+
+	#[test]
+	fn chunks_in_the_buffer_are_voxels() {
+		use physics::Voxels;
+		let mut buffer = create_buffer(10);
+		buffer
+			.insert_chunk(Point3::new(0, 0, 0), cube(1, OPAQUE))
+			.unwrap();
+		let buffer = Mutex::new(buffer);
+		let point = |chunk: Point3<i64>, x: i8| block::Point::new(chunk, Point3::new(x, 0, 0));
+		assert!(!buffer
+			.colliders_at(&point(Point3::new(0, 0, 0), 0))
+			.unwrap()
+			.is_empty());
+		assert!(buffer
+			.colliders_at(&point(Point3::new(0, 0, 0), 1))
+			.unwrap()
+			.is_empty());
+		// Chunks the client doesn't have are unknown, so they stop the player like they do on the server.
+		assert!(buffer
+			.colliders_at(&point(Point3::new(1, 0, 0), 0))
+			.is_none());
+	}
+
+	#[test]
+	fn counts_after_insert_chunk() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 1, 0);
+		// The center of a 3x3x3 opaque cube has no visible faces
+		buffer.insert_chunk(chunk, cube(3, OPAQUE)).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 26);
+		assert_eq!(buffer.inactive_count_in(&chunk), 1);
+		assert_eq!(buffer.active_count_in(&Point3::new(0, 0, 0)), 0);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 26), (Some(TRANSLUCENT), 0), (None, 64 - 26)]
+		);
+	}
+
+	#[test]
+	fn counts_after_remove_chunk() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		buffer.insert_chunk(chunk, cube(2, TRANSLUCENT)).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 8);
+		assert_eq!(buffer.inactive_count_in(&chunk), 0);
+
+		buffer.remove_chunk(&chunk).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 0);
+		assert_eq!(buffer.inactive_count_in(&chunk), 0);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 0), (Some(TRANSLUCENT), 0), (None, 64)]
+		);
+	}
+
+	#[test]
+	fn solid_blocks_in_inserted_chunks() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		buffer.insert_chunk(chunk, cube(3, OPAQUE)).unwrap();
+		// Both blocks with faces, and blocks hidden by their neighbors, are solid.
+		assert_eq!(
+			buffer.is_solid(&block::Point::new(chunk, Point3::new(0, 0, 0))),
+			Some(true)
+		);
+		assert_eq!(
+			buffer.is_solid(&block::Point::new(chunk, Point3::new(1, 1, 1))),
+			Some(true)
+		);
+		assert_eq!(
+			buffer.is_solid(&block::Point::new(chunk, Point3::new(4, 0, 0))),
+			Some(false)
+		);
+		let unloaded = Point3::new(1, 0, 0);
+		assert_eq!(
+			buffer.is_solid(&block::Point::new(unloaded, Point3::new(0, 0, 0))),
+			None
+		);
+	}
+
+	#[test]
+	fn reconcile_matching_chunk_is_unchanged() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		buffer.insert_chunk(chunk, cube(3, OPAQUE)).unwrap();
+		let _ = buffer.take_changed_ranges();
+
+		// The authoritative chunk matches the prediction, so no instances need to be uploaded.
+		buffer.reconcile_chunk(chunk, cube(3, OPAQUE)).unwrap();
+		assert!(buffer.take_changed_ranges().is_none());
+		assert_eq!(buffer.active_count_in(&chunk), 26);
+		assert_eq!(buffer.inactive_count_in(&chunk), 1);
+	}
+
+	#[test]
+	fn reconcile_changes_differing_blocks() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		buffer.insert_chunk(chunk, cube(3, OPAQUE)).unwrap();
+		let _ = buffer.take_changed_ranges();
+
+		// The prediction has an extra block in the corner, and the center block differs.
+		let mut authoritative = cube(3, OPAQUE);
+		authoritative.retain(|(point, _, _)| *point != Point3::new(2, 2, 2));
+		for (point, id, _) in authoritative.iter_mut() {
+			if *point == Point3::new(1, 1, 1) {
+				*id = TRANSLUCENT;
+			}
+		}
+		buffer.reconcile_chunk(chunk, authoritative).unwrap();
+		assert!(buffer.take_changed_ranges().is_some());
+		// The translucent center is still surrounded by opaque blocks, so it has no faces to render.
+		assert_eq!(buffer.active_count_in(&chunk), 25);
+		assert_eq!(buffer.inactive_count_in(&chunk), 1);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 25), (Some(TRANSLUCENT), 0), (None, 64 - 25)]
+		);
+	}
+
+	#[test]
+	fn state_distinguishes_instances() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		let a = block::Point::new(chunk, Point3::new(0, 0, 0));
+		let b = block::Point::new(chunk, Point3::new(1, 0, 0));
+		let touching_faces = |point: &block::Point, buffer: &IntegratedBuffer| {
+			let faces = instance_at(buffer, point).faces();
+			EnumSet::<Face>::all()
+				.iter()
+				.filter(|face| {
+					let adjacent = *point + face.direction();
+					adjacent == a || adjacent == b
+				})
+				.all(|face| faces.contains(face))
+		};
+
+		// Adjacent translucent blocks of the same type and state hide their touching faces.
+		buffer.set_id_for(&a, Some((TRANSLUCENT, 0))).unwrap();
+		buffer.set_id_for(&b, Some((TRANSLUCENT, 0))).unwrap();
+		assert!(!touching_faces(&a, &buffer));
+		assert!(!touching_faces(&b, &buffer));
+		assert_eq!(instance_at(&buffer, &a).faces().len(), 5);
+
+		// A different state is a different variant of the block, so the touching faces are shown.
+		buffer.set_id_for(&b, Some((TRANSLUCENT, 3))).unwrap();
+		assert_eq!(instance_at(&buffer, &a).state(), 0);
+		assert_eq!(instance_at(&buffer, &b).state(), 3);
+		assert!(touching_faces(&a, &buffer));
+		assert!(touching_faces(&b, &buffer));
+		assert_eq!(instance_at(&buffer, &b).faces().len(), 6);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 0), (Some(TRANSLUCENT), 2), (None, 62)]
+		);
+	}
+
+	#[test]
+	fn instances_are_lit_by_nearby_lamps() {
+		let mut buffer = create_lit_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		let lamp = block::Point::new(chunk, Point3::new(0, 0, 0));
+		let glass = block::Point::new(chunk, Point3::new(2, 0, 0));
+		let wall = block::Point::new(chunk, Point3::new(0, 2, 0));
+		buffer
+			.insert_chunk(
+				chunk,
+				vec![
+					(Point3::new(0, 0, 0), LAMP, block::DEFAULT_STATE),
+					(Point3::new(2, 0, 0), TRANSLUCENT, block::DEFAULT_STATE),
+					(Point3::new(0, 2, 0), OPAQUE, block::DEFAULT_STATE),
+				],
+			)
+			.unwrap();
+		assert_eq!(instance_at(&buffer, &lamp).light(), 3);
+		// Both blocks are next to a voxel 1 block from the lamp
+		assert_eq!(instance_at(&buffer, &glass).light(), 2);
+		assert_eq!(instance_at(&buffer, &wall).light(), 2);
+
+		buffer.set_id_for(&lamp, None).unwrap();
+		assert_eq!(instance_at(&buffer, &glass).light(), 0);
+		assert_eq!(instance_at(&buffer, &wall).light(), 0);
+	}
+
+	#[test]
+	fn unloaded_lamps_stop_lighting_neighbors() {
+		let mut buffer = create_lit_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		let neighbor = Point3::new(1, 0, 0);
+		let glass = block::Point::new(neighbor, Point3::new(0, 0, 0));
+		buffer
+			.insert_chunk(
+				chunk,
+				vec![(Point3::new(15, 0, 0), LAMP, block::DEFAULT_STATE)],
+			)
+			.unwrap();
+		buffer
+			.insert_chunk(
+				neighbor,
+				vec![(Point3::new(0, 0, 0), TRANSLUCENT, block::DEFAULT_STATE)],
+			)
+			.unwrap();
+		assert_eq!(instance_at(&buffer, &glass).light(), 3);
+
+		buffer.remove_chunk(&chunk).unwrap();
+		assert!(!buffer.light.contains_key(&chunk));
+		assert_eq!(instance_at(&buffer, &glass).light(), 0);
+	}
+
+	#[test]
+	fn greedy_chunks_render_quads() {
+		let mut buffer = create_buffer(64);
+		let chunk = Point3::new(0, 0, 0);
+		buffer.insert_chunk(chunk, cube(2, OPAQUE)).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 8);
+
+		buffer.set_mesh_mode(chunk, MeshMode::Greedy).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 0);
+		assert_eq!(buffer.inactive_count_in(&chunk), 8);
+		// Each side of the cube is merged into a single quad.
+		assert_eq!(buffer.quad_count_in(&chunk), 6);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 6), (Some(TRANSLUCENT), 0), (None, 64 - 6)]
+		);
+
+		// Removing a corner splits each of the 3 sides it was on into 2 quads,
+		// and shows the 3 faces which were touching it.
+		let corner = block::Point::new(chunk, Point3::new(1, 1, 1));
+		buffer.set_id_for(&corner, None).unwrap();
+		assert_eq!(buffer.active_count_in(&chunk), 0);
+		assert_eq!(buffer.quad_count_in(&chunk), 12);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 12), (Some(TRANSLUCENT), 0), (None, 64 - 12)]
+		);
+		for ((offset, face), (_quad, idx)) in buffer.quads[&chunk].iter() {
+			let instance = &buffer.instances[*idx];
+			assert_eq!(instance.point(), block::Point::new(chunk, *offset));
+			assert_eq!(instance.faces(), EnumSet::only(*face));
+		}
+
+		buffer.set_mesh_mode(chunk, MeshMode::Instanced).unwrap();
+		assert_eq!(buffer.quad_count_in(&chunk), 0);
+		assert_eq!(buffer.active_count_in(&chunk), 7);
+		assert_eq!(
+			buffer.category_lengths(),
+			vec![(Some(OPAQUE), 7), (Some(TRANSLUCENT), 0), (None, 64 - 7)]
+		);
+	}
+
+	#[test]
+	fn last_taken_matches_changed_ranges() {
+		let mut buffer = create_buffer(64);
+		assert_eq!(buffer.last_taken(), ChangeStats::default());
+
+		buffer
+			.insert_chunk(Point3::new(0, 0, 0), cube(3, OPAQUE))
+			.unwrap();
+		let (ranges, total_count) = buffer.take_changed_ranges().unwrap();
+		let stats = buffer.last_taken();
+		assert_eq!(stats.ranges, ranges.len());
+		assert_eq!(
+			stats.indices,
+			ranges.iter().map(|range| range.len()).sum::<usize>()
+		);
+		assert_eq!(stats.indices, total_count);
+
+		// Taking again when nothing has changed resets the stats.
+		assert!(buffer.take_changed_ranges().is_none());
+		assert_eq!(buffer.last_taken(), ChangeStats::default());
+	}
+}
@@ -1,6 +1,24 @@
 use std::cmp::Ordering;
 use std::ops::Range;
 
+/// The size of the changes taken from a [`RangeSet`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeStats {
+	/// The number of contiguous ranges.
+	pub ranges: usize,
+	/// The total number of indices across all of the ranges.
+	pub indices: usize,
+}
+
+impl ChangeStats {
+	pub fn of(ranges: &[Range<usize>]) -> Self {
+		Self {
+			ranges: ranges.len(),
+			indices: ranges.iter().map(|range| range.len()).sum(),
+		}
+	}
+}
+
 /// An ordered set of ranges.
 /// Used to keep track of what indices have changed in a vec, without having a ginormous HashSet of usize indices.
 #[derive(Default)]
@@ -1,4 +1,7 @@
-use crate::graphics::voxel::instance::{category::Category, local::IntegratedBuffer, Instance};
+use crate::graphics::{
+	voxel::instance::{category::Category, local::IntegratedBuffer, Instance},
+	Growable,
+};
 use anyhow::Result;
 use engine::channels::mpsc::Sender;
 use engine::graphics::{
@@ -8,44 +11,128 @@ use std::sync::Arc;
 
 pub struct Description {
 	pub(crate) categories: Vec<Category>,
-	pub(crate) buffer: Arc<Buffer>,
+	allocator: Arc<alloc::Allocator>,
+	buffer: Growable<Arc<Buffer>>,
 }
 
 impl Description {
-	pub fn new(allocator: &Arc<alloc::Allocator>, instance_buffer_size: usize) -> Result<Self> {
-		let buffer = Buffer::create_gpu(
+	pub fn new(
+		allocator: &Arc<alloc::Allocator>,
+		capacity: usize,
+		frames_in_flight: usize,
+	) -> Result<Self> {
+		let buffer = Self::create_buffer(allocator, capacity)?;
+		Ok(Self {
+			categories: Vec::new(),
+			allocator: allocator.clone(),
+			buffer: Growable::new("RenderVoxel", buffer, capacity, frames_in_flight),
+		})
+	}
+
+	fn create_buffer(allocator: &Arc<alloc::Allocator>, capacity: usize) -> Result<Arc<Buffer>> {
+		Ok(Buffer::create_gpu(
 			format!("RenderVoxel.InstanceBuffer"),
 			allocator,
 			flags::BufferUsage::VERTEX_BUFFER,
-			instance_buffer_size,
+			capacity * std::mem::size_of::<Instance>(),
 			None,
 			false,
-		)?;
+		)?)
+	}
 
-		Ok(Self {
-			categories: Vec::new(),
-			buffer,
-		})
+	pub fn buffer(&self) -> &Arc<Buffer> {
+		self.buffer.current()
+	}
+
+	/// Returns true if the most recent submission had more instances than the buffer could hold,
+	/// and a larger buffer could not be allocated. The instances which did not fit are not rendered.
+	pub fn capacity_exceeded(&self) -> bool {
+		self.buffer.capacity_exceeded()
+	}
+
+	/// Returns the ranges of instances which need to be written to a buffer which can hold `writable_count` instances,
+	/// dropping the instances which do not fit. All instances are written to a buffer which was just allocated.
+	fn writable_ranges(
+		changed_ranges: Vec<std::ops::Range<usize>>,
+		writable_count: usize,
+		is_new_buffer: bool,
+	) -> Vec<std::ops::Range<usize>> {
+		let changed_ranges = match is_new_buffer {
+			true => vec![0..writable_count],
+			false => changed_ranges,
+		};
+		changed_ranges
+			.into_iter()
+			.map(|range| range.start.min(writable_count)..range.end.min(writable_count))
+			.filter(|range| !range.is_empty())
+			.collect()
+	}
+
+	/// Returns the categories of instances, without the instances past the `writable_count` which are not in the buffer.
+	fn writable_categories(categories: &[Category], writable_count: usize) -> Vec<Category> {
+		use crate::graphics::voxel::instance::category::Operation;
+		categories
+			.iter()
+			.filter(|category| category.start() < writable_count)
+			.map(|category| {
+				let mut category = *category;
+				let end = category.start() + category.count();
+				if end > writable_count {
+					category.apply(Operation::ChangeSize(-((end - writable_count) as i32)));
+				}
+				category
+			})
+			.collect()
+	}
+
+	/// Returns the number of bytes of instance data that are copied to the gpu buffer
+	/// when the changed ranges are [`submitted`](Self::submit).
+	pub fn upload_size(changed_ranges: &[std::ops::Range<usize>]) -> usize {
+		let instance_count: usize = changed_ranges.iter().map(|range| range.len()).sum();
+		instance_count * std::mem::size_of::<Instance>()
 	}
 
 	pub fn submit(
 		&mut self,
 		changed_ranges: Vec<std::ops::Range<usize>>,
-		total_count: usize,
 		local: &IntegratedBuffer,
 		context: &impl GpuOpContext,
 		signal_sender: &Sender<Arc<command::Semaphore>>,
-	) -> Result<()> {
+	) -> Result<usize> {
 		self.categories.clear();
 
+		// Unused instances are at the end of the local buffer, in the category without a block-type.
+		let used_count = local
+			.get_categories()
+			.last()
+			.map(|unused| unused.start())
+			.unwrap_or_default();
+		let previous_capacity = self.buffer.capacity();
+		let writable_count = {
+			let allocator = &self.allocator;
+			self.buffer.reserve(used_count, |capacity| {
+				Self::create_buffer(allocator, capacity)
+			})
+		};
+		let changed_ranges = Self::writable_ranges(
+			changed_ranges,
+			writable_count,
+			self.buffer.capacity() != previous_capacity,
+		);
+		let total_count = changed_ranges
+			.iter()
+			.map(|range| range.len())
+			.sum::<usize>();
+		let uploaded_bytes = Self::upload_size(&changed_ranges);
+		let buffer = self.buffer.current().clone();
+
 		let mut ranges = Vec::with_capacity(changed_ranges.len());
 		let instance_size = std::mem::size_of::<Instance>();
 
 		let mut task = {
 			profiling::scope!("prepare-task");
 			let mut task =
-				GpuOperationBuilder::new(format!("Write({})", self.buffer.name()), context)?
-					.begin()?;
+				GpuOperationBuilder::new(format!("Write({})", buffer.name()), context)?.begin()?;
 			task.stage_start(total_count * instance_size)?;
 			task
 		};
@@ -69,13 +156,71 @@ impl Description {
 
 		{
 			profiling::scope!("run-task");
-			task.copy_stage_to_buffer_ranges(&self.buffer, ranges)
+			task.copy_stage_to_buffer_ranges(&buffer, ranges)
 				.send_signal_to(signal_sender)?
 				.end()?;
 		}
 
-		self.categories = local.get_categories().clone();
+		self.categories = Self::writable_categories(local.get_categories(), writable_count);
+
+		Ok(uploaded_bytes)
+	}
+}
+
+#[cfg(test)]
+mod upload_size {
+	use super::*;
+
+	#[test]
+	fn sums_changed_ranges() {
+		let instance_size = std::mem::size_of::<Instance>();
+		assert_eq!(Description::upload_size(&[]), 0);
+		assert_eq!(Description::upload_size(&[0..1]), instance_size);
+		assert_eq!(
+			Description::upload_size(&[0..4, 10..12, 100..200]),
+			(4 + 2 + 100) * instance_size
+		);
+	}
+}
+
+#[cfg(test)]
+mod writable {
+	use super::*;
+	use crate::graphics::voxel::instance::category::Operation;
+
+	fn category(id: Option<usize>, start: usize, count: usize) -> Category {
+		let mut category = Category::new(id, count);
+		category.apply(Operation::Shift(start as i32));
+		category
+	}
+
+	#[test]
+	fn instances_past_capacity_are_dropped() {
+		assert_eq!(
+			Description::writable_ranges(vec![0..2, 5..12, 20..30], 10, false),
+			vec![0..2, 5..10]
+		);
+		let categories = Description::writable_categories(
+			&[
+				category(Some(0), 0, 6),
+				category(Some(1), 6, 8),
+				category(Some(2), 14, 4),
+				category(None, 18, 100),
+			],
+			10,
+		);
+		let bounds = categories
+			.iter()
+			.map(|category| (category.id, category.start(), category.count()))
+			.collect::<Vec<_>>();
+		assert_eq!(bounds, vec![(Some(0), 0, 6), (Some(1), 6, 4)]);
+	}
 
-		Ok(())
+	#[test]
+	fn new_buffer_is_written_in_full() {
+		assert_eq!(
+			Description::writable_ranges(vec![3..4], 10, true),
+			vec![0..10]
+		);
 	}
 }
@@ -8,5 +8,9 @@ mod load_thread;
 pub use load_thread::*;
 mod model;
 pub use model::*;
+mod properties;
+pub use properties::*;
+mod reload;
+pub use reload::*;
 mod vertex;
 pub use vertex::*;
@@ -1,6 +1,6 @@
 use crate::{
 	block,
-	graphics::voxel::model::{Model, Vertex},
+	graphics::voxel::model::{Model, Properties, PropertyMap, Vertex},
 };
 use anyhow::Result;
 use engine::channels::mpsc::Sender;
@@ -8,32 +8,39 @@ use engine::graphics::{
 	buffer, command::Semaphore, descriptor, flags, utility::NamedObject, DescriptorCache,
 	GpuOpContext, GpuOperationBuilder,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, RwLock, Weak},
+};
+
+type ModelMap = HashMap<
+	block::LookupId,
+	(
+		Arc<Model>,
+		/*index start*/ usize,
+		/*vertex offset*/ usize,
+	),
+>;
 
 #[derive(Default)]
 pub struct CacheBuilder {
-	models: HashMap<
-		block::LookupId,
-		(
-			Model,
-			/*index start*/ usize,
-			/*vertex offset*/ usize,
-		),
-	>,
+	models: ModelMap,
+	properties: PropertyMap,
 	atlas_descriptor_cache: Option<DescriptorCache<(usize, usize)>>,
 	vertices: Vec<Vertex>,
 	indices: Vec<u32>,
 }
 
 impl CacheBuilder {
-	pub fn insert(&mut self, block_id: block::LookupId, model: Model) {
+	pub fn insert(&mut self, block_id: block::LookupId, model: Model, properties: Properties) {
 		use crate::graphics::model::Model;
 		let index_start = self.indices.len();
 		let vertex_offset = self.vertices.len();
 		self.vertices.append(&mut model.vertices().clone());
 		self.indices.append(&mut model.indices().clone());
 		self.models
-			.insert(block_id, (model, index_start, vertex_offset));
+			.insert(block_id, (Arc::new(model), index_start, vertex_offset));
+		self.properties.update(block_id, properties);
 	}
 
 	pub fn set_atlas_descriptor_cache(&mut self, cache: DescriptorCache<(usize, usize)>) {
@@ -50,15 +57,11 @@ impl CacheBuilder {
 }
 
 pub struct Cache {
-	models: HashMap<
-		block::LookupId,
-		(
-			Model,
-			/*index start*/ usize,
-			/*vertex offset*/ usize,
-		),
-	>,
+	models: RwLock<ModelMap>,
+	properties: RwLock<PropertyMap>,
 	atlas_descriptor_cache: DescriptorCache<(usize, usize)>,
+	/// The contents of `vertex_buffer`, kept so that the buffer can be rewritten when a model is reloaded.
+	vertices: Mutex<Vec<Vertex>>,
 	pub(crate) vertex_buffer: Arc<buffer::Buffer>,
 	pub(crate) index_buffer: Arc<buffer::Buffer>,
 }
@@ -86,12 +89,7 @@ impl Cache {
 				false,
 			)?;
 
-			GpuOperationBuilder::new(format!("Write({})", vertex_buffer.name()), context)?
-				.begin()?
-				.stage(&builder.vertices[..])?
-				.copy_stage_to_buffer(&vertex_buffer)
-				.send_signal_to(signal_sender)?
-				.end()?;
+			Self::write_vertices(&vertex_buffer, &builder.vertices, context, signal_sender)?;
 
 			let index_buffer = buffer::Buffer::create_gpu(
 				"RenderVoxel.IndexBuffer".to_owned(),
@@ -113,13 +111,48 @@ impl Cache {
 		};
 
 		Ok(Self {
-			models: builder.models,
+			models: RwLock::new(builder.models),
+			properties: RwLock::new(builder.properties),
 			atlas_descriptor_cache: builder.atlas_descriptor_cache.unwrap(),
+			vertices: Mutex::new(builder.vertices),
 			vertex_buffer,
 			index_buffer,
 		})
 	}
 
+	fn write_vertices(
+		vertex_buffer: &Arc<buffer::Buffer>,
+		vertices: &Vec<Vertex>,
+		context: &impl GpuOpContext,
+		signal_sender: &Sender<Arc<Semaphore>>,
+	) -> Result<()> {
+		GpuOperationBuilder::new(format!("Write({})", vertex_buffer.name()), context)?
+			.begin()?
+			.stage(&vertices[..])?
+			.copy_stage_to_buffer(&vertex_buffer)
+			.send_signal_to(signal_sender)?
+			.end()?;
+		Ok(())
+	}
+
+	fn active_static() -> &'static mut Option<Weak<Cache>> {
+		static mut ACTIVE: Option<Weak<Cache>> = None;
+		unsafe { &mut ACTIVE }
+	}
+
+	/// Marks the cache as the one used to render voxels, so it can be found by [`active`](Cache::active).
+	pub(crate) fn set_active(cache: &Arc<Cache>) {
+		*Self::active_static() = Some(Arc::downgrade(&cache));
+	}
+
+	/// Returns the model cache that voxels are currently being rendered with (if models have been loaded).
+	pub fn active() -> Option<Arc<Cache>> {
+		Self::active_static()
+			.as_ref()
+			.map(|weak| weak.upgrade())
+			.flatten()
+	}
+
 	pub fn descriptor_layout(&self) -> &Arc<descriptor::layout::SetLayout> {
 		self.atlas_descriptor_cache.layout()
 	}
@@ -127,11 +160,73 @@ impl Cache {
 	pub fn get(
 		&self,
 		id: &block::LookupId,
-	) -> Option<&(
-		Model,
+	) -> Option<(
+		Arc<Model>,
 		/*index start*/ usize,
 		/*vertex offset*/ usize,
 	)> {
-		self.models.get(&id)
+		self.models.read().unwrap().get(&id).cloned()
 	}
+
+	pub fn is_opaque(&self, id: &block::LookupId) -> Option<bool> {
+		self.properties.read().unwrap().is_opaque(&id)
+	}
+
+	pub fn light_emission(&self, id: &block::LookupId) -> Option<u8> {
+		self.properties.read().unwrap().light_emission(&id)
+	}
+
+	/// Replaces the model of a block which is already in the cache.
+	///
+	/// The vertex buffer is rewritten in place (not reallocated), so the replacement
+	/// must have the same geometry (number of faces) as the existing model;
+	/// only its texture coordinates, flags, and properties can change.
+	/// Returns true if the model's properties have changed.
+	pub fn replace(
+		&self,
+		id: block::LookupId,
+		model: Model,
+		properties: Properties,
+		context: &impl GpuOpContext,
+		signal_sender: &Sender<Arc<Semaphore>>,
+	) -> Result<bool> {
+		use crate::graphics::model::Model;
+		let mut models = self.models.write().unwrap();
+		let (vertex_offset, index_start) = {
+			let (existing, index_start, vertex_offset) =
+				models.get(&id).ok_or(ReplaceError::UnknownModel(id))?;
+			if existing.vertices().len() != model.vertices().len()
+				|| existing.indices() != model.indices()
+			{
+				return Err(ReplaceError::GeometryChanged(id).into());
+			}
+			(*vertex_offset, *index_start)
+		};
+
+		{
+			let mut vertices = self.vertices.lock().unwrap();
+			let range = vertex_offset..vertex_offset + model.vertices().len();
+			vertices[range].clone_from_slice(&model.vertices()[..]);
+			Self::write_vertices(&self.vertex_buffer, &vertices, context, signal_sender)?;
+		}
+
+		models.insert(id, (Arc::new(model), index_start, vertex_offset));
+		Ok(self.properties.write().unwrap().update(id, properties))
+	}
+}
+
+impl Drop for Cache {
+	fn drop(&mut self) {
+		*Self::active_static() = None;
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplaceError {
+	#[error("no block model for lookup id {0}")]
+	UnknownModel(block::LookupId),
+	#[error(
+		"the number of faces for block model {0} has changed, which requires rebuilding all models"
+	)]
+	GeometryChanged(block::LookupId),
 }
@@ -1,17 +1,30 @@
-use crate::graphics::voxel::{atlas::AtlasTexCoord, model::Flags};
+use crate::{
+	block::TextureRotation,
+	graphics::voxel::{atlas::AtlasTexCoord, model::Flags},
+};
+use std::time::Duration;
 
 pub struct FaceData {
 	pub main_tex: AtlasTexCoord,
 	pub biome_color_tex: Option<AtlasTexCoord>,
 	pub flags: Flags,
+	/// Applied to both the main texture and the biome color mask, so the mask stays aligned with the texture.
+	pub rotation: TextureRotation,
+	/// How long each frame of the main texture is drawn, if it has more than 1 frame.
+	pub frame_duration: Option<Duration>,
 }
 
 impl std::fmt::Debug for FaceData {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(
 			f,
-			"{} => (main_tex={:?}, biome_color=(enabled={}, mask={:?}))",
-			self.flags.face, self.main_tex, self.flags.biome_color_enabled, self.biome_color_tex,
+			"{} => (main_tex={:?}, biome_color=(enabled={}, mask={:?}), rotation={}, frame_duration={:?})",
+			self.flags.face,
+			self.main_tex,
+			self.flags.biome_color_enabled,
+			self.biome_color_tex,
+			self.rotation.degrees(),
+			self.frame_duration,
 		)
 	}
 }
@@ -2,7 +2,7 @@ use crate::{
 	app::state::ArcLockMachine,
 	block::{self, Block},
 	client::model::blender,
-	common::network::Storage,
+	common::{asset_batch, network::Storage},
 	graphics::voxel::{atlas, camera, model, RenderVoxel},
 	CrystalSphinx,
 };
@@ -31,6 +31,7 @@ pub fn load_models(
 	storage: Weak<RwLock<Storage>>,
 	chain: &Arc<RwLock<Chain>>,
 	phase: &Arc<Phase>,
+	viewmodel_phase: &Arc<Phase>,
 	camera: &Arc<RwLock<camera::Camera>>,
 	world: &Arc<RwLock<crate::entity::World>>,
 ) {
@@ -38,6 +39,7 @@ pub fn load_models(
 	let thread_storage = storage.clone();
 	let thread_chain = chain.clone();
 	let thread_phase = Arc::downgrade(&phase);
+	let thread_viewmodel_phase = Arc::downgrade(&viewmodel_phase);
 	let thread_camera = camera.clone();
 	let thread_world = Arc::downgrade(&world);
 	task::spawn(LOG.to_string(), async move {
@@ -49,26 +51,12 @@ pub fn load_models(
 			None => return Ok(()), // No ids were scanned
 		};
 
-		// Load each block asset (synchronously)
+		// Load the block assets, reading each pak archive once
 		log::debug!(target: LOG, "Loading {} block assets", block_ids.len());
-		let mut blocks = Vec::with_capacity(block_ids.len());
+		let blocks = asset_batch::load_many::<Block>(block_ids).await?;
 		let mut texture_ids = HashSet::with_capacity(blocks.len());
-		// TODO: This should load all of the block assets at once so we aren't constantly opening the zip archive
-		for asset_id in block_ids.into_iter() {
-			let any_box = asset::Loader::load_sync(&asset_id)?;
-			let block = match any_box.downcast::<Block>() {
-				Ok(block) => block,
-				_ => {
-					log::error!(target: LOG, "Failed to interpret block asset {}", asset_id);
-					return Ok(());
-				}
-			};
-			for (entry, _faces) in block.textures().iter() {
-				for texture_id in entry.texture_ids().iter() {
-					texture_ids.insert(texture_id.clone());
-				}
-			}
-			blocks.push((asset_id, block));
+		for (_asset_id, block) in blocks.iter() {
+			texture_ids.extend(block_texture_ids(&block));
 		}
 
 		let mut block_ids = blocks
@@ -84,14 +72,7 @@ pub fn load_models(
 			"Loading {} block texture assets",
 			texture_ids.len()
 		);
-		let mut textures = HashMap::with_capacity(texture_ids.len());
-		for asset_id in texture_ids.into_iter() {
-			if let Ok(any_box) = asset::Loader::load_sync(&asset_id) {
-				if let Ok(texture) = any_box.downcast::<Texture>() {
-					textures.insert(asset_id, texture);
-				}
-			}
-		}
+		let textures = load_textures(texture_ids);
 
 		let mut cache_builder = model::Cache::builder();
 
@@ -100,15 +81,13 @@ pub fn load_models(
 		// each block only needs to bind 1 atlas.
 		//
 		// NOTE:
-		// We are only using a 2k texture right now (2048x2048)
+		// We are only using 2k atlas pages right now (2048x2048)
 		// and expect all block textures to be 16x16.
-		// If/when we support textures larger than 16x16
-		// OR we exceed 16,384 16x16 textures, we will need to do more complex
-		// calculations such that all the textures fit onto atlases
-		// and each block only needs access to 1 atlas
-		// (even if it means uploading a given block texture on multiple atlases).
+		// When a block's textures do not fit in any existing page, they spill onto a new page
+		// (even if it means uploading a given block texture on multiple pages).
 		log::debug!(target: LOG, "Stitching block textures");
-		let mut atlas = atlas::Atlas::builder_2k();
+		let mut atlas_pages = atlas::Atlas::pages_2k();
+		let mut block_pages = HashMap::with_capacity(blocks.len());
 		for (block_id, block) in blocks.iter() {
 			let mut texture_map = HashMap::new();
 			for (entry, _faces) in block.textures().iter() {
@@ -123,17 +102,25 @@ pub fn load_models(
 					}
 				}
 			}
-			if !atlas.contains_or_fits_all(&texture_map) {
-				log::error!(
-					target: LOG,
-					"Cannot fit textures for block {} in atlas",
-					block_id
-				);
-				continue;
+			match atlas_pages.insert_all(&texture_map) {
+				Ok(page) => {
+					block_pages.insert(block_id.clone(), page);
+				}
+				Err(error) => {
+					log::error!(
+						target: LOG,
+						"Cannot fit textures for block {} in an atlas page: {}",
+						block_id,
+						error
+					);
+				}
 			}
-			// Actually insert the textures
-			atlas.insert_all(&texture_map)?;
 		}
+		log::debug!(
+			target: LOG,
+			"Stitched block textures into {} atlas page(s)",
+			atlas_pages.page_count()
+		);
 
 		log::debug!(target: LOG, "Creating block texture descriptor cache");
 		let mut atlas_descriptor_cache = {
@@ -172,25 +159,30 @@ pub fn load_models(
 				.build(&chain.logical()?)?
 		});
 
-		log::debug!(target: LOG, "Compiling atlas binary");
-		let atlas = {
+		log::debug!(target: LOG, "Compiling atlas binaries");
+		let atlases = {
 			let chain = thread_chain.read().unwrap();
-			Arc::new(atlas.build(
-				&*chain,
-				chain.signal_sender(),
-				"RenderVoxel.Atlas.0".to_owned(),
-			)?)
+			atlas_pages
+				.build(
+					&*chain,
+					chain.signal_sender(),
+					"RenderVoxel.Atlas".to_owned(),
+				)?
+				.into_iter()
+				.map(Arc::new)
+				.collect::<Vec<_>>()
 		};
 
-		// Create the descriptor set for the texture/atlas
-		let descriptor_set = {
+		// Create the descriptor set for each atlas page
+		let mut descriptor_sets = Vec::with_capacity(atlases.len());
+		for (page, atlas) in atlases.iter().enumerate() {
 			use descriptor::update::*;
 			let chain = thread_chain.read().unwrap();
 			let descriptor_set = atlas_descriptor_cache.insert(
-				// NOTE: This should be the id of the atlas and sampler in their respective caches,
-				// but right now there is only 1 atlas and 1 sampler
-				(0, 0),
-				format!("RenderVoxel.Atlas.Descriptor({}, {})", 0, 0),
+				// Keyed by the atlas page and the sampler,
+				// but right now all blocks use the same sampler.
+				(page, 0),
+				format!("RenderVoxel.Atlas.Descriptor({}, {})", page, 0),
 				chain.persistent_descriptor_pool(),
 			)?;
 
@@ -210,91 +202,51 @@ pub fn load_models(
 				}))
 				.apply(&*chain.logical()?);
 
-			descriptor_set
-		};
+			descriptor_sets.push(descriptor_set);
+		}
 
 		log::debug!(target: LOG, "Creating block models");
 		let mut models = HashMap::new();
 		for (block_id, block) in blocks.into_iter() {
-			// Create the model for the block
-			let mut builder = model::Model::builder();
-
-			builder.set_is_opaque(block.is_opaque());
-
-			// Block models "own" the atlases. If no blocks reference the atlas, it is dropped.
-			builder.set_atlas(atlas.clone(), atlas_sampler.clone(), descriptor_set.clone());
-
-			if block.textures().is_empty() {
-				log::warn!(target: LOG, "Block {} has no texture entries", block_id);
-			}
-			for (entry, faces) in block.textures() {
-				let main_tex = match atlas.get(&entry.texture_id) {
-					Some(tex) => tex,
-					None => continue,
-				};
-				let biome_color_tex = entry
-					.biome_color
-					.1
-					.as_ref()
-					.map(|id| atlas.get(&id))
-					.flatten();
-				for face in faces.iter() {
-					builder.insert(model::FaceData {
-						main_tex,
-						biome_color_tex,
-						flags: model::Flags {
-							face,
-							biome_color_enabled: entry.biome_color.0,
-							biome_color_masked: biome_color_tex.is_some(),
-						},
-					});
-				}
-			}
-
-			models.insert(block_id, builder.build());
+			// Blocks whose textures could not be stitched use the first page, and have no textured faces.
+			let page = block_pages.get(&block_id).cloned().unwrap_or(0);
+			let model = build_block_model(
+				&block_id,
+				&block,
+				&atlases[page],
+				&atlas_sampler,
+				&descriptor_sets[page],
+			);
+			models.insert(block_id, (model, model::Properties::from(&*block)));
 		}
 
 		cache_builder.set_atlas_descriptor_cache(atlas_descriptor_cache);
 
 		log::debug!(target: LOG, "Saving block models");
 		// Move the block model data into the cache
-		for (block_id, model) in models.into_iter() {
+		for (block_id, (model, properties)) in models.into_iter() {
 			let block_id = block::Lookup::lookup_value(&block_id).unwrap();
-			cache_builder.insert(block_id, model);
+			cache_builder.insert(block_id, model, properties);
 		}
 
 		log::debug!(target: LOG, "Finalizing model cache");
 		let model_cache = {
 			let chain = thread_chain.read().unwrap();
 			let model_cache = cache_builder.build(&*chain, chain.signal_sender())?;
-			model_cache
+			Arc::new(model_cache)
 		};
+		model::Cache::set_active(&model_cache);
 
 		// Gather asset ids for all model assets
-		let blender_models = match asset::Library::read()
+		let model_ids = asset::Library::read()
 			.get_ids_of_type::<blender::Asset>()
-			.cloned()
-		{
-			Some(model_ids) => {
-				let mut models = HashMap::with_capacity(model_ids.len());
-				// TODO: This should load all of the model assets at once so we aren't constantly opening the zip archive
-				for asset_id in model_ids.into_iter() {
-					let any_box = asset::Loader::load_sync(&asset_id)?;
-					let model = match any_box.downcast::<blender::Asset>() {
-						Ok(model) => model,
-						_ => {
-							log::error!(
-								target: LOG,
-								"Failed to interpret model asset {}",
-								asset_id
-							);
-							return Ok(());
-						}
-					};
-					models.insert(asset_id, model.compiled().clone());
-				}
-				models
-			}
+			.cloned();
+		let blender_models = match model_ids {
+			Some(model_ids) => asset_batch::load_many::<blender::Asset>(model_ids)
+				.await?
+				.into_iter()
+				.map(|(asset_id, model)| (asset_id, model.compiled().clone()))
+				.collect::<HashMap<_, _>>(),
 			None => HashMap::new(),
 		};
 		let (blender_model_cache, mut texture_cache) = {
@@ -322,12 +274,13 @@ pub fn load_models(
 			Arc::downgrade(&thread_chain),
 			thread_phase.clone(),
 			Arc::downgrade(&thread_camera),
-			Arc::new(model_cache),
+			model_cache,
 		);
 		let dependencies = crate::client::model::SystemDependencies {
 			storage: thread_storage.clone(),
 			render_chain: Arc::downgrade(&thread_chain),
 			render_phase: thread_phase.clone(),
+			viewmodel_phase: thread_viewmodel_phase.clone(),
 			camera: Arc::downgrade(&thread_camera),
 			world: thread_world.clone(),
 			blender_model_cache,
@@ -338,3 +291,74 @@ pub fn load_models(
 		Ok(())
 	});
 }
+
+/// Returns the ids of every texture the block's model uses.
+pub(crate) fn block_texture_ids(block: &Block) -> HashSet<asset::Id> {
+	let mut texture_ids = HashSet::new();
+	for (entry, _faces) in block.textures().iter() {
+		for texture_id in entry.texture_ids().iter() {
+			texture_ids.insert(texture_id.clone());
+		}
+	}
+	texture_ids
+}
+
+/// Synchronously loads texture assets, skipping any which fail to load.
+pub(crate) fn load_textures(
+	texture_ids: impl IntoIterator<Item = asset::Id>,
+) -> HashMap<asset::Id, Box<Texture>> {
+	let mut textures = HashMap::new();
+	for asset_id in texture_ids.into_iter() {
+		if let Ok(any_box) = asset::Loader::load_sync(&asset_id) {
+			if let Ok(texture) = any_box.downcast::<Texture>() {
+				textures.insert(asset_id, texture);
+			}
+		}
+	}
+	textures
+}
+
+/// Creates the model for a block whose textures have been stitched into the atlas.
+pub(crate) fn build_block_model(
+	block_id: &asset::Id,
+	block: &Block,
+	atlas: &Arc<atlas::Atlas>,
+	sampler: &Arc<sampler::Sampler>,
+	descriptor_set: &Weak<descriptor::Set>,
+) -> model::Model {
+	let mut builder = model::Model::builder();
+
+	// Block models "own" the atlases. If no blocks reference the atlas, it is dropped.
+	builder.set_atlas(atlas.clone(), sampler.clone(), descriptor_set.clone());
+
+	if block.textures().is_empty() {
+		log::warn!(target: LOG, "Block {} has no texture entries", block_id);
+	}
+	for (entry, faces) in block.textures() {
+		let main_tex = match atlas.get(&entry.texture_id) {
+			Some(tex) => tex,
+			None => continue,
+		};
+		let biome_color_tex = entry
+			.biome_color
+			.1
+			.as_ref()
+			.map(|id| atlas.get(&id))
+			.flatten();
+		for face in faces.iter() {
+			builder.insert(model::FaceData {
+				main_tex,
+				biome_color_tex,
+				flags: model::Flags {
+					face,
+					biome_color_enabled: entry.biome_color.0,
+					biome_color_masked: biome_color_tex.is_some(),
+				},
+				rotation: entry.rotation,
+				frame_duration: entry.animation.map(|animation| animation.frame_duration),
+			});
+		}
+	}
+
+	builder.build()
+}
@@ -46,7 +46,6 @@ static BR_MATRIX: Matrix4x2<f32> = Matrix4x2::new(
 
 #[derive(Default)]
 pub struct Builder {
-	is_opaque: bool,
 	faces: Vec<model::FaceData>,
 	vertices: Vec<Vertex>,
 	indices: Vec<u32>,
@@ -54,10 +53,6 @@ pub struct Builder {
 }
 
 impl Builder {
-	pub fn set_is_opaque(&mut self, is_opaque: bool) {
-		self.is_opaque = is_opaque;
-	}
-
 	pub fn insert(&mut self, face_data: model::FaceData) {
 		self.faces.push(face_data);
 	}
@@ -84,7 +79,6 @@ impl Builder {
 
 		let (atlas, sampler, descriptor_set) = self.atlas.unwrap();
 		Model {
-			is_opaque: self.is_opaque,
 			atlas,
 			sampler,
 			descriptor_set,
@@ -119,7 +113,16 @@ impl ModelBuilder for Builder {
 
 impl Builder {
 	fn push_face(&mut self, face_data: &model::FaceData) {
-		let unified_flags: Vector4<f32> = face_data.flags.clone().into();
+		let mut unified_flags: Vector4<f32> = face_data.flags.clone().into();
+		// Animated textures tell the shader how many frames there are, how long each is drawn,
+		// and how far apart (horizontally) the frames are in the atlas.
+		if let Some(frame_duration) = face_data.frame_duration {
+			if face_data.main_tex.frame_count > 1 {
+				unified_flags[1] = face_data.main_tex.frame_count as f32;
+				unified_flags[2] = frame_duration.as_secs_f32();
+				unified_flags[3] = face_data.main_tex.size.x;
+			}
+		}
 
 		let idx_tl = self.push_masked_vertex(&face_data, &TL_MATRIX, unified_flags);
 		let idx_tr = self.push_masked_vertex(&face_data, &TR_MATRIX, unified_flags);
@@ -137,6 +140,7 @@ impl Builder {
 	) -> u32 {
 		let offset_mask: Vector4<f32> = mask_mat.column(1).into();
 		let tex_coord_mask: Vector2<f32> = mask_mat.column(0).fixed_rows::<2>(0).into();
+		let tex_coord_mask = face_data.rotation.apply(tex_coord_mask);
 
 		let mut position = face_data.flags.face.model_offset_matrix() * offset_mask;
 		position += face_data.flags.face.model_axis();
@@ -163,12 +167,9 @@ impl Builder {
 }
 
 pub struct Model {
-	is_opaque: bool,
 	vertices: Vec<Vertex>,
 	indices: Vec<u32>,
-	#[allow(dead_code)]
 	atlas: Arc<Atlas>,
-	#[allow(dead_code)]
 	sampler: Arc<Sampler>,
 	descriptor_set: Weak<descriptor::Set>,
 }
@@ -186,8 +187,10 @@ impl Model {
 		self.descriptor_set.upgrade().unwrap()
 	}
 
-	pub fn is_opaque(&self) -> bool {
-		self.is_opaque
+	/// Returns the atlas, sampler, and descriptor set the model was built with,
+	/// so a replacement model can be built against the same atlas.
+	pub fn atlas(&self) -> (&Arc<Atlas>, &Arc<Sampler>, &Weak<descriptor::Set>) {
+		(&self.atlas, &self.sampler, &self.descriptor_set)
 	}
 }
 
@@ -203,3 +206,96 @@ impl ModelTrait for Model {
 		&self.indices
 	}
 }
+
+#[cfg(test)]
+mod texture_rotation {
+	use super::*;
+	use crate::{
+		block::TextureRotation,
+		graphics::voxel::{atlas::AtlasTexCoord, Face},
+	};
+	use engine::math::nalgebra::Vector3;
+
+	fn face(rotation: TextureRotation) -> model::FaceData {
+		model::FaceData {
+			main_tex: AtlasTexCoord {
+				offset: Point2::new(0.5, 0.25),
+				size: Vector2::new(0.25, 0.25),
+				frame_count: 1,
+			},
+			biome_color_tex: Some(AtlasTexCoord {
+				offset: Point2::new(0.0, 0.0),
+				size: Vector2::new(0.5, 0.5),
+				frame_count: 1,
+			}),
+			flags: model::Flags {
+				face: Face::Front,
+				biome_color_enabled: true,
+				biome_color_masked: true,
+			},
+			rotation,
+			frame_duration: None,
+		}
+	}
+
+	fn tex_coords(rotation: TextureRotation) -> Vec<Vector4<f32>> {
+		let mut builder = Builder::default();
+		builder.push_face(&face(rotation));
+		builder
+			.vertices
+			.iter()
+			.map(|vertex| Vector4::from(*vertex.tex_coord))
+			.collect()
+	}
+
+	#[test]
+	fn animated_face_flags() {
+		let mut animated = face(TextureRotation::None);
+		animated.main_tex.frame_count = 3;
+		animated.frame_duration = Some(std::time::Duration::from_millis(250));
+		let mut builder = Builder::default();
+		builder.push_face(&animated);
+		builder.push_face(&face(TextureRotation::None));
+		let flags = builder
+			.vertices
+			.iter()
+			.map(|vertex| Vector4::from(*vertex.model_flags))
+			.collect::<Vec<_>>();
+		// Every vertex of the animated face has the frame count, frame duration, and distance between frames.
+		for flags in flags[0..4].iter() {
+			assert_eq!(flags.yzw(), Vector3::new(3.0, 0.25, 0.25));
+		}
+		// The static face has no animation.
+		for flags in flags[4..8].iter() {
+			assert_eq!(flags.yzw(), Vector3::new(0.0, 0.0, 0.0));
+		}
+	}
+
+	#[test]
+	fn unrotated_face() {
+		// Vertices are pushed top-left, top-right, bottom-right, bottom-left.
+		assert_eq!(
+			tex_coords(TextureRotation::None),
+			vec![
+				Vector4::new(0.5, 0.25, 0.0, 0.0),
+				Vector4::new(0.75, 0.25, 0.5, 0.0),
+				Vector4::new(0.75, 0.5, 0.5, 0.5),
+				Vector4::new(0.5, 0.5, 0.0, 0.5),
+			]
+		);
+	}
+
+	#[test]
+	fn rotated_90_degrees() {
+		// The bottom-left of the texture (and its mask) is drawn at the top-left of the face.
+		assert_eq!(
+			tex_coords(TextureRotation::Clockwise90),
+			vec![
+				Vector4::new(0.5, 0.5, 0.0, 0.5),
+				Vector4::new(0.5, 0.25, 0.0, 0.0),
+				Vector4::new(0.75, 0.25, 0.5, 0.0),
+				Vector4::new(0.75, 0.5, 0.5, 0.5),
+			]
+		);
+	}
+}
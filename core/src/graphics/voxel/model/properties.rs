@@ -0,0 +1,73 @@
+use crate::block::{self, Block};
+use std::collections::HashMap;
+
+/// Properties of a block's model which do not affect its geometry,
+/// and can therefore change without rebuilding the model's vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Properties {
+	/// True if the model is fully opaque/has no chance of seeing other blocks through it.
+	pub is_opaque: bool,
+	/// The level of block light emitted by the block.
+	pub light_emission: u8,
+}
+
+impl From<&Block> for Properties {
+	fn from(block: &Block) -> Self {
+		Self {
+			is_opaque: block.is_opaque(),
+			light_emission: block.light_emission(),
+		}
+	}
+}
+
+/// The [`Properties`] of every block model in a [`Cache`](super::Cache).
+#[derive(Default)]
+pub struct PropertyMap(HashMap<block::LookupId, Properties>);
+
+impl PropertyMap {
+	pub fn get(&self, id: &block::LookupId) -> Option<&Properties> {
+		self.0.get(&id)
+	}
+
+	pub fn is_opaque(&self, id: &block::LookupId) -> Option<bool> {
+		self.get(&id).map(|properties| properties.is_opaque)
+	}
+
+	pub fn light_emission(&self, id: &block::LookupId) -> Option<u8> {
+		self.get(&id).map(|properties| properties.light_emission)
+	}
+
+	/// Sets the properties of a block model, returning true if they are different than the previous properties.
+	pub fn update(&mut self, id: block::LookupId, properties: Properties) -> bool {
+		self.0.insert(id, properties) != Some(properties)
+	}
+}
+
+#[cfg(test)]
+mod property_map {
+	use super::*;
+
+	fn block_asset(is_opaque: bool) -> Block {
+		serde_json::from_value(serde_json::json!({
+			"asset_type": "block",
+			"textures": [],
+			"is_opaque": is_opaque,
+		}))
+		.unwrap()
+	}
+
+	#[test]
+	fn opacity_change_updates_model() {
+		let mut properties = PropertyMap::default();
+		assert!(properties.update(3, Properties::from(&block_asset(true))));
+		assert_eq!(properties.is_opaque(&3), Some(true));
+
+		// The block asset is modified to no longer be opaque
+		assert!(properties.update(3, Properties::from(&block_asset(false))));
+		assert_eq!(properties.is_opaque(&3), Some(false));
+
+		// Reloading an unchanged asset is not a change
+		assert!(!properties.update(3, Properties::from(&block_asset(false))));
+		assert_eq!(properties.is_opaque(&4), None);
+	}
+}
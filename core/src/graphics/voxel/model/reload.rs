@@ -0,0 +1,54 @@
+use crate::{
+	block::{self, Block},
+	graphics::voxel::model::{self, block_texture_ids, build_block_model, load_textures},
+};
+use anyhow::Result;
+use engine::{asset, graphics::Chain};
+
+static LOG: &'static str = "model::reload";
+
+/// Rebuilds the model of a single block in the [`active model cache`](model::Cache::active)
+/// after its asset has changed (e.g. when the asset is edited while the game is running).
+///
+/// The block's textures are restitched into the atlas its model already uses,
+/// and the model is rebuilt and replaced in the cache.
+/// Both the atlas and the cache's vertex buffer are written in place,
+/// so bound descriptor sets and buffers stay valid.
+/// Changes which require more space (more atlas cells than are free, or more faces than the previous model)
+/// are not supported by a single-block reload, and require restarting to reload all models.
+pub fn reload_block(asset_id: &asset::Id, block: &Block, chain: &Chain) -> Result<()> {
+	let cache = model::Cache::active().ok_or(ReloadError::NoActiveCache)?;
+	let lookup_id = block::Lookup::lookup_value(&asset_id)
+		.ok_or_else(|| ReloadError::UnknownBlock(asset_id.clone()))?;
+	let (existing, _, _) = cache
+		.get(&lookup_id)
+		.ok_or_else(|| ReloadError::UnknownBlock(asset_id.clone()))?;
+	let (atlas, sampler, descriptor_set) = existing.atlas();
+
+	let textures = load_textures(block_texture_ids(&block));
+	let texture_map = textures.iter().collect();
+	atlas.restitch(&texture_map, chain, chain.signal_sender())?;
+
+	let model = build_block_model(&asset_id, &block, &atlas, &sampler, &descriptor_set);
+	let properties = model::Properties::from(block);
+	if cache.replace(lookup_id, model, properties, chain, chain.signal_sender())? {
+		// Voxels which have already been sent to the instance buffer were culled with the previous properties.
+		// They will use the new properties when their chunk next changes.
+		log::info!(
+			target: LOG,
+			"Properties of block {} changed to {:?}",
+			asset_id,
+			properties
+		);
+	}
+	log::info!(target: LOG, "Reloaded model for block {}", asset_id);
+	Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReloadError {
+	#[error("block models have not been loaded")]
+	NoActiveCache,
+	#[error("block {0} does not have a model")]
+	UnknownBlock(asset::Id),
+}
@@ -1,7 +1,7 @@
 use crate::{
 	app::state::{self, ArcLockMachine},
 	block,
-	client::world::chunk,
+	client::{self, world::chunk},
 	common::network::Storage,
 	graphics::voxel::{
 		camera,
@@ -18,12 +18,14 @@ use engine::{
 		chain::{operation::RequiresRecording, Operation},
 		command, flags,
 		procedure::Phase,
-		resource::ColorBuffer,
 		Chain, Drawable, Uniform,
 	},
 	Application,
 };
-use std::sync::{Arc, RwLock, Weak};
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Instant,
+};
 
 static ID: &'static str = "render-voxel";
 
@@ -34,6 +36,12 @@ pub struct RenderVoxel {
 	camera_uniform: Uniform,
 	camera: Arc<RwLock<camera::Camera>>,
 	model_cache: Arc<model::Cache>,
+	/// The client's network storage, which has the radius of the client's relevance.
+	client: Weak<RwLock<client::network::Storage>>,
+	/// The relevance radius the camera's fog was last moved to.
+	fog_radius: Option<u64>,
+	/// When rendering started, which the time sent to shaders (for animated textures) is relative to.
+	start_time: Instant,
 }
 
 impl RenderVoxel {
@@ -73,13 +81,13 @@ impl RenderVoxel {
 				let phase = callback_phase.upgrade().unwrap();
 				let arc_camera = callback_camera.upgrade().unwrap();
 
-				let chunk_receiver = match callback_storage.upgrade() {
+				let (client, chunk_receiver) = match callback_storage.upgrade() {
 					Some(arc_storage) => {
 						let storage = arc_storage.read().unwrap();
 						match storage.client() {
 							Some(arc_client) => {
 								let client = arc_client.read().unwrap();
-								client.chunk_receiver().clone()
+								(Arc::downgrade(&arc_client), client.chunk_receiver().clone())
 							}
 							None => {
 								log::error!(target: ID, "Failed to find client storage");
@@ -99,6 +107,7 @@ impl RenderVoxel {
 						&phase,
 						arc_camera,
 						callback_model_cache.clone(),
+						client,
 						chunk_receiver,
 					) {
 						Ok(arclocked) => Some(arclocked),
@@ -116,11 +125,18 @@ impl RenderVoxel {
 		phase: &Arc<Phase>,
 		camera: Arc<RwLock<camera::Camera>>,
 		model_cache: Arc<model::Cache>,
+		client: Weak<RwLock<client::network::Storage>>,
 		chunk_receiver: chunk::OperationReceiver,
 	) -> Result<ArcLockRenderVoxel> {
 		log::info!(target: ID, "Initializing");
-		let render_chunks =
-			Self::new(&chain.read().unwrap(), camera, model_cache, chunk_receiver)?.arclocked();
+		let render_chunks = Self::new(
+			&chain.read().unwrap(),
+			camera,
+			model_cache,
+			client,
+			chunk_receiver,
+		)?
+		.arclocked();
 
 		log::trace!(target: ID, "Adding to render chain");
 		let mut chain = chain.write().unwrap();
@@ -137,6 +153,7 @@ impl RenderVoxel {
 		chain: &Chain,
 		camera: Arc<RwLock<camera::Camera>>,
 		model_cache: Arc<model::Cache>,
+		client: Weak<RwLock<client::network::Storage>>,
 		chunk_receiver: chunk::OperationReceiver,
 	) -> Result<Self> {
 		log::trace!(target: ID, "Creating renderer");
@@ -150,6 +167,7 @@ impl RenderVoxel {
 			&chain.allocator()?,
 			Arc::downgrade(&model_cache),
 			chunk_receiver,
+			chain.view_count(),
 		)?;
 
 		let camera_uniform = Uniform::new::<camera::UniformData, &str>(
@@ -166,9 +184,47 @@ impl RenderVoxel {
 			camera_uniform,
 			camera,
 			model_cache,
+			client,
+			fog_radius: None,
+			start_time: Instant::now(),
 		})
 	}
 
+	/// Moves the camera's fog to the edge of the client's relevance when the relevance radius changes.
+	fn update_fog(&mut self) {
+		let radius = match self.client.upgrade() {
+			Some(arc_client) => arc_client.read().unwrap().relevance_radius(),
+			None => None,
+		};
+		if radius == self.fog_radius {
+			return;
+		}
+		self.fog_radius = radius;
+		if let Some(radius) = radius {
+			self.camera
+				.write()
+				.unwrap()
+				.fog
+				.set_relevance_radius(radius);
+		}
+	}
+
+	/// Colors the sky (which distant voxels fade into) and dims voxels based on the client's estimate of the time of day.
+	/// Until the time has been replicated, it is always noon.
+	fn update_sky(&mut self) {
+		use crate::common::world::clock::Sky;
+		let sky = match self.client.upgrade() {
+			Some(arc_client) => match arc_client.read().unwrap().clock() {
+				Some(clock) => Sky::at(clock.fraction_of_day(Instant::now())),
+				None => Sky::default(),
+			},
+			None => Sky::default(),
+		};
+		let mut camera = self.camera.write().unwrap();
+		camera.fog.color = sky.color;
+		camera.ambient = sky.ambient;
+	}
+
 	fn arclocked(self) -> ArcLockRenderVoxel {
 		Arc::new(RwLock::new(self))
 	}
@@ -191,11 +247,7 @@ impl Operation for RenderVoxel {
 	fn construct(&mut self, chain: &Chain, subpass_index: usize) -> anyhow::Result<()> {
 		use graphics::pipeline::{state::*, Pipeline};
 
-		let sample_count = {
-			let arc = chain.resources().get::<ColorBuffer>()?;
-			let color_buffer = arc.read().unwrap();
-			color_buffer.sample_count()
-		};
+		let sample_count = crate::graphics::color_sample_count(chain);
 
 		self.drawable.create_pipeline(
 			&chain.logical()?,
@@ -246,11 +298,14 @@ impl Operation for RenderVoxel {
 		chain: &Chain,
 		frame_image: usize,
 	) -> anyhow::Result<RequiresRecording> {
+		self.update_fog();
+		self.update_sky();
 		let data = self
 			.camera
 			.read()
 			.unwrap()
-			.as_uniform_data(&chain.resolution());
+			.as_uniform_data(&chain.resolution())
+			.with_time(self.start_time.elapsed().as_secs_f32());
 		self.camera_uniform.write_data(frame_image, &data)?;
 
 		// TODO: There should probably be separate instance buffers for each frame (ring of 3),
@@ -273,7 +328,7 @@ impl Operation for RenderVoxel {
 
 			let submitted_instances = self.instance_buffer.submitted();
 			buffer.bind_vertex_buffers(0, vec![&self.model_cache.vertex_buffer], vec![0]);
-			buffer.bind_vertex_buffers(1, vec![&submitted_instances.buffer], vec![0]);
+			buffer.bind_vertex_buffers(1, vec![submitted_instances.buffer()], vec![0]);
 			buffer.bind_index_buffer(&self.model_cache.index_buffer, 0);
 
 			for instances in submitted_instances.categories.iter() {
@@ -308,10 +363,10 @@ impl Operation for RenderVoxel {
 				// Draw based on the model
 				buffer.draw(
 					model.index_count(),
-					*index_start,
+					index_start,
 					instances.count(),
 					instances.start(),
-					*vertex_offset,
+					vertex_offset,
 				);
 
 				buffer.end_label();
@@ -1,8 +1,14 @@
 pub use engine::input::{self, *};
 
+pub mod bindings;
+pub use bindings::Bindings;
+
 pub static ACTION_TOGGLE_DEBUG_CMDS: &'static str = "ToggleDebugCommands";
 pub static ACTION_TOGGLE_CHUNK_BOUNDARIES: &'static str = "ToggleChunkBoundaries";
+pub static ACTION_TOGGLE_ORIENTATION_GADGET: &'static str = "ToggleOrientationGadget";
 pub static ACTION_SWAP_CAMERA_POV: &'static str = "SwapCameraPOV";
+pub static ACTION_BREAK_BLOCK: &'static str = "BreakBlock";
+pub static ACTION_PLACE_BLOCK: &'static str = "PlaceBlock";
 
 pub static AXIS_STRAFE: &'static str = "Strafe";
 pub static AXIS_MOVE: &'static str = "Move";
@@ -10,101 +16,228 @@ pub static AXIS_FLY: &'static str = "Fly";
 pub static AXIS_LOOK_HORIZONTAL: &'static str = "LookHorizontal";
 pub static AXIS_LOOK_VERTICAL: &'static str = "LookVertical";
 
+static ACTION_SETS: [&'static str; 2] = ["ApplicationActions", "CharacterControls"];
+
+/// Creates the local input user, binding the remappable actions to the keys in the [`bindings file`](Bindings::default_path).
 pub fn init() -> ArcLockUser {
+	let bindings = match Bindings::load(&Bindings::default_path()) {
+		Ok(bindings) => bindings,
+		Err(err) => {
+			log::error!(target: "input", "Failed to load bindings, using defaults: {:?}", err);
+			Bindings::default()
+		}
+	};
+	input::set_config(config(&bindings));
+
+	let arc_user = engine::input::create_user("Local");
+	if let Ok(mut user) = arc_user.write() {
+		for action_set in ACTION_SETS.iter() {
+			user.enable_action_set(Some(*action_set));
+		}
+	}
+
+	arc_user
+}
+
+/// Remaps the keys of an input [`User`].
+///
+/// The new binding is used immediately; the states of actions (which systems hold onto) are kept,
+/// and only the keys which drive them change.
+pub trait Rebind {
+	/// Binds a remappable action to a different key, saving the change to the [`bindings file`](Bindings::default_path).
+	fn rebind(&mut self, action: &str, key: &str) -> anyhow::Result<()> {
+		self.rebind_in(&Bindings::default_path(), action, key)
+	}
+
+	/// Binds a remappable action to a different key, saving the change to the bindings file at `path`.
+	fn rebind_in(&mut self, path: &std::path::Path, action: &str, key: &str) -> anyhow::Result<()>;
+}
+
+impl Rebind for User {
+	fn rebind_in(&mut self, path: &std::path::Path, action: &str, key: &str) -> anyhow::Result<()> {
+		let mut bindings = Bindings::load(path)?;
+		bindings.rebind(action, key)?;
+		bindings.save(path)?;
+		input::set_config(config(&bindings));
+		// Enabling the action sets again binds their actions to the keys in the new config.
+		for action_set in ACTION_SETS.iter() {
+			self.enable_action_set(Some(*action_set));
+		}
+		Ok(())
+	}
+}
+
+/// The remappable actions of each action set (in the same order as [`ACTION_SETS`]),
+/// and the key each is bound to in `bindings`.
+fn button_bindings(bindings: &Bindings) -> [Vec<(&'static str, prelude::Source)>; 2] {
+	let with_keys = |actions: &[&'static str]| {
+		actions
+			.iter()
+			.map(|action| (*action, bindings.source_for(action).unwrap()))
+			.collect::<Vec<_>>()
+	};
+	[
+		with_keys(&[
+			ACTION_TOGGLE_DEBUG_CMDS,
+			ACTION_TOGGLE_CHUNK_BOUNDARIES,
+			ACTION_TOGGLE_ORIENTATION_GADGET,
+		]),
+		with_keys(&[
+			ACTION_SWAP_CAMERA_POV,
+			ACTION_BREAK_BLOCK,
+			ACTION_PLACE_BLOCK,
+		]),
+	]
+}
+
+/// The input config with the remappable actions bound to the keys in `bindings`.
+fn config(bindings: &Bindings) -> Config {
 	use prelude::{Source::Keyboard, *};
-	input::set_config(
-		Config::default()
-			.add_action(ACTION_TOGGLE_DEBUG_CMDS, Kind::Button)
-			.add_action(ACTION_TOGGLE_CHUNK_BOUNDARIES, Kind::Button)
-			.add_action(ACTION_SWAP_CAMERA_POV, Kind::Button)
-			.add_action(AXIS_STRAFE, Kind::Axis)
-			.add_action(AXIS_MOVE, Kind::Axis)
-			.add_action(AXIS_FLY, Kind::Axis)
-			.add_action(AXIS_LOOK_HORIZONTAL, Kind::Axis)
-			.add_action(AXIS_LOOK_VERTICAL, Kind::Axis)
-			// The only layout is the default layout right now
-			.add_layout(LayoutId::default())
-			.add_action_set(
-				Some("ApplicationActions"),
-				ActionSet::default().with(
-					LayoutId::default(),
-					ActionMap::default()
-						.bind(ACTION_TOGGLE_DEBUG_CMDS, Keyboard(Backslash))
-						.bind(ACTION_TOGGLE_CHUNK_BOUNDARIES, Keyboard(F3)),
-				),
-			)
-			.add_action_set(
-				Some("CharacterControls"),
-				ActionSet::default().with(
-					LayoutId::default(),
-					ActionMap::default()
-						.bind(ACTION_SWAP_CAMERA_POV, Keyboard(F5))
-						.bind(
-							AXIS_MOVE,
-							[(
-								device::Kind::Keyboard,
-								((Keyboard(W) + Multiplier(1.0))
-									+ (Keyboard(S) + Multiplier(-1.0)))
+	let bind_buttons = |buttons: Vec<(&'static str, Source)>| {
+		buttons
+			.into_iter()
+			.fold(ActionMap::default(), |map, (action, source)| {
+				map.bind(action, source)
+			})
+	};
+	let [application_buttons, character_buttons] = button_bindings(bindings);
+	Config::default()
+		.add_action(ACTION_TOGGLE_DEBUG_CMDS, Kind::Button)
+		.add_action(ACTION_TOGGLE_CHUNK_BOUNDARIES, Kind::Button)
+		.add_action(ACTION_TOGGLE_ORIENTATION_GADGET, Kind::Button)
+		.add_action(ACTION_SWAP_CAMERA_POV, Kind::Button)
+		.add_action(ACTION_BREAK_BLOCK, Kind::Button)
+		.add_action(ACTION_PLACE_BLOCK, Kind::Button)
+		.add_action(AXIS_STRAFE, Kind::Axis)
+		.add_action(AXIS_MOVE, Kind::Axis)
+		.add_action(AXIS_FLY, Kind::Axis)
+		.add_action(AXIS_LOOK_HORIZONTAL, Kind::Axis)
+		.add_action(AXIS_LOOK_VERTICAL, Kind::Axis)
+		// The only layout is the default layout right now
+		.add_layout(LayoutId::default())
+		.add_action_set(
+			Some(ACTION_SETS[0]),
+			ActionSet::default().with(LayoutId::default(), bind_buttons(application_buttons)),
+		)
+		.add_action_set(
+			Some(ACTION_SETS[1]),
+			ActionSet::default().with(
+				LayoutId::default(),
+				bind_buttons(character_buttons)
+					.bind(
+						AXIS_MOVE,
+						[(
+							device::Kind::Keyboard,
+							((Keyboard(W) + Multiplier(1.0)) + (Keyboard(S) + Multiplier(-1.0)))
 								.with_behavior(Average)
 								.with_behavior(Multiplier(2.0)),
-							)],
-						)
-						.bind(
-							AXIS_STRAFE,
-							((Keyboard(A) + Multiplier(-1.0)) + (Keyboard(D) + Multiplier(1.0)))
+						)],
+					)
+					.bind(
+						AXIS_STRAFE,
+						((Keyboard(A) + Multiplier(-1.0)) + (Keyboard(D) + Multiplier(1.0)))
+							.with_behavior(Average)
+							.with_behavior(Multiplier(2.0)),
+					)
+					.bind(
+						AXIS_FLY,
+						((Keyboard(E) + Multiplier(1.0)) + (Keyboard(Q) + Multiplier(-1.0)))
+							.with_behavior(Average)
+							.with_behavior(Multiplier(2.0)),
+					)
+					.bind(
+						AXIS_LOOK_HORIZONTAL,
+						[
+							(
+								device::Kind::Mouse,
+								Source::Mouse(Mouse::Move(MouseX))
+									+ ScreenPositionDelta + Multiplier(-3.0),
+							),
+							(
+								device::Kind::Keyboard,
+								((Keyboard(Numpad4) + Multiplier(1.0))
+									+ (Keyboard(Numpad6) + Multiplier(-1.0)))
 								.with_behavior(Average)
-								.with_behavior(Multiplier(2.0)),
-						)
-						.bind(
-							AXIS_FLY,
-							((Keyboard(E) + Multiplier(1.0)) + (Keyboard(Q) + Multiplier(-1.0)))
+								.with_behavior(Multiplier(2.0))
+								.with_behavior(Multiplier(0.05)),
+							),
+						],
+					)
+					.bind(
+						AXIS_LOOK_VERTICAL,
+						[
+							(
+								device::Kind::Mouse,
+								Source::Mouse(Mouse::Move(MouseY)) + ScreenPositionDelta,
+							),
+							(
+								device::Kind::Keyboard,
+								((Keyboard(Numpad5) + Multiplier(1.0))
+									+ (Keyboard(Numpad8) + Multiplier(-1.0)))
 								.with_behavior(Average)
-								.with_behavior(Multiplier(2.0)),
-						)
-						.bind(
-							AXIS_LOOK_HORIZONTAL,
-							[
-								(
-									device::Kind::Mouse,
-									Source::Mouse(Mouse::Move(MouseX))
-										+ ScreenPositionDelta + Multiplier(-3.0),
-								),
-								(
-									device::Kind::Keyboard,
-									((Keyboard(Numpad4) + Multiplier(1.0))
-										+ (Keyboard(Numpad6) + Multiplier(-1.0)))
-									.with_behavior(Average)
-									.with_behavior(Multiplier(2.0))
-									.with_behavior(Multiplier(0.05)),
-								),
-							],
-						)
-						.bind(
-							AXIS_LOOK_VERTICAL,
-							[
-								(
-									device::Kind::Mouse,
-									Source::Mouse(Mouse::Move(MouseY)) + ScreenPositionDelta,
-								),
-								(
-									device::Kind::Keyboard,
-									((Keyboard(Numpad5) + Multiplier(1.0))
-										+ (Keyboard(Numpad8) + Multiplier(-1.0)))
-									.with_behavior(Average)
-									.with_behavior(Multiplier(2.0))
-									.with_behavior(Multiplier(0.05)),
-								),
-							],
-						),
-				),
+								.with_behavior(Multiplier(2.0))
+								.with_behavior(Multiplier(0.05)),
+							),
+						],
+					),
 			),
-	);
+		)
+}
 
-	let arc_user = engine::input::create_user("Local");
-	if let Ok(mut user) = arc_user.write() {
-		user.enable_action_set(Some("ApplicationActions"));
-		user.enable_action_set(Some("CharacterControls"));
+#[cfg(test)]
+mod rebind {
+	use super::*;
+	use prelude::{Source::Keyboard, *};
+
+	/// Returns the actions which the key drives in the config built from `bindings`.
+	fn actions_driven_by(
+		bindings: &Bindings,
+		is_key: impl Fn(&Source) -> bool,
+	) -> Vec<&'static str> {
+		button_bindings(bindings)
+			.iter()
+			.flatten()
+			.filter(|(_, source)| is_key(source))
+			.map(|(action, _)| *action)
+			.collect()
 	}
 
-	arc_user
+	#[test]
+	fn remapped_key_drives_action() {
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-bindings-{}.json",
+			uuid::Uuid::new_v4()
+		));
+		std::fs::write(
+			&path,
+			format!(r#"{{ "{}": "F6" }}"#, ACTION_TOGGLE_CHUNK_BOUNDARIES),
+		)
+		.unwrap();
+		let mut bindings = Bindings::load(&path).unwrap();
+		assert_eq!(
+			actions_driven_by(&bindings, |source| matches!(source, Keyboard(F6))),
+			vec![ACTION_TOGGLE_CHUNK_BOUNDARIES]
+		);
+		// The default key no longer drives the action.
+		assert!(actions_driven_by(&bindings, |source| matches!(source, Keyboard(F3))).is_empty());
+
+		// Conflicting keys are refused, leaving the binding as it was.
+		assert!(bindings
+			.rebind(ACTION_TOGGLE_CHUNK_BOUNDARIES, "F5")
+			.is_err());
+		assert_eq!(
+			actions_driven_by(&bindings, |source| matches!(source, Keyboard(F5))),
+			vec![ACTION_SWAP_CAMERA_POV]
+		);
+		bindings
+			.rebind(ACTION_TOGGLE_CHUNK_BOUNDARIES, "F7")
+			.unwrap();
+		assert_eq!(
+			actions_driven_by(&bindings, |source| matches!(source, Keyboard(F7))),
+			vec![ACTION_TOGGLE_CHUNK_BOUNDARIES]
+		);
+		assert!(actions_driven_by(&bindings, |source| matches!(source, Keyboard(F6))).is_empty());
+		let _ = std::fs::remove_file(&path);
+	}
 }
@@ -0,0 +1,354 @@
+use super::{
+	ACTION_BREAK_BLOCK, ACTION_PLACE_BLOCK, ACTION_SWAP_CAMERA_POV, ACTION_TOGGLE_CHUNK_BOUNDARIES,
+	ACTION_TOGGLE_DEBUG_CMDS, ACTION_TOGGLE_ORIENTATION_GADGET,
+};
+use anyhow::Result;
+use engine::input::prelude::Source;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	path::{Path, PathBuf},
+};
+
+static LOG: &'static str = "input";
+
+/// Returns the input source for the key with the provided name (as written in the bindings file),
+/// if actions can be bound to it.
+pub fn key_source(name: &str) -> Option<Source> {
+	use engine::input::prelude::{Source::Keyboard, *};
+	Some(match name {
+		"A" => Keyboard(A),
+		"B" => Keyboard(B),
+		"C" => Keyboard(C),
+		"D" => Keyboard(D),
+		"E" => Keyboard(E),
+		"F" => Keyboard(F),
+		"G" => Keyboard(G),
+		"H" => Keyboard(H),
+		"I" => Keyboard(I),
+		"J" => Keyboard(J),
+		"K" => Keyboard(K),
+		"L" => Keyboard(L),
+		"M" => Keyboard(M),
+		"N" => Keyboard(N),
+		"O" => Keyboard(O),
+		"P" => Keyboard(P),
+		"Q" => Keyboard(Q),
+		"R" => Keyboard(R),
+		"S" => Keyboard(S),
+		"T" => Keyboard(T),
+		"U" => Keyboard(U),
+		"V" => Keyboard(V),
+		"W" => Keyboard(W),
+		"X" => Keyboard(X),
+		"Y" => Keyboard(Y),
+		"Z" => Keyboard(Z),
+		"Key0" => Keyboard(Key0),
+		"Key1" => Keyboard(Key1),
+		"Key2" => Keyboard(Key2),
+		"Key3" => Keyboard(Key3),
+		"Key4" => Keyboard(Key4),
+		"Key5" => Keyboard(Key5),
+		"Key6" => Keyboard(Key6),
+		"Key7" => Keyboard(Key7),
+		"Key8" => Keyboard(Key8),
+		"Key9" => Keyboard(Key9),
+		"F1" => Keyboard(F1),
+		"F2" => Keyboard(F2),
+		"F3" => Keyboard(F3),
+		"F4" => Keyboard(F4),
+		"F5" => Keyboard(F5),
+		"F6" => Keyboard(F6),
+		"F7" => Keyboard(F7),
+		"F8" => Keyboard(F8),
+		"F9" => Keyboard(F9),
+		"F10" => Keyboard(F10),
+		"F11" => Keyboard(F11),
+		"F12" => Keyboard(F12),
+		"Numpad0" => Keyboard(Numpad0),
+		"Numpad1" => Keyboard(Numpad1),
+		"Numpad2" => Keyboard(Numpad2),
+		"Numpad3" => Keyboard(Numpad3),
+		"Numpad4" => Keyboard(Numpad4),
+		"Numpad5" => Keyboard(Numpad5),
+		"Numpad6" => Keyboard(Numpad6),
+		"Numpad7" => Keyboard(Numpad7),
+		"Numpad8" => Keyboard(Numpad8),
+		"Numpad9" => Keyboard(Numpad9),
+		"Backslash" => Keyboard(Backslash),
+		"Grave" => Keyboard(Grave),
+		"Tab" => Keyboard(Tab),
+		"Space" => Keyboard(Space),
+		_ => return None,
+	})
+}
+
+/// The keys bound to each remappable (button) action, by action name.
+/// The movement and camera axes are built from multiple keys and are not remappable.
+///
+/// Loaded from the bindings file when the input is [`initialized`](super::init),
+/// where any action which is not in the file keeps its default key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bindings(BTreeMap<String, String>);
+
+impl Default for Bindings {
+	fn default() -> Self {
+		let mut keys = BTreeMap::new();
+		keys.insert(ACTION_TOGGLE_DEBUG_CMDS.to_owned(), "Backslash".to_owned());
+		keys.insert(ACTION_TOGGLE_CHUNK_BOUNDARIES.to_owned(), "F3".to_owned());
+		keys.insert(ACTION_TOGGLE_ORIENTATION_GADGET.to_owned(), "F4".to_owned());
+		keys.insert(ACTION_SWAP_CAMERA_POV.to_owned(), "F5".to_owned());
+		keys.insert(ACTION_BREAK_BLOCK.to_owned(), "R".to_owned());
+		keys.insert(ACTION_PLACE_BLOCK.to_owned(), "F".to_owned());
+		Self(keys)
+	}
+}
+
+impl Bindings {
+	pub fn default_path() -> PathBuf {
+		let mut path = std::env::current_dir().unwrap().to_owned();
+		path.push("bindings.json");
+		path
+	}
+
+	/// Loads the bindings file, falling back to the default bindings if it does not exist.
+	/// Unknown actions and keys in the file are reported and ignored, as are bindings to a key
+	/// which another action is also bound to (those actions keep their default key).
+	pub fn load(path: &Path) -> Result<Self> {
+		let mut bindings = Self::default();
+		if !path.exists() {
+			return Ok(bindings);
+		}
+		let raw = std::fs::read_to_string(&path)?;
+		let saved: BTreeMap<String, String> = serde_json::from_str(&raw)?;
+		let mut from_file = BTreeSet::new();
+		for (action, key) in saved.into_iter() {
+			if !bindings.0.contains_key(&action) {
+				log::warn!(target: LOG, "Ignoring binding for unknown action {}", action);
+				continue;
+			}
+			if key_source(&key).is_none() {
+				log::warn!(
+					target: LOG,
+					"Ignoring binding of {} to unknown key {}",
+					action,
+					key
+				);
+				continue;
+			}
+			bindings.0.insert(action.clone(), key);
+			from_file.insert(action);
+		}
+		// The default bindings have no conflicts, so resetting the conflicting actions which were loaded
+		// from the file to their default keys eventually removes every conflict.
+		let defaults = Self::default();
+		loop {
+			let conflicts = bindings.conflicts();
+			if conflicts.is_empty() {
+				break;
+			}
+			for (key, actions) in conflicts.into_iter() {
+				for action in actions.iter() {
+					if !from_file.remove(action) {
+						continue;
+					}
+					log::warn!(
+						target: LOG,
+						"Ignoring binding of {} to key {}, which is bound to multiple actions: {}",
+						action,
+						key,
+						actions.join(", ")
+					);
+					bindings
+						.0
+						.insert(action.clone(), defaults.0[action].clone());
+				}
+			}
+		}
+		Ok(bindings)
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let json = serde_json::to_string_pretty(&self.0)?;
+		std::fs::write(&path, json)?;
+		Ok(())
+	}
+
+	/// Returns the name of the key bound to an action.
+	pub fn key_for(&self, action: &str) -> Option<&String> {
+		self.0.get(action)
+	}
+
+	/// Returns the input source of the key bound to an action.
+	/// Every bound key is [`known`](key_source), so this is only None if the action is not remappable.
+	pub fn source_for(&self, action: &str) -> Option<Source> {
+		self.key_for(action).map(|key| key_source(&key)).flatten()
+	}
+
+	/// Returns the actions bound to a key.
+	pub fn actions_for(&self, key: &str) -> Vec<&String> {
+		self.0
+			.iter()
+			.filter(|(_, bound_key)| *bound_key == key)
+			.map(|(action, _)| action)
+			.collect()
+	}
+
+	/// Returns each key which is bound to more than one action, and the actions bound to it.
+	pub fn conflicts(&self) -> Vec<(String, Vec<String>)> {
+		let mut actions_by_key: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+		for (action, key) in self.0.iter() {
+			actions_by_key.entry(key).or_default().push(action.clone());
+		}
+		actions_by_key
+			.into_iter()
+			.filter(|(_, actions)| actions.len() > 1)
+			.map(|(key, actions)| (key.clone(), actions))
+			.collect()
+	}
+
+	/// Binds an action to a different key.
+	/// Fails without changing the bindings if the action or key is unknown,
+	/// or if the key is already bound to another action.
+	pub fn rebind(&mut self, action: &str, key: &str) -> Result<(), Error> {
+		if !self.0.contains_key(action) {
+			return Err(Error::UnknownAction(action.to_owned()));
+		}
+		if key_source(key).is_none() {
+			return Err(Error::UnknownKey(key.to_owned()));
+		}
+		if let Some(other) = self
+			.actions_for(key)
+			.into_iter()
+			.find(|other| *other != action)
+		{
+			return Err(Error::KeyAlreadyBound(key.to_owned(), other.clone()));
+		}
+		self.0.insert(action.to_owned(), key.to_owned());
+		Ok(())
+	}
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("no remappable action named {0}")]
+	UnknownAction(String),
+	#[error("no key named {0}")]
+	UnknownKey(String),
+	#[error("key {0} is already bound to {1}")]
+	KeyAlreadyBound(String, String),
+}
+
+#[cfg(test)]
+mod bindings {
+	use super::*;
+
+	fn create_path() -> PathBuf {
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-bindings-{}.json",
+			uuid::Uuid::new_v4()
+		));
+		path
+	}
+
+	#[test]
+	fn missing_file_is_default() {
+		let bindings = Bindings::load(&create_path()).unwrap();
+		assert_eq!(bindings, Bindings::default());
+		assert!(bindings.conflicts().is_empty());
+	}
+
+	#[test]
+	fn load_remapped_key() {
+		let path = create_path();
+		std::fs::write(
+			&path,
+			format!(r#"{{ "{}": "F6" }}"#, ACTION_TOGGLE_CHUNK_BOUNDARIES),
+		)
+		.unwrap();
+		let bindings = Bindings::load(&path).unwrap();
+		assert_eq!(
+			bindings.key_for(ACTION_TOGGLE_CHUNK_BOUNDARIES),
+			Some(&"F6".to_owned())
+		);
+		assert_eq!(
+			bindings.actions_for("F6"),
+			vec![&ACTION_TOGGLE_CHUNK_BOUNDARIES.to_owned()]
+		);
+		assert!(bindings.actions_for("F3").is_empty());
+		// Actions which were not in the file keep their default key.
+		assert_eq!(
+			bindings.key_for(ACTION_SWAP_CAMERA_POV),
+			Some(&"F5".to_owned())
+		);
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn conflicts_are_ignored() {
+		let path = create_path();
+		std::fs::write(
+			&path,
+			format!(r#"{{ "{}": "F5" }}"#, ACTION_TOGGLE_CHUNK_BOUNDARIES),
+		)
+		.unwrap();
+		let bindings = Bindings::load(&path).unwrap();
+		assert!(bindings.conflicts().is_empty());
+		assert_eq!(
+			bindings.key_for(ACTION_TOGGLE_CHUNK_BOUNDARIES),
+			Some(&"F3".to_owned())
+		);
+		assert_eq!(
+			bindings.key_for(ACTION_SWAP_CAMERA_POV),
+			Some(&"F5".to_owned())
+		);
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn swapped_keys_are_not_conflicts() {
+		let path = create_path();
+		std::fs::write(
+			&path,
+			format!(
+				r#"{{ "{}": "F5", "{}": "F3" }}"#,
+				ACTION_TOGGLE_CHUNK_BOUNDARIES, ACTION_SWAP_CAMERA_POV
+			),
+		)
+		.unwrap();
+		let bindings = Bindings::load(&path).unwrap();
+		assert_eq!(
+			bindings.key_for(ACTION_TOGGLE_CHUNK_BOUNDARIES),
+			Some(&"F5".to_owned())
+		);
+		assert_eq!(
+			bindings.key_for(ACTION_SWAP_CAMERA_POV),
+			Some(&"F3".to_owned())
+		);
+		let _ = std::fs::remove_file(&path);
+	}
+
+	#[test]
+	fn rebind_persists() {
+		let path = create_path();
+		let mut bindings = Bindings::default();
+		assert_eq!(
+			bindings.rebind(ACTION_TOGGLE_CHUNK_BOUNDARIES, "F4"),
+			Err(Error::KeyAlreadyBound(
+				"F4".to_owned(),
+				ACTION_TOGGLE_ORIENTATION_GADGET.to_owned()
+			))
+		);
+		assert_eq!(
+			bindings.rebind(ACTION_TOGGLE_CHUNK_BOUNDARIES, "NotAKey"),
+			Err(Error::UnknownKey("NotAKey".to_owned()))
+		);
+		bindings
+			.rebind(ACTION_TOGGLE_CHUNK_BOUNDARIES, "F7")
+			.unwrap();
+		bindings.save(&path).unwrap();
+		assert_eq!(Bindings::load(&path).unwrap(), bindings);
+		let _ = std::fs::remove_file(&path);
+	}
+}
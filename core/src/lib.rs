@@ -52,6 +52,8 @@ pub mod entity;
 pub mod graphics;
 pub mod input;
 pub mod plugin;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod ui;
 
 pub struct CrystalSphinx();
@@ -78,19 +80,16 @@ pub struct Runtime {
 
 impl Runtime {
 	fn get_network_mode() -> mode::Kind {
-		let is_client = std::env::args().any(|arg| arg == "-client");
-		let is_dedicated_server = std::env::args().any(|arg| arg == "-server");
-		assert_ne!(is_client, is_dedicated_server);
-		match (is_client, is_dedicated_server) {
-			(true, false) => mode::Kind::Client,
-			(false, true) => mode::Kind::Server,
-			_ => unimplemented!(),
-		}
+		mode::Kind::from_args(std::env::args())
+			.expect("the application must be launched with -client, -server, or -listen_server")
 	}
 
 	pub fn new(config: plugin::Config) -> Self {
-		let app_mode = Self::get_network_mode();
+		Self::with_mode(config, Self::get_network_mode())
+	}
 
+	/// Creates the runtime for a network mode, instead of the mode the application was launched with.
+	pub fn with_mode(config: plugin::Config, app_mode: mode::Kind) -> Self {
 		let app_state = app::state::Machine::new(app::state::State::Launching).arclocked();
 		let world = entity::ArcLockEntityWorld::default();
 		entity::add_state_listener(&app_state, Arc::downgrade(&world));
@@ -119,13 +118,46 @@ impl Runtime {
 		}
 	}
 }
+
+#[cfg(test)]
+impl Runtime {
+	pub(crate) fn app_state(&self) -> &Arc<RwLock<app::state::Machine>> {
+		&self.app_state
+	}
+
+	pub(crate) fn world(&self) -> &entity::ArcLockEntityWorld {
+		&self.world
+	}
+
+	pub(crate) fn network_storage(&self) -> &Arc<RwLock<common::network::Storage>> {
+		&self.network_storage
+	}
+}
 impl engine::Runtime for Runtime {
+	/// Creates the log file for this launch, and installs the logger which writes to it
+	/// behind the application's [`log filters`](common::log_filter),
+	/// so the level of each target can be changed while running (e.g. with the `log` command).
 	fn logging_path() -> PathBuf {
 		let logid = std::env::args()
 			.find_map(|arg| arg.strip_prefix("-logid=").map(|s| s.to_owned()))
 			.unwrap();
 		let mut log_path = std::env::current_dir().unwrap().to_path_buf();
 		log_path.push(format!("{}_{}.log", CrystalSphinx::name(), logid));
+
+		static INSTALL_LOGGER: std::sync::Once = std::sync::Once::new();
+		INSTALL_LOGGER.call_once(|| {
+			use common::log_filter;
+			// There is no logger to report these failures to yet.
+			match log_filter::Writer::create(&log_path) {
+				Ok(writer) => {
+					if let Err(err) = log_filter::install(writer, log::LevelFilter::Trace) {
+						eprintln!("Failed to install logger: {}", err);
+					}
+				}
+				Err(err) => eprintln!("Failed to create log file {}: {}", log_path.display(), err),
+			}
+		});
+
 		log_path
 	}
 
@@ -147,7 +179,7 @@ impl engine::Runtime for Runtime {
 			engine::asset::Library::scan_pak_directory()
 				.await
 				.context("scan paks")?;
-			block::Lookup::initialize();
+			block::Lookup::initialize().await;
 			entity::component::register_types();
 
 			if let Ok(mut engine) = engine.write() {
@@ -156,7 +188,25 @@ impl engine::Runtime for Runtime {
 				// Both clients and servers run the physics simulation.
 				// The server will broadcast authoritative values (via components marked as `Replicatable`),
 				// and clients will tell the server of the changes to the entities they own via TBD.
-				engine.add_system(entity::system::Physics::new(&self.world).arclocked());
+				// The simulation is stepped at the tick rate, regardless of the frame rate.
+				// Servers run with the physics settings of their default world, which are applied when it is loaded.
+				// Only servers know the voxels of the world, so only servers collide entities with it.
+				let (physics_settings, physics_terrain) = {
+					let storage = self.network_storage.read().unwrap();
+					(
+						storage.physics_settings().clone(),
+						storage.physics_terrain().clone(),
+					)
+				};
+				engine.add_system(
+					server::tick::FixedRate::new(
+						entity::system::Physics::new(&self.world)
+							.with_settings(&physics_settings)
+							.with_terrain(&physics_terrain),
+						server::tick::ticks_per_second(),
+					)
+					.arclocked(),
+				);
 			}
 
 			if self.app_mode == mode::Kind::Server {
@@ -166,6 +216,13 @@ impl engine::Runtime for Runtime {
 					Arc::downgrade(&self.world),
 				)
 				.context("load_dedicated_server")?;
+
+				// Dedicated servers have no window, so commands are read from stdin instead of the debug panel.
+				let command_list =
+					commands::create_list(&self.app_state, &self.network_storage, &self.world);
+				if let Ok(mut engine) = engine.write() {
+					engine.add_system(commands::Intake::new(command_list).with_stdin().arclocked());
+				}
 			}
 
 			log::info!(target: CrystalSphinx::name(), "Initialization finished");
@@ -178,7 +235,7 @@ impl engine::Runtime for Runtime {
 		engine: &Arc<RwLock<Engine>>,
 		event_loop: &EventLoop<()>,
 	) -> anyhow::Result<()> {
-		if self.app_mode == mode::Kind::Server {
+		if !self.app_mode.contains(mode::Kind::Client) {
 			return Ok(());
 		}
 
@@ -216,12 +273,18 @@ impl engine::Runtime for Runtime {
 			client::UpdateCameraView::create(fn_view_world.clone(), &fn_view_input)
 		});
 
+		let fn_held_world = weak_world.clone();
+		app::store_during(&self.app_state, InGame, move || {
+			client::UpdateHeldItem::create(fn_held_world.clone())
+		});
+
 		let graphics_chain = {
 			let window = Window::builder()
 				.with_title("Crystal Sphinx")
 				.with_size(1280.0, 720.0)
 				.with_resizable(true)
 				.with_application::<CrystalSphinx>()
+				.with_device_features(graphics::chunk_boundary::enable_device_features)
 				.build(event_loop)?;
 			let graphics_chain = window.graphics_chain().clone();
 			self.window = Some(window);
@@ -240,6 +303,7 @@ impl engine::Runtime for Runtime {
 			Arc::downgrade(&self.network_storage),
 			&graphics_chain,
 			&render_phases.world,
+			&render_phases.viewmodel,
 			&arc_camera,
 			&self.world,
 		);
@@ -252,13 +316,30 @@ impl engine::Runtime for Runtime {
 			&input_user,
 		);
 		if let Ok(mut engine) = engine.write() {
+			engine.add_system(entity::system::InterpolatePositions::new(&self.world).arclocked());
 			engine
 				.add_system(entity::system::UpdateCamera::new(&self.world, arc_camera).arclocked());
+			engine.add_system(
+				entity::system::UpdateConnectionQuality::new(&self.world, &self.network_storage)
+					.arclocked(),
+			);
+		}
+
+		let command_list =
+			commands::create_list(&self.app_state, &self.network_storage, &self.world);
+		{
+			use commands::Command;
+			let antialiasing = commands::Antialiasing::new(Arc::downgrade(&graphics_chain));
+			command_list.lock().unwrap().push(antialiasing.as_arctex());
+		}
+		let command_intake = commands::Intake::new(command_list.clone()).arclocked();
+		if let Ok(mut engine) = engine.write() {
+			engine.add_system(command_intake.clone());
 		}
 
 		#[cfg(feature = "debug")]
 		{
-			let command_list = commands::create_list(&self.app_state);
+			let entity_snapshots = debug::SnapshotBuffer::default().arced();
 			let ui = egui::Ui::create(
 				self.window.as_ref().unwrap(),
 				&*event_loop,
@@ -266,12 +347,27 @@ impl engine::Runtime for Runtime {
 			)?;
 			ui.write().unwrap().add_owned_element(
 				debug::Panel::new(&input_user)
-					.with_window("Commands", debug::CommandWindow::new(command_list.clone()))
-					.with_window("Entity Inspector", debug::EntityInspector::new(&self.world))
-					.with_window("Chunk Inspector", debug::ChunkInspector::new()),
+					.with_window(
+						"Commands",
+						debug::CommandWindow::new(command_list.clone())
+							.with_console(command_intake.read().unwrap().sender()),
+					)
+					.with_window(
+						"Entity Inspector",
+						debug::EntityInspector::new(&entity_snapshots),
+					)
+					.with_window("Chunk Inspector", debug::ChunkInspector::new(&self.world))
+					.with_window("Instance Churn", debug::InstanceChurn::new())
+					.with_window(
+						"Network",
+						debug::NetworkInspector::new(&self.network_storage),
+					),
 			);
 			if let Ok(mut engine) = engine.write() {
 				engine.add_winit_listener(&ui);
+				engine.add_system(
+					debug::EntitySnapshotter::new(&self.world, &entity_snapshots).arclocked(),
+				);
 			}
 			self.egui_ui = Some(ui);
 		}
@@ -283,12 +379,23 @@ impl engine::Runtime for Runtime {
 		// TEMPORARY: Emulate loading by causing a transition to the main menu after 3 seconds
 		{
 			let thread_app_state = self.app_state.clone();
+			let is_listen_server = self.app_mode == mode::Kind::ListenServer;
 			engine::task::spawn("temp".to_owned(), async move {
 				tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-				thread_app_state
-					.write()
-					.unwrap()
-					.transition_to(app::state::State::MainMenu, None);
+				let mut app_state = thread_app_state.write().unwrap();
+				app_state.transition_to(app::state::State::MainMenu, None);
+				// A listen server hosts its world as soon as the game has loaded, instead of waiting in the main menu.
+				if is_listen_server {
+					app_state.transition_to(
+						app::state::State::LoadingWorld,
+						Some(Box::new(common::network::task::Instruction {
+							mode: mode::Kind::ListenServer.as_set(),
+							network: common::network::NetworkConfig::from_args("host_port"),
+							world_name: Some("tmp".to_owned()),
+							server_url: None,
+						})),
+					);
+				}
 				Ok(())
 			});
 		}
@@ -0,0 +1,45 @@
+use crate::block;
+use engine::math::nalgebra::Point3;
+use std::net::SocketAddr;
+
+/// A change to a single block which is about to be applied by the server,
+/// given to each [`plugin`](super::Plugin) so it can validate the change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockChangeCtx {
+	/// The connection whose player made the change, or None if the server made the change itself.
+	pub instigator: Option<SocketAddr>,
+	/// The coordinate of the chunk which contains the block.
+	pub chunk: Point3<i64>,
+	/// The offset of the block within its chunk.
+	pub offset: Point3<usize>,
+	/// The block currently at the point, or None if it is empty (air).
+	pub previous: Option<block::LookupId>,
+	/// The block which will be at the point after the change, or None if the block is being broken.
+	pub id: Option<block::LookupId>,
+}
+
+impl BlockChangeCtx {
+	/// Returns true if the change removes a block without placing a new one.
+	pub fn is_break(&self) -> bool {
+		self.id.is_none()
+	}
+}
+
+/// How a [`plugin`](super::Plugin) wants a block change to be handled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockChangeResult {
+	/// The change can be applied as it is.
+	Allow,
+	/// The change must not be applied, for the provided reason.
+	Deny(String),
+	/// The change can be applied, but with a different block placed (or the block broken if None).
+	Replace(Option<block::LookupId>),
+}
+
+/// A block change which was denied by a plugin.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+#[error("block change denied by {plugin}: {reason}")]
+pub struct BlockChangeDenied {
+	pub plugin: String,
+	pub reason: String,
+}
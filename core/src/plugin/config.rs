@@ -1,17 +1,67 @@
-use super::Plugin;
-use std::sync::Arc;
-
-#[derive(Default, Debug)]
-pub struct Config {
-	pub(super) plugins: Vec<Arc<dyn Plugin + 'static + Send + Sync>>,
-}
-
-impl Config {
-	pub fn with<T>(mut self, plugin: T) -> Self
-	where
-		T: Plugin + 'static + Send + Sync,
-	{
-		self.plugins.push(Arc::new(plugin));
-		self
-	}
-}
+use super::Plugin;
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+/// Settings which a plugin is [`configured`](Plugin::configure) with, by the name of each setting.
+pub type Parameters = HashMap<String, String>;
+
+/// The plugins compiled into the game, and how each is configured.
+///
+/// Plugins cannot be added or removed without recompiling,
+/// but the config of the compiled plugins can be [`reloaded`](super::reload_config) while the game is running.
+#[derive(Default, Debug, Clone)]
+pub struct Config {
+	pub(super) plugins: Vec<Arc<dyn Plugin + 'static + Send + Sync>>,
+	/// The names of the plugins which are compiled, but should not be used.
+	disabled: HashSet<String>,
+	parameters: HashMap<String, Parameters>,
+}
+
+impl Config {
+	pub fn with<T>(mut self, plugin: T) -> Self
+	where
+		T: Plugin + 'static + Send + Sync,
+	{
+		self.plugins.push(Arc::new(plugin));
+		self
+	}
+
+	/// Enables or disables the plugin with the provided name (plugins are enabled by default).
+	pub fn with_enabled(mut self, name: &str, enabled: bool) -> Self {
+		match enabled {
+			true => self.disabled.remove(name),
+			false => self.disabled.insert(name.to_owned()),
+		};
+		self
+	}
+
+	/// Sets a parameter which the plugin with the provided name is configured with.
+	pub fn with_parameter(mut self, name: &str, key: &str, value: &str) -> Self {
+		self.parameters
+			.entry(name.to_owned())
+			.or_default()
+			.insert(key.to_owned(), value.to_owned());
+		self
+	}
+
+	/// Returns true if a plugin with the provided name is compiled into the game (whether or not it is enabled).
+	pub fn has_plugin(&self, name: &str) -> bool {
+		self.plugins.iter().any(|plugin| plugin.name() == name)
+	}
+
+	/// Returns the name of every plugin compiled into the game, in the order they were added.
+	pub fn plugin_names(&self) -> Vec<&'static str> {
+		self.plugins.iter().map(|plugin| plugin.name()).collect()
+	}
+
+	pub fn is_enabled(&self, name: &str) -> bool {
+		!self.disabled.contains(name)
+	}
+
+	/// Returns the parameters of the plugin with the provided name, which are empty if none have been set.
+	pub fn parameters(&self, name: &str) -> Parameters {
+		self.parameters.get(name).cloned().unwrap_or_default()
+	}
+}
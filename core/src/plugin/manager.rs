@@ -1,9 +1,14 @@
-use super::{Config, Plugin, LOG};
+use super::{BlockChangeCtx, BlockChangeDenied, BlockChangeResult, Config, Plugin, LOG};
 use std::sync::{Arc, LockResult, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 #[derive(Default)]
 pub struct Manager {
 	plugins: Vec<Arc<dyn Plugin>>,
+	/// The config the active plugins were loaded (or last reloaded) with.
+	config: Config,
+	/// Incremented each time the config is reloaded,
+	/// so contributions which are cached outside of the manager (e.g. chunk decorators) know to register again.
+	revision: usize,
 }
 
 impl Manager {
@@ -24,10 +29,48 @@ impl Manager {
 
 impl Manager {
 	pub fn load(&mut self, config: &Config) {
+		self.reload_config(config);
+	}
+
+	/// Replaces the active plugins with those the config enables, and configures each with its parameters.
+	///
+	/// Contributions are gathered from the active plugins each time they are registered,
+	/// so the contributions of a disabled plugin stop being registered (and those of an enabled plugin start).
+	/// Contributions which were registered before the reload (e.g. block properties) must be registered again.
+	pub fn reload_config(&mut self, config: &Config) {
+		let previous = self
+			.plugins
+			.iter()
+			.map(|plugin| plugin.name())
+			.collect::<Vec<_>>();
+		let mut plugins: Vec<Arc<dyn Plugin>> = Vec::with_capacity(config.plugins.len());
 		for plugin_arc in config.plugins.iter() {
-			log::info!(target: LOG, "Using plugin {}", plugin_arc);
-			self.plugins.push(plugin_arc.clone());
+			let name = plugin_arc.name();
+			if !config.is_enabled(name) {
+				if previous.contains(&name) {
+					log::info!(target: LOG, "Disabled plugin {}", plugin_arc);
+				}
+				continue;
+			}
+			if !previous.contains(&name) {
+				log::info!(target: LOG, "Using plugin {}", plugin_arc);
+			}
+			plugin_arc.configure(&config.parameters(name));
+			plugins.push(plugin_arc.clone());
 		}
+		self.plugins = plugins;
+		self.config = config.clone();
+		self.revision += 1;
+	}
+
+	/// The config the active plugins were loaded with, which can be changed and [`reloaded`](super::reload_config).
+	pub fn config(&self) -> &Config {
+		&self.config
+	}
+
+	/// How many times the config has been loaded or reloaded.
+	pub fn revision(&self) -> usize {
+		self.revision
 	}
 
 	pub fn register_state_background(
@@ -45,4 +88,156 @@ impl Manager {
 			plugin.register_main_menu_music(list);
 		}
 	}
+
+	pub fn register_decorators(
+		&self,
+		list: &mut Vec<Arc<dyn crate::common::world::generator::Decorator>>,
+	) {
+		for plugin in self.plugins.iter() {
+			plugin.register_decorators(list);
+		}
+	}
+
+	pub fn register_block_properties(&self, registry: &mut crate::block::PropertyRegistry) {
+		for plugin in self.plugins.iter() {
+			plugin.register_block_properties(registry);
+		}
+	}
+
+	pub fn register_block_tick_handlers(
+		&self,
+		registry: &mut crate::server::world::block_ticks::HandlerRegistry,
+	) {
+		for plugin in self.plugins.iter() {
+			plugin.register_block_tick_handlers(registry);
+		}
+	}
+
+	/// Validates a block change against every plugin, in the order they were loaded.
+	/// Replacements made by a plugin are what later plugins see as the block being placed,
+	/// and the first plugin to deny the change stops the chain.
+	///
+	/// Returns the block which should actually be placed, or None if the block should be broken.
+	pub fn on_block_change(
+		&self,
+		mut ctx: BlockChangeCtx,
+	) -> Result<Option<crate::block::LookupId>, BlockChangeDenied> {
+		for plugin in self.plugins.iter() {
+			match plugin.on_block_change(&ctx) {
+				BlockChangeResult::Allow => {}
+				BlockChangeResult::Deny(reason) => {
+					return Err(BlockChangeDenied {
+						plugin: plugin.name().to_owned(),
+						reason,
+					});
+				}
+				BlockChangeResult::Replace(id) => {
+					ctx.id = id;
+				}
+			}
+		}
+		Ok(ctx.id)
+	}
+}
+
+#[cfg(test)]
+mod reload {
+	use super::*;
+	use crate::{app::state::State, plugin::Parameters};
+	use engine::asset;
+	use std::sync::Mutex;
+
+	struct Backgrounds {
+		name: &'static str,
+		parameters: Mutex<Option<Parameters>>,
+	}
+
+	impl Backgrounds {
+		fn new(name: &'static str) -> Self {
+			Self {
+				name,
+				parameters: Mutex::new(None),
+			}
+		}
+	}
+
+	impl Plugin for Backgrounds {
+		fn name(&self) -> &'static str {
+			self.name
+		}
+
+		fn version(&self) -> semver::Version {
+			semver::Version::new(0, 1, 0)
+		}
+
+		fn configure(&self, parameters: &Parameters) {
+			*self.parameters.lock().unwrap() = Some(parameters.clone());
+		}
+
+		fn register_state_background(&self, _state: State, list: &mut Vec<asset::Id>) {
+			list.push(asset::Id::new(self.name, "background"));
+		}
+	}
+
+	fn backgrounds(manager: &Manager) -> Vec<String> {
+		let mut list = Vec::new();
+		manager.register_state_background(State::LoadingWorld, &mut list);
+		list.into_iter().map(|id| id.to_string()).collect()
+	}
+
+	#[test]
+	fn toggling_enabled_changes_contributions() {
+		let config = Config::default()
+			.with(Backgrounds::new("alpha"))
+			.with(Backgrounds::new("beta"));
+		let mut manager = Manager::default();
+		manager.load(&config);
+		assert_eq!(
+			backgrounds(&manager),
+			vec!["alpha:background", "beta:background"]
+		);
+
+		let config = config.with_enabled("alpha", false);
+		manager.reload_config(&config);
+		assert_eq!(backgrounds(&manager), vec!["beta:background"]);
+
+		// Re-enabling keeps the order the plugins were compiled in.
+		let config = config.with_enabled("alpha", true);
+		manager.reload_config(&config);
+		assert_eq!(
+			backgrounds(&manager),
+			vec!["alpha:background", "beta:background"]
+		);
+	}
+
+	#[test]
+	fn reloading_reconfigures_plugins() {
+		let plugin = Arc::new(Backgrounds::new("alpha"));
+		let mut config = Config::default().with_parameter("alpha", "volume", "0.5");
+		config.plugins.push(plugin.clone());
+		let mut manager = Manager::default();
+		manager.load(&config);
+		let volume = |plugin: &Backgrounds| {
+			let parameters = plugin.parameters.lock().unwrap();
+			parameters.as_ref().unwrap().get("volume").cloned()
+		};
+		assert_eq!(volume(&plugin), Some("0.5".to_owned()));
+
+		manager.reload_config(&config.with_parameter("alpha", "volume", "1"));
+		assert_eq!(volume(&plugin), Some("1".to_owned()));
+	}
+
+	#[test]
+	fn reloading_keeps_the_config() {
+		let config = Config::default().with(Backgrounds::new("alpha"));
+		let mut manager = Manager::default();
+		manager.load(&config);
+		let revision = manager.revision();
+
+		let config = manager.config().clone().with_enabled("alpha", false);
+		manager.reload_config(&config);
+		assert!(!manager.config().is_enabled("alpha"));
+		assert!(manager.config().has_plugin("alpha"));
+		assert_eq!(manager.revision(), revision + 1);
+	}
 }
@@ -1,3 +1,5 @@
+mod block_change;
+pub use block_change::*;
 mod config;
 pub use config::*;
 mod manager;
@@ -6,3 +8,12 @@ mod plugin;
 pub use plugin::*;
 
 pub static LOG: &'static str = "plugin";
+
+/// Applies a new config to the loaded plugins while the game is running,
+/// and registers the contributions which were cached when the game was initialized again.
+pub fn reload_config(config: &Config) {
+	if let Ok(mut manager) = Manager::write() {
+		manager.reload_config(config);
+	}
+	crate::block::Lookup::reload_properties();
+}
@@ -1,9 +1,15 @@
-use crate::app;
+use super::{BlockChangeCtx, BlockChangeResult, Parameters};
+use crate::{app, common::world::generator::Decorator};
+use std::sync::Arc;
 
 pub trait Plugin {
 	fn name(&self) -> &'static str;
 	fn version(&self) -> semver::Version;
 
+	/// Applies the [`parameters`](super::Config::with_parameter) the plugin is configured with.
+	/// Called when the plugin is loaded, and again whenever the config is [`reloaded`](super::reload_config).
+	fn configure(&self, _parameters: &Parameters) {}
+
 	fn register_state_background(
 		&self,
 		state: app::state::State,
@@ -11,6 +17,23 @@ pub trait Plugin {
 	);
 	// temporary proof of concept function, need to have game phases at some point
 	fn register_main_menu_music(&self, _list: &mut engine::asset::WeightedIdList) {}
+	/// Adds decorators which are run, in order, on every chunk the server generates.
+	fn register_decorators(&self, _list: &mut Vec<Arc<dyn Decorator>>) {}
+	/// Adds the [`property`](crate::block::Property) types which block assets can declare,
+	/// which the plugin (or any other) can read with [`Block::get_property`](crate::block::Block::get_property).
+	fn register_block_properties(&self, _registry: &mut crate::block::PropertyRegistry) {}
+	/// Adds the handlers of [`random and scheduled ticks`](crate::server::world::block_ticks) for block types.
+	/// Handlers are registered again whenever the plugins are [`reloaded`](super::reload_config).
+	fn register_block_tick_handlers(
+		&self,
+		_registry: &mut crate::server::world::block_ticks::HandlerRegistry,
+	) {
+	}
+	/// Called on the server before a block is placed or broken,
+	/// so the plugin can allow, deny, or replace the block being placed.
+	fn on_block_change(&self, _ctx: &BlockChangeCtx) -> BlockChangeResult {
+		BlockChangeResult::Allow
+	}
 }
 
 impl std::fmt::Display for dyn Plugin + 'static + Send + Sync {
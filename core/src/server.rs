@@ -1,3 +1,7 @@
+pub mod capacity;
+pub mod metrics;
+pub mod movement;
 pub mod network;
+pub mod tick;
 pub mod user;
 pub mod world;
@@ -0,0 +1,101 @@
+//! How many players can be connected to the server at once.
+
+use crate::common::account;
+
+/// The number of players which can be connected at once, if not otherwise configured.
+pub const DEFAULT_MAX_PLAYERS: usize = 20;
+
+/// The number of players which can be connected at once,
+/// as configured by the `-max_players=` argument (or [`DEFAULT_MAX_PLAYERS`]).
+pub fn max_players() -> usize {
+	crate::common::utility::get_named_arg("max_players")
+		.map(|count| count as usize)
+		.unwrap_or(DEFAULT_MAX_PLAYERS)
+}
+
+/// Limits the number of accounts which can be connected (or connecting) to the server at once.
+///
+/// Slots are counted per account rather than per connection,
+/// so a player who reconnects before their old connection has timed out is not counted twice.
+pub struct Capacity {
+	max_players: usize,
+}
+
+impl Default for Capacity {
+	fn default() -> Self {
+		Self::new(max_players())
+	}
+}
+
+impl Capacity {
+	pub fn new(max_players: usize) -> Self {
+		Self { max_players }
+	}
+
+	pub fn max_players(&self) -> usize {
+		self.max_players
+	}
+
+	/// Returns an error if `account` cannot join while the `connected` accounts
+	/// (both authenticated and still in their handshake) hold a slot.
+	pub fn check<'a>(
+		&self,
+		account: &account::Id,
+		connected: impl IntoIterator<Item = &'a account::Id>,
+	) -> Result<(), Error> {
+		let occupied = connected
+			.into_iter()
+			.filter(|id| *id != account)
+			.collect::<std::collections::HashSet<_>>()
+			.len();
+		match occupied < self.max_players {
+			true => Ok(()),
+			false => Err(Error::Full(self.max_players)),
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+	#[error("server full ({0} players)")]
+	Full(usize),
+}
+
+#[cfg(test)]
+mod capacity {
+	use super::*;
+
+	fn ids(count: usize) -> Vec<account::Id> {
+		(0..count).map(|i| format!("account-{}", i)).collect()
+	}
+
+	#[test]
+	fn rejects_when_full() {
+		let capacity = Capacity::new(3);
+		let connected = ids(3);
+		for (i, id) in connected.iter().enumerate() {
+			assert_eq!(capacity.check(id, connected[..i].iter()), Ok(()));
+		}
+		let error = capacity.check(&"account-3".to_owned(), connected.iter());
+		assert_eq!(error, Err(Error::Full(3)));
+		assert_eq!(error.unwrap_err().to_string(), "server full (3 players)");
+	}
+
+	#[test]
+	fn reconnecting_account_is_not_counted_twice() {
+		let capacity = Capacity::new(2);
+		let connected = ids(2);
+		assert_eq!(capacity.check(&connected[1], connected.iter()), Ok(()));
+	}
+
+	#[test]
+	fn accounts_are_counted_once() {
+		let capacity = Capacity::new(2);
+		// An account with a stale connection and a new one only holds a single slot.
+		let connected = vec!["account-0".to_owned(), "account-0".to_owned()];
+		assert_eq!(
+			capacity.check(&"account-1".to_owned(), connected.iter()),
+			Ok(())
+		);
+	}
+}
@@ -0,0 +1,222 @@
+//! Runtime statistics about the server (loaded chunks, connections, replication).
+//!
+//! Values are stored as atomics which are written by the systems that own the data,
+//! so reading them (e.g. from the metrics endpoint) never blocks the game loop.
+//! When the `metrics` feature is enabled, dedicated servers serve these statistics
+//! over http in the [Prometheus text format](https://prometheus.io/docs/instrumenting/exposition_formats/),
+//! so they can be scraped by Prometheus/Grafana.
+
+use std::{
+	collections::BTreeMap,
+	mem::MaybeUninit,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Once, RwLock,
+	},
+};
+
+#[cfg(feature = "metrics")]
+static LOG: &'static str = "metrics";
+
+pub struct Metrics {
+	/// The number of chunks loaded in each world, by world name.
+	loaded_chunks: RwLock<BTreeMap<String, usize>>,
+	active_connections: AtomicUsize,
+	pending_handshakes: AtomicUsize,
+	entities_relevant: AtomicUsize,
+}
+
+impl Metrics {
+	pub fn new() -> Self {
+		Self {
+			loaded_chunks: RwLock::new(BTreeMap::new()),
+			active_connections: AtomicUsize::new(0),
+			pending_handshakes: AtomicUsize::new(0),
+			entities_relevant: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns the metrics for the running application.
+	pub fn get() -> &'static Self {
+		static mut INSTANCE: (MaybeUninit<Metrics>, Once) = (MaybeUninit::uninit(), Once::new());
+		unsafe {
+			INSTANCE
+				.1
+				.call_once(|| INSTANCE.0.as_mut_ptr().write(Metrics::new()));
+			&*INSTANCE.0.as_ptr()
+		}
+	}
+
+	/// Sets the number of chunks loaded in a world.
+	pub fn set_loaded_chunks(&self, world: &str, count: usize) {
+		let mut loaded_chunks = self.loaded_chunks.write().unwrap();
+		match loaded_chunks.get_mut(world) {
+			Some(loaded) => *loaded = count,
+			None => {
+				loaded_chunks.insert(world.to_owned(), count);
+			}
+		}
+	}
+
+	/// Stops reporting the chunks of a world, once it has been unloaded.
+	pub fn remove_loaded_chunks(&self, world: &str) {
+		self.loaded_chunks.write().unwrap().remove(world);
+	}
+
+	pub fn set_active_connections(&self, count: usize) {
+		self.active_connections.store(count, Ordering::Relaxed);
+	}
+
+	pub fn begin_handshake(&self) {
+		self.pending_handshakes.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn end_handshake(&self) {
+		self.pending_handshakes.fetch_sub(1, Ordering::Relaxed);
+	}
+
+	/// Sets the total number of entity-connection pairs for which the entity is relevant.
+	pub fn set_entities_relevant(&self, count: usize) {
+		self.entities_relevant.store(count, Ordering::Relaxed);
+	}
+
+	pub fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			loaded_chunks: self.loaded_chunks.read().unwrap().clone(),
+			active_connections: self.active_connections.load(Ordering::Relaxed),
+			pending_handshakes: self.pending_handshakes.load(Ordering::Relaxed),
+			entities_relevant: self.entities_relevant.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// The values of [`Metrics`] at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+	/// The number of chunks loaded in each world, by world name.
+	pub loaded_chunks: BTreeMap<String, usize>,
+	pub active_connections: usize,
+	pub pending_handshakes: usize,
+	pub entities_relevant: usize,
+}
+
+impl Snapshot {
+	fn gauges(&self) -> [(&'static str, &'static str, usize); 3] {
+		[
+			(
+				"crystal_sphinx_active_connections",
+				"Number of open network connections.",
+				self.active_connections,
+			),
+			(
+				"crystal_sphinx_pending_handshakes",
+				"Number of connections which have not finished authenticating.",
+				self.pending_handshakes,
+			),
+			(
+				"crystal_sphinx_entities_relevant",
+				"Number of entity-connection pairs being replicated.",
+				self.entities_relevant,
+			),
+		]
+	}
+}
+
+impl std::fmt::Display for Snapshot {
+	/// Writes the snapshot in the Prometheus text exposition format.
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		writeln!(
+			f,
+			"# HELP crystal_sphinx_loaded_chunks Number of chunks loaded in each world database."
+		)?;
+		writeln!(f, "# TYPE crystal_sphinx_loaded_chunks gauge")?;
+		for (world, count) in self.loaded_chunks.iter() {
+			writeln!(
+				f,
+				"crystal_sphinx_loaded_chunks{{world=\"{}\"}} {}",
+				world, count
+			)?;
+		}
+		for (name, help, value) in self.gauges().iter() {
+			writeln!(f, "# HELP {} {}", name, help)?;
+			writeln!(f, "# TYPE {} gauge", name)?;
+			writeln!(f, "{} {}", name, value)?;
+		}
+		Ok(())
+	}
+}
+
+/// Spawns a task which serves the application's [`Metrics`] on the provided port.
+#[cfg(feature = "metrics")]
+pub fn start_endpoint(port: u16) {
+	engine::task::spawn(LOG.to_owned(), async move {
+		let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+		log::info!(target: LOG, "Serving metrics on port {}", port);
+		serve(listener, Metrics::get()).await
+	});
+}
+
+/// Responds to every http request on the listener with a snapshot of the metrics.
+/// The request itself is not inspected, so any path will return the metrics.
+#[cfg(feature = "metrics")]
+pub async fn serve(
+	listener: tokio::net::TcpListener,
+	metrics: &'static Metrics,
+) -> anyhow::Result<()> {
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	loop {
+		let (mut stream, _address) = listener.accept().await?;
+		let body = metrics.snapshot().to_string();
+		tokio::spawn(async move {
+			let mut request = [0u8; 1024];
+			let _ = stream.read(&mut request).await;
+			let response = format!(
+				"HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+				body.len(),
+				body
+			);
+			if let Err(err) = stream.write_all(response.as_bytes()).await {
+				log::warn!(target: LOG, "Failed to write metrics response: {}", err);
+			}
+			let _ = stream.shutdown().await;
+		});
+	}
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod endpoint {
+	use super::*;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	#[tokio::test]
+	async fn serves_loaded_chunks_per_world() {
+		let metrics: &'static Metrics = Box::leak(Box::new(Metrics::new()));
+		metrics.set_loaded_chunks("world", 42);
+		metrics.set_loaded_chunks("arena", 7);
+
+		let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+			.await
+			.unwrap();
+		let address = listener.local_addr().unwrap();
+		tokio::spawn(serve(listener, metrics));
+
+		let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+		stream
+			.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+			.await
+			.unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).await.unwrap();
+
+		assert!(response.starts_with("HTTP/1.1 200 OK"));
+		let loaded_chunks = |world: &str| {
+			let prefix = format!("crystal_sphinx_loaded_chunks{{world=\"{}\"}} ", world);
+			response
+				.lines()
+				.find_map(|line| line.strip_prefix(prefix.as_str()))
+				.map(|value| value.parse::<usize>().unwrap())
+		};
+		assert_eq!(loaded_chunks("world"), Some(42));
+		assert_eq!(loaded_chunks("arena"), Some(7));
+	}
+}
@@ -0,0 +1,145 @@
+//! Sanity checks on the movement clients report for the entities they own.
+//!
+//! Clients tell the server the velocity of their player (see [`move_player`](crate::common::network::move_player)),
+//! which the server's physics integrates into the authoritative [`Position`](crate::entity::component::physics::linear::Position)
+//! each tick. Bounding the reported velocity bounds how far a client can move its entity in a tick,
+//! so a modified client cannot teleport itself.
+//! Server-initiated moves (e.g. the `tp` command) set the position directly and are never checked.
+use engine::math::nalgebra::Vector3;
+use std::time::Duration;
+
+static LOG: &'static str = "movement";
+
+/// The fastest (in blocks per second) a client can move the entity it owns.
+/// Slightly faster than players can actually move (including diagonally), so legitimate movement is never clamped.
+pub static MAX_SPEED: f32 = 8.0;
+
+/// The result of checking a reported velocity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+	/// The velocity was within the limits and is used as-is.
+	Accepted,
+	/// The velocity was faster than allowed, and has been clamped to the max speed.
+	Clamped { reported_speed: f32 },
+	/// The velocity was not a finite number, and the entity is stopped instead.
+	Rejected,
+}
+
+/// Checks the velocities clients report against a max speed.
+#[derive(Debug, Clone, Copy)]
+pub struct Validator {
+	max_speed: f32,
+}
+
+impl Default for Validator {
+	fn default() -> Self {
+		Self {
+			max_speed: MAX_SPEED,
+		}
+	}
+}
+
+impl Validator {
+	pub fn with_max_speed(mut self, max_speed: f32) -> Self {
+		self.max_speed = max_speed;
+		self
+	}
+
+	pub fn max_speed(&self) -> f32 {
+		self.max_speed
+	}
+
+	/// The farthest an entity can be moved by its client in `delta_time` (i.e. a server tick).
+	pub fn max_displacement(&self, delta_time: Duration) -> f32 {
+		self.max_speed * delta_time.as_secs_f32()
+	}
+
+	/// Returns the velocity which should be applied for a velocity reported by a client.
+	pub fn validate(&self, reported: &Vector3<f32>) -> (Vector3<f32>, Verdict) {
+		if !reported.iter().all(|axis| axis.is_finite()) {
+			return (Vector3::zeros(), Verdict::Rejected);
+		}
+		let speed = reported.magnitude();
+		if speed <= self.max_speed {
+			return (*reported, Verdict::Accepted);
+		}
+		let clamped = reported * (self.max_speed / speed);
+		(
+			clamped,
+			Verdict::Clamped {
+				reported_speed: speed,
+			},
+		)
+	}
+
+	/// Validates a velocity reported by a client, logging if it was suspicious.
+	pub fn validate_from(
+		&self,
+		client: &std::net::SocketAddr,
+		reported: &Vector3<f32>,
+	) -> Vector3<f32> {
+		let (velocity, verdict) = self.validate(reported);
+		match verdict {
+			Verdict::Accepted => {}
+			Verdict::Clamped { reported_speed } => {
+				log::warn!(
+					target: LOG,
+					"Client {} reported a speed of {:.2} blocks/s, which is faster than the max of {:.2}",
+					client,
+					reported_speed,
+					self.max_speed
+				);
+			}
+			Verdict::Rejected => {
+				log::warn!(target: LOG, "Client {} reported an invalid velocity {:?}", client, reported);
+			}
+		}
+		velocity
+	}
+}
+
+#[cfg(test)]
+mod validate {
+	use super::*;
+	use crate::{entity::component::physics::linear::Position, server::tick::ticks_per_second};
+
+	#[test]
+	fn normal_speed_is_accepted() {
+		let validator = Validator::default();
+		let reported = Vector3::new(4.0, 0.0, 4.0);
+		assert_eq!(validator.validate(&reported), (reported, Verdict::Accepted));
+	}
+
+	#[test]
+	fn over_speed_is_clamped_per_tick() {
+		let validator = Validator::default();
+		let reported = Vector3::new(0.0, 0.0, 1000.0);
+		let (velocity, verdict) = validator.validate(&reported);
+		assert_eq!(
+			verdict,
+			Verdict::Clamped {
+				reported_speed: 1000.0
+			}
+		);
+		assert!((velocity.magnitude() - MAX_SPEED).abs() < 1e-4);
+		assert!(velocity.normalize().dot(&reported.normalize()) > 0.999);
+
+		// Stepping a tick with the clamped velocity moves the entity no further than the max displacement.
+		let tick = Duration::from_secs_f32(1.0 / ticks_per_second() as f32);
+		let mut position = Position::default();
+		let start = position.world_position();
+		position += velocity * tick.as_secs_f32();
+		let moved = (position.world_position() - start).magnitude() as f32;
+		assert!(moved <= validator.max_displacement(tick) + 1e-4);
+	}
+
+	#[test]
+	fn invalid_velocity_is_rejected() {
+		let validator = Validator::default();
+		let (velocity, verdict) = validator.validate(&Vector3::new(f32::NAN, 0.0, 0.0));
+		assert_eq!(verdict, Verdict::Rejected);
+		assert_eq!(velocity, Vector3::zeros());
+		let (_, verdict) = validator.validate(&Vector3::new(0.0, f32::INFINITY, 0.0));
+		assert_eq!(verdict, Verdict::Rejected);
+	}
+}
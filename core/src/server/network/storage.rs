@@ -1,34 +1,68 @@
 use crate::{
-	common::account::{self, key},
+	common::{
+		account::{self, key},
+		world::{clock::Clock, schematic},
+	},
 	entity::{self, ArcLockEntityWorld},
+	server::capacity,
+	server::tick,
 	server::user,
-	server::world::{chunk, Database},
+	server::world::{
+		block_ticks::BlockTicks, chunk, registry, ArcLockDatabase, Database, Registry, UpdateClock,
+	},
 };
 use anyhow::{Context, Result};
 use engine::{Engine, EngineSystem};
 use std::{
 	collections::HashMap,
 	path::{Path, PathBuf},
-	sync::{Arc, RwLock},
+	sync::{Arc, RwLock, Weak},
 };
 
 static LOG: &'static str = "server";
 
+/// The name of the world that players join into.
+/// Its data is stored in the `world` directory of the save,
+/// while any other worlds are stored in `worlds/<name>`.
+pub static DEFAULT_WORLD: &'static str = "world";
+
+/// The directory in `players` which users that cannot be loaded are moved to,
+/// so they are kept for recovery by hand but are not loaded again each time the server starts.
+pub static CORRUPT_USERS_DIR: &'static str = "corrupt";
+
 pub struct Storage {
 	root_dir: PathBuf,
 
 	certificate: key::Certificate,
 	private_key: key::PrivateKey,
 	users: HashMap<account::Id, Arc<RwLock<user::Active>>>,
+	/// Finds users by the name other players know them by.
+	resolver: Box<dyn account::Resolver>,
 
-	database: Option<Arc<RwLock<Database>>>,
+	/// The worlds being hosted by the server, each with its own chunk database and loading thread.
+	worlds: HashMap<String, ArcLockDatabase>,
+	/// The world each player is in, if not the [`default world`](DEFAULT_WORLD).
+	/// A player's user data is shared by all worlds, but their entity is only ever in one world at a time.
+	player_worlds: HashMap<account::Id, String>,
+	/// The worlds which are loaded, shared with the systems that find the world each entity is in.
+	registry: Registry,
+	scheduler: tick::ArcLockScheduler,
+	/// Resolves the persistent id of each entity to the entity it currently is in the world.
+	persistent_ids: entity::ArcLockPersistentIds,
+	/// The world of the entities which are saved with the savegame, once its systems have been initialized.
+	entity_world: Weak<RwLock<entity::World>>,
+	/// How many players can be connected at once.
+	capacity: capacity::Capacity,
+	/// Random and scheduled block updates for each world, once its systems have been initialized.
+	block_ticks: HashMap<String, Arc<RwLock<BlockTicks>>>,
+	/// The time of day, which is shared by all worlds.
+	clock: Arc<RwLock<Clock>>,
 	systems: Vec<Arc<RwLock<dyn EngineSystem + Send + Sync>>>,
 }
 
 impl Storage {
 	#[profiling::function]
 	pub fn load(save_name: &str) -> Result<Self> {
-		use crate::common::utility::DataFile;
 		let mut savegame_path = std::env::current_dir().unwrap();
 		savegame_path.push("saves");
 		savegame_path.push(save_name);
@@ -37,35 +71,98 @@ impl Storage {
 			Self::create(&savegame_path).context("generating server data")?;
 		}
 		log::info!(target: LOG, "Loading data");
-		let certificate =
-			key::Certificate::load(&savegame_path).context("loading server certificate")?;
-		let private_key =
-			key::PrivateKey::load(&savegame_path).context("loading server private key")?;
+		let (certificate, private_key) = Self::load_keys(&savegame_path)?;
+		let users = Self::load_users(&Self::players_dir_path(savegame_path.to_owned()))
+			.context("loading users")?;
+		let mut resolver = account::Offline::default();
+		for (id, user) in users.iter() {
+			let name = user.read().unwrap().account().display_name().clone();
+			resolver.register(id.clone(), name);
+		}
 		Ok(Self {
 			root_dir: savegame_path.to_owned(),
 
 			certificate,
 			private_key,
-			users: Self::load_users(&Self::players_dir_path(savegame_path.to_owned()))
-				.context("loading users")?,
+			users,
+			resolver: Box::new(resolver),
 
-			database: None,
+			worlds: HashMap::new(),
+			player_worlds: HashMap::new(),
+			registry: Registry::default(),
+			scheduler: tick::Scheduler::default().arclocked(),
+			persistent_ids: entity::PersistentIds::default().arclocked(),
+			entity_world: Weak::new(),
+			capacity: capacity::Capacity::default(),
+			block_ticks: HashMap::new(),
+			clock: Arc::new(RwLock::new(Clock::default())),
 			systems: vec![],
 		})
 	}
 
 	fn create(root: &Path) -> Result<()> {
-		use crate::common::utility::DataFile;
 		log::info!(target: LOG, "Creating data");
 		std::fs::create_dir_all(root)?;
+		Self::generate_keys(root)?;
+		Ok(())
+	}
 
+	fn generate_keys(root: &Path) -> Result<()> {
+		use crate::common::utility::DataFile;
 		let (_, certificate, private_key) = key::create_pem()?;
 		std::fs::write(&key::Certificate::make_path(&root), certificate)?;
 		std::fs::write(&key::PrivateKey::make_path(&root), private_key)?;
-
 		Ok(())
 	}
 
+	/// Loads the certificate and private key which identify the server to clients.
+	/// If both files are missing, a new pair is generated (so the server's identity changes).
+	/// If only one of the files exists, either file cannot be read, or the private key does not belong to the certificate,
+	/// an error is returned instead of replacing the existing identity.
+	fn load_keys(root: &Path) -> Result<(key::Certificate, key::PrivateKey)> {
+		use crate::common::utility::DataFile;
+		let certificate_path = key::Certificate::make_path(&root);
+		let private_key_path = key::PrivateKey::make_path(&root);
+		match (certificate_path.exists(), private_key_path.exists()) {
+			(false, false) => {
+				log::warn!(
+					target: LOG,
+					"The server certificate and private key are missing, generating a new pair. Clients which have joined before will see a different server identity."
+				);
+				Self::generate_keys(root).context("generating server keys")?;
+			}
+			(true, false) => Err(Error::MissingKey {
+				name: "private key",
+				path: private_key_path.clone(),
+			})?,
+			(false, true) => Err(Error::MissingKey {
+				name: "certificate",
+				path: certificate_path.clone(),
+			})?,
+			(true, true) => {}
+		}
+		let certificate =
+			key::Certificate::load_from(&certificate_path).map_err(|err| Error::UnreadableKey {
+				name: "certificate",
+				path: certificate_path.clone(),
+				reason: err.to_string(),
+			})?;
+		let private_key =
+			key::PrivateKey::load_from(&private_key_path).map_err(|err| Error::UnreadableKey {
+				name: "private key",
+				path: private_key_path.clone(),
+				reason: err.to_string(),
+			})?;
+		key::Key::Private(certificate.clone(), private_key.clone())
+			.validate()
+			.map_err(|err| Error::MismatchedKeys {
+				certificate: certificate_path,
+				private_key: private_key_path,
+				reason: err.to_string(),
+			})?;
+		Ok((certificate, private_key))
+	}
+
 	fn players_dir_path(mut savegame_path: PathBuf) -> PathBuf {
 		savegame_path.push("players");
 		savegame_path
@@ -75,35 +172,92 @@ impl Storage {
 		Self::players_dir_path(self.root_dir.clone())
 	}
 
+	/// Returns the directory that entities with a [`persistent id`](entity::component::PersistentId) (such as players) are saved to.
+	pub fn get_entities_dir_path(&self) -> PathBuf {
+		let mut path = self.root_dir.clone();
+		path.push("entities");
+		path
+	}
+
+	/// Returns the path of a schematic (a saved region of blocks) in the savegame's `schematics` directory.
+	/// Fails if the name is not a [`valid`](schematic::validate_name) file name.
+	pub fn get_schematic_path(&self, name: &str) -> Result<PathBuf> {
+		schematic::validate_name(name)?;
+		let mut path = self.root_dir.clone();
+		path.push("schematics");
+		path.push(format!("{}.{}", name, schematic::EXTENSION));
+		Ok(path)
+	}
+
 	fn world_name(&self) -> &str {
 		self.root_dir.file_name().unwrap().to_str().unwrap()
 	}
 
+	/// Loads every user saved in the `players` directory.
+	/// Users which fail to load are skipped, and those which are [`irrecoverable`](user::Error)
+	/// are moved to the [`quarantine`](CORRUPT_USERS_DIR) directory.
 	fn load_users(path: &Path) -> Result<HashMap<account::Id, Arc<RwLock<user::Active>>>> {
 		std::fs::create_dir_all(path)?;
 		let mut users = HashMap::new();
 		for entry in std::fs::read_dir(path)? {
 			let user_path = entry?.path();
-			if user_path.is_dir() {
-				match user::Active::load(&user_path) {
-					Ok(user) => {
-						log::info!(target: LOG, "Loaded user {}", user.account().id());
-						users.insert(user.account().id().clone(), Arc::new(RwLock::new(user)));
-					}
-					Err(err) => {
-						log::warn!(
+			if !user_path.is_dir()
+				|| user_path.file_name() == Some(std::ffi::OsStr::new(CORRUPT_USERS_DIR))
+			{
+				continue;
+			}
+			match user::Active::load(&user_path) {
+				Ok(user) => {
+					log::info!(target: LOG, "Loaded user {}", user.account().id());
+					users.insert(user.account().id().clone(), Arc::new(RwLock::new(user)));
+				}
+				Err(err) if err.downcast_ref::<user::Error>().is_some() => {
+					match Self::quarantine_user(path, &user_path) {
+						Ok(destination) => log::warn!(
+							target: LOG,
+							"User {} is corrupt ({}), it has been moved to {}",
+							user_path.display(),
+							err,
+							destination.display()
+						),
+						Err(move_err) => log::error!(
 							target: LOG,
-							"Failed to load user {}: {}",
+							"User {} is corrupt ({}), and could not be moved out of the way: {}",
 							user_path.display(),
-							err
-						);
+							err,
+							move_err
+						),
 					}
 				}
+				Err(err) => {
+					log::warn!(
+						target: LOG,
+						"Failed to load user {}: {}",
+						user_path.display(),
+						err
+					);
+				}
 			}
 		}
 		Ok(users)
 	}
 
+	/// Moves a user directory into the [`quarantine`](CORRUPT_USERS_DIR) directory,
+	/// returning where it was moved to. If a user of the same name was already quarantined,
+	/// the directory is given a unique suffix instead of replacing the earlier one.
+	fn quarantine_user(players_dir: &Path, user_path: &Path) -> Result<PathBuf> {
+		let mut quarantine_dir = players_dir.to_owned();
+		quarantine_dir.push(CORRUPT_USERS_DIR);
+		std::fs::create_dir_all(&quarantine_dir)?;
+		let name = user_path.file_name().unwrap().to_string_lossy().to_string();
+		let mut destination = quarantine_dir.join(&name);
+		if destination.exists() {
+			destination = quarantine_dir.join(format!("{}.{}", name, uuid::Uuid::new_v4()));
+		}
+		std::fs::rename(&user_path, &destination)?;
+		Ok(destination)
+	}
+
 	pub fn add_user(&mut self, id: account::Id, user: Arc<RwLock<user::Active>>) {
 		self.users.insert(id, user.clone());
 		engine::task::spawn(LOG.to_string(), async move {
@@ -116,20 +270,128 @@ impl Storage {
 		self.users.get(id)
 	}
 
-	fn world_path(mut savegame_path: PathBuf) -> PathBuf {
-		savegame_path.push("world");
+	pub fn find_user_by_name(&self, name: &str) -> Option<&Arc<RwLock<user::Active>>> {
+		self.users.get(&self.resolver.id_of(name)?)
+	}
+
+	pub fn resolver(&self) -> &dyn account::Resolver {
+		self.resolver.as_ref()
+	}
+
+	/// Records the name a user is known by, which they provide each time they join.
+	pub fn register_name(&mut self, id: account::Id, name: String) {
+		self.resolver.register(id, name);
+	}
+
+	fn world_path(mut savegame_path: PathBuf, name: &str) -> PathBuf {
+		if name == DEFAULT_WORLD {
+			savegame_path.push("world");
+		} else {
+			savegame_path.push("worlds");
+			savegame_path.push(name);
+		}
 		savegame_path
 	}
 
+	/// Returns the names of the non-default worlds which have been saved.
+	fn saved_world_names(savegame_path: &Path) -> Result<Vec<String>> {
+		let mut worlds_dir = savegame_path.to_owned();
+		worlds_dir.push("worlds");
+		if !worlds_dir.exists() {
+			return Ok(Vec::new());
+		}
+		let mut names = Vec::new();
+		for entry in std::fs::read_dir(&worlds_dir)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+					names.push(name.to_owned());
+				}
+			}
+		}
+		names.sort();
+		Ok(names)
+	}
+
 	pub fn initialize_systems(&mut self, entity_world: &ArcLockEntityWorld) {
+		self.entity_world = Arc::downgrade(&entity_world);
 		self.add_system(entity::system::UserChunkTicketUpdater::new(&entity_world));
+		self.add_system(entity::system::TrackPersistentIds::new(
+			&entity_world,
+			&self.persistent_ids,
+		));
+		self.add_system(entity::system::BreakBlocks::new(
+			&entity_world,
+			&self.registry,
+		));
+		self.add_system(entity::system::PlaceBlocks::new(
+			&entity_world,
+			&self.registry,
+		));
+		let world_names = self.worlds.keys().cloned().collect::<Vec<_>>();
+		for name in world_names.into_iter() {
+			self.add_world_systems(&name);
+		}
+		self.add_system(UpdateClock::new(&self.clock, tick::ticks_per_second()));
+		tick::Scheduler::set_active(&self.scheduler);
+		self.add_system(
+			tick::TickLoop::new(self.scheduler.clone())
+				.with_ticks_per_second(tick::ticks_per_second()),
+		);
+	}
+
+	/// The scheduler for gameplay actions which should execute on a specific server tick.
+	pub fn scheduler(&self) -> &tick::ArcLockScheduler {
+		&self.scheduler
+	}
+
+	/// The map from the persistent id of each entity to the entity it currently is in the world.
+	pub fn persistent_ids(&self) -> &entity::ArcLockPersistentIds {
+		&self.persistent_ids
+	}
+
+	pub fn capacity(&self) -> &capacity::Capacity {
+		&self.capacity
+	}
+
+	/// Adds the systems which only act on the chunks of a single world.
+	fn add_world_systems(&mut self, name: &str) {
+		let chunk_cache = self.worlds[name].read().unwrap().chunk_cache().clone();
+		let block_ticks = BlockTicks::new(&chunk_cache)
+			.with_root_dir(Self::world_path(self.root_dir.clone(), name))
+			.arclocked();
+		self.add_arclocked_system(block_ticks.clone());
+		self.block_ticks.insert(name.to_owned(), block_ticks);
+	}
+
+	/// The random and scheduled block updates of a world, where block types register their tick handlers.
+	pub fn block_ticks(&self, world: &str) -> Option<&Arc<RwLock<BlockTicks>>> {
+		self.block_ticks.get(world)
+	}
+
+	/// The time of day, which commands can set or freeze.
+	pub fn clock(&self) -> &Arc<RwLock<Clock>> {
+		&self.clock
 	}
 
 	pub fn add_system<T>(&mut self, system: T)
 	where
 		T: EngineSystem + 'static + Send + Sync,
 	{
-		let system = Arc::new(RwLock::new(system));
+		self.add_arclocked_system(Arc::new(RwLock::new(system)));
+	}
+
+	/// The systems which have been added, for tests which update the server without an engine.
+	#[cfg(test)]
+	pub(crate) fn systems(&self) -> &Vec<Arc<RwLock<dyn EngineSystem + Send + Sync>>> {
+		&self.systems
+	}
+
+	/// Adds a system which other objects also hold a reference to.
+	pub fn add_arclocked_system<T>(&mut self, system: Arc<RwLock<T>>)
+	where
+		T: EngineSystem + 'static + Send + Sync,
+	{
 		{
 			let mut engine = Engine::get().write().unwrap();
 			engine.add_weak_system(Arc::downgrade(&system));
@@ -143,21 +405,366 @@ impl Storage {
 		Ok((certificate, private_key))
 	}
 
+	/// Loads the default world, and every other world which has been saved.
 	#[profiling::function]
 	pub fn start_loading_world(&mut self) -> anyhow::Result<()> {
 		log::warn!(target: "world-loader", "Loading world \"{}\"", self.world_name());
-		let database = Database::new(Self::world_path(self.root_dir.to_owned()))?;
+		self.load_world(DEFAULT_WORLD)?;
+		for name in Self::saved_world_names(&self.root_dir)?.into_iter() {
+			self.load_world(&name)?;
+		}
+		Ok(())
+	}
+
+	/// Loads (or creates) the world with the provided name, if it is not already loaded.
+	/// The world is saved in the savegame, and will be loaded each time the server starts.
+	pub fn load_world(&mut self, name: &str) -> anyhow::Result<&ArcLockDatabase> {
+		if !self.worlds.contains_key(name) {
+			log::info!(target: "world-loader", "Loading world database \"{}\"", name);
+			let database = Database::new(name, Self::world_path(self.root_dir.to_owned(), name))
+				.with_context(|| format!("loading world \"{}\"", name))?;
+			// The default world receives tickets submitted via `Ticket::submit`.
+			if name == DEFAULT_WORLD {
+				database.set_default();
+			}
+			let arc_database = Arc::new(RwLock::new(database));
+			Database::load_origin_chunk(&arc_database)?;
+			self.registry
+				.insert(name, registry::Entry::from(&*arc_database.read().unwrap()));
+			self.worlds.insert(name.to_owned(), arc_database);
+			// Worlds loaded after the server has started need their own systems too.
+			if self.entity_world.upgrade().is_some() {
+				self.add_world_systems(name);
+			}
+		}
+		Ok(self.worlds.get(name).unwrap())
+	}
+
+	pub fn world(&self, name: &str) -> Option<&ArcLockDatabase> {
+		self.worlds.get(name)
+	}
+
+	pub fn world_names(&self) -> impl Iterator<Item = &String> {
+		self.worlds.keys()
+	}
+
+	/// The worlds which are loaded, for systems which need to find the world an entity is in.
+	pub fn registry(&self) -> &Registry {
+		&self.registry
+	}
+
+	/// Returns the database an entity in the named world submits its chunk tickets to,
+	/// or None if it is the default world (whose tickets are submitted the same way as any other system).
+	pub(crate) fn ticket_world(&self, name: &str) -> Result<Option<Weak<RwLock<Database>>>> {
+		let arc_database = self
+			.worlds
+			.get(name)
+			.ok_or_else(|| Error::UnknownWorld(name.to_owned()))?;
+		Ok(match name == DEFAULT_WORLD {
+			true => None,
+			false => Some(Arc::downgrade(&arc_database)),
+		})
+	}
+
+	/// Returns the name of the world the player is in.
+	pub fn world_of(&self, id: &account::Id) -> &str {
+		match self.player_worlds.get(id) {
+			Some(name) => name.as_str(),
+			None => DEFAULT_WORLD,
+		}
+	}
 
-		let arc_database = Arc::new(RwLock::new(database));
-		let origin_res = Database::load_origin_chunk(&arc_database);
-		assert!(origin_res.is_ok());
+	/// Moves a player into another loaded world.
+	/// Their entity stops loading chunks in their previous world and begins loading chunks in the new world.
+	pub fn move_player(
+		&mut self,
+		id: &account::Id,
+		world_name: &str,
+		entity_world: &ArcLockEntityWorld,
+	) -> anyhow::Result<()> {
+		use entity::component::{
+			chunk::TicketOwner, physics::linear::Position, OwnedByAccount, Parent,
+		};
+		let ticket_world = self.ticket_world(world_name)?;
+
+		let mut world = entity_world.write().unwrap();
+		for (_entity, (owner, ticket_owner)) in
+			world.query_mut::<(&OwnedByAccount, &mut TicketOwner)>()
+		{
+			if owner.id() == id {
+				ticket_owner.set_world(world_name, ticket_world.clone());
+			}
+		}
+		// The replicator decides again which connections every entity is relevant to,
+		// so the player is sent the entities of the new world (and forgets those of the old one),
+		// and the connections in each world start or stop seeing the player.
+		for (_entity, position) in world.query_mut::<&mut Position>() {
+			position.unacknowledge_chunk();
+		}
+		for (_entity, parent) in world.query_mut::<&mut Parent>() {
+			parent.unacknowledge_relevant_chunk();
+		}
 
-		self.database = Some(arc_database);
+		match world_name == DEFAULT_WORLD {
+			true => self.player_worlds.remove(id),
+			false => self.player_worlds.insert(id.clone(), world_name.to_owned()),
+		};
+		log::info!(target: LOG, "Moved player {} to world \"{}\"", id, world_name);
 		Ok(())
 	}
 
-	pub fn chunk_cache(&self) -> chunk::cache::ArcLock {
-		let database = self.database.as_ref().unwrap().read().unwrap();
-		database.chunk_cache().clone()
+	/// Saves every entity which has a persistent id, such as the players which are online.
+	/// Returns the number of entities which were saved.
+	pub fn save_entities(&self) -> Result<usize> {
+		let arc_world = match self.entity_world.upgrade() {
+			Some(arc) => arc,
+			None => return Ok(0),
+		};
+		let world = arc_world.read().unwrap();
+		let registry = entity::component::Registry::read();
+		self.persistent_ids.read().unwrap().save_all(
+			&world,
+			&registry,
+			&self.get_entities_dir_path(),
+		)
+	}
+
+	/// Saves every user, entity, scheduled block tick, and loaded chunk (of all worlds),
+	/// and pauses chunk loading so the savegame can be copied while the server is running.
+	/// Returns the number of chunks which were saved once it is safe to copy the savegame.
+	/// The savegame is not modified until [`resume_saving`](Self::resume_saving) is called.
+	pub fn save_all_and_pause(&self, timeout: std::time::Duration) -> Result<chunk::FlushReport> {
+		for (id, user) in self.users.iter() {
+			user.read()
+				.unwrap()
+				.save()
+				.with_context(|| format!("saving user {}", id))?;
+		}
+		let entity_count = self.save_entities().context("saving entities")?;
+		for (name, block_ticks) in self.block_ticks.iter() {
+			block_ticks
+				.read()
+				.unwrap()
+				.save()
+				.with_context(|| format!("saving block ticks of world \"{}\"", name))?;
+		}
+		let backups = self
+			.worlds
+			.values()
+			.map(|database| database.read().unwrap().chunk_backup().clone())
+			.collect::<Vec<_>>();
+		// All worlds flush their chunks at the same time, rather than one after another.
+		for backup in backups.iter() {
+			backup.request_pause();
+		}
+		let mut report = chunk::FlushReport::default();
+		for backup in backups.iter() {
+			let flushed = backup.wait_until_paused(timeout)?;
+			report.saved += flushed.saved;
+			report.failed += flushed.failed;
+		}
+		log::info!(
+			target: LOG,
+			"Saved {} users, {} entities, and {} chunks, the savegame is safe to copy",
+			self.users.len(),
+			entity_count,
+			report.saved
+		);
+		Ok(report)
+	}
+
+	/// Resumes the chunk loading of all worlds after [`save_all_and_pause`](Self::save_all_and_pause).
+	pub fn resume_saving(&self) {
+		for database in self.worlds.values() {
+			database.read().unwrap().chunk_backup().resume();
+		}
+		log::info!(target: LOG, "Resumed chunk loading");
+	}
+}
+
+impl Drop for Storage {
+	fn drop(&mut self) {
+		tick::Scheduler::clear_active();
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("no world named \"{0}\" is loaded")]
+	UnknownWorld(String),
+	#[error(
+		"the server {name} at \"{}\" could not be read ({reason}). The file may be corrupt; restore it from a backup, or delete both key files to generate a new server identity",
+		.path.display()
+	)]
+	UnreadableKey {
+		name: &'static str,
+		path: PathBuf,
+		reason: String,
+	},
+	#[error(
+		"the server {name} at \"{}\" is missing, but the other key file exists. Restore it from a backup, or delete both key files to generate a new server identity",
+		.path.display()
+	)]
+	MissingKey { name: &'static str, path: PathBuf },
+	#[error(
+		"the server private key at \"{}\" does not belong to the certificate at \"{}\" ({reason}). Restore the matching pair from a backup, or delete both key files to generate a new server identity",
+		.private_key.display(),
+		.certificate.display()
+	)]
+	MismatchedKeys {
+		certificate: PathBuf,
+		private_key: PathBuf,
+		reason: String,
+	},
+}
+
+#[cfg(test)]
+mod keys {
+	use super::*;
+	use crate::common::utility::DataFile;
+
+	fn create_root_dir() -> PathBuf {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!("crystal-sphinx-keys-{}", uuid::Uuid::new_v4()));
+		std::fs::create_dir_all(&root_dir).unwrap();
+		root_dir
+	}
+
+	#[test]
+	fn missing_keys_are_regenerated() {
+		let root_dir = create_root_dir();
+		let (certificate, _private_key) = Storage::load_keys(&root_dir).unwrap();
+		assert!(key::Certificate::make_path(&root_dir).exists());
+		assert!(key::PrivateKey::make_path(&root_dir).exists());
+
+		// The generated keys are kept, so the identity is stable from then on.
+		let (reloaded, _private_key) = Storage::load_keys(&root_dir).unwrap();
+		assert_eq!(reloaded.fingerprint(), certificate.fingerprint());
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+
+	#[test]
+	fn corrupt_key_is_an_error() {
+		let root_dir = create_root_dir();
+		Storage::generate_keys(&root_dir).unwrap();
+		let private_key_path = key::PrivateKey::make_path(&root_dir);
+		std::fs::write(&private_key_path, "not a pem file").unwrap();
+
+		let err = Storage::load_keys(&root_dir).err().unwrap();
+		match err.downcast_ref::<Error>() {
+			Some(Error::UnreadableKey { path, .. }) => assert_eq!(*path, private_key_path),
+			_ => panic!("expected an unreadable key error, found {:?}", err),
+		}
+		// The corrupt file must not be replaced, so it can still be recovered.
+		assert_eq!(
+			std::fs::read_to_string(&private_key_path).unwrap(),
+			"not a pem file"
+		);
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+
+	#[test]
+	fn single_missing_key_is_an_error() {
+		let root_dir = create_root_dir();
+		Storage::generate_keys(&root_dir).unwrap();
+		let certificate_path = key::Certificate::make_path(&root_dir);
+		std::fs::remove_file(&certificate_path).unwrap();
+
+		let err = Storage::load_keys(&root_dir).err().unwrap();
+		match err.downcast_ref::<Error>() {
+			Some(Error::MissingKey { path, .. }) => assert_eq!(*path, certificate_path),
+			_ => panic!("expected a missing key error, found {:?}", err),
+		}
+		// The remaining private key is not replaced by a new pair.
+		assert!(!certificate_path.exists());
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+
+	#[test]
+	fn mismatched_keys_are_an_error() {
+		let root_dir = create_root_dir();
+		Storage::generate_keys(&root_dir).unwrap();
+		let (_, _certificate, other_private_key) = key::create_pem().unwrap();
+		let private_key_path = key::PrivateKey::make_path(&root_dir);
+		std::fs::write(&private_key_path, &other_private_key).unwrap();
+
+		let err = Storage::load_keys(&root_dir).err().unwrap();
+		assert!(matches!(
+			err.downcast_ref::<Error>(),
+			Some(Error::MismatchedKeys { .. })
+		));
+		assert_eq!(
+			std::fs::read_to_string(&private_key_path).unwrap(),
+			other_private_key
+		);
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+}
+
+#[cfg(test)]
+mod saved_users {
+	use super::*;
+	use crate::common::{account::Account, utility::DataFile};
+
+	fn create_players_dir() -> PathBuf {
+		let mut players_dir = std::env::temp_dir();
+		players_dir.push(format!("crystal-sphinx-players-{}", uuid::Uuid::new_v4()));
+		std::fs::create_dir_all(&players_dir).unwrap();
+		players_dir
+	}
+
+	/// Saves a user the way the server does when a client joins, returning the user's directory.
+	fn save_user(players_dir: &Path, id: &str) -> PathBuf {
+		let (_, certificate, private_key) = key::create_pem().unwrap();
+		let public_key = key::Key::from_pem(&format!("{}{}", certificate, private_key))
+			.unwrap()
+			.public_key()
+			.unwrap();
+		let mut account = Account::new_public(players_dir, id.to_owned(), public_key);
+		account.set_display_name(id.to_owned());
+		let user = user::Active::new(account);
+		user.save().unwrap();
+		user.account().path().to_owned()
+	}
+
+	#[test]
+	fn missing_key_is_quarantined() {
+		let players_dir = create_players_dir();
+		let _valid = save_user(&players_dir, "valid-user");
+		let missing_key = save_user(&players_dir, "missing-key");
+		std::fs::remove_file(key::PublicKey::make_path(&missing_key)).unwrap();
+
+		let users = Storage::load_users(&players_dir).unwrap();
+		assert_eq!(users.len(), 1);
+		assert!(users.contains_key("valid-user"));
+		assert!(!missing_key.exists());
+		let quarantined = players_dir.join(CORRUPT_USERS_DIR).join("missing-key");
+		assert!(Account::make_path(&quarantined).exists());
+
+		// The quarantined user is not loaded (or moved) again.
+		let users = Storage::load_users(&players_dir).unwrap();
+		assert_eq!(users.len(), 1);
+		assert!(quarantined.exists());
+
+		let _ = std::fs::remove_dir_all(&players_dir);
+	}
+
+	#[test]
+	fn invalid_key_is_rejected() {
+		let players_dir = create_players_dir();
+		let user_dir = save_user(&players_dir, "truncated-key");
+		std::fs::write(key::PublicKey::make_path(&user_dir), "AAAA").unwrap();
+
+		let err = user::Active::load(&user_dir).err().unwrap();
+		assert!(matches!(
+			err.downcast_ref::<user::Error>(),
+			Some(user::Error::InvalidKey(_))
+		));
+
+		let _ = std::fs::remove_dir_all(&players_dir);
 	}
 }
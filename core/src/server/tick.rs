@@ -0,0 +1,13 @@
+//! Fixed-rate server ticks and the scheduling of gameplay actions against them.
+
+mod scheduler;
+pub use scheduler::*;
+
+mod system;
+pub use system::*;
+
+mod timestep;
+pub use timestep::*;
+
+/// The number of ticks which have elapsed since the server started ticking.
+pub type Tick = u64;
@@ -0,0 +1,188 @@
+use super::Tick;
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, RwLock, Weak},
+};
+
+/// Alias for Arc<RwLock<[`Scheduler`](Scheduler)>>.
+pub type ArcLockScheduler = Arc<RwLock<Scheduler>>;
+
+/// An action to perform on the server when a specific tick is reached.
+/// The action is provided the tick it is being executed on.
+pub type Action = Box<dyn FnOnce(Tick) + Send + Sync>;
+
+/// A tick-indexed queue of gameplay [`actions`](Action) (crop growth, cooldowns, etc).
+///
+/// Actions are not deduplicated; any number of actions can be scheduled for the same tick,
+/// and they will be executed in the order they were scheduled.
+pub struct Scheduler {
+	current_tick: Tick,
+	pending: BTreeMap<Tick, Vec<Action>>,
+}
+
+impl Default for Scheduler {
+	fn default() -> Self {
+		Self {
+			current_tick: 0,
+			pending: BTreeMap::new(),
+		}
+	}
+}
+
+impl Scheduler {
+	pub fn arclocked(self) -> ArcLockScheduler {
+		Arc::new(RwLock::new(self))
+	}
+
+	fn active_static() -> &'static mut Option<Weak<RwLock<Scheduler>>> {
+		static mut ACTIVE: Option<Weak<RwLock<Scheduler>>> = None;
+		unsafe { &mut ACTIVE }
+	}
+
+	/// Marks the scheduler as the one being ticked by the server,
+	/// so that systems and plugins without access to server storage can schedule actions.
+	pub(crate) fn set_active(scheduler: &ArcLockScheduler) {
+		*Self::active_static() = Some(Arc::downgrade(&scheduler));
+	}
+
+	pub(crate) fn clear_active() {
+		*Self::active_static() = None;
+	}
+
+	/// Returns the scheduler of the currently running server, if there is one.
+	pub fn active() -> Option<ArcLockScheduler> {
+		Self::active_static()
+			.as_ref()
+			.map(|weak| weak.upgrade())
+			.flatten()
+	}
+}
+
+impl Scheduler {
+	/// The most recent tick which has been executed.
+	pub fn current_tick(&self) -> Tick {
+		self.current_tick
+	}
+
+	/// The number of actions which have not yet been executed.
+	pub fn pending_count(&self) -> usize {
+		self.pending.values().map(|actions| actions.len()).sum()
+	}
+
+	/// Schedules an action to execute `ticks` after the current tick.
+	/// A delay of 0 is treated as 1 (the action executes on the next tick).
+	pub fn schedule_after<F>(&mut self, ticks: Tick, action: F)
+	where
+		F: FnOnce(Tick) + Send + Sync + 'static,
+	{
+		self.schedule_at(self.current_tick + ticks.max(1), action);
+	}
+
+	/// Schedules an action to execute on a specific tick.
+	/// If the tick has already been executed, the action executes on the next tick.
+	pub fn schedule_at<F>(&mut self, tick: Tick, action: F)
+	where
+		F: FnOnce(Tick) + Send + Sync + 'static,
+	{
+		let tick = tick.max(self.current_tick + 1);
+		self.pending
+			.entry(tick)
+			.or_insert_with(Vec::new)
+			.push(Box::new(action));
+	}
+
+	/// Advances the scheduler by one tick, returning the new tick and the actions which are due on it.
+	///
+	/// The actions are returned instead of being executed so that the caller can
+	/// release any lock on the scheduler before running them (actions can schedule other actions).
+	pub fn advance(&mut self) -> (Tick, Vec<Action>) {
+		self.current_tick += 1;
+		let still_pending = self.pending.split_off(&(self.current_tick + 1));
+		let due = std::mem::replace(&mut self.pending, still_pending);
+		let actions = due.into_values().flatten().collect();
+		(self.current_tick, actions)
+	}
+
+	/// Advances the scheduler by one tick and executes all actions due on that tick.
+	pub fn tick(scheduler: &ArcLockScheduler) -> Tick {
+		let (tick, actions) = scheduler.write().unwrap().advance();
+		for action in actions.into_iter() {
+			action(tick);
+		}
+		tick
+	}
+}
+
+#[cfg(test)]
+mod scheduler {
+	use super::*;
+	use std::sync::Mutex;
+
+	#[test]
+	fn fires_exactly_after_delay() {
+		let scheduler = Scheduler::default().arclocked();
+		let fired_on = Arc::new(Mutex::new(Vec::new()));
+
+		let start = scheduler.read().unwrap().current_tick();
+		{
+			let fired_on = fired_on.clone();
+			scheduler.write().unwrap().schedule_after(3, move |tick| {
+				fired_on.lock().unwrap().push(tick);
+			});
+		}
+
+		for _ in 0..2 {
+			Scheduler::tick(&scheduler);
+			assert!(fired_on.lock().unwrap().is_empty());
+		}
+		assert_eq!(Scheduler::tick(&scheduler), start + 3);
+		assert_eq!(*fired_on.lock().unwrap(), vec![start + 3]);
+
+		for _ in 0..3 {
+			Scheduler::tick(&scheduler);
+		}
+		assert_eq!(*fired_on.lock().unwrap(), vec![start + 3]);
+		assert_eq!(scheduler.read().unwrap().pending_count(), 0);
+	}
+
+	#[test]
+	fn fires_all_on_same_tick() {
+		let scheduler = Scheduler::default().arclocked();
+		let fired = Arc::new(Mutex::new(Vec::new()));
+
+		for i in 0..100 {
+			let fired = fired.clone();
+			scheduler.write().unwrap().schedule_at(5, move |tick| {
+				fired.lock().unwrap().push((i, tick));
+			});
+		}
+		assert_eq!(scheduler.read().unwrap().pending_count(), 100);
+
+		for _ in 0..4 {
+			Scheduler::tick(&scheduler);
+		}
+		assert!(fired.lock().unwrap().is_empty());
+
+		Scheduler::tick(&scheduler);
+		let expected = (0..100).map(|i| (i, 5)).collect::<Vec<_>>();
+		assert_eq!(*fired.lock().unwrap(), expected);
+		assert_eq!(scheduler.read().unwrap().pending_count(), 0);
+	}
+
+	#[test]
+	fn past_ticks_fire_next() {
+		let scheduler = Scheduler::default().arclocked();
+		for _ in 0..10 {
+			Scheduler::tick(&scheduler);
+		}
+		let fired_on = Arc::new(Mutex::new(None));
+		{
+			let fired_on = fired_on.clone();
+			scheduler.write().unwrap().schedule_at(2, move |tick| {
+				*fired_on.lock().unwrap() = Some(tick);
+			});
+		}
+		Scheduler::tick(&scheduler);
+		assert_eq!(*fired_on.lock().unwrap(), Some(11));
+	}
+}
@@ -0,0 +1,52 @@
+use super::{ArcLockScheduler, FixedTimestep, Scheduler};
+use engine::EngineSystem;
+use std::time::Duration;
+
+static LOG: &'static str = "subsystem:server-tick";
+
+/// The number of ticks the server runs per second, if not otherwise configured.
+pub const DEFAULT_TICKS_PER_SECOND: u32 = 20;
+
+/// The number of ticks the simulation runs per second,
+/// as configured by the `-tick_rate=` argument (or [`DEFAULT_TICKS_PER_SECOND`]).
+pub fn ticks_per_second() -> u32 {
+	crate::common::utility::get_named_arg("tick_rate")
+		.map(|rate| rate as u32)
+		.unwrap_or(DEFAULT_TICKS_PER_SECOND)
+}
+
+/// System run on (integrated or dedicated) servers which converts the
+/// variable frame time of the engine into fixed-length server ticks,
+/// executing the [`scheduled actions`](Scheduler) for each tick.
+pub struct TickLoop {
+	scheduler: ArcLockScheduler,
+	timestep: FixedTimestep,
+}
+
+impl TickLoop {
+	pub fn new(scheduler: ArcLockScheduler) -> Self {
+		Self {
+			scheduler,
+			timestep: FixedTimestep::new(DEFAULT_TICKS_PER_SECOND),
+		}
+	}
+
+	pub fn with_ticks_per_second(mut self, ticks_per_second: u32) -> Self {
+		self.timestep = FixedTimestep::new(ticks_per_second);
+		self
+	}
+
+	pub fn tick_duration(&self) -> &Duration {
+		self.timestep.step()
+	}
+}
+
+impl EngineSystem for TickLoop {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!(LOG);
+		for _ in 0..self.timestep.advance(delta_time) {
+			let tick = Scheduler::tick(&self.scheduler);
+			log::trace!(target: LOG, "Executed tick {}", tick);
+		}
+	}
+}
@@ -0,0 +1,132 @@
+use engine::EngineSystem;
+use std::{
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+
+/// Converts the variable frame time of the engine into a number of fixed-length steps.
+///
+/// Time which does not make up a full step carries over to the next frame,
+/// so the number of steps over any period only depends on the elapsed time (not the frame rate).
+pub struct FixedTimestep {
+	step: Duration,
+	accumulated: Duration,
+	/// The most steps which can be taken in one frame.
+	/// If the simulation falls further behind than this (e.g. the application was suspended),
+	/// the extra time is dropped instead of trying to catch up all at once.
+	max_steps: u32,
+}
+
+impl FixedTimestep {
+	pub fn new(steps_per_second: u32) -> Self {
+		Self {
+			step: Duration::from_secs(1) / steps_per_second.max(1),
+			accumulated: Duration::ZERO,
+			max_steps: steps_per_second.max(1) * 5,
+		}
+	}
+
+	pub fn step(&self) -> &Duration {
+		&self.step
+	}
+
+	/// Accumulates the elapsed time of a frame, returning the number of steps which should be taken.
+	pub fn advance(&mut self, delta_time: Duration) -> u32 {
+		self.accumulated += delta_time;
+		let mut steps = 0;
+		while self.accumulated >= self.step {
+			self.accumulated -= self.step;
+			steps += 1;
+			if steps >= self.max_steps {
+				self.accumulated = Duration::ZERO;
+				break;
+			}
+		}
+		steps
+	}
+}
+
+/// Wraps a system so that it is updated at a fixed rate, independent of the frame rate of the engine.
+/// Each update of the wrapped system is provided the fixed [`step`](FixedTimestep::step) as its delta time.
+pub struct FixedRate<T: EngineSystem> {
+	system: T,
+	timestep: FixedTimestep,
+}
+
+impl<T> FixedRate<T>
+where
+	T: EngineSystem,
+{
+	pub fn new(system: T, updates_per_second: u32) -> Self {
+		Self {
+			system,
+			timestep: FixedTimestep::new(updates_per_second),
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+}
+
+impl<T> EngineSystem for FixedRate<T>
+where
+	T: EngineSystem,
+{
+	fn update(&mut self, delta_time: Duration, has_focus: bool) {
+		let step = *self.timestep.step();
+		for _ in 0..self.timestep.advance(delta_time) {
+			self.system.update(step, has_focus);
+		}
+	}
+}
+
+#[cfg(test)]
+mod fixed_timestep {
+	use super::*;
+
+	#[test]
+	fn steps_are_independent_of_frame_rate() {
+		for frames_per_second in [30, 60, 144].iter() {
+			let mut timestep = FixedTimestep::new(20);
+			let frame = Duration::from_secs(1) / *frames_per_second;
+			let frame_count = frames_per_second * 3;
+			let steps: u32 = (0..frame_count).map(|_| timestep.advance(frame)).sum();
+			// Frame durations are rounded to whole nanoseconds,
+			// so the expected count is based on the actual elapsed time (just under 3 seconds).
+			let elapsed = frame * frame_count;
+			let expected = (elapsed.as_nanos() / timestep.step().as_nanos()) as u32;
+			assert_eq!(steps, expected, "at {}fps", frames_per_second);
+			assert_eq!(expected, 59);
+		}
+	}
+
+	#[test]
+	fn partial_steps_carry_over() {
+		let mut timestep = FixedTimestep::new(20);
+		assert_eq!(timestep.advance(Duration::from_millis(30)), 0);
+		assert_eq!(timestep.advance(Duration::from_millis(30)), 1);
+		assert_eq!(timestep.advance(Duration::from_millis(90)), 2);
+	}
+
+	#[test]
+	fn long_frames_are_clamped() {
+		let mut timestep = FixedTimestep::new(20);
+		assert_eq!(timestep.advance(Duration::from_secs(60)), 100);
+		assert_eq!(timestep.advance(Duration::ZERO), 0);
+	}
+
+	struct Counter(Vec<Duration>);
+	impl EngineSystem for Counter {
+		fn update(&mut self, delta_time: Duration, _: bool) {
+			self.0.push(delta_time);
+		}
+	}
+
+	#[test]
+	fn fixed_rate_system() {
+		let mut system = FixedRate::new(Counter(Vec::new()), 20);
+		system.update(Duration::from_millis(120), true);
+		assert_eq!(system.system.0, vec![Duration::from_millis(50); 2]);
+	}
+}
@@ -1,6 +1,12 @@
-use crate::{common::account::Account, common::utility::DataFile};
+use crate::{
+	common::account::{
+		key::{Certificate, Key, PrivateKey, PublicKey},
+		Account,
+	},
+	common::utility::DataFile,
+};
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub struct Active {
 	account: Account,
@@ -11,9 +17,42 @@ impl Active {
 		Self { account }
 	}
 
+	/// Loads the user saved in `dir`, whose name is the id of the user's account.
+	///
+	/// The directory is validated before the user is accepted:
+	/// its metadata and key files must exist and parse, the key must be usable,
+	/// and (if the user has a certificate) the certificate's fingerprint must be the directory's name.
+	/// If the directory is irrecoverable (e.g. it was only partially written when the server stopped)
+	/// an [`Error`] is returned, as opposed to other errors (like not being allowed to read the files)
+	/// which may not happen the next time the user is loaded.
 	#[profiling::function]
 	pub fn load(dir: &Path) -> Result<Self> {
-		let account = Account::load(&dir)?;
+		let id = match dir.file_name().and_then(|name| name.to_str()) {
+			Some(id) => id.to_owned(),
+			None => return Err(Error::InvalidId(dir.to_owned()))?,
+		};
+		let meta_path = Account::make_path(&dir);
+		if !meta_path.exists() {
+			return Err(Error::MissingFile(meta_path))?;
+		}
+		let has_public_key = PublicKey::make_path(&dir).exists();
+		let has_private_key =
+			Certificate::make_path(&dir).exists() && PrivateKey::make_path(&dir).exists();
+		if !has_public_key && !has_private_key {
+			return Err(Error::MissingFile(PublicKey::make_path(&dir)))?;
+		}
+
+		let account = Account::load(&dir).map_err(Error::classify)?;
+		account
+			.key()
+			.validate()
+			.map_err(|err| Error::InvalidKey(err.to_string()))?;
+		if let Key::Private(certificate, _) = account.key() {
+			let fingerprint = certificate.fingerprint();
+			if fingerprint != id {
+				return Err(Error::MismatchedId { id, fingerprint })?;
+			}
+		}
 		Ok(Self { account })
 	}
 
@@ -31,3 +70,30 @@ impl Active {
 		&mut self.account
 	}
 }
+
+/// Why a saved user could not be loaded, when loading it again would fail the same way.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("directory \"{}\" is not a valid account id", .0.display())]
+	InvalidId(PathBuf),
+	#[error("required file \"{}\" is missing", .0.display())]
+	MissingFile(PathBuf),
+	#[error("user data could not be parsed: {0}")]
+	Unreadable(String),
+	#[error("key is not usable: {0}")]
+	InvalidKey(String),
+	#[error(
+		"directory is named for account {id}, but its certificate belongs to account {fingerprint}"
+	)]
+	MismatchedId { id: String, fingerprint: String },
+}
+
+impl Error {
+	/// Treats errors which are not from the filesystem (or are because a file is missing) as the data being unreadable.
+	fn classify(err: anyhow::Error) -> anyhow::Error {
+		match err.downcast_ref::<std::io::Error>() {
+			Some(io_err) if io_err.kind() != std::io::ErrorKind::NotFound => err,
+			_ => Self::Unreadable(err.to_string()).into(),
+		}
+	}
+}
@@ -1,7 +1,16 @@
+pub mod block_ticks;
 pub mod chunk;
+mod clock;
+pub use clock::*;
+pub mod edit;
+pub mod place;
+pub mod registry;
+pub use registry::Registry;
 
 mod database;
 pub use database::*;
 
 mod settings;
 pub use settings::*;
+
+pub mod spawn;
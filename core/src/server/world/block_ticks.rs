@@ -0,0 +1,639 @@
+//! Periodic (random) and scheduled updates of individual blocks, for behaviors like crops growing or fluids spreading.
+//!
+//! Block types opt in by registering a [`Handler`] for their [`lookup id`](block::LookupId),
+//! or by being [`registered by a plugin`](plugin::Plugin::register_block_tick_handlers) for their asset id.
+//! Each tick, a number of random blocks in every [`ticking`](Level::Ticking) chunk are given a random tick,
+//! and any ticks scheduled for specific points (e.g. by a handler, to continue spreading later) are fired.
+
+use crate::{
+	block,
+	common::world::chunk as common_chunk,
+	plugin,
+	server::{
+		tick::{FixedTimestep, Tick},
+		world::chunk::{self, Chunk, Level},
+	},
+};
+use anyhow::Result;
+use engine::{asset, math::nalgebra::Point3, EngineSystem};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+};
+
+static LOG: &'static str = "subsystem:block-ticks";
+
+/// The number of blocks in each ticking chunk which are given a random tick every tick, if not otherwise configured.
+pub static DEFAULT_RANDOM_TICKS_PER_CHUNK: usize = 3;
+
+/// The most scheduled ticks which can wait for their chunks to load at once.
+/// Ticks deferred beyond this are dropped, so a region which is never loaded again cannot grow the backlog forever.
+pub static MAX_DEFERRED_TICKS: usize = 65_536;
+
+/// Returns the offset of a point within its chunk.
+pub fn offset_in_chunk(point: &block::Point) -> Point3<usize> {
+	let offset = point.offset();
+	Point3::new(offset.x as usize, offset.y as usize, offset.z as usize)
+}
+
+/// Implemented for a block type to react to the ticks of blocks of that type.
+pub trait Handler: Send + Sync {
+	/// Called when a block of the type is chosen for a random tick.
+	fn on_random_tick(&self, _ctx: &mut Context) {}
+
+	/// Called when a tick which was scheduled for the block's point fires,
+	/// if the block at that point is (still) of the type.
+	fn on_scheduled_tick(&self, _ctx: &mut Context) {}
+}
+
+/// The tick handlers which [`plugins`](plugin::Plugin::register_block_tick_handlers) register for block types, by asset id.
+#[derive(Default)]
+pub struct HandlerRegistry(HashMap<asset::Id, Arc<dyn Handler>>);
+
+impl HandlerRegistry {
+	/// Sets the handler for blocks of a type, replacing any handler previously registered for it.
+	pub fn insert<T>(&mut self, block: asset::Id, handler: T)
+	where
+		T: Handler + 'static,
+	{
+		self.0.insert(block, Arc::new(handler));
+	}
+}
+
+/// What a [`Handler`] is given when a block is ticked.
+pub struct Context<'a> {
+	tick: Tick,
+	point: block::Point,
+	chunk: &'a mut Chunk,
+	plugins: &'a plugin::Manager,
+	scheduled: Vec<(Tick, block::Point)>,
+}
+
+impl<'a> Context<'a> {
+	/// The tick the block is being ticked on.
+	pub fn tick(&self) -> Tick {
+		self.tick
+	}
+
+	/// The point of the block being ticked.
+	pub fn point(&self) -> &block::Point {
+		&self.point
+	}
+
+	/// Returns the block at an offset in the chunk of the block being ticked.
+	pub fn block_id(&self, offset: &Point3<usize>) -> Option<block::LookupId> {
+		self.chunk.chunk.block_ids().get(offset).cloned()
+	}
+
+	/// Places (or breaks, if `id` is None) a block in the chunk of the block being ticked.
+	/// The change is [`validated`](Chunk::apply_block_change) by plugins like any other change the server makes.
+	pub fn set_block_id(
+		&mut self,
+		offset: Point3<usize>,
+		id: Option<block::LookupId>,
+	) -> Result<Option<block::LookupId>, plugin::BlockChangeDenied> {
+		self.chunk
+			.apply_block_change(self.plugins, None, offset, id)
+	}
+
+	/// Schedules a tick for a point, `ticks` after the current tick (at least 1).
+	pub fn schedule_after(&mut self, point: block::Point, ticks: Tick) {
+		self.scheduled.push((self.tick + ticks.max(1), point));
+	}
+}
+
+/// Server system which gives blocks random and scheduled ticks, at the server's tick rate.
+///
+/// Ticks which are scheduled for a chunk that is not loaded when they are due
+/// are kept until the chunk is loaded again, and fire on the first tick it is.
+/// Ticks which have not fired are [`saved`](Self::save) in the world directory (if it has one),
+/// and scheduled again when the world is next loaded.
+pub struct BlockTicks {
+	chunk_cache: chunk::cache::WeakLock,
+	/// The directory of the world, which the ticks that have not fired are saved to.
+	root_dir: Option<PathBuf>,
+	handlers: HashMap<block::LookupId, Arc<dyn Handler>>,
+	/// The handlers registered by plugins, gathered again whenever the plugins are reloaded.
+	plugin_handlers: HashMap<block::LookupId, Arc<dyn Handler>>,
+	/// The [`revision`](plugin::Manager::revision) the plugin handlers were gathered from.
+	plugin_revision: Option<usize>,
+	random_ticks_per_chunk: usize,
+	rng: StdRng,
+	timestep: FixedTimestep,
+	current_tick: Tick,
+	scheduled: BTreeMap<Tick, Vec<block::Point>>,
+	/// Scheduled ticks which were due while their chunk was not loaded, by chunk coordinate.
+	/// Each point is only deferred once, no matter how many ticks were scheduled for it.
+	deferred: HashMap<Point3<i64>, Vec<block::Point>>,
+	deferred_count: usize,
+}
+
+impl BlockTicks {
+	pub fn new(chunk_cache: &chunk::cache::ArcLock) -> Self {
+		Self {
+			chunk_cache: Arc::downgrade(&chunk_cache),
+			root_dir: None,
+			handlers: HashMap::new(),
+			plugin_handlers: HashMap::new(),
+			plugin_revision: None,
+			random_ticks_per_chunk: DEFAULT_RANDOM_TICKS_PER_CHUNK,
+			rng: StdRng::from_entropy(),
+			timestep: FixedTimestep::new(crate::server::tick::ticks_per_second()),
+			current_tick: 0,
+			scheduled: BTreeMap::new(),
+			deferred: HashMap::new(),
+			deferred_count: 0,
+		}
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+
+	pub fn with_random_ticks_per_chunk(mut self, count: usize) -> Self {
+		self.random_ticks_per_chunk = count;
+		self
+	}
+
+	/// Saves the ticks which have not fired to the world directory when the system is dropped,
+	/// and schedules the ticks saved by the previous session.
+	pub fn with_root_dir(mut self, root_dir: PathBuf) -> Self {
+		match SavedTicks::load(&root_dir) {
+			Ok(SavedTicks(saved)) => {
+				for tick in saved.into_iter() {
+					self.schedule_after(tick.point, tick.delay);
+				}
+			}
+			Err(err) => log::error!(target: LOG, "Failed to load scheduled block ticks: {:?}", err),
+		}
+		self.root_dir = Some(root_dir);
+		self
+	}
+
+	/// Makes the choice of blocks for random ticks deterministic.
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.rng = StdRng::seed_from_u64(seed);
+		self
+	}
+
+	/// Sets the handler for blocks of a type, replacing any handler previously registered for it.
+	pub fn register<T>(&mut self, id: block::LookupId, handler: T)
+	where
+		T: Handler + 'static,
+	{
+		self.handlers.insert(id, Arc::new(handler));
+	}
+
+	/// The most recent tick which has been executed.
+	pub fn current_tick(&self) -> Tick {
+		self.current_tick
+	}
+
+	/// Schedules a tick for the block at a point on a specific tick.
+	/// If the tick has already been executed, the block is ticked on the next tick.
+	pub fn schedule_at(&mut self, point: block::Point, tick: Tick) {
+		let tick = tick.max(self.current_tick + 1);
+		self.scheduled
+			.entry(tick)
+			.or_insert_with(Vec::new)
+			.push(point);
+	}
+
+	/// Schedules a tick for the block at a point, `ticks` after the current tick (at least 1).
+	pub fn schedule_after(&mut self, point: block::Point, ticks: Tick) {
+		self.schedule_at(point, self.current_tick + ticks.max(1));
+	}
+
+	/// The number of scheduled ticks which have not fired, including those waiting for their chunk to load.
+	pub fn pending_count(&self) -> usize {
+		let scheduled = self
+			.scheduled
+			.values()
+			.map(|points| points.len())
+			.sum::<usize>();
+		scheduled + self.deferred_count
+	}
+
+	/// Saves the ticks which have not fired to the world directory, relative to the current tick,
+	/// so they fire after the same delay when the world is next loaded.
+	/// Ticks which are waiting for their chunk to load fire on the first tick after it loads again.
+	pub fn save(&self) -> Result<()> {
+		let root_dir = match &self.root_dir {
+			Some(root_dir) => root_dir,
+			None => return Ok(()),
+		};
+		let scheduled = self.scheduled.iter().flat_map(|(tick, points)| {
+			let delay = tick - self.current_tick;
+			points.iter().map(move |point| SavedTick {
+				point: *point,
+				delay,
+			})
+		});
+		let deferred = self.deferred.values().flatten().map(|point| SavedTick {
+			point: *point,
+			delay: 0,
+		});
+		SavedTicks(deferred.chain(scheduled).collect()).save(root_dir)
+	}
+
+	/// Keeps ticks which are due for a chunk that is not loaded until the chunk is loaded again.
+	fn defer(&mut self, coordinate: Point3<i64>, points: Vec<block::Point>) {
+		let deferred = self.deferred.entry(coordinate).or_insert_with(Vec::new);
+		let mut dropped = 0;
+		for point in points.into_iter() {
+			if deferred.contains(&point) {
+				continue;
+			}
+			if self.deferred_count >= MAX_DEFERRED_TICKS {
+				dropped += 1;
+				continue;
+			}
+			deferred.push(point);
+			self.deferred_count += 1;
+		}
+		if deferred.is_empty() {
+			self.deferred.remove(&coordinate);
+		}
+		if dropped > 0 {
+			log::warn!(
+				target: LOG,
+				"Dropped {} ticks for chunk {}, there are already {} ticks waiting for their chunks to load",
+				dropped,
+				coordinate,
+				MAX_DEFERRED_TICKS
+			);
+		}
+	}
+
+	/// Gathers the handlers registered by plugins, if the plugins have been reloaded since they were last gathered.
+	/// Handlers cannot be gathered until the blocks have been loaded, which assigns the ids they are registered for.
+	fn gather_plugin_handlers(&mut self, plugins: &plugin::Manager) {
+		if self.plugin_revision == Some(plugins.revision()) || block::Lookup::get().is_none() {
+			return;
+		}
+		let mut registry = HandlerRegistry::default();
+		plugins.register_block_tick_handlers(&mut registry);
+		self.plugin_handlers.clear();
+		for (id, handler) in registry.0.into_iter() {
+			match block::Lookup::lookup_value(&id) {
+				Some(value) => {
+					self.plugin_handlers.insert(value, handler);
+				}
+				None => log::warn!(target: LOG, "Ignoring tick handler for unknown block {}", id),
+			}
+		}
+		self.plugin_revision = Some(plugins.revision());
+	}
+
+	/// Executes the next tick, firing the scheduled ticks which are due and then the random ticks of every ticking chunk.
+	pub fn advance(&mut self, cache: &chunk::cache::Cache, plugins: &plugin::Manager) -> Tick {
+		profiling::scope!("advance", &format!("tick={}", self.current_tick + 1));
+		self.current_tick += 1;
+		let tick = self.current_tick;
+		self.gather_plugin_handlers(plugins);
+
+		let still_scheduled = self.scheduled.split_off(&(tick + 1));
+		let due = std::mem::replace(&mut self.scheduled, still_scheduled);
+		let mut by_chunk: BTreeMap<(i64, i64, i64), Vec<block::Point>> = BTreeMap::new();
+		for point in due.into_values().flatten() {
+			let chunk = point.chunk();
+			by_chunk
+				.entry((chunk.x, chunk.y, chunk.z))
+				.or_insert_with(Vec::new)
+				.push(point);
+		}
+		// Ticks deferred while their chunk was unloaded fire before those which are due now.
+		let reloaded = self
+			.deferred
+			.keys()
+			.filter(|coordinate| cache.find(coordinate).is_some())
+			.cloned()
+			.collect::<Vec<_>>();
+		for coordinate in reloaded.into_iter() {
+			let mut points = self.deferred.remove(&coordinate).unwrap();
+			self.deferred_count -= points.len();
+			let entry = by_chunk
+				.entry((coordinate.x, coordinate.y, coordinate.z))
+				.or_insert_with(Vec::new);
+			points.append(entry);
+			*entry = points;
+		}
+
+		let mut scheduled = Vec::new();
+		for ((x, y, z), points) in by_chunk.into_iter() {
+			let coordinate = Point3::new(x, y, z);
+			match cache.find(&coordinate).and_then(|weak| weak.upgrade()) {
+				Some(arc_chunk) => {
+					let mut chunk = arc_chunk.write().unwrap();
+					for point in points.into_iter() {
+						let mut ctx = Context {
+							tick,
+							point,
+							chunk: &mut chunk,
+							plugins,
+							scheduled: Vec::new(),
+						};
+						self.fire(&mut ctx, |handler, ctx| handler.on_scheduled_tick(ctx));
+						scheduled.append(&mut ctx.scheduled);
+					}
+				}
+				None => self.defer(coordinate, points),
+			}
+		}
+
+		let has_handlers = !self.handlers.is_empty() || !self.plugin_handlers.is_empty();
+		if self.random_ticks_per_chunk > 0 && has_handlers {
+			for weak_chunk in cache.iter().map(|(_, weak_chunk)| weak_chunk) {
+				let arc_chunk = match weak_chunk.upgrade() {
+					Some(arc) => arc,
+					None => continue,
+				};
+				let mut chunk = arc_chunk.write().unwrap();
+				if chunk.level != Level::Ticking {
+					continue;
+				}
+				let coordinate = *chunk.chunk.coordinate();
+				for _ in 0..self.random_ticks_per_chunk {
+					let offset =
+						common_chunk::index_offset(self.rng.gen_range(0..common_chunk::VOLUME));
+					let offset = Point3::new(offset.x as i8, offset.y as i8, offset.z as i8);
+					let point = block::Point::new(coordinate, offset);
+					let mut ctx = Context {
+						tick,
+						point,
+						chunk: &mut chunk,
+						plugins,
+						scheduled: Vec::new(),
+					};
+					self.fire(&mut ctx, |handler, ctx| handler.on_random_tick(ctx));
+					scheduled.append(&mut ctx.scheduled);
+				}
+			}
+		}
+
+		for (tick, point) in scheduled.into_iter() {
+			self.schedule_at(point, tick);
+		}
+		tick
+	}
+
+	/// Calls `callback` with the handler for the type of the block being ticked, if the type has one.
+	/// Handlers [`registered`](Self::register) directly take precedence over those registered by plugins.
+	fn fire<F>(&self, ctx: &mut Context, callback: F)
+	where
+		F: FnOnce(&dyn Handler, &mut Context),
+	{
+		let offset = offset_in_chunk(&ctx.point);
+		let handler = ctx.block_id(&offset).and_then(|id| {
+			self.handlers
+				.get(&id)
+				.or_else(|| self.plugin_handlers.get(&id))
+		});
+		if let Some(handler) = handler {
+			callback(handler.as_ref(), ctx);
+		}
+	}
+}
+
+impl EngineSystem for BlockTicks {
+	fn update(&mut self, delta_time: std::time::Duration, _: bool) {
+		profiling::scope!(LOG);
+		let steps = self.timestep.advance(delta_time);
+		for _ in 0..steps {
+			let arc_cache = match self.chunk_cache.upgrade() {
+				Some(arc) => arc,
+				None => return,
+			};
+			// The cache is only locked for one tick at a time,
+			// so the chunk thread can insert the chunks it loads between ticks when catching up.
+			let cache = arc_cache.read().unwrap();
+			let plugins = match plugin::Manager::read() {
+				Ok(plugins) => plugins,
+				Err(_) => return,
+			};
+			self.advance(&cache, &plugins);
+		}
+	}
+}
+
+impl Drop for BlockTicks {
+	fn drop(&mut self) {
+		if let Err(err) = self.save() {
+			log::error!(target: LOG, "Failed to save scheduled block ticks: {:?}", err);
+		}
+	}
+}
+
+/// A scheduled tick which had not fired when the world was saved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct SavedTick {
+	point: block::Point,
+	/// How many ticks after the world is loaded the tick fires.
+	/// Ticks which were waiting for their chunk to load have no delay, and fire on the first tick.
+	delay: Tick,
+}
+
+/// The ticks saved alongside the world.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct SavedTicks(Vec<SavedTick>);
+
+impl SavedTicks {
+	fn create_path(mut world_root_dir: PathBuf) -> PathBuf {
+		world_root_dir.push("block_ticks.json");
+		world_root_dir
+	}
+
+	/// Loads the ticks saved in the world directory.
+	/// If the world has never saved its ticks, there are none.
+	fn load(world_root_dir: &Path) -> Result<Self> {
+		let path = Self::create_path(world_root_dir.to_owned());
+		if !path.exists() {
+			return Ok(Self::default());
+		}
+		let raw = std::fs::read_to_string(&path)?;
+		Ok(serde_json::from_str(&raw)?)
+	}
+
+	fn save(&self, world_root_dir: &Path) -> Result<()> {
+		std::fs::create_dir_all(&world_root_dir)?;
+		let json = serde_json::to_string_pretty(&self)?;
+		std::fs::write(&Self::create_path(world_root_dir.to_owned()), json)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod ticks {
+	use super::*;
+	use crate::common::world::chunk::Chunk as CommonChunk;
+	use std::sync::Mutex;
+
+	static GRASS: block::LookupId = 4;
+	static DIRT: block::LookupId = 2;
+
+	/// Records every tick it is given, turning the block into dirt on scheduled ticks.
+	#[derive(Default, Clone)]
+	struct Recorder {
+		random: Arc<Mutex<Vec<(Tick, block::Point)>>>,
+		scheduled: Arc<Mutex<Vec<(Tick, block::Point)>>>,
+	}
+
+	impl Handler for Recorder {
+		fn on_random_tick(&self, ctx: &mut Context) {
+			self.random.lock().unwrap().push((ctx.tick(), *ctx.point()));
+		}
+
+		fn on_scheduled_tick(&self, ctx: &mut Context) {
+			self.scheduled
+				.lock()
+				.unwrap()
+				.push((ctx.tick(), *ctx.point()));
+			let offset = offset_in_chunk(ctx.point());
+			ctx.set_block_id(offset, Some(DIRT)).unwrap();
+		}
+	}
+
+	fn grass_chunk(coordinate: Point3<i64>, level: Level) -> chunk::ArcLock {
+		let mut chunk = CommonChunk::new(coordinate);
+		for index in 0..common_chunk::VOLUME {
+			chunk.set_block_id(common_chunk::index_offset(index), Some(GRASS));
+		}
+		Arc::new(RwLock::new(Chunk::new(
+			std::path::PathBuf::new(),
+			chunk,
+			level,
+		)))
+	}
+
+	fn setup() -> (BlockTicks, chunk::cache::ArcLock, Recorder) {
+		let cache = Arc::new(RwLock::new(chunk::cache::Cache::new()));
+		let recorder = Recorder::default();
+		let mut ticks = BlockTicks::new(&cache).with_seed(0);
+		ticks.register(GRASS, recorder.clone());
+		(ticks, cache, recorder)
+	}
+
+	#[test]
+	fn scheduled_tick_fires_on_its_tick() {
+		let (ticks, cache, recorder) = setup();
+		let mut ticks = ticks.with_random_ticks_per_chunk(0);
+		let coordinate = Point3::new(0, 0, 0);
+		let arc_chunk = grass_chunk(coordinate, Level::Ticking);
+		cache
+			.write()
+			.unwrap()
+			.insert(coordinate, Arc::downgrade(&arc_chunk));
+		let plugins = plugin::Manager::default();
+
+		let point = block::Point::new(coordinate, Point3::new(1, 2, 3));
+		ticks.schedule_after(point, 3);
+		for _ in 0..2 {
+			ticks.advance(&cache.read().unwrap(), &plugins);
+		}
+		assert!(recorder.scheduled.lock().unwrap().is_empty());
+		assert_eq!(ticks.advance(&cache.read().unwrap(), &plugins), 3);
+		assert_eq!(*recorder.scheduled.lock().unwrap(), vec![(3, point)]);
+		assert_eq!(
+			arc_chunk
+				.read()
+				.unwrap()
+				.chunk
+				.block_ids()
+				.get(&Point3::new(1, 2, 3)),
+			Some(&DIRT)
+		);
+		assert_eq!(ticks.pending_count(), 0);
+	}
+
+	#[test]
+	fn scheduled_tick_waits_for_chunk_to_load() {
+		let (mut ticks, cache, recorder) = setup();
+		let plugins = plugin::Manager::default();
+		let coordinate = Point3::new(2, 0, 0);
+		let point = block::Point::new(coordinate, Point3::new(0, 0, 0));
+		ticks.schedule_after(point, 1);
+
+		ticks.advance(&cache.read().unwrap(), &plugins);
+		assert!(recorder.scheduled.lock().unwrap().is_empty());
+		assert_eq!(ticks.pending_count(), 1);
+
+		let arc_chunk = grass_chunk(coordinate, Level::Loaded);
+		cache
+			.write()
+			.unwrap()
+			.insert(coordinate, Arc::downgrade(&arc_chunk));
+		assert_eq!(ticks.advance(&cache.read().unwrap(), &plugins), 2);
+		assert_eq!(*recorder.scheduled.lock().unwrap(), vec![(2, point)]);
+		assert_eq!(ticks.pending_count(), 0);
+	}
+
+	#[test]
+	fn deferred_ticks_are_unique() {
+		let (mut ticks, cache, _recorder) = setup();
+		let plugins = plugin::Manager::default();
+		let point = block::Point::new(Point3::new(2, 0, 0), Point3::new(0, 0, 0));
+		for _ in 0..3 {
+			ticks.schedule_after(point, 1);
+		}
+		ticks.schedule_after(point, 2);
+		ticks.advance(&cache.read().unwrap(), &plugins);
+		ticks.advance(&cache.read().unwrap(), &plugins);
+		assert_eq!(ticks.pending_count(), 1);
+	}
+
+	#[test]
+	fn unfired_ticks_are_saved() {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-block-ticks-{}",
+			uuid::Uuid::new_v4()
+		));
+		let plugins = plugin::Manager::default();
+		let unloaded = block::Point::new(Point3::new(2, 0, 0), Point3::new(0, 0, 0));
+		let later = block::Point::new(Point3::new(3, 0, 0), Point3::new(1, 1, 1));
+		{
+			let (ticks, cache, _recorder) = setup();
+			let mut ticks = ticks.with_root_dir(root_dir.clone());
+			ticks.schedule_after(unloaded, 1);
+			ticks.schedule_after(later, 5);
+			ticks.advance(&cache.read().unwrap(), &plugins);
+		}
+
+		// The deferred tick fires as soon as possible, and the other keeps the rest of its delay.
+		let (ticks, _cache, _recorder) = setup();
+		let ticks = ticks.with_root_dir(root_dir.clone());
+		assert_eq!(ticks.scheduled.get(&1), Some(&vec![unloaded]));
+		assert_eq!(ticks.scheduled.get(&4), Some(&vec![later]));
+		assert_eq!(ticks.pending_count(), 2);
+		drop(ticks);
+		std::fs::remove_dir_all(&root_dir).unwrap();
+	}
+
+	#[test]
+	fn random_ticks_are_within_ticking_chunks() {
+		let (ticks, cache, recorder) = setup();
+		let mut ticks = ticks.with_random_ticks_per_chunk(5);
+		let plugins = plugin::Manager::default();
+		let ticking = Point3::new(0, 0, 0);
+		let inactive = Point3::new(1, 0, 0);
+		let chunks = [
+			grass_chunk(ticking, Level::Ticking),
+			grass_chunk(inactive, Level::Active),
+		];
+		{
+			let mut cache = cache.write().unwrap();
+			cache.insert(ticking, Arc::downgrade(&chunks[0]));
+			cache.insert(inactive, Arc::downgrade(&chunks[1]));
+		}
+
+		ticks.advance(&cache.read().unwrap(), &plugins);
+		ticks.advance(&cache.read().unwrap(), &plugins);
+		let random = recorder.random.lock().unwrap();
+		assert_eq!(random.len(), 10);
+		assert!(random.iter().all(|(_, point)| *point.chunk() == ticking));
+		assert_eq!(random.iter().filter(|(tick, _)| *tick == 2).count(), 5);
+	}
+}
@@ -1,12 +1,29 @@
+mod backup;
+pub use backup::*;
+
 mod chunk;
 pub use chunk::*;
 
 pub mod cache;
 pub use cache::Cache;
 
+mod clock;
+pub use clock::*;
+
+pub mod event;
+pub use event::{Event, EventBus};
+
+pub mod journal;
+
 mod level;
 pub use level::*;
 
+mod limits;
+pub use limits::*;
+
+mod ready;
+pub use ready::*;
+
 pub(crate) mod ticket;
 pub use ticket::Ticket;
 
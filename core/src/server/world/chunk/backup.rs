@@ -0,0 +1,106 @@
+use std::{
+	sync::{Condvar, Mutex},
+	time::Duration,
+};
+
+/// The result of flushing every loaded chunk of a world to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FlushReport {
+	/// The number of chunks which were saved.
+	pub saved: usize,
+	/// The number of chunks which could not be saved (see the chunk-loading log for why).
+	pub failed: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+	Running,
+	/// A pause has been requested, but the loading thread has not yet flushed its chunks.
+	FlushRequested,
+	/// The loading thread has flushed its chunks and will not load or unload any chunks until resumed.
+	Paused(FlushReport),
+}
+
+/// Pauses the chunk loading thread of a world so the world's files can be safely copied (e.g. for a backup).
+///
+/// When paused, the thread saves every loaded chunk and then stops loading, unloading, and saving chunks.
+/// Tickets received while paused are queued, and their chunks are loaded once the thread is resumed.
+///
+/// Shared between a world's [`database`](crate::server::world::Database) and its chunk loading thread.
+pub struct BackupControl {
+	state: Mutex<State>,
+	changed: Condvar,
+}
+
+impl Default for BackupControl {
+	fn default() -> Self {
+		Self {
+			state: Mutex::new(State::Running),
+			changed: Condvar::new(),
+		}
+	}
+}
+
+impl BackupControl {
+	/// Asks the loading thread to flush its chunks and pause, without waiting for it to do so.
+	pub fn request_pause(&self) {
+		let mut state = self.state.lock().unwrap();
+		if *state == State::Running {
+			*state = State::FlushRequested;
+		}
+	}
+
+	/// Waits until the loading thread has flushed its chunks and paused,
+	/// returning the result of the flush once it is safe to copy the world's files.
+	pub fn wait_until_paused(&self, timeout: Duration) -> Result<FlushReport, Error> {
+		let state = self.state.lock().unwrap();
+		let (state, _timeout) = self
+			.changed
+			.wait_timeout_while(state, timeout, |state| *state == State::FlushRequested)
+			.unwrap();
+		match *state {
+			State::Paused(report) => Ok(report),
+			State::FlushRequested => Err(Error::Timeout(timeout)),
+			State::Running => Err(Error::Resumed),
+		}
+	}
+
+	/// Requests a pause and waits for the loading thread to flush its chunks.
+	pub fn pause_and_flush(&self, timeout: Duration) -> Result<FlushReport, Error> {
+		self.request_pause();
+		self.wait_until_paused(timeout)
+	}
+
+	/// Allows the loading thread to continue loading and unloading chunks.
+	/// Also cancels a pause which has been requested but not yet reached.
+	pub fn resume(&self) {
+		*self.state.lock().unwrap() = State::Running;
+		self.changed.notify_all();
+	}
+
+	pub fn is_paused(&self) -> bool {
+		matches!(*self.state.lock().unwrap(), State::Paused(_))
+	}
+
+	/// Returns true if the loading thread should flush its chunks (and then call [`paused`](Self::paused)).
+	pub(super) fn is_flush_requested(&self) -> bool {
+		*self.state.lock().unwrap() == State::FlushRequested
+	}
+
+	/// Called by the loading thread once it has flushed its chunks.
+	pub(super) fn paused(&self, report: FlushReport) {
+		let mut state = self.state.lock().unwrap();
+		if *state == State::FlushRequested {
+			*state = State::Paused(report);
+		}
+		self.changed.notify_all();
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+	#[error("the chunk loading thread did not flush its chunks within {0:?}")]
+	Timeout(Duration),
+	#[error("the chunk loading thread was resumed before it could pause")]
+	Resumed,
+}
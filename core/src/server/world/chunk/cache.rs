@@ -1,4 +1,8 @@
-use crate::server::world::chunk::Chunk;
+use crate::{
+	block,
+	common::physics,
+	server::{metrics::Metrics, world::chunk::Chunk},
+};
 use engine::math::nalgebra::Point3;
 use std::{
 	collections::HashMap,
@@ -15,21 +19,43 @@ pub type WeakLock = Weak<RwLock<Cache>>;
 /// but be unloaded in a number of milliseconds because it has expired.
 pub struct Cache {
 	loaded_chunks: HashMap<Point3<i64>, Weak<RwLock<Chunk>>>,
+	/// The name of the world the chunks are in, which the [`loaded chunk count`](Metrics::set_loaded_chunks) is reported under.
+	/// Caches without a name are not reported.
+	world_name: Option<String>,
 }
 
 impl Cache {
 	pub fn new() -> Self {
 		Self {
 			loaded_chunks: HashMap::new(),
+			world_name: None,
 		}
 	}
 
+	pub fn with_world_name(mut self, name: &str) -> Self {
+		self.world_name = Some(name.to_owned());
+		self
+	}
+
 	pub fn insert(&mut self, coordinate: Point3<i64>, chunk: Weak<RwLock<Chunk>>) {
 		let _ = self.loaded_chunks.insert(coordinate, chunk);
+		self.report_loaded_chunks();
 	}
 
 	pub fn remove(&mut self, coordinate: &Point3<i64>) {
 		let _ = self.loaded_chunks.remove(coordinate);
+		self.report_loaded_chunks();
+	}
+
+	fn report_loaded_chunks(&self) {
+		if let Some(name) = &self.world_name {
+			Metrics::get().set_loaded_chunks(name, self.loaded_chunks.len());
+		}
+	}
+
+	/// Iterates over the coordinate of every loaded chunk, in no particular order.
+	pub fn iter(&self) -> impl Iterator<Item = (&Point3<i64>, &Weak<RwLock<Chunk>>)> {
+		self.loaded_chunks.iter()
 	}
 
 	pub fn find(&self, coordinate: &Point3<i64>) -> Option<&Weak<RwLock<Chunk>>> {
@@ -40,3 +66,24 @@ impl Cache {
 		self.loaded_chunks.get(coordinate)
 	}
 }
+
+impl Drop for Cache {
+	fn drop(&mut self) {
+		if let Some(name) = &self.world_name {
+			Metrics::get().remove_loaded_chunks(name);
+		}
+	}
+}
+
+/// The voxels of the loaded chunks, which the colliders of entities are stopped against.
+impl physics::Voxels for RwLock<Cache> {
+	fn colliders_at(&self, point: &block::Point) -> Option<Vec<block::Collider>> {
+		let arc_chunk = self.read().unwrap().find(point.chunk())?.upgrade()?;
+		let chunk = arc_chunk.read().unwrap();
+		let offset = point.offset().map(|v| v as usize);
+		Some(match chunk.chunk.block_ids().get(&offset) {
+			Some(id) => block::Lookup::collider_of(*id).colliders_at(point),
+			None => Vec::new(),
+		})
+	}
+}
@@ -1,18 +1,34 @@
 use crate::{
-	common::world::{chunk::Chunk as CommonChunk, generator},
-	server::world::chunk::Level,
+	block,
+	common::{
+		utility::Versioned,
+		world::{
+			chunk::{self as common_chunk, Chunk as CommonChunk},
+			generator,
+		},
+	},
+	graphics::voxel::instance::RangeSet,
+	plugin,
+	server::world::chunk::{event::Source, journal, Level},
 };
 use engine::math::nalgebra::Point3;
 use std::{
+	net::SocketAddr,
 	path::PathBuf,
 	sync::{Arc, RwLock},
 };
 
 pub type ArcLock = Arc<RwLock<Chunk>>;
 
+/// The number of entries the [`journal`] of a chunk can have before the chunk is saved in full.
+pub static MAX_JOURNAL_ENTRIES: usize = 32;
+
 /// A 16x16x16 chunk in the world.
 ///
-/// Data is saved to disk at `<world root>/chunks/x.y.z.kdl`.
+/// Data is saved to disk at `<world root>/chunks/x.y.z.chunk`,
+/// as a [`versioned`](crate::common::utility::Versioned) binary file.
+/// Blocks changed after the chunk was saved in full are appended to its [`journal`] instead of rewriting the whole chunk,
+/// and the journal is compacted into the chunk file once it has [`enough entries`](MAX_JOURNAL_ENTRIES).
 pub struct Chunk {
 	pub chunk: CommonChunk,
 	/// The path to the chunk on disk.
@@ -21,60 +37,473 @@ pub struct Chunk {
 	/// The current ticking level of the chunk.
 	/// Not saved to file.
 	pub(crate) level: Level,
+	/// The [`offset index`](common_chunk::offset_index) of each block which changed since the chunk was last saved.
+	dirty: RangeSet,
+	/// The number of entries in the journal since the chunk was last saved in full.
+	journal_entries: usize,
+	/// True if the next save must save the chunk in full (e.g. its journal was corrupt).
+	needs_full_save: bool,
+	/// The blocks which changed since the replicator last sent changes to clients, in the order they changed,
+	/// with the version of the chunk after each change.
+	/// Not saved to file.
+	block_changes: Vec<(Point3<usize>, Option<(block::LookupId, block::State)>, u64)>,
 }
 
 impl Chunk {
-	fn create_path_for(mut world_root: PathBuf, coordinate: &Point3<i64>) -> PathBuf {
+	pub(super) fn create_path_for(mut world_root: PathBuf, coordinate: &Point3<i64>) -> PathBuf {
 		world_root.push("chunks");
 		world_root.push(format!(
-			"{}.{}.{}.kdl",
+			"{}.{}.{}.chunk",
 			coordinate[0], coordinate[1], coordinate[2]
 		));
 		world_root
 	}
 
+	/// Loads the chunk from disk if it has been saved before, otherwise generates it.
+	/// Any blocks that the generator deferred into the chunk (while decorating its neighbors) are placed in either case.
+	/// Returns the chunk and where its data came from.
 	pub(super) fn load_or_generate(
 		coordinate: &Point3<i64>,
 		level: Level,
 		root_dir: PathBuf,
-	) -> Arc<RwLock<Self>> {
+		generator: &generator::Pipeline,
+	) -> (Arc<RwLock<Self>>, Source) {
 		let path_on_disk = Self::create_path_for(root_dir, &coordinate);
-		Arc::new(RwLock::new(if path_on_disk.exists() {
-			Self::load(path_on_disk, &coordinate, level)
-		} else {
-			Self::generate(path_on_disk, &coordinate, level)
-		}))
+		let (chunk, source) = match path_on_disk.exists() {
+			true => match Self::load(path_on_disk.clone(), &coordinate, level) {
+				Ok(mut chunk) => {
+					chunk.apply_placements(generator.take_deferred(&coordinate));
+					(chunk, Source::Disk)
+				}
+				Err(err) => {
+					log::error!(
+						target: "world",
+						"Failed to load chunk <{}, {}, {}>, it will be regenerated: {:?}",
+						coordinate.x,
+						coordinate.y,
+						coordinate.z,
+						err
+					);
+					(
+						Self::generate(path_on_disk, &coordinate, level, generator),
+						Source::Generated,
+					)
+				}
+			},
+			false => (
+				Self::generate(path_on_disk, &coordinate, level, generator),
+				Source::Generated,
+			),
+		};
+		(Arc::new(RwLock::new(chunk)), source)
 	}
 
-	pub(super) fn generate(path_on_disk: PathBuf, coordinate: &Point3<i64>, level: Level) -> Self {
-		profiling::scope!("generate-chunk", path_on_disk.to_str().unwrap_or(""));
-		//log::debug!(target: "world", "Generating chunk {}", coordinate);
-
-		let generator = generator::Flat::classic();
-		let chunk = generator.generate_chunk(*coordinate);
-
+	pub(crate) fn new(path_on_disk: PathBuf, chunk: CommonChunk, level: Level) -> Self {
 		Self {
 			path_on_disk,
 			chunk,
 			level,
+			dirty: RangeSet::default(),
+			journal_entries: 0,
+			needs_full_save: false,
+			block_changes: Vec::new(),
 		}
 	}
 
-	pub(super) fn load(path_on_disk: PathBuf, coordinate: &Point3<i64>, level: Level) -> Self {
+	pub(super) fn generate(
+		path_on_disk: PathBuf,
+		coordinate: &Point3<i64>,
+		level: Level,
+		generator: &generator::Pipeline,
+	) -> Self {
+		profiling::scope!("generate-chunk", path_on_disk.to_str().unwrap_or(""));
+		//log::debug!(target: "world", "Generating chunk {}", coordinate);
+
+		let chunk = generator.generate_chunk(*coordinate);
+
+		Self::new(path_on_disk, chunk, level)
+	}
+
+	pub(super) fn load(
+		path_on_disk: PathBuf,
+		coordinate: &Point3<i64>,
+		level: Level,
+	) -> anyhow::Result<Self> {
 		profiling::scope!("load-chunk", path_on_disk.to_str().unwrap_or(""));
-		// TODO: Load chunk from file
-		//log::debug!(target: "world", "Loading chunk {}", coordinate);
-		Self {
-			path_on_disk,
-			chunk: CommonChunk::new(*coordinate),
-			level,
+		let bytes = std::fs::read(&path_on_disk)?;
+		let mut chunk = CommonChunk::from_versioned_bytes(&bytes)?;
+		if chunk.coordinate() != coordinate {
+			return Err(LoadError::MismatchedCoordinate(
+				*chunk.coordinate(),
+				*coordinate,
+			))?;
+		}
+		let replay = journal::read(&journal::path_for(&path_on_disk))?;
+		replay.apply(&mut chunk);
+		if replay.is_corrupt {
+			log::warn!(
+				target: "world",
+				"The journal of chunk <{}, {}, {}> is corrupt, only its first {} entries were loaded",
+				coordinate.x,
+				coordinate.y,
+				coordinate.z,
+				replay.entries.len()
+			);
 		}
+		chunk.relight();
+		let mut loaded = Self::new(path_on_disk, chunk, level);
+		loaded.journal_entries = replay.entries.len();
+		// The corrupt journal is replaced the next time the chunk is saved.
+		loaded.needs_full_save = replay.is_corrupt;
+		Ok(loaded)
 	}
 
-	pub(super) fn save(&self) {
+	/// Saves the blocks which changed since the chunk was last saved to its journal,
+	/// or saves the chunk in full if it has never been saved or its journal is full.
+	pub(super) fn save(&mut self) -> anyhow::Result<()> {
+		let needs_full_save = self.needs_full_save
+			|| self.journal_entries >= MAX_JOURNAL_ENTRIES
+			|| !self.path_on_disk.exists();
+		if needs_full_save {
+			return self.save_full();
+		}
+		if self.dirty.is_empty() {
+			return Ok(());
+		}
+		profiling::scope!(
+			"save-chunk-journal",
+			self.path_on_disk.to_str().unwrap_or("")
+		);
+		let (ranges, _count) = self.dirty.take();
+		let changes = ranges
+			.into_iter()
+			.flatten()
+			.map(|index| journal::Change::read(&self.chunk, common_chunk::index_offset(index)))
+			.collect::<Vec<_>>();
+		let journal_path = journal::path_for(&self.path_on_disk);
+		if let Err(err) = journal::append(&journal_path, self.chunk.version(), &changes) {
+			// The changes are no longer tracked, so they can only be saved by saving the whole chunk.
+			self.needs_full_save = true;
+			return Err(err);
+		}
+		self.journal_entries += 1;
+		Ok(())
+	}
+
+	/// Saves the entire chunk, replacing its journal.
+	pub(super) fn save_full(&mut self) -> anyhow::Result<()> {
 		profiling::scope!("save-chunk", self.path_on_disk.to_str().unwrap_or(""));
-		let _path = &self.path_on_disk;
-		//log::debug!(target: "world", "Saving chunk {}", self.coordinate);
-		// TODO: Save chunk to disk
+		if let Some(parent) = self.path_on_disk.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(&self.path_on_disk, self.chunk.to_versioned_bytes()?)?;
+		let journal_path = journal::path_for(&self.path_on_disk);
+		if journal_path.exists() {
+			std::fs::remove_file(&journal_path)?;
+		}
+		self.dirty.take();
+		self.journal_entries = 0;
+		self.needs_full_save = false;
+		Ok(())
+	}
+
+	/// Sets the block-type at a point in the chunk, resetting its state to the [`default`](block::DEFAULT_STATE).
+	/// See [`set_block_id_with_state`](Self::set_block_id_with_state).
+	pub fn set_block_id(&mut self, offset: Point3<usize>, id: Option<block::LookupId>) {
+		self.set_block_id_with_state(offset, id.map(|id| (id, block::DEFAULT_STATE)));
+	}
+
+	/// Sets the block-type and state at a point in the chunk, so it is saved the next time the chunk is saved
+	/// and replicated to the clients which already have the chunk. The light of the chunk is updated around the point.
+	pub fn set_block_id_with_state(
+		&mut self,
+		offset: Point3<usize>,
+		block: Option<(block::LookupId, block::State)>,
+	) {
+		self.chunk.set_block_id_with_state(offset, block);
+		self.chunk.update_light(offset);
+		self.dirty.insert(common_chunk::offset_index(&offset));
+		self.block_changes
+			.push((offset, block, self.chunk.version()));
+	}
+
+	/// Places the blocks that the generator deferred into this chunk while decorating its neighbors,
+	/// as changes which are saved and replicated like any other.
+	/// Blocks which have changed since the chunk was generated are kept (see [`generator::Placement::applies_to`]).
+	/// Returns the number of blocks which were placed.
+	pub(super) fn apply_placements(&mut self, placements: Vec<generator::Placement>) -> usize {
+		let mut count = 0;
+		for placement in placements.into_iter() {
+			if placement.applies_to(&self.chunk) {
+				self.set_block_id(placement.offset, placement.id);
+				count += 1;
+			}
+		}
+		count
+	}
+
+	/// Returns true if blocks have changed since the changes were last [`taken`](Self::take_block_changes).
+	pub fn has_block_changes(&self) -> bool {
+		!self.block_changes.is_empty()
+	}
+
+	/// Returns the blocks which changed since this was last called, in the order they changed,
+	/// with the [`version`](CommonChunk::version) of the chunk after each change.
+	pub fn take_block_changes(
+		&mut self,
+	) -> Vec<(block::Point, Option<(block::LookupId, block::State)>, u64)> {
+		let coordinate = *self.chunk.coordinate();
+		self.block_changes
+			.drain(..)
+			.map(|(offset, block, version)| {
+				let offset = Point3::new(offset.x as i8, offset.y as i8, offset.z as i8);
+				(block::Point::new(coordinate, offset), block, version)
+			})
+			.collect()
+	}
+
+	/// Places (or breaks, if `id` is None) a block in the chunk,
+	/// after it has been validated by the [`plugins`](plugin::Manager::on_block_change).
+	/// Returns the block which was placed, which a plugin may have replaced.
+	/// The chunk is left unchanged if any plugin denies the change.
+	pub fn apply_block_change(
+		&mut self,
+		plugins: &plugin::Manager,
+		instigator: Option<SocketAddr>,
+		offset: Point3<usize>,
+		id: Option<block::LookupId>,
+	) -> Result<Option<block::LookupId>, plugin::BlockChangeDenied> {
+		let ctx = plugin::BlockChangeCtx {
+			instigator,
+			chunk: *self.chunk.coordinate(),
+			offset,
+			previous: self.chunk.block_ids().get(&offset).cloned(),
+			id,
+		};
+		let id = plugins.on_block_change(ctx)?;
+		self.set_block_id(offset, id);
+		Ok(id)
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum LoadError {
+	#[error("the saved chunk is at {0}, but was expected to be at {1}")]
+	MismatchedCoordinate(Point3<i64>, Point3<i64>),
+}
+
+#[cfg(test)]
+mod block_change {
+	use super::*;
+	use crate::app;
+	use plugin::{BlockChangeCtx, BlockChangeResult};
+
+	static PROTECTED: block::LookupId = 7;
+	static REPLACED: block::LookupId = 3;
+
+	/// Denies placing the protected block, and turns any placement of a replaced block into stone (id 0).
+	struct Protection;
+	impl plugin::Plugin for Protection {
+		fn name(&self) -> &'static str {
+			"protection"
+		}
+		fn version(&self) -> semver::Version {
+			semver::Version::new(0, 1, 0)
+		}
+		fn register_state_background(
+			&self,
+			_state: app::state::State,
+			_list: &mut Vec<engine::asset::Id>,
+		) {
+		}
+		fn on_block_change(&self, ctx: &BlockChangeCtx) -> BlockChangeResult {
+			match ctx.id {
+				Some(id) if id == PROTECTED => {
+					BlockChangeResult::Deny("protected block".to_owned())
+				}
+				Some(id) if id == REPLACED => BlockChangeResult::Replace(Some(0)),
+				_ => BlockChangeResult::Allow,
+			}
+		}
+	}
+
+	fn plugins() -> plugin::Manager {
+		let mut manager = plugin::Manager::default();
+		manager.load(&plugin::Config::default().with(Protection));
+		manager
+	}
+
+	fn chunk() -> Chunk {
+		let coordinate = Point3::new(0, 0, 0);
+		Chunk::new(PathBuf::new(), CommonChunk::new(coordinate), Level::Ticking)
+	}
+
+	#[test]
+	fn deferred_placements_are_changes() {
+		let mut chunk = chunk();
+		chunk.set_block_id(Point3::new(1, 0, 0), Some(PROTECTED));
+		chunk.take_block_changes();
+		let placement = |x: usize| generator::Placement {
+			offset: Point3::new(x, 0, 0),
+			id: Some(REPLACED),
+			replaces: None,
+		};
+		// The second placement is dropped, because the block is no longer what the decorator saw.
+		assert_eq!(chunk.apply_placements(vec![placement(0), placement(1)]), 1);
+		let changes = chunk.take_block_changes();
+		assert_eq!(changes.len(), 1);
+		assert_eq!(changes[0].1, Some((REPLACED, block::DEFAULT_STATE)));
+		assert_eq!(
+			chunk.chunk.block_ids().get(&Point3::new(1, 0, 0)),
+			Some(&PROTECTED)
+		);
+	}
+
+	#[test]
+	fn denied_placement_is_rejected() {
+		let plugins = plugins();
+		let mut chunk = chunk();
+		let offset = Point3::new(1, 2, 3);
+		chunk.chunk.set_block_id(offset, Some(1));
+
+		let err = chunk
+			.apply_block_change(&plugins, None, offset, Some(PROTECTED))
+			.unwrap_err();
+		assert_eq!(err.plugin, "protection");
+		assert_eq!(chunk.chunk.block_ids().get(&offset), Some(&1));
+
+		assert_eq!(
+			chunk.apply_block_change(&plugins, None, offset, Some(REPLACED)),
+			Ok(Some(0))
+		);
+		assert_eq!(chunk.chunk.block_ids().get(&offset), Some(&0));
+
+		assert_eq!(
+			chunk.apply_block_change(&plugins, None, offset, None),
+			Ok(None)
+		);
+		assert!(chunk.chunk.block_ids().is_empty());
+	}
+}
+
+#[cfg(test)]
+mod journal_saves {
+	use super::*;
+
+	fn chunk_path(name: &str) -> PathBuf {
+		let mut root = std::env::temp_dir();
+		root.push(format!("crystal-sphinx-{}-{}", name, uuid::Uuid::new_v4()));
+		Chunk::create_path_for(root, &Point3::new(1, 0, -1))
+	}
+
+	fn load(path: &PathBuf) -> Chunk {
+		Chunk::load(path.clone(), &Point3::new(1, 0, -1), Level::Ticking).unwrap()
+	}
+
+	#[test]
+	fn edits_replay_from_journal() {
+		let path = chunk_path("chunk-journal");
+		let mut chunk = Chunk::new(
+			path.clone(),
+			CommonChunk::new(Point3::new(1, 0, -1)),
+			Level::Ticking,
+		);
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(1));
+		chunk.set_block_id(Point3::new(1, 0, 0), Some(1));
+		// The first save is always in full.
+		chunk.save().unwrap();
+		assert!(!journal::path_for(&path).exists());
+
+		chunk.set_block_id(Point3::new(1, 0, 0), None);
+		chunk.set_block_id(Point3::new(2, 3, 4), Some(2));
+		chunk.save().unwrap();
+		chunk.set_block_id(Point3::new(15, 15, 15), Some(3));
+		chunk.save().unwrap();
+		// Saving without changes does not add an entry.
+		chunk.save().unwrap();
+
+		let snapshot = CommonChunk::from_versioned_bytes(&std::fs::read(&path).unwrap()).unwrap();
+		assert_eq!(snapshot.block_ids().len(), 2);
+		let replay = journal::read(&journal::path_for(&path)).unwrap();
+		assert!(!replay.is_corrupt);
+		assert_eq!(replay.entries.len(), 2);
+
+		let loaded = load(&path);
+		assert_eq!(loaded.chunk.block_ids(), chunk.chunk.block_ids());
+		assert_eq!(loaded.journal_entries, 2);
+		assert_eq!(loaded.chunk.version(), 5);
+		let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+	}
+
+	#[test]
+	fn states_are_journaled_and_replicated() {
+		let path = chunk_path("chunk-journal-state");
+		let mut chunk = Chunk::new(
+			path.clone(),
+			CommonChunk::new(Point3::new(1, 0, -1)),
+			Level::Ticking,
+		);
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(1));
+		chunk.save().unwrap();
+		chunk.set_block_id_with_state(Point3::new(0, 0, 0), Some((1, 2)));
+		chunk.save().unwrap();
+		assert_eq!(chunk.take_block_changes().last().unwrap().1, Some((1, 2)));
+
+		let loaded = load(&path);
+		assert_eq!(loaded.chunk.block_state(&Point3::new(0, 0, 0)), 2);
+		let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+	}
+
+	#[test]
+	fn reloaded_chunk_continues_version() {
+		let path = chunk_path("chunk-journal-version");
+		let mut chunk = Chunk::new(
+			path.clone(),
+			CommonChunk::new(Point3::new(1, 0, -1)),
+			Level::Ticking,
+		);
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(1));
+		chunk.save().unwrap();
+		// The block changes three times, but only its last change is journaled.
+		for id in 2..5 {
+			chunk.set_block_id(Point3::new(0, 0, 0), Some(id));
+		}
+		chunk.save().unwrap();
+		assert_eq!(chunk.chunk.version(), 4);
+
+		let mut loaded = load(&path);
+		assert_eq!(loaded.chunk.version(), 4);
+		loaded.set_block_id(Point3::new(0, 0, 0), None);
+		assert_eq!(loaded.take_block_changes()[0].2, 5);
+		let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+	}
+
+	#[test]
+	fn corrupt_journal_falls_back_to_snapshot() {
+		let path = chunk_path("chunk-journal-corrupt");
+		let mut chunk = Chunk::new(
+			path.clone(),
+			CommonChunk::new(Point3::new(1, 0, -1)),
+			Level::Ticking,
+		);
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(1));
+		chunk.save().unwrap();
+		chunk.set_block_id(Point3::new(0, 0, 0), Some(2));
+		chunk.save().unwrap();
+
+		// Simulate a crash while the entry was being written.
+		let journal_path = journal::path_for(&path);
+		let bytes = std::fs::read(&journal_path).unwrap();
+		std::fs::write(&journal_path, &bytes[..bytes.len() - 1]).unwrap();
+
+		let mut loaded = load(&path);
+		assert_eq!(
+			loaded.chunk.block_ids().get(&Point3::new(0, 0, 0)),
+			Some(&1)
+		);
+		assert!(loaded.needs_full_save);
+		loaded.save().unwrap();
+		assert!(!journal_path.exists());
+		let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
 	}
 }
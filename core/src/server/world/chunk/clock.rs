@@ -0,0 +1,60 @@
+use std::{
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// A source for the current time of the chunk loading thread.
+///
+/// Chunk and ticket expiration is measured against a clock instead of the system time directly,
+/// so the expiration behavior can be driven manually (see [`MockClock`]).
+pub trait Clock: Send + Sync {
+	fn now(&self) -> Instant;
+}
+
+/// The clock used at runtime, which reports the system time.
+#[derive(Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+}
+
+/// A clock which starts at the time it was created, and only moves forward when it is advanced.
+/// Clones of the clock share the same time.
+#[derive(Clone)]
+pub struct MockClock(Arc<Mutex<Instant>>);
+
+impl Default for MockClock {
+	fn default() -> Self {
+		Self(Arc::new(Mutex::new(Instant::now())))
+	}
+}
+
+impl MockClock {
+	pub fn advance(&self, duration: Duration) {
+		*self.0.lock().unwrap() += duration;
+	}
+}
+
+impl Clock for MockClock {
+	fn now(&self) -> Instant {
+		*self.0.lock().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod mock_clock {
+	use super::*;
+
+	#[test]
+	fn only_moves_when_advanced() {
+		let clock = MockClock::default();
+		let start = clock.now();
+		std::thread::sleep(Duration::from_millis(5));
+		assert_eq!(clock.now(), start);
+		clock.clone().advance(Duration::from_secs(3));
+		assert_eq!(clock.now().duration_since(start), Duration::from_secs(3));
+	}
+}
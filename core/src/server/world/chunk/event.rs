@@ -0,0 +1,68 @@
+use engine::{
+	channels::broadcast::{Bus, BusReader},
+	math::nalgebra::Point3,
+};
+use std::{
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+/// The number of events which can be waiting for readers before new events are discarded.
+static BUS_CAPACITY: usize = 512;
+
+/// Where a loaded chunk's data came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+	/// The chunk was previously saved, and was read from its file in the world directory.
+	Disk,
+	/// The chunk has never been saved, and was created by the world generator.
+	Generated,
+}
+
+/// Lifecycle events emitted by the chunk-loading thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	/// A chunk which was not in the cache has been loaded into memory.
+	/// Chunks which were already loaded (and have gained an additional ticket) do not emit this event.
+	Loaded {
+		coordinate: Point3<i64>,
+		source: Source,
+		/// How long it took to load or generate the chunk data.
+		duration: Duration,
+	},
+	/// A chunk has expired and was dropped from memory.
+	Unloaded {
+		coordinate: Point3<i64>,
+		/// If the chunk was successfully saved to disk before being dropped.
+		saved: bool,
+	},
+}
+
+/// The broadcast bus that chunk [`events`](Event) are sent through.
+/// Each reader receives every event emitted after it was added.
+#[derive(Clone)]
+pub struct EventBus(Arc<Mutex<Bus<Event>>>);
+
+impl EventBus {
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(Bus::new(BUS_CAPACITY))))
+	}
+
+	pub fn add_recv(&self) -> BusReader<Event> {
+		self.0.lock().unwrap().add_rx()
+	}
+
+	/// Sends the event to all readers without blocking.
+	/// If any reader has fallen so far behind that the bus is full, the event is discarded
+	/// (the chunk thread must never stall on observers).
+	pub(crate) fn emit(&self, event: Event) {
+		let mut bus = self.0.lock().unwrap();
+		if let Err(event) = bus.try_broadcast(event) {
+			log::trace!(
+				target: super::thread::LOG,
+				"Chunk event bus is full, discarding {:?}",
+				event
+			);
+		}
+	}
+}
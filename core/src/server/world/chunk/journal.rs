@@ -0,0 +1,218 @@
+//! An append-only log of the blocks which changed in a chunk since it was last saved in full.
+//!
+//! Saved next to the chunk at `<world root>/chunks/x.y.z.chunk.journal`.
+//! Each entry is written as a little-endian `u32` length, a 4 byte checksum of the payload,
+//! and the payload itself (the bincode serialized [`changes`](Change), preceded by the
+//! [`version`](CommonChunk::version) of the chunk once they were made).
+//! Entries written before the version was recorded are marked by the highest bit of their length being unset.
+//! An entry which was only partially written (e.g. the server crashed mid-save) or has been corrupted
+//! fails its checksum, and it and every entry after it are skipped when the journal is read.
+use crate::{block, common::world::chunk::Chunk as CommonChunk};
+use anyhow::Result;
+use engine::math::nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+use std::{
+	io::Write,
+	path::{Path, PathBuf},
+};
+
+const HEADER_SIZE: usize = 8;
+/// Set in the length of entries whose payload starts with the chunk's version.
+const VERSIONED_FLAG: u32 = 1 << 31;
+
+/// The block at a point in a chunk after it changed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+	pub offset: Point3<usize>,
+	/// The block-type and state at the point, or None if the block was removed.
+	pub block: Option<(block::LookupId, block::State)>,
+}
+
+impl Change {
+	/// Reads the current block at a point in a chunk.
+	pub fn read(chunk: &CommonChunk, offset: Point3<usize>) -> Self {
+		Self {
+			offset,
+			block: chunk
+				.block_ids()
+				.get(&offset)
+				.map(|id| (*id, chunk.block_state(&offset))),
+		}
+	}
+
+	pub fn apply(&self, chunk: &mut CommonChunk) {
+		chunk.set_block_id_with_state(self.offset, self.block);
+	}
+}
+
+/// The entries which were read from a journal.
+#[derive(Debug, Default)]
+pub struct Replay {
+	pub entries: Vec<Vec<Change>>,
+	/// The version of the chunk after the last entry, if it was recorded.
+	pub version: Option<u64>,
+	/// True if an entry was corrupt or partially written, and it (and all entries after it) were skipped.
+	pub is_corrupt: bool,
+}
+
+impl Replay {
+	pub fn apply(&self, chunk: &mut CommonChunk) {
+		for change in self.entries.iter().flatten() {
+			change.apply(chunk);
+		}
+		// A block which changed several times between saves only has one entry,
+		// so replaying the changes alone would leave the chunk at an older version.
+		if let Some(version) = self.version {
+			chunk.advance_version(version);
+		}
+	}
+}
+
+pub fn path_for(chunk_path: &Path) -> PathBuf {
+	let mut path = chunk_path.as_os_str().to_owned();
+	path.push(".journal");
+	PathBuf::from(path)
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+	use sha2::{Digest, Sha256};
+	let hash = Sha256::digest(payload);
+	[hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Appends an entry of changes (which leave the chunk at `version`) to the end of the journal,
+/// creating it if it does not exist.
+pub fn append(path: &Path, version: u64, changes: &Vec<Change>) -> Result<()> {
+	let payload = bincode::serialize(&(version, changes))?;
+	let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+	bytes.extend_from_slice(&(payload.len() as u32 | VERSIONED_FLAG).to_le_bytes());
+	bytes.extend_from_slice(&checksum(&payload));
+	bytes.extend_from_slice(&payload);
+	let mut file = std::fs::OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)?;
+	file.write_all(&bytes)?;
+	Ok(())
+}
+
+/// Reads every intact entry of the journal, in the order they were appended.
+/// Returns no entries if the journal does not exist.
+pub fn read(path: &Path) -> Result<Replay> {
+	if !path.exists() {
+		return Ok(Replay::default());
+	}
+	Ok(parse(&std::fs::read(path)?))
+}
+
+fn parse(mut bytes: &[u8]) -> Replay {
+	let mut replay = Replay::default();
+	while !bytes.is_empty() {
+		let entry = match bytes.len() >= HEADER_SIZE {
+			true => {
+				let len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+				let is_versioned = len & VERSIONED_FLAG != 0;
+				let len = (len & !VERSIONED_FLAG) as usize;
+				bytes
+					.get(HEADER_SIZE..HEADER_SIZE + len)
+					.filter(|payload| checksum(payload)[..] == bytes[4..HEADER_SIZE])
+					.and_then(|payload| match is_versioned {
+						true => bincode::deserialize::<(u64, Vec<Change>)>(payload)
+							.ok()
+							.map(|(version, changes)| (Some(version), changes)),
+						false => bincode::deserialize::<Vec<Change>>(payload)
+							.ok()
+							.map(|changes| (None, changes)),
+					})
+					.map(|(version, changes)| (version, changes, HEADER_SIZE + len))
+			}
+			false => None,
+		};
+		match entry {
+			Some((version, changes, size)) => {
+				replay.entries.push(changes);
+				replay.version = version.or(replay.version);
+				bytes = &bytes[size..];
+			}
+			None => {
+				replay.is_corrupt = true;
+				break;
+			}
+		}
+	}
+	replay
+}
+
+#[cfg(test)]
+mod entries {
+	use super::*;
+
+	fn entry(changes: &Vec<Change>) -> Vec<u8> {
+		let path = std::env::temp_dir().join(format!(
+			"crystal-sphinx-journal-entry-{}",
+			uuid::Uuid::new_v4()
+		));
+		append(&path, changes.len() as u64, changes).unwrap();
+		let bytes = std::fs::read(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		bytes
+	}
+
+	#[test]
+	fn partial_entry_is_skipped() {
+		let first = vec![Change {
+			offset: Point3::new(1, 2, 3),
+			block: Some((4, 0)),
+		}];
+		let second = vec![
+			Change {
+				offset: Point3::new(1, 2, 3),
+				block: None,
+			},
+			Change {
+				offset: Point3::new(4, 5, 6),
+				block: Some((2, 1)),
+			},
+		];
+		let mut bytes = entry(&first);
+		let second_bytes = entry(&second);
+		bytes.extend_from_slice(&second_bytes[..second_bytes.len() - 3]);
+
+		let replay = parse(&bytes);
+		assert!(replay.is_corrupt);
+		assert_eq!(replay.entries, vec![first]);
+		assert_eq!(replay.version, Some(1));
+	}
+
+	#[test]
+	fn unversioned_entries_are_read() {
+		let changes = vec![Change {
+			offset: Point3::new(1, 2, 3),
+			block: Some((4, 0)),
+		}];
+		let payload = bincode::serialize(&changes).unwrap();
+		let mut bytes = (payload.len() as u32).to_le_bytes().to_vec();
+		bytes.extend_from_slice(&checksum(&payload));
+		bytes.extend_from_slice(&payload);
+		bytes.extend_from_slice(&entry(&changes));
+
+		let replay = parse(&bytes);
+		assert!(!replay.is_corrupt);
+		assert_eq!(replay.entries, vec![changes.clone(), changes]);
+		assert_eq!(replay.version, Some(1));
+	}
+
+	#[test]
+	fn corrupt_entry_is_skipped() {
+		let changes = vec![Change {
+			offset: Point3::new(0, 0, 0),
+			block: Some((1, 0)),
+		}];
+		let mut bytes = entry(&changes);
+		let last = bytes.len() - 1;
+		bytes[last] ^= 0xff;
+		let replay = parse(&bytes);
+		assert!(replay.is_corrupt);
+		assert!(replay.entries.is_empty());
+	}
+}
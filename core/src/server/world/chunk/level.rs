@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// The possible levels/states a chunk could be loaded as/in.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Level {
@@ -29,7 +31,7 @@ impl Level {
 }
 
 /// A variation of the [`Level`](Level) enum which includes parameters for the levels, where applicable.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ParameterizedLevel {
 	/// The provided member is the radius of the cuboid that should be loaded.
 	/// All chunks in the cuboid-radius are loaded with the `Ticking` level.
@@ -0,0 +1,50 @@
+use std::{
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+/// How long a chunk stays loaded after it is no longer referenced by any ticket, if not otherwise configured.
+pub static DEFAULT_EXPIRATION_DELAY: Duration = Duration::from_secs(60);
+/// The furthest (in chunks) any client can see, if not otherwise configured.
+pub static DEFAULT_MAX_VIEW_DISTANCE: u64 = 16;
+
+/// Limits on how long chunks stay loaded and how far clients can see,
+/// which can be changed by admins while the server is running.
+///
+/// Shared between a world's [`database`](crate::server::world::Database), its chunk loading thread,
+/// and the [`replicator`](crate::entity::system::Replicator), which observe changes on their next update.
+pub struct Limits {
+	expiration_delay_ms: AtomicU64,
+	max_view_distance: AtomicU64,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			expiration_delay_ms: AtomicU64::new(DEFAULT_EXPIRATION_DELAY.as_millis() as u64),
+			max_view_distance: AtomicU64::new(DEFAULT_MAX_VIEW_DISTANCE),
+		}
+	}
+}
+
+impl Limits {
+	/// The amount of time a chunk can be without a ticket before it is saved to disk and unloaded.
+	pub fn expiration_delay(&self) -> Duration {
+		Duration::from_millis(self.expiration_delay_ms.load(Ordering::Relaxed))
+	}
+
+	pub fn set_expiration_delay(&self, delay: Duration) {
+		let delay_ms = delay.as_millis().min(u64::MAX as u128) as u64;
+		self.expiration_delay_ms.store(delay_ms, Ordering::Relaxed);
+	}
+
+	/// The largest chunk relevance radius any connection is allowed,
+	/// regardless of the radius requested by its entity.
+	pub fn max_view_distance(&self) -> u64 {
+		self.max_view_distance.load(Ordering::Relaxed)
+	}
+
+	pub fn set_max_view_distance(&self, distance: u64) {
+		self.max_view_distance.store(distance, Ordering::Relaxed);
+	}
+}
@@ -0,0 +1,140 @@
+use super::{cache, ArcLock, Event};
+use engine::{channels::broadcast::BusReader, math::nalgebra::Point3};
+use std::{
+	collections::{BTreeSet, HashMap},
+	sync::mpsc::RecvTimeoutError,
+	time::{Duration, Instant},
+};
+
+/// The longest a [`Waiter`] goes without checking the cache.
+/// Events are discarded when the bus is full, so the cache is checked periodically
+/// instead of only when a chunk is reported as loaded.
+static RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Waits for chunks to be loaded into a world's cache,
+/// woken by the [`events`](Event) of the chunk loading thread instead of polling the cache.
+///
+/// The waiter only holds the cache and an event reader (not the [`Database`](crate::server::world::Database)),
+/// so the database is not locked while it waits.
+pub struct Waiter {
+	cache: cache::ArcLock,
+	events: BusReader<Event>,
+}
+
+impl Waiter {
+	/// The reader must be added before any tickets for the awaited chunks are submitted,
+	/// so that no load event is missed.
+	pub fn new(cache: cache::ArcLock, events: BusReader<Event>) -> Self {
+		Self { cache, events }
+	}
+
+	/// Blocks until every chunk is in the cache or the timeout elapses,
+	/// returning the chunks which are loaded (which are all of them, if they were loaded in time).
+	pub fn wait(
+		&mut self,
+		coordinates: &[Point3<i64>],
+		timeout: Duration,
+	) -> HashMap<Point3<i64>, ArcLock> {
+		let coordinates = coordinates.iter().cloned().collect::<BTreeSet<_>>();
+		let start = Instant::now();
+		loop {
+			let loaded = self.find_loaded(&coordinates);
+			let elapsed = start.elapsed();
+			if loaded.len() == coordinates.len() || elapsed >= timeout {
+				return loaded;
+			}
+			let interval = (timeout - elapsed).min(RECHECK_INTERVAL);
+			// Any event (or the lack of one) is a reason to check the cache again.
+			if let Err(RecvTimeoutError::Disconnected) = self.events.recv_timeout(interval) {
+				// The chunk thread has stopped, so nothing else will be loaded.
+				return self.find_loaded(&coordinates);
+			}
+		}
+	}
+
+	fn find_loaded(&self, coordinates: &BTreeSet<Point3<i64>>) -> HashMap<Point3<i64>, ArcLock> {
+		let cache = self.cache.read().unwrap();
+		coordinates
+			.iter()
+			.filter_map(|coordinate| {
+				let arc_chunk = cache.find(coordinate).map(|weak| weak.upgrade()).flatten();
+				arc_chunk.map(|arc_chunk| (*coordinate, arc_chunk))
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod waiter {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		server::world::chunk::{Chunk, EventBus, Level, Source},
+	};
+	use std::{
+		path::PathBuf,
+		sync::{Arc, RwLock},
+	};
+
+	fn create_chunk(coordinate: Point3<i64>) -> ArcLock {
+		let chunk = Chunk::new(PathBuf::new(), CommonChunk::new(coordinate), Level::Loaded);
+		Arc::new(RwLock::new(chunk))
+	}
+
+	#[test]
+	fn woken_when_chunks_are_loaded() {
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let mut waiter = Waiter::new(cache.clone(), events.add_recv());
+		let coordinates = [Point3::new(0, 0, 0), Point3::new(1, 0, 0)];
+
+		let loader = {
+			let cache = cache.clone();
+			std::thread::spawn(move || {
+				let chunks = coordinates
+					.iter()
+					.map(|coordinate| {
+						std::thread::sleep(Duration::from_millis(20));
+						let arc_chunk = create_chunk(*coordinate);
+						cache
+							.write()
+							.unwrap()
+							.insert(*coordinate, Arc::downgrade(&arc_chunk));
+						events.emit(Event::Loaded {
+							coordinate: *coordinate,
+							source: Source::Generated,
+							duration: Duration::ZERO,
+						});
+						arc_chunk
+					})
+					.collect::<Vec<_>>();
+				// Keep the chunks alive until the waiter has found them.
+				std::thread::sleep(Duration::from_secs(1));
+				chunks
+			})
+		};
+
+		let start = Instant::now();
+		let loaded = waiter.wait(&coordinates, Duration::from_secs(10));
+		assert_eq!(loaded.len(), 2);
+		assert!(start.elapsed() < Duration::from_secs(1));
+		let _ = loader.join();
+	}
+
+	#[test]
+	fn returns_loaded_chunks_at_timeout() {
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let mut waiter = Waiter::new(cache.clone(), events.add_recv());
+		let arc_chunk = create_chunk(Point3::new(0, 0, 0));
+		cache
+			.write()
+			.unwrap()
+			.insert(Point3::new(0, 0, 0), Arc::downgrade(&arc_chunk));
+
+		let coordinates = [Point3::new(0, 0, 0), Point3::new(0, 1, 0)];
+		let loaded = waiter.wait(&coordinates, Duration::from_millis(50));
+		assert_eq!(loaded.len(), 1);
+		assert!(loaded.contains_key(&Point3::new(0, 0, 0)));
+	}
+}
@@ -1,19 +1,23 @@
+use crate::common::{utility::ThreadHandle, world::generator};
 use crate::server::world::chunk::{
-	self, cache,
+	self,
+	backup::{BackupControl, FlushReport},
+	cache,
+	clock::{Clock, SystemClock},
+	event::{Event, EventBus, Source},
 	ticket::{self, Ticket},
-	Chunk, Level,
+	Chunk, Level, Limits,
 };
-use crate::common::utility::ThreadHandle;
 use anyhow::Result;
 use engine::{math::nalgebra::Point3, utility::spawn_thread};
 use std::{
-	collections::HashMap,
+	collections::{HashMap, VecDeque},
 	path::PathBuf,
 	sync::{Arc, Weak},
 };
 
 /// The log category for the chunk loading thread.
-static LOG: &'static str = "chunk-loading";
+pub(super) static LOG: &'static str = "chunk-loading";
 
 /// State data about the loading thread.
 pub(crate) struct ThreadState {
@@ -24,13 +28,47 @@ pub(crate) struct ThreadState {
 	/// just weak references to what is loaded at any given time.
 	cache: cache::ArcLock,
 
+	/// The bus that lifecycle events are emitted through when chunks are loaded and unloaded.
+	events: EventBus,
+
+	/// The numerical seed of the world, used to generate chunks which have never been saved.
+	seed: u64,
+	/// The generator for chunks which have never been saved.
+	/// Created when the first chunk is loaded, because it requires the block lookup to have been loaded.
+	generator: Option<generator::Pipeline>,
+	/// The [`revision`](crate::plugin::Manager::revision) of the plugin config the generator's decorators were registered at,
+	/// or None if the generator does not use plugins.
+	generator_revision: Option<usize>,
+
+	/// The source of the current time for ticket and chunk expiration.
+	clock: Box<dyn Clock>,
+
+	/// Tickets which have been received, but whose chunks have not all been loaded yet.
+	/// Chunks are loaded from each ticket in turn (round-robin), so that one client
+	/// requesting a large region cannot starve the tickets of other clients.
+	pending_tickets: VecDeque<PendingTicket>,
+	/// The maximum number of chunks loaded from `pending_tickets` in each update of the thread.
+	chunks_per_update: usize,
 	/// List of inactive and recently dropped tickets (and the chunk coordinates they reference).
-	ticket_bindings: Vec<(Weak<Ticket>, Vec<Point3<i64>>)>,
+	ticket_bindings: Vec<(Weak<Ticket>, ticket::Hint, Vec<Point3<i64>>)>,
+	/// Tickets which were dropped within the last `expiration_delay`, paired with the time they were dropped.
+	/// These are saved as hints alongside active tickets, so the region of a recently disconnected client is restored too.
+	dropped_hints: Vec<(std::time::Instant, ticket::Hint)>,
+	/// The tickets resubmitted from the hints saved when the world was last closed.
+	/// These are held until `hint_expiration`, after which the chunks are only kept loaded by runtime tickets.
+	hinted_tickets: Vec<Arc<Ticket>>,
+	/// The amount of time the `hinted_tickets` are held for after being restored.
+	hint_duration: std::time::Duration,
+	/// The time at which the `hinted_tickets` are dropped. Will be None if there are no hinted tickets.
+	hint_expiration: Option<std::time::Instant>,
 	/// Map of coordinate to chunk states (and the actual strong reference to keep the chunk loaded).
 	chunk_states: HashMap<Point3<i64>, ChunkState>,
 
-	/// The amount of time a chunk can spend in `ticketless_chunks` before being saved to disk and dropped.
-	expiration_delay: std::time::Duration,
+	/// The limits which can be changed while the thread is running,
+	/// including the amount of time a chunk can spend in `ticketless_chunks` before being saved to disk and dropped.
+	limits: Arc<Limits>,
+	/// Pauses the loading and unloading of chunks while the world is being backed up.
+	backup: Arc<BackupControl>,
 	/// The earliest time in `ticketless_chunks`. Will be None if there are no chunks waiting to be unloaded.
 	earliest_expiration_timestamp: Option<std::time::Instant>,
 	/// The list of chunk coordinates without tickets, paired with the time the coordinate was added to the list.
@@ -42,36 +80,83 @@ pub(crate) struct ThreadState {
 	disconnected_from_requests: bool,
 }
 
+/// A ticket received by the loading thread whose chunks are still being loaded.
+struct PendingTicket {
+	ticket: Weak<Ticket>,
+	hint: ticket::Hint,
+	/// The chunks of the ticket which have yet to be loaded, in the order they will be loaded.
+	remaining: VecDeque<(Point3<i64>, Level)>,
+	/// The chunks of the ticket which have been loaded so far.
+	loaded: Vec<Point3<i64>>,
+}
+
+impl PendingTicket {
+	fn new(weak_ticket: Weak<Ticket>) -> Option<Self> {
+		// early out if the user has already dropped the ticket
+		let arc_ticket = weak_ticket.upgrade()?;
+		Some(Self {
+			hint: ticket::Hint::from(&*arc_ticket),
+			remaining: arc_ticket.coordinate_levels().into(),
+			loaded: Vec::new(),
+			ticket: weak_ticket,
+		})
+	}
+}
+
 /// Begins the chunk loading thread, returning its handle.
 /// If the handle is dropped, the thread will stop at the next loop.
+///
+/// If `persist_ticket_hints` is true, the [`hints`](ticket::HintSet) saved by the previous session are
+/// resubmitted when the thread starts, and the active tickets are saved as hints when the thread stops.
+///
+/// At most `chunks_per_update` chunks are loaded each loop of the thread,
+/// interleaved across all of the tickets which are waiting on chunks.
+///
+/// Chunks which have never been saved are generated using the world `seed`.
+///
+/// While the `backup` control is paused, tickets are queued but no chunks are loaded or unloaded.
 pub fn start(
 	root_dir: PathBuf,
 	incoming_requests: ticket::Receiver,
 	cache: &cache::ArcLock,
+	events: &EventBus,
+	persist_ticket_hints: bool,
+	chunks_per_update: usize,
+	seed: u64,
+	limits: &Arc<Limits>,
+	backup: &Arc<BackupControl>,
 ) -> anyhow::Result<ThreadHandle> {
 	let handle = Arc::new(());
 	let weak_handle = Arc::downgrade(&handle);
 	let cache = cache.clone();
+	let events = events.clone();
 	let root_dir = root_dir.clone();
+	let limits = limits.clone();
+	let backup = backup.clone();
 	let join_handle = spawn_thread(LOG, move || -> Result<()> {
-		let mut thread_state = ThreadState {
-			root_dir: root_dir.clone(),
-			cache: cache.clone(),
-			ticket_bindings: Vec::new(),
-			chunk_states: HashMap::new(),
-			expiration_delay: std::time::Duration::from_secs(60),
-			earliest_expiration_timestamp: None,
-			ticketless_chunks: Vec::new(),
-			disconnected_from_requests: false,
-		};
+		let mut thread_state = ThreadState::new(root_dir.clone(), cache.clone(), events);
+		thread_state.chunks_per_update = chunks_per_update.max(1);
+		thread_state.seed = seed;
+		thread_state.limits = limits;
+		thread_state.backup = backup;
+
+		log::info!(target: LOG, "Starting chunk-loading thread");
+		if persist_ticket_hints {
+			thread_state.restore_ticket_hints();
+		}
 
 		// while the database/cache has not been discarded,
 		// processing any pending load requests & unload any chunks no longer needed
-		log::info!(target: LOG, "Starting chunk-loading thread");
 		while weak_handle.strong_count() > 0 {
 			thread_state.update(&incoming_requests);
 			std::thread::sleep(std::time::Duration::from_millis(1));
 		}
+
+		if persist_ticket_hints {
+			if let Err(err) = thread_state.save_ticket_hints() {
+				log::error!(target: LOG, "Failed to save chunk ticket hints: {:?}", err);
+			}
+		}
 		log::info!(target: LOG, "Ending chunk-loading thread");
 
 		Ok(())
@@ -81,9 +166,69 @@ pub fn start(
 }
 
 impl ThreadState {
+	fn new(root_dir: PathBuf, cache: cache::ArcLock, events: EventBus) -> Self {
+		Self {
+			root_dir,
+			cache,
+			events,
+			seed: 0,
+			generator: None,
+			generator_revision: None,
+			clock: Box::new(SystemClock),
+			pending_tickets: VecDeque::new(),
+			chunks_per_update: 16,
+			ticket_bindings: Vec::new(),
+			dropped_hints: Vec::new(),
+			hinted_tickets: Vec::new(),
+			hint_duration: std::time::Duration::from_secs(30),
+			hint_expiration: None,
+			chunk_states: HashMap::new(),
+			limits: Arc::default(),
+			backup: Arc::default(),
+			earliest_expiration_timestamp: None,
+			ticketless_chunks: Vec::new(),
+			disconnected_from_requests: false,
+		}
+	}
+
+	/// The amount of time a chunk can spend in `ticketless_chunks` before being saved to disk and dropped.
+	fn expiration_delay(&self) -> std::time::Duration {
+		self.limits.expiration_delay()
+	}
+
+	#[cfg(test)]
+	fn with_clock<T>(mut self, clock: T) -> Self
+	where
+		T: Clock + 'static,
+	{
+		self.clock = Box::new(clock);
+		self
+	}
+
+	/// Generates chunks with a generator which does not depend on the block lookup,
+	/// which is never initialized in tests.
+	#[cfg(test)]
+	fn with_empty_generator(mut self) -> Self {
+		self.generator = Some(generator::Pipeline::new(
+			self.seed,
+			generator::Flat::default(),
+		));
+		self
+	}
+
 	#[profiling::function]
 	fn update(&mut self, incoming_requests: &ticket::Receiver) {
+		// Tickets are always received, so any which arrive while paused are queued instead of dropped.
 		self.process_new_tickets(&incoming_requests);
+		if self.backup.is_flush_requested() {
+			let report = self.save_loaded_chunks();
+			self.backup.paused(report);
+		}
+		if self.backup.is_paused() {
+			return;
+		}
+		self.load_pending_chunks();
+		self.release_expired_hints();
 		self.update_dropped_tickets();
 		if self.has_expired_chunks() {
 			let chunks_for_unloading = self.find_expired_chunks();
@@ -91,6 +236,35 @@ impl ThreadState {
 		}
 	}
 
+	/// Saves every loaded chunk to disk, without unloading any of them.
+	#[profiling::function]
+	fn save_loaded_chunks(&mut self) -> FlushReport {
+		let mut report = FlushReport::default();
+		for (coordinate, state) in self.chunk_states.iter() {
+			match state.chunk.write().unwrap().save() {
+				Ok(()) => report.saved += 1,
+				Err(err) => {
+					log::error!(
+						target: LOG,
+						"Failed to save chunk <{}, {}, {}>: {:?}",
+						coordinate.x,
+						coordinate.y,
+						coordinate.z,
+						err
+					);
+					report.failed += 1;
+				}
+			}
+		}
+		log::info!(
+			target: LOG,
+			"Saved {} loaded chunks ({} failed), pausing chunk loading",
+			report.saved,
+			report.failed
+		);
+		report
+	}
+
 	#[profiling::function]
 	fn process_new_tickets(&mut self, incoming_requests: &ticket::Receiver) {
 		use engine::channels::mpsc::TryRecvError;
@@ -98,10 +272,10 @@ impl ThreadState {
 		while !self.disconnected_from_requests && !has_emptied_requests {
 			match incoming_requests.try_recv() {
 				Ok(weak_ticket) => {
-					// TODO: Multiple chunks could be loaded concurrently.
-					// If requests are gathered first and then all new chunks are loaded at once,
-					// we could increase the throughput of the chunk loader.
-					self.sync_process_ticket(weak_ticket);
+					// The chunks of the ticket are loaded over the next updates by `load_pending_chunks`.
+					if let Some(pending) = PendingTicket::new(weak_ticket) {
+						self.pending_tickets.push_back(pending);
+					}
 				}
 				// no events, continue the loop after a short nap
 				Err(TryRecvError::Empty) => {
@@ -116,39 +290,151 @@ impl ThreadState {
 		}
 	}
 
+	/// Loads all of the chunks for a ticket immediately, without waiting for any other pending tickets.
 	#[profiling::function]
 	fn sync_process_ticket(&mut self, weak_ticket: Weak<Ticket>) {
-		let arc_ticket = match weak_ticket.upgrade() {
-			Some(ticket) => ticket,
-			None => return, // early out if the user has already dropped the ticket
-		};
-		let processed_chunks = self.sync_load_ticket_chunks(arc_ticket);
-		let mut ticket_chunks = Vec::with_capacity(processed_chunks.len());
-		for (coordinate, arc_chunk, level) in processed_chunks.into_iter() {
-			self.insert_or_update_chunk_state(&weak_ticket, coordinate, level, &arc_chunk);
-			ticket_chunks.push(coordinate);
+		if let Some(mut pending) = PendingTicket::new(weak_ticket) {
+			while self.load_next_chunk(&mut pending) {}
+			self.bind_ticket(pending);
 		}
-		self.ticket_bindings.push((weak_ticket, ticket_chunks));
 	}
 
+	/// Loads up to `chunks_per_update` chunks from the pending tickets,
+	/// taking one chunk from each ticket in turn.
+	/// Tickets which have no more chunks to load (or have been dropped) are bound to their loaded chunks.
 	#[profiling::function]
-	fn sync_load_ticket_chunks(
-		&mut self,
-		ticket: Arc<Ticket>,
-	) -> Vec<(Point3<i64>, chunk::ArcLock, Level)> {
-		let mut chunks = Vec::new();
-		let coordinate_levels = ticket.coordinate_levels();
-		for (coordinate, level) in coordinate_levels.into_iter() {
-			let chunk_id = format!(
-				"<{}, {}, {}> @ {:?}",
-				coordinate[0], coordinate[1], coordinate[2], level
+	fn load_pending_chunks(&mut self) {
+		let mut budget = self.chunks_per_update;
+		while budget > 0 {
+			let mut pending = match self.pending_tickets.pop_front() {
+				Some(pending) => pending,
+				None => break,
+			};
+			if self.load_next_chunk(&mut pending) {
+				budget -= 1;
+			}
+			// A ticket dropped part-way through loading is still bound,
+			// so the chunks it did load are released by `update_dropped_tickets`.
+			if pending.remaining.is_empty() || pending.ticket.strong_count() == 0 {
+				self.bind_ticket(pending);
+			} else {
+				self.pending_tickets.push_back(pending);
+			}
+		}
+	}
+
+	/// Loads the next chunk of a pending ticket, returning false if there was no chunk to load.
+	fn load_next_chunk(&mut self, pending: &mut PendingTicket) -> bool {
+		if pending.ticket.strong_count() == 0 {
+			return false;
+		}
+		let (coordinate, level) = match pending.remaining.pop_front() {
+			Some(next) => next,
+			None => return false,
+		};
+
+		let chunk_id = format!(
+			"<{}, {}, {}> @ {:?}",
+			coordinate[0], coordinate[1], coordinate[2], level
+		);
+		profiling::scope!("load-chunk", chunk_id.as_str());
+
+		let arc_chunk = self.sync_load_chunk(coordinate, level);
+		self.insert_or_update_chunk_state(&pending.ticket, coordinate, level, &arc_chunk);
+		pending.loaded.push(coordinate);
+		true
+	}
+
+	fn bind_ticket(&mut self, pending: PendingTicket) {
+		self.ticket_bindings
+			.push((pending.ticket, pending.hint, pending.loaded));
+	}
+
+	/// Loads the hints saved by the previous session and synchronously processes a ticket for each,
+	/// so the previously-loaded region is loaded before any clients request it.
+	#[profiling::function]
+	fn restore_ticket_hints(&mut self) {
+		let hints = match ticket::HintSet::load(&self.root_dir) {
+			Ok(hints) => hints,
+			Err(err) => {
+				log::warn!(target: LOG, "Failed to load chunk ticket hints: {:?}", err);
+				return;
+			}
+		};
+		for hint in hints.iter() {
+			let arc_ticket = Arc::new(Ticket::from(*hint));
+			self.sync_process_ticket(Arc::downgrade(&arc_ticket));
+			self.hinted_tickets.push(arc_ticket);
+		}
+		if !self.hinted_tickets.is_empty() {
+			log::debug!(
+				target: LOG,
+				"Restored {} chunk tickets from hints",
+				self.hinted_tickets.len()
 			);
-			profiling::scope!("load-chunk", chunk_id.as_str());
+			self.hint_expiration = Some(self.clock.now() + self.hint_duration);
+		}
+	}
 
-			let arc_chunk = self.sync_load_chunk(coordinate, level);
-			chunks.push((coordinate, arc_chunk, level));
+	/// Drops the tickets restored from hints once they have been held for `hint_duration`.
+	/// The dropped tickets are then handled by [`update_dropped_tickets`](Self::update_dropped_tickets) like any other.
+	fn release_expired_hints(&mut self) {
+		match self.hint_expiration {
+			Some(expiration) if self.clock.now() >= expiration => {
+				self.hint_expiration = None;
+				self.hinted_tickets.clear();
+			}
+			_ => {}
 		}
-		chunks
+	}
+
+	/// Returns the hints for every active ticket and every ticket dropped within the last `expiration_delay`.
+	fn ticket_hints(&self) -> ticket::HintSet {
+		let now = self.clock.now();
+		let mut hints = ticket::HintSet::default();
+		for pending in self.pending_tickets.iter() {
+			if pending.ticket.strong_count() > 0 {
+				hints.insert(pending.hint);
+			}
+		}
+		for (weak_ticket, hint, _chunks) in self.ticket_bindings.iter() {
+			if weak_ticket.strong_count() > 0 {
+				hints.insert(*hint);
+			}
+		}
+		for (dropped_at, hint) in self.dropped_hints.iter() {
+			if now.duration_since(*dropped_at) <= self.expiration_delay() {
+				hints.insert(*hint);
+			}
+		}
+		hints
+	}
+
+	fn generator(&mut self) -> &generator::Pipeline {
+		let plugins = crate::plugin::Manager::read().unwrap();
+		// The decorators are registered by the active plugins,
+		// so they are registered again if the plugin config has been reloaded since.
+		let is_stale = match self.generator_revision {
+			Some(revision) => revision != plugins.revision(),
+			None => false,
+		};
+		if self.generator.is_none() || is_stale {
+			let mut pipeline = generator::Pipeline::for_world(self.seed, &plugins);
+			// Placements waiting for their chunks are kept, the new decorators would not make them again.
+			if let Some(previous) = self.generator.take() {
+				pipeline = pipeline.with_deferred_from(previous);
+			}
+			for decorator in pipeline.decorators().iter() {
+				log::info!(target: LOG, "Decorating generated chunks with {:?}", decorator);
+			}
+			self.generator = Some(pipeline);
+			self.generator_revision = Some(plugins.revision());
+		}
+		self.generator.as_ref().unwrap()
+	}
+
+	fn save_ticket_hints(&self) -> Result<()> {
+		self.ticket_hints().save(&self.root_dir)
 	}
 
 	fn sync_load_chunk(&mut self, coordinate: Point3<i64>, level: Level) -> chunk::ArcLock {
@@ -166,9 +452,22 @@ impl ThreadState {
 			}
 			None => {
 				let root_dir = self.root_dir.clone();
-				let arc_chunk = Chunk::load_or_generate(&coordinate, level, root_dir);
-				let mut cache = self.cache.write().unwrap();
-				cache.insert(coordinate, Arc::downgrade(&arc_chunk));
+				let start = std::time::Instant::now();
+				let (arc_chunk, source) =
+					Chunk::load_or_generate(&coordinate, level, root_dir, self.generator());
+				let duration = start.elapsed();
+				self.cache
+					.write()
+					.unwrap()
+					.insert(coordinate, Arc::downgrade(&arc_chunk));
+				if source == Source::Generated {
+					self.apply_deferred_to_loaded();
+				}
+				self.events.emit(Event::Loaded {
+					coordinate,
+					source,
+					duration,
+				});
 				(true, arc_chunk)
 			}
 		};
@@ -176,6 +475,24 @@ impl ThreadState {
 		arc_chunk
 	}
 
+	/// Places the blocks which the generator deferred into chunks that are already loaded (e.g. a tree on the edge
+	/// of a newly generated chunk growing into its loaded neighbor), instead of waiting for them to be loaded again.
+	fn apply_deferred_to_loaded(&self) {
+		let generator = match &self.generator {
+			Some(generator) => generator,
+			None => return,
+		};
+		let cache = self.cache.read().unwrap();
+		for coordinate in generator.deferred_coordinates().into_iter() {
+			let arc_chunk = match cache.find(&coordinate).and_then(|weak| weak.upgrade()) {
+				Some(arc_chunk) => arc_chunk,
+				None => continue,
+			};
+			let placements = generator.take_deferred(&coordinate);
+			arc_chunk.write().unwrap().apply_placements(placements);
+		}
+	}
+
 	fn insert_or_update_chunk_state(
 		&mut self,
 		weak_ticket: &Weak<Ticket>,
@@ -208,7 +525,10 @@ impl ThreadState {
 	/// are sent to the pending_unload list.
 	#[profiling::function]
 	fn update_dropped_tickets(&mut self) {
-		let now = std::time::Instant::now();
+		let now = self.clock.now();
+		let expiration_delay = self.expiration_delay();
+		self.dropped_hints
+			.retain(|(dropped_at, _)| now.duration_since(*dropped_at) <= expiration_delay);
 		// Can use `Vec::drain_filter` when that api stabilizes.
 		// O(n) performance where `n` is the number of loaded chunks
 		let mut i = 0;
@@ -216,7 +536,8 @@ impl ThreadState {
 			// if there are no strong references, the ticket has been dropped
 			if self.ticket_bindings[i].0.strong_count() == 0 {
 				// dropped tickets mean their chunks should be moved to a pending list of chunks that will be removed soon
-				let (_dropped_ticket, chunks) = self.ticket_bindings.remove(i);
+				let (_dropped_ticket, hint, chunks) = self.ticket_bindings.remove(i);
+				self.dropped_hints.push((now, hint));
 				// Iterate over all the chunks that the dropped ticket referenced
 				for coordinate in chunks {
 					let state = self.chunk_states.get_mut(&coordinate).unwrap();
@@ -239,7 +560,7 @@ impl ThreadState {
 	fn has_expired_chunks(&self) -> bool {
 		match self.earliest_expiration_timestamp {
 			Some(insertion_time) => {
-				std::time::Instant::now().duration_since(insertion_time) > self.expiration_delay
+				self.clock.now().duration_since(insertion_time) > self.expiration_delay()
 			}
 			None => false,
 		}
@@ -252,14 +573,14 @@ impl ThreadState {
 		self.earliest_expiration_timestamp = None;
 
 		let mut chunks_for_unloading = Vec::new();
-		let now = std::time::Instant::now();
+		let now = self.clock.now();
 		// Can use `Vec::drain_filter` when that api stabilizes.
 		// O(n) performance where `n` is the number of loaded chunks
 		let mut i = 0;
 		while i < self.ticketless_chunks.len() {
 			let (insertion_time, coordinate) = self.ticketless_chunks[i].clone();
 			// A chunk has expired if the amount of time since insertion exceeds the maximum.
-			let has_expired = now.duration_since(insertion_time) > self.expiration_delay;
+			let has_expired = now.duration_since(insertion_time) > self.expiration_delay();
 			// If the chunk has any new tickets which reference it, it shouldnt be dropped.
 			let has_been_renewed = if let Some(state) = self.chunk_states.get(&coordinate) {
 				state.tickets.len() > 0
@@ -308,8 +629,21 @@ impl ThreadState {
 				// unload the chunk:
 				// 1. save to disk
 				// 2. drop the arc
-				let chunk = arc_chunk.read().unwrap();
-				chunk.save()
+				let saved = match arc_chunk.write().unwrap().save() {
+					Ok(()) => true,
+					Err(err) => {
+						log::error!(
+							target: LOG,
+							"Failed to save chunk <{}, {}, {}>: {:?}",
+							coordinate.x,
+							coordinate.y,
+							coordinate.z,
+							err
+						);
+						false
+					}
+				};
+				self.events.emit(Event::Unloaded { coordinate, saved });
 			}
 		}
 	}
@@ -362,3 +696,543 @@ impl ChunkState {
 		}
 	}
 }
+
+#[cfg(test)]
+mod events {
+	use super::*;
+	use crate::server::world::chunk::event::Source;
+	use engine::channels::broadcast::BusReader;
+	use std::sync::RwLock;
+
+	fn create_state() -> (ThreadState, BusReader<Event>) {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-chunk-events-{}",
+			uuid::Uuid::new_v4()
+		));
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let recv = events.add_recv();
+		let state = ThreadState::new(root_dir, cache, events).with_empty_generator();
+		(state, recv)
+	}
+
+	fn next_event(recv: &mut BusReader<Event>) -> Event {
+		recv.recv_timeout(std::time::Duration::from_secs(1))
+			.expect("no chunk event was emitted")
+	}
+
+	#[test]
+	fn load_new_chunk_emits_generated() {
+		let (mut state, mut recv) = create_state();
+		let coordinate = Point3::new(2, 0, -1);
+		let _chunk = state.sync_load_chunk(coordinate, Level::Ticking);
+		match next_event(&mut recv) {
+			Event::Loaded {
+				coordinate: loaded,
+				source,
+				..
+			} => {
+				assert_eq!(loaded, coordinate);
+				assert_eq!(source, Source::Generated);
+			}
+			event => panic!("expected a loaded event, found {:?}", event),
+		}
+		// The chunk is already in the cache, so loading it again does not emit a new event.
+		let _chunk_again = state.sync_load_chunk(coordinate, Level::Ticking);
+		assert!(recv.try_recv().is_err());
+	}
+
+	#[test]
+	fn reload_saved_chunk_emits_disk() {
+		let (mut state, mut recv) = create_state();
+		let coordinate = Point3::new(0, 1, 0);
+
+		let arc_chunk = state.sync_load_chunk(coordinate, Level::Ticking);
+		assert!(matches!(
+			next_event(&mut recv),
+			Event::Loaded {
+				source: Source::Generated,
+				..
+			}
+		));
+
+		state.unload_expired_chunks(vec![(coordinate, arc_chunk)]);
+		assert_eq!(
+			next_event(&mut recv),
+			Event::Unloaded {
+				coordinate,
+				saved: true
+			}
+		);
+
+		let path = Chunk::create_path_for(state.root_dir.clone(), &coordinate);
+		assert!(path.exists());
+
+		let _chunk = state.sync_load_chunk(coordinate, Level::Ticking);
+		match next_event(&mut recv) {
+			Event::Loaded { source, .. } => assert_eq!(source, Source::Disk),
+			event => panic!("expected a loaded event, found {:?}", event),
+		}
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+}
+
+#[cfg(test)]
+mod ticket_hints {
+	use super::*;
+	use crate::server::world::chunk::ParameterizedLevel;
+	use engine::channels::broadcast::BusReader;
+	use std::{collections::HashSet, sync::RwLock};
+
+	fn create_state(root_dir: &PathBuf) -> (ThreadState, BusReader<Event>) {
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let recv = events.add_recv();
+		let state = ThreadState::new(root_dir.clone(), cache, events).with_empty_generator();
+		(state, recv)
+	}
+
+	fn create_root_dir() -> PathBuf {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-ticket-hints-{}",
+			uuid::Uuid::new_v4()
+		));
+		root_dir
+	}
+
+	fn spawn_ticket() -> Arc<Ticket> {
+		Arc::new(Ticket {
+			coordinate: Point3::new(0, 0, 0),
+			level: ParameterizedLevel::Minimal,
+		})
+	}
+
+	#[test]
+	fn restores_saved_spawn_region() {
+		let root_dir = create_root_dir();
+		let spawn = spawn_ticket();
+		{
+			let (mut state, _recv) = create_state(&root_dir);
+			state.sync_process_ticket(Arc::downgrade(&spawn));
+			state.save_ticket_hints().unwrap();
+		}
+
+		// No tickets are submitted to the restored state, the region is loaded purely from the saved hints.
+		let (mut state, mut recv) = create_state(&root_dir);
+		state.restore_ticket_hints();
+		let mut loaded = HashSet::new();
+		while let Ok(event) = recv.try_recv() {
+			if let Event::Loaded { coordinate, .. } = event {
+				loaded.insert(coordinate);
+			}
+		}
+		let expected = spawn
+			.coordinate_levels()
+			.into_iter()
+			.map(|(coordinate, _level)| coordinate)
+			.collect::<HashSet<_>>();
+		assert_eq!(loaded, expected);
+		for coordinate in expected.iter() {
+			assert!(state.chunk_states.contains_key(coordinate));
+		}
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+
+	#[test]
+	fn recently_dropped_ticket_is_saved() {
+		let root_dir = create_root_dir();
+		let (mut state, _recv) = create_state(&root_dir);
+		let spawn = spawn_ticket();
+		state.sync_process_ticket(Arc::downgrade(&spawn));
+		let hint = ticket::Hint::from(&*spawn);
+		drop(spawn);
+		state.update_dropped_tickets();
+
+		let hints = state.ticket_hints();
+		assert_eq!(hints.iter().collect::<Vec<_>>(), vec![&hint]);
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+
+	#[test]
+	fn hinted_tickets_are_released() {
+		let root_dir = create_root_dir();
+		{
+			let mut hints = ticket::HintSet::default();
+			hints.insert(ticket::Hint::from(&*spawn_ticket()));
+			hints.save(&root_dir).unwrap();
+		}
+
+		let (mut state, _recv) = create_state(&root_dir);
+		state.hint_duration = std::time::Duration::from_secs(0);
+		state.restore_ticket_hints();
+		assert_eq!(state.hinted_tickets.len(), 1);
+
+		state.release_expired_hints();
+		state.update_dropped_tickets();
+		assert!(state.hinted_tickets.is_empty());
+		// Without any runtime tickets, the restored chunks are now waiting to be unloaded.
+		assert_eq!(state.ticketless_chunks.len(), state.chunk_states.len());
+
+		let _ = std::fs::remove_dir_all(&root_dir);
+	}
+}
+
+#[cfg(test)]
+mod fair_loading {
+	use super::*;
+	use crate::server::world::chunk::ParameterizedLevel;
+	use std::sync::RwLock;
+
+	fn create_state() -> (ThreadState, engine::channels::broadcast::BusReader<Event>) {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-fair-loading-{}",
+			uuid::Uuid::new_v4()
+		));
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let recv = events.add_recv();
+		let state = ThreadState::new(root_dir, cache, events).with_empty_generator();
+		(state, recv)
+	}
+
+	fn player_ticket(x: i64) -> Arc<Ticket> {
+		Arc::new(Ticket {
+			coordinate: Point3::new(x, 0, 0),
+			level: ParameterizedLevel::Minimal,
+		})
+	}
+
+	#[test]
+	fn players_are_interleaved() {
+		let (mut state, mut recv) = create_state();
+		state.chunks_per_update = 4;
+		// The tickets are far enough apart that they share no chunks,
+		// so every chunk of each ticket emits a loaded event.
+		let first = player_ticket(0);
+		let second = player_ticket(100);
+		let chunks_per_ticket = first.coordinate_levels().len();
+		state
+			.pending_tickets
+			.push_back(PendingTicket::new(Arc::downgrade(&first)).unwrap());
+		state
+			.pending_tickets
+			.push_back(PendingTicket::new(Arc::downgrade(&second)).unwrap());
+
+		let mut updates = 0;
+		while !state.pending_tickets.is_empty() {
+			state.load_pending_chunks();
+			updates += 1;
+		}
+		assert_eq!(updates, (chunks_per_ticket * 2 + 3) / 4);
+
+		let mut loaded_by_first = Vec::new();
+		while let Ok(event) = recv.try_recv() {
+			if let Event::Loaded { coordinate, .. } = event {
+				loaded_by_first.push(coordinate.x < 50);
+			}
+		}
+		assert_eq!(loaded_by_first.len(), chunks_per_ticket * 2);
+		// Neither player waits for the other's entire region, each load alternates between them.
+		for (i, is_first) in loaded_by_first.iter().enumerate() {
+			assert_eq!(*is_first, i % 2 == 0);
+		}
+		assert_eq!(state.ticket_bindings.len(), 2);
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+
+	#[test]
+	fn dropped_ticket_stops_loading() {
+		let (mut state, _recv) = create_state();
+		state.chunks_per_update = 1;
+		let ticket = player_ticket(0);
+		state
+			.pending_tickets
+			.push_back(PendingTicket::new(Arc::downgrade(&ticket)).unwrap());
+		state.load_pending_chunks();
+		drop(ticket);
+		state.load_pending_chunks();
+
+		assert!(state.pending_tickets.is_empty());
+		assert_eq!(state.chunk_states.len(), 1);
+		// The chunk which was loaded before the ticket was dropped is released like any other dropped ticket.
+		state.update_dropped_tickets();
+		assert_eq!(state.ticketless_chunks.len(), 1);
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+}
+
+#[cfg(test)]
+mod expiration {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		server::world::chunk::{MockClock, ParameterizedLevel},
+	};
+	use engine::channels::broadcast::BusReader;
+	use std::{sync::RwLock, time::Duration};
+
+	fn create_state(clock: &MockClock) -> (ThreadState, BusReader<Event>) {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-chunk-expiration-{}",
+			uuid::Uuid::new_v4()
+		));
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let events = EventBus::new();
+		let recv = events.add_recv();
+		let state = ThreadState::new(root_dir, cache, events).with_clock(clock.clone());
+		(state, recv)
+	}
+
+	fn player_ticket(x: i64) -> Arc<Ticket> {
+		Arc::new(Ticket {
+			coordinate: Point3::new(x, 0, 0),
+			level: ParameterizedLevel::Minimal,
+		})
+	}
+
+	/// Binds a ticket to an empty chunk at its coordinate, without generating or loading the chunk.
+	fn bind(state: &mut ThreadState, arc_ticket: &Arc<Ticket>) -> Point3<i64> {
+		let coordinate = arc_ticket.coordinate;
+		let level = Level::Minimal;
+		let path = Chunk::create_path_for(state.root_dir.clone(), &coordinate);
+		let arc_chunk = Arc::new(RwLock::new(Chunk::new(
+			path,
+			CommonChunk::new(coordinate),
+			level,
+		)));
+		state
+			.cache
+			.write()
+			.unwrap()
+			.insert(coordinate, Arc::downgrade(&arc_chunk));
+		let weak_ticket = Arc::downgrade(arc_ticket);
+		state.insert_or_update_chunk_state(&weak_ticket, coordinate, level, &arc_chunk);
+		state.bind_ticket(PendingTicket {
+			ticket: weak_ticket,
+			hint: ticket::Hint::from(&**arc_ticket),
+			remaining: VecDeque::new(),
+			loaded: vec![coordinate],
+		});
+		coordinate
+	}
+
+	fn unloaded(recv: &mut BusReader<Event>) -> Vec<Point3<i64>> {
+		let mut unloaded = Vec::new();
+		while let Ok(event) = recv.try_recv() {
+			if let Event::Unloaded { coordinate, .. } = event {
+				unloaded.push(coordinate);
+			}
+		}
+		unloaded
+	}
+
+	#[test]
+	fn dropped_chunk_unloads_after_delay() {
+		let clock = MockClock::default();
+		let (mut state, mut recv) = create_state(&clock);
+		let ticket = player_ticket(0);
+		let coordinate = bind(&mut state, &ticket);
+
+		drop(ticket);
+		state.update_dropped_tickets();
+		assert_eq!(state.ticketless_chunks.len(), 1);
+		assert!(!state.has_expired_chunks());
+
+		// A chunk expires only once it has been ticketless for longer than the delay.
+		clock.advance(state.expiration_delay());
+		assert!(!state.has_expired_chunks());
+		assert!(state.find_expired_chunks().is_empty());
+		assert_eq!(state.ticketless_chunks.len(), 1);
+
+		clock.advance(Duration::from_millis(1));
+		assert!(state.has_expired_chunks());
+		let expired = state.find_expired_chunks();
+		assert_eq!(
+			expired.iter().map(|(coord, _)| *coord).collect::<Vec<_>>(),
+			vec![coordinate]
+		);
+		assert!(state.ticketless_chunks.is_empty());
+		assert!(state.chunk_states.is_empty());
+		assert_eq!(state.earliest_expiration_timestamp, None);
+
+		state.unload_expired_chunks(expired);
+		assert_eq!(unloaded(&mut recv), vec![coordinate]);
+		assert!(state.cache.read().unwrap().find(&coordinate).is_none());
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+
+	#[test]
+	fn changed_expiration_delay_is_observed() {
+		let clock = MockClock::default();
+		let (mut state, _recv) = create_state(&clock);
+		let limits = Arc::new(Limits::default());
+		state.limits = limits.clone();
+		let ticket = player_ticket(0);
+		bind(&mut state, &ticket);
+
+		drop(ticket);
+		state.update_dropped_tickets();
+		clock.advance(Duration::from_secs(10));
+		assert!(!state.has_expired_chunks());
+
+		// Shortening the delay while the thread is running applies to chunks which are already ticketless.
+		limits.set_expiration_delay(Duration::from_secs(5));
+		assert!(state.has_expired_chunks());
+		assert_eq!(state.find_expired_chunks().len(), 1);
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+
+	#[test]
+	fn renewed_chunk_is_not_unloaded() {
+		let clock = MockClock::default();
+		let (mut state, mut recv) = create_state(&clock);
+		let first = player_ticket(0);
+		let coordinate = bind(&mut state, &first);
+
+		drop(first);
+		state.update_dropped_tickets();
+		assert_eq!(state.ticketless_chunks.len(), 1);
+
+		clock.advance(state.expiration_delay() / 2);
+		let second = player_ticket(0);
+		bind(&mut state, &second);
+
+		// The renewed chunk leaves the ticketless list, but stays loaded.
+		clock.advance(state.expiration_delay());
+		assert!(state.has_expired_chunks());
+		assert!(state.find_expired_chunks().is_empty());
+		assert!(state.ticketless_chunks.is_empty());
+		assert!(state.chunk_states.contains_key(&coordinate));
+		assert!(unloaded(&mut recv).is_empty());
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+
+	#[test]
+	fn chunks_expire_in_drop_order() {
+		let clock = MockClock::default();
+		let (mut state, _recv) = create_state(&clock);
+		let first = player_ticket(0);
+		let second = player_ticket(100);
+		let first_coord = bind(&mut state, &first);
+		let second_coord = bind(&mut state, &second);
+
+		drop(first);
+		state.update_dropped_tickets();
+		let first_dropped_at = clock.now();
+		clock.advance(Duration::from_secs(10));
+		drop(second);
+		state.update_dropped_tickets();
+		let second_dropped_at = clock.now();
+		assert_eq!(state.earliest_expiration_timestamp, Some(first_dropped_at));
+
+		clock.advance(state.expiration_delay() - Duration::from_secs(5));
+		let expired = state.find_expired_chunks();
+		assert_eq!(
+			expired.iter().map(|(coord, _)| *coord).collect::<Vec<_>>(),
+			vec![first_coord]
+		);
+		assert_eq!(
+			state.ticketless_chunks,
+			vec![(second_dropped_at, second_coord)]
+		);
+		// The next check is scheduled for the remaining chunk, so nothing is expired until it is.
+		assert_eq!(state.earliest_expiration_timestamp, Some(second_dropped_at));
+		assert!(!state.has_expired_chunks());
+
+		clock.advance(Duration::from_secs(10));
+		assert!(state.has_expired_chunks());
+		let expired = state.find_expired_chunks();
+		assert_eq!(
+			expired.iter().map(|(coord, _)| *coord).collect::<Vec<_>>(),
+			vec![second_coord]
+		);
+		assert!(state.ticketless_chunks.is_empty());
+	}
+}
+
+#[cfg(test)]
+mod backup {
+	use super::*;
+	use crate::{common::utility::Versioned, server::world::chunk::ParameterizedLevel};
+	use std::{sync::RwLock, time::Duration};
+
+	fn create_state() -> ThreadState {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-chunk-backup-{}",
+			uuid::Uuid::new_v4()
+		));
+		let cache = Arc::new(RwLock::new(cache::Cache::new()));
+		ThreadState::new(root_dir, cache, EventBus::new()).with_empty_generator()
+	}
+
+	fn ticket_at(x: i64) -> Arc<Ticket> {
+		Arc::new(Ticket {
+			coordinate: Point3::new(x, 0, 0),
+			level: ParameterizedLevel::Minimal,
+		})
+	}
+
+	#[test]
+	fn flushes_loaded_chunks_and_queues_tickets_while_paused() {
+		let mut state = create_state();
+		let (sender, receiver) = engine::channels::mpsc::unbounded();
+		let spawn = ticket_at(0);
+		state.sync_process_ticket(Arc::downgrade(&spawn));
+		let loaded_count = state.chunk_states.len();
+		assert!(loaded_count > 0);
+
+		state.backup.request_pause();
+		state.update(&receiver);
+		let report = state
+			.backup
+			.wait_until_paused(Duration::from_secs(0))
+			.unwrap();
+		assert_eq!(report.saved, loaded_count);
+		assert_eq!(report.failed, 0);
+		// Every loaded chunk matches what is on disk.
+		for (coordinate, chunk_state) in state.chunk_states.iter() {
+			let path = Chunk::create_path_for(state.root_dir.clone(), coordinate);
+			let on_disk = std::fs::read(&path).unwrap();
+			let in_memory = chunk_state
+				.chunk
+				.read()
+				.unwrap()
+				.chunk
+				.to_versioned_bytes()
+				.unwrap();
+			assert_eq!(on_disk, in_memory);
+		}
+
+		// Tickets sent while paused are queued, but not loaded.
+		let far = ticket_at(100);
+		sender.try_send(Arc::downgrade(&far)).unwrap();
+		state.update(&receiver);
+		assert!(state.backup.is_paused());
+		assert_eq!(state.pending_tickets.len(), 1);
+		assert_eq!(state.chunk_states.len(), loaded_count);
+
+		state.backup.resume();
+		while !state.pending_tickets.is_empty() {
+			state.update(&receiver);
+		}
+		assert_eq!(
+			state.chunk_states.len(),
+			loaded_count + far.coordinate_levels().len()
+		);
+
+		let _ = std::fs::remove_dir_all(&state.root_dir);
+	}
+}
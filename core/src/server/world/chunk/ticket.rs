@@ -6,6 +6,9 @@ use anyhow::Result;
 use engine::math::nalgebra::{Point3, Vector3};
 use std::sync::Arc;
 
+mod hint;
+pub use hint::*;
+
 /// The channel through which chunk [tickets are sent](Ticket::submit).
 pub(crate) type Sender = engine::channels::mpsc::Sender<std::sync::Weak<Ticket>>;
 /// The channel through which chunk tickets are received by the [`chunk loading thread`](super::thread::start).
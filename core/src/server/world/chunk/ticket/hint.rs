@@ -0,0 +1,74 @@
+use crate::server::world::chunk::{ParameterizedLevel, Ticket};
+use anyhow::Result;
+use engine::math::nalgebra::Point3;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A record of a chunk [`Ticket`] which was recently active in the world.
+///
+/// Hints are saved when the world is closed, and resubmitted as tickets when the world is next loaded,
+/// so the previously-loaded region starts loading before any clients have joined.
+/// Hints are not authoritative; the tickets created from them are only held for a short time after the world loads.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hint {
+	pub coordinate: Point3<i64>,
+	pub level: ParameterizedLevel,
+}
+
+impl From<&Ticket> for Hint {
+	fn from(ticket: &Ticket) -> Self {
+		Self {
+			coordinate: ticket.coordinate,
+			level: ticket.level,
+		}
+	}
+}
+
+impl From<Hint> for Ticket {
+	fn from(hint: Hint) -> Self {
+		Self {
+			coordinate: hint.coordinate,
+			level: hint.level,
+		}
+	}
+}
+
+/// The set of [`hints`](Hint) saved alongside the world.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct HintSet(Vec<Hint>);
+
+impl HintSet {
+	fn create_path(mut world_root_dir: PathBuf) -> PathBuf {
+		world_root_dir.push("ticket_hints.json");
+		world_root_dir
+	}
+
+	/// Loads the hints saved in the world directory.
+	/// If the world has never saved its hints, the set is empty.
+	pub fn load(world_root_dir: &Path) -> Result<Self> {
+		let path = Self::create_path(world_root_dir.to_owned());
+		if !path.exists() {
+			return Ok(Self::default());
+		}
+		let raw = std::fs::read_to_string(&path)?;
+		Ok(serde_json::from_str(&raw)?)
+	}
+
+	pub fn save(&self, world_root_dir: &Path) -> Result<()> {
+		std::fs::create_dir_all(&world_root_dir)?;
+		let json = serde_json::to_string_pretty(&self)?;
+		std::fs::write(&Self::create_path(world_root_dir.to_owned()), json)?;
+		Ok(())
+	}
+
+	/// Adds the hint to the set, if an identical hint is not already present.
+	pub fn insert(&mut self, hint: Hint) {
+		if !self.0.contains(&hint) {
+			self.0.push(hint);
+		}
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &Hint> {
+		self.0.iter()
+	}
+}
@@ -0,0 +1,60 @@
+use crate::{common::world::clock::Clock, server::tick::FixedTimestep};
+use engine::EngineSystem;
+use std::{
+	sync::{Arc, RwLock, Weak},
+	time::Duration,
+};
+
+/// Server system which advances the time of day by one tick for every server tick.
+pub struct UpdateClock {
+	clock: Weak<RwLock<Clock>>,
+	timestep: FixedTimestep,
+}
+
+impl UpdateClock {
+	pub fn new(clock: &Arc<RwLock<Clock>>, ticks_per_second: u32) -> Self {
+		Self {
+			clock: Arc::downgrade(&clock),
+			timestep: FixedTimestep::new(ticks_per_second),
+		}
+	}
+}
+
+impl EngineSystem for UpdateClock {
+	fn update(&mut self, delta_time: Duration, _: bool) {
+		profiling::scope!("subsystem:world-clock");
+		let ticks = self.timestep.advance(delta_time);
+		if ticks == 0 {
+			return;
+		}
+		if let Some(arc_clock) = self.clock.upgrade() {
+			arc_clock.write().unwrap().advance(ticks as u64);
+		}
+	}
+}
+
+#[cfg(test)]
+mod update_clock {
+	use super::*;
+
+	fn run(frame_times: &[u64]) -> u64 {
+		let clock = Arc::new(RwLock::new(Clock::default()));
+		let mut system = UpdateClock::new(&clock, 20);
+		for millis in frame_times.iter() {
+			system.update(Duration::from_millis(*millis), true);
+		}
+		let time = clock.read().unwrap().time();
+		time
+	}
+
+	#[test]
+	fn advances_deterministically() {
+		let start = Clock::default().time();
+		// 3 seconds at 20 ticks per second, regardless of the frame rate.
+		assert_eq!(run(&[1000, 1000, 1000]), start + 60);
+		let mut frames = vec![16; 187];
+		frames.push(8);
+		assert_eq!(run(&frames), start + 60);
+		assert_eq!(run(&[30, 20, 7, 3]), start + 1);
+	}
+}
@@ -1,13 +1,14 @@
+use crate::common::{utility::ThreadHandle, world::generator};
 use crate::server::world::{
-	chunk::{cache, thread, ticket, Level, Ticket},
+	chunk::{cache, thread, ticket, BackupControl, EventBus, Level, Limits, Ticket, Waiter},
 	Settings,
 };
-use crate::common::utility::ThreadHandle;
 use anyhow::Result;
 use engine::math::nalgebra::Point3;
 use std::{
+	mem::MaybeUninit,
 	path::PathBuf,
-	sync::{Arc, RwLock, Weak},
+	sync::{Arc, Once, RwLock, Weak},
 };
 
 /// Alias for Arc<RwLock<[`Database`](Database)>>.
@@ -16,9 +17,12 @@ pub type ArcLockDatabase = Arc<RwLock<Database>>;
 /// The data about a world (its chunks, settings, etc).
 /// Exists on the server, does not contain presentational/graphical data.
 pub struct Database {
-	_settings: Settings,
+	settings: Settings,
 	chunk_cache: cache::ArcLock,
-	_load_request_sender: Arc<ticket::Sender>,
+	chunk_events: EventBus,
+	chunk_limits: Arc<Limits>,
+	chunk_backup: Arc<BackupControl>,
+	load_request_sender: Arc<ticket::Sender>,
 	// When this is dropped, the loading thread stops.
 	_chunk_thread_handle: ThreadHandle,
 
@@ -26,34 +30,68 @@ pub struct Database {
 }
 
 impl Database {
-	pub fn new(root_path: PathBuf) -> anyhow::Result<Self> {
+	/// Loads (or creates) the world saved at `root_path`, whose chunks are reported in the server's metrics under `name`.
+	pub fn new(name: &str, root_path: PathBuf) -> anyhow::Result<Self> {
 		let settings = Settings::load(&root_path).unwrap();
 
-		let chunk_cache = Arc::new(RwLock::new(cache::Cache::new()));
+		let chunk_cache = Arc::new(RwLock::new(cache::Cache::new().with_world_name(name)));
+
+		let chunk_events = EventBus::new();
+		let chunk_limits = Arc::new(Limits::default());
+		let chunk_backup = Arc::new(BackupControl::default());
 
 		let (load_request_sender, load_request_receiver) = engine::channels::mpsc::unbounded();
-		let thread_handle = thread::start(root_path, load_request_receiver, &chunk_cache)?;
+		let thread_handle = thread::start(
+			root_path,
+			load_request_receiver,
+			&chunk_cache,
+			&chunk_events,
+			settings.persist_ticket_hints(),
+			settings.chunks_per_update(),
+			generator::Pipeline::seed_from_str(settings.seed()),
+			&chunk_limits,
+			&chunk_backup,
+		)?;
 
 		let load_request_sender = Arc::new(load_request_sender);
-		*Self::ticket_sender_static() = Some(Arc::downgrade(&load_request_sender));
 
 		Ok(Self {
-			_settings: settings,
+			settings,
 			chunk_cache,
-			_load_request_sender: load_request_sender,
+			chunk_events,
+			chunk_limits,
+			chunk_backup,
+			load_request_sender,
 			_chunk_thread_handle: thread_handle,
 
 			held_tickets: Vec::new(),
 		})
 	}
 
-	fn ticket_sender_static() -> &'static mut Option<Weak<ticket::Sender>> {
-		static mut TICKET_SENDER: Option<Weak<ticket::Sender>> = None;
-		unsafe { &mut TICKET_SENDER }
+	/// Makes this the database which tickets are sent to by [`Ticket::submit`], until it is dropped.
+	/// Tickets for any other database must be sent via [`submit_ticket`](Self::submit_ticket).
+	pub fn set_default(&self) {
+		*Self::ticket_sender_static().write().unwrap() =
+			Some(Arc::downgrade(&self.load_request_sender));
+	}
+
+	/// The loading thread of the default database, which is shared by (and locked against) every thread in the process,
+	/// including the databases of other worlds which check if they are the default when they are dropped.
+	fn ticket_sender_static() -> &'static RwLock<Option<Weak<ticket::Sender>>> {
+		static mut TICKET_SENDER: (MaybeUninit<RwLock<Option<Weak<ticket::Sender>>>>, Once) =
+			(MaybeUninit::uninit(), Once::new());
+		unsafe {
+			TICKET_SENDER
+				.1
+				.call_once(|| TICKET_SENDER.0.as_mut_ptr().write(RwLock::new(None)));
+			&*TICKET_SENDER.0.as_ptr()
+		}
 	}
 
 	fn ticket_sender() -> Result<Arc<ticket::Sender>> {
 		Ok(Self::ticket_sender_static()
+			.read()
+			.unwrap()
 			.as_ref()
 			.map(|weak| weak.upgrade())
 			.flatten()
@@ -64,25 +102,71 @@ impl Database {
 		Ok(Self::ticket_sender()?.try_send(Arc::downgrade(&ticket))?)
 	}
 
+	/// Sends the ticket to the loading thread of this database.
+	/// Unlike [`Ticket::submit`], which always uses the [`default`](Self::set_default) database,
+	/// this can request chunks for any of the worlds a server is hosting.
+	pub fn submit_ticket(&self, ticket: Ticket) -> Result<Arc<Ticket>> {
+		let arc_ticket = Arc::new(ticket);
+		self.load_request_sender
+			.try_send(Arc::downgrade(&arc_ticket))?;
+		Ok(arc_ticket)
+	}
+
+	pub fn settings(&self) -> &Settings {
+		&self.settings
+	}
+
 	pub fn chunk_cache(&self) -> &cache::ArcLock {
 		&self.chunk_cache
 	}
 
+	/// The limits on chunk loading and replication for this world, which can be changed at runtime.
+	pub fn chunk_limits(&self) -> &Arc<Limits> {
+		&self.chunk_limits
+	}
+
+	/// Pauses the chunk loading thread of this world, so its files can be copied without being modified.
+	pub fn chunk_backup(&self) -> &Arc<BackupControl> {
+		&self.chunk_backup
+	}
+
+	/// Returns a reader which receives the load/unload [`events`](crate::server::world::chunk::Event)
+	/// of every chunk from this point onwards.
+	pub fn add_chunk_event_recv(
+		&self,
+	) -> engine::channels::broadcast::BusReader<crate::server::world::chunk::Event> {
+		self.chunk_events.add_recv()
+	}
+
+	/// Returns a waiter which can block until chunks are loaded, without holding this database.
+	/// It only observes chunks which are loaded after it is created.
+	pub fn chunk_waiter(&self) -> Waiter {
+		Waiter::new(self.chunk_cache.clone(), self.add_chunk_event_recv())
+	}
+
 	pub fn load_origin_chunk(arc_world: &ArcLockDatabase) -> Result<()> {
-		arc_world.write().unwrap().held_tickets.push(
-			Ticket {
-				coordinate: Point3::new(0, 0, 0),
-				level: (Level::Ticking, 2).into(),
-			}
-			.submit()?,
-		);
+		let mut world = arc_world.write().unwrap();
+		let ticket = world.submit_ticket(Ticket {
+			coordinate: Point3::new(0, 0, 0),
+			level: (Level::Ticking, 2).into(),
+		})?;
+		world.held_tickets.push(ticket);
 		Ok(())
 	}
 }
 
 impl Drop for Database {
 	fn drop(&mut self) {
-		*Self::ticket_sender_static() = None;
+		// The check and the reset happen under the same lock,
+		// so another database becoming the default in between is never undone.
+		let mut default_sender = Self::ticket_sender_static().write().unwrap();
+		let is_default = match default_sender.as_ref().map(|weak| weak.upgrade()).flatten() {
+			Some(sender) => Arc::ptr_eq(&sender, &self.load_request_sender),
+			None => false,
+		};
+		if is_default {
+			*default_sender = None;
+		}
 	}
 }
 
@@ -94,3 +178,102 @@ impl std::fmt::Display for NoWorldDatabase {
 		write!(f, "No world database")
 	}
 }
+
+#[cfg(test)]
+mod worlds {
+	use super::*;
+	use crate::server::world::chunk::{Event, ParameterizedLevel};
+
+	fn create_root_dir(name: &str) -> PathBuf {
+		let mut root_dir = std::env::temp_dir();
+		root_dir.push(format!(
+			"crystal-sphinx-world-{}-{}",
+			name,
+			uuid::Uuid::new_v4()
+		));
+		root_dir
+	}
+
+	fn wait_for_loaded(database: &Database, ticket: Ticket) -> (Arc<Ticket>, Point3<i64>) {
+		let mut recv = database.add_chunk_event_recv();
+		let ticket = database.submit_ticket(ticket).unwrap();
+		match recv.recv_timeout(std::time::Duration::from_secs(5)) {
+			Ok(Event::Loaded { coordinate, .. }) => (ticket, coordinate),
+			event => panic!("expected a loaded event, found {:?}", event),
+		}
+	}
+
+	#[test]
+	fn independent_chunk_databases() {
+		let (lobby_dir, arena_dir) = (create_root_dir("lobby"), create_root_dir("arena"));
+		let lobby = Database::new("lobby", lobby_dir.clone()).unwrap();
+		let arena = Database::new("arena", arena_dir.clone()).unwrap();
+
+		let lobby_chunk = Point3::new(0, 0, 0);
+		let arena_chunk = Point3::new(5, 0, 5);
+		let (_lobby_ticket, loaded) = wait_for_loaded(
+			&lobby,
+			Ticket {
+				coordinate: lobby_chunk,
+				level: ParameterizedLevel::Loaded,
+			},
+		);
+		assert_eq!(loaded, lobby_chunk);
+		let (_arena_ticket, loaded) = wait_for_loaded(
+			&arena,
+			Ticket {
+				coordinate: arena_chunk,
+				level: ParameterizedLevel::Loaded,
+			},
+		);
+		assert_eq!(loaded, arena_chunk);
+
+		// Each world only has the chunks that were requested of it.
+		{
+			let lobby_cache = lobby.chunk_cache().read().unwrap();
+			assert!(lobby_cache.find(&lobby_chunk).is_some());
+			assert!(lobby_cache.find(&arena_chunk).is_none());
+			let arena_cache = arena.chunk_cache().read().unwrap();
+			assert!(arena_cache.find(&arena_chunk).is_some());
+			assert!(arena_cache.find(&lobby_chunk).is_none());
+		}
+
+		drop((lobby, arena));
+		let _ = std::fs::remove_dir_all(&lobby_dir);
+		let _ = std::fs::remove_dir_all(&arena_dir);
+	}
+
+	/// The only test which sets the default database, because it is shared by every test in the process.
+	#[test]
+	fn default_database_receives_submitted_tickets() {
+		let (lobby_dir, arena_dir) = (create_root_dir("lobby"), create_root_dir("arena"));
+		let lobby = Database::new("lobby", lobby_dir.clone()).unwrap();
+		let arena = Database::new("arena", arena_dir.clone()).unwrap();
+		assert!(Database::ticket_sender().is_err());
+		lobby.set_default();
+
+		let mut recv = lobby.add_chunk_event_recv();
+		let coordinate = Point3::new(0, 0, 0);
+		let _ticket = Ticket {
+			coordinate,
+			level: ParameterizedLevel::Loaded,
+		}
+		.submit()
+		.unwrap();
+		match recv.recv_timeout(std::time::Duration::from_secs(5)) {
+			Ok(Event::Loaded {
+				coordinate: loaded, ..
+			}) => assert_eq!(loaded, coordinate),
+			event => panic!("expected a loaded event, found {:?}", event),
+		}
+
+		// Unloading the other world doesn't stop tickets from reaching the default world.
+		drop(arena);
+		assert!(Database::ticket_sender().is_ok());
+		drop(lobby);
+		assert!(Database::ticket_sender().is_err());
+
+		let _ = std::fs::remove_dir_all(&lobby_dir);
+		let _ = std::fs::remove_dir_all(&arena_dir);
+	}
+}
@@ -0,0 +1,529 @@
+//! Edits which change many blocks at once (e.g. pasting a [`schematic`](crate::common::world::schematic)
+//! or filling a region), and may span many chunks which are not loaded.
+//!
+//! Each block goes through the [`plugin validated`](chunk::Chunk::apply_block_change) edit path,
+//! and is replicated to the clients which have its chunk like any other block change.
+
+use crate::{
+	block,
+	common::world::{chunk::DIAMETER, schematic::Schematic},
+	plugin,
+	server::world::{
+		chunk::{self, ParameterizedLevel, Ticket},
+		ArcLockDatabase,
+	},
+};
+use anyhow::Result;
+use engine::math::nalgebra::{Point3, Vector3};
+use std::{
+	collections::{BTreeSet, HashMap},
+	net::SocketAddr,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+/// How long to wait for the chunks of an edit to be loaded (or generated).
+pub static LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Splits a world-space block coordinate into the coordinate of its chunk and its offset within that chunk.
+pub fn split(point: Point3<i64>) -> (Point3<i64>, Point3<usize>) {
+	let diameter = DIAMETER as i64;
+	(
+		point.map(|v| v.div_euclid(diameter)),
+		point.map(|v| v.rem_euclid(diameter) as usize),
+	)
+}
+
+/// A change to the block at a world-space block coordinate.
+pub type BlockEdit = (Point3<i64>, Option<block::LookupId>);
+
+/// Returns the edits which paste every block of a schematic (including air) with its minimum corner at `origin`.
+pub fn paste(schematic: &Schematic, origin: Point3<i64>) -> Vec<BlockEdit> {
+	schematic
+		.blocks()
+		.map(|(offset, id)| (origin + offset.cast::<i64>(), id))
+		.collect()
+}
+
+/// The most chunks which a [`fill`] loads and changes at once,
+/// so that filling a large region does not keep every chunk in it loaded (or locked) for the whole edit.
+pub static FILL_BATCH_CHUNKS: usize = 8;
+
+/// Returns the edits which set every block in the region between two (inclusive) corners to `id`.
+pub fn fill(min: Point3<i64>, max: Point3<i64>, id: Option<block::LookupId>) -> Vec<BlockEdit> {
+	let (min, max) = (min.inf(&max), min.sup(&max));
+	let mut edits = Vec::new();
+	for y in min.y..=max.y {
+		for z in min.z..=max.z {
+			for x in min.x..=max.x {
+				edits.push((Point3::new(x, y, z), id));
+			}
+		}
+	}
+	edits
+}
+
+/// Splits the region between two (inclusive) corners into the (inclusive) part of the region in each chunk,
+/// so a large edit can be applied a few chunks at a time.
+pub fn chunk_regions(min: Point3<i64>, max: Point3<i64>) -> Vec<(Point3<i64>, Point3<i64>)> {
+	let (min, max) = (min.inf(&max), min.sup(&max));
+	let diameter = DIAMETER as i64;
+	let (min_chunk, max_chunk) = (split(min).0, split(max).0);
+	let mut regions = Vec::new();
+	for y in min_chunk.y..=max_chunk.y {
+		for z in min_chunk.z..=max_chunk.z {
+			for x in min_chunk.x..=max_chunk.x {
+				let chunk_min = Point3::new(x, y, z) * diameter;
+				let chunk_max = chunk_min + Vector3::new(1, 1, 1) * (diameter - 1);
+				regions.push((min.sup(&chunk_min), max.inf(&chunk_max)));
+			}
+		}
+	}
+	regions
+}
+
+/// How many blocks of an edit were changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Report {
+	pub changed: usize,
+	/// Blocks which already were the requested block.
+	pub unchanged: usize,
+	/// Blocks which a plugin denied changing.
+	pub denied: usize,
+}
+
+impl std::ops::AddAssign for Report {
+	fn add_assign(&mut self, other: Self) {
+		self.changed += other.changed;
+		self.unchanged += other.unchanged;
+		self.denied += other.denied;
+	}
+}
+
+/// The last message of the edits started by a command, which is shown in the debug panel.
+pub type Status = Arc<Mutex<Option<String>>>;
+
+/// Runs an edit on its own thread, so waiting for its chunks to be loaded (or generated)
+/// never stalls the engine thread which runs commands.
+///
+/// The status is set when the edit starts, and replaced with the message (or error) of the edit when it finishes.
+pub fn spawn<F>(name: &str, status: &Status, edit: F) -> std::io::Result<()>
+where
+	F: FnOnce() -> Result<String> + Send + 'static,
+{
+	*status.lock().unwrap() = Some("Waiting for chunks to load...".to_owned());
+	let status = status.clone();
+	std::thread::Builder::new()
+		.name(name.to_owned())
+		.spawn(move || {
+			let message = match edit() {
+				Ok(message) => message,
+				Err(err) => format!("{}", err),
+			};
+			*status.lock().unwrap() = Some(message);
+		})?;
+	Ok(())
+}
+
+/// The chunks which an edit changes, which are kept loaded until this is dropped.
+pub struct Chunks {
+	_tickets: Vec<Arc<Ticket>>,
+	chunks: HashMap<Point3<i64>, chunk::ArcLock>,
+}
+
+impl Chunks {
+	/// Loads (or generates) every chunk which the edits change,
+	/// blocking until they are all loaded so the edit is never partially applied.
+	pub fn load_for(
+		database: &ArcLockDatabase,
+		edits: &[BlockEdit],
+		timeout: Duration,
+	) -> Result<Self> {
+		let coordinates = edits
+			.iter()
+			.map(|(point, _)| split(*point).0)
+			.collect::<BTreeSet<_>>();
+		Self::load(database, coordinates, timeout)
+	}
+
+	/// Loads (or generates) every chunk which contains a block in the region between two (inclusive) corners.
+	pub fn load_region(
+		database: &ArcLockDatabase,
+		min: Point3<i64>,
+		max: Point3<i64>,
+		timeout: Duration,
+	) -> Result<Self> {
+		let (min_chunk, max_chunk) = (split(min).0, split(max).0);
+		let mut coordinates = BTreeSet::new();
+		for x in min_chunk.x..=max_chunk.x {
+			for y in min_chunk.y..=max_chunk.y {
+				for z in min_chunk.z..=max_chunk.z {
+					coordinates.insert(Point3::new(x, y, z));
+				}
+			}
+		}
+		Self::load(database, coordinates, timeout)
+	}
+
+	/// Submits a ticket for each chunk, blocking until they are all in the database's cache.
+	/// The database is only locked while the tickets are submitted, not while waiting for the chunks.
+	fn load(
+		database: &ArcLockDatabase,
+		coordinates: BTreeSet<Point3<i64>>,
+		timeout: Duration,
+	) -> Result<Self> {
+		let (tickets, mut waiter) = {
+			let database = database.read().unwrap();
+			let waiter = database.chunk_waiter();
+			let tickets = coordinates
+				.iter()
+				.map(|coordinate| {
+					database.submit_ticket(Ticket {
+						coordinate: *coordinate,
+						level: ParameterizedLevel::Loaded,
+					})
+				})
+				.collect::<Result<Vec<_>>>()?;
+			(tickets, waiter)
+		};
+
+		let coordinates = coordinates.into_iter().collect::<Vec<_>>();
+		let chunks = waiter.wait(&coordinates, timeout);
+		if chunks.len() < coordinates.len() {
+			return Err(Error::ChunksNotLoaded(
+				coordinates.len() - chunks.len(),
+				timeout,
+			))?;
+		}
+		Ok(Self {
+			_tickets: tickets,
+			chunks,
+		})
+	}
+
+	/// Edits chunks which are already loaded, and are kept loaded by something else.
+	pub fn from_loaded(chunks: Vec<chunk::ArcLock>) -> Self {
+		let chunks = chunks
+			.into_iter()
+			.map(|arc_chunk| {
+				let coordinate = *arc_chunk.read().unwrap().chunk.coordinate();
+				(coordinate, arc_chunk)
+			})
+			.collect();
+		Self {
+			_tickets: Vec::new(),
+			chunks,
+		}
+	}
+
+	/// Returns the block at a world-space block coordinate,
+	/// or None if the chunk containing it is not one of the loaded chunks.
+	pub fn get(&self, point: Point3<i64>) -> Option<Option<block::LookupId>> {
+		let (coordinate, offset) = split(point);
+		let arc_chunk = self.chunks.get(&coordinate)?;
+		let chunk = arc_chunk.read().unwrap();
+		Some(chunk.chunk.block_ids().get(&offset).cloned())
+	}
+
+	/// Applies the edits, locking each chunk once for all of the edits in it.
+	/// Edits to chunks which were not loaded are skipped.
+	pub fn apply(
+		&self,
+		plugins: &plugin::Manager,
+		instigator: Option<SocketAddr>,
+		edits: &[BlockEdit],
+	) -> Report {
+		let mut by_chunk = HashMap::<Point3<i64>, Vec<_>>::new();
+		for (point, id) in edits.iter() {
+			let (coordinate, offset) = split(*point);
+			by_chunk.entry(coordinate).or_default().push((offset, *id));
+		}
+
+		let mut report = Report::default();
+		for (coordinate, chunk_edits) in by_chunk.into_iter() {
+			let arc_chunk = match self.chunks.get(&coordinate) {
+				Some(arc_chunk) => arc_chunk,
+				None => continue,
+			};
+			let mut chunk = arc_chunk.write().unwrap();
+			for (offset, id) in chunk_edits.into_iter() {
+				// Unchanged blocks are not validated, saved, or replicated.
+				if chunk.chunk.block_ids().get(&offset).cloned() == id {
+					report.unchanged += 1;
+					continue;
+				}
+				match chunk.apply_block_change(plugins, instigator, offset, id) {
+					Ok(_) => report.changed += 1,
+					Err(_) => report.denied += 1,
+				}
+			}
+		}
+		report
+	}
+
+	/// Copies the blocks in the region between two corners (inclusive) into a schematic.
+	/// Each chunk is read while it is locked, so no chunk is copied in the middle of an edit.
+	/// Blocks in chunks which were not loaded are copied as air.
+	pub fn copy(&self, min: Point3<i64>, max: Point3<i64>) -> Schematic {
+		let (min, max) = (min.inf(&max), min.sup(&max));
+		let size = (max - min).map(|v| v as usize + 1);
+		let mut schematic = Schematic::new(size);
+		let mut by_chunk = HashMap::<Point3<i64>, Vec<_>>::new();
+		for y in 0..size.y {
+			for z in 0..size.z {
+				for x in 0..size.x {
+					let offset = Vector3::new(x, y, z);
+					let (coordinate, chunk_offset) = split(min + offset.cast::<i64>());
+					by_chunk
+						.entry(coordinate)
+						.or_default()
+						.push((offset, chunk_offset));
+				}
+			}
+		}
+		for (coordinate, offsets) in by_chunk.into_iter() {
+			let arc_chunk = match self.chunks.get(&coordinate) {
+				Some(arc_chunk) => arc_chunk,
+				None => continue,
+			};
+			let chunk = arc_chunk.read().unwrap();
+			for (offset, chunk_offset) in offsets.into_iter() {
+				let id = chunk.chunk.block_ids().get(&chunk_offset).cloned();
+				schematic.set(&offset, id);
+			}
+		}
+		schematic
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("{0} chunks of the edit were not loaded within {1:?}")]
+	ChunksNotLoaded(usize, Duration),
+}
+
+#[cfg(test)]
+mod paste {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		server::world::chunk::{Chunk, Level},
+	};
+	use std::{
+		path::PathBuf,
+		sync::{Arc, RwLock},
+	};
+
+	fn loaded_chunks(coordinates: &[Point3<i64>]) -> Vec<chunk::ArcLock> {
+		coordinates
+			.iter()
+			.map(|coordinate| {
+				let chunk =
+					Chunk::new(PathBuf::new(), CommonChunk::new(*coordinate), Level::Loaded);
+				Arc::new(RwLock::new(chunk))
+			})
+			.collect()
+	}
+
+	fn block_at(chunks: &[chunk::ArcLock], point: Point3<i64>) -> Option<block::LookupId> {
+		let (coordinate, offset) = split(point);
+		let arc_chunk = chunks
+			.iter()
+			.find(|arc_chunk| *arc_chunk.read().unwrap().chunk.coordinate() == coordinate)
+			.unwrap();
+		let id = arc_chunk
+			.read()
+			.unwrap()
+			.chunk
+			.block_ids()
+			.get(&offset)
+			.cloned();
+		id
+	}
+
+	#[test]
+	fn schematic_blocks_are_placed_at_offsets() {
+		let mut schematic = Schematic::new(Vector3::new(2, 2, 2));
+		schematic.set(&Vector3::new(0, 0, 0), Some(1));
+		schematic.set(&Vector3::new(1, 0, 0), Some(2));
+		schematic.set(&Vector3::new(1, 1, 1), Some(3));
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-paste-{}.schematic",
+			uuid::Uuid::new_v4()
+		));
+		schematic.save(&path).unwrap();
+		let schematic = Schematic::load(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+
+		// The origin is at the edge of a chunk, so the paste spans 4 chunks (x and z both cross a boundary).
+		let origin = Point3::new(15, 0, -1);
+		let chunks = loaded_chunks(&[
+			Point3::new(0, 0, -1),
+			Point3::new(1, 0, -1),
+			Point3::new(0, 0, 0),
+			Point3::new(1, 0, 0),
+		]);
+		// Air in the schematic replaces whatever was in the world.
+		chunks[0]
+			.write()
+			.unwrap()
+			.set_block_id(Point3::new(15, 1, 15), Some(9));
+		let _ = chunks[0].write().unwrap().take_block_changes();
+
+		let edit = Chunks::from_loaded(chunks.clone());
+		let report = edit.apply(
+			&plugin::Manager::default(),
+			None,
+			&paste(&schematic, origin),
+		);
+		assert_eq!(
+			report,
+			Report {
+				changed: 4,
+				unchanged: 4,
+				denied: 0
+			}
+		);
+
+		assert_eq!(block_at(&chunks, Point3::new(15, 0, -1)), Some(1));
+		assert_eq!(block_at(&chunks, Point3::new(16, 0, -1)), Some(2));
+		assert_eq!(block_at(&chunks, Point3::new(16, 1, 0)), Some(3));
+		assert_eq!(block_at(&chunks, Point3::new(15, 1, -1)), None);
+		assert_eq!(block_at(&chunks, Point3::new(15, 0, 0)), None);
+
+		// Every changed block is replicated to the clients which have its chunk.
+		let changes = chunks
+			.iter()
+			.map(|arc_chunk| arc_chunk.write().unwrap().take_block_changes().len())
+			.sum::<usize>();
+		assert_eq!(changes, 4);
+	}
+
+	/// Creates an empty chunk for every chunk which contains a block in the region.
+	fn region_chunks(min: Point3<i64>, max: Point3<i64>) -> Vec<chunk::ArcLock> {
+		let (min_chunk, max_chunk) = (split(min).0, split(max).0);
+		let mut coordinates = Vec::new();
+		for x in min_chunk.x..=max_chunk.x {
+			for y in min_chunk.y..=max_chunk.y {
+				for z in min_chunk.z..=max_chunk.z {
+					coordinates.push(Point3::new(x, y, z));
+				}
+			}
+		}
+		loaded_chunks(&coordinates)
+	}
+
+	#[test]
+	fn fill_sets_every_block_once() {
+		// The region crosses a chunk boundary on every axis.
+		let (min, max) = (Point3::new(14, 14, -2), Point3::new(17, 17, 1));
+		let chunks = region_chunks(min, max);
+		assert_eq!(chunks.len(), 8);
+		let edit = Chunks::from_loaded(chunks.clone());
+		let plugins = plugin::Manager::default();
+
+		let edits = fill(max, min, Some(2));
+		assert_eq!(edits.len(), 64);
+		let report = edit.apply(&plugins, None, &edits);
+		assert_eq!(
+			report,
+			Report {
+				changed: 64,
+				unchanged: 0,
+				denied: 0
+			}
+		);
+
+		let mut changes = chunks
+			.iter()
+			.flat_map(|arc_chunk| arc_chunk.write().unwrap().take_block_changes())
+			.map(|(point, id, _version)| {
+				let offset = point.offset().coords.cast::<i64>();
+				(*point.chunk() * (DIAMETER as i64) + offset, id)
+			})
+			.collect::<Vec<_>>();
+		changes.sort_by_key(|(point, _)| (point.y, point.z, point.x));
+		assert_eq!(changes, edits);
+
+		// Filling with the block which is already there changes (and replicates) nothing.
+		let report = edit.apply(&plugins, None, &fill(min, max, Some(2)));
+		assert_eq!(
+			report,
+			Report {
+				changed: 0,
+				unchanged: 64,
+				denied: 0
+			}
+		);
+		let changes = chunks
+			.iter()
+			.map(|arc_chunk| arc_chunk.write().unwrap().has_block_changes())
+			.collect::<Vec<_>>();
+		assert_eq!(changes, vec![false; 8]);
+	}
+
+	#[test]
+	fn chunk_regions_cover_the_region() {
+		let (min, max) = (Point3::new(-3, 0, 5), Point3::new(20, 3, 40));
+		let regions = chunk_regions(max, min);
+		assert_eq!(regions.len(), 3 * 1 * 3);
+		let blocks = regions
+			.iter()
+			.map(|(min, max)| fill(*min, *max, None).len())
+			.sum::<usize>();
+		assert_eq!(blocks, fill(min, max, None).len());
+		for (region_min, region_max) in regions.into_iter() {
+			assert_eq!(split(region_min).0, split(region_max).0);
+		}
+	}
+
+	#[test]
+	fn copied_region_pastes_identically() {
+		let (min, max) = (Point3::new(10, 2, -3), Point3::new(20, 5, 6));
+		let source = region_chunks(min, max);
+		let mut edits = Vec::new();
+		for x in min.x..=max.x {
+			for y in min.y..=max.y {
+				for z in min.z..=max.z {
+					// Mostly air, with a few runs of blocks
+					let id = match (x + y * 3 + z * 7).rem_euclid(5) {
+						0 => Some(1),
+						1 => Some(2),
+						_ => None,
+					};
+					edits.push((Point3::new(x, y, z), id));
+				}
+			}
+		}
+		let plugins = plugin::Manager::default();
+		Chunks::from_loaded(source.clone()).apply(&plugins, None, &edits);
+
+		// The corners can be given in any order.
+		let schematic = Chunks::from_loaded(source.clone()).copy(max, min);
+		assert_eq!(*schematic.size(), Vector3::new(11, 4, 10));
+		let mut path = std::env::temp_dir();
+		path.push(format!(
+			"crystal-sphinx-copy-{}.schematic",
+			uuid::Uuid::new_v4()
+		));
+		schematic.save(&path).unwrap();
+		let schematic = Schematic::load(&path).unwrap();
+		let _ = std::fs::remove_file(&path);
+
+		let origin = Point3::new(-5, 30, 40);
+		let offset = origin - min;
+		let destination = region_chunks(origin, max + offset);
+		Chunks::from_loaded(destination.clone()).apply(&plugins, None, &paste(&schematic, origin));
+
+		for (point, id) in edits.into_iter() {
+			assert_eq!(block_at(&source, point), id, "source changed at {}", point);
+			assert_eq!(
+				block_at(&destination, point + offset),
+				id,
+				"mismatch at {}",
+				point
+			);
+		}
+	}
+}
@@ -0,0 +1,184 @@
+//! Placing a block against the face of a block which a player is looking at (found by a [`raycast`](crate::common::world::raycast)).
+//!
+//! The block goes in the empty neighbor on the side of the face which was hit,
+//! and is applied through the [`plugin validated`](super::edit::Chunks::apply) edit path like any other edit.
+
+use crate::{
+	block,
+	common::{
+		physics,
+		world::{chunk::SIZE_I, raycast::RaycastHit},
+	},
+	entity::component::physics::{linear::Position, Collider},
+	plugin,
+	server::world::edit::Chunks,
+};
+use engine::math::nalgebra::Point3;
+use std::net::SocketAddr;
+
+/// Places a block against the face of the block which was hit.
+/// The chunk containing the placed block must be one of the `chunks`
+/// (e.g. loaded by [`load_for`](Chunks::load_for) the block being placed).
+///
+/// Returns the point the block was placed at.
+pub fn place(
+	chunks: &Chunks,
+	entities: &hecs::World,
+	plugins: &plugin::Manager,
+	instigator: Option<SocketAddr>,
+	hit: &RaycastHit,
+	id: block::LookupId,
+) -> Result<block::Point, Error> {
+	let target = hit.point + hit.face.direction();
+	let coordinate = world_coordinate(&target);
+	match chunks.get(coordinate) {
+		None => return Err(Error::NotLoaded(target)),
+		Some(Some(_)) => return Err(Error::Occupied(target)),
+		Some(None) => {}
+	}
+
+	// The block cannot be placed where its colliders would overlap an entity.
+	let colliders = block::Lookup::collider_of(id).colliders_at(&target);
+	for (_entity, (position, collider)) in entities.query::<(&Position, Option<&Collider>)>().iter()
+	{
+		// Entities without a collider of their own are treated as being the size of a player.
+		let entity = collider.cloned().unwrap_or_else(Collider::player);
+		let (entity_min, entity_max) = physics::bounds(&position.world_position(), entity.size());
+		let is_inside = colliders.iter().any(|collider| {
+			// Entities can stand inside blocks which they pass through.
+			if !entity.groups().interacts_with(&collider.groups) {
+				return false;
+			}
+			let half_extents = collider.half_extents.cast::<f64>();
+			let (min, max) = (
+				collider.center - half_extents,
+				collider.center + half_extents,
+			);
+			// Entities which are only touching the block (e.g. standing on it) are not inside it.
+			(0..3).all(|i| min[i] < entity_max[i] && entity_min[i] < max[i])
+		});
+		if is_inside {
+			return Err(Error::InsideEntity(target));
+		}
+	}
+
+	let report = chunks.apply(plugins, instigator, &[(coordinate, Some(id))]);
+	match report.changed {
+		0 => Err(Error::Denied(target)),
+		_ => Ok(target),
+	}
+}
+
+/// Returns the world-space block coordinate of a point.
+fn world_coordinate(point: &block::Point) -> Point3<i64> {
+	let mut coordinate = Point3::origin();
+	for i in 0..3 {
+		coordinate[i] = point.chunk()[i] * SIZE_I[i] as i64 + point.offset()[i] as i64;
+	}
+	coordinate
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+	#[error("cannot place a block at {0}, its chunk is not loaded")]
+	NotLoaded(block::Point),
+	#[error("cannot place a block at {0}, there is already a block there")]
+	Occupied(block::Point),
+	#[error("cannot place a block at {0}, an entity is in the way")]
+	InsideEntity(block::Point),
+	#[error("a plugin denied placing a block at {0}")]
+	Denied(block::Point),
+}
+
+#[cfg(test)]
+mod place {
+	use super::*;
+	use crate::{
+		common::world::chunk::Chunk as CommonChunk,
+		graphics::voxel::Face,
+		server::world::chunk::{self, Chunk, Level},
+	};
+	use std::{
+		path::PathBuf,
+		sync::{Arc, RwLock},
+	};
+
+	fn loaded_chunk() -> chunk::ArcLock {
+		let mut chunk = Chunk::new(
+			PathBuf::new(),
+			CommonChunk::new(Point3::origin()),
+			Level::Loaded,
+		);
+		chunk.set_block_id(Point3::new(4, 4, 4), Some(1));
+		chunk.set_block_id(Point3::new(8, 4, 8), Some(1));
+		let _ = chunk.take_block_changes();
+		Arc::new(RwLock::new(chunk))
+	}
+
+	fn spawn_at(entities: &mut hecs::World, position: Point3<f64>) {
+		let mut component = Position::default();
+		component.set_world_position(position);
+		entities.spawn((component,));
+	}
+
+	fn top_of(offset: Point3<i8>) -> RaycastHit {
+		RaycastHit {
+			point: block::Point::new(Point3::origin(), offset),
+			face: Face::Up,
+		}
+	}
+
+	#[test]
+	fn places_above_top_face() {
+		let arc_chunk = loaded_chunk();
+		let chunks = Chunks::from_loaded(vec![arc_chunk.clone()]);
+		let mut entities = hecs::World::new();
+		// Standing on top of where the block goes does not prevent placing it.
+		spawn_at(&mut entities, Point3::new(4.5, 6.0, 4.5));
+
+		let placed = place(
+			&chunks,
+			&entities,
+			&plugin::Manager::default(),
+			None,
+			&top_of(Point3::new(4, 4, 4)),
+			2,
+		);
+		let above = block::Point::new(Point3::origin(), Point3::new(4, 5, 4));
+		assert_eq!(placed, Ok(above));
+		assert_eq!(chunks.get(Point3::new(4, 5, 4)), Some(Some(2)));
+		assert_eq!(arc_chunk.write().unwrap().take_block_changes().len(), 1);
+
+		// The block is now there, so placing against the same face again is rejected.
+		let again = place(
+			&chunks,
+			&entities,
+			&plugin::Manager::default(),
+			None,
+			&top_of(Point3::new(4, 4, 4)),
+			2,
+		);
+		assert_eq!(again, Err(Error::Occupied(above)));
+	}
+
+	#[test]
+	fn rejected_when_entity_is_in_the_way() {
+		let arc_chunk = loaded_chunk();
+		let chunks = Chunks::from_loaded(vec![arc_chunk.clone()]);
+		let mut entities = hecs::World::new();
+		spawn_at(&mut entities, Point3::new(8.5, 5.0, 8.5));
+
+		let placed = place(
+			&chunks,
+			&entities,
+			&plugin::Manager::default(),
+			None,
+			&top_of(Point3::new(8, 4, 8)),
+			2,
+		);
+		let above = block::Point::new(Point3::origin(), Point3::new(8, 5, 8));
+		assert_eq!(placed, Err(Error::InsideEntity(above)));
+		assert_eq!(chunks.get(Point3::new(8, 5, 8)), Some(None));
+		assert!(arc_chunk.write().unwrap().take_block_changes().is_empty());
+	}
+}
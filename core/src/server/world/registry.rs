@@ -0,0 +1,146 @@
+use crate::{
+	common::world::generator,
+	entity::component::chunk::TicketOwner,
+	server::{
+		network::DEFAULT_WORLD,
+		world::{chunk, Database},
+	},
+};
+use std::{
+	collections::HashMap,
+	sync::{Arc, RwLock},
+};
+
+/// What the systems acting on entities need to know about a world, without holding its [`Database`].
+#[derive(Clone)]
+pub struct Entry {
+	chunk_cache: chunk::cache::WeakLock,
+	chunk_limits: Arc<chunk::Limits>,
+	seed: u64,
+}
+
+impl Entry {
+	pub fn new(chunk_cache: &chunk::cache::ArcLock) -> Self {
+		Self {
+			chunk_cache: Arc::downgrade(&chunk_cache),
+			chunk_limits: Arc::new(chunk::Limits::default()),
+			seed: 0,
+		}
+	}
+
+	pub fn with_limits(mut self, chunk_limits: Arc<chunk::Limits>) -> Self {
+		self.chunk_limits = chunk_limits;
+		self
+	}
+
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	/// The chunks of the world which are loaded, or None if the world has been unloaded.
+	pub fn chunk_cache(&self) -> Option<chunk::cache::ArcLock> {
+		self.chunk_cache.upgrade()
+	}
+
+	pub fn chunk_limits(&self) -> &Arc<chunk::Limits> {
+		&self.chunk_limits
+	}
+
+	/// The numerical seed the chunks of the world are generated with.
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+}
+
+impl From<&Database> for Entry {
+	fn from(database: &Database) -> Self {
+		Self::new(database.chunk_cache())
+			.with_limits(database.chunk_limits().clone())
+			.with_seed(generator::Pipeline::seed_from_str(
+				database.settings().seed(),
+			))
+	}
+}
+
+/// The worlds a server is hosting, by name.
+///
+/// Shared between the server's storage, which adds each world as it is loaded,
+/// and the systems which need to find the world an entity is in (e.g. to break blocks or replicate chunks)
+/// without locking the storage. Only weak references to each world's chunks are held,
+/// so the chunks are still unloaded with the world.
+#[derive(Clone, Default)]
+pub struct Registry(Arc<RwLock<HashMap<String, Entry>>>);
+
+impl Registry {
+	pub fn insert(&self, name: &str, entry: Entry) {
+		self.0.write().unwrap().insert(name.to_owned(), entry);
+	}
+
+	pub fn get(&self, name: &str) -> Option<Entry> {
+		self.0.read().unwrap().get(name).cloned()
+	}
+
+	/// Returns the chunk cache of a world, if it is loaded.
+	pub fn chunk_cache(&self, name: &str) -> Option<chunk::cache::ArcLock> {
+		self.get(name)?.chunk_cache()
+	}
+
+	/// Returns every world which has been loaded, by world name.
+	pub fn entries(&self) -> HashMap<String, Entry> {
+		self.0.read().unwrap().clone()
+	}
+
+	/// Returns the chunk cache of every world which is loaded, by world name.
+	pub fn chunk_caches(&self) -> HashMap<String, chunk::cache::ArcLock> {
+		self.0
+			.read()
+			.unwrap()
+			.iter()
+			.filter_map(|(name, entry)| Some((name.clone(), entry.chunk_cache()?)))
+			.collect()
+	}
+
+	/// Returns the name of the world an entity is in, given its [`TicketOwner`] (if it has one).
+	/// Entities are in the [`default world`](DEFAULT_WORLD) unless they have been moved to another.
+	pub fn world_of(ticket_owner: Option<&TicketOwner>) -> &str {
+		match ticket_owner {
+			Some(ticket_owner) => ticket_owner.world_name(),
+			None => DEFAULT_WORLD,
+		}
+	}
+}
+
+#[cfg(test)]
+mod lookup {
+	use super::*;
+	use crate::server::world::chunk::cache::Cache;
+
+	#[test]
+	fn unloaded_worlds_are_not_found() {
+		let registry = Registry::default();
+		let lobby = Arc::new(RwLock::new(Cache::new()));
+		let arena = Arc::new(RwLock::new(Cache::new()));
+		registry.insert("lobby", Entry::new(&lobby));
+		registry.insert("arena", Entry::new(&arena).with_seed(5));
+		assert!(Arc::ptr_eq(&registry.chunk_cache("lobby").unwrap(), &lobby));
+		assert_eq!(registry.get("arena").unwrap().seed(), 5);
+		assert!(registry.get("nether").is_none());
+
+		drop(arena);
+		assert!(registry.chunk_cache("arena").is_none());
+		assert_eq!(
+			registry.chunk_caches().keys().collect::<Vec<_>>(),
+			vec!["lobby"]
+		);
+	}
+
+	#[test]
+	fn entities_are_in_the_default_world() {
+		assert_eq!(Registry::world_of(None), DEFAULT_WORLD);
+		let mut ticket_owner = TicketOwner::default();
+		assert_eq!(Registry::world_of(Some(&ticket_owner)), DEFAULT_WORLD);
+		ticket_owner.set_world("arena", None);
+		assert_eq!(Registry::world_of(Some(&ticket_owner)), "arena");
+	}
+}
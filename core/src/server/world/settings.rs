@@ -1,13 +1,38 @@
+use crate::common::physics;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Settings {
 	#[serde(skip)]
 	root_path: PathBuf,
 	#[serde(default = "Settings::default_seed")]
 	seed: String,
+	/// If true, the chunk tickets active when the world closes are saved as hints,
+	/// and the hinted region starts loading as soon as the world is next opened.
+	#[serde(default = "Settings::default_persist_ticket_hints")]
+	persist_ticket_hints: bool,
+	/// The maximum number of chunks the chunk-loading thread loads in each of its updates.
+	/// Chunks are loaded from each waiting ticket in turn, so this limit is shared fairly between clients.
+	#[serde(default = "Settings::default_chunks_per_update")]
+	chunks_per_update: usize,
+	/// The gravity and scale of the world, which are written with their defaults when the world is created
+	/// and can be edited (e.g. for creative or modded worlds) before it is next loaded.
+	#[serde(default)]
+	physics: physics::Settings,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			root_path: PathBuf::default(),
+			seed: String::default(),
+			persist_ticket_hints: Self::default_persist_ticket_hints(),
+			chunks_per_update: Self::default_chunks_per_update(),
+			physics: physics::Settings::default(),
+		}
+	}
 }
 
 impl Settings {
@@ -24,6 +49,26 @@ impl Settings {
 	pub fn seed(&self) -> &String {
 		&self.seed
 	}
+
+	fn default_persist_ticket_hints() -> bool {
+		true
+	}
+
+	pub fn persist_ticket_hints(&self) -> bool {
+		self.persist_ticket_hints
+	}
+
+	fn default_chunks_per_update() -> usize {
+		16
+	}
+
+	pub fn chunks_per_update(&self) -> usize {
+		self.chunks_per_update
+	}
+
+	pub fn physics(&self) -> &physics::Settings {
+		&self.physics
+	}
 }
 
 impl Settings {
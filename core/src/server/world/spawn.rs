@@ -0,0 +1,234 @@
+use crate::{
+	block,
+	common::world::chunk::{self, Chunk},
+	plugin,
+	server::world::{
+		chunk::{ParameterizedLevel, Ticket},
+		edit::{self, split},
+		ArcLockDatabase,
+	},
+};
+use anyhow::Result;
+use engine::{
+	asset,
+	math::nalgebra::{Point3, Vector3},
+};
+use std::{ops::RangeInclusive, time::Duration};
+
+static LOG: &'static str = "spawn";
+
+/// The vertical chunks (relative to the chunk of the requested spawn point) which are searched for a surface.
+pub static COLUMN_CHUNK_Y: RangeInclusive<i64> = -2..=2;
+
+/// How long to wait for the chunks of the spawn column to be loaded (or generated).
+pub static COLUMN_LOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The block that a platform is made of when a column has no safe place to stand.
+pub fn platform_block() -> asset::Id {
+	asset::Id::new("vanilla", "blocks/stone")
+}
+
+/// Finds a safe position for a player to spawn at in the column of blocks containing `around`,
+/// which is the world-space block coordinate the player would otherwise spawn at.
+///
+/// The column is scanned from the top of its highest chunk downwards, for the first solid block with two air blocks above it.
+/// The chunks of the column are loaded (or generated) if they are not already loaded,
+/// and the database is only locked while they are requested (not while waiting for them).
+/// If there is no safe place to stand in the column, a platform is built beneath `around`.
+///
+/// Returns the world-space position of the bottom-center of the air block above the surface.
+pub fn find_safe_spawn(database: &ArcLockDatabase, around: Point3<i64>) -> Result<Point3<f32>> {
+	profiling::scope!("find_safe_spawn", &format!("{}", around));
+	let (chunk, offset) = split(around);
+	let column_chunks = COLUMN_CHUNK_Y
+		.clone()
+		.map(|y| Point3::new(chunk.x, chunk.y + y, chunk.z))
+		.collect::<Vec<_>>();
+	let (_tickets, mut waiter) = {
+		let database = database.read().unwrap();
+		// The waiter is created before the tickets are submitted, so no chunk is loaded without it seeing.
+		let waiter = database.chunk_waiter();
+		let tickets = column_chunks
+			.iter()
+			.map(|coordinate| {
+				database.submit_ticket(Ticket {
+					coordinate: *coordinate,
+					level: ParameterizedLevel::Loaded,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+		(tickets, waiter)
+	};
+	let loaded = waiter.wait(&column_chunks, COLUMN_LOAD_TIMEOUT);
+	if loaded.len() < column_chunks.len() {
+		return Err(Error::ColumnNotLoaded(COLUMN_LOAD_TIMEOUT))?;
+	}
+	let column = loaded
+		.into_iter()
+		.map(|(_, arc_chunk)| arc_chunk)
+		.collect::<Vec<_>>();
+
+	let surface = {
+		let chunks = column
+			.iter()
+			.map(|arc_chunk| arc_chunk.read().unwrap())
+			.collect::<Vec<_>>();
+		let chunks = chunks.iter().map(|chunk| &chunk.chunk).collect::<Vec<_>>();
+		find_surface(&chunks, (offset.x, offset.z))
+	};
+	let surface_y = match surface {
+		Some(y) => y,
+		None => {
+			log::warn!(
+				target: LOG,
+				"No safe surface in the column at {}, building a platform",
+				around
+			);
+			let id = block::Lookup::lookup_value(&platform_block())
+				.ok_or(Error::MissingPlatformBlock(platform_block()))?;
+			let report = build_platform(&column, around, id);
+			if report.denied > 0 {
+				log::warn!(
+					target: LOG,
+					"Plugins denied {} blocks of the spawn platform at {}",
+					report.denied,
+					around
+				);
+			}
+			around.y - 1
+		}
+	};
+
+	Ok(Point3::new(
+		around.x as f32 + 0.5,
+		(surface_y + 1) as f32,
+		around.z as f32 + 0.5,
+	))
+}
+
+/// Scans a column of chunks from the top down, returning the world-space y coordinate
+/// of the first solid block (at the provided x-z offset) which has two air blocks above it.
+///
+/// Any block is treated as solid, and the chunks can be in any order.
+/// Nothing is known about the blocks above the highest chunk,
+/// so a block at the very top of the column is never considered to be a surface.
+pub fn find_surface(column: &Vec<&Chunk>, offset: (usize, usize)) -> Option<i64> {
+	let mut column = column.clone();
+	column.sort_by_key(|chunk| std::cmp::Reverse(chunk.coordinate().y));
+	let mut air_above = 0;
+	for chunk in column.into_iter() {
+		for block_y in (0..chunk::DIAMETER).rev() {
+			let block_offset = Point3::new(offset.0, block_y, offset.1);
+			match chunk.block_ids().contains_key(&block_offset) {
+				true if air_above >= 2 => {
+					return Some(chunk.coordinate().y * chunk::DIAMETER as i64 + block_y as i64);
+				}
+				true => air_above = 0,
+				false => air_above += 1,
+			}
+		}
+	}
+	None
+}
+
+/// Places a 3x3 platform of blocks beneath `around`, clearing the two blocks above each so a player can stand on it.
+/// The blocks are [`validated`](crate::server::world::chunk::Chunk::apply_block_change) by plugins like any other edit,
+/// so a plugin can protect the blocks around the spawn point.
+fn build_platform(
+	column: &Vec<crate::server::world::chunk::ArcLock>,
+	around: Point3<i64>,
+	id: block::LookupId,
+) -> edit::Report {
+	let mut edits = Vec::new();
+	for x in -1..=1 {
+		for z in -1..=1 {
+			for (y, block) in [(-1, Some(id)), (0, None), (1, None)] {
+				edits.push((around + Vector3::new(x, y, z), block));
+			}
+		}
+	}
+	// Blocks which spill into neighboring columns are only placed if those chunks are loaded.
+	let chunks = edit::Chunks::from_loaded(column.clone());
+	let plugins = plugin::Manager::read().unwrap();
+	chunks.apply(&plugins, None, &edits)
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("the chunks of the spawn column were not loaded within {0:?}")]
+	ColumnNotLoaded(Duration),
+	#[error("the platform block {0} is not in the block lookup")]
+	MissingPlatformBlock(asset::Id),
+}
+
+#[cfg(test)]
+mod surface {
+	use super::*;
+
+	/// A column of three chunks (y=0..48), which is solid at the very top (y=42..48)
+	/// and has a floor at the bottom (y=0..=2).
+	fn column() -> Vec<Chunk> {
+		let mut chunks = (0..3)
+			.map(|y| Chunk::new(Point3::new(0, y, 0)))
+			.collect::<Vec<_>>();
+		for y in (0..=2).chain(42..48) {
+			set_block(&mut chunks, y);
+		}
+		// The chunks do not need to be ordered.
+		chunks.reverse();
+		chunks.swap(0, 1);
+		chunks
+	}
+
+	fn set_block(chunks: &mut Vec<Chunk>, y: i64) {
+		let (coordinate, offset) = split(Point3::new(3, y, 4));
+		let chunk = chunks
+			.iter_mut()
+			.find(|chunk| *chunk.coordinate() == coordinate)
+			.unwrap();
+		chunk.set_block_id(offset, Some(0));
+	}
+
+	fn surface_of(chunks: &Vec<Chunk>) -> Option<i64> {
+		find_surface(&chunks.iter().collect(), (3, 4))
+	}
+
+	#[test]
+	fn returns_first_valid_surface() {
+		let mut chunks = column();
+		assert_eq!(surface_of(&chunks), Some(2));
+
+		// A block with exactly two air blocks above it (beneath the solid ceiling) is the first valid surface.
+		set_block(&mut chunks, 39);
+		assert_eq!(surface_of(&chunks), Some(39));
+	}
+
+	#[test]
+	fn skips_surfaces_without_headroom() {
+		let mut chunks = column();
+		// There is only one air block between the ceiling and the block at y=40,
+		// so the surface is the next solid block below it (and not the block at y=20 further down).
+		set_block(&mut chunks, 40);
+		set_block(&mut chunks, 23);
+		set_block(&mut chunks, 20);
+		assert_eq!(surface_of(&chunks), Some(23));
+	}
+
+	#[test]
+	fn no_surface_in_empty_column() {
+		let chunks = vec![Chunk::new(Point3::new(0, 0, 0))];
+		assert_eq!(surface_of(&chunks), None);
+		// The top of the column is never a surface, as the blocks above it are unknown.
+		let mut chunks = chunks;
+		chunks[0].set_block_id(Point3::new(3, 15, 4), Some(0));
+		assert_eq!(surface_of(&chunks), None);
+	}
+
+	#[test]
+	fn split_negative_coordinates() {
+		assert_eq!(
+			split(Point3::new(-1, 17, 0)),
+			(Point3::new(-1, 1, 0), Point3::new(15, 1, 0))
+		);
+	}
+}
@@ -0,0 +1,329 @@
+//! In-process games for tests of behavior which spans the server's systems
+//! (e.g. a player joining and their entity being replicated to their client).
+//!
+//! A [`ListenServer`] hosts a world the same way the game does, and its local client joins over socknet,
+//! which exercises the network streams and the handshake.
+//!
+//! A headless [`Server`] can have any number of clients,
+//! which are connected to the [`replicator`](entity::system::Replicator) over local (loopback) handles,
+//! the same kind used for the client of an integrated server, instead of over a socknet connection.
+//! This means tests do not need sockets, certificates, or an async runtime,
+//! but it also means the handshake itself is not exercised; [`Server::login`] starts from an authenticated account.
+//! The server is ticked manually, and what each client has been told is reconstructed
+//! from the replicator's [`recording`](entity::system::replicator::recording), like a recording made by a real server.
+
+use crate::{
+	block,
+	client::world::chunk::{Operation, OperationReceiver},
+	common::{account, world::chunk::Chunk as CommonChunk},
+	entity::{
+		self, archetype,
+		system::replicator::recording::{self, ClientView},
+		ArcLockEntityWorld,
+	},
+	server::{
+		network::DEFAULT_WORLD,
+		world::{
+			chunk::{self, cache::Cache, Chunk, Level},
+			registry, Registry,
+		},
+	},
+};
+use engine::{math::nalgebra::Point3, EngineSystem};
+use std::{
+	cell::RefCell,
+	io::Write,
+	net::SocketAddr,
+	sync::{Arc, Mutex, RwLock},
+};
+
+mod listen_server;
+pub use listen_server::*;
+
+/// An in-memory destination for a [`recording`](recording::Recorder), which can be read while it is being written.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/// The server side of the harness: an entity world, the chunks which are loaded, and the replicator.
+pub struct Server {
+	world: ArcLockEntityWorld,
+	chunk_cache: chunk::cache::ArcLock,
+	/// The chunks which have been loaded, which are only weakly referenced by the cache.
+	chunks: Vec<chunk::ArcLock>,
+	replicator: entity::system::Replicator,
+	recording: SharedBuffer,
+	next_port: u16,
+}
+
+/// A client which has logged in to a [`Server`].
+pub struct Client {
+	address: SocketAddr,
+	account_id: account::Id,
+	entity: hecs::Entity,
+	chunk_operations: OperationReceiver,
+	/// Operations which have been received, but not yet read by one of the `received_` functions.
+	unread_operations: RefCell<Vec<Operation>>,
+}
+
+impl Server {
+	pub fn new() -> Self {
+		entity::component::register_types();
+		let world = Arc::new(RwLock::new(entity::World::new()));
+		let chunk_cache = Arc::new(RwLock::new(Cache::new()));
+		let recording = SharedBuffer::default();
+		let recorder = recording::Recorder::new(recording.clone()).arclocked();
+		let worlds = Registry::default();
+		worlds.insert(DEFAULT_WORLD, registry::Entry::new(&chunk_cache));
+		let replicator = entity::system::Replicator::headless(&world, &worlds, recorder);
+		Self {
+			world,
+			chunk_cache,
+			chunks: Vec::new(),
+			replicator,
+			recording,
+			next_port: 25565,
+		}
+	}
+
+	pub fn world(&self) -> &ArcLockEntityWorld {
+		&self.world
+	}
+
+	/// Loads an empty chunk, so it can be replicated to clients it is relevant to.
+	pub fn load_chunk(&mut self, coordinate: Point3<i64>) {
+		let arc_chunk = Arc::new(RwLock::new(Chunk::new(
+			std::path::PathBuf::new(),
+			CommonChunk::new(coordinate),
+			Level::Ticking,
+		)));
+		self.chunk_cache
+			.write()
+			.unwrap()
+			.insert(coordinate, Arc::downgrade(&arc_chunk));
+		self.chunks.push(arc_chunk);
+	}
+
+	/// Connects a client for an account which has passed authentication,
+	/// spawning its player entity the same way the handshake does.
+	pub fn login(&mut self, account_id: &str) -> Client {
+		let address = SocketAddr::from(([127, 0, 0, 1], self.next_port));
+		self.next_port += 1;
+
+		let (chunk_sender, chunk_operations) = engine::channels::mpsc::unbounded();
+		self.replicator
+			.add_local_connection(address, chunk_sender)
+			.unwrap();
+
+		let account_id = account_id.to_owned();
+		let builder = archetype::player::Server::new()
+			.with_user_id(account_id.clone())
+			.with_address(address)
+			.build();
+		let entity = self.world.write().unwrap().spawn(builder.build());
+
+		Client {
+			address,
+			account_id,
+			entity,
+			chunk_operations,
+			unread_operations: RefCell::new(Vec::new()),
+		}
+	}
+
+	/// Changes a block in a loaded chunk, as a server system would.
+	pub fn set_block(&mut self, point: &block::Point, id: Option<block::LookupId>) {
+		let arc_chunk = self
+			.chunks
+			.iter()
+			.find(|arc_chunk| arc_chunk.read().unwrap().chunk.coordinate() == point.chunk())
+			.expect("the chunk must be loaded");
+		let offset = crate::server::world::block_ticks::offset_in_chunk(point);
+		arc_chunk.write().unwrap().set_block_id(offset, id);
+	}
+
+	/// Runs a single server tick.
+	pub fn tick(&mut self) {
+		let tick_duration = self.replicator.tick_duration();
+		self.replicator.update(tick_duration, false);
+	}
+
+	pub fn tick_n(&mut self, count: usize) {
+		for _ in 0..count {
+			self.tick();
+		}
+	}
+
+	/// Replays everything the server has told the client so far into a headless client,
+	/// whose entity world has the entities replicated to the client.
+	pub fn replay_of(&self, client: &Client) -> recording::Player {
+		let entries = {
+			let buffer = self.recording.0.lock().unwrap();
+			recording::read(&buffer[..]).unwrap()
+		};
+		let mut player = recording::Player::new(entries, client.address);
+		player.advance_to(std::time::Duration::MAX);
+		player
+	}
+
+	/// Returns what the server has told the client so far.
+	pub fn view_of(&self, client: &Client) -> ClientView {
+		self.replay_of(client).finish()
+	}
+}
+
+impl Client {
+	pub fn address(&self) -> &SocketAddr {
+		&self.address
+	}
+
+	pub fn account_id(&self) -> &account::Id {
+		&self.account_id
+	}
+
+	/// The entity the server spawned for the client's player.
+	pub fn entity(&self) -> &hecs::Entity {
+		&self.entity
+	}
+
+	/// Removes the operations the client has received (and not yet read) which `filter` accepts.
+	fn take_operations<T>(&self, filter: impl Fn(&Operation) -> Option<T>) -> Vec<T> {
+		let mut unread = self.unread_operations.borrow_mut();
+		while let Ok(operation) = self.chunk_operations.try_recv() {
+			unread.push(operation);
+		}
+		let mut taken = Vec::new();
+		unread.retain(|operation| match filter(operation) {
+			Some(item) => {
+				taken.push(item);
+				false
+			}
+			None => true,
+		});
+		taken
+	}
+
+	/// Returns the coordinates of the chunks the client has received since this was last called.
+	pub fn received_chunks(&self) -> Vec<Point3<i64>> {
+		self.take_operations(|operation| match operation {
+			Operation::Insert(coordinate, _, _) => Some(*coordinate),
+			_ => None,
+		})
+	}
+
+	/// Returns the block changes the client has received since this was last called, in the order they were received.
+	pub fn received_block_changes(
+		&self,
+	) -> Vec<(block::Point, Option<(block::LookupId, block::State)>, u64)> {
+		self.take_operations(|operation| match operation {
+			Operation::SetBlocks(changes) => Some(changes.clone()),
+			_ => None,
+		})
+		.into_iter()
+		.flatten()
+		.collect()
+	}
+}
+
+#[cfg(test)]
+mod login {
+	use super::*;
+	use crate::entity::component::PersistentId;
+
+	#[test]
+	fn client_receives_its_player() {
+		let mut server = Server::new();
+		server.load_chunk(Point3::new(0, 0, 0));
+		let client = server.login("player-one");
+		server.tick_n(2);
+
+		let view = server.view_of(&client);
+		let player_id = PersistentId::for_account(client.account_id());
+		assert!(view.persistent_entities.contains(&player_id));
+		assert!(view.chunk_relevance.is_relevant(&Point3::new(0, 0, 0)));
+		assert!(view.chunks.contains(&Point3::new(0, 0, 0)));
+		assert_eq!(client.received_chunks(), vec![Point3::new(0, 0, 0)]);
+		assert!(server.world().read().unwrap().contains(*client.entity()));
+	}
+
+	#[test]
+	fn client_replicates_its_player_components() {
+		use crate::entity::component::physics::linear::Position;
+		let mut server = Server::new();
+		server.load_chunk(Point3::new(0, 0, 0));
+		let client = server.login("player-one");
+		server.tick_n(2);
+
+		let replay = server.replay_of(&client);
+		let client_entity = replay.client_entity(client.entity()).unwrap();
+		let server_position = {
+			let world = server.world().read().unwrap();
+			let position = world.get::<&Position>(*client.entity()).unwrap();
+			position.world_position()
+		};
+		let client_world = replay.entity_world().read().unwrap();
+		let position = client_world.get::<&Position>(client_entity).unwrap();
+		assert_eq!(position.world_position(), server_position);
+	}
+
+	#[test]
+	fn clients_see_each_other() {
+		let mut server = Server::new();
+		let first = server.login("player-one");
+		let second = server.login("player-two");
+		assert_ne!(first.address(), second.address());
+		server.tick();
+
+		let view = server.view_of(&first);
+		assert!(view
+			.persistent_entities
+			.contains(&PersistentId::for_account(second.account_id())));
+	}
+}
+
+#[cfg(test)]
+mod block_changes {
+	use super::*;
+	use crate::entity::component::physics::linear::Position;
+
+	#[test]
+	fn edit_is_sent_to_clients_with_the_chunk() {
+		let mut server = Server::new();
+		server.load_chunk(Point3::new(0, 0, 0));
+		let near = server.login("player-one");
+		let far = server.login("player-two");
+		server
+			.world()
+			.read()
+			.unwrap()
+			.get::<&mut Position>(*far.entity())
+			.unwrap()
+			.set_world_position(Point3::new(1000.0, 0.0, 0.0));
+		server.tick_n(2);
+		assert_eq!(near.received_chunks(), vec![Point3::new(0, 0, 0)]);
+		assert!(far.received_chunks().is_empty());
+
+		let point = block::Point::new(Point3::new(0, 0, 0), Point3::new(1, 2, 3));
+		server.set_block(&point, Some(1));
+		server.tick();
+
+		let block = Some((1, block::DEFAULT_STATE));
+		assert_eq!(near.received_block_changes(), vec![(point, block, 1)]);
+		assert!(far.received_block_changes().is_empty());
+		assert_eq!(server.view_of(&near).blocks.get(&point), Some(&block));
+		assert!(server.view_of(&far).blocks.is_empty());
+
+		// Changes are only sent once.
+		server.tick();
+		assert!(near.received_block_changes().is_empty());
+	}
+}
@@ -0,0 +1,213 @@
+use crate::{
+	app::state::State,
+	client,
+	common::{
+		account,
+		network::{mode, task::Instruction, NetworkConfig},
+	},
+	entity::{self, component::OwnedByAccount},
+	plugin, Runtime,
+};
+use engine::EngineSystem;
+use std::{
+	path::PathBuf,
+	sync::RwLockWriteGuard,
+	time::{Duration, Instant},
+};
+
+/// How long a test waits for the world to load and the local client to join it.
+pub static JOIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How much time passes each time the harness is ticked while waiting.
+static TICK: Duration = Duration::from_millis(50);
+
+/// A game hosting a world as an integrated client-server (`-listen_server`),
+/// whose local client joins it over a loopback socknet connection.
+///
+/// Hosting goes through the same path as the main menu: the app state enters `LoadingWorld`,
+/// the network is loaded with every stream registered, and the client connects to the server's endpoint
+/// and performs the real [`handshake`](crate::common::network::handshake), which spawns the player.
+///
+/// The network mode and the logged in account are global to the process,
+/// so only one harness can exist at a time (others wait for it to be dropped),
+/// and dedicated clients cannot join from the same process.
+pub struct ListenServer {
+	runtime: Runtime,
+	/// The directory the world and accounts are saved in, which is removed when the harness is dropped.
+	root: PathBuf,
+	account_id: account::Id,
+	/// The port the world is hosted on.
+	port: u16,
+	_exclusive: RwLockWriteGuard<'static, ()>,
+}
+
+impl ListenServer {
+	fn exclusive() -> RwLockWriteGuard<'static, ()> {
+		use engine::utility::singleton::*;
+		static mut INSTANCE: Singleton<()> = Singleton::uninit();
+		let lock = unsafe { INSTANCE.get_or_default() };
+		// A test which failed while hosting does not stop the next one from hosting.
+		lock.write()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	/// Starts hosting a new world as the account named `user_name`,
+	/// the same as launching with `-listen_server -user=<user_name>`.
+	/// Must be called from within an async runtime, because loading the network is an async task.
+	pub fn host(user_name: &str) -> anyhow::Result<Self> {
+		let exclusive = Self::exclusive();
+		let root = std::env::temp_dir().join(format!(
+			"crystal-sphinx-listen-server-{}",
+			uuid::Uuid::new_v4()
+		));
+		entity::component::register_types();
+
+		let account_id = {
+			let mut manager = client::account::Manager::write()?;
+			manager.set_root(root.join("accounts"));
+			let account_id = manager.ensure_account(&user_name.to_owned())?;
+			manager.login_as(&account_id)?;
+			account_id
+		};
+
+		let runtime = Runtime::with_mode(plugin::Config::default(), mode::Kind::ListenServer);
+		crate::common::network::task::add_load_network_listener(
+			runtime.app_state(),
+			runtime.network_storage(),
+			runtime.world(),
+		);
+
+		// Any free port, so tests do not conflict with each other or a game which is running.
+		let port = std::net::UdpSocket::bind(("127.0.0.1", 0))?
+			.local_addr()?
+			.port();
+		let instruction = Instruction {
+			mode: mode::Kind::ListenServer.as_set(),
+			network: NetworkConfig::default().with_port(port),
+			// An absolute name replaces the saves directory, so the world is saved with the accounts.
+			world_name: Some(root.join("world").display().to_string()),
+			server_url: None,
+		};
+
+		let harness = Self {
+			runtime,
+			root,
+			account_id,
+			port,
+			_exclusive: exclusive,
+		};
+		harness.transition_to(State::MainMenu, None);
+		harness.transition_to(State::LoadingWorld, Some(Box::new(instruction)));
+		Ok(harness)
+	}
+
+	fn transition_to(&self, state: State, data: crate::app::state::TransitionData) {
+		let mut app_state = self.runtime.app_state().write().unwrap();
+		app_state.transition_to(state, data);
+		app_state.update(Duration::ZERO, true);
+	}
+
+	pub fn account_id(&self) -> &account::Id {
+		&self.account_id
+	}
+
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+
+	pub fn world(&self) -> &entity::ArcLockEntityWorld {
+		self.runtime.world()
+	}
+
+	pub fn state(&self) -> State {
+		self.runtime.app_state().read().unwrap().get()
+	}
+
+	/// Updates the app state and the systems of the server once, as the engine does each frame.
+	pub fn tick(&self, delta_time: Duration) {
+		self.runtime
+			.app_state()
+			.write()
+			.unwrap()
+			.update(delta_time, true);
+		let systems = {
+			let storage = self.runtime.network_storage().read().unwrap();
+			match storage.server() {
+				Some(arc_server) => arc_server.read().unwrap().systems().clone(),
+				None => Vec::new(),
+			}
+		};
+		for system in systems.into_iter() {
+			system.write().unwrap().update(delta_time, true);
+		}
+	}
+
+	/// Ticks until `predicate` is true, giving the network tasks time to run in between.
+	/// Returns false if the predicate is still false after `timeout`.
+	pub async fn tick_until<F>(&self, timeout: Duration, predicate: F) -> bool
+	where
+		F: Fn(&Self) -> bool,
+	{
+		let start = Instant::now();
+		while !predicate(self) {
+			if start.elapsed() > timeout {
+				return false;
+			}
+			self.tick(TICK);
+			tokio::time::sleep(TICK).await;
+		}
+		true
+	}
+
+	/// The entity the server spawned for the local client's account, if it has joined.
+	pub fn player(&self) -> Option<hecs::Entity> {
+		let world = self.world().read().unwrap();
+		let mut query = world.query::<&OwnedByAccount>();
+		let player = query
+			.iter()
+			.find(|(_, owner)| *owner.id() == self.account_id)
+			.map(|(entity, _)| entity);
+		player
+	}
+}
+
+impl Drop for ListenServer {
+	fn drop(&mut self) {
+		if let Ok(mut manager) = client::account::Manager::write() {
+			manager.logout();
+		}
+		mode::set(mode::Set::empty());
+		let _ = std::fs::remove_dir_all(&self.root);
+	}
+}
+
+#[cfg(test)]
+mod loopback {
+	use super::*;
+	use crate::entity::component::{Camera, OwnedByConnection};
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn client_joins_and_receives_its_player() -> anyhow::Result<()> {
+		let server = ListenServer::host("player-one")?;
+		let joined = server
+			.tick_until(JOIN_TIMEOUT, |server| server.state() == State::InGame)
+			.await;
+		assert!(joined, "the client never entered the game");
+		{
+			// The endpoint was bound to the port the world was hosted on, rather than the default port.
+			let storage = server.runtime.network_storage().read().unwrap();
+			let endpoint = storage.endpoint().expect("the network was loaded");
+			assert_eq!(endpoint.address().port(), server.port());
+		}
+
+		let player = server
+			.player()
+			.expect("the handshake spawns the player's entity");
+		let world = server.world().read().unwrap();
+		// The connection which completed the handshake owns the player,
+		assert!(world.get::<&OwnedByConnection>(player).is_ok());
+		// and the server gave it the components of the local client, because it is the client's account.
+		assert!(world.get::<&Camera>(player).is_ok());
+		Ok(())
+	}
+}
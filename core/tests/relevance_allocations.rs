@@ -0,0 +1,119 @@
+//! Counts the allocations made by [`Relevance::difference`], which needs its own test binary
+//! because the counting allocator replaces the global allocator of every test in the binary.
+use crystal_sphinx::entity::system::replicator::relevancy::{
+	Area, AxisAlignedBoundingBox, Relevance,
+};
+use engine::math::nalgebra::Point3;
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	cell::Cell,
+	collections::HashSet,
+};
+
+/// Counts the allocations made by each thread, so tests running in parallel do not affect each other.
+struct CountingAllocator;
+
+thread_local! {
+	static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+fn count_allocation() {
+	let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		count_allocation();
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		count_allocation();
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+	let before = ALLOCATIONS.with(|count| count.get());
+	let result = f();
+	let after = ALLOCATIONS.with(|count| count.get());
+	(result, after - before)
+}
+
+/// The M3 difference algorithm before the no-overlap fast path and scratch list,
+/// which allocated new sets for every cuboid of the other relevance.
+fn set_per_cuboid_difference(lhs: &Relevance, rhs: &Relevance) -> HashSet<AxisAlignedBoundingBox> {
+	let mut cuboids = lhs.iter_cuboids().collect::<HashSet<_>>();
+	let other_cuboids = rhs.iter_cuboids().collect::<HashSet<_>>();
+	for other_cuboid in other_cuboids.into_iter() {
+		let mut resulting_cuboids = HashSet::with_capacity(cuboids.len());
+		for cuboid in cuboids.into_iter() {
+			let not_in_other = match cuboid.intersects(&other_cuboid) {
+				false => Some(HashSet::from([cuboid])),
+				true => cuboid.difference(&other_cuboid),
+			};
+			if let Some(not_in_other) = not_in_other {
+				for cuboid in not_in_other.into_iter() {
+					resulting_cuboids.insert(cuboid);
+				}
+			}
+		}
+		cuboids = resulting_cuboids;
+	}
+	cuboids
+}
+
+fn relevance(areas: &[(Point3<i64>, u64)]) -> Relevance {
+	let mut relevance = Relevance::default();
+	for (point, radius) in areas.iter() {
+		relevance.push(Area::new(*point, *radius));
+	}
+	relevance
+}
+
+fn assert_fewer_allocations(lhs: &Relevance, rhs: &Relevance) {
+	let (expected, set_allocations) = count_allocations(|| set_per_cuboid_difference(lhs, rhs));
+	let (actual, allocations) = count_allocations(|| lhs.difference(rhs));
+	assert_eq!(actual, expected);
+	assert!(
+		allocations < set_allocations,
+		"{} allocations is not fewer than {}",
+		allocations,
+		set_allocations
+	);
+}
+
+#[test]
+fn no_overlap() {
+	let lhs = relevance(&[(Point3::new(0, 0, 0), 6)]);
+	let rhs = relevance(&[(Point3::new(100, 0, 0), 6)]);
+	assert_fewer_allocations(&lhs, &rhs);
+}
+
+#[test]
+fn no_overlap_two_areas() {
+	let lhs = relevance(&[(Point3::new(0, 0, 0), 6), (Point3::new(0, 50, 0), 6)]);
+	let rhs = relevance(&[(Point3::new(100, 0, 0), 6), (Point3::new(0, -50, 0), 6)]);
+	assert_fewer_allocations(&lhs, &rhs);
+}
+
+#[test]
+fn moved_one_chunk() {
+	let lhs = relevance(&[(Point3::new(1, 0, 0), 6)]);
+	let rhs = relevance(&[(Point3::new(0, 0, 0), 6)]);
+	assert_fewer_allocations(&lhs, &rhs);
+}
+
+#[test]
+fn moved_diagonally_with_unmoved_area() {
+	let lhs = relevance(&[(Point3::new(1, 1, 1), 6), (Point3::new(0, 50, 0), 6)]);
+	let rhs = relevance(&[(Point3::new(0, 0, 0), 6), (Point3::new(0, 50, 0), 6)]);
+	assert_fewer_allocations(&lhs, &rhs);
+}
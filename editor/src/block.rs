@@ -1,2 +1,5 @@
 mod block;
 pub use block::*;
+
+mod watcher;
+pub use watcher::*;
@@ -0,0 +1,132 @@
+use crystal_sphinx::{block::Block, graphics::voxel::model};
+use engine::{asset, graphics::Chain, EngineSystem};
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock, Weak},
+	time::{Duration, SystemTime},
+};
+
+static LOG: &'static str = "block-watcher";
+
+/// How often the block asset directory is scanned for changes.
+static SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the block asset sources of a module, and reloads the model of any block whose source changes
+/// into the active [`model cache`](model::Cache) (if block models have been loaded).
+pub struct Watcher {
+	module: String,
+	/// The root asset directory of the module (block sources are in the `blocks` subdirectory).
+	asset_root: PathBuf,
+	chain: Weak<RwLock<Chain>>,
+	modified_at: HashMap<PathBuf, SystemTime>,
+	time_until_scan: Duration,
+}
+
+impl Watcher {
+	pub fn new(module: String, asset_root: PathBuf, chain: Weak<RwLock<Chain>>) -> Self {
+		let mut watcher = Self {
+			module,
+			asset_root,
+			chain,
+			modified_at: HashMap::new(),
+			time_until_scan: SCAN_INTERVAL,
+		};
+		// Record the current state of all sources, so that only changes made from now on are reloaded.
+		let _ = watcher.scan();
+		watcher
+	}
+
+	pub fn arclocked(self) -> Arc<RwLock<Self>> {
+		Arc::new(RwLock::new(self))
+	}
+
+	/// Returns the paths of all block sources which have been created or modified since the last scan.
+	fn scan(&mut self) -> Vec<PathBuf> {
+		let mut sources = Vec::new();
+		collect_sources(&self.asset_root.join("blocks"), &mut sources);
+		let mut changed = Vec::new();
+		for (path, modified) in sources.into_iter() {
+			if self.modified_at.insert(path.clone(), modified) != Some(modified) {
+				changed.push(path);
+			}
+		}
+		changed
+	}
+
+	fn asset_id(&self, path: &Path) -> Option<asset::Id> {
+		let relative = path.strip_prefix(&self.asset_root).ok()?.with_extension("");
+		let name = relative
+			.components()
+			.map(|component| component.as_os_str().to_str())
+			.collect::<Option<Vec<_>>>()?
+			.join("/");
+		Some(asset::Id::new(&self.module, &name))
+	}
+
+	fn reload(&self, path: &Path) -> anyhow::Result<()> {
+		let asset_id = self
+			.asset_id(&path)
+			.ok_or_else(|| Error::InvalidPath(path.to_owned()))?;
+		let content = std::fs::read_to_string(&path)?;
+		let any_box = editor::asset::deserialize::<Block>(&path.to_owned(), &content)?;
+		let block = any_box
+			.downcast::<Block>()
+			.map_err(|_| Error::NotABlock(asset_id.clone()))?;
+		let arc_chain = self.chain.upgrade().ok_or(Error::InvalidChain)?;
+		let chain = arc_chain.read().unwrap();
+		model::reload_block(&asset_id, &block, &chain)
+	}
+}
+
+impl EngineSystem for Watcher {
+	fn update(&mut self, delta_time: Duration, _has_focus: bool) {
+		if delta_time < self.time_until_scan {
+			self.time_until_scan -= delta_time;
+			return;
+		}
+		self.time_until_scan = SCAN_INTERVAL;
+		for path in self.scan().into_iter() {
+			// Nothing to reload into until the block models have been loaded.
+			if model::Cache::active().is_none() {
+				continue;
+			}
+			if let Err(err) = self.reload(&path) {
+				log::warn!(
+					target: LOG,
+					"Failed to reload block {}: {}",
+					path.display(),
+					err
+				);
+			}
+		}
+	}
+}
+
+/// Recursively finds all kdl files in a directory, paired with the time they were last modified.
+fn collect_sources(directory: &Path, sources: &mut Vec<(PathBuf, SystemTime)>) {
+	let entries = match std::fs::read_dir(directory) {
+		Ok(entries) => entries,
+		Err(_) => return,
+	};
+	for entry in entries.filter_map(|entry| entry.ok()) {
+		let path = entry.path();
+		if path.is_dir() {
+			collect_sources(&path, sources);
+		} else if path.extension().map(|ext| ext == "kdl").unwrap_or(false) {
+			if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+				sources.push((path, modified));
+			}
+		}
+	}
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+	#[error("{0} is not in the module's asset directory")]
+	InvalidPath(PathBuf),
+	#[error("asset {0} is not a block")]
+	NotABlock(asset::Id),
+	#[error("graphics chain is invalid")]
+	InvalidChain,
+}
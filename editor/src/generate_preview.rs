@@ -0,0 +1,76 @@
+//! The `generate-preview` commandlet, which runs world generation headlessly
+//! and writes a top-down image of the result, so generation can be iterated on without launching the game.
+//!
+//! Usage: `-generate_preview=<min x>,<min z>,<max x>,<max z> [-seed=<seed>] [-preview_chunk_y=<min>,<max>] [-preview_out=<path>]`
+//! where the region and vertical range are in (inclusive) chunk coordinates.
+use anyhow::Result;
+use crystal_sphinx::{
+	block,
+	common::world::generator::{self, preview},
+};
+use std::path::PathBuf;
+
+static LOG: &'static str = "generate-preview";
+
+fn named_arg(name: &str) -> Option<String> {
+	let prefix = format!("-{}=", name);
+	std::env::args().find_map(|arg| arg.strip_prefix(&prefix).map(str::to_owned))
+}
+
+/// Runs the commandlet if it was requested by the launch arguments.
+/// Returns true if the preview was generated.
+pub async fn run_from_args() -> Result<bool> {
+	let region = match named_arg("generate_preview") {
+		Some(region) => region.parse::<preview::Region>()?,
+		None => return Ok(false),
+	};
+	let region = match named_arg("preview_chunk_y") {
+		Some(range) => {
+			let bounds = range
+				.split(',')
+				.map(|bound| bound.trim().parse::<i64>())
+				.collect::<Result<Vec<_>, _>>()?;
+			match bounds[..] {
+				[min, max] if min <= max => region.with_chunk_y(min..max + 1),
+				_ => anyhow::bail!(
+					"\"{}\" is not a valid vertical range, expected <min>,<max>",
+					range
+				),
+			}
+		}
+		None => region,
+	};
+	let seed = named_arg("seed").unwrap_or_default();
+	let path = named_arg("preview_out")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("generation_preview.png"));
+
+	// The generator places blocks by their lookup ids, which requires the block assets to be known.
+	engine::asset::Library::scan_pak_directory().await?;
+	block::Lookup::initialize();
+
+	let mut pipeline = generator::Pipeline::new(
+		generator::Pipeline::seed_from_str(&seed),
+		generator::Flat::classic(),
+	);
+	let mut decorators = Vec::new();
+	crystal_sphinx::plugin::Manager::read()
+		.unwrap()
+		.register_decorators(&mut decorators);
+	for decorator in decorators.into_iter() {
+		pipeline.add_decorator(decorator);
+	}
+
+	let (width, height) = region.image_size();
+	log::info!(
+		target: LOG,
+		"Generating a {}x{} preview of {:?} with seed \"{}\"",
+		width,
+		height,
+		region,
+		seed
+	);
+	preview::save_preview(&pipeline, &region, &path)?;
+	log::info!(target: LOG, "Saved preview to {}", path.display());
+	Ok(true)
+}
@@ -14,16 +14,19 @@ use engine::{
 
 pub mod blender_model;
 pub mod block;
+pub mod generate_preview;
 
 pub struct Runtime {
 	window: Option<Window>,
 	workspace: Option<Arc<RwLock<Workspace>>>,
+	block_watcher: Option<Arc<RwLock<block::Watcher>>>,
 }
 impl Runtime {
 	pub fn new() -> Self {
 		Self {
 			window: None,
 			workspace: None,
+			block_watcher: None,
 		}
 	}
 }
@@ -40,7 +43,9 @@ impl engine::Runtime for Runtime {
 		Box::pin(async move {
 			self.create_editor().await?;
 			let ran_commandlets = editor::Editor::run_commandlets().await;
-			Ok(!ran_commandlets)
+			// Previews are generated without launching the display, like any other commandlet.
+			let generated_preview = generate_preview::run_from_args().await?;
+			Ok(!(ran_commandlets || generated_preview))
 		})
 	}
 
@@ -64,9 +69,21 @@ impl engine::Runtime for Runtime {
 
 		let ui = engine::ui::egui::Ui::create(&window, &*event_loop, &render_phase)?;
 		editor::ui::icons::Icon::load_all(ui.clone());
+		let block_watcher = block::Watcher::new(
+			CrystalSphinx::name().to_owned(),
+			PathBuf::from(std::env!("CARGO_MANIFEST_DIR"))
+				.join("..")
+				.join("core")
+				.join("assets"),
+			Arc::downgrade(window.graphics_chain()),
+		)
+		.arclocked();
+
 		if let Ok(mut engine) = engine.write() {
 			engine.add_winit_listener(&ui);
+			engine.add_weak_system(Arc::downgrade(&block_watcher));
 		}
+		self.block_watcher = Some(block_watcher);
 
 		self.window = Some(window);
 